@@ -0,0 +1,19 @@
+// Runs RescueCipher from @arcium-hq/client for medrec, so the CLI encrypts and
+// decrypts exactly like the TypeScript tests.
+//
+// Reads {op, shared_secret, nonce, values | ciphertexts} as JSON on stdin and writes
+// {values} to stdout: ciphertext byte arrays for "encrypt", decimal strings for "decrypt".
+const { RescueCipher } = require("@arcium-hq/client");
+
+let input = "";
+process.stdin.on("data", (chunk) => (input += chunk));
+process.stdin.on("end", () => {
+  const request = JSON.parse(input);
+  const cipher = new RescueCipher(Uint8Array.from(request.shared_secret));
+  const nonce = Uint8Array.from(request.nonce);
+  const values =
+    request.op === "encrypt"
+      ? cipher.encrypt(request.values.map(BigInt), nonce)
+      : cipher.decrypt(request.ciphertexts, nonce).map(String);
+  process.stdout.write(JSON.stringify({ values }));
+});
@@ -0,0 +1,91 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use serde::Deserialize;
+use serde_json::json;
+use share_medical_records_client::{ClientError, FieldCipher, Result, NONCE_LEN};
+
+/// Rescue cipher run by an external command speaking the `rescue_cipher.js` protocol.
+pub struct ExternalCipher {
+    command: String,
+    shared_secret: [u8; 32],
+}
+
+#[derive(Deserialize)]
+struct EncryptResponse {
+    values: Vec<[u8; 32]>,
+}
+
+#[derive(Deserialize)]
+struct DecryptResponse {
+    values: Vec<String>,
+}
+
+impl ExternalCipher {
+    pub fn new(command: &str, shared_secret: [u8; 32]) -> Self {
+        Self {
+            command: command.to_string(),
+            shared_secret,
+        }
+    }
+
+    fn run(&self, request: serde_json::Value) -> Result<Vec<u8>> {
+        let fail = |e: &dyn std::fmt::Display| ClientError::Cipher(e.to_string());
+        let mut parts = self.command.split_whitespace();
+        let program = parts.next().ok_or_else(|| fail(&"empty cipher command"))?;
+        let mut child = Command::new(program)
+            .args(parts)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|e| fail(&e))?;
+        child
+            .stdin
+            .take()
+            .expect("stdin is piped")
+            .write_all(request.to_string().as_bytes())
+            .map_err(|e| fail(&e))?;
+        let output = child.wait_with_output().map_err(|e| fail(&e))?;
+        if !output.status.success() {
+            return Err(fail(&format!(
+                "cipher command exited with {}",
+                output.status
+            )));
+        }
+        Ok(output.stdout)
+    }
+}
+
+impl FieldCipher for ExternalCipher {
+    fn encrypt(&self, plaintext: &[u128], nonce: &[u8; NONCE_LEN]) -> Result<Vec<[u8; 32]>> {
+        let stdout = self.run(json!({
+            "op": "encrypt",
+            "shared_secret": self.shared_secret,
+            "nonce": nonce,
+            "values": plaintext.iter().map(u128::to_string).collect::<Vec<_>>(),
+        }))?;
+        let response: EncryptResponse =
+            serde_json::from_slice(&stdout).map_err(|e| ClientError::Cipher(e.to_string()))?;
+        Ok(response.values)
+    }
+
+    fn decrypt(&self, ciphertexts: &[[u8; 32]], nonce: &[u8; NONCE_LEN]) -> Result<Vec<u128>> {
+        let stdout = self.run(json!({
+            "op": "decrypt",
+            "shared_secret": self.shared_secret,
+            "nonce": nonce,
+            "ciphertexts": ciphertexts,
+        }))?;
+        let response: DecryptResponse =
+            serde_json::from_slice(&stdout).map_err(|e| ClientError::Cipher(e.to_string()))?;
+        response
+            .values
+            .iter()
+            .map(|value| {
+                value
+                    .parse()
+                    .map_err(|_| ClientError::Cipher(format!("not a u128: {value}")))
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,251 @@
+//! `gen-fixtures`: seeds a cluster with synthetic patients, providers and consents.
+//!
+//! Every patient gets a funded wallet, a validly encrypted record and consent for one of
+//! the generated providers. Wallets, x25519 secrets and record keys are written under
+//! `--out`, laid out so `medrec --keypair <out>/patients/<key>.json --state-dir
+//! <out>/state` operates as any generated patient.
+
+mod cipher;
+// Shared with `medrec`, which also loads record keys
+#[allow(dead_code)]
+mod state;
+
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anchor_client::solana_client::rpc_client::RpcClient;
+use anchor_client::solana_sdk::commitment_config::CommitmentConfig;
+use anchor_client::solana_sdk::instruction::Instruction;
+use anchor_client::solana_sdk::signature::{
+    read_keypair_file, write_keypair_file, Keypair, Signature, Signer,
+};
+use anchor_client::solana_sdk::system_instruction;
+use anchor_client::solana_sdk::transaction::Transaction;
+use anyhow::{anyhow, bail, Result};
+use clap::Parser;
+use serde_json::json;
+use share_medical_records::{ProviderRole, CONSENT_SCOPE_PATIENT_DATA};
+use share_medical_records_client::*;
+
+use crate::cipher::ExternalCipher;
+use crate::state::RecordKeys;
+
+const SECONDS_PER_DAY: i64 = 86_400;
+
+#[derive(Parser)]
+#[command(
+    name = "gen-fixtures",
+    about = "Seed synthetic patients, providers and consents"
+)]
+struct Cli {
+    /// RPC endpoint, or `devnet` / `mainnet`
+    #[arg(long, default_value = "devnet")]
+    url: String,
+    /// Wallet funding every generated account; also registers providers with
+    /// `--register-providers`, so it must then be a registrar
+    #[arg(long, default_value = "~/.config/solana/id.json")]
+    keypair: String,
+    /// MXE x25519 public key, hex encoded
+    #[arg(long, env = "MEDREC_MXE_PUBKEY")]
+    mxe_pubkey: String,
+    /// Command implementing the Rescue cipher
+    #[arg(
+        long,
+        env = "MEDREC_CIPHER_CMD",
+        default_value = "node cli/rescue_cipher.js"
+    )]
+    cipher_cmd: String,
+    #[arg(long, default_value_t = 5)]
+    patients: usize,
+    #[arg(long, default_value_t = 2)]
+    providers: usize,
+    /// Lamports sent to each generated wallet
+    #[arg(long, default_value_t = 50_000_000)]
+    lamports: u64,
+    /// Days until generated consents lapse
+    #[arg(long, default_value_t = 30)]
+    consent_days: i64,
+    /// Register the generated providers as physicians
+    #[arg(long)]
+    register_providers: bool,
+    /// Directory the generated keys and manifest are written to
+    #[arg(long, default_value = "fixtures")]
+    out: String,
+}
+
+fn expand_home(path: &str) -> String {
+    match (path.strip_prefix("~/"), std::env::var("HOME")) {
+        (Some(rest), Ok(home)) => format!("{home}/{rest}"),
+        _ => path.to_string(),
+    }
+}
+
+fn rpc_url(url: &str) -> &str {
+    match url {
+        "devnet" => "https://api.devnet.solana.com",
+        "mainnet" => "https://api.mainnet-beta.solana.com",
+        url => url,
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn parse_hex32(value: &str) -> Result<[u8; 32]> {
+    let value = value.trim();
+    if value.len() != 64 {
+        bail!("expected 32 hex-encoded bytes");
+    }
+    let mut bytes = [0; 32];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&value[2 * i..2 * i + 2], 16)?;
+    }
+    Ok(bytes)
+}
+
+/// Plausible, varied but reproducible record for the `index`th patient.
+fn synthetic_record(index: usize) -> PatientRecord {
+    let i = index as u64;
+    PatientRecord {
+        patient_id: 100_000 + i,
+        age: (18 + (i * 7) % 70) as u8,
+        gender: i % 2 == 0,
+        blood_type: (i % 8) as u8,
+        weight: (50 + (i * 13) % 60) as u16,
+        height: (150 + (i * 11) % 45) as u16,
+        allergies: [0, 1, 2, 3, 4].map(|bit| (i >> bit) & 1 == 1),
+    }
+}
+
+struct Seeder {
+    rpc: RpcClient,
+    funder: Keypair,
+}
+
+impl Seeder {
+    fn send(&self, instructions: &[Instruction], signers: &[&Keypair]) -> Result<Signature> {
+        let blockhash = self.rpc.get_latest_blockhash()?;
+        let mut all_signers = vec![&self.funder];
+        all_signers.extend_from_slice(signers);
+        let tx = Transaction::new_signed_with_payer(
+            instructions,
+            Some(&self.funder.pubkey()),
+            &all_signers,
+            blockhash,
+        );
+        Ok(self.rpc.send_and_confirm_transaction(&tx)?)
+    }
+
+    fn fund(&self, wallet: &Keypair, lamports: u64) -> Instruction {
+        system_instruction::transfer(&self.funder.pubkey(), &wallet.pubkey(), lamports)
+    }
+}
+
+fn write_keypair(dir: &Path, keypair: &Keypair) -> Result<String> {
+    fs::create_dir_all(dir)?;
+    let path = dir.join(format!("{}.json", keypair.pubkey()));
+    write_keypair_file(keypair, &path).map_err(|e| anyhow!("failed to write keypair: {e}"))?;
+    Ok(path.display().to_string())
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let rpc = RpcClient::new_with_commitment(
+        rpc_url(&cli.url).to_string(),
+        CommitmentConfig::confirmed(),
+    );
+    let funder = read_keypair_file(expand_home(&cli.keypair))
+        .map_err(|e| anyhow!("failed to read keypair: {e}"))?;
+    let seeder = Seeder { rpc, funder };
+    let mxe_pubkey = parse_hex32(&cli.mxe_pubkey)?;
+    if cli.providers == 0 && cli.patients > 0 {
+        bail!("patients need at least one provider to consent to");
+    }
+
+    let out = Path::new(&cli.out);
+    let state_dir = out.join("state").display().to_string();
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
+    let expires_at = now + cli.consent_days * SECONDS_PER_DAY;
+
+    let mut providers = Vec::new();
+    let mut provider_manifest = Vec::new();
+    for _ in 0..cli.providers {
+        let provider = Keypair::new();
+        let encryption = EncryptionKeypair::generate();
+        let mut instructions = vec![seeder.fund(&provider, cli.lamports)];
+        if cli.register_providers {
+            instructions.push(register_provider_ix(
+                seeder.funder.pubkey(),
+                provider.pubkey(),
+                ProviderRole::Physician,
+            ));
+            instructions.push(attest_provider_enc_key_ix(
+                seeder.funder.pubkey(),
+                provider.pubkey(),
+                encryption.public_key(),
+            ));
+        }
+        seeder.send(&instructions, &[])?;
+        provider_manifest.push(json!({
+            "wallet": provider.pubkey().to_string(),
+            "keypair_file": write_keypair(&out.join("providers"), &provider)?,
+            "x25519_secret": hex(&encryption.secret_bytes()),
+            "x25519_public": hex(&encryption.public_key()),
+            "registered": cli.register_providers,
+        }));
+        println!("provider {}", provider.pubkey());
+        providers.push(provider);
+    }
+
+    let mut patient_manifest = Vec::new();
+    for index in 0..cli.patients {
+        let patient = Keypair::new();
+        let record = synthetic_record(index);
+        let sender = EncryptionKeypair::generate();
+        let keys = RecordKeys {
+            sender_secret: sender.secret_bytes(),
+            nonce: random_nonce(),
+        };
+        let cipher = ExternalCipher::new(&cli.cipher_cmd, sender.shared_secret(&mxe_pubkey));
+        let encrypted = record.encrypt(&cipher, sender.public_key(), keys.nonce)?;
+        let receiver = providers[index % providers.len()].pubkey();
+
+        seeder.send(
+            &[
+                seeder.fund(&patient, cli.lamports),
+                store_patient_data_ix(seeder.funder.pubkey(), patient.pubkey(), &encrypted),
+                grant_consent_ix(
+                    patient.pubkey(),
+                    receiver,
+                    CONSENT_SCOPE_PATIENT_DATA,
+                    expires_at,
+                ),
+            ],
+            &[&patient],
+        )?;
+        keys.save(&state_dir, &patient.pubkey())?;
+        patient_manifest.push(json!({
+            "wallet": patient.pubkey().to_string(),
+            "keypair_file": write_keypair(&out.join("patients"), &patient)?,
+            "patient_data": patient_data_address(&patient.pubkey()).to_string(),
+            "record": format!("{record:?}"),
+            "consent_receiver": receiver.to_string(),
+            "consent_expires_at": expires_at,
+        }));
+        println!("patient {} -> consent for {receiver}", patient.pubkey());
+    }
+
+    let manifest = json!({
+        "cluster": rpc_url(&cli.url),
+        "program_id": share_medical_records::ID.to_string(),
+        "state_dir": state_dir,
+        "providers": provider_manifest,
+        "patients": patient_manifest,
+    });
+    let manifest_path = out.join("manifest.json");
+    fs::write(&manifest_path, serde_json::to_string_pretty(&manifest)?)?;
+    println!("wrote {}", manifest_path.display());
+    Ok(())
+}
@@ -0,0 +1,402 @@
+//! `medrec`: operate the share_medical_records program from the command line.
+//!
+//! Encryption runs through `rescue_cipher.js`, which wraps `RescueCipher` from
+//! `@arcium-hq/client`; install the repo's node dependencies before storing or
+//! decrypting records.
+
+mod cipher;
+mod state;
+
+use anchor_client::solana_client::rpc_client::RpcClient;
+use anchor_client::solana_sdk::commitment_config::CommitmentConfig;
+use anchor_client::solana_sdk::instruction::Instruction;
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anchor_client::solana_sdk::signature::{read_keypair_file, Keypair, Signature, Signer};
+use anchor_client::solana_sdk::transaction::Transaction;
+use anchor_lang::{AnchorDeserialize, Discriminator};
+use anyhow::{anyhow, bail, Context, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use clap::{Parser, Subcommand};
+use share_medical_records::{
+    ConsentGrantedEvent, ConsentRevokedEvent, PatientDataStoredEvent, PatientDataUpdatedEvent,
+    ReceivedPatientDataCompactEvent, ReceivedPatientDataEvent, SharePatientDataAbortedEvent,
+    SharePatientDataQueuedEvent,
+};
+use share_medical_records_client::*;
+use solana_transaction_status::UiTransactionEncoding;
+
+use crate::cipher::ExternalCipher;
+use crate::state::RecordKeys;
+
+#[derive(Parser)]
+#[command(name = "medrec", about = "Store, share and consent to medical records")]
+struct Cli {
+    /// RPC endpoint, or `devnet` / `mainnet`
+    #[arg(long, global = true, default_value = "devnet")]
+    url: String,
+    /// Wallet keypair that signs as the patient
+    #[arg(long, global = true, default_value = "~/.config/solana/id.json")]
+    keypair: String,
+    /// MXE x25519 public key, hex encoded
+    #[arg(long, global = true, env = "MEDREC_MXE_PUBKEY")]
+    mxe_pubkey: Option<String>,
+    /// Command implementing the Rescue cipher
+    #[arg(
+        long,
+        global = true,
+        env = "MEDREC_CIPHER_CMD",
+        default_value = "node cli/rescue_cipher.js"
+    )]
+    cipher_cmd: String,
+    /// Directory the encryption keys of stored records are kept in
+    #[arg(long, global = true, default_value = "~/.config/medrec")]
+    state_dir: String,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(clap::Args)]
+struct RecordArgs {
+    #[arg(long)]
+    patient_id: u64,
+    #[arg(long)]
+    age: u8,
+    #[arg(long)]
+    gender: bool,
+    #[arg(long)]
+    blood_type: u8,
+    #[arg(long)]
+    weight: u16,
+    #[arg(long)]
+    height: u16,
+    /// Five comma-separated flags, e.g. `false,true,false,false,false`
+    #[arg(long, value_delimiter = ',')]
+    allergies: Vec<bool>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Encrypt and store a new record
+    Store(RecordArgs),
+    /// Encrypt and replace the stored record
+    Update(RecordArgs),
+    /// Grant a receiver consent to parts of the record
+    Grant {
+        receiver: Pubkey,
+        /// Bitmask of consent scopes
+        #[arg(long, default_value_t = share_medical_records::CONSENT_SCOPE_PATIENT_DATA)]
+        scope: u16,
+        /// Unix timestamp the consent lapses at
+        #[arg(long)]
+        expires_at: i64,
+    },
+    /// Revoke a receiver's consent
+    Revoke { receiver: Pubkey },
+    /// Share the record with a receiver's x25519 key
+    Share {
+        /// Receiver's x25519 public key, hex encoded
+        receiver: String,
+        #[arg(long)]
+        cluster_offset: u32,
+        /// Defaults to a random offset
+        #[arg(long)]
+        computation_offset: Option<u64>,
+        /// Percentile of recent prioritization fees to price compute units at
+        #[arg(long, default_value_t = 75)]
+        fee_percentile: u8,
+        /// Highest compute unit price to pay, in micro-lamports
+        #[arg(long, default_value_t = 1_000_000)]
+        max_priority_fee: u64,
+    },
+    /// List program events logged against the record
+    Log {
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+    },
+    /// Decrypt a `ReceivedPatientDataEvent` as its receiver
+    DecryptEvent {
+        /// Base64 event data from a `Program data:` log or event CPI
+        data: String,
+        /// File holding the receiver's x25519 secret key, hex encoded
+        #[arg(long)]
+        secret_key_file: String,
+    },
+}
+
+fn expand_home(path: &str) -> String {
+    match (path.strip_prefix("~/"), std::env::var("HOME")) {
+        (Some(rest), Ok(home)) => format!("{home}/{rest}"),
+        _ => path.to_string(),
+    }
+}
+
+fn rpc_url(url: &str) -> &str {
+    match url {
+        "devnet" => "https://api.devnet.solana.com",
+        "mainnet" => "https://api.mainnet-beta.solana.com",
+        url => url,
+    }
+}
+
+fn parse_hex32(value: &str) -> Result<[u8; 32]> {
+    let value = value.trim();
+    if value.len() != 64 {
+        bail!("expected 32 hex-encoded bytes");
+    }
+    let mut bytes = [0; 32];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&value[2 * i..2 * i + 2], 16)?;
+    }
+    Ok(bytes)
+}
+
+struct App {
+    rpc: RpcClient,
+    payer: Keypair,
+    cli: Cli,
+}
+
+impl App {
+    fn send(&self, instructions: &[Instruction]) -> Result<Signature> {
+        let blockhash = self.rpc.get_latest_blockhash()?;
+        let tx = Transaction::new_signed_with_payer(
+            instructions,
+            Some(&self.payer.pubkey()),
+            &[&self.payer],
+            blockhash,
+        );
+        Ok(self.rpc.send_and_confirm_transaction(&tx)?)
+    }
+
+    /// Draws a computation offset the patient has not queued before.
+    fn allocate_offset(&self) -> Result<u64> {
+        let account = self
+            .rpc
+            .get_account_with_commitment(
+                &used_offsets_address(&self.payer.pubkey()),
+                self.rpc.commitment(),
+            )?
+            .value;
+        let mut allocator =
+            OffsetAllocator::from_account_data(account.as_ref().map(|a| &a.data[..]))?;
+        Ok(allocator.allocate())
+    }
+
+    /// Page of the patient's access log the next share is logged to.
+    fn access_log_page(&self) -> Result<u32> {
+        let patient_data = patient_data_address(&self.payer.pubkey());
+        let account = self
+            .rpc
+            .get_account_with_commitment(
+                &patient_registry_address(&patient_data),
+                self.rpc.commitment(),
+            )?
+            .value;
+        Ok(current_access_log_page(
+            account.as_ref().map(|a| &a.data[..]),
+        )?)
+    }
+
+    fn mxe_pubkey(&self) -> Result<[u8; 32]> {
+        let mxe_pubkey = self
+            .cli
+            .mxe_pubkey
+            .as_deref()
+            .ok_or_else(|| anyhow!("--mxe-pubkey or MEDREC_MXE_PUBKEY is required"))?;
+        parse_hex32(mxe_pubkey)
+    }
+
+    /// Encrypts a record under a fresh key and nonce, returned so shares can use them.
+    fn encrypt_record(&self, args: &RecordArgs) -> Result<(EncryptedPatientRecord, RecordKeys)> {
+        let allergies: [bool; 5] = args
+            .allergies
+            .clone()
+            .try_into()
+            .map_err(|_| anyhow!("expected five allergy flags"))?;
+        let record = PatientRecord {
+            patient_id: args.patient_id,
+            age: args.age,
+            gender: args.gender,
+            blood_type: args.blood_type,
+            weight: args.weight,
+            height: args.height,
+            allergies,
+        };
+
+        let sender = EncryptionKeypair::generate();
+        let keys = RecordKeys {
+            sender_secret: sender.secret_bytes(),
+            nonce: random_nonce(),
+        };
+        let cipher = ExternalCipher::new(
+            &self.cli.cipher_cmd,
+            sender.shared_secret(&self.mxe_pubkey()?),
+        );
+        let encrypted = record.encrypt(&cipher, sender.public_key(), keys.nonce)?;
+        Ok((encrypted, keys))
+    }
+
+    fn log(&self, limit: usize) -> Result<()> {
+        let patient_data = patient_data_address(&self.payer.pubkey());
+        let signatures = self.rpc.get_signatures_for_address(&patient_data)?;
+        for status in signatures.into_iter().take(limit) {
+            let signature: Signature = status.signature.parse()?;
+            let tx = self
+                .rpc
+                .get_transaction(&signature, UiTransactionEncoding::Json)?;
+            let logs: Option<Vec<String>> = tx
+                .transaction
+                .meta
+                .and_then(|meta| meta.log_messages.into());
+            let events: Vec<&str> = logs
+                .iter()
+                .flatten()
+                .filter_map(|log| log.strip_prefix("Program data: "))
+                .filter_map(|data| BASE64.decode(data).ok())
+                .filter_map(|bytes| event_name(&bytes))
+                .collect();
+            println!(
+                "{} slot {} {}",
+                status.signature,
+                status.slot,
+                events.join(", ")
+            );
+        }
+        Ok(())
+    }
+}
+
+fn event_name(bytes: &[u8]) -> Option<&'static str> {
+    let known: [(&[u8], &str); 7] = [
+        (PatientDataStoredEvent::DISCRIMINATOR, "PatientDataStored"),
+        (PatientDataUpdatedEvent::DISCRIMINATOR, "PatientDataUpdated"),
+        (
+            SharePatientDataQueuedEvent::DISCRIMINATOR,
+            "SharePatientDataQueued",
+        ),
+        (
+            ReceivedPatientDataEvent::DISCRIMINATOR,
+            "ReceivedPatientData",
+        ),
+        (
+            ReceivedPatientDataCompactEvent::DISCRIMINATOR,
+            "ReceivedPatientDataCompact",
+        ),
+        (
+            SharePatientDataAbortedEvent::DISCRIMINATOR,
+            "SharePatientDataAborted",
+        ),
+        (ConsentGrantedEvent::DISCRIMINATOR, "ConsentGranted"),
+        (ConsentRevokedEvent::DISCRIMINATOR, "ConsentRevoked"),
+    ];
+    known
+        .iter()
+        .find(|(discriminator, _)| bytes.starts_with(discriminator))
+        .map(|(_, name)| *name)
+}
+
+fn decrypt_event(ctx: &App, data: &str, secret_key_file: &str) -> Result<()> {
+    let mut bytes = BASE64.decode(data.trim())?;
+    if bytes.starts_with(&anchor_lang::event::EVENT_IX_TAG_LE[..]) {
+        bytes.drain(..8);
+    }
+    let body = bytes
+        .strip_prefix(ReceivedPatientDataEvent::DISCRIMINATOR)
+        .context("not a ReceivedPatientDataEvent")?;
+    let event = ReceivedPatientDataEvent::try_from_slice(body)?;
+
+    let secret = std::fs::read_to_string(expand_home(secret_key_file))?;
+    let receiver = EncryptionKeypair::from_secret_bytes(parse_hex32(&secret)?);
+    if event.receiver_enc_key != receiver.public_key() {
+        bail!("event is encrypted for another receiver key");
+    }
+    let cipher = ExternalCipher::new(
+        &ctx.cli.cipher_cmd,
+        receiver.shared_secret(&ctx.mxe_pubkey()?),
+    );
+    let decrypted = DecryptedPatientData::from_event(&event, &cipher)?;
+    println!(
+        "record {} (computation {}, slot {})",
+        decrypted.patient_record, decrypted.computation_offset, event.slot
+    );
+    println!("{:#?}", decrypted.record);
+    Ok(())
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let rpc = RpcClient::new_with_commitment(
+        rpc_url(&cli.url).to_string(),
+        CommitmentConfig::confirmed(),
+    );
+    let payer = read_keypair_file(expand_home(&cli.keypair))
+        .map_err(|e| anyhow!("failed to read keypair: {e}"))?;
+    let ctx = App { rpc, payer, cli };
+    let patient = ctx.payer.pubkey();
+    let state_dir = expand_home(&ctx.cli.state_dir);
+
+    let signature = match &ctx.cli.command {
+        Command::Store(args) => {
+            let (record, keys) = ctx.encrypt_record(args)?;
+            let signature = ctx.send(&[store_patient_data_ix(patient, patient, &record)])?;
+            keys.save(&state_dir, &patient)?;
+            signature
+        }
+        Command::Update(args) => {
+            let (record, keys) = ctx.encrypt_record(args)?;
+            let signature = ctx.send(&[update_patient_data_ix(patient, &record)])?;
+            keys.save(&state_dir, &patient)?;
+            signature
+        }
+        Command::Grant {
+            receiver,
+            scope,
+            expires_at,
+        } => ctx.send(&[grant_consent_ix(patient, *receiver, *scope, *expires_at)])?,
+        Command::Revoke { receiver } => ctx.send(&[revoke_consent_ix(patient, *receiver)])?,
+        Command::Share {
+            receiver,
+            cluster_offset,
+            computation_offset,
+            fee_percentile,
+            max_priority_fee,
+        } => {
+            let keys = RecordKeys::load(&state_dir, &patient)?;
+            let sender = EncryptionKeypair::from_secret_bytes(keys.sender_secret);
+            let computation_offset = match computation_offset {
+                Some(offset) => *offset,
+                None => ctx.allocate_offset()?,
+            };
+            let share = ShareBuilder::new(
+                patient,
+                patient,
+                sender.public_key(),
+                keys.nonce,
+                parse_hex32(receiver)?,
+                random_nonce(),
+            )
+            .access_log_page(ctx.access_log_page()?)
+            .priority_fee(PriorityFee::Percentile {
+                percentile: *fee_percentile,
+                max_micro_lamports: *max_priority_fee,
+            });
+            let recent_fees: Vec<u64> = ctx
+                .rpc
+                .get_recent_prioritization_fees(
+                    &share.prioritization_fee_accounts(computation_offset, *cluster_offset),
+                )?
+                .into_iter()
+                .map(|fee| fee.prioritization_fee)
+                .collect();
+            ctx.send(&share.build_with_budget(computation_offset, *cluster_offset, &recent_fees))?
+        }
+        Command::Log { limit } => return ctx.log(*limit),
+        Command::DecryptEvent {
+            data,
+            secret_key_file,
+        } => return decrypt_event(&ctx, data, secret_key_file),
+    };
+    println!("{signature}");
+    Ok(())
+}
@@ -0,0 +1,33 @@
+use std::fs;
+use std::path::PathBuf;
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+/// Encryption of a patient's current record, kept locally because shares need the
+/// sender key and nonce it was encrypted with.
+#[derive(Serialize, Deserialize)]
+pub struct RecordKeys {
+    pub sender_secret: [u8; 32],
+    pub nonce: [u8; 16],
+}
+
+fn path(state_dir: &str, patient: &Pubkey) -> PathBuf {
+    PathBuf::from(state_dir).join(format!("{patient}.json"))
+}
+
+impl RecordKeys {
+    pub fn load(state_dir: &str, patient: &Pubkey) -> Result<Self> {
+        let path = path(state_dir, patient);
+        let data = fs::read(&path)
+            .with_context(|| format!("no stored record keys at {}", path.display()))?;
+        Ok(serde_json::from_slice(&data)?)
+    }
+
+    pub fn save(&self, state_dir: &str, patient: &Pubkey) -> Result<()> {
+        fs::create_dir_all(state_dir)?;
+        fs::write(path(state_dir, patient), serde_json::to_vec(self)?)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,60 @@
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::AccountDeserialize;
+use share_medical_records::{AccessLogEntry, AccessLogPage, PatientRegistry, ACCESS_LOG_PAGE_SIZE};
+
+use crate::access_log_page_address;
+
+/// Page the next share of a record is logged to, from the data of its `patient_registry`
+/// account, `None` before the record's first audited action.
+///
+/// Shares must pass this page; the client's share helpers assume the first one.
+pub fn current_access_log_page(registry_data: Option<&[u8]>) -> anchor_lang::Result<u32> {
+    registry_data
+        .map(|mut data| PatientRegistry::try_deserialize(&mut data))
+        .transpose()
+        .map(|registry| registry.map_or(0, |registry| registry.access_log_page()))
+}
+
+/// Addresses of the stored pages of a record's access log, oldest first, given the
+/// `access_entries` of its `PatientRegistry`.
+pub fn access_log_page_addresses(
+    patient_data: &Pubkey,
+    access_entries: u64,
+) -> impl Iterator<Item = Pubkey> + '_ {
+    let pages = access_entries.div_ceil(ACCESS_LOG_PAGE_SIZE as u64) as u32;
+    (0..pages).map(move |page| access_log_page_address(patient_data, page))
+}
+
+/// Reads page `page` of a record's access log split into pages of `page_size` entries,
+/// oldest share first, for showing a patient who accessed their record.
+///
+/// `fetch` returns the data of an account, e.g. through RPC `getAccountInfo`, or `None`
+/// when it does not exist; only the stored pages the requested page spans are fetched.
+/// Pages past the end of the log come back short or empty.
+pub fn read_access_log(
+    patient_data: &Pubkey,
+    page: u64,
+    page_size: usize,
+    mut fetch: impl FnMut(&Pubkey) -> Option<Vec<u8>>,
+) -> anchor_lang::Result<Vec<AccessLogEntry>> {
+    let start = page * page_size as u64;
+    let first = start / ACCESS_LOG_PAGE_SIZE as u64;
+    let mut skip = (start % ACCESS_LOG_PAGE_SIZE as u64) as usize;
+    let mut entries = Vec::with_capacity(page_size);
+    for stored in first.. {
+        if entries.len() == page_size {
+            break;
+        }
+        let Some(data) = fetch(&access_log_page_address(patient_data, stored as u32)) else {
+            break;
+        };
+        let log = AccessLogPage::try_deserialize(&mut data.as_slice())?;
+        let wanted = page_size - entries.len();
+        entries.extend(log.entries.iter().skip(skip).take(wanted).copied());
+        if log.entries.len() < ACCESS_LOG_PAGE_SIZE {
+            break;
+        }
+        skip = 0;
+    }
+    Ok(entries)
+}
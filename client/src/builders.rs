@@ -0,0 +1,374 @@
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::{system_program, ToAccountMetas};
+use share_medical_records::{accounts, cpi_client, instruction, CONSENT_SCOPE_PATIENT_DATA};
+
+use crate::instructions::build;
+use crate::{
+    access_log_page_address, compute_budget_ixs, consent_grant_address, fee_authority_address,
+    fee_escrow_address, grant_consent_ix, nonce_to_u128, patient_data_address,
+    patient_registry_address, provider_profile_address, share_compute_units, shared_record_address,
+    staged_share_address, store_patient_data_ix, treasury_address, update_patient_data_ix,
+    upsert_patient_data_ix, EncryptedPatientRecord, PriorityFee, NONCE_LEN,
+    PREPARE_SHARE_COMPUTE_UNIT_LIMIT,
+};
+
+/// Token accounts a share of a record that charges an access fee pays it through.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShareFee {
+    /// Mint the patient set the fee in
+    pub mint: Pubkey,
+    /// Payer's token account of `mint` the fee is escrowed from
+    pub payer_fee_account: Pubkey,
+    /// Patient's token account of `mint` the fee is released to
+    pub patient_fee_account: Pubkey,
+    /// Token program owning `mint`
+    pub token_program: Pubkey,
+}
+
+/// Builds `share_patient_data`, or the `prepare_share` and `execute_share` pair, from the
+/// share's parameters, filling in every account in the order the program expects.
+///
+/// The consent grant is passed whenever `payer` is not the patient, unless the share is
+/// relayed under the patient's signed approval, which must then be verified by an ed25519
+/// program instruction right before it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShareBuilder {
+    payer: Pubkey,
+    patient: Pubkey,
+    sender_pub_key: [u8; 32],
+    nonce: [u8; NONCE_LEN],
+    receiver: [u8; 32],
+    receiver_nonce: [u8; NONCE_LEN],
+    cluster_hint: Option<u32>,
+    approval_expires_at: Option<i64>,
+    fee: Option<ShareFee>,
+    access_log_page: u32,
+    compact: bool,
+    priority_fee: PriorityFee,
+}
+
+impl ShareBuilder {
+    /// A share by `payer` of the patient's record, stored under `sender_pub_key` and
+    /// `nonce`, to `receiver`, re-encrypted with `receiver_nonce`.
+    pub fn new(
+        payer: Pubkey,
+        patient: Pubkey,
+        sender_pub_key: [u8; 32],
+        nonce: [u8; NONCE_LEN],
+        receiver: [u8; 32],
+        receiver_nonce: [u8; NONCE_LEN],
+    ) -> Self {
+        Self {
+            payer,
+            patient,
+            sender_pub_key,
+            nonce,
+            receiver,
+            receiver_nonce,
+            cluster_hint: None,
+            approval_expires_at: None,
+            fee: None,
+            access_log_page: 0,
+            compact: false,
+            priority_fee: PriorityFee::None,
+        }
+    }
+
+    /// Routes the computation to an approved cluster instead of the MXE default.
+    pub fn cluster_hint(mut self, cluster_offset: u32) -> Self {
+        self.cluster_hint = Some(cluster_offset);
+        self
+    }
+
+    /// Relays the share under the patient's signed approval, valid until `expires_at`.
+    pub fn approved_until(mut self, expires_at: i64) -> Self {
+        self.approval_expires_at = Some(expires_at);
+        self
+    }
+
+    /// Pays the record's access fee through `fee`.
+    pub fn access_fee(mut self, fee: ShareFee) -> Self {
+        self.fee = Some(fee);
+        self
+    }
+
+    /// Logs the share to `page` of the record's access log, its registry's
+    /// `access_log_page()`, instead of the first.
+    pub fn access_log_page(mut self, page: u32) -> Self {
+        self.access_log_page = page;
+        self
+    }
+
+    /// Delivers the share into the receiver's `SharedRecord`, as the program requires
+    /// while its compact events are on.
+    pub fn compact(mut self) -> Self {
+        self.compact = true;
+        self
+    }
+
+    /// Prices the compute units of the transactions `build_with_budget` and
+    /// `build_staged_with_budget` return by `fee`.
+    pub fn priority_fee(mut self, fee: PriorityFee) -> Self {
+        self.priority_fee = fee;
+        self
+    }
+
+    /// `share_patient_data` queued at `computation_offset` on the given cluster.
+    pub fn build(&self, computation_offset: u64, cluster_offset: u32) -> Instruction {
+        cpi_client::share_patient_data_ix(
+            self.accounts(computation_offset, cluster_offset),
+            instruction::SharePatientData {
+                computation_offset,
+                receiver: self.receiver,
+                receiver_nonce: nonce_to_u128(&self.receiver_nonce),
+                sender_pub_key: self.sender_pub_key,
+                nonce: nonce_to_u128(&self.nonce),
+                cluster_hint: self.cluster_hint,
+                approval_expires_at: self.approval_expires_at,
+            },
+        )
+    }
+
+    /// `prepare_share` and `execute_share` for when the share does not fit one
+    /// transaction; send them in that order, in separate transactions.
+    pub fn build_staged(&self, computation_offset: u64, cluster_offset: u32) -> [Instruction; 2] {
+        let patient_data = patient_data_address(&self.patient);
+        let staged_share = staged_share_address(&self.payer, computation_offset);
+        let prepare = build(
+            accounts::PrepareShare {
+                payer: self.payer,
+                patient_data,
+                staged_share,
+                patient_registry: patient_registry_address(&patient_data),
+                instructions_sysvar: anchor_lang::solana_program::sysvar::instructions::ID,
+                provider_profile: provider_profile_address(&self.payer),
+                system_program: system_program::ID,
+            },
+            instruction::PrepareShare {
+                computation_offset,
+                receiver: self.receiver,
+                receiver_nonce: nonce_to_u128(&self.receiver_nonce),
+                sender_pub_key: self.sender_pub_key,
+                nonce: nonce_to_u128(&self.nonce),
+                cluster_hint: self.cluster_hint,
+                approval_expires_at: self.approval_expires_at,
+            },
+        );
+
+        let mut accounts = self.accounts(computation_offset, cluster_offset);
+        accounts.staged_share = Some(staged_share);
+        let execute = build(
+            accounts,
+            instruction::ExecuteShare {
+                computation_offset,
+                receiver: self.receiver,
+            },
+        );
+        [prepare, execute]
+    }
+
+    /// `build` behind compute budget instructions requesting the share's estimated compute
+    /// units, priced by its priority fee.
+    ///
+    /// Shares queued without a budget often get dropped under congestion. `recent_fees`
+    /// are the `getRecentPrioritizationFees` of `prioritization_fee_accounts`, and only
+    /// read by a percentile fee.
+    pub fn build_with_budget(
+        &self,
+        computation_offset: u64,
+        cluster_offset: u32,
+        recent_fees: &[u64],
+    ) -> Vec<Instruction> {
+        let mut ixs = compute_budget_ixs(
+            share_compute_units(self.fee.is_some()),
+            self.priority_fee.micro_lamports(recent_fees),
+        );
+        ixs.push(self.build(computation_offset, cluster_offset));
+        ixs
+    }
+
+    /// `build_staged`, each transaction behind its own compute budget instructions, as
+    /// in `build_with_budget`.
+    pub fn build_staged_with_budget(
+        &self,
+        computation_offset: u64,
+        cluster_offset: u32,
+        recent_fees: &[u64],
+    ) -> [Vec<Instruction>; 2] {
+        let micro_lamports = self.priority_fee.micro_lamports(recent_fees);
+        let units = [
+            PREPARE_SHARE_COMPUTE_UNIT_LIMIT,
+            share_compute_units(self.fee.is_some()),
+        ];
+        let mut staged = self
+            .build_staged(computation_offset, cluster_offset)
+            .into_iter();
+        units.map(|units| {
+            let mut ixs = compute_budget_ixs(units, micro_lamports);
+            ixs.extend(staged.next());
+            ixs
+        })
+    }
+
+    /// Writable accounts of the share, to query `getRecentPrioritizationFees` for.
+    pub fn prioritization_fee_accounts(
+        &self,
+        computation_offset: u64,
+        cluster_offset: u32,
+    ) -> Vec<Pubkey> {
+        self.accounts(computation_offset, cluster_offset)
+            .to_account_metas(None)
+            .into_iter()
+            .filter(|meta| meta.is_writable)
+            .map(|meta| meta.pubkey)
+            .collect()
+    }
+
+    fn accounts(&self, computation_offset: u64, cluster_offset: u32) -> accounts::SharePatientData {
+        let patient_data = patient_data_address(&self.patient);
+        let mut accounts = cpi_client::share_patient_data_accounts(
+            self.payer,
+            patient_data,
+            self.receiver,
+            computation_offset,
+            cluster_offset,
+        );
+        accounts.access_log_page = access_log_page_address(&patient_data, self.access_log_page);
+        if self.compact {
+            accounts.shared_record = Some(shared_record_address(&patient_data, &self.receiver));
+        }
+        if self.payer != self.patient && self.approval_expires_at.is_none() {
+            accounts.consent_grant = Some(consent_grant_address(&patient_data, &self.payer));
+        }
+        if let Some(fee) = self.fee {
+            accounts.fee_mint = Some(fee.mint);
+            accounts.payer_fee_account = Some(fee.payer_fee_account);
+            accounts.patient_fee_account = Some(fee.patient_fee_account);
+            accounts.treasury = Some(treasury_address(&fee.mint));
+            accounts.fee_escrow = Some(fee_escrow_address(computation_offset));
+            accounts.fee_authority = Some(fee_authority_address());
+            accounts.token_program = Some(fee.token_program);
+        }
+        accounts
+    }
+}
+
+/// Builds a consent grant from the patient to `receiver`, covering the structured record
+/// unless another scope is set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConsentBuilder {
+    patient: Pubkey,
+    receiver: Pubkey,
+    expires_at: i64,
+    scope: u16,
+    relayer: Option<(Pubkey, i64)>,
+}
+
+impl ConsentBuilder {
+    pub fn new(patient: Pubkey, receiver: Pubkey, expires_at: i64) -> Self {
+        Self {
+            patient,
+            receiver,
+            expires_at,
+            scope: CONSENT_SCOPE_PATIENT_DATA,
+            relayer: None,
+        }
+    }
+
+    /// Covers `scope`, a bitmask of `CONSENT_SCOPE_*` values, instead.
+    pub fn scope(mut self, scope: u16) -> Self {
+        self.scope = scope;
+        self
+    }
+
+    /// Has `relayer` submit and pay for the grant under the patient's signed approval,
+    /// valid until `approval_expires_at`; the ed25519 program instruction verifying it
+    /// must come right before the grant.
+    pub fn relayed_by(mut self, relayer: Pubkey, approval_expires_at: i64) -> Self {
+        self.relayer = Some((relayer, approval_expires_at));
+        self
+    }
+
+    /// `grant_consent`, or `grant_consent_sponsored` when relayed.
+    pub fn build(&self) -> Instruction {
+        let Some((payer, approval_expires_at)) = self.relayer else {
+            return grant_consent_ix(self.patient, self.receiver, self.scope, self.expires_at);
+        };
+        let patient_data = patient_data_address(&self.patient);
+        build(
+            accounts::GrantConsentSponsored {
+                payer,
+                patient_data,
+                consent_grant: consent_grant_address(&patient_data, &self.receiver),
+                patient_registry: patient_registry_address(&patient_data),
+                instructions_sysvar: anchor_lang::solana_program::sysvar::instructions::ID,
+                system_program: system_program::ID,
+            },
+            instruction::GrantConsentSponsored {
+                patient: self.patient,
+                receiver: self.receiver,
+                scope: self.scope,
+                expires_at: self.expires_at,
+                approval_expires_at,
+            },
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum StoreMode {
+    Store,
+    Update,
+    Upsert { overwrite: bool },
+}
+
+/// Builds the store of a patient's record, whichever instruction writes it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StoreBuilder {
+    patient: Pubkey,
+    record: EncryptedPatientRecord,
+    payer: Pubkey,
+    mode: StoreMode,
+}
+
+impl StoreBuilder {
+    /// A first store of `record` as the patient's record, paid for by the patient.
+    pub fn new(patient: Pubkey, record: EncryptedPatientRecord) -> Self {
+        Self {
+            patient,
+            record,
+            payer: patient,
+            mode: StoreMode::Store,
+        }
+    }
+
+    /// Has `payer`, e.g. the onboarding clinic, cover the record's rent.
+    pub fn payer(mut self, payer: Pubkey) -> Self {
+        self.payer = payer;
+        self
+    }
+
+    /// Replaces the existing record instead.
+    pub fn update(mut self) -> Self {
+        self.mode = StoreMode::Update;
+        self
+    }
+
+    /// Stores the record if there is none, and replaces an existing one only with
+    /// `overwrite`, so the instruction can be resent.
+    pub fn upsert(mut self, overwrite: bool) -> Self {
+        self.mode = StoreMode::Upsert { overwrite };
+        self
+    }
+
+    pub fn build(&self) -> Instruction {
+        match self.mode {
+            StoreMode::Store => store_patient_data_ix(self.payer, self.patient, &self.record),
+            StoreMode::Update => update_patient_data_ix(self.patient, &self.record),
+            StoreMode::Upsert { overwrite } => {
+                upsert_patient_data_ix(self.payer, self.patient, &self.record, overwrite)
+            }
+        }
+    }
+}
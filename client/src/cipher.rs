@@ -0,0 +1,19 @@
+use crate::Result;
+
+/// Length of an encryption nonce.
+pub const NONCE_LEN: usize = 16;
+
+/// Symmetric cipher over field elements, as used for `Enc<Shared, T>` values.
+///
+/// Implemented by a Rescue cipher in counter mode keyed with the x25519 shared secret of
+/// the encrypting party and the MXE or receiver. Each plaintext scalar maps to one
+/// 32-byte little-endian ciphertext, in order.
+pub trait FieldCipher {
+    fn encrypt(&self, plaintext: &[u128], nonce: &[u8; NONCE_LEN]) -> Result<Vec<[u8; 32]>>;
+    fn decrypt(&self, ciphertexts: &[[u8; 32]], nonce: &[u8; NONCE_LEN]) -> Result<Vec<u128>>;
+}
+
+/// Reads a nonce the way the program stores it, as a little-endian `u128`.
+pub fn nonce_to_u128(nonce: &[u8; NONCE_LEN]) -> u128 {
+    u128::from_le_bytes(*nonce)
+}
@@ -0,0 +1,88 @@
+use anchor_lang::prelude::{pubkey, Pubkey};
+use anchor_lang::solana_program::instruction::Instruction;
+use share_medical_records::SHARE_COMPUTE_UNIT_LIMIT;
+
+pub const COMPUTE_BUDGET_PROGRAM_ID: Pubkey =
+    pubkey!("ComputeBudget111111111111111111111111111111");
+/// Compute units to request for `prepare_share`, which only stages the arguments and
+/// verifies a relayed approval.
+pub const PREPARE_SHARE_COMPUTE_UNIT_LIMIT: u32 = 40_000;
+/// Compute units to add to a share that pays an access fee, for initializing the escrow
+/// and the token transfer into it.
+pub const ACCESS_FEE_COMPUTE_UNITS: u32 = 40_000;
+
+/// `ComputeBudgetInstruction` variant tags, in the order the program declares them.
+const SET_COMPUTE_UNIT_LIMIT: u8 = 2;
+const SET_COMPUTE_UNIT_PRICE: u8 = 3;
+
+/// How a share's compute unit price is chosen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PriorityFee {
+    /// No price instruction; the transaction pays the base fee only
+    #[default]
+    None,
+    /// A fixed price in micro-lamports per compute unit
+    Fixed(u64),
+    /// The `percentile` of the recent prioritization fees of the share's writable
+    /// accounts, capped at `max_micro_lamports`
+    Percentile {
+        percentile: u8,
+        max_micro_lamports: u64,
+    },
+}
+
+impl PriorityFee {
+    /// Price in micro-lamports per compute unit, given `recent_fees` as returned by
+    /// `getRecentPrioritizationFees` for the transaction's writable accounts.
+    ///
+    /// Fixed prices ignore `recent_fees`; a percentile of no samples is zero.
+    pub fn micro_lamports(&self, recent_fees: &[u64]) -> u64 {
+        match *self {
+            PriorityFee::None => 0,
+            PriorityFee::Fixed(micro_lamports) => micro_lamports,
+            PriorityFee::Percentile {
+                percentile,
+                max_micro_lamports,
+            } => {
+                if recent_fees.is_empty() {
+                    return 0;
+                }
+                let mut fees = recent_fees.to_vec();
+                fees.sort_unstable();
+                let rank = usize::from(percentile.min(100)) * (fees.len() - 1) / 100;
+                fees[rank].min(max_micro_lamports)
+            }
+        }
+    }
+}
+
+pub fn set_compute_unit_limit_ix(units: u32) -> Instruction {
+    let mut data = vec![SET_COMPUTE_UNIT_LIMIT];
+    data.extend_from_slice(&units.to_le_bytes());
+    Instruction::new_with_bytes(COMPUTE_BUDGET_PROGRAM_ID, &data, Vec::new())
+}
+
+pub fn set_compute_unit_price_ix(micro_lamports: u64) -> Instruction {
+    let mut data = vec![SET_COMPUTE_UNIT_PRICE];
+    data.extend_from_slice(&micro_lamports.to_le_bytes());
+    Instruction::new_with_bytes(COMPUTE_BUDGET_PROGRAM_ID, &data, Vec::new())
+}
+
+/// The compute budget instructions to put ahead of an instruction needing `units`, priced
+/// at `micro_lamports` per unit; the price is left out when zero.
+pub fn compute_budget_ixs(units: u32, micro_lamports: u64) -> Vec<Instruction> {
+    let mut ixs = vec![set_compute_unit_limit_ix(units)];
+    if micro_lamports > 0 {
+        ixs.push(set_compute_unit_price_ix(micro_lamports));
+    }
+    ixs
+}
+
+/// Estimated compute units of a share, with or without an access fee.
+pub fn share_compute_units(pays_access_fee: bool) -> u32 {
+    if pays_access_fee {
+        SHARE_COMPUTE_UNIT_LIMIT + ACCESS_FEE_COMPUTE_UNITS
+    } else {
+        SHARE_COMPUTE_UNIT_LIMIT
+    }
+}
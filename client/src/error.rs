@@ -0,0 +1,29 @@
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClientError {
+    /// A decrypted scalar does not fit the field it belongs to
+    FieldOutOfRange { field: &'static str, value: u128 },
+    /// The cipher returned a different number of values than were given
+    ScalarCountMismatch { expected: usize, actual: usize },
+    /// The cipher implementation failed
+    Cipher(String),
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientError::FieldOutOfRange { field, value } => {
+                write!(f, "decrypted {field} is out of range: {value}")
+            }
+            ClientError::ScalarCountMismatch { expected, actual } => {
+                write!(f, "expected {expected} scalars, got {actual}")
+            }
+            ClientError::Cipher(message) => write!(f, "cipher failed: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {}
+
+pub type Result<T> = std::result::Result<T, ClientError>;
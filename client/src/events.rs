@@ -0,0 +1,109 @@
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::hash::hashv;
+use share_medical_records::{
+    AuditAttestation, ReceivedPatientDataEvent, SharePreviewEvent, SharedRecord,
+};
+
+use crate::{FieldCipher, PatientRecord, Result, NONCE_LEN};
+
+/// A record shared with this receiver, decrypted out of its `ReceivedPatientDataEvent`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecryptedPatientData {
+    /// Record that was shared
+    pub patient_record: Pubkey,
+    /// Computation that re-encrypted it, matching the offset the share was queued with
+    pub computation_offset: u64,
+    /// Nonce the cluster re-encrypted the record under
+    pub nonce: [u8; NONCE_LEN],
+    pub record: PatientRecord,
+}
+
+impl DecryptedPatientData {
+    /// Decrypts the event's ciphertexts in circuit order and types each field.
+    ///
+    /// `cipher` must be keyed with the receiver's shared secret with the MXE.
+    pub fn from_event<C: FieldCipher>(
+        event: &ReceivedPatientDataEvent,
+        cipher: &C,
+    ) -> Result<Self> {
+        let ciphertexts = event.ciphertexts();
+        Ok(Self {
+            patient_record: event.patient_record,
+            computation_offset: event.computation_offset,
+            nonce: event.nonce,
+            record: PatientRecord::from_scalars(&cipher.decrypt(&ciphertexts, &event.nonce)?)?,
+        })
+    }
+
+    /// Decrypts the latest share delivered into `shared_record`, for shares made while
+    /// the program's compact events are on.
+    ///
+    /// `cipher` must be keyed with the receiver's shared secret with the MXE.
+    pub fn from_shared_record<C: FieldCipher>(
+        shared_record: &SharedRecord,
+        cipher: &C,
+    ) -> Result<Self> {
+        Ok(Self {
+            patient_record: shared_record.patient_data,
+            computation_offset: shared_record.computation_offset,
+            nonce: shared_record.nonce,
+            record: PatientRecord::from_scalars(
+                &cipher.decrypt(&shared_record.ciphertexts, &shared_record.nonce)?,
+            )?,
+        })
+    }
+}
+
+/// A preview of the patient's own record, decrypted out of its `SharePreviewEvent`.
+///
+/// Fields the preview was not asked for decrypt to zero, `false` or no allergies; render
+/// only those selected by the mask `preview_share` was queued with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecryptedSharePreview {
+    /// Record that was previewed
+    pub patient_record: Pubkey,
+    /// Computation that produced the preview, matching the offset it was queued with
+    pub computation_offset: u64,
+    pub record: PatientRecord,
+}
+
+impl DecryptedSharePreview {
+    /// Decrypts the event's ciphertexts in circuit order and types each field.
+    ///
+    /// `cipher` must be keyed with the patient's shared secret with the MXE.
+    pub fn from_event<C: FieldCipher>(event: &SharePreviewEvent, cipher: &C) -> Result<Self> {
+        Ok(Self {
+            patient_record: event.patient_record,
+            computation_offset: event.computation_offset,
+            record: PatientRecord::from_scalars(
+                &cipher.decrypt(&event.ciphertexts(), &event.nonce)?,
+            )?,
+        })
+    }
+}
+
+/// Replays a record's audit chain over `events`, the decoded `Program data:` logs of its
+/// store, share and consent events in the order they were emitted.
+///
+/// A complete history replays to the `audit_head` of the record's `PatientRegistry`.
+pub fn audit_chain_head<'a>(events: impl IntoIterator<Item = &'a [u8]>) -> [u8; 32] {
+    events
+        .into_iter()
+        .fold([0; 32], |head, event| hashv(&[&head, event]).to_bytes())
+}
+
+/// Checks an export of the events inside an `AuditAttestation`'s window, in emission
+/// order, against the checkpoint.
+pub fn matches_audit_attestation<'a>(
+    attestation: &AuditAttestation,
+    events: impl IntoIterator<Item = &'a [u8]>,
+) -> bool {
+    let mut entries = attestation.start_entries;
+    let head = events
+        .into_iter()
+        .fold(attestation.start_head, |head, event| {
+            entries += 1;
+            hashv(&[&head, event]).to_bytes()
+        });
+    entries == attestation.end_entries && head == attestation.end_head
+}
@@ -0,0 +1,1441 @@
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::{system_program, InstructionData, ToAccountMetas};
+use share_medical_records::cpi_client;
+use share_medical_records::{
+    accounts, instruction, BundleSource, CodedValue, CompressedCiphertexts, EncryptedContentKey,
+    EncryptedLabValues, EncryptedMedAdministration, KinDegree, ProviderRole, Specialty,
+};
+
+use crate::{
+    access_request_address, audit_attestation_address, callback_guard_address,
+    caregiver_grant_address, code_set_address, cohort_snapshot_address, compressed_tree_address,
+    consent_grant_address, disaster_mode_address, ems_authority_address, fee_authority_address,
+    fhir_anchor_address, inbox_address, ingest_authority_address, kin_link_address,
+    kin_roster_address, lab_result_address, med_administration_log_address,
+    med_administrator_address, nonce_to_u128, patient_commitment_address, patient_data_address,
+    patient_registry_address, program_config_address, provider_profile_address,
+    research_opt_in_address, research_vault_address, share_code_address,
+    specialty_template_address, staged_share_address, telehealth_session_address, treasury_address,
+    trial_enrollment_address, trial_escrow_address, trial_vault_address, used_nonces_address,
+    used_offsets_address, EncryptedPatientRecord, NONCE_LEN,
+};
+
+pub(crate) fn build(accounts: impl ToAccountMetas, data: impl InstructionData) -> Instruction {
+    Instruction {
+        program_id: share_medical_records::ID,
+        accounts: accounts.to_account_metas(None),
+        data: data.data(),
+    }
+}
+
+/// Stores `record` as the patient's on-chain record, with `payer` covering its rent.
+///
+/// Pass the patient as `payer` when they pay for themselves.
+pub fn store_patient_data_ix(
+    payer: Pubkey,
+    patient: Pubkey,
+    record: &EncryptedPatientRecord,
+) -> Instruction {
+    let [patient_id, age, gender, blood_type, weight, height] = record.fields();
+    build(
+        accounts::StorePatientData {
+            payer,
+            patient,
+            system_program: system_program::ID,
+            patient_data: patient_data_address(&patient),
+            program_config: program_config_address(),
+            used_nonces: used_nonces_address(&patient_data_address(&patient)),
+            patient_registry: patient_registry_address(&patient_data_address(&patient)),
+        },
+        instruction::StorePatientData {
+            patient_id,
+            age,
+            gender,
+            blood_type,
+            weight,
+            height,
+            allergies: record.allergies(),
+            enc_pubkey: record.sender_pub_key,
+            nonce: nonce_to_u128(&record.nonce),
+        },
+    )
+}
+
+/// Replaces the patient's on-chain record with `record`.
+pub fn update_patient_data_ix(patient: Pubkey, record: &EncryptedPatientRecord) -> Instruction {
+    let [patient_id, age, gender, blood_type, weight, height] = record.fields();
+    build(
+        accounts::UpdatePatientData {
+            patient,
+            patient_data: patient_data_address(&patient),
+            program_config: program_config_address(),
+            used_nonces: used_nonces_address(&patient_data_address(&patient)),
+            patient_registry: patient_registry_address(&patient_data_address(&patient)),
+            system_program: system_program::ID,
+        },
+        instruction::UpdatePatientData {
+            patient_id,
+            age,
+            gender,
+            blood_type,
+            weight,
+            height,
+            allergies: record.allergies(),
+            enc_pubkey: record.sender_pub_key,
+            nonce: nonce_to_u128(&record.nonce),
+        },
+    )
+}
+
+/// Wipes the patient's record once its minimum retention has passed, leaving the account.
+pub fn tombstone_patient_data_ix(patient: Pubkey) -> Instruction {
+    build(
+        accounts::RemovePatientData {
+            patient,
+            patient_data: patient_data_address(&patient),
+            program_config: program_config_address(),
+        },
+        instruction::TombstonePatientData {},
+    )
+}
+
+/// Stores `record` as the patient's record if it has none, replacing an existing one only
+/// when `overwrite` is set; safe to resend.
+pub fn upsert_patient_data_ix(
+    payer: Pubkey,
+    patient: Pubkey,
+    record: &EncryptedPatientRecord,
+    overwrite: bool,
+) -> Instruction {
+    let [patient_id, age, gender, blood_type, weight, height] = record.fields();
+    build(
+        accounts::UpsertPatientData {
+            payer,
+            patient,
+            system_program: system_program::ID,
+            patient_data: patient_data_address(&patient),
+            program_config: program_config_address(),
+            used_nonces: used_nonces_address(&patient_data_address(&patient)),
+            patient_registry: patient_registry_address(&patient_data_address(&patient)),
+        },
+        instruction::UpsertPatientData {
+            patient_id,
+            age,
+            gender,
+            blood_type,
+            weight,
+            height,
+            allergies: record.allergies(),
+            enc_pubkey: record.sender_pub_key,
+            nonce: nonce_to_u128(&record.nonce),
+            overwrite,
+        },
+    )
+}
+
+/// Stores `record` in commitment mode, with its ciphertexts uploaded to `cid`.
+pub fn store_patient_commitment_ix(
+    patient: Pubkey,
+    record: &EncryptedPatientRecord,
+    cid: String,
+) -> Instruction {
+    build(
+        accounts::StorePatientCommitment {
+            patient,
+            patient_commitment: patient_commitment_address(&patient),
+            program_config: program_config_address(),
+            system_program: system_program::ID,
+        },
+        instruction::StorePatientCommitment {
+            fields: record.fields(),
+            allergies: record.allergies(),
+            enc_pubkey: record.sender_pub_key,
+            nonce: nonce_to_u128(&record.nonce),
+            cid,
+        },
+    )
+}
+
+/// Shares the patient's record with `receiver` on the given cluster, without an access fee.
+///
+/// `sender_pub_key` and `nonce` are those the stored record was encrypted with.
+pub fn share_patient_data_ix(
+    patient: Pubkey,
+    sender_pub_key: [u8; 32],
+    nonce: [u8; NONCE_LEN],
+    receiver: [u8; 32],
+    receiver_nonce: [u8; NONCE_LEN],
+    computation_offset: u64,
+    cluster_offset: u32,
+) -> Instruction {
+    let accounts = cpi_client::share_patient_data_accounts(
+        patient,
+        patient_data_address(&patient),
+        receiver,
+        computation_offset,
+        cluster_offset,
+    );
+    share_ix(
+        accounts,
+        sender_pub_key,
+        nonce,
+        receiver,
+        receiver_nonce,
+        computation_offset,
+    )
+}
+
+/// Shares the patient's record as `payer`, under the consent the patient granted them.
+#[allow(clippy::too_many_arguments)]
+pub fn share_consented_patient_data_ix(
+    payer: Pubkey,
+    patient: Pubkey,
+    sender_pub_key: [u8; 32],
+    nonce: [u8; NONCE_LEN],
+    receiver: [u8; 32],
+    receiver_nonce: [u8; NONCE_LEN],
+    computation_offset: u64,
+    cluster_offset: u32,
+) -> Instruction {
+    let patient_data = patient_data_address(&patient);
+    let mut accounts = cpi_client::share_patient_data_accounts(
+        payer,
+        patient_data,
+        receiver,
+        computation_offset,
+        cluster_offset,
+    );
+    accounts.consent_grant = Some(consent_grant_address(&patient_data, &payer));
+    share_ix(
+        accounts,
+        sender_pub_key,
+        nonce,
+        receiver,
+        receiver_nonce,
+        computation_offset,
+    )
+}
+
+fn share_ix(
+    accounts: accounts::SharePatientData,
+    sender_pub_key: [u8; 32],
+    nonce: [u8; NONCE_LEN],
+    receiver: [u8; 32],
+    receiver_nonce: [u8; NONCE_LEN],
+    computation_offset: u64,
+) -> Instruction {
+    cpi_client::share_patient_data_ix(
+        accounts,
+        instruction::SharePatientData {
+            computation_offset,
+            receiver,
+            receiver_nonce: nonce_to_u128(&receiver_nonce),
+            sender_pub_key,
+            nonce: nonce_to_u128(&nonce),
+            cluster_hint: None,
+            approval_expires_at: None,
+        },
+    )
+}
+
+/// Stages a share of the patient's record to `receiver` by `payer`, for when the share
+/// does not fit one transaction; queue it with [`execute_share_ix`].
+#[allow(clippy::too_many_arguments)]
+pub fn prepare_share_ix(
+    payer: Pubkey,
+    patient: Pubkey,
+    sender_pub_key: [u8; 32],
+    nonce: [u8; NONCE_LEN],
+    receiver: [u8; 32],
+    receiver_nonce: [u8; NONCE_LEN],
+    computation_offset: u64,
+    approval_expires_at: Option<i64>,
+) -> Instruction {
+    let patient_data = patient_data_address(&patient);
+    build(
+        accounts::PrepareShare {
+            payer,
+            patient_data,
+            staged_share: staged_share_address(&payer, computation_offset),
+            patient_registry: patient_registry_address(&patient_data),
+            instructions_sysvar: anchor_lang::solana_program::sysvar::instructions::ID,
+            provider_profile: provider_profile_address(&payer),
+            system_program: system_program::ID,
+        },
+        instruction::PrepareShare {
+            computation_offset,
+            receiver,
+            receiver_nonce: nonce_to_u128(&receiver_nonce),
+            sender_pub_key,
+            nonce: nonce_to_u128(&nonce),
+            cluster_hint: None,
+            approval_expires_at,
+        },
+    )
+}
+
+/// Queues the share `payer` staged at `computation_offset` on the given cluster.
+///
+/// A payer other than the patient shares under the consent the patient granted them.
+pub fn execute_share_ix(
+    payer: Pubkey,
+    patient: Pubkey,
+    receiver: [u8; 32],
+    computation_offset: u64,
+    cluster_offset: u32,
+) -> Instruction {
+    let patient_data = patient_data_address(&patient);
+    let mut accounts = cpi_client::share_patient_data_accounts(
+        payer,
+        patient_data,
+        receiver,
+        computation_offset,
+        cluster_offset,
+    );
+    if payer != patient {
+        accounts.consent_grant = Some(consent_grant_address(&patient_data, &payer));
+    }
+    accounts.staged_share = Some(staged_share_address(&payer, computation_offset));
+    build(
+        accounts,
+        instruction::ExecuteShare {
+            computation_offset,
+            receiver,
+        },
+    )
+}
+
+/// Closes the share `payer` staged at `computation_offset` without queueing it.
+pub fn discard_staged_share_ix(payer: Pubkey, computation_offset: u64) -> Instruction {
+    build(
+        accounts::DiscardStagedShare {
+            payer,
+            staged_share: staged_share_address(&payer, computation_offset),
+        },
+        instruction::DiscardStagedShare {},
+    )
+}
+
+/// Previews the fields of the patient's record selected by `fields`, a bitmask of
+/// `PatientField` bits, re-encrypted for the patient's own key under `preview_nonce`.
+///
+/// `nonce` is the one the stored record was encrypted with; decrypt the resulting
+/// `SharePreviewEvent` with [`DecryptedSharePreview`](crate::DecryptedSharePreview).
+pub fn preview_share_ix(
+    patient: Pubkey,
+    nonce: [u8; NONCE_LEN],
+    preview_nonce: [u8; NONCE_LEN],
+    fields: u16,
+    computation_offset: u64,
+    cluster_offset: u32,
+) -> Instruction {
+    build(
+        cpi_client::preview_share_accounts(patient, computation_offset, cluster_offset),
+        instruction::PreviewShare {
+            computation_offset,
+            nonce: nonce_to_u128(&nonce),
+            preview_nonce: nonce_to_u128(&preview_nonce),
+            fields,
+        },
+    )
+}
+
+/// `share_bundle` of the patient's record with their labs and prescriptions FHIR
+/// anchors, each `(anchor_id, sender_pub_key, nonce)`, to `receiver`.
+#[allow(clippy::too_many_arguments)]
+pub fn share_bundle_ix(
+    patient: Pubkey,
+    record: ([u8; 32], [u8; NONCE_LEN]),
+    labs: (u64, [u8; 32], [u8; NONCE_LEN]),
+    prescriptions: (u64, [u8; 32], [u8; NONCE_LEN]),
+    receiver: [u8; 32],
+    receiver_nonce: [u8; NONCE_LEN],
+    computation_offset: u64,
+    cluster_offset: u32,
+) -> Instruction {
+    let source = |pub_key: [u8; 32], nonce: [u8; NONCE_LEN]| BundleSource {
+        pub_key,
+        nonce: nonce_to_u128(&nonce),
+    };
+    build(
+        cpi_client::share_bundle_accounts(
+            patient,
+            labs.0,
+            prescriptions.0,
+            computation_offset,
+            cluster_offset,
+        ),
+        instruction::ShareBundle {
+            computation_offset,
+            receiver,
+            receiver_nonce: nonce_to_u128(&receiver_nonce),
+            labs_anchor_id: labs.0,
+            prescriptions_anchor_id: prescriptions.0,
+            record: source(record.0, record.1),
+            labs: source(labs.1, labs.2),
+            prescriptions: source(prescriptions.1, prescriptions.2),
+        },
+    )
+}
+
+pub fn grant_consent_ix(
+    patient: Pubkey,
+    receiver: Pubkey,
+    scope: u16,
+    expires_at: i64,
+) -> Instruction {
+    let patient_data = patient_data_address(&patient);
+    build(
+        accounts::GrantConsent {
+            patient,
+            patient_data,
+            consent_grant: consent_grant_address(&patient_data, &receiver),
+            patient_registry: patient_registry_address(&patient_data),
+            system_program: system_program::ID,
+        },
+        instruction::GrantConsent {
+            receiver,
+            scope,
+            expires_at,
+        },
+    )
+}
+
+pub fn revoke_consent_ix(patient: Pubkey, receiver: Pubkey) -> Instruction {
+    let patient_data = patient_data_address(&patient);
+    build(
+        accounts::RevokeConsent {
+            patient,
+            consent_grant: consent_grant_address(&patient_data, &receiver),
+            patient_registry: patient_registry_address(&patient_data),
+            system_program: system_program::ID,
+        },
+        instruction::RevokeConsent {},
+    )
+}
+
+/// Opens the patient's telehealth session `session_id` with `provider`, granting it
+/// `scope` for at most `max_slots` slots.
+pub fn open_session_ix(
+    patient: Pubkey,
+    session_id: [u8; 16],
+    provider: Pubkey,
+    scope: u16,
+    max_slots: u64,
+) -> Instruction {
+    let patient_data = patient_data_address(&patient);
+    build(
+        accounts::OpenSession {
+            patient,
+            patient_data,
+            session: telehealth_session_address(&patient_data, &session_id),
+            consent_grant: consent_grant_address(&patient_data, &provider),
+            patient_registry: patient_registry_address(&patient_data),
+            program_config: program_config_address(),
+            system_program: system_program::ID,
+        },
+        instruction::OpenSession {
+            session_id,
+            provider,
+            scope,
+            max_slots,
+        },
+    )
+}
+
+/// Closes the session `session_id` of `patient_data` with `provider`, signed by `closer`,
+/// the patient or the provider.
+pub fn close_session_ix(
+    closer: Pubkey,
+    patient_data: Pubkey,
+    session_id: [u8; 16],
+    provider: Pubkey,
+) -> Instruction {
+    build(
+        accounts::CloseSession {
+            closer,
+            session: telehealth_session_address(&patient_data, &session_id),
+            consent_grant: consent_grant_address(&patient_data, &provider),
+            patient_registry: patient_registry_address(&patient_data),
+            system_program: system_program::ID,
+        },
+        instruction::CloseSession {},
+    )
+}
+
+/// Sets the `PatientField` bits of the fields the deployment collects; signed by the
+/// config admin.
+pub fn set_record_fields_ix(admin: Pubkey, record_fields: u16) -> Instruction {
+    build(
+        accounts::UpdateConfig {
+            admin,
+            program_config: program_config_address(),
+        },
+        instruction::SetRecordFields { record_fields },
+    )
+}
+
+/// Switches shares between full events and delivery into `SharedRecord`; signed by the
+/// config admin.
+pub fn set_compact_events_ix(admin: Pubkey, enabled: bool) -> Instruction {
+    build(
+        accounts::UpdateConfig {
+            admin,
+            program_config: program_config_address(),
+        },
+        instruction::SetCompactEvents { enabled },
+    )
+}
+
+/// Sets the treasury's cut of access fees and payouts in basis points; signed by the
+/// config admin.
+pub fn set_fee_split_ix(admin: Pubkey, treasury_bps: u16) -> Instruction {
+    build(
+        accounts::UpdateConfig {
+            admin,
+            program_config: program_config_address(),
+        },
+        instruction::SetFeeSplit { treasury_bps },
+    )
+}
+
+/// Lists the off-chain source of the circuit at `comp_def_offset` in the config manifest;
+/// signed by the config admin.
+pub fn set_circuit_source_ix(
+    admin: Pubkey,
+    comp_def_offset: u32,
+    source_url: String,
+    source_hash: [u8; 32],
+) -> Instruction {
+    build(
+        accounts::UpdateConfig {
+            admin,
+            program_config: program_config_address(),
+        },
+        instruction::SetCircuitSource {
+            comp_def_offset,
+            source_url,
+            source_hash,
+        },
+    )
+}
+
+/// Moves shares to the share circuit at `comp_def_offset` as `version`, accepting the
+/// previous one for `grace_period` seconds more; signed by the config admin.
+pub fn activate_circuit_version_ix(
+    admin: Pubkey,
+    version: u32,
+    comp_def_offset: u32,
+    grace_period: i64,
+) -> Instruction {
+    build(
+        accounts::ActivateCircuitVersion {
+            admin,
+            program_config: program_config_address(),
+            comp_def_account: cpi_client::comp_def_address(comp_def_offset),
+        },
+        instruction::ActivateCircuitVersion {
+            version,
+            comp_def_offset,
+            grace_period,
+        },
+    )
+}
+
+/// Retires the circuit at `comp_def_offset` once nothing queued against it is pending;
+/// signed by the config admin.
+pub fn retire_comp_def_ix(admin: Pubkey, comp_def_offset: u32) -> Instruction {
+    build(
+        accounts::UpdateConfig {
+            admin,
+            program_config: program_config_address(),
+        },
+        instruction::RetireCompDef { comp_def_offset },
+    )
+}
+
+/// Publishes version `version` of the code set `name`; signed by the config admin.
+pub fn publish_code_set_ix(
+    admin: Pubkey,
+    name: &str,
+    system: &str,
+    version: u16,
+    values: Vec<CodedValue>,
+) -> Instruction {
+    build(
+        accounts::PublishCodeSet {
+            admin,
+            program_config: program_config_address(),
+            code_set: code_set_address(name),
+            system_program: system_program::ID,
+        },
+        instruction::PublishCodeSet {
+            name: name.to_string(),
+            system: system.to_string(),
+            version,
+            values,
+        },
+    )
+}
+
+/// Sets the fields `specialty` requests by default; signed by the config admin.
+pub fn set_specialty_template_ix(admin: Pubkey, specialty: Specialty, fields: u16) -> Instruction {
+    build(
+        accounts::SetSpecialtyTemplate {
+            admin,
+            program_config: program_config_address(),
+            specialty_template: specialty_template_address(specialty),
+            system_program: system_program::ID,
+        },
+        instruction::SetSpecialtyTemplate { specialty, fields },
+    )
+}
+
+/// Requests access to `patient_data` for a visit of `specialty`, asking for `fields` or,
+/// without them, the specialty template's.
+pub fn request_access_ix(
+    provider: Pubkey,
+    patient_data: Pubkey,
+    specialty: Specialty,
+    fields: Option<u16>,
+    purpose: u16,
+) -> Instruction {
+    build(
+        accounts::RequestAccess {
+            provider,
+            provider_profile: provider_profile_address(&provider),
+            patient_data,
+            specialty_template: specialty_template_address(specialty),
+            access_request: access_request_address(&patient_data, &provider),
+            inbox: inbox_address(&patient_data),
+            program_config: program_config_address(),
+            system_program: system_program::ID,
+        },
+        instruction::RequestAccess { fields, purpose },
+    )
+}
+
+/// Opens the notification inbox of `patient`'s record.
+pub fn open_inbox_ix(patient: Pubkey) -> Instruction {
+    let patient_data = patient_data_address(&patient);
+    build(
+        accounts::OpenInbox {
+            patient,
+            patient_data,
+            inbox: inbox_address(&patient_data),
+            system_program: system_program::ID,
+        },
+        instruction::OpenInbox {},
+    )
+}
+
+/// Posts to the inbox of `patient_data` that its consent to `receiver` lapses soon.
+/// Anyone may send it.
+pub fn notify_consent_expiring_ix(patient_data: Pubkey, receiver: Pubkey) -> Instruction {
+    build(
+        accounts::NotifyConsentExpiring {
+            consent_grant: consent_grant_address(&patient_data, &receiver),
+            inbox: inbox_address(&patient_data),
+            program_config: program_config_address(),
+        },
+        instruction::NotifyConsentExpiring {},
+    )
+}
+
+/// Replaces the registrars allowed to vet providers; signed by the config admin.
+pub fn set_registrars_ix(admin: Pubkey, registrars: Vec<Pubkey>) -> Instruction {
+    build(
+        accounts::UpdateConfig {
+            admin,
+            program_config: program_config_address(),
+        },
+        instruction::SetRegistrars { registrars },
+    )
+}
+
+/// Replaces the auditors allowed to checkpoint audit chains; signed by the config admin.
+pub fn set_auditors_ix(admin: Pubkey, auditors: Vec<Pubkey>) -> Instruction {
+    build(
+        accounts::UpdateConfig {
+            admin,
+            program_config: program_config_address(),
+        },
+        instruction::SetAuditors { auditors },
+    )
+}
+
+/// Registers `provider` in the registry; `registrar` must be listed in the program config.
+pub fn register_provider_ix(
+    registrar: Pubkey,
+    provider: Pubkey,
+    role: ProviderRole,
+) -> Instruction {
+    build(
+        accounts::RegisterProvider {
+            registrar,
+            program_config: program_config_address(),
+            provider_profile: provider_profile_address(&provider),
+            system_program: system_program::ID,
+        },
+        instruction::RegisterProvider { provider, role },
+    )
+}
+
+/// Attests `enc_pubkey` as the x25519 key `provider` receives consented shares with.
+pub fn attest_provider_enc_key_ix(
+    registrar: Pubkey,
+    provider: Pubkey,
+    enc_pubkey: [u8; 32],
+) -> Instruction {
+    build(
+        accounts::UpdateProvider {
+            registrar,
+            program_config: program_config_address(),
+            provider_profile: provider_profile_address(&provider),
+        },
+        instruction::AttestProviderEncKey { enc_pubkey },
+    )
+}
+
+/// Rotates the attested x25519 key of `provider` to `enc_pubkey`, keeping their consents.
+pub fn rotate_provider_enc_key_ix(
+    registrar: Pubkey,
+    provider: Pubkey,
+    enc_pubkey: [u8; 32],
+) -> Instruction {
+    build(
+        accounts::UpdateProvider {
+            registrar,
+            program_config: program_config_address(),
+            provider_profile: provider_profile_address(&provider),
+        },
+        instruction::RotateProviderEncKey { enc_pubkey },
+    )
+}
+
+/// Creates a one-time share code for the patient's record, covering the `PatientField`
+/// bits in `fields`, addressed by `code_key`, the ed25519 public key derived from the
+/// code shown as a QR code.
+///
+/// `nonce` is the one the stored record was encrypted with.
+pub fn create_share_code_ix(
+    patient: Pubkey,
+    code_key: Pubkey,
+    fields: u16,
+    nonce: [u8; NONCE_LEN],
+    expires_at: i64,
+) -> Instruction {
+    let patient_data = patient_data_address(&patient);
+    build(
+        accounts::CreateShareCode {
+            patient,
+            patient_data,
+            share_code: share_code_address(&code_key),
+            patient_registry: patient_registry_address(&patient_data),
+            program_config: program_config_address(),
+            system_program: system_program::ID,
+        },
+        instruction::CreateShareCode {
+            code_key,
+            fields,
+            nonce: nonce_to_u128(&nonce),
+            expires_at,
+        },
+    )
+}
+
+/// Redeems the share code whose key is `code_key` for `provider`, on the given cluster.
+///
+/// Must come right after an ed25519 program instruction verifying
+/// `share_code_redemption_message` for `provider` and their attested key, signed with the
+/// code's key. `patient_data` is the record the code's account names.
+pub fn redeem_share_code_ix(
+    provider: Pubkey,
+    code_key: Pubkey,
+    patient_data: Pubkey,
+    provider_nonce: [u8; NONCE_LEN],
+    computation_offset: u64,
+    cluster_offset: u32,
+) -> Instruction {
+    build(
+        cpi_client::redeem_share_code_accounts(
+            provider,
+            code_key,
+            patient_data,
+            computation_offset,
+            cluster_offset,
+        ),
+        instruction::RedeemShareCode {
+            computation_offset,
+            provider_nonce: nonce_to_u128(&provider_nonce),
+        },
+    )
+}
+
+/// Registers the EMS authority of `region` with the key its packets are encrypted for;
+/// signed by the config admin.
+pub fn set_ems_authority_ix(
+    admin: Pubkey,
+    region: u32,
+    authority: Pubkey,
+    enc_pubkey: [u8; 32],
+) -> Instruction {
+    build(
+        accounts::SetEmsAuthority {
+            admin,
+            program_config: program_config_address(),
+            ems_authority: ems_authority_address(region),
+            system_program: system_program::ID,
+        },
+        instruction::SetEmsAuthority {
+            region,
+            authority,
+            enc_pubkey,
+        },
+    )
+}
+
+/// Sets the disaster committee and how many members co-sign a declaration; signed by the
+/// config admin.
+pub fn set_disaster_committee_ix(
+    admin: Pubkey,
+    committee: Vec<Pubkey>,
+    threshold: u8,
+) -> Instruction {
+    build(
+        accounts::UpdateConfig {
+            admin,
+            program_config: program_config_address(),
+        },
+        instruction::SetDisasterCommittee {
+            committee,
+            threshold,
+        },
+    )
+}
+
+/// Sets the regulators who may request disclosures, the committee approving them, the
+/// approvals needed and the seconds between a regulator's disclosures; signed by the
+/// config admin.
+pub fn set_disclosure_policy_ix(
+    admin: Pubkey,
+    regulators: Vec<Pubkey>,
+    committee: Vec<Pubkey>,
+    threshold: u8,
+    interval: i64,
+) -> Instruction {
+    build(
+        accounts::UpdateConfig {
+            admin,
+            program_config: program_config_address(),
+        },
+        instruction::SetDisclosurePolicy {
+            regulators,
+            committee,
+            threshold,
+            interval,
+        },
+    )
+}
+
+/// Declares a disaster in `region` from `starts_at` to `ends_at`, paid by `declarer`;
+/// each of `co_signers` must also sign the transaction.
+pub fn declare_disaster_mode_ix(
+    declarer: Pubkey,
+    co_signers: &[Pubkey],
+    region: u32,
+    starts_at: i64,
+    ends_at: i64,
+) -> Instruction {
+    let mut ix = build(
+        accounts::DeclareDisasterMode {
+            declarer,
+            program_config: program_config_address(),
+            ems_authority: ems_authority_address(region),
+            disaster_mode: disaster_mode_address(region),
+            system_program: system_program::ID,
+        },
+        instruction::DeclareDisasterMode {
+            region,
+            starts_at,
+            ends_at,
+        },
+    );
+    ix.accounts.extend(
+        co_signers
+            .iter()
+            .map(|co_signer| AccountMeta::new_readonly(*co_signer, true)),
+    );
+    ix
+}
+
+/// Ends the disaster declaration of `region`; signed by any committee member.
+pub fn end_disaster_mode_ix(member: Pubkey, region: u32) -> Instruction {
+    build(
+        accounts::EndDisasterMode {
+            member,
+            program_config: program_config_address(),
+            disaster_mode: disaster_mode_address(region),
+        },
+        instruction::EndDisasterMode {},
+    )
+}
+
+/// Fetches the patient's emergency packet for `region`, re-encrypted for `responder`'s
+/// attested key, while a disaster is declared there.
+pub fn fetch_disaster_packet_ix(
+    responder: Pubkey,
+    patient: Pubkey,
+    region: u32,
+    responder_nonce: [u8; NONCE_LEN],
+    computation_offset: u64,
+    cluster_offset: u32,
+) -> Instruction {
+    build(
+        cpi_client::fetch_disaster_packet_accounts(
+            responder,
+            patient_data_address(&patient),
+            region,
+            computation_offset,
+            cluster_offset,
+        ),
+        instruction::FetchDisasterPacket {
+            computation_offset,
+            responder_nonce: nonce_to_u128(&responder_nonce),
+        },
+    )
+}
+
+/// Closes the patient's record, refunding its rent; its registry stays as the audit trail.
+pub fn close_patient_data_ix(patient: Pubkey) -> Instruction {
+    let patient_data = patient_data_address(&patient);
+    build(
+        accounts::ClosePatientData {
+            patient,
+            patient_data,
+            program_config: program_config_address(),
+            used_nonces: used_nonces_address(&patient_data),
+            patient_registry: patient_registry_address(&patient_data),
+        },
+        instruction::ClosePatientData {},
+    )
+}
+
+/// Anchors version `version` of an encrypted FHIR bundle of `resource_type` as the
+/// patient's anchor `anchor_id`, with its content key encrypted to the MXE.
+pub fn anchor_fhir_resource_ix(
+    patient: Pubkey,
+    anchor_id: u64,
+    resource_type: &str,
+    version: u32,
+    bundle_hash: [u8; 32],
+    content_key: EncryptedContentKey,
+) -> Instruction {
+    build(
+        accounts::AnchorFhirResource {
+            patient,
+            fhir_anchor: fhir_anchor_address(&patient, anchor_id),
+            program_config: program_config_address(),
+            system_program: system_program::ID,
+        },
+        instruction::AnchorFhirResource {
+            anchor_id,
+            resource_type: resource_type.to_string(),
+            version,
+            bundle_hash,
+            content_key,
+        },
+    )
+}
+
+/// Shares the content key of the patient's anchor `anchor_id` with `receiver`, on the
+/// given cluster.
+///
+/// `relatives` are the records the patient's record is linked to; a genomic anchor is
+/// only shared when all of them are given. `sender_pub_key` and `nonce` are the ones the
+/// content key was encrypted with.
+#[allow(clippy::too_many_arguments)]
+pub fn share_fhir_content_key_ix(
+    patient: Pubkey,
+    anchor_id: u64,
+    relatives: &[Pubkey],
+    receiver: [u8; 32],
+    receiver_nonce: [u8; NONCE_LEN],
+    sender_pub_key: [u8; 32],
+    nonce: [u8; NONCE_LEN],
+    computation_offset: u64,
+    cluster_offset: u32,
+) -> Instruction {
+    let patient_data = patient_data_address(&patient);
+    let mut ix = build(
+        cpi_client::share_fhir_content_key_accounts(
+            patient,
+            anchor_id,
+            computation_offset,
+            cluster_offset,
+        ),
+        instruction::ShareFhirContentKey {
+            computation_offset,
+            receiver,
+            receiver_nonce: nonce_to_u128(&receiver_nonce),
+            sender_pub_key,
+            nonce: nonce_to_u128(&nonce),
+        },
+    );
+    ix.accounts.extend(relatives.iter().map(|relative| {
+        AccountMeta::new_readonly(kin_link_address(&patient_data, relative), false)
+    }));
+    ix
+}
+
+/// Writes the lab's result `result_id` against `patient_data`, with `values` encrypted to
+/// the MXE under `enc_pubkey` and `nonce`.
+pub fn submit_lab_result_ix(
+    lab: Pubkey,
+    patient_data: Pubkey,
+    result_id: u64,
+    values: EncryptedLabValues,
+    enc_pubkey: [u8; 32],
+    nonce: u128,
+) -> Instruction {
+    build(
+        accounts::SubmitLabResult {
+            lab,
+            provider_profile: provider_profile_address(&lab),
+            patient_data,
+            consent_grant: consent_grant_address(&patient_data, &lab),
+            patient_registry: patient_registry_address(&patient_data),
+            lab_result: lab_result_address(&patient_data, &lab, result_id),
+            program_config: program_config_address(),
+            system_program: system_program::ID,
+        },
+        instruction::SubmitLabResult {
+            result_id,
+            values,
+            enc_pubkey,
+            nonce,
+        },
+    )
+}
+
+/// Links the records of two relatives, signed by both patients.
+///
+/// The patient whose record has the lower address pays the link's rent.
+pub fn link_kin_ix(patient: Pubkey, relative: Pubkey, degree: KinDegree) -> Instruction {
+    let (patient_a, patient_b) = if patient_data_address(&patient) < patient_data_address(&relative)
+    {
+        (patient, relative)
+    } else {
+        (relative, patient)
+    };
+    let (record_a, record_b) = (
+        patient_data_address(&patient_a),
+        patient_data_address(&patient_b),
+    );
+    build(
+        accounts::LinkKin {
+            patient_a,
+            patient_b,
+            record_a,
+            record_b,
+            kin_link: kin_link_address(&record_a, &record_b),
+            kin_roster_a: kin_roster_address(&record_a),
+            kin_roster_b: kin_roster_address(&record_b),
+            system_program: system_program::ID,
+        },
+        instruction::LinkKin { degree },
+    )
+}
+
+/// Gives or withdraws the patient's consent on their kin link to `relative`'s record.
+pub fn set_kin_consent_ix(patient: Pubkey, relative: Pubkey, consented: bool) -> Instruction {
+    let patient_data = patient_data_address(&patient);
+    build(
+        accounts::SetKinConsent {
+            patient,
+            patient_data,
+            kin_link: kin_link_address(&patient_data, &patient_data_address(&relative)),
+        },
+        instruction::SetKinConsent { consented },
+    )
+}
+
+/// Removes the patient's kin link to `relative`'s record once that record is closed,
+/// signed by the patient alone.
+pub fn unlink_closed_kin_ix(patient: Pubkey, relative: Pubkey) -> Instruction {
+    let patient_data = patient_data_address(&patient);
+    let relative_data = patient_data_address(&relative);
+    build(
+        accounts::UnlinkClosedKin {
+            patient,
+            patient_data,
+            relative_data,
+            kin_link: kin_link_address(&patient_data, &relative_data),
+            kin_roster: kin_roster_address(&patient_data),
+            relative_kin_roster: kin_roster_address(&relative_data),
+        },
+        instruction::UnlinkClosedKin {},
+    )
+}
+
+/// Checkpoints the audit chain of `patient_data`, which has audited `audit_entries`
+/// events, from `previous_attestation` or the start of the chain; signed by `auditor`.
+pub fn attest_audit_window_ix(
+    auditor: Pubkey,
+    patient_data: Pubkey,
+    audit_entries: u64,
+    previous_attestation: Option<Pubkey>,
+) -> Instruction {
+    build(
+        accounts::AttestAuditWindow {
+            auditor,
+            patient_registry: patient_registry_address(&patient_data),
+            previous_attestation,
+            audit_attestation: audit_attestation_address(&patient_data, audit_entries),
+            program_config: program_config_address(),
+            system_program: system_program::ID,
+        },
+        instruction::AttestAuditWindow {},
+    )
+}
+
+/// Grants `caregiver` periodic re-encryptions of `fields` of `patient`'s record, whose
+/// current nonce is `nonce`, for `caregiver_enc_key`.
+pub fn grant_caregiver_access_ix(
+    patient: Pubkey,
+    caregiver: Pubkey,
+    caregiver_enc_key: [u8; 32],
+    fields: u16,
+    nonce: u128,
+    interval_slots: u64,
+    expires_at: i64,
+) -> Instruction {
+    let patient_data = patient_data_address(&patient);
+    build(
+        accounts::GrantCaregiverAccess {
+            patient,
+            patient_data,
+            caregiver_grant: caregiver_grant_address(&patient_data, &caregiver),
+            patient_registry: patient_registry_address(&patient_data),
+            program_config: program_config_address(),
+            system_program: system_program::ID,
+        },
+        instruction::GrantCaregiverAccess {
+            caregiver,
+            caregiver_enc_key,
+            fields,
+            nonce,
+            interval_slots,
+            expires_at,
+        },
+    )
+}
+
+/// Revokes the caregiver grant of `patient`'s record to `caregiver`.
+pub fn revoke_caregiver_access_ix(patient: Pubkey, caregiver: Pubkey) -> Instruction {
+    let patient_data = patient_data_address(&patient);
+    build(
+        accounts::RevokeCaregiverAccess {
+            patient,
+            caregiver_grant: caregiver_grant_address(&patient_data, &caregiver),
+            patient_registry: patient_registry_address(&patient_data),
+            system_program: system_program::ID,
+        },
+        instruction::RevokeCaregiverAccess {},
+    )
+}
+
+/// Lets `staff` log medication given to `patient`, encrypted for the grant to
+/// `caregiver`.
+pub fn authorize_med_administrator_ix(
+    patient: Pubkey,
+    caregiver: Pubkey,
+    staff: Pubkey,
+) -> Instruction {
+    let patient_data = patient_data_address(&patient);
+    build(
+        accounts::AuthorizeMedAdministrator {
+            patient,
+            patient_data,
+            caregiver_grant: caregiver_grant_address(&patient_data, &caregiver),
+            med_administrator: med_administrator_address(&patient_data, &staff),
+            med_administration_log: med_administration_log_address(&patient_data),
+            system_program: system_program::ID,
+        },
+        instruction::AuthorizeMedAdministrator { staff },
+    )
+}
+
+/// Stops `staff` logging medication given to `patient`.
+pub fn revoke_med_administrator_ix(patient: Pubkey, staff: Pubkey) -> Instruction {
+    build(
+        accounts::RevokeMedAdministrator {
+            patient,
+            med_administrator: med_administrator_address(&patient_data_address(&patient), &staff),
+        },
+        instruction::RevokeMedAdministrator {},
+    )
+}
+
+/// Logs medication `staff` gave the patient of `patient_data`, with `values` encrypted
+/// under `enc_pubkey` and `nonce` for the grant to `caregiver`.
+pub fn med_administration_ix(
+    staff: Pubkey,
+    patient_data: Pubkey,
+    caregiver: Pubkey,
+    values: EncryptedMedAdministration,
+    enc_pubkey: [u8; 32],
+    nonce: u128,
+) -> Instruction {
+    build(
+        accounts::MedAdministration {
+            staff,
+            med_administrator: med_administrator_address(&patient_data, &staff),
+            patient_data,
+            caregiver_grant: caregiver_grant_address(&patient_data, &caregiver),
+            med_administration_log: med_administration_log_address(&patient_data),
+            program_config: program_config_address(),
+        },
+        instruction::MedAdministration {
+            values,
+            enc_pubkey,
+            nonce,
+        },
+    )
+}
+
+/// Creates the empty compressed record tree `tree_id` owned by `authority`.
+pub fn init_compressed_tree_ix(authority: Pubkey, tree_id: u64) -> Instruction {
+    build(
+        accounts::InitCompressedTree {
+            authority,
+            tree: compressed_tree_address(&authority, tree_id),
+            system_program: system_program::ID,
+        },
+        instruction::InitCompressedTree { tree_id },
+    )
+}
+
+/// Lets `app` append up to `quota_per_epoch` records per epoch to `tree`, signed by the
+/// patient owning it.
+pub fn grant_ingest_authority_ix(
+    patient: Pubkey,
+    tree: Pubkey,
+    app: Pubkey,
+    quota_per_epoch: u32,
+) -> Instruction {
+    build(
+        accounts::GrantIngestAuthority {
+            patient,
+            tree,
+            ingest_authority: ingest_authority_address(&tree, &app),
+            system_program: system_program::ID,
+        },
+        instruction::GrantIngestAuthority {
+            app,
+            quota_per_epoch,
+        },
+    )
+}
+
+/// Stops `app` appending to `tree`, signed by the patient owning it.
+pub fn revoke_ingest_authority_ix(patient: Pubkey, tree: Pubkey, app: Pubkey) -> Instruction {
+    build(
+        accounts::RevokeIngestAuthority {
+            patient,
+            ingest_authority: ingest_authority_address(&tree, &app),
+        },
+        instruction::RevokeIngestAuthority {},
+    )
+}
+
+/// Appends a record to `tree` on behalf of its owner, signed by `app`.
+pub fn ingest_compressed_record_ix(
+    app: Pubkey,
+    tree: Pubkey,
+    enc_pubkey: [u8; 32],
+    nonce: u128,
+    ciphertexts: CompressedCiphertexts,
+) -> Instruction {
+    build(
+        accounts::IngestCompressedRecord {
+            app,
+            ingest_authority: ingest_authority_address(&tree, &app),
+            tree,
+            program_config: program_config_address(),
+        },
+        instruction::IngestCompressedRecord {
+            enc_pubkey,
+            nonce,
+            ciphertexts,
+        },
+    )
+}
+
+/// Pays the enrollment of `patient_data` in `trial_escrow` for its confirmed milestones
+/// into `patient_token_account`, the patient's account of `mint`. Anyone may send it.
+pub fn claim_trial_payout_ix(
+    trial_escrow: Pubkey,
+    patient_data: Pubkey,
+    patient_token_account: Pubkey,
+    mint: Pubkey,
+    token_program: Pubkey,
+) -> Instruction {
+    build(
+        accounts::ClaimTrialPayout {
+            trial_escrow,
+            trial_enrollment: trial_enrollment_address(&trial_escrow, &patient_data),
+            escrow_vault: trial_vault_address(&trial_escrow),
+            patient_token_account,
+            treasury: treasury_address(&mint),
+            mint,
+            program_config: program_config_address(),
+            fee_authority: fee_authority_address(),
+            token_program,
+        },
+        instruction::ClaimTrialPayout {},
+    )
+}
+
+/// Returns the unearned deposit of the sponsor's trial `trial_id` to
+/// `sponsor_token_account` once the trial has ended.
+pub fn close_trial_escrow_ix(
+    sponsor: Pubkey,
+    trial_id: u64,
+    sponsor_token_account: Pubkey,
+    mint: Pubkey,
+    token_program: Pubkey,
+) -> Instruction {
+    let trial_escrow = trial_escrow_address(&sponsor, trial_id);
+    build(
+        accounts::CloseTrialEscrow {
+            sponsor,
+            trial_escrow,
+            escrow_vault: trial_vault_address(&trial_escrow),
+            sponsor_token_account,
+            mint,
+            fee_authority: fee_authority_address(),
+            token_program,
+        },
+        instruction::CloseTrialEscrow {},
+    )
+}
+
+/// `cohort_query` by `researcher` over `research_escrow`, counting the records of
+/// `members`, each `(patient_data, payout_token_account)`, that match the filter.
+///
+/// The count is encrypted for `researcher_pub_key` under `researcher_nonce`; the members'
+/// opt-ins are derived from their records.
+#[allow(clippy::too_many_arguments)]
+pub fn cohort_query_ix(
+    researcher: Pubkey,
+    research_escrow: Pubkey,
+    mint: Pubkey,
+    researcher_token_account: Pubkey,
+    token_program: Pubkey,
+    members: &[(Pubkey, Pubkey)],
+    researcher_pub_key: [u8; 32],
+    researcher_nonce: [u8; NONCE_LEN],
+    min_age: u8,
+    max_age: u8,
+    blood_type: u8,
+    computation_offset: u64,
+    cluster_offset: u32,
+) -> Instruction {
+    let mut ix = build(
+        cpi_client::cohort_query_accounts(
+            researcher,
+            research_escrow,
+            mint,
+            researcher_token_account,
+            token_program,
+            computation_offset,
+            cluster_offset,
+        ),
+        instruction::CohortQuery {
+            computation_offset,
+            researcher_pub_key,
+            researcher_nonce: nonce_to_u128(&researcher_nonce),
+            min_age,
+            max_age,
+            blood_type,
+        },
+    );
+    ix.accounts.extend(
+        members
+            .iter()
+            .flat_map(|(patient_data, payout_token_account)| {
+                [
+                    AccountMeta::new_readonly(*patient_data, false),
+                    AccountMeta::new_readonly(research_opt_in_address(patient_data), false),
+                    AccountMeta::new_readonly(*payout_token_account, false),
+                ]
+            }),
+    );
+    ix
+}
+
+/// Refunds `research_escrow` to the researcher once its cohort query, queued at
+/// `computation_offset`, has timed out. Anyone may send it.
+pub fn expire_research_escrow_ix(
+    research_escrow: Pubkey,
+    researcher: Pubkey,
+    researcher_token_account: Pubkey,
+    mint: Pubkey,
+    token_program: Pubkey,
+    computation_offset: u64,
+) -> Instruction {
+    build(
+        accounts::ExpireResearchEscrow {
+            program_config: program_config_address(),
+            research_escrow,
+            escrow_vault: research_vault_address(&research_escrow),
+            researcher_token_account,
+            researcher,
+            mint,
+            fee_authority: fee_authority_address(),
+            token_program,
+            callback_guard: callback_guard_address(computation_offset),
+            used_offsets: used_offsets_address(&researcher),
+            cohort_snapshot: cohort_snapshot_address(&research_escrow),
+        },
+        instruction::ExpireResearchEscrow {},
+    )
+}
+
+/// Reactivates `prescription` once the fill verification `pharmacy` queued at
+/// `computation_offset` has timed out. Anyone may send it.
+pub fn expire_prescription_verification_ix(
+    prescription: Pubkey,
+    pharmacy: Pubkey,
+    computation_offset: u64,
+) -> Instruction {
+    build(
+        accounts::ExpirePrescriptionVerification {
+            program_config: program_config_address(),
+            prescription,
+            callback_guard: callback_guard_address(computation_offset),
+            used_offsets: used_offsets_address(&pharmacy),
+        },
+        instruction::ExpirePrescriptionVerification {},
+    )
+}
+
+/// Returns `prior_auth` to `Pending` once the validation `insurer` queued at
+/// `computation_offset` has timed out. Anyone may send it.
+pub fn expire_prior_auth_validation_ix(
+    prior_auth: Pubkey,
+    insurer: Pubkey,
+    computation_offset: u64,
+) -> Instruction {
+    build(
+        accounts::ExpirePriorAuthValidation {
+            program_config: program_config_address(),
+            prior_auth,
+            callback_guard: callback_guard_address(computation_offset),
+            used_offsets: used_offsets_address(&insurer),
+        },
+        instruction::ExpirePriorAuthValidation {},
+    )
+}
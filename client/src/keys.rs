@@ -0,0 +1,50 @@
+use rand::rngs::OsRng;
+use rand::RngCore;
+use x25519_dalek::{PublicKey, StaticSecret};
+
+use crate::NONCE_LEN;
+
+/// x25519 keypair a patient or receiver encrypts and decrypts records with.
+pub struct EncryptionKeypair {
+    secret: StaticSecret,
+    public: PublicKey,
+}
+
+impl EncryptionKeypair {
+    pub fn generate() -> Self {
+        Self::from_secret(StaticSecret::random_from_rng(OsRng))
+    }
+
+    pub fn from_secret_bytes(secret: [u8; 32]) -> Self {
+        Self::from_secret(StaticSecret::from(secret))
+    }
+
+    fn from_secret(secret: StaticSecret) -> Self {
+        let public = PublicKey::from(&secret);
+        Self { secret, public }
+    }
+
+    /// Secret key bytes, for keeping the keypair between sessions.
+    pub fn secret_bytes(&self) -> [u8; 32] {
+        self.secret.to_bytes()
+    }
+
+    /// Public key passed to instructions as `sender_pub_key` or `receiver`.
+    pub fn public_key(&self) -> [u8; 32] {
+        self.public.to_bytes()
+    }
+
+    /// Shared secret with `peer`, e.g. the MXE's x25519 key or a receiver's.
+    pub fn shared_secret(&self, peer: &[u8; 32]) -> [u8; 32] {
+        self.secret
+            .diffie_hellman(&PublicKey::from(*peer))
+            .to_bytes()
+    }
+}
+
+/// Fresh random nonce; never reuse one with the same shared secret.
+pub fn random_nonce() -> [u8; NONCE_LEN] {
+    let mut nonce = [0; NONCE_LEN];
+    OsRng.fill_bytes(&mut nonce);
+    nonce
+}
@@ -0,0 +1,35 @@
+//! Off-chain helpers for integrating with the share_medical_records program.
+//!
+//! Covers the glue every integrator otherwise rewrites: x25519 keys and shared secrets,
+//! packing a patient record into the scalars the circuits expect, encrypting it,
+//! deriving program addresses, building instructions and decrypting shared records
+//! out of events.
+//!
+//! Encryption goes through [`FieldCipher`], implemented by a Rescue cipher keyed with an
+//! x25519 shared secret, the same construction as `RescueCipher` in `@arcium-hq/client`.
+
+pub mod access_log;
+pub mod builders;
+pub mod cipher;
+pub mod compute_budget;
+pub mod error;
+pub mod events;
+pub mod instructions;
+pub mod keys;
+pub mod offsets;
+pub mod pdas;
+pub mod record;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+pub use access_log::*;
+pub use builders::*;
+pub use cipher::*;
+pub use compute_budget::*;
+pub use error::*;
+pub use events::*;
+pub use instructions::*;
+pub use keys::*;
+pub use offsets::*;
+pub use pdas::*;
+pub use record::*;
@@ -0,0 +1,49 @@
+use std::collections::HashSet;
+
+use anchor_lang::AccountDeserialize;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use share_medical_records::UsedOffsets;
+
+/// Draws computation offsets a payer has not used, so queueing never trips
+/// `OffsetAlreadyUsed`.
+///
+/// Seeded with the payer's on-chain `UsedOffsets` filter, it skips every offset the
+/// filter may hold, including the rare fresh offset that collides with it, as well as
+/// offsets it already handed out before they reached the chain.
+#[derive(Default)]
+pub struct OffsetAllocator {
+    used: Option<UsedOffsets>,
+    allocated: HashSet<u64>,
+}
+
+impl OffsetAllocator {
+    pub fn new(used: Option<UsedOffsets>) -> Self {
+        Self {
+            used,
+            allocated: HashSet::new(),
+        }
+    }
+
+    /// Builds an allocator from the data of the payer's `used_offsets` account, `None`
+    /// when the payer has never queued a computation.
+    pub fn from_account_data(data: Option<&[u8]>) -> anchor_lang::Result<Self> {
+        let used = data
+            .map(|mut data| UsedOffsets::try_deserialize(&mut data))
+            .transpose()?;
+        Ok(Self::new(used))
+    }
+
+    pub fn allocate(&mut self) -> u64 {
+        loop {
+            let offset = OsRng.next_u64();
+            let used = self
+                .used
+                .as_ref()
+                .is_some_and(|used| used.may_contain(offset));
+            if !used && self.allocated.insert(offset) {
+                return offset;
+            }
+        }
+    }
+}
@@ -0,0 +1,564 @@
+//! Every program-derived address of the program, one typed function per seed scheme.
+//!
+//! Seeds are listed in the order the program's account constraints spell them, and
+//! `derive_all` collects every address keyed by a patient alone, so integrators and
+//! auditors check derivations against one place rather than against each instruction.
+
+use anchor_lang::prelude::Pubkey;
+use share_medical_records::{
+    Specialty, ACCESS_FEE_SEED, ACCESS_LOG_SEED, ACCESS_REQUEST_SEED, ADVERSE_EVENT_SEED,
+    ATTESTATION_SEED, AUDIT_ATTESTATION_SEED, CALLBACK_GUARD_SEED, CLEARANCE_SEED,
+    CLEARANCE_TEMPLATE_SEED, CODE_SET_SEED, COHORT_SNAPSHOT_SEED, COMPRESSED_TREE_SEED,
+    CONSENT_GRANT_SEED, DICOM_REFERENCE_SEED, DISASTER_MODE_SEED, DISCLOSURE_REQUEST_SEED,
+    DISPENSE_ENTRY_SEED, DOCUMENT_VAULT_SEED, DONOR_PROFILE_SEED, EMERGENCY_PACKET_SEED,
+    EMERGENCY_POLICY_SEED, EMS_AUTHORITY_SEED, FEE_AUTHORITY_SEED, FEE_ESCROW_SEED,
+    FHIR_ANCHOR_SEED, INBOX_SEED, INGEST_AUTHORITY_SEED, KIN_LINK_SEED, KIN_ROSTER_SEED,
+    LAB_RESULT_SEED, LOOKUP_TABLE_AUTHORITY_SEED, MED_ADMINISTRATION_LOG_SEED,
+    MED_ADMINISTRATOR_SEED, PATIENT_COMMITMENT_SEED, PATIENT_REGISTRY_SEED, PENDING_SHARE_SEED,
+    PRESCRIPTION_SEED, PRIOR_AUTH_ESCALATION_SEED, PRIOR_AUTH_SEED, PROGRAM_CONFIG_SEED,
+    PROVIDER_NAME_SEED, PROVIDER_SEED, PUBLIC_HEALTH_AUTHORITY_SEED, REFUND_BALANCE_SEED,
+    REFUND_VAULT_SEED, REGULATOR_ACTIVITY_SEED, REPORTER_TALLY_SEED, RESEARCH_ESCROW_SEED,
+    RESEARCH_OPT_IN_SEED, RESEARCH_VAULT_SEED, SCHEMA_DESCRIPTOR_SEED, SHARED_RECORD_SEED,
+    SHARE_CODE_SEED, SHARE_RECEIPT_SEED, STAGED_SHARE_SEED, SUBSCRIPTION_SEED,
+    SURVEILLANCE_TALLY_SEED, TELEHEALTH_SESSION_SEED, TREASURY_SEED, TRIAL_ENROLLMENT_SEED,
+    TRIAL_ESCROW_SEED, TRIAL_VAULT_SEED, USED_NONCES_SEED, USED_OFFSETS_SEED, WAITLIST_ENTRY_SEED,
+    WAITLIST_RUN_SEED, WORMHOLE_EMITTER_SEED, WORMHOLE_MESSAGE_SEED,
+};
+
+/// Seed of a patient's record; the program spells it inline in its constraints.
+pub const PATIENT_DATA_SEED: &[u8] = b"patient_data";
+
+/// Every seed prefix the program derives addresses under.
+pub const SEED_PREFIXES: [&[u8]; 72] = [
+    ACCESS_FEE_SEED,
+    ACCESS_LOG_SEED,
+    ACCESS_REQUEST_SEED,
+    ADVERSE_EVENT_SEED,
+    ATTESTATION_SEED,
+    AUDIT_ATTESTATION_SEED,
+    CALLBACK_GUARD_SEED,
+    CAREGIVER_GRANT_SEED,
+    CASE_REPORT_SEED,
+    CLEARANCE_SEED,
+    CLEARANCE_TEMPLATE_SEED,
+    CODE_SET_SEED,
+    COHORT_SNAPSHOT_SEED,
+    COMPRESSED_TREE_SEED,
+    CONSENT_GRANT_SEED,
+    DICOM_REFERENCE_SEED,
+    DISASTER_MODE_SEED,
+    DISCLOSURE_REQUEST_SEED,
+    DISPENSE_ENTRY_SEED,
+    DOCUMENT_VAULT_SEED,
+    DONOR_PROFILE_SEED,
+    EMERGENCY_PACKET_SEED,
+    EMERGENCY_POLICY_SEED,
+    EMS_AUTHORITY_SEED,
+    FEE_AUTHORITY_SEED,
+    FEE_ESCROW_SEED,
+    FHIR_ANCHOR_SEED,
+    INBOX_SEED,
+    INGEST_AUTHORITY_SEED,
+    KIN_LINK_SEED,
+    KIN_ROSTER_SEED,
+    LAB_RESULT_SEED,
+    LOOKUP_TABLE_AUTHORITY_SEED,
+    MED_ADMINISTRATION_LOG_SEED,
+    MED_ADMINISTRATOR_SEED,
+    PATIENT_COMMITMENT_SEED,
+    PATIENT_DATA_SEED,
+    PATIENT_REGISTRY_SEED,
+    PENDING_SHARE_SEED,
+    PRESCRIPTION_SEED,
+    PRIOR_AUTH_ESCALATION_SEED,
+    PRIOR_AUTH_SEED,
+    PROGRAM_CONFIG_SEED,
+    PROVIDER_NAME_SEED,
+    PROVIDER_SEED,
+    PUBLIC_HEALTH_AUTHORITY_SEED,
+    REFUND_BALANCE_SEED,
+    REFUND_VAULT_SEED,
+    REGULATOR_ACTIVITY_SEED,
+    REPORTER_TALLY_SEED,
+    RESEARCH_ESCROW_SEED,
+    RESEARCH_OPT_IN_SEED,
+    RESEARCH_VAULT_SEED,
+    SCHEMA_DESCRIPTOR_SEED,
+    SHARED_RECORD_SEED,
+    SHARE_CODE_SEED,
+    SHARE_RECEIPT_SEED,
+    SPECIALTY_TEMPLATE_SEED,
+    STAGED_SHARE_SEED,
+    SUBSCRIPTION_SEED,
+    SURVEILLANCE_TALLY_SEED,
+    TELEHEALTH_SESSION_SEED,
+    TREASURY_SEED,
+    TRIAL_ENROLLMENT_SEED,
+    TRIAL_ESCROW_SEED,
+    TRIAL_VAULT_SEED,
+    USED_NONCES_SEED,
+    USED_OFFSETS_SEED,
+    WAITLIST_ENTRY_SEED,
+    WAITLIST_RUN_SEED,
+    WORMHOLE_EMITTER_SEED,
+    WORMHOLE_MESSAGE_SEED,
+];
+
+fn program_address(seeds: &[&[u8]]) -> Pubkey {
+    Pubkey::find_program_address(seeds, &share_medical_records::ID).0
+}
+
+// Program-wide singletons
+
+pub fn program_config_address() -> Pubkey {
+    program_address(&[PROGRAM_CONFIG_SEED])
+}
+
+pub fn refund_vault_address() -> Pubkey {
+    program_address(&[REFUND_VAULT_SEED])
+}
+
+/// Authority owning every access fee escrow.
+pub fn fee_authority_address() -> Pubkey {
+    program_address(&[FEE_AUTHORITY_SEED])
+}
+
+pub fn lookup_table_authority_address() -> Pubkey {
+    program_address(&[LOOKUP_TABLE_AUTHORITY_SEED])
+}
+
+/// Emitter the program posts Wormhole messages as.
+pub fn wormhole_emitter_address() -> Pubkey {
+    program_address(&[WORMHOLE_EMITTER_SEED])
+}
+
+pub fn wormhole_message_address(sequence: u64) -> Pubkey {
+    program_address(&[WORMHOLE_MESSAGE_SEED, &sequence.to_le_bytes()])
+}
+
+pub fn treasury_address(mint: &Pubkey) -> Pubkey {
+    program_address(&[TREASURY_SEED, mint.as_ref()])
+}
+
+pub fn schema_descriptor_address(record_type: &str) -> Pubkey {
+    program_address(&[SCHEMA_DESCRIPTOR_SEED, record_type.as_bytes()])
+}
+
+pub fn code_set_address(name: &str) -> Pubkey {
+    program_address(&[CODE_SET_SEED, name.as_bytes()])
+}
+
+pub fn specialty_template_address(specialty: Specialty) -> Pubkey {
+    program_address(&[SPECIALTY_TEMPLATE_SEED, &[specialty as u8]])
+}
+
+// Keyed by the patient's wallet
+
+pub fn patient_data_address(patient: &Pubkey) -> Pubkey {
+    program_address(&[PATIENT_DATA_SEED, patient.as_ref()])
+}
+
+pub fn patient_commitment_address(patient: &Pubkey) -> Pubkey {
+    program_address(&[PATIENT_COMMITMENT_SEED, patient.as_ref()])
+}
+
+pub fn document_vault_address(owner: &Pubkey) -> Pubkey {
+    program_address(&[DOCUMENT_VAULT_SEED, owner.as_ref()])
+}
+
+pub fn fhir_anchor_address(patient: &Pubkey, anchor_id: u64) -> Pubkey {
+    program_address(&[FHIR_ANCHOR_SEED, patient.as_ref(), &anchor_id.to_le_bytes()])
+}
+
+// Keyed by the patient's record
+
+pub fn patient_registry_address(patient_data: &Pubkey) -> Pubkey {
+    program_address(&[PATIENT_REGISTRY_SEED, patient_data.as_ref()])
+}
+
+pub fn used_nonces_address(patient_data: &Pubkey) -> Pubkey {
+    program_address(&[USED_NONCES_SEED, patient_data.as_ref()])
+}
+
+pub fn access_fee_address(patient_data: &Pubkey) -> Pubkey {
+    program_address(&[ACCESS_FEE_SEED, patient_data.as_ref()])
+}
+
+pub fn research_opt_in_address(patient_data: &Pubkey) -> Pubkey {
+    program_address(&[RESEARCH_OPT_IN_SEED, patient_data.as_ref()])
+}
+
+pub fn access_log_page_address(patient_data: &Pubkey, page: u32) -> Pubkey {
+    program_address(&[ACCESS_LOG_SEED, patient_data.as_ref(), &page.to_le_bytes()])
+}
+
+pub fn consent_grant_address(patient_data: &Pubkey, receiver: &Pubkey) -> Pubkey {
+    program_address(&[CONSENT_GRANT_SEED, patient_data.as_ref(), receiver.as_ref()])
+}
+
+pub fn caregiver_grant_address(patient_data: &Pubkey, caregiver: &Pubkey) -> Pubkey {
+    program_address(&[
+        CAREGIVER_GRANT_SEED,
+        patient_data.as_ref(),
+        caregiver.as_ref(),
+    ])
+}
+
+pub fn access_request_address(patient_data: &Pubkey, provider: &Pubkey) -> Pubkey {
+    program_address(&[
+        ACCESS_REQUEST_SEED,
+        patient_data.as_ref(),
+        provider.as_ref(),
+    ])
+}
+
+pub fn pending_share_address(patient_data: &Pubkey, receiver: &[u8; 32]) -> Pubkey {
+    program_address(&[PENDING_SHARE_SEED, patient_data.as_ref(), receiver])
+}
+
+pub fn shared_record_address(patient_data: &Pubkey, receiver: &[u8; 32]) -> Pubkey {
+    program_address(&[SHARED_RECORD_SEED, patient_data.as_ref(), receiver])
+}
+
+/// Log every medication administration against `patient_data` is chained into.
+pub fn med_administration_log_address(patient_data: &Pubkey) -> Pubkey {
+    program_address(&[MED_ADMINISTRATION_LOG_SEED, patient_data.as_ref()])
+}
+
+pub fn med_administrator_address(patient_data: &Pubkey, staff: &Pubkey) -> Pubkey {
+    program_address(&[
+        MED_ADMINISTRATOR_SEED,
+        patient_data.as_ref(),
+        staff.as_ref(),
+    ])
+}
+
+/// Count of the kin links of `patient_data`.
+pub fn kin_roster_address(patient_data: &Pubkey) -> Pubkey {
+    program_address(&[KIN_ROSTER_SEED, patient_data.as_ref()])
+}
+
+/// Kin link between two records, in either order; the program keys it by the lower one.
+pub fn kin_link_address(record: &Pubkey, relative: &Pubkey) -> Pubkey {
+    let (record_a, record_b) = if record < relative {
+        (record, relative)
+    } else {
+        (relative, record)
+    };
+    program_address(&[KIN_LINK_SEED, record_a.as_ref(), record_b.as_ref()])
+}
+
+/// One-time share code stored under `code_key`, the ed25519 public key derived from a
+/// scanned code.
+pub fn share_code_address(code_key: &Pubkey) -> Pubkey {
+    program_address(&[SHARE_CODE_SEED, code_key.as_ref()])
+}
+
+pub fn share_receipt_address(patient_data: &Pubkey, computation_offset: u64) -> Pubkey {
+    program_address(&[
+        SHARE_RECEIPT_SEED,
+        patient_data.as_ref(),
+        &computation_offset.to_le_bytes(),
+    ])
+}
+
+pub fn attestation_address(patient_data: &Pubkey, credential_type: u8) -> Pubkey {
+    program_address(&[ATTESTATION_SEED, patient_data.as_ref(), &[credential_type]])
+}
+
+pub fn dicom_reference_address(patient_data: &Pubkey, reference_id: u64) -> Pubkey {
+    program_address(&[
+        DICOM_REFERENCE_SEED,
+        patient_data.as_ref(),
+        &reference_id.to_le_bytes(),
+    ])
+}
+
+pub fn emergency_policy_address(patient_data: &Pubkey, organization: &Pubkey) -> Pubkey {
+    program_address(&[
+        EMERGENCY_POLICY_SEED,
+        patient_data.as_ref(),
+        organization.as_ref(),
+    ])
+}
+
+pub fn emergency_packet_address(patient_data: &Pubkey) -> Pubkey {
+    program_address(&[EMERGENCY_PACKET_SEED, patient_data.as_ref()])
+}
+
+pub fn inbox_address(patient_data: &Pubkey) -> Pubkey {
+    program_address(&[INBOX_SEED, patient_data.as_ref()])
+}
+
+pub fn donor_profile_address(patient_data: &Pubkey) -> Pubkey {
+    program_address(&[DONOR_PROFILE_SEED, patient_data.as_ref()])
+}
+
+pub fn waitlist_entry_address(patient_data: &Pubkey) -> Pubkey {
+    program_address(&[WAITLIST_ENTRY_SEED, patient_data.as_ref()])
+}
+
+pub fn ems_authority_address(region: u32) -> Pubkey {
+    program_address(&[EMS_AUTHORITY_SEED, &region.to_le_bytes()])
+}
+
+/// Disaster declaration of the EMS region `region`.
+pub fn disaster_mode_address(region: u32) -> Pubkey {
+    program_address(&[DISASTER_MODE_SEED, &region.to_le_bytes()])
+}
+
+pub fn public_health_authority_address(region: u32) -> Pubkey {
+    program_address(&[PUBLIC_HEALTH_AUTHORITY_SEED, &region.to_le_bytes()])
+}
+
+pub fn telehealth_session_address(patient_data: &Pubkey, session_id: &[u8; 16]) -> Pubkey {
+    program_address(&[TELEHEALTH_SESSION_SEED, patient_data.as_ref(), session_id])
+}
+
+pub fn disclosure_request_address(patient_data: &Pubkey, court_order_hash: &[u8; 32]) -> Pubkey {
+    program_address(&[
+        DISCLOSURE_REQUEST_SEED,
+        patient_data.as_ref(),
+        court_order_hash,
+    ])
+}
+
+/// Result `result_id` a lab submitted against `patient_data`.
+pub fn lab_result_address(patient_data: &Pubkey, lab: &Pubkey, result_id: u64) -> Pubkey {
+    program_address(&[
+        LAB_RESULT_SEED,
+        patient_data.as_ref(),
+        lab.as_ref(),
+        &result_id.to_le_bytes(),
+    ])
+}
+
+pub fn prescription_address(patient_data: &Pubkey, prescription_id: u64) -> Pubkey {
+    program_address(&[
+        PRESCRIPTION_SEED,
+        patient_data.as_ref(),
+        &prescription_id.to_le_bytes(),
+    ])
+}
+
+pub fn adverse_event_address(reporter: &Pubkey, report_id: u64) -> Pubkey {
+    program_address(&[
+        ADVERSE_EVENT_SEED,
+        reporter.as_ref(),
+        &report_id.to_le_bytes(),
+    ])
+}
+
+pub fn surveillance_tally_address(sponsor: &Pubkey) -> Pubkey {
+    program_address(&[SURVEILLANCE_TALLY_SEED, sponsor.as_ref()])
+}
+
+/// Counter of the reports `reporter` has counted into `tally`.
+pub fn reporter_tally_address(tally: &Pubkey, reporter: &Pubkey) -> Pubkey {
+    program_address(&[REPORTER_TALLY_SEED, tally.as_ref(), reporter.as_ref()])
+}
+
+/// Dispensing ledger entry of fill `fill` of `prescription`.
+pub fn dispense_entry_address(prescription: &Pubkey, fill: u16) -> Pubkey {
+    program_address(&[
+        DISPENSE_ENTRY_SEED,
+        prescription.as_ref(),
+        &fill.to_le_bytes(),
+    ])
+}
+
+// Keyed by the payer of a computation
+
+pub fn used_offsets_address(payer: &Pubkey) -> Pubkey {
+    program_address(&[USED_OFFSETS_SEED, payer.as_ref()])
+}
+
+pub fn refund_balance_address(payer: &Pubkey) -> Pubkey {
+    program_address(&[REFUND_BALANCE_SEED, payer.as_ref()])
+}
+
+pub fn staged_share_address(payer: &Pubkey, computation_offset: u64) -> Pubkey {
+    program_address(&[
+        STAGED_SHARE_SEED,
+        payer.as_ref(),
+        &computation_offset.to_le_bytes(),
+    ])
+}
+
+// Keyed by a computation
+
+pub fn callback_guard_address(computation_offset: u64) -> Pubkey {
+    program_address(&[CALLBACK_GUARD_SEED, &computation_offset.to_le_bytes()])
+}
+
+pub fn fee_escrow_address(computation_offset: u64) -> Pubkey {
+    program_address(&[FEE_ESCROW_SEED, &computation_offset.to_le_bytes()])
+}
+
+/// Log of the `waitlist_priority` run queued at `computation_offset`.
+pub fn waitlist_run_address(computation_offset: u64) -> Pubkey {
+    program_address(&[WAITLIST_RUN_SEED, &computation_offset.to_le_bytes()])
+}
+
+// Keyed by providers, regulators and researchers
+
+pub fn provider_profile_address(provider: &Pubkey) -> Pubkey {
+    program_address(&[PROVIDER_SEED, provider.as_ref()])
+}
+
+/// Binding of a provider to the SNS name account `name_account`.
+pub fn provider_name_address(name_account: &Pubkey) -> Pubkey {
+    program_address(&[PROVIDER_NAME_SEED, name_account.as_ref()])
+}
+
+/// Prior-authorization request `request_id` filed by `provider`.
+pub fn prior_auth_address(provider: &Pubkey, request_id: u64) -> Pubkey {
+    program_address(&[
+        PRIOR_AUTH_SEED,
+        provider.as_ref(),
+        &request_id.to_le_bytes(),
+    ])
+}
+
+/// Escalation of the prior-authorization request at `prior_auth`.
+pub fn prior_auth_escalation_address(prior_auth: &Pubkey) -> Pubkey {
+    program_address(&[PRIOR_AUTH_ESCALATION_SEED, prior_auth.as_ref()])
+}
+
+pub fn case_report_address(provider: &Pubkey, case_id: u64) -> Pubkey {
+    program_address(&[CASE_REPORT_SEED, provider.as_ref(), &case_id.to_le_bytes()])
+}
+
+pub fn subscription_address(provider: &Pubkey) -> Pubkey {
+    program_address(&[SUBSCRIPTION_SEED, provider.as_ref()])
+}
+
+pub fn regulator_activity_address(regulator: &Pubkey) -> Pubkey {
+    program_address(&[REGULATOR_ACTIVITY_SEED, regulator.as_ref()])
+}
+
+pub fn research_escrow_address(researcher: &Pubkey, query_id: u64) -> Pubkey {
+    program_address(&[
+        RESEARCH_ESCROW_SEED,
+        researcher.as_ref(),
+        &query_id.to_le_bytes(),
+    ])
+}
+
+pub fn research_vault_address(research_escrow: &Pubkey) -> Pubkey {
+    program_address(&[RESEARCH_VAULT_SEED, research_escrow.as_ref()])
+}
+
+pub fn trial_escrow_address(sponsor: &Pubkey, trial_id: u64) -> Pubkey {
+    program_address(&[TRIAL_ESCROW_SEED, sponsor.as_ref(), &trial_id.to_le_bytes()])
+}
+
+pub fn trial_vault_address(trial_escrow: &Pubkey) -> Pubkey {
+    program_address(&[TRIAL_VAULT_SEED, trial_escrow.as_ref()])
+}
+
+pub fn trial_enrollment_address(trial_escrow: &Pubkey, patient_data: &Pubkey) -> Pubkey {
+    program_address(&[
+        TRIAL_ENROLLMENT_SEED,
+        trial_escrow.as_ref(),
+        patient_data.as_ref(),
+    ])
+}
+
+pub fn clearance_template_address(employer: &Pubkey, template_id: u64) -> Pubkey {
+    program_address(&[
+        CLEARANCE_TEMPLATE_SEED,
+        employer.as_ref(),
+        &template_id.to_le_bytes(),
+    ])
+}
+
+pub fn clearance_address(clearance_template: &Pubkey, patient_data: &Pubkey) -> Pubkey {
+    program_address(&[
+        CLEARANCE_SEED,
+        clearance_template.as_ref(),
+        patient_data.as_ref(),
+    ])
+}
+
+pub fn cohort_snapshot_address(research_escrow: &Pubkey) -> Pubkey {
+    program_address(&[COHORT_SNAPSHOT_SEED, research_escrow.as_ref()])
+}
+
+pub fn audit_attestation_address(patient_data: &Pubkey, end_entries: u64) -> Pubkey {
+    program_address(&[
+        AUDIT_ATTESTATION_SEED,
+        patient_data.as_ref(),
+        &end_entries.to_le_bytes(),
+    ])
+}
+
+pub fn compressed_tree_address(authority: &Pubkey, tree_id: u64) -> Pubkey {
+    program_address(&[
+        COMPRESSED_TREE_SEED,
+        authority.as_ref(),
+        &tree_id.to_le_bytes(),
+    ])
+}
+
+pub fn ingest_authority_address(tree: &Pubkey, app: &Pubkey) -> Pubkey {
+    program_address(&[INGEST_AUTHORITY_SEED, tree.as_ref(), app.as_ref()])
+}
+
+/// Every address keyed by a patient alone: their record, the accounts keyed by it, and
+/// those they hold as the payer of their own shares.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PatientAddresses {
+    pub patient_data: Pubkey,
+    pub patient_commitment: Pubkey,
+    pub document_vault: Pubkey,
+    pub patient_registry: Pubkey,
+    pub used_nonces: Pubkey,
+    pub access_fee: Pubkey,
+    pub research_opt_in: Pubkey,
+    /// First page of the access log
+    pub access_log_page: Pubkey,
+    pub used_offsets: Pubkey,
+    pub refund_balance: Pubkey,
+    pub emergency_packet: Pubkey,
+    pub inbox: Pubkey,
+}
+
+impl PatientAddresses {
+    /// The addresses in field order.
+    pub fn to_array(&self) -> [Pubkey; 12] {
+        [
+            self.patient_data,
+            self.patient_commitment,
+            self.document_vault,
+            self.patient_registry,
+            self.used_nonces,
+            self.access_fee,
+            self.research_opt_in,
+            self.access_log_page,
+            self.used_offsets,
+            self.refund_balance,
+            self.emergency_packet,
+            self.inbox,
+        ]
+    }
+}
+
+/// Derives every address keyed by `patient` alone.
+pub fn derive_all(patient: &Pubkey) -> PatientAddresses {
+    let patient_data = patient_data_address(patient);
+    PatientAddresses {
+        patient_data,
+        patient_commitment: patient_commitment_address(patient),
+        document_vault: document_vault_address(patient),
+        patient_registry: patient_registry_address(&patient_data),
+        used_nonces: used_nonces_address(&patient_data),
+        access_fee: access_fee_address(&patient_data),
+        research_opt_in: research_opt_in_address(&patient_data),
+        access_log_page: access_log_page_address(&patient_data, 0),
+        used_offsets: used_offsets_address(patient),
+        refund_balance: refund_balance_address(patient),
+        emergency_packet: emergency_packet_address(&patient_data),
+        inbox: inbox_address(&patient_data),
+    }
+}
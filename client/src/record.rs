@@ -0,0 +1,146 @@
+use anchor_lang::prelude::Pubkey;
+
+use share_medical_records::{join_record, split_record, PatientField};
+
+use crate::{nonce_to_u128, ClientError, FieldCipher, Result, NONCE_LEN};
+
+/// Scalars a patient record encrypts to, in the order of the circuits' `PatientData`.
+pub const PATIENT_RECORD_SCALARS: usize = share_medical_records::PATIENT_RECORD_BLOCKS;
+
+/// Plaintext patient record, mirroring `PatientData` in the circuits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PatientRecord {
+    pub patient_id: u64,
+    pub age: u8,
+    pub gender: bool,
+    pub blood_type: u8,
+    pub weight: u16,
+    pub height: u16,
+    pub allergies: [bool; 5],
+}
+
+fn narrow<T: TryFrom<u128>>(field: &'static str, value: u128) -> Result<T> {
+    T::try_from(value).map_err(|_| ClientError::FieldOutOfRange { field, value })
+}
+
+fn narrow_bool(field: &'static str, value: u128) -> Result<bool> {
+    match value {
+        0 => Ok(false),
+        1 => Ok(true),
+        _ => Err(ClientError::FieldOutOfRange { field, value }),
+    }
+}
+
+impl PatientRecord {
+    pub fn to_scalars(&self) -> [u128; PATIENT_RECORD_SCALARS] {
+        join_record(
+            [
+                self.patient_id as u128,
+                self.age as u128,
+                self.gender as u128,
+                self.blood_type as u128,
+                self.weight as u128,
+                self.height as u128,
+            ],
+            self.allergies.map(u128::from),
+        )
+    }
+
+    pub fn from_scalars(scalars: &[u128]) -> Result<Self> {
+        let scalars: &[u128; PATIENT_RECORD_SCALARS] =
+            scalars
+                .try_into()
+                .map_err(|_| ClientError::ScalarCountMismatch {
+                    expected: PATIENT_RECORD_SCALARS,
+                    actual: scalars.len(),
+                })?;
+        let ([patient_id, age, gender, blood_type, weight, height], allergies) =
+            split_record(scalars);
+        let mut allergy_flags = [false; 5];
+        for (flag, scalar) in allergy_flags.iter_mut().zip(allergies) {
+            *flag = narrow_bool("allergies", scalar)?;
+        }
+        Ok(Self {
+            patient_id: narrow("patient_id", patient_id)?,
+            age: narrow("age", age)?,
+            gender: narrow_bool("gender", gender)?,
+            blood_type: narrow("blood_type", blood_type)?,
+            weight: narrow("weight", weight)?,
+            height: narrow("height", height)?,
+            allergies: allergy_flags,
+        })
+    }
+
+    /// Encrypts the record with a cipher keyed for the MXE.
+    ///
+    /// `sender_pub_key` is the public half of the key the cipher was derived from; shares
+    /// of the record must pass it along with `nonce`.
+    pub fn encrypt<C: FieldCipher>(
+        &self,
+        cipher: &C,
+        sender_pub_key: [u8; 32],
+        nonce: [u8; NONCE_LEN],
+    ) -> Result<EncryptedPatientRecord> {
+        let ciphertexts = cipher.encrypt(&self.to_scalars(), &nonce)?;
+        let ciphertexts: [[u8; 32]; PATIENT_RECORD_SCALARS] =
+            ciphertexts.try_into().map_err(|c: Vec<[u8; 32]>| {
+                ClientError::ScalarCountMismatch {
+                    expected: PATIENT_RECORD_SCALARS,
+                    actual: c.len(),
+                }
+            })?;
+        Ok(EncryptedPatientRecord {
+            ciphertexts,
+            sender_pub_key,
+            nonce,
+        })
+    }
+}
+
+/// A patient record encrypted for the MXE, with what later shares need to read it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EncryptedPatientRecord {
+    pub ciphertexts: [[u8; 32]; PATIENT_RECORD_SCALARS],
+    pub sender_pub_key: [u8; 32],
+    pub nonce: [u8; NONCE_LEN],
+}
+
+impl EncryptedPatientRecord {
+    /// Ciphertexts of `field`.
+    pub fn field(&self, field: PatientField) -> &[[u8; 32]] {
+        field.of(&self.ciphertexts)
+    }
+
+    /// Ciphertexts of the scalar fields, in `PatientData` order.
+    pub fn fields(&self) -> [[u8; 32]; 6] {
+        split_record(&self.ciphertexts).0
+    }
+
+    pub fn allergies(&self) -> [[u8; 32]; 5] {
+        split_record(&self.ciphertexts).1
+    }
+
+    /// The record with the blocks of every field outside `record_fields`, the deployment's
+    /// `PatientField` bits, zeroed as the program expects them.
+    pub fn retain_fields(mut self, record_fields: u16) -> Self {
+        for field in PatientField::ALL {
+            if record_fields & field.bit() == 0 {
+                for i in field.blocks() {
+                    self.ciphertexts[i] = [0; 32];
+                }
+            }
+        }
+        self
+    }
+
+    /// Commitment the program keeps when `patient` stores the record in commitment mode.
+    pub fn commitment(&self, patient: &Pubkey) -> [u8; 32] {
+        share_medical_records::patient_commitment(
+            patient,
+            &self.sender_pub_key,
+            nonce_to_u128(&self.nonce),
+            &self.fields(),
+            &self.allergies(),
+        )
+    }
+}
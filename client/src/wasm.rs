@@ -0,0 +1,230 @@
+//! `wasm_bindgen` exports for browser wallets, built with the `wasm` feature for
+//! `wasm32-unknown-unknown`.
+//!
+//! The cipher is a JavaScript object with `encrypt(bigint[], Uint8Array): number[][]` and
+//! `decrypt(number[][], Uint8Array): bigint[]`, i.e. a `RescueCipher` from
+//! `@arcium-hq/client`. Records are packed, addresses derived and instructions built by
+//! the same code native clients use. Instructions are returned as
+//! `{ programId, keys, data }` with base58 keys, ready for `TransactionInstruction`.
+
+use std::str::FromStr;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::AnchorDeserialize;
+use js_sys::{Array, BigInt, Function, Object, Reflect, Uint8Array};
+use share_medical_records::ReceivedPatientDataEvent;
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    ClientError, DecryptedPatientData, EncryptionKeypair, FieldCipher, PatientRecord, Result,
+    NONCE_LEN,
+};
+
+/// A JavaScript cipher object called through its `encrypt` and `decrypt` methods.
+pub struct JsCipher(JsValue);
+
+impl JsCipher {
+    pub fn new(cipher: JsValue) -> Self {
+        Self(cipher)
+    }
+
+    fn call(&self, method: &str, data: &JsValue, nonce: &[u8; NONCE_LEN]) -> Result<JsValue> {
+        let function: Function = Reflect::get(&self.0, &method.into())
+            .map_err(js_error)?
+            .dyn_into()
+            .map_err(js_error)?;
+        function
+            .call2(&self.0, data, &Uint8Array::from(&nonce[..]))
+            .map_err(js_error)
+    }
+}
+
+fn js_error(e: JsValue) -> ClientError {
+    ClientError::Cipher(format!("{e:?}"))
+}
+
+impl FieldCipher for JsCipher {
+    fn encrypt(&self, plaintext: &[u128], nonce: &[u8; NONCE_LEN]) -> Result<Vec<[u8; 32]>> {
+        let plaintext: Array = plaintext
+            .iter()
+            .map(|value| BigInt::new(&value.to_string().into()).map(JsValue::from))
+            .collect::<std::result::Result<_, _>>()
+            .map_err(|e| js_error(e.into()))?;
+        let ciphertexts = self.call("encrypt", &plaintext, nonce)?;
+        Array::from(&ciphertexts)
+            .iter()
+            .map(|ciphertext| {
+                let bytes = Uint8Array::new(&ciphertext).to_vec();
+                bytes
+                    .try_into()
+                    .map_err(|_| ClientError::Cipher("ciphertext is not 32 bytes".into()))
+            })
+            .collect()
+    }
+
+    fn decrypt(&self, ciphertexts: &[[u8; 32]], nonce: &[u8; NONCE_LEN]) -> Result<Vec<u128>> {
+        let ciphertexts: Array = ciphertexts
+            .iter()
+            .map(|ciphertext| Array::from(&Uint8Array::from(&ciphertext[..])))
+            .collect();
+        let plaintext = self.call("decrypt", &ciphertexts, nonce)?;
+        Array::from(&plaintext)
+            .iter()
+            .map(|value| {
+                let digits: String = BigInt::from(value)
+                    .to_string(10)
+                    .map_err(|e| js_error(e.into()))?
+                    .into();
+                digits
+                    .parse()
+                    .map_err(|_| ClientError::Cipher(format!("{digits} is not a u128")))
+            })
+            .collect()
+    }
+}
+
+fn pubkey(key: &str) -> std::result::Result<Pubkey, JsError> {
+    Pubkey::from_str(key).map_err(|e| JsError::new(&format!("invalid public key {key}: {e}")))
+}
+
+fn bytes<const N: usize>(name: &str, value: &[u8]) -> std::result::Result<[u8; N], JsError> {
+    value
+        .try_into()
+        .map_err(|_| JsError::new(&format!("{name} must be {N} bytes")))
+}
+
+fn client_error(e: ClientError) -> JsError {
+    JsError::new(&e.to_string())
+}
+
+fn set(object: &Object, key: &str, value: impl Into<JsValue>) {
+    // Setting a property on a plain object cannot fail
+    Reflect::set(object, &key.into(), &value.into()).unwrap();
+}
+
+fn instruction_to_js(ix: Instruction) -> JsValue {
+    let keys: Array = ix
+        .accounts
+        .iter()
+        .map(|meta| {
+            let key = Object::new();
+            set(&key, "pubkey", meta.pubkey.to_string());
+            set(&key, "isSigner", meta.is_signer);
+            set(&key, "isWritable", meta.is_writable);
+            JsValue::from(key)
+        })
+        .collect();
+    let object = Object::new();
+    set(&object, "programId", ix.program_id.to_string());
+    set(&object, "keys", keys);
+    set(&object, "data", Uint8Array::from(&ix.data[..]));
+    object.into()
+}
+
+/// Secret key of a fresh x25519 keypair.
+#[wasm_bindgen(js_name = generateEncryptionSecret)]
+pub fn generate_encryption_secret() -> Vec<u8> {
+    EncryptionKeypair::generate().secret_bytes().to_vec()
+}
+
+#[wasm_bindgen(js_name = encryptionPublicKey)]
+pub fn encryption_public_key(secret: &[u8]) -> std::result::Result<Vec<u8>, JsError> {
+    let keypair = EncryptionKeypair::from_secret_bytes(bytes("secret", secret)?);
+    Ok(keypair.public_key().to_vec())
+}
+
+/// Shared secret to key the cipher with, e.g. with the MXE's x25519 key.
+#[wasm_bindgen(js_name = sharedSecret)]
+pub fn shared_secret(secret: &[u8], peer: &[u8]) -> std::result::Result<Vec<u8>, JsError> {
+    let keypair = EncryptionKeypair::from_secret_bytes(bytes("secret", secret)?);
+    Ok(keypair.shared_secret(&bytes("peer", peer)?).to_vec())
+}
+
+#[wasm_bindgen(js_name = randomNonce)]
+pub fn random_nonce() -> Vec<u8> {
+    crate::random_nonce().to_vec()
+}
+
+#[wasm_bindgen(js_name = patientDataAddress)]
+pub fn patient_data_address(patient: &str) -> std::result::Result<String, JsError> {
+    Ok(crate::patient_data_address(&pubkey(patient)?).to_string())
+}
+
+/// Encrypts a record and builds the `store_patient_data` instruction storing it, or
+/// `update_patient_data` when `update` is set.
+#[allow(clippy::too_many_arguments)]
+#[wasm_bindgen(js_name = storePatientDataInstruction)]
+pub fn store_patient_data_instruction(
+    patient: &str,
+    patient_id: u64,
+    age: u8,
+    gender: bool,
+    blood_type: u8,
+    weight: u16,
+    height: u16,
+    allergies: Vec<u8>,
+    cipher: JsValue,
+    sender_pub_key: &[u8],
+    nonce: &[u8],
+    update: bool,
+) -> std::result::Result<JsValue, JsError> {
+    let allergies: [u8; 5] = bytes("allergies", &allergies)?;
+    let record = PatientRecord {
+        patient_id,
+        age,
+        gender,
+        blood_type,
+        weight,
+        height,
+        allergies: allergies.map(|allergy| allergy != 0),
+    };
+    let encrypted = record
+        .encrypt(
+            &JsCipher::new(cipher),
+            bytes("sender_pub_key", sender_pub_key)?,
+            bytes("nonce", nonce)?,
+        )
+        .map_err(client_error)?;
+    let patient = pubkey(patient)?;
+    let ix = if update {
+        crate::update_patient_data_ix(patient, &encrypted)
+    } else {
+        crate::store_patient_data_ix(patient, patient, &encrypted)
+    };
+    Ok(instruction_to_js(ix))
+}
+
+/// Decrypts a `ReceivedPatientDataEvent`, given its borsh bytes after the discriminator,
+/// into a plain object with one property per record field.
+#[wasm_bindgen(js_name = decryptReceivedPatientData)]
+pub fn decrypt_received_patient_data(
+    event: &[u8],
+    cipher: JsValue,
+) -> std::result::Result<JsValue, JsError> {
+    let event = ReceivedPatientDataEvent::try_from_slice(event)
+        .map_err(|e| JsError::new(&format!("invalid event: {e}")))?;
+    let decrypted =
+        DecryptedPatientData::from_event(&event, &JsCipher::new(cipher)).map_err(client_error)?;
+    let record = decrypted.record;
+    let object = Object::new();
+    set(
+        &object,
+        "patientRecord",
+        decrypted.patient_record.to_string(),
+    );
+    set(
+        &object,
+        "computationOffset",
+        BigInt::from(decrypted.computation_offset),
+    );
+    set(&object, "patientId", BigInt::from(record.patient_id));
+    set(&object, "age", record.age);
+    set(&object, "gender", record.gender);
+    set(&object, "bloodType", record.blood_type);
+    set(&object, "weight", record.weight);
+    set(&object, "height", record.height);
+    let allergies: Array = record.allergies.iter().map(|&a| JsValue::from(a)).collect();
+    set(&object, "allergies", allergies);
+    Ok(object.into())
+}
@@ -22,4 +22,593 @@ mod circuits {
         let input = input_ctxt.to_arcis();
         receiver.from_arcis(input)
     }
+
+    /// Re-encrypts a commitment-mode record, passed by value and already checked against
+    /// its on-chain commitment, for a receiver.
+    #[instruction]
+    pub fn share_committed_patient_data(
+        receiver: Shared,
+        input_ctxt: Enc<Shared, PatientData>,
+    ) -> Enc<Shared, PatientData> {
+        let input = input_ctxt.to_arcis();
+        receiver.from_arcis(input)
+    }
+
+    /// Symmetric key an off-chain document is encrypted with, split into two limbs.
+    pub struct ContentKey {
+        pub lo: u128,
+        pub hi: u128,
+    }
+
+    /// Re-encrypts the content key of an off-chain document for a receiver.
+    #[instruction]
+    pub fn share_content_key(
+        receiver: Shared,
+        content_key: Enc<Shared, ContentKey>,
+    ) -> Enc<Shared, ContentKey> {
+        let content_key = content_key.to_arcis();
+        receiver.from_arcis(content_key)
+    }
+
+    /// A patient's structured record shared together with the content keys of their
+    /// lab results and prescriptions.
+    pub struct RecordBundle {
+        pub record: PatientData,
+        pub labs: ContentKey,
+        pub prescriptions: ContentKey,
+    }
+
+    /// Re-encrypts a record bundle for a receiver in one computation.
+    #[instruction]
+    pub fn share_bundle(
+        receiver: Shared,
+        record: Enc<Shared, PatientData>,
+        labs: Enc<Shared, ContentKey>,
+        prescriptions: Enc<Shared, ContentKey>,
+    ) -> Enc<Shared, RecordBundle> {
+        let bundle = RecordBundle {
+            record: record.to_arcis(),
+            labs: labs.to_arcis(),
+            prescriptions: prescriptions.to_arcis(),
+        };
+        receiver.from_arcis(bundle)
+    }
+
+    /// Identifiers of a DICOM study, as 16-byte ASCII limbs.
+    pub struct DicomIdentifiers {
+        pub study_uid: [u128; 4],
+        pub accession_number: u128,
+    }
+
+    /// Re-encrypts the identifiers of a DICOM study for a receiver.
+    #[instruction]
+    pub fn share_dicom_reference(
+        receiver: Shared,
+        identifiers: Enc<Shared, DicomIdentifiers>,
+    ) -> Enc<Shared, DicomIdentifiers> {
+        let identifiers = identifiers.to_arcis();
+        receiver.from_arcis(identifiers)
+    }
+
+    /// Values of a compressed record such as a vitals reading or encounter summary.
+    pub struct CompressedRecord {
+        pub values: [u128; 4],
+    }
+
+    /// Re-encrypts a compressed record, already checked against its tree, for a receiver.
+    #[instruction]
+    pub fn share_compressed_record(
+        receiver: Shared,
+        record: Enc<Shared, CompressedRecord>,
+    ) -> Enc<Shared, CompressedRecord> {
+        let record = record.to_arcis();
+        receiver.from_arcis(record)
+    }
+
+    /// Re-encrypts a record for a regulator under a committee-approved disclosure.
+    ///
+    /// Kept separate from `share_patient_data` so disclosures run under their own comp def
+    /// and can be audited and retired independently.
+    #[instruction]
+    pub fn regulatory_disclosure(
+        regulator: Shared,
+        record: Enc<Shared, PatientData>,
+    ) -> Enc<Shared, PatientData> {
+        let record = record.to_arcis();
+        regulator.from_arcis(record)
+    }
+
+    /// Fields of a record responders need at the roadside.
+    pub struct EmergencyFields {
+        pub age: u8,
+        pub blood_type: u8,
+        pub allergies: [bool; 5],
+    }
+
+    /// Re-encrypts a record's emergency fields for a regional EMS authority, ahead of
+    /// any emergency.
+    #[instruction]
+    pub fn emergency_packet(
+        ems: Shared,
+        record: Enc<Shared, PatientData>,
+    ) -> Enc<Shared, EmergencyFields> {
+        let record = record.to_arcis();
+        let fields = EmergencyFields {
+            age: record.age,
+            blood_type: record.blood_type,
+            allergies: record.allergies,
+        };
+        ems.from_arcis(fields)
+    }
+
+    /// Re-encrypts an emergency packet for a responder during a declared disaster, without
+    /// the patient generating a packet for them.
+    #[instruction]
+    pub fn disaster_packet(
+        responder: Shared,
+        packet: Enc<Shared, EmergencyFields>,
+    ) -> Enc<Shared, EmergencyFields> {
+        responder.from_arcis(packet.to_arcis())
+    }
+
+    /// Checks whether a record shows an age of at least `min_age`.
+    ///
+    /// Only the pass or fail result is revealed; the age itself stays encrypted.
+    #[instruction]
+    pub fn verify_age_over(record: Enc<Shared, PatientData>, min_age: u8) -> bool {
+        let record = record.to_arcis();
+        (record.age >= min_age).reveal()
+    }
+
+    /// Checks a record against an employer's clearance requirements: an age of at least
+    /// `min_age` and none of the allergies whose bits are set in `excluded_allergies`.
+    ///
+    /// Only the pass or fail result is revealed; no field value leaves the cluster.
+    #[instruction]
+    pub fn clearance_check(
+        record: Enc<Shared, PatientData>,
+        min_age: u8,
+        excluded_allergies: u8,
+    ) -> bool {
+        let record = record.to_arcis();
+        let mut cleared = record.age >= min_age;
+        for i in 0..5 {
+            if (excluded_allergies >> i) & 1 == 1 && record.allergies[i] {
+                cleared = false;
+            }
+        }
+        cleared.reveal()
+    }
+
+    /// Diagnosis and procedure codes of a prior-authorization request, as the scalars
+    /// their code sets assign.
+    pub struct PriorAuthCodes {
+        pub diagnosis: u64,
+        pub procedure: u64,
+    }
+
+    /// Diagnosis and procedure pairs an insurer covers; `diagnoses[i]` goes with
+    /// `procedures[i]`. Unused slots hold zeros; a zero diagnosis never matches.
+    pub struct CoveragePolicy {
+        pub diagnoses: [u64; 4],
+        pub procedures: [u64; 4],
+    }
+
+    /// Outcomes of `validate_prior_auth`, matching the program's `PRIOR_AUTH_OUTCOME_*`.
+    const PRIOR_AUTH_DENIED: u8 = 0;
+    const PRIOR_AUTH_APPROVED: u8 = 1;
+    const PRIOR_AUTH_NEEDS_REVIEW: u8 = 2;
+
+    /// Checks whether a prior-authorization request's codes match a pair the insurer's
+    /// policy covers.
+    ///
+    /// A request whose diagnosis the policy covers, but not with the requested procedure,
+    /// needs review rather than being denied outright. Only the outcome is revealed; the
+    /// codes and the policy stay encrypted.
+    #[instruction]
+    pub fn validate_prior_auth(
+        request: Enc<Shared, PriorAuthCodes>,
+        policy: Enc<Shared, CoveragePolicy>,
+    ) -> u8 {
+        let request = request.to_arcis();
+        let policy = policy.to_arcis();
+
+        let mut approved = false;
+        let mut diagnosis_covered = false;
+        for i in 0..4 {
+            let diagnosis_matches = policy.diagnoses[i] == request.diagnosis;
+            if diagnosis_matches && request.diagnosis != 0 {
+                diagnosis_covered = true;
+                if policy.procedures[i] == request.procedure {
+                    approved = true;
+                }
+            }
+        }
+
+        let outcome = if approved {
+            PRIOR_AUTH_APPROVED
+        } else if diagnosis_covered {
+            PRIOR_AUTH_NEEDS_REVIEW
+        } else {
+            PRIOR_AUTH_DENIED
+        };
+        outcome.reveal()
+    }
+
+    /// Moves a prescription's refill count, encrypted by its prescriber, into state only
+    /// the cluster can decrypt, so later fills can count it down.
+    #[instruction]
+    pub fn issue_prescription(mxe: Mxe, refills: Enc<Shared, u8>) -> Enc<Mxe, u8> {
+        let refills = refills.to_arcis();
+        mxe.from_arcis(refills)
+    }
+
+    /// Counts one fill against a prescription's refills, if any are left.
+    ///
+    /// Returns the updated count, still encrypted to the cluster, and whether the fill
+    /// may be dispensed; only the latter is revealed.
+    #[instruction]
+    pub fn verify_prescription(refills: Enc<Mxe, u8>) -> (Enc<Mxe, u8>, bool) {
+        let remaining = refills.to_arcis();
+        let dispensable = remaining > 0;
+        let next = if dispensable {
+            remaining - 1
+        } else {
+            remaining
+        };
+        (refills.owner.from_arcis(next), dispensable.reveal())
+    }
+
+    /// Suspected drug and observed reaction of an adverse drug event report.
+    pub struct AdverseEvent {
+        pub drug_code: u64,
+        pub reaction: u64,
+    }
+
+    /// Adverse event reports counted per drug of a sponsor's tally.
+    pub struct AdverseEventCounts {
+        pub counts: [u16; 8],
+    }
+
+    /// Starts a sponsor's adverse event tally at zero, encrypted to the cluster.
+    #[instruction]
+    pub fn init_adverse_event_tally(mxe: Mxe) -> Enc<Mxe, AdverseEventCounts> {
+        mxe.from_arcis(AdverseEventCounts { counts: [0; 8] })
+    }
+
+    /// Counts one adverse event report against the tally slot of the drug it names.
+    ///
+    /// Reports naming none of `drug_codes` leave the tally unchanged; nothing is revealed
+    /// either way, not even whether the report matched.
+    #[instruction]
+    pub fn tally_adverse_event(
+        report: Enc<Shared, AdverseEvent>,
+        tally: Enc<Mxe, AdverseEventCounts>,
+        drug_codes: [u64; 8],
+    ) -> Enc<Mxe, AdverseEventCounts> {
+        let report = report.to_arcis();
+        let mut counts = tally.to_arcis();
+        for i in 0..8 {
+            if drug_codes[i] != 0 && report.drug_code == drug_codes[i] {
+                counts.counts[i] += 1;
+            }
+        }
+        tally.owner.from_arcis(counts)
+    }
+
+    /// Re-encrypts a tally's counts for its sponsor, zeroing any below `min_count` so a
+    /// small count can't be traced back to the few reports behind it.
+    #[instruction]
+    pub fn adverse_event_counts(
+        sponsor: Shared,
+        tally: Enc<Mxe, AdverseEventCounts>,
+        min_count: u16,
+    ) -> Enc<Shared, AdverseEventCounts> {
+        let mut counts = tally.to_arcis();
+        for i in 0..8 {
+            if counts.counts[i] < min_count {
+                counts.counts[i] = 0;
+            }
+        }
+        sponsor.from_arcis(counts)
+    }
+
+    /// Diagnosis and patient identifier of a notifiable disease case.
+    pub struct NotifiableCase {
+        pub diagnosis: u64,
+        pub patient_id: u64,
+    }
+
+    /// Cases counted per notifiable disease of a public-health authority.
+    pub struct CaseCounts {
+        pub counts: [u16; 8],
+    }
+
+    /// Starts a public-health authority's case counts at zero, encrypted to the cluster.
+    #[instruction]
+    pub fn open_case_counts(mxe: Mxe) -> Enc<Mxe, CaseCounts> {
+        mxe.from_arcis(CaseCounts { counts: [0; 8] })
+    }
+
+    /// Counts a reported case against the notifiable disease its diagnosis matches, and
+    /// seals the case to the cluster.
+    ///
+    /// Nothing is revealed: the counts stay encrypted to the cluster, and the case can
+    /// only be read again through `reveal_case`.
+    #[instruction]
+    pub fn notify_case(
+        mxe: Mxe,
+        case: Enc<Shared, NotifiableCase>,
+        counts: Enc<Mxe, CaseCounts>,
+        disease_codes: [u64; 8],
+    ) -> (Enc<Mxe, CaseCounts>, Enc<Mxe, NotifiableCase>) {
+        let case = case.to_arcis();
+        let mut tally = counts.to_arcis();
+        for i in 0..8 {
+            if disease_codes[i] != 0 && case.diagnosis == disease_codes[i] {
+                tally.counts[i] += 1;
+            }
+        }
+        (counts.owner.from_arcis(tally), mxe.from_arcis(case))
+    }
+
+    /// Re-encrypts a public-health authority's case counts for it.
+    #[instruction]
+    pub fn case_counts(authority: Shared, counts: Enc<Mxe, CaseCounts>) -> Enc<Shared, CaseCounts> {
+        authority.from_arcis(counts.to_arcis())
+    }
+
+    /// Re-encrypts a sealed case for the public-health authority, on the legal
+    /// committee's order.
+    #[instruction]
+    pub fn reveal_case(
+        authority: Shared,
+        case: Enc<Mxe, NotifiableCase>,
+    ) -> Enc<Shared, NotifiableCase> {
+        authority.from_arcis(case.to_arcis())
+    }
+
+    /// Zeroes the fields of `record` outside `fields`, a mask of the program's
+    /// `PatientField` bits in `PatientData` field order.
+    fn select_fields(mut record: PatientData, fields: u16) -> PatientData {
+        if fields & (1 << 0) == 0 {
+            record.patient_id = 0;
+        }
+        if fields & (1 << 1) == 0 {
+            record.age = 0;
+        }
+        if fields & (1 << 2) == 0 {
+            record.gender = false;
+        }
+        if fields & (1 << 3) == 0 {
+            record.blood_type = 0;
+        }
+        if fields & (1 << 4) == 0 {
+            record.weight = 0;
+        }
+        if fields & (1 << 5) == 0 {
+            record.height = 0;
+        }
+        if fields & (1 << 6) == 0 {
+            record.allergies = [false; 5];
+        }
+        record
+    }
+
+    /// Re-encrypts the fields of a record selected by `fields` back to its own patient,
+    /// zeroing the rest, so they can see what a share would disclose.
+    ///
+    /// Bits of `fields` follow the program's `PatientField`, in `PatientData` field order.
+    #[instruction]
+    pub fn preview_share(
+        patient: Shared,
+        input_ctxt: Enc<Shared, PatientData>,
+        fields: u16,
+    ) -> Enc<Shared, PatientData> {
+        patient.from_arcis(select_fields(input_ctxt.to_arcis(), fields))
+    }
+
+    /// Re-encrypts the fields of a record a caregiver grant covers for the caregiver,
+    /// zeroing the rest.
+    #[instruction]
+    pub fn caregiver_update(
+        caregiver: Shared,
+        record: Enc<Shared, PatientData>,
+        fields: u16,
+    ) -> Enc<Shared, PatientData> {
+        caregiver.from_arcis(select_fields(record.to_arcis(), fields))
+    }
+
+    /// Re-encrypts the fields of a record a one-time share code covers for the provider
+    /// who redeemed it, zeroing the rest.
+    #[instruction]
+    pub fn redeem_share_code(
+        provider: Shared,
+        record: Enc<Shared, PatientData>,
+        fields: u16,
+    ) -> Enc<Shared, PatientData> {
+        provider.from_arcis(select_fields(record.to_arcis(), fields))
+    }
+
+    /// Re-encrypts the fields an insurer disputes in a prior-authorization review for the
+    /// insurer, once the patient has approved the disclosure, zeroing the rest.
+    #[instruction]
+    pub fn disclose_escalated_fields(
+        insurer: Shared,
+        record: Enc<Shared, PatientData>,
+        fields: u16,
+    ) -> Enc<Shared, PatientData> {
+        insurer.from_arcis(select_fields(record.to_arcis(), fields))
+    }
+
+    /// Blood type value matching any record in a cohort query.
+    const ANY_BLOOD_TYPE: u8 = u8::MAX;
+
+    /// Counts the records of a fixed-size cohort matching an age range and blood type.
+    ///
+    /// Only the count is returned, encrypted for the researcher; which records matched
+    /// stays hidden from everyone.
+    #[instruction]
+    pub fn cohort_query(
+        researcher: Shared,
+        min_age: u8,
+        max_age: u8,
+        blood_type: u8,
+        record_0: Enc<Shared, PatientData>,
+        record_1: Enc<Shared, PatientData>,
+        record_2: Enc<Shared, PatientData>,
+        record_3: Enc<Shared, PatientData>,
+    ) -> Enc<Shared, u8> {
+        let records = [
+            record_0.to_arcis(),
+            record_1.to_arcis(),
+            record_2.to_arcis(),
+            record_3.to_arcis(),
+        ];
+
+        let mut count: u8 = 0;
+        for record in records.iter() {
+            let in_age_range = record.age >= min_age && record.age <= max_age;
+            let blood_type_matches =
+                blood_type == ANY_BLOOD_TYPE || record.blood_type == blood_type;
+            if in_age_range && blood_type_matches {
+                count += 1;
+            }
+        }
+
+        researcher.from_arcis(count)
+    }
+
+    /// A registered blood donor.
+    pub struct Donor {
+        /// Red-cell antigens the donor carries
+        pub antigen_a: bool,
+        pub antigen_b: bool,
+        pub rh_d: bool,
+        /// Products the donor may currently give, in the program's `DONOR_PRODUCT_*`
+        /// bit order: whole blood, platelets, plasma
+        pub eligible: [bool; 3],
+        /// Random value the donor chose, which identifies them only to themselves
+        pub token: u64,
+    }
+
+    /// Result of a donor search: how many donors matched and their tokens, packed to the
+    /// front in no particular relation to the order donors were passed in.
+    pub struct CompatibleDonors {
+        pub count: u8,
+        pub tokens: [u64; 4],
+    }
+
+    /// Finds the donors of a fixed-size batch whose red cells a recipient can receive and
+    /// who may give one of `products`.
+    ///
+    /// `recipient_antigens` and `products` are plaintext masks in the program's
+    /// `BLOOD_ANTIGEN_*` and `DONOR_PRODUCT_*` bit order. Only the count and the matching
+    /// donors' tokens are returned, encrypted for the blood bank; a token sits at the
+    /// next free slot rather than at its donor's position, so which donors matched stays
+    /// hidden.
+    #[instruction]
+    pub fn find_compatible_donors(
+        blood_bank: Shared,
+        recipient_antigens: u8,
+        products: u8,
+        donor_0: Enc<Shared, Donor>,
+        donor_1: Enc<Shared, Donor>,
+        donor_2: Enc<Shared, Donor>,
+        donor_3: Enc<Shared, Donor>,
+    ) -> Enc<Shared, CompatibleDonors> {
+        let donors = [
+            donor_0.to_arcis(),
+            donor_1.to_arcis(),
+            donor_2.to_arcis(),
+            donor_3.to_arcis(),
+        ];
+        let recipient_a = recipient_antigens & (1 << 0) != 0;
+        let recipient_b = recipient_antigens & (1 << 1) != 0;
+        let recipient_rh_d = recipient_antigens & (1 << 2) != 0;
+
+        let mut found = CompatibleDonors {
+            count: 0,
+            tokens: [0; 4],
+        };
+        for donor in donors.iter() {
+            let red_cells_compatible = (!donor.antigen_a || recipient_a)
+                && (!donor.antigen_b || recipient_b)
+                && (!donor.rh_d || recipient_rh_d);
+            let mut can_give = false;
+            for product in 0..3 {
+                if products & (1 << product) != 0 && donor.eligible[product] {
+                    can_give = true;
+                }
+            }
+            let compatible = red_cells_compatible && can_give;
+            for slot in 0..4 {
+                if compatible && found.count == slot as u8 {
+                    found.tokens[slot] = donor.token;
+                }
+            }
+            if compatible {
+                found.count += 1;
+            }
+        }
+
+        blood_bank.from_arcis(found)
+    }
+
+    /// A transplant candidate's clinical urgency.
+    pub struct WaitlistCandidate {
+        /// Urgency score assigned by the candidate's transplant center; higher is sicker
+        pub urgency: u16,
+        /// Days on the waitlist, which breaks ties in urgency
+        pub days_waiting: u16,
+        /// Random value the candidate chose, which identifies them only to themselves
+        /// and the centers they gave it to
+        pub token: u64,
+    }
+
+    /// Candidates' tokens from highest to lowest priority.
+    pub struct WaitlistRanking {
+        pub tokens: [u64; 4],
+    }
+
+    /// Orders a fixed-size batch of candidates by urgency, then by days waiting.
+    ///
+    /// Sorted with a compare-exchange network, so the work done is the same whatever the
+    /// order. Only the ranked tokens are returned, encrypted for the coordinator, who
+    /// learns neither the candidates' scores nor the margins between them.
+    #[instruction]
+    pub fn waitlist_priority(
+        coordinator: Shared,
+        candidate_0: Enc<Shared, WaitlistCandidate>,
+        candidate_1: Enc<Shared, WaitlistCandidate>,
+        candidate_2: Enc<Shared, WaitlistCandidate>,
+        candidate_3: Enc<Shared, WaitlistCandidate>,
+    ) -> Enc<Shared, WaitlistRanking> {
+        let candidates = [
+            candidate_0.to_arcis(),
+            candidate_1.to_arcis(),
+            candidate_2.to_arcis(),
+            candidate_3.to_arcis(),
+        ];
+        let mut priorities = [0u32; 4];
+        let mut tokens = [0u64; 4];
+        for i in 0..4 {
+            priorities[i] =
+                (candidates[i].urgency as u32) * (1 << 16) + candidates[i].days_waiting as u32;
+            tokens[i] = candidates[i].token;
+        }
+
+        for (i, j) in [(0, 1), (2, 3), (0, 2), (1, 3), (1, 2)] {
+            let (priority_i, priority_j) = (priorities[i], priorities[j]);
+            let (token_i, token_j) = (tokens[i], tokens[j]);
+            if priority_j > priority_i {
+                priorities[i] = priority_j;
+                priorities[j] = priority_i;
+                tokens[i] = token_j;
+                tokens[j] = token_i;
+            }
+        }
+
+        coordinator.from_arcis(WaitlistRanking { tokens })
+    }
 }
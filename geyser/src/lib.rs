@@ -0,0 +1,26 @@
+//! Push notifications for hospital integration engines, from a validator's Geyser
+//! interface instead of RPC polling.
+//!
+//! [`Notification`] is the schema: new access requests, settled and completed shares,
+//! consent changes and new lab results, with strongly typed payloads. [`MedicalRecordsPlugin`] is an
+//! example plugin streaming them as JSON lines; load the `cdylib` build with the
+//! validator's `--geyser-plugin-config`.
+
+pub mod plugin;
+pub mod schema;
+
+pub use plugin::*;
+pub use schema::*;
+
+use agave_geyser_plugin_interface::geyser_plugin_interface::GeyserPlugin;
+
+/// Entry point the validator loads the plugin through.
+///
+/// # Safety
+///
+/// Called by the validator's plugin loader, which takes ownership of the returned box.
+#[no_mangle]
+#[allow(improper_ctypes_definitions)]
+pub unsafe extern "C" fn _create_plugin() -> *mut dyn GeyserPlugin {
+    Box::into_raw(Box::<MedicalRecordsPlugin>::default())
+}
@@ -0,0 +1,214 @@
+//! Example Geyser plugin pushing [`Notification`]s as newline-delimited JSON over TCP.
+//!
+//! Validator config:
+//!
+//! ```json
+//! { "libpath": "target/release/libshare_medical_records_geyser.so", "endpoint": "127.0.0.1:9300" }
+//! ```
+//!
+//! Notifications are queued to a background thread that reconnects to `endpoint` as
+//! needed; the validator is never blocked on the integration engine, and notifications
+//! produced while it is unreachable are dropped once the queue is full.
+
+use std::collections::HashMap;
+use std::io::Write;
+use std::net::TcpStream;
+use std::sync::mpsc::{sync_channel, SyncSender, TrySendError};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use agave_geyser_plugin_interface::geyser_plugin_interface::{
+    GeyserPlugin, GeyserPluginError, ReplicaAccountInfoVersions, ReplicaTransactionInfoVersions,
+    Result,
+};
+use anchor_lang::event::EVENT_IX_TAG_LE;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::Deserialize;
+use solana_sdk::pubkey::Pubkey;
+
+use crate::Notification;
+
+/// Notifications held while the endpoint is unreachable.
+const QUEUE_LEN: usize = 10_000;
+
+#[derive(Deserialize)]
+struct Config {
+    endpoint: String,
+}
+
+#[derive(Default, Debug)]
+pub struct MedicalRecordsPlugin {
+    sender: Option<SyncSender<Notification>>,
+    /// Open pending shares and their computation offsets, to report them settled once
+    /// their account is closed
+    pending: Mutex<HashMap<Pubkey, u64>>,
+}
+
+impl MedicalRecordsPlugin {
+    fn push(&self, notification: Notification) {
+        let Some(sender) = &self.sender else {
+            return;
+        };
+        if let Err(TrySendError::Full(_)) = sender.try_send(notification) {
+            log::warn!("notification queue full, dropping notification");
+        }
+    }
+
+    fn program_id() -> [u8; 32] {
+        share_medical_records::ID.to_bytes()
+    }
+}
+
+fn forward(endpoint: String, receiver: std::sync::mpsc::Receiver<Notification>) {
+    let mut stream: Option<TcpStream> = None;
+    for notification in receiver {
+        let mut line = serde_json::to_vec(&notification).expect("notifications serialize");
+        line.push(b'\n');
+        loop {
+            if stream.is_none() {
+                match TcpStream::connect(&endpoint) {
+                    Ok(connected) => stream = Some(connected),
+                    Err(e) => {
+                        log::warn!("cannot reach {endpoint}: {e}");
+                        thread::sleep(Duration::from_secs(1));
+                        continue;
+                    }
+                }
+            }
+            match stream.as_mut().map(|stream| stream.write_all(&line)) {
+                Some(Ok(())) => break,
+                _ => stream = None,
+            }
+        }
+    }
+}
+
+impl GeyserPlugin for MedicalRecordsPlugin {
+    fn name(&self) -> &'static str {
+        "share_medical_records"
+    }
+
+    fn on_load(&mut self, config_file: &str, _is_reload: bool) -> Result<()> {
+        let config = std::fs::read_to_string(config_file)?;
+        let config: Config = serde_json::from_str(&config)
+            .map_err(|e| GeyserPluginError::ConfigFileReadError { msg: e.to_string() })?;
+        let (sender, receiver) = sync_channel(QUEUE_LEN);
+        thread::spawn(move || forward(config.endpoint, receiver));
+        self.sender = Some(sender);
+        Ok(())
+    }
+
+    fn on_unload(&mut self) {
+        // Dropping the sender ends the forwarding thread
+        self.sender = None;
+    }
+
+    fn update_account(
+        &self,
+        account: ReplicaAccountInfoVersions,
+        slot: u64,
+        is_startup: bool,
+    ) -> Result<()> {
+        let (address, owner, lamports, data) = match account {
+            ReplicaAccountInfoVersions::V0_0_1(a) => (a.pubkey, a.owner, a.lamports, a.data),
+            ReplicaAccountInfoVersions::V0_0_2(a) => (a.pubkey, a.owner, a.lamports, a.data),
+            ReplicaAccountInfoVersions::V0_0_3(a) => (a.pubkey, a.owner, a.lamports, a.data),
+        };
+        if is_startup || owner != Self::program_id() {
+            return Ok(());
+        }
+        let Ok(address) = Pubkey::try_from(address) else {
+            return Ok(());
+        };
+
+        let mut pending = self.pending.lock().unwrap();
+        if lamports == 0 {
+            if let Some(computation_offset) = pending.remove(&address) {
+                self.push(Notification::AccessSettled {
+                    slot,
+                    pending_share: address.to_string(),
+                    computation_offset,
+                });
+            }
+            return Ok(());
+        }
+        if pending.contains_key(&address) {
+            return Ok(());
+        }
+        if let Some(notification) = Notification::from_account(&address, data, slot) {
+            if let Notification::AccessRequested {
+                computation_offset, ..
+            } = notification
+            {
+                pending.insert(address, computation_offset);
+            }
+            self.push(notification);
+        }
+        Ok(())
+    }
+
+    fn notify_transaction(
+        &self,
+        transaction: ReplicaTransactionInfoVersions,
+        slot: u64,
+    ) -> Result<()> {
+        let (signature, is_vote, tx, meta) = match transaction {
+            ReplicaTransactionInfoVersions::V0_0_1(t) => (
+                t.signature,
+                t.is_vote,
+                t.transaction,
+                t.transaction_status_meta,
+            ),
+            ReplicaTransactionInfoVersions::V0_0_2(t) => (
+                t.signature,
+                t.is_vote,
+                t.transaction,
+                t.transaction_status_meta,
+            ),
+        };
+        let program_id = share_medical_records::ID;
+        let account_keys = tx.message().account_keys();
+        if is_vote || meta.status.is_err() || !account_keys.iter().any(|k| *k == program_id) {
+            return Ok(());
+        }
+        let signature = signature.to_string();
+
+        // Events emitted through the logs, then through event CPI
+        let logged = meta
+            .log_messages
+            .iter()
+            .flatten()
+            .filter_map(|log| log.strip_prefix("Program data: "))
+            .filter_map(|data| BASE64.decode(data).ok());
+        let cpi = meta
+            .inner_instructions
+            .iter()
+            .flatten()
+            .flat_map(|inner| &inner.instructions)
+            .filter(|ix| {
+                account_keys.get(ix.instruction.program_id_index as usize) == Some(&program_id)
+            })
+            .filter_map(|ix| {
+                ix.instruction
+                    .data
+                    .strip_prefix(EVENT_IX_TAG_LE)
+                    .map(<[u8]>::to_vec)
+            });
+        for event in logged.chain(cpi) {
+            if let Some(notification) = Notification::from_event(&event, slot, &signature) {
+                self.push(notification);
+            }
+        }
+        Ok(())
+    }
+
+    fn account_data_notifications_enabled(&self) -> bool {
+        true
+    }
+
+    fn transaction_notifications_enabled(&self) -> bool {
+        true
+    }
+}
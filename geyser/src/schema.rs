@@ -0,0 +1,179 @@
+//! Typed notifications a hospital integration engine receives, serialized as JSON with a
+//! `type` tag. Decoding lives here so consumers can depend on the crate for the types
+//! alone.
+
+use anchor_lang::{AccountDeserialize, AnchorDeserialize, Discriminator};
+use serde::{Deserialize, Serialize};
+use share_medical_records::{
+    ConsentGrantedEvent, ConsentRevokedEvent, NewResultAvailableEvent, PendingShare,
+    ReceivedPatientDataCompactEvent, ReceivedPatientDataEvent, SharePatientDataAbortedEvent,
+};
+use solana_sdk::pubkey::Pubkey;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Notification {
+    /// A share was queued and waits for its MPC computation
+    AccessRequested {
+        slot: u64,
+        pending_share: String,
+        payer: String,
+        patient_data: String,
+        computation_offset: u64,
+        queued_at: i64,
+    },
+    /// A queued share was settled, by its callback or by expiry
+    AccessSettled {
+        slot: u64,
+        pending_share: String,
+        computation_offset: u64,
+    },
+    /// A record was re-encrypted for its receiver; fields are ciphertexts only the
+    /// receiver can decrypt
+    ShareCompleted {
+        slot: u64,
+        signature: String,
+        patient_data: String,
+        receiver_enc_key: [u8; 32],
+        computation_offset: u64,
+        nonce: [u8; 16],
+        ciphertexts: Vec<[u8; 32]>,
+    },
+    /// A record was re-encrypted for its receiver and written to its shared record, which
+    /// holds the ciphertexts `output_hash` commits to
+    ShareDelivered {
+        slot: u64,
+        signature: String,
+        patient_data: String,
+        shared_record: String,
+        computation_offset: u64,
+        output_hash: [u8; 32],
+    },
+    ShareAborted {
+        slot: u64,
+        signature: String,
+        computation_offset: u64,
+    },
+    ConsentGranted {
+        slot: u64,
+        signature: String,
+        consent_grant: String,
+        patient_data: String,
+        receiver: String,
+        scope: u16,
+        expires_at: i64,
+    },
+    ConsentRevoked {
+        slot: u64,
+        signature: String,
+        consent_grant: String,
+        patient_data: String,
+        receiver: String,
+    },
+    /// A lab wrote a new encrypted result for the patient owning `patient`
+    NewResultAvailable {
+        slot: u64,
+        signature: String,
+        lab_result: String,
+        patient_data: String,
+        patient: String,
+        lab: String,
+        sequence: u64,
+    },
+}
+
+impl Notification {
+    /// Decodes a program account update, `None` for accounts nobody is notified about.
+    pub fn from_account(address: &Pubkey, data: &[u8], slot: u64) -> Option<Self> {
+        if !data.starts_with(PendingShare::DISCRIMINATOR) {
+            return None;
+        }
+        let share = PendingShare::try_deserialize(&mut &data[..]).ok()?;
+        Some(Notification::AccessRequested {
+            slot,
+            pending_share: address.to_string(),
+            payer: share.payer.to_string(),
+            patient_data: share.patient_data.to_string(),
+            computation_offset: share.computation_offset,
+            queued_at: share.queued_at,
+        })
+    }
+
+    /// Decodes an event, discriminator included, emitted by `signature`.
+    pub fn from_event(bytes: &[u8], slot: u64, signature: &str) -> Option<Self> {
+        let signature = signature.to_string();
+        let (discriminator, mut body) = (bytes.get(..8)?, bytes.get(8..)?);
+        let notification = if discriminator == ReceivedPatientDataEvent::DISCRIMINATOR {
+            let event = ReceivedPatientDataEvent::deserialize(&mut body).ok()?;
+            let mut ciphertexts = vec![
+                event.patient_id,
+                event.age,
+                event.gender,
+                event.blood_type,
+                event.weight,
+                event.height,
+            ];
+            ciphertexts.extend_from_slice(&event.allergies);
+            Notification::ShareCompleted {
+                slot,
+                signature,
+                patient_data: event.patient_record.to_string(),
+                receiver_enc_key: event.receiver_enc_key,
+                computation_offset: event.computation_offset,
+                nonce: event.nonce,
+                ciphertexts,
+            }
+        } else if discriminator == ReceivedPatientDataCompactEvent::DISCRIMINATOR {
+            let event = ReceivedPatientDataCompactEvent::deserialize(&mut body).ok()?;
+            Notification::ShareDelivered {
+                slot,
+                signature,
+                patient_data: event.patient_record.to_string(),
+                shared_record: event.shared_record.to_string(),
+                computation_offset: event.computation_offset,
+                output_hash: event.output_hash,
+            }
+        } else if discriminator == SharePatientDataAbortedEvent::DISCRIMINATOR {
+            let event = SharePatientDataAbortedEvent::deserialize(&mut body).ok()?;
+            Notification::ShareAborted {
+                slot,
+                signature,
+                computation_offset: event.computation_offset,
+            }
+        } else if discriminator == ConsentGrantedEvent::DISCRIMINATOR {
+            let event = ConsentGrantedEvent::deserialize(&mut body).ok()?;
+            Notification::ConsentGranted {
+                slot,
+                signature,
+                consent_grant: event.consent_grant.to_string(),
+                patient_data: event.patient_data.to_string(),
+                receiver: event.receiver.to_string(),
+                scope: event.scope,
+                expires_at: event.expires_at,
+            }
+        } else if discriminator == ConsentRevokedEvent::DISCRIMINATOR {
+            let event = ConsentRevokedEvent::deserialize(&mut body).ok()?;
+            Notification::ConsentRevoked {
+                slot,
+                signature,
+                consent_grant: event.consent_grant.to_string(),
+                patient_data: event.patient_data.to_string(),
+                receiver: event.receiver.to_string(),
+            }
+        } else if discriminator == NewResultAvailableEvent::DISCRIMINATOR {
+            let event = NewResultAvailableEvent::deserialize(&mut body).ok()?;
+            Notification::NewResultAvailable {
+                slot,
+                signature,
+                lab_result: event.lab_result.to_string(),
+                patient_data: event.patient_data.to_string(),
+                patient: event.patient.to_string(),
+                lab: event.lab.to_string(),
+                sequence: event.sequence,
+            }
+        } else {
+            return None;
+        };
+        Some(notification)
+    }
+}
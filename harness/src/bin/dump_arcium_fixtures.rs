@@ -0,0 +1,69 @@
+//! Dumps the Arcium accounts `share_patient_data` reads into harness fixtures.
+//!
+//! Usage: `dump-arcium-fixtures [rpc_url] [cluster_offset] [out_dir]`, defaulting to
+//! devnet, the devnet cluster and the harness `fixtures` directory.
+
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde_json::json;
+use share_medical_records::cpi_client::share_patient_data_accounts;
+use share_medical_records_harness::{DEVNET_CLUSTER_OFFSET, FIXTURES_DIR};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+
+fn main() -> Result<()> {
+    let mut args = std::env::args().skip(1);
+    let url = args
+        .next()
+        .unwrap_or_else(|| "https://api.devnet.solana.com".into());
+    let cluster_offset = match args.next() {
+        Some(offset) => offset.parse().context("cluster offset is not a u32")?,
+        None => DEVNET_CLUSTER_OFFSET,
+    };
+    let out_dir = args
+        .next()
+        .map(PathBuf::from)
+        .unwrap_or(FIXTURES_DIR.into());
+    std::fs::create_dir_all(&out_dir)?;
+
+    let rpc = RpcClient::new(url);
+    let share = share_patient_data_accounts(
+        Pubkey::default(),
+        Pubkey::default(),
+        [0; 32],
+        0,
+        cluster_offset,
+    );
+    let addresses = [
+        ("mxe", share.mxe_account),
+        ("mempool", share.mempool_account),
+        ("execpool", share.executing_pool),
+        ("comp_def_share_patient_data", share.comp_def_account),
+        ("cluster", share.cluster_account),
+        ("fee_pool", share.pool_account),
+        ("clock", share.clock_account),
+    ];
+    for (name, address) in addresses {
+        let account = rpc
+            .get_account(&address)
+            .with_context(|| format!("fetching {name} at {address}"))?;
+        let dump = json!({
+            "pubkey": address.to_string(),
+            "account": {
+                "lamports": account.lamports,
+                "data": [BASE64.encode(&account.data), "base64"],
+                "owner": account.owner.to_string(),
+                "executable": account.executable,
+                "rentEpoch": account.rent_epoch,
+                "space": account.data.len(),
+            },
+        });
+        let path = out_dir.join(format!("{name}.json"));
+        std::fs::write(&path, serde_json::to_string_pretty(&dump)?)?;
+        println!("{name}: {address} -> {}", path.display());
+    }
+    Ok(())
+}
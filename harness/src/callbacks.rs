@@ -0,0 +1,248 @@
+//! Builders for callback instructions carrying synthetic computation outputs.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::solana_program::sysvar;
+use anchor_lang::InstructionData;
+use arcium_anchor::prelude::*;
+use share_medical_records::cpi_client::{
+    cohort_query_accounts, comp_def_address, preview_share_accounts, share_patient_data_accounts,
+    shared_record_address,
+};
+use share_medical_records::{
+    CohortQueryOutput, PreviewShareOutput, SharePatientDataOutput, INBOX_SEED, PROGRAM_CONFIG_SEED,
+    REFUND_BALANCE_SEED, USED_OFFSETS_SEED,
+};
+
+fn program_address(seeds: &[&[u8]]) -> Pubkey {
+    Pubkey::find_program_address(seeds, &share_medical_records::ID).0
+}
+
+/// Successful `share_patient_data` output: the record re-encrypted for `encryption_key`.
+pub fn share_patient_data_success(
+    encryption_key: [u8; 32],
+    nonce: u128,
+    ciphertexts: [[u8; 32]; 11],
+) -> ComputationOutputs<SharePatientDataOutput> {
+    ComputationOutputs::Success(SharePatientDataOutput {
+        field_0: SharedEncryptedStruct {
+            encryption_key,
+            nonce,
+            ciphertexts,
+        },
+    })
+}
+
+/// Successful `preview_share` output: the previewed fields re-encrypted for
+/// `encryption_key`.
+pub fn preview_share_success(
+    encryption_key: [u8; 32],
+    nonce: u128,
+    ciphertexts: [[u8; 32]; 11],
+) -> ComputationOutputs<PreviewShareOutput> {
+    ComputationOutputs::Success(PreviewShareOutput {
+        field_0: SharedEncryptedStruct {
+            encryption_key,
+            nonce,
+            ciphertexts,
+        },
+    })
+}
+
+/// `preview_share_callback` delivering `output` for the preview of `patient`'s record
+/// queued at `computation_offset`.
+pub fn preview_share_callback_ix(
+    patient: Pubkey,
+    computation_offset: u64,
+    output: ComputationOutputs<PreviewShareOutput>,
+) -> Instruction {
+    // The cluster offset only names the cluster account, which the callback does not take
+    let preview = preview_share_accounts(patient, computation_offset, 0);
+    let accounts = share_medical_records::accounts::PreviewShareCallback {
+        arcium_program: ARCIUM_PROG_ID,
+        comp_def_account: preview.comp_def_account,
+        instructions_sysvar: sysvar::instructions::ID,
+        program_config: preview.program_config,
+        callback_guard: preview.callback_guard,
+        patient_data: preview.patient_data,
+        used_offsets: preview.used_offsets,
+        event_authority: program_address(&[b"__event_authority"]),
+        program: share_medical_records::ID,
+    };
+    Instruction {
+        program_id: share_medical_records::ID,
+        accounts: accounts.to_account_metas(None),
+        data: share_medical_records::instruction::PreviewShareCallback { output }.data(),
+    }
+}
+
+/// Successful `cohort_query` output: the encrypted count of matching records.
+pub fn cohort_query_success(
+    encryption_key: [u8; 32],
+    nonce: u128,
+    count: [u8; 32],
+) -> ComputationOutputs<CohortQueryOutput> {
+    ComputationOutputs::Success(CohortQueryOutput {
+        field_0: SharedEncryptedStruct {
+            encryption_key,
+            nonce,
+            ciphertexts: [count],
+        },
+    })
+}
+
+/// `cohort_query_callback` delivering `output` for the query of `researcher` over
+/// `research_escrow` queued at `computation_offset`, paying the cohort into
+/// `payout_token_accounts` in member order.
+#[allow(clippy::too_many_arguments)]
+pub fn cohort_query_callback_ix(
+    researcher: Pubkey,
+    research_escrow: Pubkey,
+    mint: Pubkey,
+    researcher_token_account: Pubkey,
+    token_program: Pubkey,
+    payout_token_accounts: &[Pubkey],
+    computation_offset: u64,
+    output: ComputationOutputs<CohortQueryOutput>,
+) -> Instruction {
+    let query = cohort_query_accounts(
+        researcher,
+        research_escrow,
+        mint,
+        researcher_token_account,
+        token_program,
+        computation_offset,
+        0,
+    );
+    let accounts = share_medical_records::accounts::CohortQueryCallback {
+        arcium_program: ARCIUM_PROG_ID,
+        comp_def_account: query.comp_def_account,
+        instructions_sysvar: sysvar::instructions::ID,
+        program_config: query.program_config,
+        callback_guard: query.callback_guard,
+        research_escrow,
+        escrow_vault: query.escrow_vault,
+        researcher_token_account,
+        treasury: query.treasury,
+        researcher,
+        mint,
+        fee_authority: query.fee_authority,
+        token_program,
+        cohort_snapshot: query.cohort_snapshot,
+        used_offsets: query.used_offsets,
+    };
+    let mut metas = accounts.to_account_metas(None);
+    metas.extend(
+        payout_token_accounts
+            .iter()
+            .map(|payout| AccountMeta::new(*payout, false)),
+    );
+    Instruction {
+        program_id: share_medical_records::ID,
+        accounts: metas,
+        data: share_medical_records::instruction::CohortQueryCallback { output }.data(),
+    }
+}
+
+/// `share_patient_data_callback` delivering `output` for the pending share of
+/// `patient_data` to `receiver`, queued at `computation_offset`, for a record without an
+/// access fee.
+pub fn share_patient_data_callback_ix(
+    payer: Pubkey,
+    patient_data: Pubkey,
+    receiver: [u8; 32],
+    computation_offset: u64,
+    output: ComputationOutputs<SharePatientDataOutput>,
+) -> Instruction {
+    share_callback_ix(
+        payer,
+        patient_data,
+        receiver,
+        computation_offset,
+        None,
+        output,
+    )
+}
+
+/// `share_patient_data_callback_ix` for a share queued while compact events are on,
+/// delivering into the receiver's shared record.
+pub fn compact_share_patient_data_callback_ix(
+    payer: Pubkey,
+    patient_data: Pubkey,
+    receiver: [u8; 32],
+    computation_offset: u64,
+    output: ComputationOutputs<SharePatientDataOutput>,
+) -> Instruction {
+    share_callback_ix(
+        payer,
+        patient_data,
+        receiver,
+        computation_offset,
+        Some(shared_record_address(&patient_data, &receiver)),
+        output,
+    )
+}
+
+/// `share_patient_data_callback_ix` for a share queued through the share circuit version
+/// at `share_comp_def` rather than the original one.
+pub fn versioned_share_patient_data_callback_ix(
+    payer: Pubkey,
+    patient_data: Pubkey,
+    receiver: [u8; 32],
+    computation_offset: u64,
+    share_comp_def: u32,
+    output: ComputationOutputs<SharePatientDataOutput>,
+) -> Instruction {
+    let mut ix =
+        share_patient_data_callback_ix(payer, patient_data, receiver, computation_offset, output);
+    let original = comp_def_address(comp_def_offset("share_patient_data"));
+    for meta in ix
+        .accounts
+        .iter_mut()
+        .filter(|meta| meta.pubkey == original)
+    {
+        meta.pubkey = comp_def_address(share_comp_def);
+    }
+    ix
+}
+
+fn share_callback_ix(
+    payer: Pubkey,
+    patient_data: Pubkey,
+    receiver: [u8; 32],
+    computation_offset: u64,
+    shared_record: Option<Pubkey>,
+    output: ComputationOutputs<SharePatientDataOutput>,
+) -> Instruction {
+    // The share's own accounts already derive the comp def, pending share, guard and receipt
+    let share = share_patient_data_accounts(payer, patient_data, receiver, computation_offset, 0);
+    let accounts = share_medical_records::accounts::SharePatientDataCallback {
+        arcium_program: ARCIUM_PROG_ID,
+        comp_def_account: share.comp_def_account,
+        instructions_sysvar: sysvar::instructions::ID,
+        program_config: program_address(&[PROGRAM_CONFIG_SEED]),
+        callback_guard: share.callback_guard,
+        pending_share: share.pending_share,
+        payer,
+        refund_balance: program_address(&[REFUND_BALANCE_SEED, payer.as_ref()]),
+        patient_data,
+        used_offsets: program_address(&[USED_OFFSETS_SEED, payer.as_ref()]),
+        share_receipt: share.share_receipt,
+        shared_record,
+        inbox: program_address(&[INBOX_SEED, patient_data.as_ref()]),
+        fee_escrow: None,
+        patient_fee_account: None,
+        treasury: None,
+        payer_fee_account: None,
+        fee_mint: None,
+        fee_authority: None,
+        token_program: None,
+        event_authority: program_address(&[b"__event_authority"]),
+        program: share_medical_records::ID,
+    };
+    Instruction {
+        program_id: share_medical_records::ID,
+        accounts: accounts.to_account_metas(None),
+        data: share_medical_records::instruction::SharePatientDataCallback { output }.data(),
+    }
+}
@@ -0,0 +1,415 @@
+//! Local test harness for share_medical_records.
+//!
+//! Runs the program's BPF build in `solana-program-test` next to a native mock of the
+//! Arcium program (see `mock_arcium`), so tests exercise queueing and callbacks without a
+//! live MPC cluster:
+//!
+//! 1. `anchor build`, then dump the Arcium accounts the program reads once with
+//!    `cargo run -p share_medical_records_harness --bin dump-arcium-fixtures`.
+//! 2. Start a [`Harness`] with those fixtures and send instructions through it.
+//! 3. Check what was queued with [`Harness::queued`] and deliver a synthetic
+//!    `ComputationOutputs` with [`Harness::inject_callback`], e.g. built by
+//!    [`share_patient_data_callback_ix`].
+//!
+//! The mock charges no Arcium fees and runs no circuits: outputs are whatever the test
+//! injects.
+
+mod callbacks;
+mod mock_arcium;
+
+pub use callbacks::*;
+pub use mock_arcium::{ArciumInvocation, INVOKE_CALLBACK_TAG};
+
+use std::collections::HashSet;
+use std::path::Path;
+use std::str::FromStr;
+
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::system_instruction;
+use anchor_lang::{AccountDeserialize, AccountSerialize, Space};
+use anchor_spl::token::spl_token;
+use anchor_spl::token::spl_token::solana_program::program_option::COption;
+use anchor_spl::token::spl_token::solana_program::program_pack::Pack;
+use anyhow::{anyhow, Context, Result};
+use arcium_anchor::prelude::{comp_def_offset, derive_comp_pda, ARCIUM_PROG_ID};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde_json::Value;
+use share_medical_records::cpi_client::comp_def_address;
+use share_medical_records::{
+    CircuitManifestEntry, ProgramConfig, DEFAULT_CLOCK_SKEW_TOLERANCE, DEFAULT_COMPUTATION_TIMEOUT,
+    DEFAULT_MAX_IN_FLIGHT_PER_PAYER, PATIENT_FIELDS_ALL, PROGRAM_CONFIG_SEED,
+};
+use solana_program_test::{
+    processor, BanksClient, BanksTransactionResultWithMetadata, ProgramTest,
+};
+use solana_sdk::account::Account;
+use solana_sdk::clock::Clock;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::rent::Rent;
+use solana_sdk::signature::{Keypair, Signature, Signer};
+use solana_sdk::transaction::Transaction;
+
+/// Directory `anchor build` writes the program binary to.
+const DEPLOY_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../target/deploy");
+
+/// Directory `dump-arcium-fixtures` writes to by default.
+pub const FIXTURES_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures");
+
+/// Cluster `dump-arcium-fixtures` dumps by default, the devnet MXE's.
+pub const DEVNET_CLUSTER_OFFSET: u32 = 1078779259;
+
+/// Circuits the program initializes, all listed in the preloaded config's manifest.
+pub const CIRCUITS: [&str; 28] = [
+    "share_patient_data",
+    "cohort_query",
+    "regulatory_disclosure",
+    "share_content_key",
+    "share_dicom_reference",
+    "share_compressed_record",
+    "verify_age_over",
+    "share_committed_patient_data",
+    "preview_share",
+    "share_bundle",
+    "validate_prior_auth",
+    "issue_prescription",
+    "verify_prescription",
+    "clearance_check",
+    "emergency_packet",
+    "init_adverse_event_tally",
+    "tally_adverse_event",
+    "adverse_event_counts",
+    "open_case_counts",
+    "notify_case",
+    "case_counts",
+    "reveal_case",
+    "caregiver_update",
+    "find_compatible_donors",
+    "waitlist_priority",
+    "redeem_share_code",
+    "disclose_escalated_fields",
+    "disaster_packet",
+];
+
+pub struct HarnessBuilder {
+    test: ProgramTest,
+}
+
+impl HarnessBuilder {
+    /// Preloads every account dump in `dir`, in the `solana account --output json` format.
+    pub fn arcium_fixtures(mut self, dir: impl AsRef<Path>) -> Result<Self> {
+        let dir = dir.as_ref();
+        let entries =
+            std::fs::read_dir(dir).with_context(|| format!("reading {}", dir.display()))?;
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "json") {
+                let dump: Value = serde_json::from_str(&std::fs::read_to_string(&path)?)?;
+                let (address, account) = parse_account_dump(&dump)
+                    .with_context(|| format!("parsing {}", path.display()))?;
+                self.test.add_account(address, account);
+            }
+        }
+        Ok(self)
+    }
+
+    /// Preloads the `share_patient_data` comp def dumped in `dir` again at each of
+    /// `comp_def_offsets`, standing in for new share circuit versions.
+    pub fn comp_def_copies(
+        mut self,
+        dir: impl AsRef<Path>,
+        comp_def_offsets: &[u32],
+    ) -> Result<Self> {
+        let path = dir.as_ref().join("comp_def_share_patient_data.json");
+        let dump: Value = serde_json::from_str(
+            &std::fs::read_to_string(&path)
+                .with_context(|| format!("reading {}", path.display()))?,
+        )?;
+        let (_, account) =
+            parse_account_dump(&dump).with_context(|| format!("parsing {}", path.display()))?;
+        for &comp_def_offset in comp_def_offsets {
+            self.test
+                .add_account(comp_def_address(comp_def_offset), account.clone());
+        }
+        Ok(self)
+    }
+
+    /// Preloads a single account, e.g. a token mint or a pre-existing record.
+    pub fn account(mut self, address: Pubkey, account: Account) -> Self {
+        self.test.add_account(address, account);
+        self
+    }
+
+    /// Preloads `value` as an account of the program, rent-exempt at its full space, e.g.
+    /// an account a computation would otherwise have to deliver first.
+    pub fn program_account<T: AccountSerialize + Space>(self, address: Pubkey, value: &T) -> Self {
+        let mut data = Vec::with_capacity(8 + T::INIT_SPACE);
+        value
+            .try_serialize(&mut data)
+            .expect("program account serializes");
+        data.resize(8 + T::INIT_SPACE, 0);
+        self.account(
+            address,
+            Account {
+                lamports: Rent::default().minimum_balance(data.len()),
+                data,
+                owner: share_medical_records::ID,
+                executable: false,
+                rent_epoch: 0,
+            },
+        )
+    }
+
+    /// Preloads an initialized SPL token mint with `decimals` and no mint authority.
+    pub fn token_mint(self, address: Pubkey, decimals: u8) -> Self {
+        let mint = spl_token::state::Mint {
+            mint_authority: COption::None,
+            supply: 0,
+            decimals,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        };
+        let mut data = vec![0; spl_token::state::Mint::LEN];
+        spl_token::state::Mint::pack(mint, &mut data).expect("mint packs");
+        self.token_program_account(address, data)
+    }
+
+    /// Preloads an SPL token account of `mint`, owned by `owner` and holding `amount`.
+    pub fn token_account(self, address: Pubkey, mint: Pubkey, owner: Pubkey, amount: u64) -> Self {
+        let account = spl_token::state::Account {
+            mint,
+            owner,
+            amount,
+            state: spl_token::state::AccountState::Initialized,
+            ..Default::default()
+        };
+        let mut data = vec![0; spl_token::state::Account::LEN];
+        spl_token::state::Account::pack(account, &mut data).expect("token account packs");
+        self.token_program_account(address, data)
+    }
+
+    fn token_program_account(self, address: Pubkey, data: Vec<u8>) -> Self {
+        self.account(
+            address,
+            Account {
+                lamports: Rent::default().minimum_balance(data.len()),
+                data,
+                owner: spl_token::ID,
+                executable: false,
+                rent_epoch: 0,
+            },
+        )
+    }
+
+    /// Preloads the program config as `initialize_config` leaves it, with `admin` as admin
+    /// and every circuit in [`CIRCUITS`] initialized.
+    ///
+    /// `initialize_config` itself needs the upgradeable loader's program data account,
+    /// which `solana-program-test` does not create for BPF programs it loads.
+    pub fn program_config(mut self, admin: Pubkey) -> Self {
+        let (address, bump) =
+            Pubkey::find_program_address(&[PROGRAM_CONFIG_SEED], &share_medical_records::ID);
+        let config = ProgramConfig {
+            admin,
+            pending_admin: None,
+            paused: false,
+            paused_store: false,
+            paused_share: false,
+            paused_compute: false,
+            circuit_version: 1,
+            active_share_comp_def: comp_def_offset("share_patient_data"),
+            deprecated_share_comp_def: None,
+            deprecation_ends_at: 0,
+            computation_timeout: DEFAULT_COMPUTATION_TIMEOUT,
+            approved_clusters: Vec::new(),
+            registrars: Vec::new(),
+            admission_oracles: Vec::new(),
+            regulators: Vec::new(),
+            disclosure_committee: Vec::new(),
+            disclosure_threshold: 0,
+            disclosure_interval: 0,
+            retention_policies: Vec::new(),
+            event_cpi: false,
+            lookup_table: Pubkey::default(),
+            treasury_bps: 0,
+            subscription_mint: Pubkey::default(),
+            subscription_tiers: Vec::new(),
+            circuit_manifest: CIRCUITS
+                .iter()
+                .map(|name| CircuitManifestEntry {
+                    comp_def_offset: comp_def_offset(name),
+                    source_url: String::new(),
+                    source_hash: [0; 32],
+                    initialized: true,
+                    pending_computations: 0,
+                    retired: false,
+                })
+                .collect(),
+            clock_skew_tolerance: DEFAULT_CLOCK_SKEW_TOLERANCE,
+            max_in_flight_per_payer: DEFAULT_MAX_IN_FLIGHT_PER_PAYER,
+            record_fields: PATIENT_FIELDS_ALL,
+            compact_events: false,
+            auditors: Vec::new(),
+            disaster_committee: Vec::new(),
+            disaster_threshold: 0,
+            bump,
+        };
+        let mut data = Vec::with_capacity(8 + ProgramConfig::INIT_SPACE);
+        config
+            .try_serialize(&mut data)
+            .expect("program config serializes");
+        data.resize(8 + ProgramConfig::INIT_SPACE, 0);
+        self.test.add_account(
+            address,
+            Account {
+                lamports: Rent::default().minimum_balance(data.len()),
+                data,
+                owner: share_medical_records::ID,
+                executable: false,
+                rent_epoch: 0,
+            },
+        );
+        self
+    }
+
+    pub async fn start(self) -> Harness {
+        let (banks, payer, _) = self.test.start().await;
+        Harness {
+            banks,
+            payer,
+            sent: HashSet::new(),
+        }
+    }
+}
+
+pub struct Harness {
+    pub banks: BanksClient,
+    /// Funded keypair paying for every transaction the harness sends
+    pub payer: Keypair,
+    /// Signatures of every transaction sent, so a repeat waits for a fresh blockhash
+    /// rather than failing as already processed
+    sent: HashSet<Signature>,
+}
+
+impl Harness {
+    pub fn builder() -> HarnessBuilder {
+        if std::env::var_os("BPF_OUT_DIR").is_none() {
+            std::env::set_var("BPF_OUT_DIR", DEPLOY_DIR);
+        }
+        let mut test = ProgramTest::new("share_medical_records", share_medical_records::ID, None);
+        test.prefer_bpf(true);
+        test.add_program(
+            "arcium",
+            ARCIUM_PROG_ID,
+            processor!(mock_arcium::process_instruction),
+        );
+        HarnessBuilder { test }
+    }
+
+    /// Sends `ixs` in one transaction, returning its result and logs rather than failing
+    /// on a program error.
+    pub async fn process(
+        &mut self,
+        ixs: &[Instruction],
+        signers: &[&Keypair],
+    ) -> Result<BanksTransactionResultWithMetadata> {
+        let mut blockhash = self.banks.get_latest_blockhash().await?;
+        let mut all_signers = vec![&self.payer];
+        all_signers.extend_from_slice(signers);
+        loop {
+            let tx = Transaction::new_signed_with_payer(
+                ixs,
+                Some(&self.payer.pubkey()),
+                &all_signers,
+                blockhash,
+            );
+            if self.sent.insert(tx.signatures[0]) {
+                return Ok(self.banks.process_transaction_with_metadata(tx).await?);
+            }
+            blockhash = self.banks.get_new_latest_blockhash(&blockhash).await?;
+        }
+    }
+
+    /// Instructions sent to Arcium that involved `account`, oldest first. Pass a
+    /// computation account to find what queued it.
+    pub fn queued(&self, account: &Pubkey) -> Vec<ArciumInvocation> {
+        mock_arcium::INVOCATIONS
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|invocation| invocation.accounts.contains(account))
+            .cloned()
+            .collect()
+    }
+
+    /// Delivers `callback` for the computation queued at `computation_offset` to the
+    /// program through the mock Arcium program, as the MPC cluster would once the
+    /// computation finalizes.
+    pub async fn inject_callback(
+        &mut self,
+        computation_offset: u64,
+        callback: Instruction,
+    ) -> Result<BanksTransactionResultWithMetadata> {
+        let mut accounts = vec![
+            AccountMeta::new_readonly(derive_comp_pda!(computation_offset), false),
+            AccountMeta::new_readonly(callback.program_id, false),
+        ];
+        accounts.extend(callback.accounts);
+        let data = [INVOKE_CALLBACK_TAG.as_slice(), &callback.data].concat();
+        let ix = Instruction {
+            program_id: ARCIUM_PROG_ID,
+            accounts,
+            data,
+        };
+        self.process(&[ix], &[]).await
+    }
+
+    pub async fn account(&mut self, address: Pubkey) -> Result<Option<Account>> {
+        Ok(self.banks.get_account(address).await?)
+    }
+
+    /// Reads and deserializes a program account, `None` if it does not exist.
+    pub async fn program_account<T: AccountDeserialize>(
+        &mut self,
+        address: Pubkey,
+    ) -> Result<Option<T>> {
+        match self.account(address).await? {
+            Some(account) => Ok(Some(T::try_deserialize(&mut &account.data[..])?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Instruction moving `lamports` from the harness payer to `to`, e.g. to let a fresh
+    /// wallet pay rent in the same transaction.
+    pub fn fund_ix(&self, to: &Pubkey, lamports: u64) -> Instruction {
+        system_instruction::transfer(&self.payer.pubkey(), to, lamports)
+    }
+
+    /// Current unix timestamp of the bank.
+    pub async fn now(&mut self) -> Result<i64> {
+        Ok(self.banks.get_sysvar::<Clock>().await?.unix_timestamp)
+    }
+}
+
+fn parse_account_dump(dump: &Value) -> Result<(Pubkey, Account)> {
+    let field = |value: &Value, key: &str| -> Result<Value> {
+        value
+            .get(key)
+            .cloned()
+            .ok_or_else(|| anyhow!("missing `{key}`"))
+    };
+    let address = Pubkey::from_str(field(dump, "pubkey")?.as_str().unwrap_or_default())?;
+    let account = field(dump, "account")?;
+    let data = field(&account, "data")?;
+    let data = BASE64.decode(data[0].as_str().unwrap_or_default())?;
+    Ok((
+        address,
+        Account {
+            lamports: field(&account, "lamports")?.as_u64().unwrap_or_default(),
+            data,
+            owner: Pubkey::from_str(field(&account, "owner")?.as_str().unwrap_or_default())?,
+            executable: field(&account, "executable")?.as_bool().unwrap_or_default(),
+            rent_epoch: 0,
+        },
+    ))
+}
@@ -0,0 +1,75 @@
+//! Native stand-in for the Arcium program, registered at `ARCIUM_PROG_ID`.
+//!
+//! Every instruction the program sends to Arcium (`queue_computation`,
+//! `init_computation_definition`, ...) succeeds without side effects and is recorded, so
+//! tests can see what was queued. Instructions tagged with [`INVOKE_CALLBACK_TAG`] are
+//! forwarded to the program instead, which makes callbacks arrive through Arcium the way
+//! the MPC cluster delivers them, with the computation account passed to the top-level
+//! instruction as Arcium does.
+
+use std::sync::Mutex;
+
+use anchor_lang::solana_program::account_info::AccountInfo;
+use anchor_lang::solana_program::entrypoint::ProgramResult;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::program::invoke;
+use anchor_lang::solana_program::program_error::ProgramError;
+use anchor_lang::solana_program::pubkey::Pubkey;
+
+/// Prefix marking an instruction the mock forwards to the program named by its second
+/// account. The first is the computation being called back and is not forwarded.
+pub const INVOKE_CALLBACK_TAG: &[u8; 8] = b"mockcbix";
+
+/// An instruction the program sent to Arcium.
+#[derive(Clone, Debug)]
+pub struct ArciumInvocation {
+    /// Accounts passed, in order
+    pub accounts: Vec<Pubkey>,
+    /// Instruction data, Anchor discriminator included
+    pub data: Vec<u8>,
+}
+
+impl ArciumInvocation {
+    /// Whether this is the Anchor instruction `name` of the Arcium program.
+    pub fn is(&self, name: &str) -> bool {
+        let preimage = format!("global:{name}");
+        let hash = anchor_lang::solana_program::hash::hash(preimage.as_bytes());
+        self.data.starts_with(&hash.to_bytes()[..8])
+    }
+}
+
+// Native processors run in the test process, so invocations are recorded in process
+// memory. Tests running in parallel tell theirs apart by the accounts involved.
+pub(crate) static INVOCATIONS: Mutex<Vec<ArciumInvocation>> = Mutex::new(Vec::new());
+
+pub fn process_instruction(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    if let Some(callback) = data.strip_prefix(INVOKE_CALLBACK_TAG.as_slice()) {
+        let (program, callback_accounts) = accounts
+            .get(1..)
+            .and_then(<[AccountInfo]>::split_first)
+            .ok_or(ProgramError::NotEnoughAccountKeys)?;
+        let ix = Instruction {
+            program_id: *program.key,
+            accounts: callback_accounts
+                .iter()
+                .map(|account| AccountMeta {
+                    pubkey: *account.key,
+                    is_signer: account.is_signer,
+                    is_writable: account.is_writable,
+                })
+                .collect(),
+            data: callback.to_vec(),
+        };
+        return invoke(&ix, accounts);
+    }
+
+    INVOCATIONS.lock().unwrap().push(ArciumInvocation {
+        accounts: accounts.iter().map(|account| *account.key).collect(),
+        data: data.to_vec(),
+    });
+    Ok(())
+}
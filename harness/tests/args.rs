@@ -0,0 +1,385 @@
+//! Layout of the arguments each circuit is queued with.
+//!
+//! The circuits read their inputs positionally, so these pin the order of the encryption
+//! headers, plaintexts and ciphertexts, and the account slices the cluster reads.
+
+use arcium_anchor::prelude::Argument;
+use share_medical_records::{
+    adverse_event_counts_args, caregiver_update_args, case_counts_args, clearance_check_args,
+    cohort_member_args, cohort_query_args, disaster_packet_args, disclose_escalated_fields_args,
+    donor_args, emergency_packet_args, find_compatible_donors_args, issue_prescription_args,
+    notify_case_args, preview_share_args, redeem_share_code_args, regulatory_disclosure_args,
+    reveal_case_args, share_bundle_args, share_committed_patient_data_args,
+    share_compressed_record_args, share_content_key_args, share_content_key_value_args,
+    share_dicom_reference_args, share_patient_data_args, tally_adverse_event_args,
+    validate_prior_auth_args, verify_age_over_args, verify_prescription_args,
+    waitlist_candidate_args, waitlist_priority_args, BundleSource, EncryptedContentKey,
+    PatientField, ADVERSE_EVENT_CIPHERTEXTS_LEN, CASE_COUNTS_CIPHERTEXTS_LEN, CIPHERTEXTS_OFFSET,
+    COMPRESSED_RECORD_VALUES, CONTENT_KEY_CIPHERTEXTS_LEN, DICOM_CIPHERTEXTS_LEN,
+    DONOR_CIPHERTEXTS_LEN, EMERGENCY_PACKET_BLOCKS, PATIENT_DATA_CIPHERTEXTS_LEN,
+    PRESCRIPTION_CIPHERTEXTS_LEN, PRIOR_AUTH_CIPHERTEXTS_LEN, SEALED_CASE_CIPHERTEXTS_LEN,
+    SURVEILLANCE_TALLY_CIPHERTEXTS_LEN, WAITLIST_CIPHERTEXTS_LEN,
+};
+use solana_sdk::pubkey::Pubkey;
+
+const RECEIVER: [u8; 32] = [1; 32];
+const RECEIVER_NONCE: u128 = 2;
+const SENDER: [u8; 32] = [3; 32];
+const NONCE: u128 = 4;
+
+/// Checks `args` starts with the `Enc<Shared, _>` header of `pub_key` and `nonce`.
+fn assert_shared(args: &[Argument], pub_key: [u8; 32], nonce: u128) {
+    assert!(matches!(args[0], Argument::ArcisPubkey(key) if key == pub_key));
+    assert!(matches!(args[1], Argument::PlaintextU128(n) if n == nonce));
+}
+
+/// Checks `arg` reads the `len` ciphertext bytes at the start of `account`.
+fn assert_ciphertexts(arg: &Argument, account: Pubkey, len: u32) {
+    assert!(matches!(
+        arg,
+        Argument::Account(key, offset, length)
+            if *key == account && *offset == CIPHERTEXTS_OFFSET && *length == len
+    ));
+}
+
+/// Checks a share of `account` to `RECEIVER`, encrypted under `SENDER` and `NONCE`.
+fn assert_account_share(args: &[Argument], account: Pubkey, len: u32) {
+    assert_eq!(args.len(), 5);
+    assert_shared(args, RECEIVER, RECEIVER_NONCE);
+    assert_shared(&args[2..], SENDER, NONCE);
+    assert_ciphertexts(&args[4], account, len);
+}
+
+#[test]
+fn share_patient_data_reads_the_record_ciphertexts() {
+    let record = Pubkey::new_unique();
+    let args = share_patient_data_args(RECEIVER, RECEIVER_NONCE, SENDER, NONCE, record);
+    assert_account_share(&args, record, PATIENT_DATA_CIPHERTEXTS_LEN);
+}
+
+#[test]
+fn regulatory_disclosure_is_laid_out_like_a_share() {
+    let record = Pubkey::new_unique();
+    let args = regulatory_disclosure_args(RECEIVER, RECEIVER_NONCE, SENDER, NONCE, record);
+    assert_account_share(&args, record, PATIENT_DATA_CIPHERTEXTS_LEN);
+}
+
+#[test]
+fn emergency_packet_is_laid_out_like_a_share() {
+    let record = Pubkey::new_unique();
+    let args = emergency_packet_args(RECEIVER, RECEIVER_NONCE, SENDER, NONCE, record);
+    assert_account_share(&args, record, PATIENT_DATA_CIPHERTEXTS_LEN);
+}
+
+#[test]
+fn preview_share_passes_the_field_mask_after_a_share() {
+    let record = Pubkey::new_unique();
+    let fields = PatientField::Age.bit() | PatientField::Allergies.bit();
+    let args = preview_share_args(RECEIVER, RECEIVER_NONCE, SENDER, NONCE, record, fields);
+    assert_account_share(&args[..5], record, PATIENT_DATA_CIPHERTEXTS_LEN);
+    assert_eq!(args.len(), 6);
+    assert!(matches!(args[5], Argument::PlaintextU16(mask) if mask == fields));
+}
+
+#[test]
+fn caregiver_update_is_laid_out_like_a_preview() {
+    let record = Pubkey::new_unique();
+    let fields = CAREGIVER_FIELDS;
+    let args = caregiver_update_args(RECEIVER, RECEIVER_NONCE, SENDER, NONCE, record, fields);
+    assert_account_share(&args[..5], record, PATIENT_DATA_CIPHERTEXTS_LEN);
+    assert_eq!(args.len(), 6);
+    assert!(matches!(args[5], Argument::PlaintextU16(mask) if mask == fields));
+}
+
+#[test]
+fn redeem_share_code_is_laid_out_like_a_preview() {
+    let record = Pubkey::new_unique();
+    let fields = PatientField::Age.bit() | PatientField::Allergies.bit();
+    let args = redeem_share_code_args(RECEIVER, RECEIVER_NONCE, SENDER, NONCE, record, fields);
+    assert_account_share(&args[..5], record, PATIENT_DATA_CIPHERTEXTS_LEN);
+    assert_eq!(args.len(), 6);
+    assert!(matches!(args[5], Argument::PlaintextU16(mask) if mask == fields));
+}
+
+#[test]
+fn disaster_packet_passes_the_packet_by_value_under_the_ems_key() {
+    let packet: [[u8; 32]; EMERGENCY_PACKET_BLOCKS] = std::array::from_fn(|i| [i as u8; 32]);
+    let args = disaster_packet_args(RECEIVER, RECEIVER_NONCE, SENDER, NONCE, &packet);
+    assert_eq!(args.len(), 4 + EMERGENCY_PACKET_BLOCKS);
+    assert_shared(&args, RECEIVER, RECEIVER_NONCE);
+    assert_shared(&args[2..], SENDER, NONCE);
+    assert!(matches!(args[4], Argument::EncryptedU8(age) if age == packet[0]));
+    assert!(matches!(args[5], Argument::EncryptedU8(blood_type) if blood_type == packet[1]));
+    for (arg, allergy) in args[6..].iter().zip(&packet[2..]) {
+        assert!(matches!(arg, Argument::EncryptedBool(value) if value == allergy));
+    }
+}
+
+#[test]
+fn disclose_escalated_fields_is_laid_out_like_a_preview() {
+    let record = Pubkey::new_unique();
+    let fields = PatientField::Weight.bit() | PatientField::Height.bit();
+    let args =
+        disclose_escalated_fields_args(RECEIVER, RECEIVER_NONCE, SENDER, NONCE, record, fields);
+    assert_account_share(&args[..5], record, PATIENT_DATA_CIPHERTEXTS_LEN);
+    assert_eq!(args.len(), 6);
+    assert!(matches!(args[5], Argument::PlaintextU16(mask) if mask == fields));
+}
+
+#[test]
+fn verify_age_over_passes_the_threshold_after_the_record() {
+    let record = Pubkey::new_unique();
+    let args = verify_age_over_args(SENDER, NONCE, record, 18);
+    assert_eq!(args.len(), 4);
+    assert_shared(&args, SENDER, NONCE);
+    assert_ciphertexts(&args[2], record, PATIENT_DATA_CIPHERTEXTS_LEN);
+    assert!(matches!(args[3], Argument::PlaintextU8(18)));
+}
+
+#[test]
+fn clearance_check_passes_the_template_requirements_after_the_record() {
+    let record = Pubkey::new_unique();
+    let args = clearance_check_args(SENDER, NONCE, record, 18, 0b00101);
+    assert_eq!(args.len(), 5);
+    assert_shared(&args, SENDER, NONCE);
+    assert_ciphertexts(&args[2], record, PATIENT_DATA_CIPHERTEXTS_LEN);
+    assert!(matches!(args[3], Argument::PlaintextU8(18)));
+    assert!(matches!(args[4], Argument::PlaintextU8(0b00101)));
+}
+
+#[test]
+fn validate_prior_auth_reads_the_request_codes_then_the_policy() {
+    let request = Pubkey::new_unique();
+    let policy: [[u8; 32]; 8] = std::array::from_fn(|i| [i as u8 + 10; 32]);
+    let args = validate_prior_auth_args(SENDER, NONCE, request, RECEIVER, RECEIVER_NONCE, &policy);
+    assert_eq!(args.len(), 5 + policy.len());
+    assert_shared(&args, SENDER, NONCE);
+    assert_ciphertexts(&args[2], request, PRIOR_AUTH_CIPHERTEXTS_LEN);
+    assert_shared(&args[3..], RECEIVER, RECEIVER_NONCE);
+    for (arg, value) in args[5..].iter().zip(policy) {
+        assert!(matches!(arg, Argument::EncryptedU64(ciphertext) if *ciphertext == value));
+    }
+}
+
+#[test]
+fn issue_prescription_encrypts_the_prescribed_refills_to_the_cluster() {
+    let args = issue_prescription_args(9, SENDER, NONCE, [5; 32]);
+    assert_eq!(args.len(), 4);
+    assert!(matches!(args[0], Argument::PlaintextU128(9)));
+    assert_shared(&args[1..], SENDER, NONCE);
+    assert!(matches!(args[3], Argument::EncryptedU8(refills) if refills == [5; 32]));
+}
+
+#[test]
+fn verify_prescription_reads_the_cluster_owned_refills() {
+    let prescription = Pubkey::new_unique();
+    let args = verify_prescription_args(NONCE, prescription);
+    assert_eq!(args.len(), 2);
+    assert!(matches!(args[0], Argument::PlaintextU128(nonce) if nonce == NONCE));
+    assert_ciphertexts(&args[1], prescription, PRESCRIPTION_CIPHERTEXTS_LEN);
+}
+
+#[test]
+fn tally_adverse_event_reads_the_report_then_the_counts() {
+    let [report, tally] = [(); 2].map(|_| Pubkey::new_unique());
+    let drug_codes = [11, 12, 13, 0, 0, 0, 0, 0];
+    let args = tally_adverse_event_args(SENDER, NONCE, report, 9, tally, &drug_codes);
+    assert_eq!(args.len(), 13);
+    assert_shared(&args, SENDER, NONCE);
+    assert_ciphertexts(&args[2], report, ADVERSE_EVENT_CIPHERTEXTS_LEN);
+    assert!(matches!(args[3], Argument::PlaintextU128(9)));
+    assert_ciphertexts(&args[4], tally, SURVEILLANCE_TALLY_CIPHERTEXTS_LEN);
+    for (arg, code) in args[5..].iter().zip(drug_codes) {
+        assert!(matches!(arg, Argument::PlaintextU64(c) if *c == code));
+    }
+}
+
+#[test]
+fn adverse_event_counts_passes_the_threshold_after_the_counts() {
+    let tally = Pubkey::new_unique();
+    let args = adverse_event_counts_args(RECEIVER, RECEIVER_NONCE, 9, tally, 5);
+    assert_eq!(args.len(), 5);
+    assert_shared(&args, RECEIVER, RECEIVER_NONCE);
+    assert!(matches!(args[2], Argument::PlaintextU128(9)));
+    assert_ciphertexts(&args[3], tally, SURVEILLANCE_TALLY_CIPHERTEXTS_LEN);
+    assert!(matches!(args[4], Argument::PlaintextU16(5)));
+}
+
+#[test]
+fn notify_case_passes_the_case_by_value_then_reads_the_counts() {
+    let authority = Pubkey::new_unique();
+    let disease_codes = [21, 22, 0, 0, 0, 0, 0, 0];
+    let args = notify_case_args(
+        9,
+        SENDER,
+        NONCE,
+        [5; 32],
+        [6; 32],
+        10,
+        authority,
+        &disease_codes,
+    );
+    assert_eq!(args.len(), 15);
+    assert!(matches!(args[0], Argument::PlaintextU128(9)));
+    assert_shared(&args[1..], SENDER, NONCE);
+    assert!(matches!(args[3], Argument::EncryptedU64(c) if c == [5; 32]));
+    assert!(matches!(args[4], Argument::EncryptedU64(c) if c == [6; 32]));
+    assert!(matches!(args[5], Argument::PlaintextU128(10)));
+    assert_ciphertexts(&args[6], authority, CASE_COUNTS_CIPHERTEXTS_LEN);
+    for (arg, code) in args[7..].iter().zip(disease_codes) {
+        assert!(matches!(arg, Argument::PlaintextU64(c) if *c == code));
+    }
+}
+
+#[test]
+fn case_counts_and_reveal_read_cluster_owned_state() {
+    let [authority, case_report] = [(); 2].map(|_| Pubkey::new_unique());
+    let args = case_counts_args(RECEIVER, RECEIVER_NONCE, 9, authority);
+    assert_eq!(args.len(), 4);
+    assert_shared(&args, RECEIVER, RECEIVER_NONCE);
+    assert!(matches!(args[2], Argument::PlaintextU128(9)));
+    assert_ciphertexts(&args[3], authority, CASE_COUNTS_CIPHERTEXTS_LEN);
+
+    let args = reveal_case_args(RECEIVER, RECEIVER_NONCE, 10, case_report);
+    assert_eq!(args.len(), 4);
+    assert_shared(&args, RECEIVER, RECEIVER_NONCE);
+    assert!(matches!(args[2], Argument::PlaintextU128(10)));
+    assert_ciphertexts(&args[3], case_report, SEALED_CASE_CIPHERTEXTS_LEN);
+}
+
+#[test]
+fn share_bundle_reads_the_record_then_both_content_keys() {
+    let [record, labs, prescriptions] = [(); 3].map(|_| Pubkey::new_unique());
+    let source = |seed: u8| BundleSource {
+        pub_key: [seed; 32],
+        nonce: u128::from(seed),
+    };
+    let args = share_bundle_args(
+        RECEIVER,
+        RECEIVER_NONCE,
+        (source(3), record),
+        (source(5), labs),
+        (source(7), prescriptions),
+    );
+    assert_eq!(args.len(), 11);
+    assert_shared(&args, RECEIVER, RECEIVER_NONCE);
+    assert_shared(&args[2..], [3; 32], 3);
+    assert_ciphertexts(&args[4], record, PATIENT_DATA_CIPHERTEXTS_LEN);
+    assert_shared(&args[5..], [5; 32], 5);
+    assert_ciphertexts(&args[7], labs, CONTENT_KEY_CIPHERTEXTS_LEN);
+    assert_shared(&args[8..], [7; 32], 7);
+    assert_ciphertexts(&args[10], prescriptions, CONTENT_KEY_CIPHERTEXTS_LEN);
+}
+
+#[test]
+fn share_content_key_reads_the_key_from_either_source() {
+    let anchor = Pubkey::new_unique();
+    let args = share_content_key_args(RECEIVER, RECEIVER_NONCE, SENDER, NONCE, anchor);
+    assert_account_share(&args, anchor, CONTENT_KEY_CIPHERTEXTS_LEN);
+
+    let content_key = EncryptedContentKey::new([[5; 32], [6; 32]]);
+    let args = share_content_key_value_args(RECEIVER, RECEIVER_NONCE, SENDER, NONCE, &content_key);
+    assert_eq!(args.len(), 6);
+    assert_shared(&args, RECEIVER, RECEIVER_NONCE);
+    assert_shared(&args[2..], SENDER, NONCE);
+    assert!(matches!(args[4], Argument::EncryptedU128(limb) if limb == content_key.blocks[0]));
+    assert!(matches!(args[5], Argument::EncryptedU128(limb) if limb == content_key.blocks[1]));
+}
+
+#[test]
+fn share_dicom_reference_reads_the_reference_ciphertexts() {
+    let reference = Pubkey::new_unique();
+    let args = share_dicom_reference_args(RECEIVER, RECEIVER_NONCE, SENDER, NONCE, reference);
+    assert_account_share(&args, reference, DICOM_CIPHERTEXTS_LEN);
+}
+
+#[test]
+fn share_compressed_record_passes_the_ciphertexts_by_value() {
+    let ciphertexts = [[7; 32]; COMPRESSED_RECORD_VALUES];
+    let args = share_compressed_record_args(RECEIVER, RECEIVER_NONCE, SENDER, NONCE, &ciphertexts);
+    assert_eq!(args.len(), 4 + COMPRESSED_RECORD_VALUES);
+    assert_shared(&args, RECEIVER, RECEIVER_NONCE);
+    assert_shared(&args[2..], SENDER, NONCE);
+    assert!(args[4..]
+        .iter()
+        .all(|arg| matches!(arg, Argument::EncryptedU128(c) if *c == [7; 32])));
+}
+
+#[test]
+fn share_committed_patient_data_passes_each_field_as_its_type() {
+    let fields = std::array::from_fn(|i| [i as u8; 32]);
+    let allergies = [[9; 32]; 5];
+    let args = share_committed_patient_data_args(
+        RECEIVER,
+        RECEIVER_NONCE,
+        SENDER,
+        NONCE,
+        &fields,
+        &allergies,
+    );
+    assert_eq!(args.len(), 4 + 11);
+    assert_shared(&args, RECEIVER, RECEIVER_NONCE);
+    assert_shared(&args[2..], SENDER, NONCE);
+    assert!(matches!(args[4], Argument::EncryptedU64(c) if c == fields[0]));
+    assert!(matches!(args[5], Argument::EncryptedU8(c) if c == fields[1]));
+    assert!(matches!(args[6], Argument::EncryptedBool(c) if c == fields[2]));
+    assert!(matches!(args[7], Argument::EncryptedU8(c) if c == fields[3]));
+    assert!(matches!(args[8], Argument::EncryptedU16(c) if c == fields[4]));
+    assert!(matches!(args[9], Argument::EncryptedU16(c) if c == fields[5]));
+    assert!(args[10..]
+        .iter()
+        .all(|arg| matches!(arg, Argument::EncryptedBool(c) if *c == [9; 32])));
+}
+
+#[test]
+fn cohort_query_appends_members_after_the_filter() {
+    let records = [Pubkey::new_unique(), Pubkey::new_unique()];
+    let members = records
+        .iter()
+        .flat_map(|record| cohort_member_args(SENDER, NONCE, *record))
+        .collect();
+    let args = cohort_query_args(RECEIVER, RECEIVER_NONCE, 20, 40, 3, members);
+    assert_eq!(args.len(), 5 + 3 * records.len());
+    assert_shared(&args, RECEIVER, RECEIVER_NONCE);
+    assert!(matches!(args[2], Argument::PlaintextU8(20)));
+    assert!(matches!(args[3], Argument::PlaintextU8(40)));
+    assert!(matches!(args[4], Argument::PlaintextU8(3)));
+    for (member, record) in args[5..].chunks(3).zip(records) {
+        assert_shared(member, SENDER, NONCE);
+        assert_ciphertexts(&member[2], record, PATIENT_DATA_CIPHERTEXTS_LEN);
+    }
+}
+
+#[test]
+fn compatible_donors_search_appends_donors_after_the_request() {
+    let profiles = [Pubkey::new_unique(), Pubkey::new_unique()];
+    let donors = profiles
+        .iter()
+        .flat_map(|profile| donor_args(SENDER, NONCE, *profile))
+        .collect();
+    let args = find_compatible_donors_args(RECEIVER, RECEIVER_NONCE, 0b101, 0b010, donors);
+    assert_eq!(args.len(), 4 + 3 * profiles.len());
+    assert_shared(&args, RECEIVER, RECEIVER_NONCE);
+    assert!(matches!(args[2], Argument::PlaintextU8(0b101)));
+    assert!(matches!(args[3], Argument::PlaintextU8(0b010)));
+    for (donor, profile) in args[4..].chunks(3).zip(profiles) {
+        assert_shared(donor, SENDER, NONCE);
+        assert_ciphertexts(&donor[2], profile, DONOR_CIPHERTEXTS_LEN);
+    }
+}
+
+#[test]
+fn waitlist_priority_appends_candidates_after_the_coordinator() {
+    let entries = [Pubkey::new_unique(), Pubkey::new_unique()];
+    let candidates = entries
+        .iter()
+        .flat_map(|entry| waitlist_candidate_args(SENDER, NONCE, *entry))
+        .collect();
+    let args = waitlist_priority_args(RECEIVER, RECEIVER_NONCE, candidates);
+    assert_eq!(args.len(), 2 + 3 * entries.len());
+    assert_shared(&args, RECEIVER, RECEIVER_NONCE);
+    for (candidate, entry) in args[2..].chunks(3).zip(entries) {
+        assert_shared(candidate, SENDER, NONCE);
+        assert_ciphertexts(&candidate[2], entry, WAITLIST_CIPHERTEXTS_LEN);
+    }
+}
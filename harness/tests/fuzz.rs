@@ -0,0 +1,3899 @@
+//! Property-based checks that instruction inputs and account layouts the program should
+//! reject are rejected, whatever their values.
+//!
+//! Runs against the BPF build, so `anchor build` first. Only the share properties queue
+//! computations; they need Arcium fixtures dumped for the default cluster and are ignored
+//! unless run with `--ignored`.
+
+use std::cell::RefCell;
+
+use anchor_lang::error::ErrorCode as AnchorErrorCode;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::Event;
+use anchor_spl::token::TokenAccount;
+use arcium_anchor::prelude::comp_def_offset as comp_def_offset_of;
+use proptest::prelude::*;
+use proptest::test_runner::{Config, TestCaseError, TestRunner};
+use share_medical_records::cpi_client;
+use share_medical_records::{
+    lab_result_hash, med_administration_hash, patient_commitment, share_code_redemption_message,
+    share_output_hash, AccessLogPage, AccessRequest, AuditAttestation, CallbackGuard,
+    CallbackState, CodeSet, CodedValue, CohortMember, CohortSnapshot, CompressedCiphertexts,
+    CompressedRecordTree, ConsentGrant, DisasterMode, EmergencyPacket, EncryptedContentKey,
+    EncryptedMedAdministration, ErrorCode, EscrowStatus, ExpiryClock, InFlightComputation, Inbox,
+    IngestAuthority, KinDegree, KinLink, KinRoster, LabResult, MedAdministrationLog,
+    NotificationKind, OneTimeShareCode, PatientCommitment, PatientData, PatientDataUpdatedEvent,
+    PatientField, PatientRegistry, Prescription, PrescriptionStatus, PriorAuthRequest,
+    PriorAuthStatus, ProviderRole, ResearchEscrow, ResearchOptIn, ShareReceipt, SharedRecord,
+    Specialty, StagedShare, TelehealthSession, TrialEnrollment, TrialEscrow, UsedOffsets,
+    ACCESS_LOG_PAGE_SIZE, CALLBACK_GUARD_SEED, CAREGIVER_FIELDS, COHORT_SIZE, COHORT_SNAPSHOT_SEED,
+    CONSENT_EXPIRY_NOTICE, CONSENT_SCOPE_ALL, CONSENT_SCOPE_CLINICAL_MESSAGES,
+    CONSENT_SCOPE_PATIENT_DATA, DEFAULT_MAX_IN_FLIGHT_PER_PAYER, EMERGENCY_PACKET_SEED,
+    INBOX_CAPACITY, LAB_RESULT_VALUES, MAX_AUDITORS, MAX_BPS, MAX_CODE_SET_VALUES,
+    MAX_DISASTER_MODE_NOTICE_SECONDS, MAX_DISASTER_MODE_SECONDS, MAX_DISCLOSURE_COMMITTEE,
+    MAX_IN_FLIGHT_PER_PAYER, MAX_REGULATORS, MAX_SESSION_SLOTS, MAX_SHARE_CODE_SECONDS,
+    MAX_TRIAL_MILESTONES, MED_ADMINISTRATION_VALUES, MIN_CAREGIVER_INTERVAL_SLOTS, MS_PER_SLOT,
+    PATIENT_FIELDS_ALL, PRESCRIPTION_SEED, PRIOR_AUTH_SEED, RESEARCH_OPT_IN_SEED,
+    SHARE_COMPUTE_UNIT_LIMIT, TRIAL_ENROLLMENT_SEED, USED_OFFSETS_BITS, USED_OFFSETS_SEED,
+};
+use share_medical_records_client::*;
+use share_medical_records_harness::{
+    cohort_query_callback_ix, cohort_query_success, compact_share_patient_data_callback_ix,
+    preview_share_callback_ix, preview_share_success, share_patient_data_callback_ix,
+    share_patient_data_success, versioned_share_patient_data_callback_ix, Harness, HarnessBuilder,
+    DEVNET_CLUSTER_OFFSET, FIXTURES_DIR,
+};
+use solana_sdk::compute_budget::ComputeBudgetInstruction;
+use solana_sdk::ed25519_instruction::new_ed25519_instruction_with_signature;
+use solana_sdk::hash::hashv;
+use solana_sdk::instruction::InstructionError;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{keypair_from_seed, Keypair, Signer};
+use solana_sdk::transaction::TransactionError;
+use tokio::runtime::Runtime;
+
+const CASES: u32 = 64;
+/// Enough for a patient to pay the rent of its record and consents.
+const PATIENT_LAMPORTS: u64 = 100_000_000;
+
+struct Bench {
+    rt: Runtime,
+    harness: RefCell<Harness>,
+    /// Admin of the preloaded program config
+    admin: Keypair,
+}
+
+impl Bench {
+    fn new() -> Self {
+        Self::start(Harness::builder())
+    }
+
+    /// A bench that can queue computations; panics if no Arcium fixtures were dumped.
+    fn with_arcium_fixtures() -> Self {
+        Self::start(Harness::builder().arcium_fixtures(FIXTURES_DIR).unwrap())
+    }
+
+    /// `with_arcium_fixtures`, with the share comp def also preloaded at each of
+    /// `comp_def_offsets` as a later circuit version.
+    fn with_share_circuit_versions(comp_def_offsets: &[u32]) -> Self {
+        let builder = Harness::builder()
+            .arcium_fixtures(FIXTURES_DIR)
+            .and_then(|builder| builder.comp_def_copies(FIXTURES_DIR, comp_def_offsets))
+            .unwrap();
+        Self::start(builder)
+    }
+
+    /// `with_arcium_fixtures`, with the dumped share comp def standing in for the comp
+    /// defs of `circuits` too; the mock Arcium program runs none of them.
+    fn with_circuit_stand_ins(circuits: &[&str]) -> Self {
+        let comp_def_offsets: Vec<u32> = circuits
+            .iter()
+            .map(|name| comp_def_offset_of(name))
+            .collect();
+        Self::with_share_circuit_versions(&comp_def_offsets)
+    }
+
+    fn start(builder: HarnessBuilder) -> Self {
+        let rt = Runtime::new().unwrap();
+        let admin = Keypair::new();
+        let harness = rt.block_on(builder.program_config(admin.pubkey()).start());
+        Self {
+            rt,
+            harness: RefCell::new(harness),
+            admin,
+        }
+    }
+
+    /// Sends `ixs` signed by `signers`, returning the transaction's result.
+    fn send(&self, ixs: &[Instruction], signers: &[&Keypair]) -> Result<(), TransactionError> {
+        let ixs = self.on_current_log_pages(ixs);
+        let mut harness = self.harness.borrow_mut();
+        self.rt
+            .block_on(harness.process(&ixs, signers))
+            .unwrap()
+            .result
+    }
+
+    /// `ixs` with each share moved from the first page of its record's access log, where
+    /// the client helpers log it, to the record's current page.
+    fn on_current_log_pages(&self, ixs: &[Instruction]) -> Vec<Instruction> {
+        let mut ixs = ixs.to_vec();
+        for ix in ixs
+            .iter_mut()
+            .filter(|ix| ix.program_id == share_medical_records::ID)
+        {
+            let keys: Vec<Pubkey> = ix.accounts.iter().map(|meta| meta.pubkey).collect();
+            for patient_data in &keys {
+                let first_page = access_log_page_address(patient_data, 0);
+                let Some(meta) = ix
+                    .accounts
+                    .iter_mut()
+                    .find(|meta| meta.pubkey == first_page)
+                else {
+                    continue;
+                };
+                let page = self
+                    .read::<PatientRegistry>(patient_registry_address(patient_data))
+                    .map_or(0, |registry| registry.access_log_page());
+                meta.pubkey = access_log_page_address(patient_data, page);
+            }
+        }
+        ixs
+    }
+
+    /// Sends `ixs` signed by `signers`, returning the compute units the transaction
+    /// consumed if it succeeded.
+    fn compute_units(&self, ixs: &[Instruction], signers: &[&Keypair]) -> Option<u64> {
+        let ixs = self.on_current_log_pages(ixs);
+        let mut harness = self.harness.borrow_mut();
+        let result = self.rt.block_on(harness.process(&ixs, signers)).unwrap();
+        result.result.ok()?;
+        Some(result.metadata?.compute_units_consumed)
+    }
+
+    /// Sends `ixs` signed by `signers`, returning whether the transaction succeeded.
+    fn succeeds(&self, ixs: &[Instruction], signers: &[&Keypair]) -> bool {
+        self.send(ixs, signers).is_ok()
+    }
+
+    /// Delivers `callback` for the computation at `computation_offset` through the mock
+    /// Arcium program, returning whether it succeeded.
+    fn callback_succeeds(&self, computation_offset: u64, callback: Instruction) -> bool {
+        let mut harness = self.harness.borrow_mut();
+        let result = self
+            .rt
+            .block_on(harness.inject_callback(computation_offset, callback))
+            .unwrap();
+        result.result.is_ok()
+    }
+
+    fn now(&self) -> i64 {
+        self.rt.block_on(self.harness.borrow_mut().now()).unwrap()
+    }
+
+    fn read<T: anchor_lang::AccountDeserialize>(&self, address: Pubkey) -> Option<T> {
+        let mut harness = self.harness.borrow_mut();
+        self.rt.block_on(harness.program_account(address)).unwrap()
+    }
+
+    fn fund_ix(&self, wallet: &Keypair) -> Instruction {
+        self.harness
+            .borrow()
+            .fund_ix(&wallet.pubkey(), PATIENT_LAMPORTS)
+    }
+
+    /// A funded provider registered by the admin with `role`, and the x25519 key attested
+    /// for them.
+    fn provider(&self, role: ProviderRole) -> (Keypair, [u8; 32]) {
+        let provider = Keypair::new();
+        let enc_key = EncryptionKeypair::generate().public_key();
+        let admin = self.admin.pubkey();
+        assert!(self.succeeds(
+            &[
+                self.fund_ix(&self.admin),
+                self.fund_ix(&provider),
+                set_registrars_ix(admin, vec![admin]),
+                register_provider_ix(admin, provider.pubkey(), role),
+                attest_provider_enc_key_ix(admin, provider.pubkey(), enc_key),
+            ],
+            &[&self.admin],
+        ));
+        (provider, enc_key)
+    }
+
+    /// A funded patient with a stored record.
+    fn patient(&self, record: &EncryptedPatientRecord) -> Keypair {
+        let patient = Keypair::new();
+        let store = store_patient_data_ix(patient.pubkey(), patient.pubkey(), record);
+        assert!(self.succeeds(&[self.fund_ix(&patient), store], &[&patient]));
+        patient
+    }
+}
+
+/// A record whose nonce is taken from its first ciphertext, so distinct records never
+/// reuse one.
+fn record(ciphertexts: [[u8; 32]; 11]) -> EncryptedPatientRecord {
+    EncryptedPatientRecord {
+        ciphertexts,
+        sender_pub_key: [0; 32],
+        nonce: ciphertexts[0][..16].try_into().unwrap(),
+    }
+}
+
+fn ciphertexts() -> impl Strategy<Value = [[u8; 32]; 11]> {
+    prop::array::uniform11(prop::array::uniform32(any::<u8>()))
+}
+
+/// Fixed ciphertexts, distinct per `seed`, that pass the store-time sanity checks.
+fn distinct_ciphertexts(seed: u8) -> [[u8; 32]; 11] {
+    std::array::from_fn(|i| {
+        let mut block = [seed; 32];
+        block[31] = i as u8;
+        block
+    })
+}
+
+/// `ix` with its share comp def moved to the circuit version at `comp_def_offset`.
+fn through_comp_def(mut ix: Instruction, comp_def_offset: u32) -> Instruction {
+    let original = cpi_client::comp_def_address(comp_def_offset_of("share_patient_data"));
+    for meta in ix
+        .accounts
+        .iter_mut()
+        .filter(|meta| meta.pubkey == original)
+    {
+        meta.pubkey = cpi_client::comp_def_address(comp_def_offset);
+    }
+    ix
+}
+
+/// Bump of the program address derived from `seeds`.
+fn program_bump(seeds: &[&[u8]]) -> u8 {
+    Pubkey::find_program_address(seeds, &share_medical_records::ID).1
+}
+
+/// Offsets of `payer` with each of `offsets` in flight until far in the future.
+fn in_flight_offsets(payer: Pubkey, offsets: &[u64]) -> UsedOffsets {
+    let mut in_flight = [InFlightComputation::default(); MAX_IN_FLIGHT_PER_PAYER];
+    for (slot, &computation_offset) in in_flight.iter_mut().zip(offsets) {
+        *slot = InFlightComputation {
+            computation_offset,
+            deadline: i64::MAX,
+        };
+    }
+    UsedOffsets {
+        payer,
+        bits: [0; USED_OFFSETS_BITS / 8],
+        count: offsets.len() as u64,
+        bump: program_bump(&[USED_OFFSETS_SEED, payer.as_ref()]),
+        in_flight,
+    }
+}
+
+/// Whether `computation_offset` still holds one of `payer`'s in-flight slots.
+fn holds_slot(bench: &Bench, payer: &Pubkey, computation_offset: u64) -> bool {
+    bench
+        .read::<UsedOffsets>(used_offsets_address(payer))
+        .unwrap()
+        .in_flight
+        .iter()
+        .any(|slot| slot.deadline > 0 && slot.computation_offset == computation_offset)
+}
+
+fn check(holds: bool, message: &str) -> Result<(), TestCaseError> {
+    prop_assert!(holds, "{}", message);
+    Ok(())
+}
+
+#[test]
+fn stored_ciphertexts_round_trip_and_truncated_allergies_are_rejected() {
+    let bench = Bench::new();
+    let mut runner = TestRunner::new(Config::with_cases(CASES));
+    // Bytes cut from the end of the instruction data, i.e. from the nonce, the encryption
+    // key and the allergies
+    let strategy = (ciphertexts(), 0usize..=16 + 6 * 32);
+    runner
+        .run(&strategy, |(ciphertexts, cut)| {
+            let patient = Keypair::new();
+            let mut record = record(ciphertexts);
+            record.sender_pub_key = ciphertexts[0];
+            let mut store = store_patient_data_ix(patient.pubkey(), patient.pubkey(), &record);
+            store.data.truncate(store.data.len() - cut);
+            let stored = bench.succeeds(&[bench.fund_ix(&patient), store], &[&patient]);
+
+            check(
+                stored == (cut == 0),
+                "only complete allergy arrays, keys and nonces are accepted",
+            )?;
+            if stored {
+                let data: PatientData =
+                    bench.read(patient_data_address(&patient.pubkey())).unwrap();
+                prop_assert_eq!(data.patient_id, ciphertexts[0]);
+                prop_assert_eq!(data.height, ciphertexts[5]);
+                prop_assert_eq!(&data.allergies[..], &ciphertexts[6..]);
+                prop_assert_eq!(data.enc_pubkey, record.sender_pub_key);
+            }
+            Ok(())
+        })
+        .unwrap();
+}
+
+#[test]
+fn zeroed_or_duplicated_ciphertexts_are_rejected() {
+    let bench = Bench::new();
+    let mut runner = TestRunner::new(Config::with_cases(CASES));
+    // Whether to zero out a block or copy an allergy slot over another, the block to zero
+    // and the slots to copy between
+    let strategy = (
+        ciphertexts(),
+        any::<bool>(),
+        0usize..11,
+        6usize..11,
+        6usize..11,
+    );
+    runner
+        .run(&strategy, |(mut ciphertexts, zero, zeroed, from, to)| {
+            prop_assume!(from != to);
+            let expected = if zero {
+                ciphertexts[zeroed] = [0; 32];
+                ErrorCode::ZeroCiphertext
+            } else {
+                ciphertexts[to] = ciphertexts[from];
+                ErrorCode::DuplicateAllergyCiphertext
+            };
+            let patient = Keypair::new();
+            let store =
+                store_patient_data_ix(patient.pubkey(), patient.pubkey(), &record(ciphertexts));
+            prop_assert_eq!(
+                bench.send(&[bench.fund_ix(&patient), store], &[&patient]),
+                Err(TransactionError::InstructionError(
+                    1,
+                    InstructionError::Custom(u32::from(expected))
+                ))
+            );
+            Ok(())
+        })
+        .unwrap();
+}
+
+#[test]
+fn records_hold_exactly_the_collected_fields() {
+    let bench = Bench::new();
+    let record_fields =
+        PATIENT_FIELDS_ALL & !(PatientField::Weight.bit() | PatientField::Height.bit());
+    assert!(bench.succeeds(
+        &[set_record_fields_ix(bench.admin.pubkey(), record_fields)],
+        &[&bench.admin],
+    ));
+    let mut runner = TestRunner::new(Config::with_cases(CASES));
+    // Whether to store an uncollected field, and a block of a collected one to zero
+    let strategy = (1u8.., any::<bool>(), 0usize..6);
+    runner
+        .run(&strategy, |(seed, uncollected, zeroed)| {
+            let mut stored = record(distinct_ciphertexts(seed)).retain_fields(record_fields);
+            let expected = if uncollected {
+                stored.ciphertexts[PatientField::Height.blocks().start] = [seed; 32];
+                Some(ErrorCode::FieldNotCollected)
+            } else if zeroed < PatientField::Weight.blocks().start {
+                stored.ciphertexts[zeroed] = [0; 32];
+                Some(ErrorCode::ZeroCiphertext)
+            } else {
+                None
+            };
+            let patient = Keypair::new();
+            let store = store_patient_data_ix(patient.pubkey(), patient.pubkey(), &stored);
+            prop_assert_eq!(
+                bench.send(&[bench.fund_ix(&patient), store], &[&patient]),
+                match expected {
+                    Some(error) => Err(TransactionError::InstructionError(
+                        1,
+                        InstructionError::Custom(u32::from(error))
+                    )),
+                    None => Ok(()),
+                }
+            );
+            Ok(())
+        })
+        .unwrap();
+}
+
+#[test]
+fn commitments_bind_the_stored_ciphertexts() {
+    let bench = Bench::new();
+    let mut runner = TestRunner::new(Config::with_cases(CASES));
+    // Seeds of the first and replacing records, and a ciphertext byte to flip
+    let strategy = (1u8.., 1u8.., 0usize..11, 0usize..32);
+    runner
+        .run(&strategy, |(first, second, block, byte)| {
+            prop_assume!(first != second);
+            let patient = Keypair::new();
+            let address = patient_commitment_address(&patient.pubkey());
+            let store = |seed| {
+                let mut stored = record(distinct_ciphertexts(seed));
+                stored.sender_pub_key = EncryptionKeypair::generate().public_key();
+                let ix = store_patient_commitment_ix(patient.pubkey(), &stored, "bafy".repeat(16));
+                (stored, ix)
+            };
+
+            let (stored, ix) = store(first);
+            check(
+                bench.succeeds(&[bench.fund_ix(&patient), ix], &[&patient]),
+                "the commitment is stored",
+            )?;
+            let commitment: PatientCommitment = bench.read(address).unwrap();
+            prop_assert_eq!(commitment.commitment, stored.commitment(&patient.pubkey()));
+            let mut tampered = stored;
+            tampered.ciphertexts[block][byte] ^= 1;
+            check(
+                tampered.commitment(&patient.pubkey()) != commitment.commitment,
+                "a changed ciphertext changes the commitment",
+            )?;
+
+            let (replacement, ix) = store(second);
+            check(
+                bench.succeeds(&[ix], &[&patient]),
+                "the commitment is replaced",
+            )?;
+            let replaced: PatientCommitment = bench.read(address).unwrap();
+            prop_assert_eq!(
+                replaced.commitment,
+                replacement.commitment(&patient.pubkey())
+            );
+            prop_assert_eq!(replaced.created_slot, commitment.created_slot);
+            Ok(())
+        })
+        .unwrap();
+}
+
+#[test]
+fn updates_reusing_a_nonce_are_rejected() {
+    let bench = Bench::new();
+    let stored = record(distinct_ciphertexts(6));
+    let patient = bench.patient(&stored);
+    let mut runner = TestRunner::new(Config::with_cases(CASES));
+    runner
+        .run(&(ciphertexts(), any::<bool>()), |(ciphertexts, reuse)| {
+            let mut update = record(ciphertexts);
+            if reuse {
+                update.nonce = stored.nonce;
+            }
+            check(
+                bench.succeeds(
+                    &[update_patient_data_ix(patient.pubkey(), &update)],
+                    &[&patient],
+                ) != reuse,
+                "an update succeeds unless its nonce was used with the record",
+            )
+        })
+        .unwrap();
+}
+
+#[test]
+fn upserts_replace_records_only_when_asked() {
+    let bench = Bench::new();
+    let mut runner = TestRunner::new(Config::with_cases(CASES));
+    runner
+        .run(
+            &(1u8.., 1u8.., any::<bool>()),
+            |(first, second, overwrite)| {
+                let patient = Keypair::new();
+                let (first, second) = (
+                    record(distinct_ciphertexts(first)),
+                    record(distinct_ciphertexts(second)),
+                );
+                let upsert = |record, overwrite| {
+                    upsert_patient_data_ix(patient.pubkey(), patient.pubkey(), record, overwrite)
+                };
+                check(
+                    bench.succeeds(
+                        &[bench.fund_ix(&patient), upsert(&first, true)],
+                        &[&patient],
+                    ),
+                    "an upsert stores a new record",
+                )?;
+                check(
+                    bench.succeeds(&[upsert(&second, overwrite)], &[&patient]),
+                    "an upsert of an existing record succeeds whether or not it overwrites",
+                )?;
+
+                let data: PatientData =
+                    bench.read(patient_data_address(&patient.pubkey())).unwrap();
+                let kept = if overwrite { &second } else { &first };
+                prop_assert_eq!(data.patient_id, kept.ciphertexts[0]);
+                let replaced = overwrite && first.ciphertexts != second.ciphertexts;
+                prop_assert_eq!(data.version, if replaced { 2 } else { 1 });
+                Ok(())
+            },
+        )
+        .unwrap();
+}
+
+#[test]
+fn consent_accepts_only_known_scopes_and_future_expiries() {
+    let bench = Bench::new();
+    let patient = bench.patient(&record(distinct_ciphertexts(1)));
+    let mut runner = TestRunner::new(Config::with_cases(CASES));
+    let strategy = (any::<u16>(), -100_000i64..100_000);
+    runner
+        .run(&strategy, |(scope, ttl)| {
+            let receiver = Pubkey::new_unique();
+            let expires_at = bench.now() + ttl;
+            let granted = bench.succeeds(
+                &[grant_consent_ix(
+                    patient.pubkey(),
+                    receiver,
+                    scope,
+                    expires_at,
+                )],
+                &[&patient],
+            );
+
+            let valid_scope = scope != 0 && scope & !CONSENT_SCOPE_ALL == 0;
+            check(
+                granted == (valid_scope && ttl > 0),
+                "grant outcome must follow scope and expiry validity",
+            )
+        })
+        .unwrap();
+}
+
+#[test]
+fn code_sets_need_distinct_codes_and_rising_versions() {
+    let bench = Bench::new();
+    let published = std::cell::Cell::new(0u32);
+    let mut runner = TestRunner::new(Config::with_cases(CASES));
+    // Small value and code alphabets, so duplicates come up
+    let strategy = prop::collection::vec((0u64..6, "[A-F][+-]?"), 1..=MAX_CODE_SET_VALUES);
+    runner
+        .run(&strategy, |values| {
+            let values: Vec<CodedValue> = values
+                .into_iter()
+                .map(|(value, code)| CodedValue { value, code })
+                .collect();
+            let distinct = values.iter().enumerate().all(|(i, coded)| {
+                values[..i]
+                    .iter()
+                    .all(|other| other.value != coded.value && other.code != coded.code)
+            });
+            let name = format!("set-{}", published.replace(published.get() + 1));
+            let publish = |version| {
+                bench.succeeds(
+                    &[publish_code_set_ix(
+                        bench.admin.pubkey(),
+                        &name,
+                        "snomed-ct",
+                        version,
+                        values.clone(),
+                    )],
+                    &[&bench.admin],
+                )
+            };
+            check(
+                publish(1) == distinct,
+                "a code set is published only if its values and codes are distinct",
+            )?;
+            if !distinct {
+                return Ok(());
+            }
+            let code_set: Option<CodeSet> = bench.read(code_set_address(&name));
+            check(
+                code_set.is_some_and(|code_set| {
+                    values.iter().all(|coded| {
+                        code_set.value_of(&coded.code) == Some(coded.value)
+                            && code_set.code_of(coded.value) == Some(coded.code.as_str())
+                    })
+                }),
+                "each code maps to its value and back",
+            )?;
+            check(publish(3), "a higher version replaces the set")?;
+            check(!publish(2), "a version below the published one is rejected")
+        })
+        .unwrap();
+}
+
+#[test]
+fn revoked_consent_covers_nothing() {
+    let bench = Bench::new();
+    let patient = bench.patient(&record(distinct_ciphertexts(2)));
+    let mut runner = TestRunner::new(Config::with_cases(CASES));
+    let strategy = (1..=CONSENT_SCOPE_ALL, any::<u16>());
+    runner
+        .run(&strategy, |(scope, requested)| {
+            let receiver = Pubkey::new_unique();
+            let expires_at = bench.now() + 3_600;
+            check(
+                bench.succeeds(
+                    &[
+                        grant_consent_ix(patient.pubkey(), receiver, scope, expires_at),
+                        revoke_consent_ix(patient.pubkey(), receiver),
+                    ],
+                    &[&patient],
+                ),
+                "grant and revoke succeed",
+            )?;
+
+            let grant: ConsentGrant = bench
+                .read(consent_grant_address(
+                    &patient_data_address(&patient.pubkey()),
+                    &receiver,
+                ))
+                .unwrap();
+            let clock = ExpiryClock {
+                unix_timestamp: bench.now(),
+                slot: 0,
+                tolerance_slots: 0,
+            };
+            check(
+                grant.require_covers(requested, &clock).is_err(),
+                "a revoked consent covers no scope",
+            )
+        })
+        .unwrap();
+}
+
+#[test]
+fn expiries_pass_on_either_clock() {
+    let mut runner = TestRunner::new(Config::with_cases(CASES));
+    // Seconds until expiry, seconds the timestamp lags wall time by and the tolerance
+    let strategy = (0i64..100_000, 0i64..100_000, 0i64..10_000);
+    runner
+        .run(&strategy, |(ttl, lag, tolerance)| {
+            let granted = ExpiryClock {
+                unix_timestamp: 1_700_000_000,
+                slot: 300_000_000,
+                tolerance_slots: 0,
+            };
+            let expires_at = granted.unix_timestamp + ttl;
+            let expires_at_slot = granted.slot_at(expires_at);
+            // Slots advance at the nominal rate while the timestamp falls behind
+            let elapsed = ttl + tolerance + 1;
+            let later = ExpiryClock {
+                unix_timestamp: granted.unix_timestamp + elapsed - lag.min(elapsed),
+                slot: granted.slot + elapsed as u64 * 1000 / MS_PER_SLOT,
+                tolerance_slots: tolerance as u64 * 1000 / MS_PER_SLOT + 1,
+            };
+            check(
+                later.has_passed(expires_at, expires_at_slot),
+                "a lagging timestamp does not outlive the slot tolerance",
+            )?;
+            check(
+                !granted.has_passed(expires_at, expires_at_slot) || ttl == 0,
+                "an expiry is not passed when estimated",
+            )
+        })
+        .unwrap();
+}
+
+#[test]
+fn permuted_accounts_are_rejected() {
+    let bench = Bench::new();
+    let owner = bench.patient(&record(distinct_ciphertexts(3)));
+    let other = bench.patient(&record(distinct_ciphertexts(4)));
+    let mut runner = TestRunner::new(Config::with_cases(CASES));
+    // The instruction to tamper with, a permutation of its non-signer accounts plus the
+    // other patient's record, and fresh data so no two cases send the same transaction
+    let strategy = (
+        any::<bool>(),
+        Just((0..8).collect::<Vec<usize>>()).prop_shuffle(),
+        ciphertexts(),
+    );
+    runner
+        .run(&strategy, |(grant, order, ciphertexts)| {
+            let mut ix = if grant {
+                let expires_at = bench.now() + 3_600;
+                let receiver = Pubkey::new_unique();
+                grant_consent_ix(owner.pubkey(), receiver, CONSENT_SCOPE_ALL, expires_at)
+            } else {
+                update_patient_data_ix(owner.pubkey(), &record(ciphertexts))
+            };
+            let mut keys: Vec<Pubkey> = ix
+                .accounts
+                .iter()
+                .filter(|meta| !meta.is_signer)
+                .map(|meta| meta.pubkey)
+                .collect();
+            let own_accounts = keys.len();
+            keys.push(patient_data_address(&other.pubkey()));
+            let permuted: Vec<Pubkey> = order
+                .iter()
+                .filter(|&&i| i < keys.len())
+                .map(|&i| keys[i])
+                .collect();
+            let unchanged = permuted[..own_accounts] == keys[..own_accounts];
+            for (meta, key) in ix
+                .accounts
+                .iter_mut()
+                .filter(|meta| !meta.is_signer)
+                .zip(&permuted)
+            {
+                meta.pubkey = *key;
+            }
+
+            check(
+                bench.succeeds(&[ix], &[&owner]) == unchanged,
+                "only the instruction's own accounts, in order, are accepted",
+            )
+        })
+        .unwrap();
+}
+
+#[test]
+#[ignore = "requires Arcium fixtures"]
+fn shares_need_record_control_or_covering_consent() {
+    let bench = Bench::with_arcium_fixtures();
+    let mut stored = record(distinct_ciphertexts(5));
+    stored.sender_pub_key = EncryptionKeypair::generate().public_key();
+    let patient = bench.patient(&stored);
+    let mut runner = TestRunner::new(Config::with_cases(CASES));
+    // Whether the patient shares their own record, the consent the provider holds (none,
+    // covering, missing the record's scope or revoked), the computation offset and a fresh
+    // receiver nonce
+    let strategy = (any::<bool>(), 0u8..4, any::<u64>(), any::<[u8; 16]>());
+    runner
+        .run(
+            &strategy,
+            |(patient_pays, consent, computation_offset, receiver_nonce)| {
+                let provider = Keypair::new();
+                let expires_at = bench.now() + 3_600;
+                let grant = |scope| {
+                    grant_consent_ix(patient.pubkey(), provider.pubkey(), scope, expires_at)
+                };
+                let setup = match consent {
+                    1 => vec![grant(CONSENT_SCOPE_PATIENT_DATA)],
+                    2 => vec![grant(CONSENT_SCOPE_ALL & !CONSENT_SCOPE_PATIENT_DATA)],
+                    3 => vec![
+                        grant(CONSENT_SCOPE_ALL),
+                        revoke_consent_ix(patient.pubkey(), provider.pubkey()),
+                    ],
+                    _ => Vec::new(),
+                };
+                if !setup.is_empty() {
+                    check(
+                        bench.succeeds(&setup, &[&patient]),
+                        "consent setup succeeds",
+                    )?;
+                }
+
+                let receiver = EncryptionKeypair::generate().public_key();
+                let (payer, share) = if patient_pays {
+                    let share = share_patient_data_ix(
+                        patient.pubkey(),
+                        stored.sender_pub_key,
+                        stored.nonce,
+                        receiver,
+                        receiver_nonce,
+                        computation_offset,
+                        DEVNET_CLUSTER_OFFSET,
+                    );
+                    (&patient, share)
+                } else {
+                    let share = share_consented_patient_data_ix(
+                        provider.pubkey(),
+                        patient.pubkey(),
+                        stored.sender_pub_key,
+                        stored.nonce,
+                        receiver,
+                        receiver_nonce,
+                        computation_offset,
+                        DEVNET_CLUSTER_OFFSET,
+                    );
+                    (&provider, share)
+                };
+                let result = bench.send(&[bench.fund_ix(payer), share], &[payer]);
+
+                let expected = match (patient_pays, consent) {
+                    (true, _) | (false, 1) => None,
+                    // The consent account the provider passes was never created
+                    (false, 0) => Some(AnchorErrorCode::AccountNotInitialized as u32),
+                    (false, 2) => Some(u32::from(ErrorCode::ConsentPurposeNotCovered)),
+                    (false, _) => Some(u32::from(ErrorCode::ConsentRevoked)),
+                };
+                prop_assert_eq!(
+                    result,
+                    match expected {
+                        None => Ok(()),
+                        Some(code) => Err(TransactionError::InstructionError(
+                            1,
+                            InstructionError::Custom(code)
+                        )),
+                    }
+                );
+                if expected.is_some() {
+                    return Ok(());
+                }
+                // Settles the share so the patient's in-flight computations stay under the cap
+                check(
+                    bench.callback_succeeds(
+                        computation_offset,
+                        share_patient_data_callback_ix(
+                            payer.pubkey(),
+                            patient_data_address(&patient.pubkey()),
+                            receiver,
+                            computation_offset,
+                            share_patient_data_success(receiver, 0, [[0; 32]; 11]),
+                        ),
+                    ),
+                    "the callback settles the share",
+                )
+            },
+        )
+        .unwrap();
+}
+
+#[test]
+#[ignore = "requires Arcium fixtures"]
+fn a_pending_share_blocks_another_to_the_same_receiver() {
+    let bench = Bench::with_arcium_fixtures();
+    let mut stored = record(distinct_ciphertexts(7));
+    stored.sender_pub_key = EncryptionKeypair::generate().public_key();
+    let patient = bench.patient(&stored);
+    let mut runner = TestRunner::new(Config::with_cases(CASES));
+    let strategy = (any::<[u8; 16]>(), any::<[u8; 16]>(), any::<u64>());
+    runner
+        .run(
+            &strategy,
+            |(first_nonce, second_nonce, computation_offset)| {
+                prop_assume!(first_nonce != second_nonce);
+                let receiver = EncryptionKeypair::generate().public_key();
+                let share = |receiver_nonce, computation_offset| {
+                    share_patient_data_ix(
+                        patient.pubkey(),
+                        stored.sender_pub_key,
+                        stored.nonce,
+                        receiver,
+                        receiver_nonce,
+                        computation_offset,
+                        DEVNET_CLUSTER_OFFSET,
+                    )
+                };
+                let retry = share(second_nonce, computation_offset.wrapping_add(1));
+
+                check(
+                    bench.succeeds(
+                        &[
+                            bench.fund_ix(&patient),
+                            share(first_nonce, computation_offset),
+                        ],
+                        &[&patient],
+                    ),
+                    "the first share is queued",
+                )?;
+                check(
+                    !bench.succeeds(&[retry.clone()], &[&patient]),
+                    "a second share to the receiver is rejected while the first is pending",
+                )?;
+                check(
+                    bench.callback_succeeds(
+                        computation_offset,
+                        share_patient_data_callback_ix(
+                            patient.pubkey(),
+                            patient_data_address(&patient.pubkey()),
+                            receiver,
+                            computation_offset,
+                            share_patient_data_success(receiver, 0, [[0; 32]; 11]),
+                        ),
+                    ),
+                    "the callback settles the first share",
+                )?;
+                check(
+                    bench.succeeds(&[retry], &[&patient]),
+                    "the receiver can be shared with again once the first share settled",
+                )?;
+                check(
+                    bench.callback_succeeds(
+                        computation_offset.wrapping_add(1),
+                        share_patient_data_callback_ix(
+                            patient.pubkey(),
+                            patient_data_address(&patient.pubkey()),
+                            receiver,
+                            computation_offset.wrapping_add(1),
+                            share_patient_data_success(receiver, 0, [[0; 32]; 11]),
+                        ),
+                    ),
+                    "the callback settles the second share",
+                )
+            },
+        )
+        .unwrap();
+}
+
+#[test]
+#[ignore = "requires Arcium fixtures"]
+fn replayed_callbacks_are_rejected() {
+    let bench = Bench::with_arcium_fixtures();
+    let mut stored = record(distinct_ciphertexts(10));
+    stored.sender_pub_key = EncryptionKeypair::generate().public_key();
+    let patient = bench.patient(&stored);
+    let mut runner = TestRunner::new(Config::with_cases(CASES));
+    let strategy = (any::<[u8; 16]>(), any::<u64>());
+    runner
+        .run(&strategy, |(receiver_nonce, computation_offset)| {
+            let receiver = EncryptionKeypair::generate().public_key();
+            let callback = share_patient_data_callback_ix(
+                patient.pubkey(),
+                patient_data_address(&patient.pubkey()),
+                receiver,
+                computation_offset,
+                share_patient_data_success(receiver, 0, [[0; 32]; 11]),
+            );
+
+            check(
+                bench.succeeds(
+                    &[
+                        bench.fund_ix(&patient),
+                        share_patient_data_ix(
+                            patient.pubkey(),
+                            stored.sender_pub_key,
+                            stored.nonce,
+                            receiver,
+                            receiver_nonce,
+                            computation_offset,
+                            DEVNET_CLUSTER_OFFSET,
+                        ),
+                    ],
+                    &[&patient],
+                ),
+                "the share is queued",
+            )?;
+            check(
+                bench.callback_succeeds(computation_offset, callback.clone()),
+                "the first callback settles the share",
+            )?;
+            let guard: Option<CallbackGuard> =
+                bench.read(callback_guard_address(computation_offset));
+            check(
+                guard.is_some_and(|guard| guard.state == CallbackState::Consumed),
+                "the callback consumes its guard",
+            )?;
+            check(
+                !bench.callback_succeeds(computation_offset, callback),
+                "a replayed callback is rejected",
+            )
+        })
+        .unwrap();
+}
+
+#[test]
+#[ignore = "requires Arcium fixtures"]
+fn shares_go_through_the_active_and_deprecated_circuit_versions() {
+    let original = comp_def_offset_of("share_patient_data");
+    let activated = comp_def_offset_of("share_patient_data_v2");
+    let unlisted = comp_def_offset_of("share_patient_data_v3");
+    let bench = Bench::with_share_circuit_versions(&[activated, unlisted]);
+    assert!(bench.succeeds(
+        &[
+            set_circuit_source_ix(
+                bench.admin.pubkey(),
+                activated,
+                "https://circuits.example/share_patient_data_v2.arcis".to_string(),
+                [2; 32],
+            ),
+            activate_circuit_version_ix(bench.admin.pubkey(), 2, activated, 24 * 60 * 60),
+        ],
+        &[&bench.admin],
+    ));
+    let mut stored = record(distinct_ciphertexts(15));
+    stored.sender_pub_key = EncryptionKeypair::generate().public_key();
+    let patient = bench.patient(&stored);
+    let patient_data = patient_data_address(&patient.pubkey());
+    let mut runner = TestRunner::new(Config::with_cases(CASES));
+    let strategy = (any::<[u8; 16]>(), any::<u64>(), any::<bool>());
+    runner
+        .run(
+            &strategy,
+            |(receiver_nonce, computation_offset, through_active)| {
+                let (version, other) = if through_active {
+                    (activated, original)
+                } else {
+                    (original, activated)
+                };
+                let receiver = EncryptionKeypair::generate().public_key();
+                let share = share_patient_data_ix(
+                    patient.pubkey(),
+                    stored.sender_pub_key,
+                    stored.nonce,
+                    receiver,
+                    receiver_nonce,
+                    computation_offset,
+                    DEVNET_CLUSTER_OFFSET,
+                );
+                check(
+                    !bench.succeeds(
+                        &[
+                            bench.fund_ix(&patient),
+                            through_comp_def(share.clone(), unlisted),
+                        ],
+                        &[&patient],
+                    ),
+                    "a share through a version never activated is rejected",
+                )?;
+                check(
+                    bench.succeeds(
+                        &[bench.fund_ix(&patient), through_comp_def(share, version)],
+                        &[&patient],
+                    ),
+                    "the share is queued through the active or the deprecated version",
+                )?;
+                let guard: Option<CallbackGuard> =
+                    bench.read(callback_guard_address(computation_offset));
+                check(
+                    guard.is_some_and(|guard| guard.comp_def_offset == version),
+                    "the guard expects the version the share was queued through",
+                )?;
+
+                let callback = |comp_def_offset| {
+                    versioned_share_patient_data_callback_ix(
+                        patient.pubkey(),
+                        patient_data,
+                        receiver,
+                        computation_offset,
+                        comp_def_offset,
+                        share_patient_data_success(receiver, 0, [[0; 32]; 11]),
+                    )
+                };
+                check(
+                    !bench.callback_succeeds(computation_offset, callback(other)),
+                    "a callback through the other version is rejected",
+                )?;
+                check(
+                    bench.callback_succeeds(computation_offset, callback(version)),
+                    "the callback through the queued version settles the share",
+                )
+            },
+        )
+        .unwrap();
+}
+
+#[test]
+#[ignore = "requires Arcium fixtures"]
+fn shares_are_appended_to_the_access_log() {
+    let bench = Bench::with_arcium_fixtures();
+    let mut stored = record(distinct_ciphertexts(12));
+    stored.sender_pub_key = EncryptionKeypair::generate().public_key();
+    let patient = bench.patient(&stored);
+    let patient_data = patient_data_address(&patient.pubkey());
+    let logged = std::cell::Cell::new(0usize);
+    let mut runner = TestRunner::new(Config::with_cases(CASES));
+    let strategy = (any::<[u8; 16]>(), any::<u64>());
+    runner
+        .run(&strategy, |(receiver_nonce, computation_offset)| {
+            let receiver = EncryptionKeypair::generate().public_key();
+            let share = share_patient_data_ix(
+                patient.pubkey(),
+                stored.sender_pub_key,
+                stored.nonce,
+                receiver,
+                receiver_nonce,
+                computation_offset,
+                DEVNET_CLUSTER_OFFSET,
+            );
+            check(
+                bench.succeeds(&[bench.fund_ix(&patient), share], &[&patient]),
+                "the share is queued",
+            )?;
+            let index = logged.replace(logged.get() + 1);
+            let page: Option<AccessLogPage> = bench.read(access_log_page_address(
+                &patient_data,
+                (index / ACCESS_LOG_PAGE_SIZE) as u32,
+            ));
+            let entry =
+                page.and_then(|page| page.entries.get(index % ACCESS_LOG_PAGE_SIZE).copied());
+            check(
+                entry.is_some_and(|entry| {
+                    entry.accessor == patient.pubkey()
+                        && entry.receiver == receiver
+                        && entry.computation_offset == computation_offset
+                }),
+                "the share is the next entry of the log, on its fixed-size page",
+            )?;
+            check(
+                bench.callback_succeeds(
+                    computation_offset,
+                    share_patient_data_callback_ix(
+                        patient.pubkey(),
+                        patient_data,
+                        receiver,
+                        computation_offset,
+                        share_patient_data_success(receiver, 0, [[0; 32]; 11]),
+                    ),
+                ),
+                "the callback settles the share",
+            )
+        })
+        .unwrap();
+}
+
+#[test]
+#[ignore = "requires Arcium fixtures"]
+fn delivered_shares_seal_their_receipt() {
+    let bench = Bench::with_arcium_fixtures();
+    let mut stored = record(distinct_ciphertexts(13));
+    stored.sender_pub_key = EncryptionKeypair::generate().public_key();
+    let patient = bench.patient(&stored);
+    let patient_data = patient_data_address(&patient.pubkey());
+    let mut runner = TestRunner::new(Config::with_cases(CASES));
+    let strategy = (
+        any::<[u8; 16]>(),
+        any::<u64>(),
+        any::<u128>(),
+        ciphertexts(),
+    );
+    runner
+        .run(
+            &strategy,
+            |(receiver_nonce, computation_offset, output_nonce, output)| {
+                let receiver = EncryptionKeypair::generate().public_key();
+                let share = share_patient_data_ix(
+                    patient.pubkey(),
+                    stored.sender_pub_key,
+                    stored.nonce,
+                    receiver,
+                    receiver_nonce,
+                    computation_offset,
+                    DEVNET_CLUSTER_OFFSET,
+                );
+                check(
+                    bench.succeeds(&[bench.fund_ix(&patient), share], &[&patient]),
+                    "the share is queued",
+                )?;
+                let address = share_receipt_address(&patient_data, computation_offset);
+                let receipt: Option<ShareReceipt> = bench.read(address);
+                check(
+                    receipt.is_some_and(|receipt| {
+                        receipt.receiver == receiver
+                            && receipt.purpose == CONSENT_SCOPE_PATIENT_DATA
+                            && receipt.fields == PATIENT_FIELDS_ALL
+                            && receipt.slot == 0
+                    }),
+                    "the share opens an unsealed receipt",
+                )?;
+
+                check(
+                    bench.callback_succeeds(
+                        computation_offset,
+                        share_patient_data_callback_ix(
+                            patient.pubkey(),
+                            patient_data,
+                            receiver,
+                            computation_offset,
+                            share_patient_data_success(receiver, output_nonce, output),
+                        ),
+                    ),
+                    "the callback settles the share",
+                )?;
+                let receipt: Option<ShareReceipt> = bench.read(address);
+                let output_hash =
+                    share_output_hash(&receiver, &output_nonce.to_le_bytes(), &output);
+                check(
+                    receipt.is_some_and(|receipt| {
+                        receipt.slot > 0 && receipt.output_hash == output_hash
+                    }),
+                    "the callback seals the receipt with the delivered output",
+                )
+            },
+        )
+        .unwrap();
+}
+
+#[test]
+#[ignore = "requires Arcium fixtures"]
+fn compact_shares_deliver_into_the_shared_record() {
+    let bench = Bench::with_arcium_fixtures();
+    assert!(bench.succeeds(
+        &[set_compact_events_ix(bench.admin.pubkey(), true)],
+        &[&bench.admin],
+    ));
+    let mut stored = record(distinct_ciphertexts(14));
+    stored.sender_pub_key = EncryptionKeypair::generate().public_key();
+    let patient = bench.patient(&stored);
+    let patient_data = patient_data_address(&patient.pubkey());
+    let mismatch = u32::from(ErrorCode::SharedRecordMismatch);
+    let mut runner = TestRunner::new(Config::with_cases(CASES));
+    let strategy = (
+        any::<[u8; 16]>(),
+        any::<u64>(),
+        any::<u128>(),
+        ciphertexts(),
+    );
+    runner
+        .run(
+            &strategy,
+            |(receiver_nonce, computation_offset, output_nonce, output)| {
+                let receiver = EncryptionKeypair::generate().public_key();
+                let share = ShareBuilder::new(
+                    patient.pubkey(),
+                    patient.pubkey(),
+                    stored.sender_pub_key,
+                    stored.nonce,
+                    receiver,
+                    receiver_nonce,
+                );
+                prop_assert_eq!(
+                    bench.send(
+                        &[
+                            bench.fund_ix(&patient),
+                            share.build(computation_offset, DEVNET_CLUSTER_OFFSET),
+                        ],
+                        &[&patient],
+                    ),
+                    Err(TransactionError::InstructionError(
+                        1,
+                        InstructionError::Custom(mismatch)
+                    ))
+                );
+                check(
+                    bench.succeeds(
+                        &[
+                            bench.fund_ix(&patient),
+                            share
+                                .compact()
+                                .build(computation_offset, DEVNET_CLUSTER_OFFSET),
+                        ],
+                        &[&patient],
+                    ),
+                    "the compact share is queued",
+                )?;
+
+                check(
+                    bench.callback_succeeds(
+                        computation_offset,
+                        compact_share_patient_data_callback_ix(
+                            patient.pubkey(),
+                            patient_data,
+                            receiver,
+                            computation_offset,
+                            share_patient_data_success(receiver, output_nonce, output),
+                        ),
+                    ),
+                    "the callback settles the share",
+                )?;
+                let shared_record: Option<SharedRecord> =
+                    bench.read(shared_record_address(&patient_data, &receiver));
+                let receipt: Option<ShareReceipt> =
+                    bench.read(share_receipt_address(&patient_data, computation_offset));
+                check(
+                    shared_record.zip(receipt).is_some_and(|(shared, receipt)| {
+                        shared.computation_offset == computation_offset
+                            && shared.ciphertexts == output
+                            && shared.output_hash() == receipt.output_hash
+                    }),
+                    "the callback writes the output its receipt commits to",
+                )
+            },
+        )
+        .unwrap();
+}
+
+#[test]
+#[ignore = "requires Arcium fixtures"]
+fn staged_shares_queue_once_for_their_receiver() {
+    let bench = Bench::with_arcium_fixtures();
+    let mut stored = record(distinct_ciphertexts(11));
+    stored.sender_pub_key = EncryptionKeypair::generate().public_key();
+    let patient = bench.patient(&stored);
+    let mismatch = u32::from(ErrorCode::StagedShareMismatch);
+    let mut runner = TestRunner::new(Config::with_cases(CASES));
+    let strategy = (any::<[u8; 16]>(), any::<u64>());
+    runner
+        .run(&strategy, |(receiver_nonce, computation_offset)| {
+            let receiver = EncryptionKeypair::generate().public_key();
+            let other = EncryptionKeypair::generate().public_key();
+            let staged = staged_share_address(&patient.pubkey(), computation_offset);
+            let execute = |receiver| {
+                execute_share_ix(
+                    patient.pubkey(),
+                    patient.pubkey(),
+                    receiver,
+                    computation_offset,
+                    DEVNET_CLUSTER_OFFSET,
+                )
+            };
+
+            check(
+                bench.succeeds(
+                    &[
+                        bench.fund_ix(&patient),
+                        prepare_share_ix(
+                            patient.pubkey(),
+                            patient.pubkey(),
+                            stored.sender_pub_key,
+                            stored.nonce,
+                            receiver,
+                            receiver_nonce,
+                            computation_offset,
+                            None,
+                        ),
+                    ],
+                    &[&patient],
+                ),
+                "the share is staged",
+            )?;
+            prop_assert_eq!(
+                bench.send(&[execute(other)], &[&patient]),
+                Err(TransactionError::InstructionError(
+                    0,
+                    InstructionError::Custom(mismatch)
+                ))
+            );
+            check(
+                bench.succeeds(&[execute(receiver)], &[&patient]),
+                "the staged share is queued for its receiver",
+            )?;
+            check(
+                bench.read::<StagedShare>(staged).is_none(),
+                "queueing closes the staged share",
+            )?;
+            check(
+                !bench.succeeds(&[execute(receiver)], &[&patient]),
+                "a staged share is queued once",
+            )?;
+            check(
+                bench.callback_succeeds(
+                    computation_offset,
+                    share_patient_data_callback_ix(
+                        patient.pubkey(),
+                        patient_data_address(&patient.pubkey()),
+                        receiver,
+                        computation_offset,
+                        share_patient_data_success(receiver, 0, [[0; 32]; 11]),
+                    ),
+                ),
+                "the callback settles the staged share",
+            )
+        })
+        .unwrap();
+}
+
+#[test]
+#[ignore = "requires Arcium fixtures"]
+fn shares_staged_before_a_key_rotation_are_rejected() {
+    let bench = Bench::with_arcium_fixtures();
+    let mut stored = record(distinct_ciphertexts(16));
+    stored.sender_pub_key = EncryptionKeypair::generate().public_key();
+    let patient = bench.patient(&stored);
+    let registrar = Keypair::new();
+    let provider = Keypair::new();
+    assert!(bench.succeeds(
+        &[
+            bench.fund_ix(&registrar),
+            bench.fund_ix(&provider),
+            set_registrars_ix(bench.admin.pubkey(), vec![registrar.pubkey()]),
+            register_provider_ix(
+                registrar.pubkey(),
+                provider.pubkey(),
+                ProviderRole::Physician
+            ),
+        ],
+        &[&bench.admin, &registrar],
+    ));
+    assert!(bench.succeeds(
+        &[grant_consent_ix(
+            patient.pubkey(),
+            provider.pubkey(),
+            CONSENT_SCOPE_PATIENT_DATA,
+            bench.now() + 86_400,
+        )],
+        &[&patient],
+    ));
+    let first_key = EncryptionKeypair::generate().public_key();
+    assert!(bench.succeeds(
+        &[attest_provider_enc_key_ix(
+            registrar.pubkey(),
+            provider.pubkey(),
+            first_key,
+        )],
+        &[&registrar],
+    ));
+    let custom = |code: ErrorCode| {
+        Err(TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(u32::from(code)),
+        ))
+    };
+    assert_eq!(
+        bench.send(
+            &[attest_provider_enc_key_ix(
+                registrar.pubkey(),
+                provider.pubkey(),
+                EncryptionKeypair::generate().public_key(),
+            )],
+            &[&registrar],
+        ),
+        custom(ErrorCode::EncKeyAlreadyAttested),
+        "an attested key is only replaced by rotating it"
+    );
+
+    let current_key = RefCell::new(first_key);
+    let mut runner = TestRunner::new(Config::with_cases(CASES));
+    let strategy = (any::<[u8; 16]>(), any::<u64>());
+    runner
+        .run(&strategy, |(receiver_nonce, computation_offset)| {
+            let receiver = *current_key.borrow();
+            let prepare = |receiver, computation_offset| {
+                prepare_share_ix(
+                    provider.pubkey(),
+                    patient.pubkey(),
+                    stored.sender_pub_key,
+                    stored.nonce,
+                    receiver,
+                    receiver_nonce,
+                    computation_offset,
+                    None,
+                )
+            };
+            let execute = |receiver, computation_offset| {
+                execute_share_ix(
+                    provider.pubkey(),
+                    patient.pubkey(),
+                    receiver,
+                    computation_offset,
+                    DEVNET_CLUSTER_OFFSET,
+                )
+            };
+            check(
+                bench.succeeds(
+                    &[
+                        bench.fund_ix(&provider),
+                        prepare(receiver, computation_offset),
+                    ],
+                    &[&provider],
+                ),
+                "the share is staged for the attested key",
+            )?;
+            prop_assert_eq!(
+                bench.send(
+                    &[rotate_provider_enc_key_ix(
+                        registrar.pubkey(),
+                        provider.pubkey(),
+                        receiver,
+                    )],
+                    &[&registrar],
+                ),
+                custom(ErrorCode::InvalidKeyRotation)
+            );
+            let rotated = EncryptionKeypair::generate().public_key();
+            check(
+                bench.succeeds(
+                    &[rotate_provider_enc_key_ix(
+                        registrar.pubkey(),
+                        provider.pubkey(),
+                        rotated,
+                    )],
+                    &[&registrar],
+                ),
+                "the registrar rotates the key",
+            )?;
+            *current_key.borrow_mut() = rotated;
+
+            prop_assert_eq!(
+                bench.send(&[execute(receiver, computation_offset)], &[&provider]),
+                custom(ErrorCode::ProviderKeyRotated)
+            );
+            check(
+                bench.succeeds(
+                    &[discard_staged_share_ix(
+                        provider.pubkey(),
+                        computation_offset,
+                    )],
+                    &[&provider],
+                ),
+                "the stale staged share is discarded",
+            )?;
+
+            let restaged = computation_offset.wrapping_add(1);
+            check(
+                bench.succeeds(
+                    &[prepare(rotated, restaged), execute(rotated, restaged)],
+                    &[&provider],
+                ),
+                "a share staged for the rotated key is queued",
+            )?;
+            check(
+                bench.callback_succeeds(
+                    restaged,
+                    share_patient_data_callback_ix(
+                        provider.pubkey(),
+                        patient_data_address(&patient.pubkey()),
+                        rotated,
+                        restaged,
+                        share_patient_data_success(rotated, 0, [[0; 32]; 11]),
+                    ),
+                ),
+                "the callback settles the share",
+            )
+        })
+        .unwrap();
+}
+
+#[test]
+fn budgeted_shares_request_their_limit_at_the_chosen_price() {
+    let mut runner = TestRunner::new(Config::with_cases(CASES));
+    // Recent prioritization fees, the percentile and cap, and whether a fee is paid
+    let strategy = (
+        prop::collection::vec(0u64..10_000_000, 0..150),
+        0u8..=100,
+        1u64..,
+        any::<bool>(),
+    );
+    runner
+        .run(
+            &strategy,
+            |(recent_fees, percentile, max_micro_lamports, pays_fee)| {
+                let fee = PriorityFee::Percentile {
+                    percentile,
+                    max_micro_lamports,
+                };
+                let micro_lamports = fee.micro_lamports(&recent_fees);
+                prop_assert!(micro_lamports <= max_micro_lamports);
+                prop_assert!(
+                    recent_fees.contains(&micro_lamports)
+                        || micro_lamports == max_micro_lamports
+                        || recent_fees.is_empty() && micro_lamports == 0
+                );
+
+                let mut share = ShareBuilder::new(
+                    Pubkey::new_unique(),
+                    Pubkey::new_unique(),
+                    [1; 32],
+                    [2; 16],
+                    [3; 32],
+                    [4; 16],
+                )
+                .priority_fee(fee);
+                if pays_fee {
+                    share = share.access_fee(ShareFee {
+                        mint: Pubkey::new_unique(),
+                        payer_fee_account: Pubkey::new_unique(),
+                        patient_fee_account: Pubkey::new_unique(),
+                        token_program: Pubkey::new_unique(),
+                    });
+                }
+                let ixs = share.build_with_budget(7, DEVNET_CLUSTER_OFFSET, &recent_fees);
+                let mut expected = vec![ComputeBudgetInstruction::set_compute_unit_limit(
+                    share_compute_units(pays_fee),
+                )];
+                if micro_lamports > 0 {
+                    expected.push(ComputeBudgetInstruction::set_compute_unit_price(
+                        micro_lamports,
+                    ));
+                }
+                expected.push(share.build(7, DEVNET_CLUSTER_OFFSET));
+                prop_assert_eq!(ixs, expected);
+                Ok(())
+            },
+        )
+        .unwrap();
+}
+
+#[test]
+#[ignore = "requires Arcium fixtures"]
+fn shares_fit_their_compute_unit_limit() {
+    let bench = Bench::with_arcium_fixtures();
+    let mut stored = record(distinct_ciphertexts(12));
+    stored.sender_pub_key = EncryptionKeypair::generate().public_key();
+    let patient = bench.patient(&stored);
+    let mut runner = TestRunner::new(Config::with_cases(CASES));
+    // Whether the patient shares their own record or a consented provider does, the
+    // priority fee, the computation offset and a fresh receiver nonce
+    let strategy = (
+        any::<bool>(),
+        0u64..1_000_000,
+        any::<u64>(),
+        any::<[u8; 16]>(),
+    );
+    runner
+        .run(
+            &strategy,
+            |(patient_pays, micro_lamports, computation_offset, receiver_nonce)| {
+                let provider = Keypair::new();
+                let receiver = EncryptionKeypair::generate().public_key();
+                let (payer, share) = if patient_pays {
+                    let share = share_patient_data_ix(
+                        patient.pubkey(),
+                        stored.sender_pub_key,
+                        stored.nonce,
+                        receiver,
+                        receiver_nonce,
+                        computation_offset,
+                        DEVNET_CLUSTER_OFFSET,
+                    );
+                    (&patient, share)
+                } else {
+                    let grant = grant_consent_ix(
+                        patient.pubkey(),
+                        provider.pubkey(),
+                        CONSENT_SCOPE_PATIENT_DATA,
+                        bench.now() + 3_600,
+                    );
+                    check(
+                        bench.succeeds(&[grant], &[&patient]),
+                        "consent setup succeeds",
+                    )?;
+                    let share = share_consented_patient_data_ix(
+                        provider.pubkey(),
+                        patient.pubkey(),
+                        stored.sender_pub_key,
+                        stored.nonce,
+                        receiver,
+                        receiver_nonce,
+                        computation_offset,
+                        DEVNET_CLUSTER_OFFSET,
+                    );
+                    (&provider, share)
+                };
+                check(
+                    bench.succeeds(&[bench.fund_ix(payer)], &[payer]),
+                    "the payer is funded",
+                )?;
+
+                let units = bench.compute_units(
+                    &[
+                        ComputeBudgetInstruction::set_compute_unit_limit(SHARE_COMPUTE_UNIT_LIMIT),
+                        ComputeBudgetInstruction::set_compute_unit_price(micro_lamports),
+                        share,
+                    ],
+                    &[payer],
+                );
+                check(
+                    units.is_some_and(|units| units <= u64::from(SHARE_COMPUTE_UNIT_LIMIT)),
+                    "the share is queued within its compute unit limit",
+                )?;
+                check(
+                    bench.callback_succeeds(
+                        computation_offset,
+                        share_patient_data_callback_ix(
+                            payer.pubkey(),
+                            patient_data_address(&patient.pubkey()),
+                            receiver,
+                            computation_offset,
+                            share_patient_data_success(receiver, 0, [[0; 32]; 11]),
+                        ),
+                    ),
+                    "the callback settles the share",
+                )
+            },
+        )
+        .unwrap();
+}
+
+#[test]
+#[ignore = "requires Arcium fixtures"]
+fn payers_are_capped_at_their_in_flight_computations() {
+    let bench = Bench::with_arcium_fixtures();
+    let capped = u32::from(ErrorCode::TooManyInFlightComputations);
+    let mut runner = TestRunner::new(Config::with_cases(8));
+    runner
+        .run(&any::<u64>(), |computation_offset| {
+            let mut stored = record(distinct_ciphertexts(9));
+            stored.sender_pub_key = EncryptionKeypair::generate().public_key();
+            let patient = bench.patient(&stored);
+            let share = |receiver, index: u64| {
+                share_patient_data_ix(
+                    patient.pubkey(),
+                    stored.sender_pub_key,
+                    stored.nonce,
+                    receiver,
+                    index.to_le_bytes().repeat(2).try_into().unwrap(),
+                    computation_offset.wrapping_add(index),
+                    DEVNET_CLUSTER_OFFSET,
+                )
+            };
+            let receivers: Vec<[u8; 32]> = (0..=DEFAULT_MAX_IN_FLIGHT_PER_PAYER)
+                .map(|_| EncryptionKeypair::generate().public_key())
+                .collect();
+
+            for (index, receiver) in receivers[1..].iter().enumerate() {
+                check(
+                    bench.succeeds(
+                        &[bench.fund_ix(&patient), share(*receiver, index as u64 + 1)],
+                        &[&patient],
+                    ),
+                    "shares up to the cap are queued",
+                )?;
+            }
+            prop_assert_eq!(
+                bench.send(&[share(receivers[0], 0)], &[&patient]),
+                Err(TransactionError::InstructionError(
+                    0,
+                    InstructionError::Custom(capped)
+                ))
+            );
+            check(
+                bench.callback_succeeds(
+                    computation_offset.wrapping_add(1),
+                    share_patient_data_callback_ix(
+                        patient.pubkey(),
+                        patient_data_address(&patient.pubkey()),
+                        receivers[1],
+                        computation_offset.wrapping_add(1),
+                        share_patient_data_success(receivers[1], 0, [[0; 32]; 11]),
+                    ),
+                ),
+                "the callback settles a share",
+            )?;
+            check(
+                bench.succeeds(&[share(receivers[0], 0)], &[&patient]),
+                "a settled share frees its slot",
+            )
+        })
+        .unwrap();
+}
+
+#[test]
+#[ignore = "requires Arcium fixtures"]
+fn non_share_callbacks_free_their_in_flight_slot() {
+    let bench = Bench::with_circuit_stand_ins(&["preview_share"]);
+    let capped = u32::from(ErrorCode::TooManyInFlightComputations);
+    let mut runner = TestRunner::new(Config::with_cases(8));
+    runner
+        .run(&any::<u64>(), |computation_offset| {
+            let stored = record(distinct_ciphertexts(21));
+            let patient = bench.patient(&stored);
+            let preview = |index: u64| {
+                preview_share_ix(
+                    patient.pubkey(),
+                    stored.nonce,
+                    index.to_le_bytes().repeat(2).try_into().unwrap(),
+                    PATIENT_FIELDS_ALL,
+                    computation_offset.wrapping_add(index),
+                    DEVNET_CLUSTER_OFFSET,
+                )
+            };
+
+            for index in 1..=u64::from(DEFAULT_MAX_IN_FLIGHT_PER_PAYER) {
+                check(
+                    bench.succeeds(&[bench.fund_ix(&patient), preview(index)], &[&patient]),
+                    "previews up to the cap are queued",
+                )?;
+            }
+            prop_assert_eq!(
+                bench.send(&[preview(0)], &[&patient]),
+                Err(TransactionError::InstructionError(
+                    0,
+                    InstructionError::Custom(capped)
+                ))
+            );
+            check(
+                bench.callback_succeeds(
+                    computation_offset.wrapping_add(1),
+                    preview_share_callback_ix(
+                        patient.pubkey(),
+                        computation_offset.wrapping_add(1),
+                        preview_share_success(stored.sender_pub_key, 0, [[0; 32]; 11]),
+                    ),
+                ),
+                "the callback delivers a preview",
+            )?;
+            check(
+                bench.succeeds(&[preview(0)], &[&patient]),
+                "a delivered preview frees its slot",
+            )
+        })
+        .unwrap();
+}
+
+#[test]
+#[ignore = "requires Arcium fixtures"]
+fn circuits_are_retired_only_once_nothing_is_pending() {
+    let bench = Bench::with_circuit_stand_ins(&["preview_share"]);
+    let comp_def_offset = comp_def_offset_of("preview_share");
+    let stored = record(distinct_ciphertexts(22));
+    let patient = bench.patient(&stored);
+    let preview = |computation_offset: u64| {
+        bench.send(
+            &[
+                bench.fund_ix(&patient),
+                preview_share_ix(
+                    patient.pubkey(),
+                    stored.nonce,
+                    [7; 16],
+                    PATIENT_FIELDS_ALL,
+                    computation_offset,
+                    DEVNET_CLUSTER_OFFSET,
+                ),
+            ],
+            &[&patient],
+        )
+    };
+    let retire = || {
+        bench.send(
+            &[retire_comp_def_ix(bench.admin.pubkey(), comp_def_offset)],
+            &[&bench.admin],
+        )
+    };
+
+    assert_eq!(preview(1), Ok(()));
+    assert_eq!(
+        retire(),
+        Err(TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(u32::from(ErrorCode::CircuitInUse))
+        ))
+    );
+    assert!(bench.callback_succeeds(
+        1,
+        preview_share_callback_ix(
+            patient.pubkey(),
+            1,
+            preview_share_success(stored.sender_pub_key, 0, [[0; 32]; 11]),
+        ),
+    ));
+    assert_eq!(retire(), Ok(()));
+    assert_eq!(
+        preview(2),
+        Err(TransactionError::InstructionError(
+            1,
+            InstructionError::Custom(u32::from(ErrorCode::CircuitVersionRetired))
+        ))
+    );
+}
+
+#[test]
+#[ignore = "requires Arcium fixtures"]
+fn records_are_frozen_while_shares_are_in_flight() {
+    let bench = Bench::with_arcium_fixtures();
+    let mut stored = record(distinct_ciphertexts(8));
+    stored.sender_pub_key = EncryptionKeypair::generate().public_key();
+    let patient = bench.patient(&stored);
+    let frozen = u32::from(ErrorCode::RecordFrozen);
+    let mut runner = TestRunner::new(Config::with_cases(CASES));
+    let strategy = (ciphertexts(), any::<[u8; 16]>(), any::<u64>());
+    runner
+        .run(
+            &strategy,
+            |(ciphertexts, receiver_nonce, computation_offset)| {
+                let receiver = EncryptionKeypair::generate().public_key();
+                // Keeps the key shares pass, so later cases can share the updated record
+                let mut update = record(ciphertexts);
+                update.sender_pub_key = stored.sender_pub_key;
+                let update = update_patient_data_ix(patient.pubkey(), &update);
+
+                check(
+                    bench.succeeds(
+                        &[
+                            bench.fund_ix(&patient),
+                            share_patient_data_ix(
+                                patient.pubkey(),
+                                stored.sender_pub_key,
+                                stored.nonce,
+                                receiver,
+                                receiver_nonce,
+                                computation_offset,
+                                DEVNET_CLUSTER_OFFSET,
+                            ),
+                        ],
+                        &[&patient],
+                    ),
+                    "the share is queued",
+                )?;
+                prop_assert_eq!(
+                    bench.send(&[update.clone()], &[&patient]),
+                    Err(TransactionError::InstructionError(
+                        0,
+                        InstructionError::Custom(frozen)
+                    ))
+                );
+                prop_assert_eq!(
+                    bench.send(&[tombstone_patient_data_ix(patient.pubkey())], &[&patient]),
+                    Err(TransactionError::InstructionError(
+                        0,
+                        InstructionError::Custom(frozen)
+                    ))
+                );
+                check(
+                    bench.callback_succeeds(
+                        computation_offset,
+                        share_patient_data_callback_ix(
+                            patient.pubkey(),
+                            patient_data_address(&patient.pubkey()),
+                            receiver,
+                            computation_offset,
+                            share_patient_data_success(receiver, 0, [[0; 32]; 11]),
+                        ),
+                    ),
+                    "the callback settles the share",
+                )?;
+                check(
+                    bench.succeeds(&[update], &[&patient]),
+                    "the record can be updated once the share settled",
+                )
+            },
+        )
+        .unwrap();
+}
+
+#[test]
+#[ignore = "requires Arcium fixtures"]
+fn share_codes_redeem_once_for_the_provider_the_code_signed_for() {
+    let bench = Bench::with_circuit_stand_ins(&["redeem_share_code"]);
+    let stored = record(distinct_ciphertexts(17));
+    let patient = bench.patient(&stored);
+    let patient_data = patient_data_address(&patient.pubkey());
+    let (provider, enc_key) = bench.provider(ProviderRole::Physician);
+    let (rival, rival_enc_key) = bench.provider(ProviderRole::Physician);
+    let custom = |index: u8, code: ErrorCode| {
+        Err(TransactionError::InstructionError(
+            index,
+            InstructionError::Custom(u32::from(code)),
+        ))
+    };
+    let mut runner = TestRunner::new(Config::with_cases(CASES));
+    let strategy = (any::<[u8; 32]>(), any::<[u8; 16]>(), any::<u64>());
+    runner
+        .run(&strategy, |(code, provider_nonce, computation_offset)| {
+            let code_key = keypair_from_seed(&code).unwrap();
+            let signed_by = |signer: &Keypair, provider: &Keypair, enc_key: &[u8; 32]| {
+                let message = share_code_redemption_message(&provider.pubkey(), enc_key);
+                new_ed25519_instruction_with_signature(
+                    &message,
+                    &signer.sign_message(&message).into(),
+                    &signer.pubkey().to_bytes(),
+                )
+            };
+            let redeem = |provider: &Keypair, computation_offset: u64| {
+                redeem_share_code_ix(
+                    provider.pubkey(),
+                    code_key.pubkey(),
+                    patient_data,
+                    provider_nonce,
+                    computation_offset,
+                    DEVNET_CLUSTER_OFFSET,
+                )
+            };
+            check(
+                bench.succeeds(
+                    &[
+                        bench.fund_ix(&patient),
+                        create_share_code_ix(
+                            patient.pubkey(),
+                            code_key.pubkey(),
+                            PATIENT_FIELDS_ALL,
+                            stored.nonce,
+                            bench.now() + MAX_SHARE_CODE_SECONDS,
+                        ),
+                    ],
+                    &[&patient],
+                ),
+                "the patient creates the code",
+            )?;
+
+            prop_assert_eq!(
+                bench.send(&[redeem(&provider, computation_offset)], &[&provider]),
+                custom(0, ErrorCode::MissingApprovalSignature)
+            );
+            prop_assert_eq!(
+                bench.send(
+                    &[
+                        signed_by(&patient, &provider, &enc_key),
+                        redeem(&provider, computation_offset),
+                    ],
+                    &[&provider],
+                ),
+                custom(1, ErrorCode::InvalidApprovalSignature)
+            );
+            prop_assert_eq!(
+                bench.send(
+                    &[
+                        signed_by(&code_key, &provider, &enc_key),
+                        redeem(&rival, computation_offset),
+                    ],
+                    &[&rival],
+                ),
+                custom(1, ErrorCode::InvalidApprovalSignature)
+            );
+            check(
+                bench.succeeds(
+                    &[
+                        signed_by(&code_key, &provider, &enc_key),
+                        redeem(&provider, computation_offset),
+                    ],
+                    &[&provider],
+                ),
+                "the code redeems for the provider it signed for",
+            )?;
+            let share_code: OneTimeShareCode =
+                bench.read(share_code_address(&code_key.pubkey())).unwrap();
+            prop_assert_eq!(share_code.redeemed_by, provider.pubkey());
+
+            let next_offset = computation_offset.wrapping_add(1);
+            prop_assert_eq!(
+                bench.send(
+                    &[
+                        signed_by(&code_key, &rival, &rival_enc_key),
+                        redeem(&rival, next_offset),
+                    ],
+                    &[&rival],
+                ),
+                custom(1, ErrorCode::ShareCodeRedeemed)
+            );
+            Ok(())
+        })
+        .unwrap();
+}
+
+#[test]
+fn disclosure_policies_list_each_regulator_and_member_once() {
+    let bench = Bench::new();
+    let admin = bench.admin.pubkey();
+    let mut runner = TestRunner::new(Config::with_cases(CASES));
+    let strategy = (
+        2..=MAX_REGULATORS,
+        2..=MAX_DISCLOSURE_COMMITTEE,
+        any::<prop::sample::Index>(),
+        any::<bool>(),
+    );
+    runner
+        .run(
+            &strategy,
+            |(regulator_count, member_count, repeated, among_regulators)| {
+                let regulators: Vec<Pubkey> =
+                    (0..regulator_count).map(|_| Pubkey::new_unique()).collect();
+                let committee: Vec<Pubkey> =
+                    (0..member_count).map(|_| Pubkey::new_unique()).collect();
+                let set = |regulators: &[Pubkey], committee: &[Pubkey]| {
+                    bench.send(
+                        &[set_disclosure_policy_ix(
+                            admin,
+                            regulators.to_vec(),
+                            committee.to_vec(),
+                            2,
+                            0,
+                        )],
+                        &[&bench.admin],
+                    )
+                };
+                // The last entry of one list replaced by an earlier one
+                let (mut repeated_regulators, mut repeated_committee) =
+                    (regulators.clone(), committee.clone());
+                let list = if among_regulators {
+                    &mut repeated_regulators
+                } else {
+                    &mut repeated_committee
+                };
+                let last = list.len() - 1;
+                list[last] = list[repeated.index(last)];
+
+                prop_assert_eq!(
+                    set(&repeated_regulators, &repeated_committee),
+                    Err(TransactionError::InstructionError(
+                        0,
+                        InstructionError::Custom(u32::from(ErrorCode::InvalidDisclosurePolicy))
+                    ))
+                );
+                prop_assert_eq!(set(&regulators, &committee), Ok(()));
+                Ok(())
+            },
+        )
+        .unwrap();
+}
+
+#[test]
+#[ignore = "requires Arcium fixtures"]
+fn disaster_packets_are_fetched_only_while_declared() {
+    const REGION: u32 = 7;
+    let patient = Keypair::new();
+    let patient_data = patient_data_address(&patient.pubkey());
+    let (packet_address, bump) = Pubkey::find_program_address(
+        &[EMERGENCY_PACKET_SEED, patient_data.as_ref()],
+        &share_medical_records::ID,
+    );
+    let packet = EmergencyPacket {
+        patient_data,
+        region: REGION,
+        receiver_enc_key: EncryptionKeypair::generate().public_key(),
+        nonce: [1; 16],
+        ciphertexts: std::array::from_fn(|i| [i as u8 + 1; 32]),
+        record_version: 0,
+        computation_offset: 1,
+        generated_slot: 0,
+        pending_region: REGION,
+        pending_record_version: 0,
+        bump,
+    };
+    let builder = Harness::builder()
+        .arcium_fixtures(FIXTURES_DIR)
+        .and_then(|builder| {
+            builder.comp_def_copies(FIXTURES_DIR, &[comp_def_offset_of("disaster_packet")])
+        })
+        .unwrap();
+    let bench = Bench::start(builder.program_account(packet_address, &packet));
+    let stored = record(distinct_ciphertexts(18));
+    assert!(bench.succeeds(
+        &[
+            bench.fund_ix(&patient),
+            store_patient_data_ix(patient.pubkey(), patient.pubkey(), &stored),
+        ],
+        &[&patient],
+    ));
+    let (responder, _) = bench.provider(ProviderRole::Physician);
+    let (member, co_signer) = (Keypair::new(), Keypair::new());
+    let admin = bench.admin.pubkey();
+    let custom = |index: u8, code: ErrorCode| {
+        Err(TransactionError::InstructionError(
+            index,
+            InstructionError::Custom(u32::from(code)),
+        ))
+    };
+    assert_eq!(
+        bench.send(
+            &[set_disaster_committee_ix(
+                admin,
+                vec![member.pubkey(), co_signer.pubkey(), member.pubkey()],
+                2,
+            )],
+            &[&bench.admin],
+        ),
+        custom(0, ErrorCode::InvalidDisasterCommittee),
+        "a member listed twice could meet the threshold alone"
+    );
+    assert!(bench.succeeds(
+        &[
+            bench.fund_ix(&member),
+            set_ems_authority_ix(
+                admin,
+                REGION,
+                admin,
+                EncryptionKeypair::generate().public_key()
+            ),
+            set_disaster_committee_ix(admin, vec![member.pubkey(), co_signer.pubkey()], 2),
+        ],
+        &[&bench.admin],
+    ));
+
+    let mut runner = TestRunner::new(Config::with_cases(CASES));
+    let strategy = (
+        1..=MAX_DISASTER_MODE_NOTICE_SECONDS,
+        1..=MAX_DISASTER_MODE_SECONDS,
+        any::<[u8; 16]>(),
+        any::<u64>(),
+    );
+    runner
+        .run(
+            &strategy,
+            |(delay, duration, responder_nonce, computation_offset)| {
+                let declare = |starts_at: i64| {
+                    bench.send(
+                        &[declare_disaster_mode_ix(
+                            member.pubkey(),
+                            &[co_signer.pubkey()],
+                            REGION,
+                            starts_at,
+                            starts_at + duration,
+                        )],
+                        &[&member, &co_signer],
+                    )
+                };
+                let fetch = |computation_offset| {
+                    bench.send(
+                        &[fetch_disaster_packet_ix(
+                            responder.pubkey(),
+                            patient.pubkey(),
+                            REGION,
+                            responder_nonce,
+                            computation_offset,
+                            DEVNET_CLUSTER_OFFSET,
+                        )],
+                        &[&responder],
+                    )
+                };
+                let end =
+                    || bench.succeeds(&[end_disaster_mode_ix(member.pubkey(), REGION)], &[&member]);
+                let now = bench.now();
+
+                prop_assert_eq!(
+                    declare(now + MAX_DISASTER_MODE_NOTICE_SECONDS + delay),
+                    custom(0, ErrorCode::InvalidDisasterWindow)
+                );
+                prop_assert_eq!(declare(now + delay), Ok(()));
+                prop_assert_eq!(
+                    fetch(computation_offset),
+                    custom(0, ErrorCode::DisasterModeInactive)
+                );
+                prop_assert_eq!(
+                    declare(now),
+                    custom(0, ErrorCode::DisasterModeStillDeclared)
+                );
+                check(end(), "a member ends the pending declaration")?;
+
+                prop_assert_eq!(declare(now), Ok(()));
+                prop_assert_eq!(fetch(computation_offset), Ok(()));
+                let disaster_mode: DisasterMode =
+                    bench.read(disaster_mode_address(REGION)).unwrap();
+                prop_assert_eq!(disaster_mode.fetches, 1);
+                prop_assert_eq!(
+                    disaster_mode.declared_by,
+                    vec![member.pubkey(), co_signer.pubkey()]
+                );
+                check(end(), "a member ends the declaration")?;
+                prop_assert_eq!(
+                    fetch(computation_offset.wrapping_add(1)),
+                    custom(0, ErrorCode::DisasterModeInactive)
+                );
+                Ok(())
+            },
+        )
+        .unwrap();
+}
+
+#[test]
+#[ignore = "requires Arcium fixtures"]
+fn genomic_anchors_are_shared_only_with_every_kin_link_consented() {
+    let bench = Bench::with_circuit_stand_ins(&["share_content_key"]);
+    let custom = |index: u8, code: ErrorCode| {
+        Err(TransactionError::InstructionError(
+            index,
+            InstructionError::Custom(u32::from(code)),
+        ))
+    };
+    let mut runner = TestRunner::new(Config::with_cases(CASES));
+    let strategy = (
+        prop_oneof![
+            Just(KinDegree::First),
+            Just(KinDegree::Second),
+            Just(KinDegree::Third)
+        ],
+        any::<[u8; 32]>(),
+        any::<[u8; 16]>(),
+        any::<u64>(),
+    );
+    runner
+        .run(
+            &strategy,
+            |(degree, receiver, receiver_nonce, computation_offset)| {
+                let patient = bench.patient(&record(distinct_ciphertexts(19)));
+                let relative = bench.patient(&record(distinct_ciphertexts(20)));
+                let patient_data = patient_data_address(&patient.pubkey());
+                let relative_data = patient_data_address(&relative.pubkey());
+                check(
+                    bench.succeeds(
+                        &[anchor_fhir_resource_ix(
+                            patient.pubkey(),
+                            0,
+                            "MolecularSequence",
+                            1,
+                            [1; 32],
+                            EncryptedContentKey::new([[2; 32], [3; 32]]),
+                        )],
+                        &[&patient],
+                    ),
+                    "the patient anchors a genomic resource",
+                )?;
+                let share = |relatives: &[Pubkey]| {
+                    bench.send(
+                        &[share_fhir_content_key_ix(
+                            patient.pubkey(),
+                            0,
+                            relatives,
+                            receiver,
+                            receiver_nonce,
+                            [4; 32],
+                            [5; 16],
+                            computation_offset,
+                            DEVNET_CLUSTER_OFFSET,
+                        )],
+                        &[&patient],
+                    )
+                };
+                let unlink = || {
+                    bench.send(
+                        &[unlink_closed_kin_ix(patient.pubkey(), relative.pubkey())],
+                        &[&patient],
+                    )
+                };
+
+                check(
+                    bench.succeeds(
+                        &[link_kin_ix(patient.pubkey(), relative.pubkey(), degree)],
+                        &[&patient, &relative],
+                    ),
+                    "both relatives sign the link",
+                )?;
+                prop_assert_eq!(share(&[]), custom(0, ErrorCode::KinLinksIncomplete));
+                check(
+                    bench.succeeds(
+                        &[set_kin_consent_ix(
+                            relative.pubkey(),
+                            patient.pubkey(),
+                            false,
+                        )],
+                        &[&relative],
+                    ),
+                    "the relative withdraws their consent",
+                )?;
+                prop_assert_eq!(
+                    share(&[relative_data]),
+                    custom(0, ErrorCode::KinConsentMissing)
+                );
+                prop_assert_eq!(unlink(), custom(0, ErrorCode::KinRecordNotClosed));
+
+                check(
+                    bench.succeeds(&[close_patient_data_ix(relative.pubkey())], &[&relative]),
+                    "the relative closes their record",
+                )?;
+                prop_assert_eq!(unlink(), Ok(()));
+                let kin_roster: KinRoster = bench.read(kin_roster_address(&patient_data)).unwrap();
+                prop_assert_eq!(kin_roster.links, 0);
+                prop_assert!(bench
+                    .read::<KinLink>(kin_link_address(&patient_data, &relative_data))
+                    .is_none());
+                prop_assert_eq!(share(&[]), Ok(()));
+                Ok(())
+            },
+        )
+        .unwrap();
+}
+
+#[test]
+fn trial_escrows_pay_confirmed_milestones_and_refund_only_the_rest() {
+    const TRIAL_ID: u64 = 1;
+    let custom = |index: u8, code: ErrorCode| {
+        Err(TransactionError::InstructionError(
+            index,
+            InstructionError::Custom(u32::from(code)),
+        ))
+    };
+    let mut runner = TestRunner::new(Config::with_cases(CASES));
+    // Terms, the treasury cut, milestones confirmed and paid on the claimed enrollment
+    // and on all others, and whether the sponsor closes before the claim
+    let strategy = (
+        1..=MAX_TRIAL_MILESTONES,
+        1u64..1_000_000,
+        1u16..50,
+        0..=MAX_BPS,
+        any::<[u32; 4]>(),
+        any::<bool>(),
+    );
+    runner
+        .run(
+            &strategy,
+            |(milestones, payout, max_enrollments, treasury_bps, raw, close_first)| {
+                let confirmed = (raw[0] % (milestones as u32 + 1)) as u64;
+                let paid = (raw[1] % (confirmed as u32 + 1)) as u64;
+                let others_confirmed =
+                    (raw[2] % ((max_enrollments as u32 - 1) * milestones as u32 + 1)) as u64;
+                let others_paid = (raw[3] % (others_confirmed as u32 + 1)) as u64;
+                let deposit = milestones as u64 * payout * max_enrollments as u64;
+
+                let (sponsor, patient) = (Keypair::new(), Keypair::new());
+                let patient_data = patient_data_address(&patient.pubkey());
+                let mint = Pubkey::new_unique();
+                let trial_escrow = trial_escrow_address(&sponsor.pubkey(), TRIAL_ID);
+                let escrow = TrialEscrow {
+                    sponsor: sponsor.pubkey(),
+                    site: Pubkey::new_unique(),
+                    mint,
+                    trial_id: TRIAL_ID,
+                    milestones,
+                    payout_per_milestone: payout,
+                    max_enrollments,
+                    enrolled: max_enrollments,
+                    unlocked: (confirmed + others_confirmed) * payout,
+                    paid: (paid + others_paid) * payout,
+                    ends_at: 0,
+                    closed: false,
+                    bump: 0,
+                };
+                let trial_enrollment = trial_enrollment_address(&trial_escrow, &patient_data);
+                let enrollment = TrialEnrollment {
+                    trial_escrow,
+                    patient_data,
+                    patient: patient.pubkey(),
+                    enrolled_at: 0,
+                    milestones_confirmed: confirmed as u8,
+                    milestones_paid: paid as u8,
+                    confirmation_head: [0; 32],
+                    bump: program_bump(&[
+                        TRIAL_ENROLLMENT_SEED,
+                        trial_escrow.as_ref(),
+                        patient_data.as_ref(),
+                    ]),
+                };
+                let fee_authority = fee_authority_address();
+                let (sponsor_tokens, patient_tokens, rival_tokens) = (
+                    Pubkey::new_unique(),
+                    Pubkey::new_unique(),
+                    Pubkey::new_unique(),
+                );
+                let bench = Bench::start(
+                    Harness::builder()
+                        .token_mint(mint, 6)
+                        .program_account(trial_escrow, &escrow)
+                        .program_account(trial_enrollment, &enrollment)
+                        .token_account(
+                            trial_vault_address(&trial_escrow),
+                            mint,
+                            fee_authority,
+                            deposit - escrow.paid,
+                        )
+                        .token_account(treasury_address(&mint), mint, fee_authority, 0)
+                        .token_account(sponsor_tokens, mint, sponsor.pubkey(), 0)
+                        .token_account(patient_tokens, mint, patient.pubkey(), 0)
+                        .token_account(rival_tokens, mint, sponsor.pubkey(), 0),
+                );
+                check(
+                    bench.succeeds(
+                        &[set_fee_split_ix(bench.admin.pubkey(), treasury_bps)],
+                        &[&bench.admin],
+                    ),
+                    "the admin sets the treasury cut",
+                )?;
+                let tokens = |address: Pubkey| bench.read::<TokenAccount>(address).unwrap().amount;
+                let claim = |to: Pubkey| {
+                    bench.send(
+                        &[claim_trial_payout_ix(
+                            trial_escrow,
+                            patient_data,
+                            to,
+                            mint,
+                            anchor_spl::token::ID,
+                        )],
+                        &[],
+                    )
+                };
+                let close = |by: &Keypair| {
+                    bench.send(
+                        &[close_trial_escrow_ix(
+                            by.pubkey(),
+                            TRIAL_ID,
+                            sponsor_tokens,
+                            mint,
+                            anchor_spl::token::ID,
+                        )],
+                        &[by],
+                    )
+                };
+                let claimable = (confirmed - paid) * payout;
+                let claimed = if claimable > 0 {
+                    Ok(())
+                } else {
+                    custom(0, ErrorCode::NothingToClaim)
+                };
+
+                prop_assert_eq!(close(&patient), custom(0, ErrorCode::Unauthorized));
+                prop_assert_eq!(
+                    claim(rival_tokens),
+                    Err(TransactionError::InstructionError(
+                        0,
+                        InstructionError::Custom(AnchorErrorCode::ConstraintTokenOwner as u32),
+                    ))
+                );
+                if close_first {
+                    prop_assert_eq!(close(&sponsor), Ok(()));
+                    prop_assert_eq!(claim(patient_tokens), claimed);
+                } else {
+                    prop_assert_eq!(claim(patient_tokens), claimed);
+                    prop_assert_eq!(close(&sponsor), Ok(()));
+                }
+
+                let treasury_cut = claimable * treasury_bps as u64 / MAX_BPS as u64;
+                prop_assert_eq!(tokens(patient_tokens), claimable - treasury_cut);
+                prop_assert_eq!(tokens(treasury_address(&mint)), treasury_cut);
+                prop_assert_eq!(tokens(sponsor_tokens), deposit - escrow.unlocked);
+                prop_assert_eq!(
+                    tokens(trial_vault_address(&trial_escrow)),
+                    (others_confirmed - others_paid) * payout
+                );
+                let escrow: TrialEscrow = bench.read(trial_escrow).unwrap();
+                prop_assert!(escrow.closed);
+                prop_assert_eq!(
+                    escrow.paid,
+                    escrow.unlocked - (others_confirmed - others_paid) * payout
+                );
+                prop_assert_eq!(close(&sponsor), custom(0, ErrorCode::TrialClosed));
+                prop_assert_eq!(claim(patient_tokens), custom(0, ErrorCode::NothingToClaim));
+                Ok(())
+            },
+        )
+        .unwrap();
+}
+
+#[test]
+fn stuck_cohort_queries_refund_the_researcher_after_the_timeout() {
+    let custom = |index: u8, code: ErrorCode| {
+        Err(TransactionError::InstructionError(
+            index,
+            InstructionError::Custom(u32::from(code)),
+        ))
+    };
+    let mut runner = TestRunner::new(Config::with_cases(CASES));
+    let strategy = (1u64..u64::MAX / 2, any::<u64>());
+    runner
+        .run(&strategy, |(amount, computation_offset)| {
+            let researcher = Keypair::new();
+            let mint = Pubkey::new_unique();
+            let fee_authority = fee_authority_address();
+            let (researcher_tokens, rival_tokens) = (Pubkey::new_unique(), Pubkey::new_unique());
+            // A query stuck since genesis and one queued far in the future, which cannot
+            // have timed out yet
+            let mut builder = Harness::builder()
+                .token_mint(mint, 6)
+                .token_account(researcher_tokens, mint, researcher.pubkey(), 0)
+                .token_account(rival_tokens, mint, Pubkey::new_unique(), 0);
+            let queries = [
+                (0, computation_offset),
+                (i64::MAX / 2, computation_offset.wrapping_add(1)),
+            ];
+            builder = builder.program_account(
+                used_offsets_address(&researcher.pubkey()),
+                &in_flight_offsets(researcher.pubkey(), &queries.map(|(_, offset)| offset)),
+            );
+            for (query_id, (queued_at, computation_offset)) in queries.into_iter().enumerate() {
+                let research_escrow =
+                    research_escrow_address(&researcher.pubkey(), query_id as u64);
+                let cohort_snapshot = cohort_snapshot_address(&research_escrow);
+                builder = builder
+                    .program_account(
+                        research_escrow,
+                        &ResearchEscrow {
+                            researcher: researcher.pubkey(),
+                            mint,
+                            query_id: query_id as u64,
+                            amount,
+                            status: EscrowStatus::Pending,
+                            bump: 0,
+                        },
+                    )
+                    .token_account(
+                        research_vault_address(&research_escrow),
+                        mint,
+                        fee_authority,
+                        amount,
+                    )
+                    .program_account(
+                        cohort_snapshot,
+                        &CohortSnapshot {
+                            research_escrow,
+                            researcher: researcher.pubkey(),
+                            query_id: query_id as u64,
+                            computation_offset,
+                            comp_def_offset: comp_def_offset_of("cohort_query"),
+                            circuit_source_hash: [0; 32],
+                            circuit_version: 1,
+                            min_age: 0,
+                            max_age: u8::MAX,
+                            blood_type: u8::MAX,
+                            members: [CohortMember::default(); COHORT_SIZE],
+                            queued_slot: 0,
+                            queued_at,
+                            completed_slot: 0,
+                            result_enc_key: [0; 32],
+                            result_nonce: [0; 16],
+                            result: [0; 32],
+                            bump: program_bump(&[COHORT_SNAPSHOT_SEED, research_escrow.as_ref()]),
+                        },
+                    )
+                    .program_account(
+                        callback_guard_address(computation_offset),
+                        &CallbackGuard {
+                            computation_offset,
+                            comp_def_offset: comp_def_offset_of("cohort_query"),
+                            state: CallbackState::Expected,
+                            bump: program_bump(&[
+                                CALLBACK_GUARD_SEED,
+                                &computation_offset.to_le_bytes(),
+                            ]),
+                        },
+                    );
+            }
+            let bench = Bench::start(builder);
+            let expire = |query_id: u64, to: Pubkey| {
+                let (_, computation_offset) = queries[query_id as usize];
+                bench.send(
+                    &[expire_research_escrow_ix(
+                        research_escrow_address(&researcher.pubkey(), query_id),
+                        researcher.pubkey(),
+                        to,
+                        mint,
+                        anchor_spl::token::ID,
+                        computation_offset,
+                    )],
+                    &[],
+                )
+            };
+            let tokens = |address: Pubkey| bench.read::<TokenAccount>(address).unwrap().amount;
+            let stuck = research_escrow_address(&researcher.pubkey(), 0);
+
+            prop_assert_eq!(
+                expire(1, researcher_tokens),
+                custom(0, ErrorCode::ComputationNotTimedOut)
+            );
+            prop_assert_eq!(
+                expire(0, rival_tokens),
+                Err(TransactionError::InstructionError(
+                    0,
+                    InstructionError::Custom(AnchorErrorCode::ConstraintTokenOwner as u32),
+                ))
+            );
+            prop_assert_eq!(expire(0, researcher_tokens), Ok(()));
+            prop_assert_eq!(tokens(researcher_tokens), amount);
+            let escrow: ResearchEscrow = bench.read(stuck).unwrap();
+            check(
+                escrow.status == EscrowStatus::Refunded,
+                "the expired escrow is refunded",
+            )?;
+            prop_assert!(bench
+                .read::<CohortSnapshot>(cohort_snapshot_address(&stuck))
+                .is_none());
+            check(
+                !holds_slot(&bench, &researcher.pubkey(), computation_offset)
+                    && holds_slot(&bench, &researcher.pubkey(), queries[1].1),
+                "only the expired query frees its in-flight slot",
+            )?;
+            let guard: CallbackGuard = bench
+                .read(callback_guard_address(computation_offset))
+                .unwrap();
+            check(
+                guard.state == CallbackState::Consumed,
+                "a late callback finds its guard consumed",
+            )?;
+            prop_assert_ne!(expire(0, researcher_tokens), Ok(()));
+            Ok(())
+        })
+        .unwrap();
+}
+
+#[test]
+fn stuck_prescription_verifications_reactivate_after_the_timeout() {
+    let custom = |index: u8, code: ErrorCode| {
+        Err(TransactionError::InstructionError(
+            index,
+            InstructionError::Custom(u32::from(code)),
+        ))
+    };
+    let mut runner = TestRunner::new(Config::with_cases(CASES));
+    let strategy = (any::<[u8; 32]>(), any::<u16>(), any::<u64>());
+    runner
+        .run(&strategy, |(refills, dispensed, computation_offset)| {
+            let (patient_data, pharmacy) = (Pubkey::new_unique(), Pubkey::new_unique());
+            // A fill stuck since genesis and one queued far in the future, which cannot
+            // have timed out yet
+            let verifications = [
+                (0, computation_offset),
+                (i64::MAX / 2, computation_offset.wrapping_add(1)),
+            ];
+            let mut builder = Harness::builder().program_account(
+                used_offsets_address(&pharmacy),
+                &in_flight_offsets(pharmacy, &verifications.map(|(_, offset)| offset)),
+            );
+            for (prescription_id, (verifying_since, computation_offset)) in
+                verifications.into_iter().enumerate()
+            {
+                let prescription_id = prescription_id as u64;
+                builder = builder
+                    .program_account(
+                        prescription_address(&patient_data, prescription_id),
+                        &Prescription {
+                            refills,
+                            refills_nonce: 1,
+                            patient_data,
+                            prescriber: Pubkey::new_unique(),
+                            prescription_id,
+                            status: PrescriptionStatus::Verifying,
+                            pharmacy,
+                            dispensed,
+                            computation_offset,
+                            verifying_since,
+                            bump: program_bump(&[
+                                PRESCRIPTION_SEED,
+                                patient_data.as_ref(),
+                                &prescription_id.to_le_bytes(),
+                            ]),
+                        },
+                    )
+                    .program_account(
+                        callback_guard_address(computation_offset),
+                        &CallbackGuard {
+                            computation_offset,
+                            comp_def_offset: comp_def_offset_of("verify_prescription"),
+                            state: CallbackState::Expected,
+                            bump: program_bump(&[
+                                CALLBACK_GUARD_SEED,
+                                &computation_offset.to_le_bytes(),
+                            ]),
+                        },
+                    );
+            }
+            let bench = Bench::start(builder);
+            let expire = |prescription_id: u64| {
+                let (_, computation_offset) = verifications[prescription_id as usize];
+                bench.send(
+                    &[expire_prescription_verification_ix(
+                        prescription_address(&patient_data, prescription_id),
+                        pharmacy,
+                        computation_offset,
+                    )],
+                    &[],
+                )
+            };
+
+            prop_assert_eq!(expire(1), custom(0, ErrorCode::ComputationNotTimedOut));
+            prop_assert_eq!(expire(0), Ok(()));
+            let prescription: Prescription =
+                bench.read(prescription_address(&patient_data, 0)).unwrap();
+            check(
+                prescription.status == PrescriptionStatus::Active,
+                "the expired prescription is active again",
+            )?;
+            prop_assert_eq!(prescription.pharmacy, Pubkey::default());
+            prop_assert_eq!(prescription.refills, refills);
+            prop_assert_eq!(prescription.dispensed, dispensed);
+            check(
+                !holds_slot(&bench, &pharmacy, computation_offset)
+                    && holds_slot(&bench, &pharmacy, verifications[1].1),
+                "only the expired fill frees its in-flight slot",
+            )?;
+            let guard: CallbackGuard = bench
+                .read(callback_guard_address(computation_offset))
+                .unwrap();
+            check(
+                guard.state == CallbackState::Consumed,
+                "a late callback finds its guard consumed",
+            )?;
+            prop_assert_eq!(expire(0), custom(0, ErrorCode::PrescriptionNotVerifying));
+            Ok(())
+        })
+        .unwrap();
+}
+
+#[test]
+fn stuck_prior_auth_validations_return_to_pending_after_the_timeout() {
+    let custom = |index: u8, code: ErrorCode| {
+        Err(TransactionError::InstructionError(
+            index,
+            InstructionError::Custom(u32::from(code)),
+        ))
+    };
+    let mut runner = TestRunner::new(Config::with_cases(CASES));
+    let strategy = (any::<[u8; 32]>(), any::<[u8; 32]>(), any::<u64>());
+    runner
+        .run(&strategy, |(diagnosis, procedure, computation_offset)| {
+            let (provider, insurer) = (Pubkey::new_unique(), Pubkey::new_unique());
+            // A validation stuck since genesis and one queued far in the future, which
+            // cannot have timed out yet
+            let validations = [
+                (0, computation_offset),
+                (i64::MAX / 2, computation_offset.wrapping_add(1)),
+            ];
+            let mut builder = Harness::builder().program_account(
+                used_offsets_address(&insurer),
+                &in_flight_offsets(insurer, &validations.map(|(_, offset)| offset)),
+            );
+            for (request_id, (validating_since, computation_offset)) in
+                validations.into_iter().enumerate()
+            {
+                let request_id = request_id as u64;
+                builder = builder
+                    .program_account(
+                        prior_auth_address(&provider, request_id),
+                        &PriorAuthRequest {
+                            diagnosis,
+                            procedure,
+                            provider,
+                            insurer,
+                            patient_data: Pubkey::new_unique(),
+                            request_id,
+                            enc_pubkey: [9; 32],
+                            nonce: 1,
+                            status: PriorAuthStatus::Validating,
+                            computation_offset,
+                            validating_since,
+                            decided_at: 0,
+                            bump: program_bump(&[
+                                PRIOR_AUTH_SEED,
+                                provider.as_ref(),
+                                &request_id.to_le_bytes(),
+                            ]),
+                        },
+                    )
+                    .program_account(
+                        callback_guard_address(computation_offset),
+                        &CallbackGuard {
+                            computation_offset,
+                            comp_def_offset: comp_def_offset_of("validate_prior_auth"),
+                            state: CallbackState::Expected,
+                            bump: program_bump(&[
+                                CALLBACK_GUARD_SEED,
+                                &computation_offset.to_le_bytes(),
+                            ]),
+                        },
+                    );
+            }
+            let bench = Bench::start(builder);
+            let expire = |request_id: u64| {
+                let (_, computation_offset) = validations[request_id as usize];
+                bench.send(
+                    &[expire_prior_auth_validation_ix(
+                        prior_auth_address(&provider, request_id),
+                        insurer,
+                        computation_offset,
+                    )],
+                    &[],
+                )
+            };
+
+            prop_assert_eq!(expire(1), custom(0, ErrorCode::ComputationNotTimedOut));
+            prop_assert_eq!(expire(0), Ok(()));
+            let prior_auth: PriorAuthRequest =
+                bench.read(prior_auth_address(&provider, 0)).unwrap();
+            check(
+                prior_auth.status == PriorAuthStatus::Pending,
+                "the expired request is pending again",
+            )?;
+            prop_assert_eq!(prior_auth.diagnosis, diagnosis);
+            prop_assert_eq!(prior_auth.procedure, procedure);
+            prop_assert_eq!(prior_auth.decided_at, 0);
+            check(
+                !holds_slot(&bench, &insurer, computation_offset)
+                    && holds_slot(&bench, &insurer, validations[1].1),
+                "only the expired validation frees its in-flight slot",
+            )?;
+            let guard: CallbackGuard = bench
+                .read(callback_guard_address(computation_offset))
+                .unwrap();
+            check(
+                guard.state == CallbackState::Consumed,
+                "a late callback finds its guard consumed",
+            )?;
+            prop_assert_eq!(expire(0), custom(0, ErrorCode::PriorAuthNotValidating));
+            Ok(())
+        })
+        .unwrap();
+}
+
+#[test]
+fn lab_results_need_a_laboratory_holding_a_clinical_messages_grant() {
+    let bench = Bench::new();
+    let custom = |code: ErrorCode| {
+        Err(TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(u32::from(code)),
+        ))
+    };
+    let patient = bench.patient(&record(distinct_ciphertexts(23)));
+    let patient_data = patient_data_address(&patient.pubkey());
+    let enc_pubkey = EncryptionKeypair::generate().public_key();
+    let (physician, _) = bench.provider(ProviderRole::Physician);
+    assert!(bench.succeeds(
+        &[grant_consent_ix(
+            patient.pubkey(),
+            physician.pubkey(),
+            CONSENT_SCOPE_ALL,
+            bench.now() + 3_600,
+        )],
+        &[&patient],
+    ));
+    assert_eq!(
+        bench.send(
+            &[submit_lab_result_ix(
+                physician.pubkey(),
+                patient_data,
+                0,
+                [[1; 32]; LAB_RESULT_VALUES],
+                enc_pubkey,
+                1,
+            )],
+            &[&physician],
+        ),
+        custom(ErrorCode::Unauthorized),
+        "only registered laboratories submit results"
+    );
+
+    let mut runner = TestRunner::new(Config::with_cases(CASES));
+    // Values, nonce and result id of the submission, the scope granted to the lab and
+    // whether it holds a grant at all
+    let strategy = (
+        prop::array::uniform4(prop::array::uniform32(any::<u8>())),
+        any::<u128>(),
+        any::<u64>(),
+        1..=CONSENT_SCOPE_ALL,
+        any::<bool>(),
+    );
+    runner
+        .run(&strategy, |(values, nonce, result_id, scope, granted)| {
+            let (lab, _) = bench.provider(ProviderRole::Laboratory);
+            if granted {
+                check(
+                    bench.succeeds(
+                        &[grant_consent_ix(
+                            patient.pubkey(),
+                            lab.pubkey(),
+                            scope,
+                            bench.now() + 3_600,
+                        )],
+                        &[&patient],
+                    ),
+                    "the patient grants the lab",
+                )?;
+            }
+            let registry_before: PatientRegistry =
+                bench.read(patient_registry_address(&patient_data)).unwrap();
+            let submitted = bench.send(
+                &[submit_lab_result_ix(
+                    lab.pubkey(),
+                    patient_data,
+                    result_id,
+                    values,
+                    enc_pubkey,
+                    nonce,
+                )],
+                &[&lab],
+            );
+
+            if !granted {
+                prop_assert_eq!(
+                    submitted,
+                    Err(TransactionError::InstructionError(
+                        0,
+                        InstructionError::Custom(AnchorErrorCode::AccountNotInitialized as u32),
+                    ))
+                );
+                return Ok(());
+            }
+            if scope & CONSENT_SCOPE_CLINICAL_MESSAGES == 0 {
+                prop_assert_eq!(submitted, custom(ErrorCode::ConsentPurposeNotCovered));
+                return Ok(());
+            }
+            prop_assert_eq!(submitted, Ok(()));
+            let result: LabResult = bench
+                .read(lab_result_address(&patient_data, &lab.pubkey(), result_id))
+                .unwrap();
+            prop_assert_eq!(result.values, values);
+            prop_assert_eq!(result.nonce, nonce);
+            prop_assert_eq!(result.lab, lab.pubkey());
+            prop_assert_eq!(result.patient_data, patient_data);
+            prop_assert_eq!(result.sequence, registry_before.entries);
+            let registry: PatientRegistry =
+                bench.read(patient_registry_address(&patient_data)).unwrap();
+            check(
+                registry.entries == registry_before.entries + 1
+                    && registry.head
+                        == hashv(&[
+                            &registry_before.head,
+                            &lab_result_hash(&enc_pubkey, nonce, &values),
+                        ])
+                        .to_bytes(),
+                "the result's hash is chained onto the registry",
+            )
+        })
+        .unwrap();
+}
+
+#[test]
+#[ignore = "requires Arcium fixtures"]
+fn cohort_queries_snapshot_their_members_and_result() {
+    let custom = |code: ErrorCode| {
+        Err(TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(u32::from(code)),
+        ))
+    };
+    let mut runner = TestRunner::new(Config::with_cases(CASES));
+    // The filter, the escrowed amount, the member that has not opted in, if any, and the
+    // encrypted count the cluster returns
+    let strategy = (
+        any::<[u8; 3]>(),
+        1u64..1_000_000,
+        any::<u64>(),
+        prop::option::of(0..COHORT_SIZE),
+        any::<[u8; 32]>(),
+    );
+    runner
+        .run(
+            &strategy,
+            |([min_age, max_age, blood_type], amount, computation_offset, opted_out, count)| {
+                let researcher = Keypair::new();
+                let patients: [Keypair; COHORT_SIZE] = std::array::from_fn(|_| Keypair::new());
+                let records: [EncryptedPatientRecord; COHORT_SIZE] =
+                    std::array::from_fn(|i| record(distinct_ciphertexts(24 + i as u8)));
+                let mint = Pubkey::new_unique();
+                let research_escrow = research_escrow_address(&researcher.pubkey(), 0);
+                let researcher_tokens = Pubkey::new_unique();
+                let payouts: [Pubkey; COHORT_SIZE] = std::array::from_fn(|_| Pubkey::new_unique());
+                let opt_in_key = EncryptionKeypair::generate().public_key();
+                let fee_authority = fee_authority_address();
+                let mut builder = Harness::builder()
+                    .arcium_fixtures(FIXTURES_DIR)
+                    .and_then(|builder| {
+                        builder.comp_def_copies(FIXTURES_DIR, &[comp_def_offset_of("cohort_query")])
+                    })
+                    .unwrap()
+                    .token_mint(mint, 6)
+                    .program_account(
+                        research_escrow,
+                        &ResearchEscrow {
+                            researcher: researcher.pubkey(),
+                            mint,
+                            query_id: 0,
+                            amount,
+                            status: EscrowStatus::Open,
+                            bump: 0,
+                        },
+                    )
+                    .token_account(
+                        research_vault_address(&research_escrow),
+                        mint,
+                        fee_authority,
+                        amount,
+                    )
+                    .token_account(treasury_address(&mint), mint, fee_authority, 0)
+                    .token_account(researcher_tokens, mint, researcher.pubkey(), 0);
+                for (i, patient) in patients.iter().enumerate() {
+                    let patient_data = patient_data_address(&patient.pubkey());
+                    builder = builder
+                        .token_account(payouts[i], mint, patient.pubkey(), 0)
+                        .program_account(
+                            research_opt_in_address(&patient_data),
+                            &ResearchOptIn {
+                                patient: patient.pubkey(),
+                                opted_in: opted_out != Some(i),
+                                enc_pubkey: opt_in_key,
+                                nonce: i as u128 + 1,
+                                bump: program_bump(&[RESEARCH_OPT_IN_SEED, patient_data.as_ref()]),
+                            },
+                        );
+                }
+                let bench = Bench::start(builder);
+                for (patient, record) in patients.iter().zip(&records) {
+                    check(
+                        bench.succeeds(
+                            &[
+                                bench.fund_ix(patient),
+                                store_patient_data_ix(patient.pubkey(), patient.pubkey(), record),
+                            ],
+                            &[patient],
+                        ),
+                        "each member stores a record",
+                    )?;
+                }
+                check(
+                    bench.succeeds(&[bench.fund_ix(&researcher)], &[&researcher]),
+                    "the researcher is funded",
+                )?;
+                let members: Vec<(Pubkey, Pubkey)> = patients
+                    .iter()
+                    .zip(payouts)
+                    .map(|(patient, payout)| (patient_data_address(&patient.pubkey()), payout))
+                    .collect();
+                let query = |members: &[(Pubkey, Pubkey)]| {
+                    bench.send(
+                        &[cohort_query_ix(
+                            researcher.pubkey(),
+                            research_escrow,
+                            mint,
+                            researcher_tokens,
+                            anchor_spl::token::ID,
+                            members,
+                            EncryptionKeypair::generate().public_key(),
+                            [7; 16],
+                            min_age,
+                            max_age,
+                            blood_type,
+                            computation_offset,
+                            DEVNET_CLUSTER_OFFSET,
+                        )],
+                        &[&researcher],
+                    )
+                };
+                let snapshot =
+                    || bench.read::<CohortSnapshot>(cohort_snapshot_address(&research_escrow));
+
+                let mut repeated = members.clone();
+                repeated[COHORT_SIZE - 1] = repeated[0];
+                prop_assert_eq!(
+                    query(&repeated),
+                    match opted_out {
+                        Some(i) if i < COHORT_SIZE - 1 => custom(ErrorCode::CohortMemberNotOptedIn),
+                        _ => custom(ErrorCode::DuplicateCohortMember),
+                    }
+                );
+                if opted_out.is_some() {
+                    prop_assert_eq!(query(&members), custom(ErrorCode::CohortMemberNotOptedIn));
+                    check(snapshot().is_none(), "a rejected cohort leaves no snapshot")?;
+                    return Ok(());
+                }
+                check(snapshot().is_none(), "a rejected cohort leaves no snapshot")?;
+                prop_assert_eq!(query(&members), Ok(()));
+
+                let queued = snapshot().unwrap();
+                prop_assert_eq!(queued.research_escrow, research_escrow);
+                prop_assert_eq!(queued.researcher, researcher.pubkey());
+                prop_assert_eq!(queued.computation_offset, computation_offset);
+                prop_assert_eq!(queued.comp_def_offset, comp_def_offset_of("cohort_query"));
+                prop_assert_eq!(queued.circuit_version, 1);
+                prop_assert_eq!(
+                    (queued.min_age, queued.max_age, queued.blood_type),
+                    (min_age, max_age, blood_type)
+                );
+                prop_assert_eq!(queued.completed_slot, 0);
+                for (i, member) in queued.members.iter().enumerate() {
+                    let (patient_data, _) = members[i];
+                    let ciphertexts = records[i].ciphertexts;
+                    prop_assert_eq!(member.patient_data, patient_data);
+                    prop_assert_eq!(
+                        member.research_opt_in,
+                        research_opt_in_address(&patient_data)
+                    );
+                    prop_assert_eq!(member.record_version, 1);
+                    prop_assert_eq!(
+                        member.record_commitment,
+                        patient_commitment(
+                            &patients[i].pubkey(),
+                            &opt_in_key,
+                            i as u128 + 1,
+                            ciphertexts[..6].try_into().unwrap(),
+                            ciphertexts[6..].try_into().unwrap(),
+                        )
+                    );
+                }
+
+                let result_key = EncryptionKeypair::generate().public_key();
+                check(
+                    bench.callback_succeeds(
+                        computation_offset,
+                        cohort_query_callback_ix(
+                            researcher.pubkey(),
+                            research_escrow,
+                            mint,
+                            researcher_tokens,
+                            anchor_spl::token::ID,
+                            &payouts,
+                            computation_offset,
+                            cohort_query_success(result_key, 9, count),
+                        ),
+                    ),
+                    "the count is delivered",
+                )?;
+                let completed = snapshot().unwrap();
+                prop_assert_eq!(completed.result, count);
+                prop_assert_eq!(completed.result_enc_key, result_key);
+                prop_assert_eq!(completed.result_nonce, 9u128.to_le_bytes());
+                check(
+                    completed.completed_slot >= queued.queued_slot,
+                    "the snapshot records when the result arrived",
+                )?;
+                let escrow: ResearchEscrow = bench.read(research_escrow).unwrap();
+                check(
+                    escrow.status == EscrowStatus::Settled,
+                    "the escrow settles with the result",
+                )?;
+                Ok(())
+            },
+        )
+        .unwrap();
+}
+
+#[test]
+fn telehealth_sessions_grant_consent_until_closed() {
+    let bench = Bench::new();
+    let custom = |index: u8, code: ErrorCode| {
+        Err(TransactionError::InstructionError(
+            index,
+            InstructionError::Custom(u32::from(code)),
+        ))
+    };
+    let patient = bench.patient(&record(distinct_ciphertexts(28)));
+    let patient_data = patient_data_address(&patient.pubkey());
+    let mut runner = TestRunner::new(Config::with_cases(CASES));
+    // The session, its scope and length, and whether the provider rather than the
+    // patient closes it
+    let strategy = (
+        any::<[u8; 16]>(),
+        1..=CONSENT_SCOPE_ALL,
+        0..=MAX_SESSION_SLOTS + 1,
+        any::<bool>(),
+    );
+    runner
+        .run(&strategy, |(session_id, scope, max_slots, by_provider)| {
+            let (provider, stranger) = (Keypair::new(), Keypair::new());
+            let open = |session_id: [u8; 16]| {
+                bench.send(
+                    &[open_session_ix(
+                        patient.pubkey(),
+                        session_id,
+                        provider.pubkey(),
+                        scope,
+                        max_slots,
+                    )],
+                    &[&patient],
+                )
+            };
+            let close = |closer: &Keypair| {
+                bench.send(
+                    &[
+                        bench.fund_ix(closer),
+                        close_session_ix(
+                            closer.pubkey(),
+                            patient_data,
+                            session_id,
+                            provider.pubkey(),
+                        ),
+                    ],
+                    &[closer],
+                )
+            };
+            let grant = || {
+                bench
+                    .read::<ConsentGrant>(consent_grant_address(&patient_data, &provider.pubkey()))
+                    .unwrap()
+            };
+
+            if max_slots == 0 || max_slots > MAX_SESSION_SLOTS {
+                prop_assert_eq!(open(session_id), custom(0, ErrorCode::InvalidSessionLength));
+                return Ok(());
+            }
+            prop_assert_eq!(open(session_id), Ok(()));
+            let session: TelehealthSession = bench
+                .read(telehealth_session_address(&patient_data, &session_id))
+                .unwrap();
+            let granted = grant();
+            check(
+                !granted.revoked
+                    && granted.scope == scope
+                    && granted.expires_at_slot == session.ends_at_slot
+                    && granted.granted_at == session.opened_at,
+                "the session grants its scope until it ends",
+            )?;
+            prop_assert_eq!(session.provider, provider.pubkey());
+            prop_assert_eq!(session.closed_at, 0);
+            let mut other_id = session_id;
+            other_id[0] ^= 1;
+            prop_assert_eq!(open(other_id), custom(0, ErrorCode::SessionConsentActive));
+
+            // Closes are the second instruction, after funding the closer
+            prop_assert_eq!(close(&stranger), custom(1, ErrorCode::Unauthorized));
+            let closer = if by_provider { &provider } else { &patient };
+            prop_assert_eq!(close(closer), Ok(()));
+            check(grant().revoked, "closing the session revokes its consent")?;
+            let session: TelehealthSession = bench
+                .read(telehealth_session_address(&patient_data, &session_id))
+                .unwrap();
+            check(session.closed_at != 0, "the session is closed")?;
+            prop_assert_eq!(close(closer), custom(1, ErrorCode::SessionClosed));
+            prop_assert_eq!(open(other_id), Ok(()));
+            Ok(())
+        })
+        .unwrap();
+}
+
+#[test]
+fn access_requests_default_to_their_specialty_template() {
+    let bench = Bench::new();
+    let custom = |code: ErrorCode| {
+        Err(TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(u32::from(code)),
+        ))
+    };
+    let patient = bench.patient(&record(distinct_ciphertexts(29)));
+    let patient_data = patient_data_address(&patient.pubkey());
+    let admin = &bench.admin;
+    let set_template = |signer: &Keypair, specialty, fields| {
+        bench.send(
+            &[
+                bench.fund_ix(signer),
+                set_specialty_template_ix(signer.pubkey(), specialty, fields),
+            ],
+            &[signer],
+        )
+    };
+    assert_eq!(
+        set_template(&Keypair::new(), Specialty::Dentistry, PATIENT_FIELDS_ALL),
+        Err(TransactionError::InstructionError(
+            1,
+            InstructionError::Custom(u32::from(ErrorCode::Unauthorized)),
+        )),
+        "only the admin publishes templates"
+    );
+    let unregistered = Keypair::new();
+    assert!(bench.succeeds(&[bench.fund_ix(&unregistered)], &[&unregistered]));
+    assert!(set_template(admin, Specialty::Dentistry, PATIENT_FIELDS_ALL).is_ok());
+    assert_eq!(
+        bench.send(
+            &[request_access_ix(
+                unregistered.pubkey(),
+                patient_data,
+                Specialty::Dentistry,
+                None,
+                CONSENT_SCOPE_PATIENT_DATA,
+            )],
+            &[&unregistered],
+        ),
+        Err(TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(AnchorErrorCode::AccountNotInitialized as u32),
+        )),
+        "only registered providers request access"
+    );
+
+    let mut runner = TestRunner::new(Config::with_cases(CASES));
+    // The specialty and its template, the fields and purpose of the request; masks run
+    // past the known fields and scopes so invalid ones come up
+    let strategy = (
+        prop::sample::select(vec![
+            Specialty::GeneralPractice,
+            Specialty::Dentistry,
+            Specialty::Cardiology,
+            Specialty::Dermatology,
+            Specialty::Oncology,
+            Specialty::Pediatrics,
+        ]),
+        0..=PATIENT_FIELDS_ALL * 2,
+        prop::option::of(0..=PATIENT_FIELDS_ALL * 2),
+        0..=CONSENT_SCOPE_ALL * 2,
+    );
+    runner
+        .run(&strategy, |(specialty, template, requested, purpose)| {
+            let valid_mask = |fields: u16| fields != 0 && fields & !PATIENT_FIELDS_ALL == 0;
+            let set = set_template(admin, specialty, template);
+            if !valid_mask(template) {
+                prop_assert_eq!(
+                    set,
+                    Err(TransactionError::InstructionError(
+                        1,
+                        InstructionError::Custom(u32::from(ErrorCode::InvalidPatientFieldMask)),
+                    ))
+                );
+                return Ok(());
+            }
+            prop_assert_eq!(set, Ok(()));
+
+            let (provider, _) = bench.provider(ProviderRole::Physician);
+            let requested_access = bench.send(
+                &[request_access_ix(
+                    provider.pubkey(),
+                    patient_data,
+                    specialty,
+                    requested,
+                    purpose,
+                )],
+                &[&provider],
+            );
+            let fields = requested.unwrap_or(template);
+            if purpose == 0 || purpose & !CONSENT_SCOPE_ALL != 0 {
+                prop_assert_eq!(requested_access, custom(ErrorCode::InvalidConsentScope));
+                return Ok(());
+            }
+            if !valid_mask(fields) {
+                prop_assert_eq!(requested_access, custom(ErrorCode::InvalidPatientFieldMask));
+                return Ok(());
+            }
+            prop_assert_eq!(requested_access, Ok(()));
+            let request: AccessRequest = bench
+                .read(access_request_address(&patient_data, &provider.pubkey()))
+                .unwrap();
+            prop_assert_eq!(request.specialty, specialty);
+            prop_assert_eq!(request.fields, fields);
+            prop_assert_eq!(request.purpose, purpose);
+            check(
+                request.exceeds_template == (fields & !template != 0),
+                "requests beyond the template are flagged",
+            )
+        })
+        .unwrap();
+}
+
+#[test]
+fn ingest_authorities_append_within_their_quota_until_revoked() {
+    let bench = Bench::new();
+    let custom = |index: u8, code: ErrorCode| {
+        Err(TransactionError::InstructionError(
+            index,
+            InstructionError::Custom(u32::from(code)),
+        ))
+    };
+    let mut runner = TestRunner::new(Config::with_cases(CASES));
+    // The tree, the app's quota and the records it appends
+    let strategy = (
+        any::<u64>(),
+        1..=4u32,
+        any::<[u8; 32]>(),
+        any::<u128>(),
+        any::<CompressedCiphertexts>(),
+    );
+    runner
+        .run(
+            &strategy,
+            |(tree_id, quota, enc_pubkey, nonce, ciphertexts)| {
+                let (patient, app, stranger) = (Keypair::new(), Keypair::new(), Keypair::new());
+                let tree = compressed_tree_address(&patient.pubkey(), tree_id);
+                prop_assert_eq!(
+                    bench.send(
+                        &[
+                            bench.fund_ix(&patient),
+                            init_compressed_tree_ix(patient.pubkey(), tree_id),
+                        ],
+                        &[&patient],
+                    ),
+                    Ok(())
+                );
+                let grant = |quota| {
+                    bench.send(
+                        &[grant_ingest_authority_ix(
+                            patient.pubkey(),
+                            tree,
+                            app.pubkey(),
+                            quota,
+                        )],
+                        &[&patient],
+                    )
+                };
+                let ingest = |app: &Keypair| {
+                    bench.send(
+                        &[ingest_compressed_record_ix(
+                            app.pubkey(),
+                            tree,
+                            enc_pubkey,
+                            nonce,
+                            ciphertexts,
+                        )],
+                        &[app],
+                    )
+                };
+                let authority = || {
+                    bench
+                        .read::<IngestAuthority>(ingest_authority_address(&tree, &app.pubkey()))
+                        .unwrap()
+                };
+                let appended = || bench.read::<CompressedRecordTree>(tree).unwrap().next_index;
+
+                prop_assert_eq!(grant(0), custom(0, ErrorCode::InvalidIngestQuota));
+                // Only the tree's owner grants; the stranger pays, so grants second
+                prop_assert_eq!(
+                    bench.send(
+                        &[
+                            bench.fund_ix(&stranger),
+                            grant_ingest_authority_ix(
+                                stranger.pubkey(),
+                                tree,
+                                stranger.pubkey(),
+                                quota,
+                            ),
+                        ],
+                        &[&stranger],
+                    ),
+                    custom(1, ErrorCode::Unauthorized)
+                );
+                prop_assert_eq!(grant(quota), Ok(()));
+                let granted = authority();
+                prop_assert_eq!(granted.patient, patient.pubkey());
+                prop_assert_eq!(granted.app, app.pubkey());
+                prop_assert_eq!(granted.quota_per_epoch, quota);
+                check(!granted.revoked, "a fresh grant is live")?;
+                prop_assert_eq!(
+                    ingest(&stranger),
+                    Err(TransactionError::InstructionError(
+                        0,
+                        InstructionError::Custom(AnchorErrorCode::AccountNotInitialized as u32),
+                    ))
+                );
+
+                for _ in 0..quota {
+                    prop_assert_eq!(ingest(&app), Ok(()));
+                }
+                prop_assert_eq!(appended(), u64::from(quota));
+                let epoch = authority().epoch;
+                // The quota resets when the epoch rolls over, which short warmup epochs
+                // may do mid-case
+                match ingest(&app) {
+                    Ok(()) => check(
+                        authority().epoch > epoch && authority().appended == 1,
+                        "an append past the quota only lands in a new epoch",
+                    )?,
+                    over => {
+                        prop_assert_eq!(over, custom(0, ErrorCode::IngestQuotaExceeded));
+                        prop_assert_eq!(appended(), u64::from(quota));
+                    }
+                }
+
+                let revoke = |signer: &Keypair| {
+                    bench.send(
+                        &[revoke_ingest_authority_ix(
+                            signer.pubkey(),
+                            tree,
+                            app.pubkey(),
+                        )],
+                        &[signer],
+                    )
+                };
+                prop_assert_eq!(revoke(&stranger), custom(0, ErrorCode::Unauthorized));
+                prop_assert_eq!(revoke(&patient), Ok(()));
+                check(authority().revoked, "the grant is revoked")?;
+                let before = appended();
+                prop_assert_eq!(ingest(&app), custom(0, ErrorCode::IngestAuthorityRevoked));
+                prop_assert_eq!(appended(), before);
+                // Granting again restores the app's access
+                prop_assert_eq!(grant(quota), Ok(()));
+                check(!authority().revoked, "a new grant lifts the revocation")?;
+                Ok(())
+            },
+        )
+        .unwrap();
+}
+
+#[test]
+fn audit_attestations_checkpoint_consecutive_windows_of_the_chain() {
+    let bench = Bench::new();
+    let custom = |code: ErrorCode| {
+        Err(TransactionError::InstructionError(
+            1,
+            InstructionError::Custom(u32::from(code)),
+        ))
+    };
+    let admin = &bench.admin;
+    let set_auditors =
+        |auditors: Vec<Pubkey>| bench.send(&[set_auditors_ix(admin.pubkey(), auditors)], &[admin]);
+    assert_eq!(
+        set_auditors(vec![Pubkey::new_unique(); MAX_AUDITORS + 1]),
+        Err(TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(u32::from(ErrorCode::TooManyAuditors)),
+        ))
+    );
+    let entries = |patient_data: &Pubkey| {
+        bench
+            .read::<PatientRegistry>(patient_registry_address(patient_data))
+            .unwrap()
+            .audit_entries
+    };
+    // Auditors pay for their checkpoints, so attest second
+    let attest = |auditor: &Keypair, patient_data: Pubkey, previous: Option<Pubkey>| {
+        bench.send(
+            &[
+                bench.fund_ix(auditor),
+                attest_audit_window_ix(
+                    auditor.pubkey(),
+                    patient_data,
+                    entries(&patient_data),
+                    previous,
+                ),
+            ],
+            &[auditor],
+        )
+    };
+
+    let mut runner = TestRunner::new(Config::with_cases(CASES));
+    // The auditors, which of them attests, and the updates between the checkpoints
+    let strategy = (1..=MAX_AUDITORS, any::<prop::sample::Index>(), 1..=3u8);
+    runner
+        .run(&strategy, |(auditor_count, attesting, updates)| {
+            let auditors: Vec<Keypair> = (0..auditor_count).map(|_| Keypair::new()).collect();
+            prop_assert_eq!(
+                set_auditors(auditors.iter().map(Keypair::pubkey).collect()),
+                Ok(())
+            );
+            let auditor = attesting.get(&auditors);
+            let patient = bench.patient(&record(distinct_ciphertexts(30)));
+            let patient_data = patient_data_address(&patient.pubkey());
+            let other = bench.patient(&record(distinct_ciphertexts(30)));
+            let other_data = patient_data_address(&other.pubkey());
+
+            prop_assert_eq!(
+                attest(&Keypair::new(), patient_data, None),
+                custom(ErrorCode::NotAnAuditor)
+            );
+            prop_assert_eq!(attest(auditor, patient_data, None), Ok(()));
+            prop_assert_eq!(attest(auditor, other_data, None), Ok(()));
+            let first_address = audit_attestation_address(&patient_data, entries(&patient_data));
+            let first: AuditAttestation = bench.read(first_address).unwrap();
+            let registry: PatientRegistry =
+                bench.read(patient_registry_address(&patient_data)).unwrap();
+            prop_assert_eq!(first.patient_data, patient_data);
+            prop_assert_eq!(first.auditor, auditor.pubkey());
+            check(
+                first.start_entries == 0 && first.start_head == [0; 32],
+                "a checkpoint without a previous one starts at the start of the chain",
+            )?;
+            check(
+                first.end_entries == registry.audit_entries
+                    && first.end_head == registry.audit_head,
+                "a checkpoint records the chain as it stands",
+            )?;
+            check(
+                attest(auditor, patient_data, Some(first_address)).is_err(),
+                "a checkpoint of an unchanged chain can't be replaced",
+            )?;
+            prop_assert_eq!(
+                bench
+                    .read::<AuditAttestation>(first_address)
+                    .unwrap()
+                    .end_head,
+                first.end_head
+            );
+
+            let mut window = Vec::new();
+            for seed in 0..updates {
+                let update = record(distinct_ciphertexts(31 + seed));
+                prop_assert!(bench.succeeds(
+                    &[update_patient_data_ix(patient.pubkey(), &update)],
+                    &[&patient],
+                ));
+                window.push(
+                    PatientDataUpdatedEvent {
+                        patient_data,
+                        enc_pubkey: update.sender_pub_key,
+                        nonce: nonce_to_u128(&update.nonce),
+                    }
+                    .data(),
+                );
+            }
+            let other_first = audit_attestation_address(&other_data, entries(&other_data));
+            prop_assert_eq!(
+                attest(auditor, patient_data, Some(other_first)),
+                custom(ErrorCode::InvalidAuditWindow)
+            );
+            prop_assert_eq!(attest(auditor, patient_data, Some(first_address)), Ok(()));
+            let second: AuditAttestation = bench
+                .read(audit_attestation_address(
+                    &patient_data,
+                    entries(&patient_data),
+                ))
+                .unwrap();
+            check(
+                second.start_entries == first.end_entries
+                    && second.start_head == first.end_head
+                    && second.start_slot == first.end_slot,
+                "a checkpoint starts where the previous one ended",
+            )?;
+            let exported = window.iter().map(Vec::as_slice);
+            check(
+                matches_audit_attestation(&second, exported.clone()),
+                "the events in between replay to the checkpoint",
+            )?;
+            check(
+                !matches_audit_attestation(&second, exported.skip(1)),
+                "an export missing an event doesn't",
+            )
+        })
+        .unwrap();
+}
+
+#[test]
+fn inboxes_keep_the_latest_notifications_once_opened() {
+    let bench = Bench::new();
+    let custom = |code: ErrorCode| {
+        Err(TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(u32::from(code)),
+        ))
+    };
+    let admin = &bench.admin;
+    assert!(bench.succeeds(
+        &[set_specialty_template_ix(
+            admin.pubkey(),
+            Specialty::GeneralPractice,
+            PATIENT_FIELDS_ALL,
+        )],
+        &[admin],
+    ));
+    let mut runner = TestRunner::new(Config::with_cases(CASES));
+    // The access requests filed once the inbox is open, and how far off the consent
+    // posted about lapses, well inside or outside the notice
+    let strategy = (
+        0..=INBOX_CAPACITY + 3,
+        prop_oneof![
+            60..=CONSENT_EXPIRY_NOTICE - 60,
+            CONSENT_EXPIRY_NOTICE + 60..=2 * CONSENT_EXPIRY_NOTICE,
+        ],
+    );
+    runner
+        .run(&strategy, |(requests, lapses_in)| {
+            let patient = bench.patient(&record(distinct_ciphertexts(34)));
+            let patient_data = patient_data_address(&patient.pubkey());
+            let request = || -> Result<Pubkey, TestCaseError> {
+                let (provider, _) = bench.provider(ProviderRole::Physician);
+                prop_assert_eq!(
+                    bench.send(
+                        &[request_access_ix(
+                            provider.pubkey(),
+                            patient_data,
+                            Specialty::GeneralPractice,
+                            None,
+                            CONSENT_SCOPE_PATIENT_DATA,
+                        )],
+                        &[&provider],
+                    ),
+                    Ok(())
+                );
+                Ok(access_request_address(&patient_data, &provider.pubkey()))
+            };
+            let inbox = || bench.read::<Inbox>(inbox_address(&patient_data));
+
+            // Notifying instructions work the same before the patient opens an inbox
+            request()?;
+            check(inbox().is_none(), "nothing opens the inbox for the patient")?;
+            let open = || bench.send(&[open_inbox_ix(patient.pubkey())], &[&patient]);
+            prop_assert_eq!(open(), Ok(()));
+            check(open().is_err(), "an inbox opens once")?;
+            let opened = inbox().unwrap();
+            prop_assert_eq!(opened.patient_data, patient_data);
+            prop_assert_eq!(opened.posted, 0);
+
+            let mut subjects = Vec::new();
+            for _ in 0..requests {
+                subjects.push(request()?);
+            }
+            let posted = inbox().unwrap();
+            prop_assert_eq!(posted.posted, requests as u64);
+            prop_assert_eq!(posted.notifications.len(), requests.min(INBOX_CAPACITY));
+            for (n, subject) in subjects
+                .iter()
+                .enumerate()
+                .skip(requests.saturating_sub(INBOX_CAPACITY))
+            {
+                let notification = posted.notifications[n % INBOX_CAPACITY];
+                check(
+                    notification.kind == NotificationKind::AccessRequested
+                        && notification.subject == *subject,
+                    "the latest requests are kept in order of posting",
+                )?;
+            }
+
+            let receiver = Pubkey::new_unique();
+            prop_assert!(bench.succeeds(
+                &[grant_consent_ix(
+                    patient.pubkey(),
+                    receiver,
+                    CONSENT_SCOPE_PATIENT_DATA,
+                    bench.now() + lapses_in,
+                )],
+                &[&patient],
+            ));
+            let notify = || bench.send(&[notify_consent_expiring_ix(patient_data, receiver)], &[]);
+            if lapses_in > CONSENT_EXPIRY_NOTICE {
+                prop_assert_eq!(notify(), custom(ErrorCode::ConsentNotExpiring));
+                prop_assert_eq!(inbox().unwrap().posted, requests as u64);
+                return Ok(());
+            }
+            prop_assert_eq!(notify(), Ok(()));
+            let notified = inbox().unwrap();
+            prop_assert_eq!(notified.posted, requests as u64 + 1);
+            let notification = notified.notifications[requests % INBOX_CAPACITY];
+            check(
+                notification.kind == NotificationKind::ConsentExpiring
+                    && notification.subject == consent_grant_address(&patient_data, &receiver),
+                "the lapsing consent is posted about",
+            )?;
+            prop_assert_eq!(notify(), custom(ErrorCode::AlreadyNotified));
+            Ok(())
+        })
+        .unwrap();
+}
+
+#[test]
+fn med_administrations_chain_into_the_log_while_authorized() {
+    let bench = Bench::new();
+    let custom = |code: ErrorCode| {
+        Err(TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(u32::from(code)),
+        ))
+    };
+    let not_initialized = Err(TransactionError::InstructionError(
+        0,
+        InstructionError::Custom(AnchorErrorCode::AccountNotInitialized as u32),
+    ));
+    let stored = record(distinct_ciphertexts(35));
+    let patient = bench.patient(&stored);
+    let patient_data = patient_data_address(&patient.pubkey());
+    let guardian = Pubkey::new_unique();
+    let authorize = |staff: &Pubkey| {
+        bench.send(
+            &[authorize_med_administrator_ix(
+                patient.pubkey(),
+                guardian,
+                *staff,
+            )],
+            &[&patient],
+        )
+    };
+    assert_eq!(
+        authorize(&Pubkey::new_unique()),
+        not_initialized,
+        "staff are authorized against an existing caregiver grant"
+    );
+    assert!(bench.succeeds(
+        &[grant_caregiver_access_ix(
+            patient.pubkey(),
+            guardian,
+            EncryptionKeypair::generate().public_key(),
+            CAREGIVER_FIELDS,
+            nonce_to_u128(&stored.nonce),
+            MIN_CAREGIVER_INTERVAL_SLOTS,
+            bench.now() + 24 * 60 * 60,
+        )],
+        &[&patient],
+    ));
+    let log = || {
+        bench
+            .read::<MedAdministrationLog>(med_administration_log_address(&patient_data))
+            .unwrap()
+    };
+    let administer = |staff: &Keypair, values, enc_pubkey, nonce| {
+        bench.send(
+            &[med_administration_ix(
+                staff.pubkey(),
+                patient_data,
+                guardian,
+                values,
+                enc_pubkey,
+                nonce,
+            )],
+            &[staff],
+        )
+    };
+
+    let mut runner = TestRunner::new(Config::with_cases(CASES));
+    // The entries a staff member logs before the patient revokes them
+    let strategy =
+        prop::collection::vec((any::<EncryptedMedAdministration>(), any::<u128>()), 1..=4);
+    runner
+        .run(&strategy, |entries| {
+            let (staff, stranger) = (Keypair::new(), Keypair::new());
+            let enc_pubkey = EncryptionKeypair::generate().public_key();
+            prop_assert_eq!(authorize(&staff.pubkey()), Ok(()));
+            let (values, nonce) = entries[0];
+            prop_assert_eq!(
+                administer(&stranger, values, enc_pubkey, nonce),
+                not_initialized.clone()
+            );
+            prop_assert_eq!(
+                administer(&staff, values, [0; 32], nonce),
+                custom(ErrorCode::InvalidEncryptionKey)
+            );
+
+            for (values, nonce) in entries {
+                let before = log();
+                let sent_after = bench.now();
+                prop_assert_eq!(administer(&staff, values, enc_pubkey, nonce), Ok(()));
+                let after = log();
+                prop_assert_eq!(after.patient_data, patient_data);
+                prop_assert_eq!(after.entries, before.entries + 1);
+                // The entry is timestamped by the clock while it was processed
+                let chained = (sent_after..=bench.now()).any(|logged_at| {
+                    med_administration_hash(
+                        &before.head,
+                        before.entries,
+                        &staff.pubkey(),
+                        &enc_pubkey,
+                        nonce,
+                        &values,
+                        logged_at,
+                    ) == after.head
+                });
+                check(chained, "each entry is chained onto the previous head")?;
+            }
+
+            // Only the patient who authorized the staff member revokes them
+            let revoke = |signer: &Keypair| {
+                let mut ix = revoke_med_administrator_ix(patient.pubkey(), staff.pubkey());
+                ix.accounts[0].pubkey = signer.pubkey();
+                bench.send(&[ix], &[signer])
+            };
+            prop_assert_eq!(revoke(&stranger), custom(ErrorCode::Unauthorized));
+            prop_assert_eq!(revoke(&patient), Ok(()));
+            let logged = log().entries;
+            prop_assert_eq!(
+                administer(&staff, values, enc_pubkey, nonce),
+                custom(ErrorCode::MedAdministratorRevoked)
+            );
+            prop_assert_eq!(log().entries, logged);
+            Ok(())
+        })
+        .unwrap();
+
+    let staff = Keypair::new();
+    assert!(authorize(&staff.pubkey()).is_ok());
+    assert!(bench.succeeds(
+        &[revoke_caregiver_access_ix(patient.pubkey(), guardian)],
+        &[&patient],
+    ));
+    assert_eq!(
+        administer(
+            &staff,
+            [[1; 32]; MED_ADMINISTRATION_VALUES],
+            EncryptionKeypair::generate().public_key(),
+            1,
+        ),
+        custom(ErrorCode::CaregiverGrantInactive),
+        "entries need the guardian's grant to stay active"
+    );
+}
@@ -0,0 +1,125 @@
+//! Account layouts the circuits depend on.
+//!
+//! Circuits read record ciphertexts straight out of account data, so these check each
+//! `CiphertextRegion` covers exactly the ciphertext fields once serialized, and that
+//! `INIT_SPACE` fits the largest serialized account. Multi-block values are `CipherBlob`s,
+//! which must serialize as their bare blocks for those regions to hold.
+
+use anchor_lang::{AccountDeserialize, AccountSerialize, AnchorSerialize, Space};
+use share_medical_records::{
+    CipherBlob, CiphertextRegion, DicomReference, EncryptedContentKey, EncryptedStudyUid,
+    FhirAnchor, PatientData, PatientField, CIPHERTEXTS_OFFSET, MAX_DICOM_MODALITY_LEN,
+    MAX_DICOM_POINTER_LEN, MAX_FHIR_RESOURCE_TYPE_LEN,
+};
+use solana_sdk::pubkey::Pubkey;
+
+/// Distinct ciphertext bytes, so a region shifted by any amount reads different values.
+fn ciphertext(index: u8) -> [u8; 32] {
+    std::array::from_fn(|byte| index.wrapping_mul(32).wrapping_add(byte as u8))
+}
+
+fn serialize<T: AccountSerialize>(account: &T) -> Vec<u8> {
+    let mut data = Vec::new();
+    account.try_serialize(&mut data).unwrap();
+    data
+}
+
+/// Checks the region of `T` in `data` is `ciphertexts` and `data` fills `INIT_SPACE`.
+fn assert_region<T: CiphertextRegion>(data: &[u8], ciphertexts: &[[u8; 32]]) {
+    let start = CIPHERTEXTS_OFFSET as usize;
+    let end = start + T::CIPHERTEXTS_LEN as usize;
+    assert_eq!(data[start..end], ciphertexts.concat());
+    assert_eq!(data.len(), 8 + T::INIT_SPACE);
+}
+
+#[test]
+fn patient_data_region_covers_the_ciphertexts() {
+    let record = PatientData {
+        patient_id: ciphertext(0),
+        age: ciphertext(1),
+        gender: ciphertext(2),
+        blood_type: ciphertext(3),
+        weight: ciphertext(4),
+        height: ciphertext(5),
+        allergies: std::array::from_fn(|i| ciphertext(6 + i as u8)),
+        created_slot: u64::MAX,
+        tombstoned: true,
+        patient_authority: Pubkey::new_unique(),
+        enc_pubkey: [0xff; 32],
+        nonce: u128::MAX,
+        in_flight_computations: u8::MAX,
+        version: u32::MAX,
+    };
+    let ciphertexts: Vec<[u8; 32]> = (0..11).map(ciphertext).collect();
+    assert_region::<PatientData>(&serialize(&record), &ciphertexts);
+}
+
+#[test]
+fn patient_fields_cover_each_record_block_once() {
+    let blocks: Vec<usize> = PatientField::ALL
+        .iter()
+        .flat_map(|field| field.blocks())
+        .collect();
+    assert_eq!(blocks, (0..11).collect::<Vec<_>>());
+
+    let ciphertexts: [[u8; 32]; 11] = std::array::from_fn(|i| ciphertext(i as u8));
+    let mut record =
+        PatientData::try_deserialize_unchecked(&mut &[0; 8 + PatientData::INIT_SPACE][..]).unwrap();
+    record.set_ciphertexts(&ciphertexts);
+    assert_eq!(record.ciphertexts(), ciphertexts);
+    for field in PatientField::ALL {
+        assert_eq!(record.field(field), field.of(&ciphertexts));
+    }
+    assert_region::<PatientData>(&serialize(&record), &ciphertexts);
+}
+
+#[test]
+fn dicom_reference_region_covers_the_ciphertexts() {
+    let reference = DicomReference {
+        study_uid: EncryptedStudyUid::new(std::array::from_fn(|i| ciphertext(i as u8))),
+        accession_number: ciphertext(4),
+        patient_data: Pubkey::new_unique(),
+        imaging_center: Pubkey::new_unique(),
+        reference_id: u64::MAX,
+        modality: "M".repeat(MAX_DICOM_MODALITY_LEN),
+        pointer: "p".repeat(MAX_DICOM_POINTER_LEN),
+        enc_pubkey: [0xff; 32],
+        nonce: u128::MAX,
+        bump: u8::MAX,
+    };
+    let ciphertexts: Vec<[u8; 32]> = (0..5).map(ciphertext).collect();
+    assert_region::<DicomReference>(&serialize(&reference), &ciphertexts);
+}
+
+#[test]
+fn fhir_anchor_region_covers_the_content_key() {
+    let anchor = FhirAnchor {
+        content_key: EncryptedContentKey::new([ciphertext(0), ciphertext(1)]),
+        patient: Pubkey::new_unique(),
+        anchor_id: u64::MAX,
+        resource_type: "R".repeat(MAX_FHIR_RESOURCE_TYPE_LEN),
+        version: u32::MAX,
+        bundle_hash: [0xff; 32],
+        bump: u8::MAX,
+    };
+    assert_region::<FhirAnchor>(&serialize(&anchor), &[ciphertext(0), ciphertext(1)]);
+}
+
+#[test]
+fn cipher_blobs_serialize_as_their_bare_blocks() {
+    let blob = CipherBlob::<3>::new(std::array::from_fn(|i| ciphertext(i as u8)));
+    let mut data = Vec::new();
+    blob.serialize(&mut data).unwrap();
+    assert_eq!(data, blob.blocks.concat());
+    assert_eq!(data.len(), CipherBlob::<3>::LEN as usize);
+    assert_eq!(data.len(), CipherBlob::<3>::INIT_SPACE);
+}
+
+#[test]
+fn cipher_blob_limbs_round_trip_the_plaintext() {
+    let uid = b"1.2.840.113619.2.55.3.604688119.969.1268071029.320";
+    let limbs = EncryptedStudyUid::limbs(uid).unwrap();
+    assert_eq!(limbs[0].to_le_bytes(), uid[..16]);
+    assert_eq!(EncryptedStudyUid::plaintext(&limbs), uid);
+    assert!(EncryptedStudyUid::limbs(&[b'9'; 65]).is_none());
+}
@@ -0,0 +1,230 @@
+//! Address derivations of the client's `pdas` module.
+//!
+//! Checks each seed scheme against the addresses the program's own `cpi_client`
+//! builders derive, and that no two schemes, or two patients, can land on one address.
+
+use std::collections::HashSet;
+
+use proptest::prelude::*;
+use share_medical_records::{cpi_client, Specialty};
+use share_medical_records_client::*;
+use solana_sdk::pubkey::Pubkey;
+
+fn pubkey() -> impl Strategy<Value = Pubkey> {
+    any::<[u8; 32]>().prop_map(Pubkey::new_from_array)
+}
+
+#[test]
+fn seed_prefixes_are_distinct() {
+    let prefixes: HashSet<&[u8]> = SEED_PREFIXES.into_iter().collect();
+    assert_eq!(prefixes.len(), SEED_PREFIXES.len());
+}
+
+proptest! {
+    #[test]
+    fn share_accounts_match_the_program_derivations(
+        payer in pubkey(),
+        patient in pubkey(),
+        receiver in any::<[u8; 32]>(),
+        computation_offset in any::<u64>(),
+    ) {
+        let patient_data = patient_data_address(&patient);
+        let accounts = cpi_client::share_patient_data_accounts(
+            payer,
+            patient_data,
+            receiver,
+            computation_offset,
+            0,
+        );
+        prop_assert_eq!(accounts.program_config, program_config_address());
+        prop_assert_eq!(accounts.access_fee, access_fee_address(&patient_data));
+        prop_assert_eq!(accounts.subscription, subscription_address(&payer));
+        prop_assert_eq!(
+            accounts.pending_share,
+            pending_share_address(&patient_data, &receiver)
+        );
+        prop_assert_eq!(accounts.refund_balance, refund_balance_address(&payer));
+        prop_assert_eq!(accounts.used_offsets, used_offsets_address(&payer));
+        prop_assert_eq!(
+            accounts.callback_guard,
+            callback_guard_address(computation_offset)
+        );
+        prop_assert_eq!(accounts.used_nonces, used_nonces_address(&patient_data));
+        prop_assert_eq!(
+            accounts.patient_registry,
+            patient_registry_address(&patient_data)
+        );
+        prop_assert_eq!(
+            accounts.access_log_page,
+            access_log_page_address(&patient_data, 0)
+        );
+        prop_assert_eq!(
+            accounts.share_receipt,
+            share_receipt_address(&patient_data, computation_offset)
+        );
+        prop_assert_eq!(accounts.provider_profile, provider_profile_address(&payer));
+
+        prop_assert_eq!(
+            cpi_client::staged_share_address(&payer, computation_offset),
+            staged_share_address(&payer, computation_offset)
+        );
+        prop_assert_eq!(
+            cpi_client::shared_record_address(&patient_data, &receiver),
+            shared_record_address(&patient_data, &receiver)
+        );
+        prop_assert_eq!(
+            cpi_client::consent_grant_address(&patient_data, &payer),
+            consent_grant_address(&patient_data, &payer)
+        );
+    }
+
+    #[test]
+    fn bundle_and_fee_accounts_match_the_program_derivations(
+        patient in pubkey(),
+        mint in pubkey(),
+        labs in any::<u64>(),
+        prescriptions in any::<u64>(),
+        computation_offset in any::<u64>(),
+    ) {
+        let accounts = cpi_client::share_bundle_accounts(
+            patient,
+            labs,
+            prescriptions,
+            computation_offset,
+            0,
+        );
+        let patient_data = patient_data_address(&patient);
+        prop_assert_eq!(accounts.patient_data, patient_data);
+        prop_assert_eq!(
+            accounts.patient_registry,
+            patient_registry_address(&patient_data)
+        );
+        prop_assert_eq!(accounts.labs_anchor, fhir_anchor_address(&patient, labs));
+        prop_assert_eq!(
+            accounts.prescriptions_anchor,
+            fhir_anchor_address(&patient, prescriptions)
+        );
+        prop_assert_eq!(
+            share_medical_records::treasury_address(&mint),
+            treasury_address(&mint)
+        );
+    }
+
+    #[test]
+    fn derive_all_matches_each_scheme_and_never_collides(
+        patient in pubkey(),
+        other in pubkey(),
+    ) {
+        prop_assume!(patient != other);
+        let addresses = derive_all(&patient);
+        let patient_data = patient_data_address(&patient);
+        prop_assert_eq!(addresses.patient_data, patient_data);
+        prop_assert_eq!(
+            addresses.patient_commitment,
+            patient_commitment_address(&patient)
+        );
+        prop_assert_eq!(addresses.document_vault, document_vault_address(&patient));
+        prop_assert_eq!(
+            addresses.research_opt_in,
+            research_opt_in_address(&patient_data)
+        );
+        prop_assert_eq!(addresses.used_offsets, used_offsets_address(&patient));
+        prop_assert_eq!(
+            addresses.emergency_packet,
+            emergency_packet_address(&patient_data)
+        );
+        prop_assert_eq!(addresses.inbox, inbox_address(&patient_data));
+
+        let mut seen: HashSet<Pubkey> = addresses.to_array().into_iter().collect();
+        prop_assert_eq!(seen.len(), 12);
+        seen.extend(derive_all(&other).to_array());
+        prop_assert_eq!(seen.len(), 24);
+    }
+
+    #[test]
+    fn schemes_sharing_key_shapes_never_collide(
+        key in pubkey(),
+        id in any::<u64>(),
+        session_id in any::<[u8; 16]>(),
+    ) {
+        let bytes = key.to_bytes();
+        let addresses = [
+            patient_data_address(&key),
+            patient_commitment_address(&key),
+            document_vault_address(&key),
+            patient_registry_address(&key),
+            used_nonces_address(&key),
+            access_fee_address(&key),
+            research_opt_in_address(&key),
+            used_offsets_address(&key),
+            refund_balance_address(&key),
+            provider_profile_address(&key),
+            provider_name_address(&key),
+            subscription_address(&key),
+            regulator_activity_address(&key),
+            research_vault_address(&key),
+            trial_vault_address(&key),
+            treasury_address(&key),
+            emergency_packet_address(&key),
+            donor_profile_address(&key),
+            prior_auth_escalation_address(&key),
+            med_administration_log_address(&key),
+            kin_roster_address(&key),
+            waitlist_entry_address(&key),
+            cohort_snapshot_address(&key),
+            surveillance_tally_address(&key),
+            pending_share_address(&key, &bytes),
+            shared_record_address(&key, &bytes),
+            share_code_address(&key),
+            consent_grant_address(&key, &key),
+            caregiver_grant_address(&key, &key),
+            access_request_address(&key, &key),
+            trial_enrollment_address(&key, &key),
+            med_administrator_address(&key, &key),
+            kin_link_address(&key, &key),
+            ingest_authority_address(&key, &key),
+            emergency_policy_address(&key, &key),
+            clearance_address(&key, &key),
+            disclosure_request_address(&key, &bytes),
+            telehealth_session_address(&key, &session_id),
+            fhir_anchor_address(&key, id),
+            dicom_reference_address(&key, id),
+            share_receipt_address(&key, id),
+            staged_share_address(&key, id),
+            research_escrow_address(&key, id),
+            trial_escrow_address(&key, id),
+            audit_attestation_address(&key, id),
+            compressed_tree_address(&key, id),
+            prior_auth_address(&key, id),
+            clearance_template_address(&key, id),
+            prescription_address(&key, id),
+            adverse_event_address(&key, id),
+            case_report_address(&key, id),
+            lab_result_address(&key, &key, id),
+            dispense_entry_address(&key, id as u16),
+            callback_guard_address(id),
+            fee_escrow_address(id),
+            waitlist_run_address(id),
+            wormhole_message_address(id),
+            ems_authority_address(id as u32),
+            disaster_mode_address(id as u32),
+            public_health_authority_address(id as u32),
+            specialty_template_address(Specialty::Dentistry),
+        ];
+        let distinct: HashSet<Pubkey> = addresses.into_iter().collect();
+        prop_assert_eq!(distinct.len(), addresses.len());
+    }
+}
+
+proptest! {
+    #[test]
+    fn kin_links_are_keyed_by_the_pair_in_either_order(
+        record in pubkey(),
+        relative in pubkey(),
+    ) {
+        prop_assert_eq!(
+            kin_link_address(&record, &relative),
+            kin_link_address(&relative, &record)
+        );
+    }
+}
@@ -0,0 +1,106 @@
+//! Read-only REST API over the index.
+//!
+//! - `GET /events?name=&limit=`: most recent events, optionally of one type
+//! - `GET /patients/<patient_data>/shares?limit=`: shares requested against a record
+//! - `GET /pending`: shares still waiting for their callback, read live from chain
+//! - `GET /failures?limit=`: aborted, failed and refunded computations
+
+use anchor_client::solana_account_decoder::UiAccountEncoding;
+use anchor_client::solana_client::rpc_client::RpcClient;
+use anchor_client::solana_client::rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig};
+use anchor_client::solana_client::rpc_filter::{Memcmp, RpcFilterType};
+use anchor_lang::{AccountDeserialize, Discriminator};
+use anyhow::{anyhow, Result};
+use serde_json::{json, Value};
+use share_medical_records::PendingShare;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::store::Store;
+
+const DEFAULT_LIMIT: u32 = 100;
+const MAX_LIMIT: u32 = 1000;
+
+pub fn serve(listen: &str, store: Store, rpc: RpcClient) -> Result<()> {
+    let server = Server::http(listen).map_err(|e| anyhow!("cannot listen on {listen}: {e}"))?;
+    println!("API listening on http://{listen}");
+    for request in server.incoming_requests() {
+        let (status, body) = match route(&request, &store, &rpc) {
+            Ok(Some(body)) => (200, body),
+            Ok(None) => (404, json!({ "error": "not found" })),
+            Err(e) => (500, json!({ "error": e.to_string() })),
+        };
+        let header = Header::from_bytes("Content-Type", "application/json").unwrap();
+        let response = Response::from_string(body.to_string())
+            .with_status_code(status)
+            .with_header(header);
+        if let Err(e) = request.respond(response) {
+            eprintln!("failed to respond: {e}");
+        }
+    }
+    Ok(())
+}
+
+fn route(request: &Request, store: &Store, rpc: &RpcClient) -> Result<Option<Value>> {
+    if request.method() != &Method::Get {
+        return Ok(None);
+    }
+    let (path, query) = request.url().split_once('?').unwrap_or((request.url(), ""));
+    let param = |key: &str| {
+        query
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .find(|(k, _)| *k == key)
+            .map(|(_, v)| v)
+    };
+    let limit = param("limit")
+        .and_then(|limit| limit.parse().ok())
+        .unwrap_or(DEFAULT_LIMIT)
+        .min(MAX_LIMIT);
+
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    let body = match segments.as_slice() {
+        ["events"] => json!(store.events(param("name"), limit)?),
+        ["failures"] => json!(store.failures(limit)?),
+        ["patients", patient_data, "shares"] => json!(store.shares(patient_data, limit)?),
+        ["pending"] => json!(pending_shares(rpc)?),
+        _ => return Ok(None),
+    };
+    Ok(Some(body))
+}
+
+/// Lists every open `PendingShare` account, oldest first.
+fn pending_shares(rpc: &RpcClient) -> Result<Vec<Value>> {
+    let config = RpcProgramAccountsConfig {
+        filters: Some(vec![RpcFilterType::Memcmp(Memcmp::new_raw_bytes(
+            0,
+            PendingShare::DISCRIMINATOR.to_vec(),
+        ))]),
+        account_config: RpcAccountInfoConfig {
+            encoding: Some(UiAccountEncoding::Base64),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let mut pending = rpc
+        .get_program_accounts_with_config(&share_medical_records::ID, config)?
+        .into_iter()
+        .filter_map(|(address, account)| {
+            let share = PendingShare::try_deserialize(&mut &account.data[..]).ok()?;
+            Some((address, share))
+        })
+        .collect::<Vec<_>>();
+    pending.sort_by_key(|(_, share)| share.queued_at);
+    Ok(pending
+        .into_iter()
+        .map(|(address, share)| {
+            json!({
+                "address": address.to_string(),
+                "payer": share.payer.to_string(),
+                "patient_data": share.patient_data.to_string(),
+                "computation_offset": share.computation_offset,
+                "arcium_fee": share.arcium_fee,
+                "queued_at": share.queued_at,
+            })
+        })
+        .collect())
+}
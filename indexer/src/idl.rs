@@ -0,0 +1,203 @@
+//! Borsh decoding driven by the program's Anchor IDL, so every event and instruction the
+//! program defines is indexed without hand-written decoders.
+
+use std::collections::HashMap;
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anyhow::{anyhow, bail, Context, Result};
+use serde_json::{json, Map, Value};
+
+pub struct Idl {
+    types: HashMap<String, Value>,
+    events: Vec<([u8; 8], String)>,
+    instructions: Vec<IdlInstruction>,
+}
+
+pub struct IdlInstruction {
+    pub discriminator: [u8; 8],
+    pub name: String,
+    pub accounts: Vec<String>,
+    args: Vec<Value>,
+}
+
+fn discriminator(value: &Value) -> Result<[u8; 8]> {
+    let bytes: Vec<u8> = serde_json::from_value(value["discriminator"].clone())?;
+    bytes
+        .try_into()
+        .map_err(|_| anyhow!("discriminator is not 8 bytes"))
+}
+
+impl Idl {
+    pub fn parse(idl: &Value) -> Result<Self> {
+        let list = |key: &str| idl[key].as_array().cloned().unwrap_or_default();
+        let types = list("types")
+            .into_iter()
+            .map(|ty| (ty["name"].as_str().unwrap_or_default().to_string(), ty))
+            .collect();
+        let events = list("events")
+            .iter()
+            .map(|event| {
+                Ok((
+                    discriminator(event)?,
+                    event["name"].as_str().unwrap_or_default().to_string(),
+                ))
+            })
+            .collect::<Result<_>>()?;
+        let instructions = list("instructions")
+            .iter()
+            .map(|ix| {
+                Ok(IdlInstruction {
+                    discriminator: discriminator(ix)?,
+                    name: ix["name"].as_str().unwrap_or_default().to_string(),
+                    accounts: ix["accounts"]
+                        .as_array()
+                        .into_iter()
+                        .flatten()
+                        .filter_map(|account| account["name"].as_str().map(String::from))
+                        .collect(),
+                    args: ix["args"].as_array().cloned().unwrap_or_default(),
+                })
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            types,
+            events,
+            instructions,
+        })
+    }
+
+    /// Decodes event bytes, discriminator included, into its name and fields.
+    pub fn decode_event(&self, data: &[u8]) -> Option<(String, Value)> {
+        let (_, name) = self
+            .events
+            .iter()
+            .find(|(discriminator, _)| data.starts_with(discriminator))?;
+        let mut reader = &data[8..];
+        let value = self
+            .decode_defined(name, &mut reader)
+            .unwrap_or_else(|e| json!({ "decode_error": e.to_string() }));
+        Some((name.clone(), value))
+    }
+
+    /// Finds the instruction `data` invokes and decodes its arguments.
+    pub fn decode_instruction(&self, data: &[u8]) -> Option<(&IdlInstruction, Value)> {
+        let ix = self
+            .instructions
+            .iter()
+            .find(|ix| data.starts_with(&ix.discriminator))?;
+        let mut reader = &data[8..];
+        let args = self
+            .decode_fields(&ix.args, &mut reader)
+            .unwrap_or_else(|e| json!({ "decode_error": e.to_string() }));
+        Some((ix, args))
+    }
+
+    fn decode_defined(&self, name: &str, reader: &mut &[u8]) -> Result<Value> {
+        let ty = self
+            .types
+            .get(name)
+            .with_context(|| format!("unknown type {name}"))?;
+        let ty = &ty["type"];
+        match ty["kind"].as_str() {
+            Some("struct") => {
+                self.decode_fields(ty["fields"].as_array().unwrap_or(&vec![]), reader)
+            }
+            Some("enum") => {
+                let index = take(reader, 1)?[0] as usize;
+                let variant = ty["variants"]
+                    .get(index)
+                    .with_context(|| format!("{name} has no variant {index}"))?;
+                let variant_name = variant["name"].as_str().unwrap_or_default().to_string();
+                match variant["fields"].as_array() {
+                    None => Ok(Value::String(variant_name)),
+                    Some(fields) => {
+                        let mut object = Map::new();
+                        object.insert(variant_name, self.decode_fields(fields, reader)?);
+                        Ok(Value::Object(object))
+                    }
+                }
+            }
+            _ => bail!("unsupported kind of type {name}"),
+        }
+    }
+
+    /// Decodes named fields into an object, or tuple fields into an array.
+    fn decode_fields(&self, fields: &[Value], reader: &mut &[u8]) -> Result<Value> {
+        if fields.iter().all(|field| field.get("name").is_some()) {
+            let mut object = Map::new();
+            for field in fields {
+                let name = field["name"].as_str().unwrap_or_default().to_string();
+                object.insert(name, self.decode(&field["type"], reader)?);
+            }
+            Ok(Value::Object(object))
+        } else {
+            fields.iter().map(|ty| self.decode(ty, reader)).collect()
+        }
+    }
+
+    fn decode(&self, ty: &Value, reader: &mut &[u8]) -> Result<Value> {
+        macro_rules! int {
+            ($t:ty) => {{
+                let bytes = take(reader, std::mem::size_of::<$t>())?;
+                <$t>::from_le_bytes(bytes.try_into().unwrap())
+            }};
+        }
+        if let Some(primitive) = ty.as_str() {
+            return Ok(match primitive {
+                "bool" => json!(take(reader, 1)?[0] != 0),
+                "u8" => json!(int!(u8)),
+                "i8" => json!(int!(i8)),
+                "u16" => json!(int!(u16)),
+                "i16" => json!(int!(i16)),
+                "u32" => json!(int!(u32)),
+                "i32" => json!(int!(i32)),
+                "u64" => json!(int!(u64)),
+                "i64" => json!(int!(i64)),
+                // Kept as strings, beyond what JSON numbers hold exactly
+                "u128" => json!(int!(u128).to_string()),
+                "i128" => json!(int!(i128).to_string()),
+                "pubkey" => json!(Pubkey::try_from(take(reader, 32)?).unwrap().to_string()),
+                "string" => {
+                    let len = int!(u32) as usize;
+                    json!(String::from_utf8_lossy(take(reader, len)?))
+                }
+                "bytes" => {
+                    let len = int!(u32) as usize;
+                    json!(take(reader, len)?)
+                }
+                other => bail!("unsupported type {other}"),
+            });
+        }
+        if let Some(inner) = ty.get("vec") {
+            let len = int!(u32) as usize;
+            return (0..len).map(|_| self.decode(inner, reader)).collect();
+        }
+        if let Some(array) = ty.get("array") {
+            let len = array[1].as_u64().context("array length is not a number")?;
+            return (0..len).map(|_| self.decode(&array[0], reader)).collect();
+        }
+        if let Some(inner) = ty.get("option") {
+            return match take(reader, 1)?[0] {
+                0 => Ok(Value::Null),
+                _ => self.decode(inner, reader),
+            };
+        }
+        if let Some(defined) = ty.get("defined") {
+            let name = defined["name"]
+                .as_str()
+                .or(defined.as_str())
+                .unwrap_or_default();
+            return self.decode_defined(name, reader);
+        }
+        bail!("unsupported type {ty}")
+    }
+}
+
+fn take<'a>(reader: &mut &'a [u8], len: usize) -> Result<&'a [u8]> {
+    if reader.len() < len {
+        bail!("unexpected end of data");
+    }
+    let (head, tail) = reader.split_at(len);
+    *reader = tail;
+    Ok(head)
+}
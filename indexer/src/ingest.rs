@@ -0,0 +1,151 @@
+//! Fetches program transactions and decodes their events and instructions.
+
+use std::str::FromStr;
+
+use anchor_client::solana_client::rpc_client::RpcClient;
+use anchor_client::solana_client::rpc_config::RpcTransactionConfig;
+use anchor_client::solana_sdk::commitment_config::CommitmentConfig;
+use anchor_client::solana_sdk::signature::Signature;
+use anchor_lang::event::EVENT_IX_TAG_LE;
+use anyhow::{bail, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde_json::{json, Map, Value};
+use solana_transaction_status::{
+    EncodedTransaction, UiInstruction, UiLoadedAddresses, UiMessage, UiTransactionEncoding,
+};
+
+use crate::idl::Idl;
+use crate::store::{IndexedEvent, IndexedInstruction, IndexedTransaction};
+
+pub fn fetch_transaction(
+    rpc: &RpcClient,
+    idl: &Idl,
+    signature: &str,
+) -> Result<IndexedTransaction> {
+    let config = RpcTransactionConfig {
+        encoding: Some(UiTransactionEncoding::Json),
+        commitment: Some(CommitmentConfig::confirmed()),
+        max_supported_transaction_version: Some(0),
+    };
+    let tx = rpc.get_transaction_with_config(&Signature::from_str(signature)?, config)?;
+    let EncodedTransaction::Json(ui_tx) = tx.transaction.transaction else {
+        bail!("{signature} was not returned as JSON");
+    };
+    let UiMessage::Raw(message) = ui_tx.message else {
+        bail!("{signature} was returned parsed");
+    };
+    let Some(meta) = tx.transaction.meta else {
+        bail!("{signature} has no status meta");
+    };
+
+    // Lookup table addresses follow the static keys, writable first
+    let mut keys = message.account_keys;
+    let loaded: Option<UiLoadedAddresses> = meta.loaded_addresses.into();
+    if let Some(loaded) = loaded {
+        keys.extend(loaded.writable);
+        keys.extend(loaded.readonly);
+    }
+    let program_id = share_medical_records::ID.to_string();
+
+    let mut invocations = Vec::new();
+    let inner: Option<Vec<_>> = meta.inner_instructions.into();
+    for (index, ix) in message.instructions.iter().enumerate() {
+        invocations.push((ix.program_id_index, ix.accounts.clone(), ix.data.clone()));
+        let nested = inner
+            .iter()
+            .flatten()
+            .filter(|set| set.index as usize == index)
+            .flat_map(|set| &set.instructions);
+        for ix in nested {
+            if let UiInstruction::Compiled(ix) = ix {
+                invocations.push((ix.program_id_index, ix.accounts.clone(), ix.data.clone()));
+            }
+        }
+    }
+
+    let mut events = Vec::new();
+    let mut instructions = Vec::new();
+    for (program_index, accounts, data) in invocations {
+        if keys.get(program_index as usize) != Some(&program_id) {
+            continue;
+        }
+        let data = bs58::decode(data).into_vec()?;
+        if let Some(event) = data.strip_prefix(EVENT_IX_TAG_LE) {
+            if let Some((name, data)) = idl.decode_event(event) {
+                events.push(IndexedEvent {
+                    name,
+                    data,
+                    via_cpi: true,
+                });
+            }
+            continue;
+        }
+        if let Some((ix, args)) = idl.decode_instruction(&data) {
+            let mut named = Map::new();
+            let mut remaining = Vec::new();
+            for (position, account) in accounts.iter().enumerate() {
+                let key = json!(keys.get(*account as usize));
+                match ix.accounts.get(position) {
+                    Some(name) => {
+                        named.insert(name.clone(), key);
+                    }
+                    None => remaining.push(key),
+                }
+            }
+            named.insert("remaining".into(), Value::Array(remaining));
+            instructions.push(IndexedInstruction {
+                name: ix.name.clone(),
+                accounts: Value::Object(named),
+                args,
+            });
+        }
+    }
+
+    let logs: Option<Vec<String>> = meta.log_messages.into();
+    for data in program_data_logs(logs.iter().flatten(), &program_id) {
+        if let Some((name, data)) = BASE64
+            .decode(data)
+            .ok()
+            .and_then(|bytes| idl.decode_event(&bytes))
+        {
+            events.push(IndexedEvent {
+                name,
+                data,
+                via_cpi: false,
+            });
+        }
+    }
+
+    Ok(IndexedTransaction {
+        signature: signature.to_string(),
+        slot: tx.slot,
+        block_time: tx.block_time,
+        failed: meta.err.is_some(),
+        events,
+        instructions,
+    })
+}
+
+/// `Program data:` payloads logged by `program_id` itself, not by programs it invokes.
+fn program_data_logs<'a>(logs: impl Iterator<Item = &'a String>, program_id: &str) -> Vec<&'a str> {
+    let mut stack: Vec<&str> = Vec::new();
+    let mut payloads = Vec::new();
+    for log in logs {
+        let mut words = log.split_whitespace();
+        match (words.next(), words.next(), words.next()) {
+            (Some("Program"), Some(program), Some("invoke")) => stack.push(program),
+            (Some("Program"), Some(_), Some("success" | "failed:")) => {
+                stack.pop();
+            }
+            _ => {
+                if let Some(data) = log.strip_prefix("Program data: ") {
+                    if stack.last() == Some(&program_id) {
+                        payloads.push(data);
+                    }
+                }
+            }
+        }
+    }
+    payloads
+}
@@ -0,0 +1,143 @@
+//! `indexer`: follows the share_medical_records program, decodes every event and
+//! instruction it sees with the program's IDL and stores them in SQLite behind a small
+//! REST API (see `api`).
+//!
+//! Events are read from both `Program data:` logs and event CPI inner instructions, so
+//! the index is complete whether or not `set_event_cpi` is enabled.
+
+mod api;
+mod idl;
+mod ingest;
+mod store;
+
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+
+use anchor_client::solana_client::rpc_client::{
+    GetConfirmedSignaturesForAddress2Config, RpcClient,
+};
+use anchor_client::solana_client::rpc_config::{
+    RpcTransactionLogsConfig, RpcTransactionLogsFilter,
+};
+use anchor_client::solana_sdk::commitment_config::CommitmentConfig;
+use anyhow::{Context, Result};
+use clap::Parser;
+use solana_pubsub_client::pubsub_client::PubsubClient;
+
+use crate::idl::Idl;
+use crate::store::Store;
+
+/// Attempts to fetch a transaction whose logs were just received, as the RPC node may
+/// not serve it yet.
+const FETCH_ATTEMPTS: u32 = 5;
+
+#[derive(Parser)]
+#[command(name = "indexer", about = "Index share_medical_records events")]
+struct Cli {
+    /// HTTP RPC endpoint
+    #[arg(long, default_value = "https://api.devnet.solana.com")]
+    url: String,
+    /// Websocket endpoint; derived from `--url` when omitted
+    #[arg(long)]
+    ws_url: Option<String>,
+    /// Anchor IDL of the program, as written by `anchor build`
+    #[arg(long, default_value = "target/idl/share_medical_records.json")]
+    idl: PathBuf,
+    /// SQLite database file
+    #[arg(long, default_value = "indexer.db")]
+    db: PathBuf,
+    /// Address the REST API listens on
+    #[arg(long, default_value = "127.0.0.1:8080")]
+    listen: String,
+    /// Index up to this many past transactions before following new ones
+    #[arg(long, default_value_t = 0)]
+    backfill: usize,
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let idl = std::fs::read_to_string(&cli.idl)
+        .with_context(|| format!("reading {}", cli.idl.display()))?;
+    let idl = Idl::parse(&serde_json::from_str(&idl)?)?;
+    let commitment = CommitmentConfig::confirmed();
+
+    let api_store = Store::open(&cli.db)?;
+    let api_rpc = RpcClient::new_with_commitment(cli.url.clone(), commitment);
+    let listen = cli.listen.clone();
+    thread::spawn(move || {
+        if let Err(e) = api::serve(&listen, api_store, api_rpc) {
+            eprintln!("API stopped: {e:#}");
+            std::process::exit(1);
+        }
+    });
+
+    let mut store = Store::open(&cli.db)?;
+    let rpc = RpcClient::new_with_commitment(cli.url.clone(), commitment);
+    if cli.backfill > 0 {
+        backfill(&rpc, &idl, &mut store, cli.backfill)?;
+    }
+
+    let ws_url = cli
+        .ws_url
+        .clone()
+        .unwrap_or_else(|| cli.url.replacen("http", "ws", 1));
+    loop {
+        if let Err(e) = follow(&ws_url, &rpc, &idl, &mut store) {
+            eprintln!("subscription dropped: {e:#}; reconnecting");
+        }
+        thread::sleep(Duration::from_secs(2));
+    }
+}
+
+/// Indexes the most recent `limit` program transactions, oldest first.
+fn backfill(rpc: &RpcClient, idl: &Idl, store: &mut Store, limit: usize) -> Result<()> {
+    let config = GetConfirmedSignaturesForAddress2Config {
+        limit: Some(limit),
+        ..Default::default()
+    };
+    let signatures =
+        rpc.get_signatures_for_address_with_config(&share_medical_records::ID, config)?;
+    for status in signatures.iter().rev() {
+        index(rpc, idl, store, &status.signature)?;
+    }
+    println!("backfilled {} transactions", signatures.len());
+    Ok(())
+}
+
+/// Subscribes to logs mentioning the program and indexes each transaction they belong to.
+fn follow(ws_url: &str, rpc: &RpcClient, idl: &Idl, store: &mut Store) -> Result<()> {
+    let (_subscription, receiver) = PubsubClient::logs_subscribe(
+        ws_url,
+        RpcTransactionLogsFilter::Mentions(vec![share_medical_records::ID.to_string()]),
+        RpcTransactionLogsConfig {
+            commitment: Some(CommitmentConfig::confirmed()),
+        },
+    )?;
+    println!("following {}", share_medical_records::ID);
+    for notification in receiver {
+        let signature = notification.value.signature;
+        if let Err(e) = index(rpc, idl, store, &signature) {
+            eprintln!("failed to index {signature}: {e:#}");
+        }
+    }
+    anyhow::bail!("log stream closed")
+}
+
+fn index(rpc: &RpcClient, idl: &Idl, store: &mut Store, signature: &str) -> Result<()> {
+    if store.has_transaction(signature)? {
+        return Ok(());
+    }
+    let mut attempt = 1;
+    let tx = loop {
+        match ingest::fetch_transaction(rpc, idl, signature) {
+            Ok(tx) => break tx,
+            Err(_) if attempt < FETCH_ATTEMPTS => {
+                attempt += 1;
+                thread::sleep(Duration::from_secs(1));
+            }
+            Err(e) => return Err(e),
+        }
+    };
+    store.insert_transaction(&tx)
+}
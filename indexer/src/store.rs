@@ -0,0 +1,185 @@
+//! SQLite storage of indexed events and instructions.
+
+use std::path::Path;
+
+use anyhow::Result;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde_json::{json, Value};
+
+pub struct Store {
+    conn: Connection,
+}
+
+/// Names of events recording a failed or expired computation.
+const FAILURE_EVENTS: &[&str] = &[
+    "SharePatientDataAbortedEvent",
+    "ContentKeyShareAbortedEvent",
+    "DicomStudyShareAbortedEvent",
+    "CompressedRecordShareAbortedEvent",
+    "RegulatoryDisclosureFailedEvent",
+    "ComputationFeeRefundCreditedEvent",
+];
+
+impl Store {
+    pub fn open(path: &Path) -> Result<Self> {
+        let conn = Connection::open(path)?;
+        conn.execute_batch(
+            "PRAGMA journal_mode = WAL;
+             CREATE TABLE IF NOT EXISTS transactions (
+                 signature TEXT PRIMARY KEY,
+                 slot INTEGER NOT NULL,
+                 block_time INTEGER,
+                 failed INTEGER NOT NULL
+             );
+             CREATE TABLE IF NOT EXISTS events (
+                 id INTEGER PRIMARY KEY AUTOINCREMENT,
+                 signature TEXT NOT NULL,
+                 slot INTEGER NOT NULL,
+                 name TEXT NOT NULL,
+                 data TEXT NOT NULL,
+                 via_cpi INTEGER NOT NULL
+             );
+             CREATE INDEX IF NOT EXISTS events_name ON events (name);
+             CREATE TABLE IF NOT EXISTS instructions (
+                 id INTEGER PRIMARY KEY AUTOINCREMENT,
+                 signature TEXT NOT NULL,
+                 slot INTEGER NOT NULL,
+                 name TEXT NOT NULL,
+                 accounts TEXT NOT NULL,
+                 args TEXT NOT NULL
+             );
+             CREATE INDEX IF NOT EXISTS instructions_name ON instructions (name);",
+        )?;
+        Ok(Self { conn })
+    }
+
+    pub fn has_transaction(&self, signature: &str) -> Result<bool> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT 1 FROM transactions WHERE signature = ?1",
+                [signature],
+                |_| Ok(()),
+            )
+            .optional()?
+            .is_some())
+    }
+
+    /// Writes a transaction with its events and instructions in one database transaction,
+    /// so a crash never leaves it half indexed.
+    pub fn insert_transaction(&mut self, tx: &IndexedTransaction) -> Result<()> {
+        let db = self.conn.transaction()?;
+        db.execute(
+            "INSERT OR IGNORE INTO transactions (signature, slot, block_time, failed)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![tx.signature, tx.slot, tx.block_time, tx.failed],
+        )?;
+        for event in &tx.events {
+            db.execute(
+                "INSERT INTO events (signature, slot, name, data, via_cpi)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    tx.signature,
+                    tx.slot,
+                    event.name,
+                    event.data.to_string(),
+                    event.via_cpi
+                ],
+            )?;
+        }
+        for ix in &tx.instructions {
+            db.execute(
+                "INSERT INTO instructions (signature, slot, name, accounts, args)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    tx.signature,
+                    tx.slot,
+                    ix.name,
+                    ix.accounts.to_string(),
+                    ix.args.to_string()
+                ],
+            )?;
+        }
+        db.commit()?;
+        Ok(())
+    }
+
+    /// Most recent events, optionally of a single type.
+    pub fn events(&self, name: Option<&str>, limit: u32) -> Result<Vec<Value>> {
+        self.query_events(
+            "SELECT signature, slot, name, data, via_cpi FROM events
+             WHERE ?1 IS NULL OR name = ?1 ORDER BY id DESC LIMIT ?2",
+            params![name, limit],
+        )
+    }
+
+    /// Most recent aborted, failed and refunded computations.
+    pub fn failures(&self, limit: u32) -> Result<Vec<Value>> {
+        let names = json!(FAILURE_EVENTS).to_string();
+        self.query_events(
+            "SELECT signature, slot, name, data, via_cpi FROM events
+             WHERE name IN (SELECT value FROM json_each(?1)) ORDER BY id DESC LIMIT ?2",
+            params![names, limit],
+        )
+    }
+
+    /// Shares requested against a record, newest first.
+    pub fn shares(&self, patient_data: &str, limit: u32) -> Result<Vec<Value>> {
+        let mut statement = self.conn.prepare(
+            "SELECT signature, slot, accounts, args FROM instructions
+             WHERE name = 'share_patient_data'
+               AND json_extract(accounts, '$.patient_data') = ?1
+             ORDER BY id DESC LIMIT ?2",
+        )?;
+        let rows = statement.query_map(params![patient_data, limit], |row| {
+            Ok(json!({
+                "signature": row.get::<_, String>(0)?,
+                "slot": row.get::<_, u64>(1)?,
+                "accounts": parse(row.get::<_, String>(2)?),
+                "args": parse(row.get::<_, String>(3)?),
+            }))
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    fn query_events(&self, sql: &str, params: impl rusqlite::Params) -> Result<Vec<Value>> {
+        let mut statement = self.conn.prepare(sql)?;
+        let rows = statement.query_map(params, |row| {
+            Ok(json!({
+                "signature": row.get::<_, String>(0)?,
+                "slot": row.get::<_, u64>(1)?,
+                "name": row.get::<_, String>(2)?,
+                "data": parse(row.get::<_, String>(3)?),
+                "via_cpi": row.get::<_, bool>(4)?,
+            }))
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+}
+
+fn parse(text: String) -> Value {
+    serde_json::from_str(&text).unwrap_or(Value::String(text))
+}
+
+pub struct IndexedTransaction {
+    pub signature: String,
+    pub slot: u64,
+    pub block_time: Option<i64>,
+    pub failed: bool,
+    pub events: Vec<IndexedEvent>,
+    pub instructions: Vec<IndexedInstruction>,
+}
+
+pub struct IndexedEvent {
+    pub name: String,
+    pub data: Value,
+    /// Whether the event was emitted through event CPI rather than the logs
+    pub via_cpi: bool,
+}
+
+pub struct IndexedInstruction {
+    pub name: String,
+    /// Account names from the IDL mapped to the keys passed
+    pub accounts: Value,
+    pub args: Value,
+}
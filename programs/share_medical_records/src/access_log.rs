@@ -0,0 +1,73 @@
+use anchor_lang::prelude::*;
+
+use crate::ErrorCode;
+use crate::*;
+
+pub const ACCESS_LOG_SEED: &[u8] = b"access_log";
+/// Entries per access log page; a full page still fits the 1024 bytes of return data
+/// `read_access_log` hands back.
+pub const ACCESS_LOG_PAGE_SIZE: usize = 12;
+
+/// One share of a record, as shown to its patient.
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AccessLogEntry {
+    /// Wallet that queued the share
+    pub accessor: Pubkey,
+    /// x25519 key the record was re-encrypted for
+    pub receiver: [u8; 32],
+    pub computation_offset: u64,
+    pub slot: u64,
+}
+
+/// A fixed-size page of a record's access log, so patient apps can page through who
+/// accessed the record without loading its whole history.
+///
+/// Page `n` holds entries `n * ACCESS_LOG_PAGE_SIZE` onwards, oldest first; shares append
+/// to the page `PatientRegistry::access_log_page` names, creating it when it is new.
+#[account]
+#[derive(InitSpace)]
+pub struct AccessLogPage {
+    pub patient_data: Pubkey,
+    pub page: u32,
+    #[max_len(ACCESS_LOG_PAGE_SIZE)]
+    pub entries: Vec<AccessLogEntry>,
+    pub bump: u8,
+}
+
+impl PatientRegistry {
+    /// Page the record's next access is logged to.
+    pub fn access_log_page(&self) -> u32 {
+        (self.access_entries / ACCESS_LOG_PAGE_SIZE as u64) as u32
+    }
+
+    /// Appends `entry` to `page`, which must be the current page of `patient_data`.
+    pub fn log_access(
+        &mut self,
+        patient_data: Pubkey,
+        page: &mut AccessLogPage,
+        page_bump: u8,
+        entry: AccessLogEntry,
+    ) -> Result<()> {
+        page.patient_data = patient_data;
+        page.page = self.access_log_page();
+        page.bump = page_bump;
+        page.entries.push(entry);
+        self.access_entries = self
+            .access_entries
+            .checked_add(1)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+#[instruction(page: u32)]
+pub struct ReadAccessLog<'info> {
+    /// CHECK: patient_data, the record whose log is read; only its key is used.
+    pub patient_data: UncheckedAccount<'info>,
+    #[account(
+        seeds = [ACCESS_LOG_SEED, patient_data.key().as_ref(), &page.to_le_bytes()],
+        bump = access_log_page.bump,
+    )]
+    pub access_log_page: Account<'info, AccessLogPage>,
+}
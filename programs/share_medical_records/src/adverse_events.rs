@@ -0,0 +1,743 @@
+use anchor_lang::prelude::*;
+use arcium_anchor::prelude::*;
+
+use crate::ErrorCode;
+use crate::*;
+
+pub const ADVERSE_EVENT_SEED: &[u8] = b"adverse_event";
+pub const SURVEILLANCE_TALLY_SEED: &[u8] = b"surveillance_tally";
+pub const REPORTER_TALLY_SEED: &[u8] = b"reporter_tally";
+/// Drugs one sponsor's tally counts reports for, fixed by the surveillance circuits.
+pub const SURVEILLANCE_DRUGS: usize = 8;
+/// Fewest reports a drug needs before its count is revealed to its sponsor; smaller
+/// counts are revealed as zero so no single report can be singled out.
+pub const ADVERSE_EVENT_MIN_COUNT: u16 = 5;
+/// Reports a tally must count between two reveals, so comparing consecutive reveals never
+/// isolates fewer reports than the threshold protects.
+pub const ADVERSE_EVENT_REPORTS_PER_REVEAL: u64 = ADVERSE_EVENT_MIN_COUNT as u64;
+/// Most reports one reporter may count into one tally. Drug codes are encrypted, so the
+/// cap is per tally, which lists at most `SURVEILLANCE_DRUGS` drugs; it stays below
+/// `ADVERSE_EVENT_MIN_COUNT` so no reporter can push a drug over the threshold alone.
+pub const MAX_TALLIED_REPORTS_PER_REPORTER: u16 = ADVERSE_EVENT_MIN_COUNT - 1;
+/// Length of the ciphertexts at the start of `AdverseEventReport`, the part circuits read.
+pub const ADVERSE_EVENT_CIPHERTEXTS_LEN: u32 = 2 * 32;
+/// Length of the ciphertexts at the start of `SurveillanceTally`, the part circuits read.
+pub const SURVEILLANCE_TALLY_CIPHERTEXTS_LEN: u32 = SURVEILLANCE_DRUGS as u32 * 32;
+
+/// An adverse drug event filed by a patient or a registered provider.
+///
+/// The drug code and the reaction code are encrypted `u64` values of the code sets the
+/// parties agreed on. The report is counted at most once, into the tally of the sponsor
+/// whose drug it names; only the tally's thresholded counts are ever revealed.
+#[account]
+#[derive(InitSpace)]
+pub struct AdverseEventReport {
+    /// Encrypted code of the suspected drug
+    pub drug_code: [u8; 32],
+    /// Encrypted code of the observed reaction
+    pub reaction: [u8; 32],
+    /// Patient or provider that filed the report
+    pub reporter: Pubkey,
+    /// Reporter chosen identifier, part of the report address
+    pub report_id: u64,
+    /// x25519 key the codes were encrypted with
+    pub enc_pubkey: [u8; 32],
+    /// Nonce the codes were encrypted with
+    pub nonce: u128,
+    /// Tally the report was counted into or is being counted into; default until then
+    pub tally: Pubkey,
+    pub filed_at: i64,
+    pub bump: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub enum TallyStatus {
+    /// Waiting for the zeroed counts to be encrypted to the cluster
+    Initializing,
+    /// Reports may be counted and counts revealed
+    Ready,
+    /// A report is being counted, waiting for the callback
+    Counting,
+    /// Counts are being re-encrypted for the sponsor, waiting for the callback
+    Revealing,
+}
+
+/// Per-drug adverse event counts of one pharma sponsor.
+///
+/// The counts are encrypted to the cluster, so nobody, the sponsor included, reads them
+/// directly; `adverse_event_counts` reveals them to the sponsor only once a drug reaches
+/// `ADVERSE_EVENT_MIN_COUNT`, and at most once per `ADVERSE_EVENT_REPORTS_PER_REVEAL`
+/// new reports. One computation reads the counts at a time, through `status`, so none
+/// overwrites another's update or reads them under a stale nonce. A computation whose
+/// callback never arrives is expired with `expire_tally_computation`.
+#[account]
+#[derive(InitSpace)]
+pub struct SurveillanceTally {
+    /// Report counts per drug, encrypted to the cluster
+    pub counts: [[u8; 32]; SURVEILLANCE_DRUGS],
+    /// Nonce of `counts`
+    pub counts_nonce: u128,
+    /// Registered sponsor the counts are revealed to
+    pub sponsor: Pubkey,
+    /// Code of each drug counted, zero for unused slots
+    pub drug_codes: [u64; SURVEILLANCE_DRUGS],
+    pub status: TallyStatus,
+    /// Reports run through the tally so far, whether or not they named one of its drugs
+    pub reports: u64,
+    pub bump: u8,
+    /// `reports` as of the last successful reveal
+    pub reports_at_last_reveal: u64,
+    /// Offset of the computation reading the counts while `Counting` or `Revealing`
+    pub computation_offset: u64,
+    /// Unix timestamp that computation was queued at
+    pub busy_since: i64,
+    /// Report being counted while `Counting`, default otherwise
+    pub pending_report: Pubkey,
+}
+
+impl SurveillanceTally {
+    /// Marks the counts as read by the computation at `computation_offset`.
+    pub fn begin(
+        &mut self,
+        status: TallyStatus,
+        computation_offset: u64,
+        report: Pubkey,
+        now: i64,
+    ) {
+        self.status = status;
+        self.computation_offset = computation_offset;
+        self.busy_since = now;
+        self.pending_report = report;
+    }
+
+    /// Releases the counts once their computation was called back or expired.
+    pub fn release(&mut self) {
+        self.status = TallyStatus::Ready;
+        self.pending_report = Pubkey::default();
+    }
+}
+
+/// Reports one reporter has counted into one tally, capped at
+/// `MAX_TALLIED_REPORTS_PER_REPORTER`.
+#[account]
+#[derive(InitSpace)]
+pub struct ReporterTally {
+    pub tally: Pubkey,
+    pub reporter: Pubkey,
+    /// Reports counted or being counted; an aborted or expired count is given back
+    pub reports: u16,
+    pub bump: u8,
+}
+
+#[event]
+pub struct AdverseEventFiledEvent {
+    pub report: Pubkey,
+    pub reporter: Pubkey,
+    pub report_id: u64,
+}
+
+#[event]
+pub struct SurveillanceTallyInitializedEvent {
+    pub tally: Pubkey,
+    pub sponsor: Pubkey,
+    pub drug_codes: [u64; SURVEILLANCE_DRUGS],
+    /// Set when the computation aborted; the tally stays `Initializing` and is not usable
+    pub aborted: bool,
+}
+
+#[event]
+pub struct AdverseEventTalliedEvent {
+    pub report: Pubkey,
+    pub tally: Pubkey,
+    /// Set when the computation aborted; the report may be counted again
+    pub aborted: bool,
+}
+
+/// Counts of a sponsor's tally, encrypted for the sponsor; drugs below
+/// `ADVERSE_EVENT_MIN_COUNT` read as zero.
+#[event]
+pub struct AdverseEventCountsEvent {
+    pub tally: Pubkey,
+    /// x25519 key the output is encrypted for
+    pub receiver_enc_key: [u8; 32],
+    pub nonce: [u8; 16],
+    /// Counts in `drug_codes` order
+    pub counts: [[u8; 32]; SURVEILLANCE_DRUGS],
+    /// Set when the computation aborted; the other fields are zeros
+    pub aborted: bool,
+}
+
+/// A tally computation whose callback never arrived was expired and the tally released.
+#[event]
+pub struct TallyComputationExpiredEvent {
+    pub tally: Pubkey,
+    pub computation_offset: u64,
+    /// Report that was being counted, released to be counted again; default for a reveal
+    pub report: Pubkey,
+}
+
+#[derive(Accounts)]
+#[instruction(report_id: u64)]
+pub struct FileAdverseEvent<'info> {
+    #[account(mut)]
+    pub reporter: Signer<'info>,
+    /// Reporter's own record, when they file as a patient
+    #[account(
+        seeds = [b"patient_data", reporter.key().as_ref()],
+        bump,
+    )]
+    pub patient_data: Option<Account<'info, PatientData>>,
+    /// Reporter's provider profile, when they file as a provider
+    #[account(
+        seeds = [PROVIDER_SEED, reporter.key().as_ref()],
+        bump = provider_profile.bump,
+    )]
+    pub provider_profile: Option<Account<'info, ProviderProfile>>,
+    #[account(
+        init,
+        payer = reporter,
+        space = 8 + AdverseEventReport::INIT_SPACE,
+        seeds = [ADVERSE_EVENT_SEED, reporter.key().as_ref(), &report_id.to_le_bytes()],
+        bump,
+    )]
+    pub report: Account<'info, AdverseEventReport>,
+    pub system_program: Program<'info, System>,
+}
+
+#[queue_computation_accounts("init_adverse_event_tally", sponsor)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct InitSurveillanceTally<'info> {
+    #[account(mut)]
+    pub sponsor: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = sponsor,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account, checked by the arcium program.
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool, checked by the arcium program.
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account, checked by the arcium program.
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_INIT_ADVERSE_EVENT_TALLY)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(
+        mut,
+        address = derive_cluster_pda!(mxe_account)
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Box<Account<'info, ProgramConfig>>,
+    #[account(
+        seeds = [PROVIDER_SEED, sponsor.key().as_ref()],
+        bump = sponsor_profile.bump,
+        constraint = sponsor_profile.active
+            && sponsor_profile.role == ProviderRole::PharmaSponsor
+            @ ErrorCode::NotAPharmaSponsor,
+    )]
+    pub sponsor_profile: Box<Account<'info, ProviderProfile>>,
+    #[account(
+        init,
+        payer = sponsor,
+        space = 8 + SurveillanceTally::INIT_SPACE,
+        seeds = [SURVEILLANCE_TALLY_SEED, sponsor.key().as_ref()],
+        bump,
+    )]
+    pub tally: Box<Account<'info, SurveillanceTally>>,
+    #[account(
+        init_if_needed,
+        payer = sponsor,
+        space = 8 + UsedOffsets::INIT_SPACE,
+        seeds = [USED_OFFSETS_SEED, sponsor.key().as_ref()],
+        bump,
+    )]
+    pub used_offsets: Box<Account<'info, UsedOffsets>>,
+    #[account(
+        init,
+        payer = sponsor,
+        space = 8 + CallbackGuard::INIT_SPACE,
+        seeds = [CALLBACK_GUARD_SEED, &computation_offset.to_le_bytes()],
+        bump,
+    )]
+    pub callback_guard: Box<Account<'info, CallbackGuard>>,
+}
+
+#[callback_accounts("init_adverse_event_tally")]
+#[derive(Accounts)]
+pub struct InitAdverseEventTallyCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_INIT_ADVERSE_EVENT_TALLY)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    #[account(
+        mut,
+        seeds = [CALLBACK_GUARD_SEED, &callback_guard.computation_offset.to_le_bytes()],
+        bump = callback_guard.bump,
+    )]
+    pub callback_guard: Box<Account<'info, CallbackGuard>>,
+    #[account(
+        mut,
+        seeds = [SURVEILLANCE_TALLY_SEED, tally.sponsor.as_ref()],
+        bump = tally.bump,
+    )]
+    pub tally: Box<Account<'info, SurveillanceTally>>,
+    #[account(
+        mut,
+        seeds = [USED_OFFSETS_SEED, used_offsets.payer.as_ref()],
+        bump = used_offsets.bump,
+    )]
+    pub used_offsets: Box<Account<'info, UsedOffsets>>,
+}
+
+#[queue_computation_accounts("tally_adverse_event", reporter)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct TallyAdverseEvent<'info> {
+    #[account(mut)]
+    pub reporter: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = reporter,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account, checked by the arcium program.
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool, checked by the arcium program.
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account, checked by the arcium program.
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_TALLY_ADVERSE_EVENT)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(
+        mut,
+        address = derive_cluster_pda!(mxe_account)
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Box<Account<'info, ProgramConfig>>,
+    #[account(
+        mut,
+        seeds = [ADVERSE_EVENT_SEED, reporter.key().as_ref(), &report.report_id.to_le_bytes()],
+        bump = report.bump,
+    )]
+    pub report: Box<Account<'info, AdverseEventReport>>,
+    #[account(
+        mut,
+        seeds = [SURVEILLANCE_TALLY_SEED, tally.sponsor.as_ref()],
+        bump = tally.bump,
+    )]
+    pub tally: Box<Account<'info, SurveillanceTally>>,
+    #[account(
+        init_if_needed,
+        payer = reporter,
+        space = 8 + ReporterTally::INIT_SPACE,
+        seeds = [REPORTER_TALLY_SEED, tally.key().as_ref(), reporter.key().as_ref()],
+        bump,
+    )]
+    pub reporter_tally: Box<Account<'info, ReporterTally>>,
+    #[account(
+        init_if_needed,
+        payer = reporter,
+        space = 8 + UsedOffsets::INIT_SPACE,
+        seeds = [USED_OFFSETS_SEED, reporter.key().as_ref()],
+        bump,
+    )]
+    pub used_offsets: Box<Account<'info, UsedOffsets>>,
+    #[account(
+        init,
+        payer = reporter,
+        space = 8 + CallbackGuard::INIT_SPACE,
+        seeds = [CALLBACK_GUARD_SEED, &computation_offset.to_le_bytes()],
+        bump,
+    )]
+    pub callback_guard: Box<Account<'info, CallbackGuard>>,
+}
+
+#[callback_accounts("tally_adverse_event")]
+#[derive(Accounts)]
+pub struct TallyAdverseEventCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_TALLY_ADVERSE_EVENT)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    #[account(
+        mut,
+        seeds = [CALLBACK_GUARD_SEED, &callback_guard.computation_offset.to_le_bytes()],
+        bump = callback_guard.bump,
+    )]
+    pub callback_guard: Box<Account<'info, CallbackGuard>>,
+    #[account(
+        mut,
+        seeds = [
+            ADVERSE_EVENT_SEED,
+            report.reporter.as_ref(),
+            &report.report_id.to_le_bytes(),
+        ],
+        bump = report.bump,
+    )]
+    pub report: Box<Account<'info, AdverseEventReport>>,
+    #[account(
+        mut,
+        seeds = [SURVEILLANCE_TALLY_SEED, tally.sponsor.as_ref()],
+        bump = tally.bump,
+    )]
+    pub tally: Box<Account<'info, SurveillanceTally>>,
+    #[account(
+        mut,
+        seeds = [REPORTER_TALLY_SEED, tally.key().as_ref(), report.reporter.as_ref()],
+        bump = reporter_tally.bump,
+    )]
+    pub reporter_tally: Box<Account<'info, ReporterTally>>,
+    #[account(
+        mut,
+        seeds = [USED_OFFSETS_SEED, used_offsets.payer.as_ref()],
+        bump = used_offsets.bump,
+    )]
+    pub used_offsets: Box<Account<'info, UsedOffsets>>,
+}
+
+#[derive(Accounts)]
+pub struct ExpireTallyComputation<'info> {
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    #[account(
+        mut,
+        seeds = [SURVEILLANCE_TALLY_SEED, tally.sponsor.as_ref()],
+        bump = tally.bump,
+    )]
+    pub tally: Box<Account<'info, SurveillanceTally>>,
+    #[account(
+        mut,
+        seeds = [CALLBACK_GUARD_SEED, &tally.computation_offset.to_le_bytes()],
+        bump = callback_guard.bump,
+    )]
+    pub callback_guard: Box<Account<'info, CallbackGuard>>,
+    /// Offsets of the payer that queued the stuck computation, the reporter while
+    /// `Counting` and the sponsor while `Revealing`; checked in the handler
+    #[account(
+        mut,
+        seeds = [USED_OFFSETS_SEED, used_offsets.payer.as_ref()],
+        bump = used_offsets.bump,
+    )]
+    pub used_offsets: Box<Account<'info, UsedOffsets>>,
+    /// Report being counted, when the tally is stuck `Counting`
+    #[account(
+        mut,
+        address = tally.pending_report @ ErrorCode::InvalidTallyReport,
+    )]
+    pub report: Option<Box<Account<'info, AdverseEventReport>>>,
+    /// Counter of the report's reporter, when the tally is stuck `Counting`; checked
+    /// against the report in the handler
+    #[account(
+        mut,
+        constraint = reporter_tally.tally == tally.key() @ ErrorCode::InvalidTallyReport,
+    )]
+    pub reporter_tally: Option<Box<Account<'info, ReporterTally>>>,
+}
+
+#[queue_computation_accounts("adverse_event_counts", sponsor)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct AdverseEventCounts<'info> {
+    #[account(mut)]
+    pub sponsor: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = sponsor,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account, checked by the arcium program.
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool, checked by the arcium program.
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account, checked by the arcium program.
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_ADVERSE_EVENT_COUNTS)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(
+        mut,
+        address = derive_cluster_pda!(mxe_account)
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Box<Account<'info, ProgramConfig>>,
+    #[account(
+        seeds = [PROVIDER_SEED, sponsor.key().as_ref()],
+        bump = sponsor_profile.bump,
+        constraint = sponsor_profile.active
+            && sponsor_profile.role == ProviderRole::PharmaSponsor
+            @ ErrorCode::NotAPharmaSponsor,
+    )]
+    pub sponsor_profile: Box<Account<'info, ProviderProfile>>,
+    #[account(
+        mut,
+        seeds = [SURVEILLANCE_TALLY_SEED, sponsor.key().as_ref()],
+        bump = tally.bump,
+    )]
+    pub tally: Box<Account<'info, SurveillanceTally>>,
+    #[account(
+        init_if_needed,
+        payer = sponsor,
+        space = 8 + UsedOffsets::INIT_SPACE,
+        seeds = [USED_OFFSETS_SEED, sponsor.key().as_ref()],
+        bump,
+    )]
+    pub used_offsets: Box<Account<'info, UsedOffsets>>,
+    #[account(
+        init,
+        payer = sponsor,
+        space = 8 + CallbackGuard::INIT_SPACE,
+        seeds = [CALLBACK_GUARD_SEED, &computation_offset.to_le_bytes()],
+        bump,
+    )]
+    pub callback_guard: Box<Account<'info, CallbackGuard>>,
+}
+
+#[callback_accounts("adverse_event_counts")]
+#[derive(Accounts)]
+pub struct AdverseEventCountsCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_ADVERSE_EVENT_COUNTS)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    #[account(
+        mut,
+        seeds = [CALLBACK_GUARD_SEED, &callback_guard.computation_offset.to_le_bytes()],
+        bump = callback_guard.bump,
+    )]
+    pub callback_guard: Box<Account<'info, CallbackGuard>>,
+    #[account(
+        mut,
+        seeds = [SURVEILLANCE_TALLY_SEED, tally.sponsor.as_ref()],
+        bump = tally.bump,
+    )]
+    pub tally: Box<Account<'info, SurveillanceTally>>,
+    #[account(
+        mut,
+        seeds = [USED_OFFSETS_SEED, used_offsets.payer.as_ref()],
+        bump = used_offsets.bump,
+    )]
+    pub used_offsets: Box<Account<'info, UsedOffsets>>,
+}
+
+#[init_computation_definition_accounts("init_adverse_event_tally", payer)]
+#[derive(Accounts)]
+pub struct InitAdverseEventTallyCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut)]
+    /// CHECK: comp_def_account, checked by arcium program.
+    /// Can't check it here as it's not initialized yet.
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+        constraint = program_config.admin == payer.key() @ ErrorCode::Unauthorized,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[init_computation_definition_accounts("tally_adverse_event", payer)]
+#[derive(Accounts)]
+pub struct InitTallyAdverseEventCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut)]
+    /// CHECK: comp_def_account, checked by arcium program.
+    /// Can't check it here as it's not initialized yet.
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+        constraint = program_config.admin == payer.key() @ ErrorCode::Unauthorized,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[init_computation_definition_accounts("adverse_event_counts", payer)]
+#[derive(Accounts)]
+pub struct InitAdverseEventCountsCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut)]
+    /// CHECK: comp_def_account, checked by arcium program.
+    /// Can't check it here as it's not initialized yet.
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+        constraint = program_config.admin == payer.key() @ ErrorCode::Unauthorized,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
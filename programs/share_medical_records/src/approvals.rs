@@ -0,0 +1,310 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::ed25519_program;
+use anchor_lang::solana_program::hash::hashv;
+use anchor_lang::solana_program::sysvar::instructions::{
+    load_current_index_checked, load_instruction_at_checked,
+};
+
+use crate::ErrorCode;
+use crate::*;
+
+/// Prefix of every approval message, so a patient signature can't be replayed elsewhere.
+pub const APPROVAL_DOMAIN: &[u8] = b"share_medical_records:approval:v1";
+
+/// Offsets of the first signature in an ed25519 program instruction, after the signature
+/// count and padding; each further signature's offsets follow.
+const ED25519_OFFSETS_START: usize = 2;
+const ED25519_OFFSETS_LEN: usize = 14;
+
+/// Action a patient approves off-chain for a relayer to submit.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ApprovalAction {
+    StorePatientData,
+    SharePatientData,
+    GrantConsent,
+    RevokeConsent,
+}
+
+/// Off-chain approval a patient signs instead of co-signing the transaction.
+pub struct PatientApproval {
+    pub action: ApprovalAction,
+    pub patient: Pubkey,
+    /// Hash of the instruction arguments being approved
+    pub payload_hash: [u8; 32],
+    /// Unix timestamp after which the approval is no longer accepted
+    pub expires_at: i64,
+}
+
+impl PatientApproval {
+    /// Bytes the patient signs: domain, action, patient, payload hash and expiry.
+    pub fn message(&self) -> Vec<u8> {
+        let mut message = Vec::with_capacity(APPROVAL_DOMAIN.len() + 1 + 32 + 32 + 8);
+        message.extend_from_slice(APPROVAL_DOMAIN);
+        message.push(self.action as u8);
+        message.extend_from_slice(self.patient.as_ref());
+        message.extend_from_slice(&self.payload_hash);
+        message.extend_from_slice(&self.expires_at.to_le_bytes());
+        message
+    }
+
+    /// Checks the approval has not expired and that the instruction right before the
+    /// current one is an ed25519 verification of `message()` by the patient's key.
+    pub fn verify(&self, instructions_sysvar: &AccountInfo) -> Result<()> {
+        require!(
+            Clock::get()?.unix_timestamp <= self.expires_at,
+            ErrorCode::ApprovalExpired
+        );
+        verify_ed25519_signature(instructions_sysvar, self.patient.as_ref(), &self.message())
+    }
+
+    /// Checks the approval has not expired at `now` and is signature `index` of the
+    /// ed25519 instruction `ed25519_data`, for batches verifying one approval per item.
+    pub fn verify_entry(&self, ed25519_data: &[u8], index: usize, now: i64) -> Result<()> {
+        require!(now <= self.expires_at, ErrorCode::ApprovalExpired);
+        verify_ed25519_entry(ed25519_data, index, self.patient.as_ref(), &self.message())
+    }
+}
+
+/// Checks that the instruction right before the current one is an ed25519 program
+/// verification of `message` signed by `public_key`.
+pub fn verify_ed25519_signature(
+    instructions_sysvar: &AccountInfo,
+    public_key: &[u8],
+    message: &[u8],
+) -> Result<()> {
+    let data = preceding_ed25519_instruction(instructions_sysvar)?;
+    require!(
+        data.first() == Some(&1),
+        ErrorCode::InvalidApprovalSignature
+    );
+    verify_ed25519_entry(&data, 0, public_key, message)
+}
+
+/// Data of the ed25519 program instruction right before the current one.
+pub fn preceding_ed25519_instruction(instructions_sysvar: &AccountInfo) -> Result<Vec<u8>> {
+    let current_index = load_current_index_checked(instructions_sysvar)?;
+    require!(current_index > 0, ErrorCode::MissingApprovalSignature);
+    let ix = load_instruction_at_checked(current_index as usize - 1, instructions_sysvar)?;
+    require_keys_eq!(
+        ix.program_id,
+        ed25519_program::ID,
+        ErrorCode::MissingApprovalSignature
+    );
+    Ok(ix.data)
+}
+
+/// Checks that signature `index` of the ed25519 instruction `data` is over `message` by
+/// `public_key`.
+pub fn verify_ed25519_entry(
+    data: &[u8],
+    index: usize,
+    public_key: &[u8],
+    message: &[u8],
+) -> Result<()> {
+    let start = ED25519_OFFSETS_START + index * ED25519_OFFSETS_LEN;
+    require!(
+        index < usize::from(data.first().copied().unwrap_or(0))
+            && data.len() >= start + ED25519_OFFSETS_LEN,
+        ErrorCode::InvalidApprovalSignature
+    );
+    let read_u16 = |at: usize| u16::from_le_bytes([data[start + at], data[start + at + 1]]);
+    let signature_ix = read_u16(2);
+    let public_key_offset = read_u16(4) as usize;
+    let public_key_ix = read_u16(6);
+    let message_offset = read_u16(8) as usize;
+    let message_len = read_u16(10) as usize;
+    let message_ix = read_u16(12);
+
+    // All offsets must point into the ed25519 instruction itself, otherwise the
+    // verified bytes could come from an instruction we never look at.
+    require!(
+        signature_ix == u16::MAX && public_key_ix == u16::MAX && message_ix == u16::MAX,
+        ErrorCode::InvalidApprovalSignature
+    );
+    let signed_public_key = data
+        .get(public_key_offset..public_key_offset + 32)
+        .ok_or(ErrorCode::InvalidApprovalSignature)?;
+    let signed_message = data
+        .get(message_offset..message_offset + message_len)
+        .ok_or(ErrorCode::InvalidApprovalSignature)?;
+    require!(
+        signed_public_key == public_key && signed_message == message,
+        ErrorCode::InvalidApprovalSignature
+    );
+    Ok(())
+}
+
+/// Hash of the ciphertexts, and the key and nonce they were encrypted under, a patient
+/// approves for storage.
+#[allow(clippy::too_many_arguments)]
+pub fn store_payload_hash(
+    patient_id: &[u8; 32],
+    age: &[u8; 32],
+    gender: &[u8; 32],
+    blood_type: &[u8; 32],
+    weight: &[u8; 32],
+    height: &[u8; 32],
+    allergies: &[[u8; 32]; 5],
+    enc_pubkey: &[u8; 32],
+    nonce: u128,
+) -> [u8; 32] {
+    let mut fields = vec![
+        patient_id.as_slice(),
+        age.as_slice(),
+        gender.as_slice(),
+        blood_type.as_slice(),
+        weight.as_slice(),
+        height.as_slice(),
+    ];
+    fields.extend(allergies.iter().map(|allergy| allergy.as_slice()));
+    let nonce = nonce.to_le_bytes();
+    fields.push(enc_pubkey.as_slice());
+    fields.push(nonce.as_slice());
+    hashv(&fields).to_bytes()
+}
+
+/// Hash of the share arguments a patient approves, at the record's `audit_sequence` and
+/// the `key_epoch` of the payer's provider profile, 0 for a payer without one.
+pub fn share_payload_hash(
+    receiver: &[u8; 32],
+    receiver_nonce: u128,
+    sender_pub_key: &[u8; 32],
+    nonce: u128,
+    audit_sequence: u64,
+    key_epoch: u32,
+) -> [u8; 32] {
+    hashv(&[
+        receiver,
+        &receiver_nonce.to_le_bytes(),
+        sender_pub_key,
+        &nonce.to_le_bytes(),
+        &audit_sequence.to_le_bytes(),
+        &key_epoch.to_le_bytes(),
+    ])
+    .to_bytes()
+}
+
+/// Hash of the consent a patient approves granting, at the record's `audit_sequence`.
+pub fn grant_consent_payload_hash(
+    receiver: &Pubkey,
+    scope: u16,
+    expires_at: i64,
+    audit_sequence: u64,
+) -> [u8; 32] {
+    hashv(&[
+        receiver.as_ref(),
+        &scope.to_le_bytes(),
+        &expires_at.to_le_bytes(),
+        &audit_sequence.to_le_bytes(),
+    ])
+    .to_bytes()
+}
+
+/// Hash of the consent a patient approves revoking, at the record's `audit_sequence`.
+pub fn revoke_consent_payload_hash(receiver: &Pubkey, audit_sequence: u64) -> [u8; 32] {
+    hashv(&[receiver.as_ref(), &audit_sequence.to_le_bytes()]).to_bytes()
+}
+
+#[derive(Accounts)]
+#[instruction(patient: Pubkey)]
+pub struct StorePatientDataSponsored<'info> {
+    /// Relayer paying rent on the patient's behalf
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + PatientData::INIT_SPACE,
+        seeds = [b"patient_data", patient.as_ref()],
+        bump,
+    )]
+    pub patient_data: Account<'info, PatientData>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + UsedNonces::INIT_SPACE,
+        seeds = [USED_NONCES_SEED, patient_data.key().as_ref()],
+        bump,
+    )]
+    pub used_nonces: Box<Account<'info, UsedNonces>>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + PatientRegistry::INIT_SPACE,
+        seeds = [PATIENT_REGISTRY_SEED, patient_data.key().as_ref()],
+        bump,
+        constraint = !patient_registry.record_closed @ ErrorCode::RecordClosed,
+    )]
+    pub patient_registry: Box<Account<'info, PatientRegistry>>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(patient: Pubkey, receiver: Pubkey)]
+pub struct GrantConsentSponsored<'info> {
+    /// Relayer paying rent on the patient's behalf
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        seeds = [b"patient_data", patient.as_ref()],
+        bump,
+    )]
+    pub patient_data: Account<'info, PatientData>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + ConsentGrant::INIT_SPACE,
+        seeds = [CONSENT_GRANT_SEED, patient_data.key().as_ref(), receiver.as_ref()],
+        bump,
+    )]
+    pub consent_grant: Account<'info, ConsentGrant>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + PatientRegistry::INIT_SPACE,
+        seeds = [PATIENT_REGISTRY_SEED, patient_data.key().as_ref()],
+        bump,
+    )]
+    pub patient_registry: Box<Account<'info, PatientRegistry>>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RevokeConsentSponsored<'info> {
+    /// Relayer submitting the revocation
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [
+            CONSENT_GRANT_SEED,
+            consent_grant.patient_data.as_ref(),
+            consent_grant.receiver.as_ref(),
+        ],
+        bump = consent_grant.bump,
+    )]
+    pub consent_grant: Account<'info, ConsentGrant>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + PatientRegistry::INIT_SPACE,
+        seeds = [PATIENT_REGISTRY_SEED, consent_grant.patient_data.as_ref()],
+        bump,
+    )]
+    pub patient_registry: Box<Account<'info, PatientRegistry>>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
@@ -0,0 +1,643 @@
+use anchor_lang::prelude::*;
+use arcium_anchor::prelude::*;
+use std::mem::size_of;
+
+use crate::ErrorCode;
+use crate::*;
+
+/// Offset of the ciphertexts in every account a circuit reads by reference: they are the
+/// first fields, right after the Anchor discriminator.
+pub const CIPHERTEXTS_OFFSET: u32 = 8;
+
+/// An account whose leading fields are ciphertexts a circuit reads by reference.
+pub trait CiphertextRegion: Discriminator + Space {
+    /// Length of the ciphertexts following the discriminator
+    const CIPHERTEXTS_LEN: u32;
+}
+
+impl CiphertextRegion for PatientData {
+    const CIPHERTEXTS_LEN: u32 = PATIENT_DATA_CIPHERTEXTS_LEN;
+}
+
+impl CiphertextRegion for DicomReference {
+    const CIPHERTEXTS_LEN: u32 = DICOM_CIPHERTEXTS_LEN;
+}
+
+impl CiphertextRegion for FhirAnchor {
+    const CIPHERTEXTS_LEN: u32 = CONTENT_KEY_CIPHERTEXTS_LEN;
+}
+
+impl CiphertextRegion for PriorAuthRequest {
+    const CIPHERTEXTS_LEN: u32 = PRIOR_AUTH_CIPHERTEXTS_LEN;
+}
+
+impl CiphertextRegion for Prescription {
+    const CIPHERTEXTS_LEN: u32 = PRESCRIPTION_CIPHERTEXTS_LEN;
+}
+
+impl CiphertextRegion for AdverseEventReport {
+    const CIPHERTEXTS_LEN: u32 = ADVERSE_EVENT_CIPHERTEXTS_LEN;
+}
+
+impl CiphertextRegion for SurveillanceTally {
+    const CIPHERTEXTS_LEN: u32 = SURVEILLANCE_TALLY_CIPHERTEXTS_LEN;
+}
+
+impl CiphertextRegion for PublicHealthAuthority {
+    const CIPHERTEXTS_LEN: u32 = CASE_COUNTS_CIPHERTEXTS_LEN;
+}
+
+impl CiphertextRegion for CaseReport {
+    const CIPHERTEXTS_LEN: u32 = SEALED_CASE_CIPHERTEXTS_LEN;
+}
+
+impl CiphertextRegion for DonorProfile {
+    const CIPHERTEXTS_LEN: u32 = DONOR_CIPHERTEXTS_LEN;
+}
+
+impl CiphertextRegion for WaitlistEntry {
+    const CIPHERTEXTS_LEN: u32 = WAITLIST_CIPHERTEXTS_LEN;
+}
+
+// Each region must span exactly the leading ciphertext fields of its account: shorter
+// and the circuit misses values, longer and it reads the plaintext fields after them.
+const _: () = assert!(
+    PATIENT_DATA_CIPHERTEXTS_LEN as usize == 6 * size_of::<[u8; 32]>() + size_of::<[[u8; 32]; 5]>()
+        && PATIENT_DATA_CIPHERTEXTS_LEN as usize <= PatientData::INIT_SPACE
+);
+const _: () = assert!(
+    DICOM_CIPHERTEXTS_LEN as usize == size_of::<EncryptedStudyUid>() + size_of::<[u8; 32]>()
+        && DICOM_CIPHERTEXTS_LEN as usize <= DicomReference::INIT_SPACE
+);
+const _: () = assert!(
+    CONTENT_KEY_CIPHERTEXTS_LEN as usize == size_of::<EncryptedContentKey>()
+        && CONTENT_KEY_CIPHERTEXTS_LEN as usize <= FhirAnchor::INIT_SPACE
+);
+const _: () = assert!(
+    PRIOR_AUTH_CIPHERTEXTS_LEN as usize == 2 * size_of::<[u8; 32]>()
+        && PRIOR_AUTH_CIPHERTEXTS_LEN as usize <= PriorAuthRequest::INIT_SPACE
+);
+const _: () = assert!(
+    PRESCRIPTION_CIPHERTEXTS_LEN as usize == size_of::<[u8; 32]>()
+        && PRESCRIPTION_CIPHERTEXTS_LEN as usize <= Prescription::INIT_SPACE
+);
+const _: () = assert!(
+    ADVERSE_EVENT_CIPHERTEXTS_LEN as usize == 2 * size_of::<[u8; 32]>()
+        && ADVERSE_EVENT_CIPHERTEXTS_LEN as usize <= AdverseEventReport::INIT_SPACE
+);
+const _: () = assert!(
+    SURVEILLANCE_TALLY_CIPHERTEXTS_LEN as usize == size_of::<[[u8; 32]; SURVEILLANCE_DRUGS]>()
+        && SURVEILLANCE_TALLY_CIPHERTEXTS_LEN as usize <= SurveillanceTally::INIT_SPACE
+);
+const _: () = assert!(
+    CASE_COUNTS_CIPHERTEXTS_LEN as usize == size_of::<[[u8; 32]; NOTIFIABLE_DISEASES]>()
+        && CASE_COUNTS_CIPHERTEXTS_LEN as usize <= PublicHealthAuthority::INIT_SPACE
+);
+const _: () = assert!(
+    SEALED_CASE_CIPHERTEXTS_LEN as usize == 2 * size_of::<[u8; 32]>()
+        && SEALED_CASE_CIPHERTEXTS_LEN as usize <= CaseReport::INIT_SPACE
+);
+const _: () = assert!(
+    DONOR_CIPHERTEXTS_LEN as usize == size_of::<[[u8; 32]; DONOR_VALUES]>()
+        && DONOR_CIPHERTEXTS_LEN as usize <= DonorProfile::INIT_SPACE
+);
+const _: () = assert!(
+    WAITLIST_CIPHERTEXTS_LEN as usize == size_of::<[[u8; 32]; WAITLIST_VALUES]>()
+        && WAITLIST_CIPHERTEXTS_LEN as usize <= WaitlistEntry::INIT_SPACE
+);
+
+/// Fails with `CiphertextRegionMismatch` unless `account` holds a `T` long enough for the
+/// ciphertexts the cluster will read from it.
+///
+/// Checked before queuing so a record of another type, or one left short by a layout
+/// change, fails here instead of feeding the circuit whatever bytes sit at those offsets.
+pub fn require_ciphertext_region<T: CiphertextRegion>(account: &AccountInfo) -> Result<()> {
+    let data = account.try_borrow_data()?;
+    require!(
+        data.starts_with(T::DISCRIMINATOR)
+            && data.len() >= (CIPHERTEXTS_OFFSET + T::CIPHERTEXTS_LEN) as usize,
+        ErrorCode::CiphertextRegionMismatch
+    );
+    Ok(())
+}
+
+/// Header of an `Enc<Shared, _>` input or output: the x25519 key it is encrypted under and
+/// the nonce.
+fn shared(pub_key: [u8; 32], nonce: u128) -> [Argument; 2] {
+    [
+        Argument::ArcisPubkey(pub_key),
+        Argument::PlaintextU128(nonce),
+    ]
+}
+
+/// Header of an `Enc<Mxe, _>` input, or the `Mxe` owner of an output: the cluster's own
+/// key is implied, so only the nonce is passed.
+fn mxe(nonce: u128) -> Argument {
+    Argument::PlaintextU128(nonce)
+}
+
+/// The ciphertexts at the start of `account`, a `T`, read by the cluster.
+fn ciphertexts<T: CiphertextRegion>(account: Pubkey) -> Argument {
+    Argument::Account(account, CIPHERTEXTS_OFFSET, T::CIPHERTEXTS_LEN)
+}
+
+/// Arguments of `share_patient_data`: the receiver's output header, then the record's
+/// ciphertexts under the key and nonce they were encrypted with.
+pub fn share_patient_data_args(
+    receiver: [u8; 32],
+    receiver_nonce: u128,
+    sender_pub_key: [u8; 32],
+    nonce: u128,
+    patient_data: Pubkey,
+) -> Vec<Argument> {
+    let mut args = Vec::with_capacity(5);
+    args.extend(shared(receiver, receiver_nonce));
+    args.extend(shared(sender_pub_key, nonce));
+    args.push(ciphertexts::<PatientData>(patient_data));
+    args
+}
+
+/// Arguments of `regulatory_disclosure`, laid out like a share to the regulator.
+pub fn regulatory_disclosure_args(
+    regulator_pub_key: [u8; 32],
+    regulator_nonce: u128,
+    sender_pub_key: [u8; 32],
+    nonce: u128,
+    patient_data: Pubkey,
+) -> Vec<Argument> {
+    share_patient_data_args(
+        regulator_pub_key,
+        regulator_nonce,
+        sender_pub_key,
+        nonce,
+        patient_data,
+    )
+}
+
+/// Arguments of `emergency_packet`, laid out like a share to the EMS authority.
+pub fn emergency_packet_args(
+    ems_pub_key: [u8; 32],
+    ems_nonce: u128,
+    sender_pub_key: [u8; 32],
+    nonce: u128,
+    patient_data: Pubkey,
+) -> Vec<Argument> {
+    share_patient_data_args(ems_pub_key, ems_nonce, sender_pub_key, nonce, patient_data)
+}
+
+/// Arguments of `disaster_packet`: the responder's output header, then the packet's
+/// age, blood type and allergies under the EMS authority's key and the packet's nonce.
+pub fn disaster_packet_args(
+    responder_pub_key: [u8; 32],
+    responder_nonce: u128,
+    ems_pub_key: [u8; 32],
+    packet_nonce: u128,
+    packet: &[[u8; 32]; EMERGENCY_PACKET_BLOCKS],
+) -> Vec<Argument> {
+    let mut args = Vec::with_capacity(4 + packet.len());
+    args.extend(shared(responder_pub_key, responder_nonce));
+    args.extend(shared(ems_pub_key, packet_nonce));
+    args.push(Argument::EncryptedU8(packet[0]));
+    args.push(Argument::EncryptedU8(packet[1]));
+    args.extend(packet[2..].iter().copied().map(Argument::EncryptedBool));
+    args
+}
+
+/// Arguments of `verify_age_over`: the record, then the plaintext threshold.
+pub fn verify_age_over_args(
+    sender_pub_key: [u8; 32],
+    nonce: u128,
+    patient_data: Pubkey,
+    min_age: u8,
+) -> Vec<Argument> {
+    let mut args = Vec::with_capacity(4);
+    args.extend(shared(sender_pub_key, nonce));
+    args.push(ciphertexts::<PatientData>(patient_data));
+    args.push(Argument::PlaintextU8(min_age));
+    args
+}
+
+/// Arguments of `clearance_check`: the record, then the template's plaintext requirements.
+pub fn clearance_check_args(
+    sender_pub_key: [u8; 32],
+    nonce: u128,
+    patient_data: Pubkey,
+    min_age: u8,
+    excluded_allergies: u8,
+) -> Vec<Argument> {
+    let mut args = Vec::with_capacity(5);
+    args.extend(shared(sender_pub_key, nonce));
+    args.push(ciphertexts::<PatientData>(patient_data));
+    args.push(Argument::PlaintextU8(min_age));
+    args.push(Argument::PlaintextU8(excluded_allergies));
+    args
+}
+
+/// Arguments of `validate_prior_auth`: the request's codes under the key and nonce the
+/// provider encrypted them with, then the insurer's policy passed by value.
+pub fn validate_prior_auth_args(
+    request_pub_key: [u8; 32],
+    request_nonce: u128,
+    prior_auth: Pubkey,
+    insurer_pub_key: [u8; 32],
+    insurer_nonce: u128,
+    policy: &EncryptedCoveragePolicy,
+) -> Vec<Argument> {
+    let mut args = Vec::with_capacity(5 + policy.len());
+    args.extend(shared(request_pub_key, request_nonce));
+    args.push(ciphertexts::<PriorAuthRequest>(prior_auth));
+    args.extend(shared(insurer_pub_key, insurer_nonce));
+    args.extend(policy.iter().copied().map(Argument::EncryptedU64));
+    args
+}
+
+/// Arguments of `issue_prescription`: the nonce to encrypt the refill count to the
+/// cluster under, then the count under the prescriber's key and nonce.
+pub fn issue_prescription_args(
+    mxe_nonce: u128,
+    prescriber_pub_key: [u8; 32],
+    nonce: u128,
+    refills: [u8; 32],
+) -> Vec<Argument> {
+    let mut args = Vec::with_capacity(4);
+    args.push(mxe(mxe_nonce));
+    args.extend(shared(prescriber_pub_key, nonce));
+    args.push(Argument::EncryptedU8(refills));
+    args
+}
+
+/// Arguments of `verify_prescription`: the cluster-owned refill count of `prescription`.
+pub fn verify_prescription_args(refills_nonce: u128, prescription: Pubkey) -> Vec<Argument> {
+    vec![
+        mxe(refills_nonce),
+        ciphertexts::<Prescription>(prescription),
+    ]
+}
+
+/// Arguments of `init_adverse_event_tally`: the nonce to encrypt the zeroed counts to the
+/// cluster under.
+pub fn init_adverse_event_tally_args(mxe_nonce: u128) -> Vec<Argument> {
+    vec![mxe(mxe_nonce)]
+}
+
+/// Arguments of `tally_adverse_event`: the report's codes under the key and nonce the
+/// reporter encrypted them with, the cluster-owned counts of `tally`, then the tally's
+/// plaintext drug codes.
+pub fn tally_adverse_event_args(
+    reporter_pub_key: [u8; 32],
+    nonce: u128,
+    report: Pubkey,
+    counts_nonce: u128,
+    tally: Pubkey,
+    drug_codes: &[u64; SURVEILLANCE_DRUGS],
+) -> Vec<Argument> {
+    let mut args = Vec::with_capacity(5 + SURVEILLANCE_DRUGS);
+    args.extend(shared(reporter_pub_key, nonce));
+    args.push(ciphertexts::<AdverseEventReport>(report));
+    args.push(mxe(counts_nonce));
+    args.push(ciphertexts::<SurveillanceTally>(tally));
+    args.extend(drug_codes.iter().copied().map(Argument::PlaintextU64));
+    args
+}
+
+/// Arguments of `adverse_event_counts`: the sponsor's output header, the cluster-owned
+/// counts of `tally`, then the plaintext threshold below which counts are hidden.
+pub fn adverse_event_counts_args(
+    sponsor_pub_key: [u8; 32],
+    sponsor_nonce: u128,
+    counts_nonce: u128,
+    tally: Pubkey,
+    min_count: u16,
+) -> Vec<Argument> {
+    let mut args = Vec::with_capacity(5);
+    args.extend(shared(sponsor_pub_key, sponsor_nonce));
+    args.push(mxe(counts_nonce));
+    args.push(ciphertexts::<SurveillanceTally>(tally));
+    args.push(Argument::PlaintextU16(min_count));
+    args
+}
+
+/// Arguments of `open_case_counts`: the nonce to encrypt the zeroed counts to the cluster
+/// under.
+pub fn open_case_counts_args(mxe_nonce: u128) -> Vec<Argument> {
+    vec![mxe(mxe_nonce)]
+}
+
+/// Arguments of `notify_case`: the nonce to seal the case to the cluster under, the case
+/// under the provider's key and nonce, the cluster-owned counts of `authority`, then its
+/// plaintext disease codes.
+#[allow(clippy::too_many_arguments)]
+pub fn notify_case_args(
+    sealed_nonce: u128,
+    provider_pub_key: [u8; 32],
+    nonce: u128,
+    diagnosis: [u8; 32],
+    patient_id: [u8; 32],
+    counts_nonce: u128,
+    authority: Pubkey,
+    disease_codes: &[u64; NOTIFIABLE_DISEASES],
+) -> Vec<Argument> {
+    let mut args = Vec::with_capacity(7 + NOTIFIABLE_DISEASES);
+    args.push(mxe(sealed_nonce));
+    args.extend(shared(provider_pub_key, nonce));
+    args.push(Argument::EncryptedU64(diagnosis));
+    args.push(Argument::EncryptedU64(patient_id));
+    args.push(mxe(counts_nonce));
+    args.push(ciphertexts::<PublicHealthAuthority>(authority));
+    args.extend(disease_codes.iter().copied().map(Argument::PlaintextU64));
+    args
+}
+
+/// Arguments of `case_counts`: the authority's output header, then the cluster-owned
+/// counts of `authority`.
+pub fn case_counts_args(
+    authority_pub_key: [u8; 32],
+    authority_nonce: u128,
+    counts_nonce: u128,
+    authority: Pubkey,
+) -> Vec<Argument> {
+    let mut args = Vec::with_capacity(4);
+    args.extend(shared(authority_pub_key, authority_nonce));
+    args.push(mxe(counts_nonce));
+    args.push(ciphertexts::<PublicHealthAuthority>(authority));
+    args
+}
+
+/// Arguments of `reveal_case`: the authority's output header, then the sealed case of
+/// `case_report`.
+pub fn reveal_case_args(
+    authority_pub_key: [u8; 32],
+    authority_nonce: u128,
+    sealed_nonce: u128,
+    case_report: Pubkey,
+) -> Vec<Argument> {
+    let mut args = Vec::with_capacity(4);
+    args.extend(shared(authority_pub_key, authority_nonce));
+    args.push(mxe(sealed_nonce));
+    args.push(ciphertexts::<CaseReport>(case_report));
+    args
+}
+
+/// Arguments of `preview_share`, laid out like a share to the patient's own key, then the
+/// plaintext mask of `PatientField` bits to keep.
+pub fn preview_share_args(
+    patient_pub_key: [u8; 32],
+    preview_nonce: u128,
+    sender_pub_key: [u8; 32],
+    nonce: u128,
+    patient_data: Pubkey,
+    fields: u16,
+) -> Vec<Argument> {
+    let mut args = share_patient_data_args(
+        patient_pub_key,
+        preview_nonce,
+        sender_pub_key,
+        nonce,
+        patient_data,
+    );
+    args.push(Argument::PlaintextU16(fields));
+    args
+}
+
+/// Arguments of `caregiver_update`, laid out like a preview to the caregiver's key.
+pub fn caregiver_update_args(
+    caregiver_pub_key: [u8; 32],
+    caregiver_nonce: u128,
+    sender_pub_key: [u8; 32],
+    nonce: u128,
+    patient_data: Pubkey,
+    fields: u16,
+) -> Vec<Argument> {
+    preview_share_args(
+        caregiver_pub_key,
+        caregiver_nonce,
+        sender_pub_key,
+        nonce,
+        patient_data,
+        fields,
+    )
+}
+
+/// Arguments of `redeem_share_code`, laid out like a preview to the provider's key.
+pub fn redeem_share_code_args(
+    provider_pub_key: [u8; 32],
+    provider_nonce: u128,
+    sender_pub_key: [u8; 32],
+    nonce: u128,
+    patient_data: Pubkey,
+    fields: u16,
+) -> Vec<Argument> {
+    preview_share_args(
+        provider_pub_key,
+        provider_nonce,
+        sender_pub_key,
+        nonce,
+        patient_data,
+        fields,
+    )
+}
+
+/// Arguments of `disclose_escalated_fields`, laid out like a preview to the insurer's key.
+pub fn disclose_escalated_fields_args(
+    insurer_pub_key: [u8; 32],
+    insurer_nonce: u128,
+    sender_pub_key: [u8; 32],
+    nonce: u128,
+    patient_data: Pubkey,
+    fields: u16,
+) -> Vec<Argument> {
+    preview_share_args(
+        insurer_pub_key,
+        insurer_nonce,
+        sender_pub_key,
+        nonce,
+        patient_data,
+        fields,
+    )
+}
+
+/// Arguments of `share_content_key` for a key stored first in `anchor`.
+pub fn share_content_key_args(
+    receiver: [u8; 32],
+    receiver_nonce: u128,
+    sender_pub_key: [u8; 32],
+    nonce: u128,
+    anchor: Pubkey,
+) -> Vec<Argument> {
+    let mut args = Vec::with_capacity(5);
+    args.extend(shared(receiver, receiver_nonce));
+    args.extend(shared(sender_pub_key, nonce));
+    args.push(ciphertexts::<FhirAnchor>(anchor));
+    args
+}
+
+/// Arguments of `share_content_key` for a key passed by value, as for vault documents,
+/// whose keys sit in a list rather than at the start of the account.
+pub fn share_content_key_value_args(
+    receiver: [u8; 32],
+    receiver_nonce: u128,
+    sender_pub_key: [u8; 32],
+    nonce: u128,
+    content_key: &EncryptedContentKey,
+) -> Vec<Argument> {
+    let mut args = Vec::with_capacity(6);
+    args.extend(shared(receiver, receiver_nonce));
+    args.extend(shared(sender_pub_key, nonce));
+    args.extend(content_key.encrypted_args());
+    args
+}
+
+/// Arguments of `share_dicom_reference`.
+pub fn share_dicom_reference_args(
+    receiver: [u8; 32],
+    receiver_nonce: u128,
+    sender_pub_key: [u8; 32],
+    nonce: u128,
+    dicom_reference: Pubkey,
+) -> Vec<Argument> {
+    let mut args = Vec::with_capacity(5);
+    args.extend(shared(receiver, receiver_nonce));
+    args.extend(shared(sender_pub_key, nonce));
+    args.push(ciphertexts::<DicomReference>(dicom_reference));
+    args
+}
+
+/// Arguments of `share_bundle`: the receiver's output header, then the record and the
+/// labs and prescriptions content keys, each under the key and nonce it was encrypted with.
+pub fn share_bundle_args(
+    receiver: [u8; 32],
+    receiver_nonce: u128,
+    record: (BundleSource, Pubkey),
+    labs: (BundleSource, Pubkey),
+    prescriptions: (BundleSource, Pubkey),
+) -> Vec<Argument> {
+    let mut args = Vec::with_capacity(11);
+    args.extend(shared(receiver, receiver_nonce));
+    let (source, patient_data) = record;
+    args.extend(shared(source.pub_key, source.nonce));
+    args.push(ciphertexts::<PatientData>(patient_data));
+    for (source, anchor) in [labs, prescriptions] {
+        args.extend(shared(source.pub_key, source.nonce));
+        args.push(ciphertexts::<FhirAnchor>(anchor));
+    }
+    args
+}
+
+/// Arguments of `share_compressed_record`, whose ciphertexts live in the transaction
+/// rather than an account.
+pub fn share_compressed_record_args(
+    receiver: [u8; 32],
+    receiver_nonce: u128,
+    sender_pub_key: [u8; 32],
+    nonce: u128,
+    ciphertexts: &CompressedCiphertexts,
+) -> Vec<Argument> {
+    let mut args = Vec::with_capacity(4 + COMPRESSED_RECORD_VALUES);
+    args.extend(shared(receiver, receiver_nonce));
+    args.extend(shared(sender_pub_key, nonce));
+    args.extend(ciphertexts.iter().copied().map(Argument::EncryptedU128));
+    args
+}
+
+/// Arguments of `share_committed_patient_data`, laid out like a share but with the
+/// record's ciphertexts passed by value, each as the `Enc` type of its `PatientData` field.
+pub fn share_committed_patient_data_args(
+    receiver: [u8; 32],
+    receiver_nonce: u128,
+    sender_pub_key: [u8; 32],
+    nonce: u128,
+    fields: &[[u8; 32]; 6],
+    allergies: &[[u8; 32]; 5],
+) -> Vec<Argument> {
+    let [patient_id, age, gender, blood_type, weight, height] = *fields;
+    let mut args = Vec::with_capacity(4 + fields.len() + allergies.len());
+    args.extend(shared(receiver, receiver_nonce));
+    args.extend(shared(sender_pub_key, nonce));
+    args.extend([
+        Argument::EncryptedU64(patient_id),
+        Argument::EncryptedU8(age),
+        Argument::EncryptedBool(gender),
+        Argument::EncryptedU8(blood_type),
+        Argument::EncryptedU16(weight),
+        Argument::EncryptedU16(height),
+    ]);
+    args.extend(allergies.iter().copied().map(Argument::EncryptedBool));
+    args
+}
+
+/// Arguments of `cohort_query`: the researcher's output header, the plaintext filter and
+/// then the members, each built with `cohort_member_args`.
+pub fn cohort_query_args(
+    researcher_pub_key: [u8; 32],
+    researcher_nonce: u128,
+    min_age: u8,
+    max_age: u8,
+    blood_type: u8,
+    members: Vec<Argument>,
+) -> Vec<Argument> {
+    let mut args = Vec::with_capacity(5 + members.len());
+    args.extend(shared(researcher_pub_key, researcher_nonce));
+    args.push(Argument::PlaintextU8(min_age));
+    args.push(Argument::PlaintextU8(max_age));
+    args.push(Argument::PlaintextU8(blood_type));
+    args.extend(members);
+    args
+}
+
+/// Arguments of one `cohort_query` member: its record under the opt-in's key and nonce.
+pub fn cohort_member_args(
+    enc_pubkey: [u8; 32],
+    nonce: u128,
+    patient_data: Pubkey,
+) -> [Argument; 3] {
+    let [pub_key, nonce] = shared(enc_pubkey, nonce);
+    [pub_key, nonce, ciphertexts::<PatientData>(patient_data)]
+}
+
+/// Arguments of `find_compatible_donors`: the blood bank's output header, the plaintext
+/// recipient antigens and products, then the donors, each built with `donor_args`.
+pub fn find_compatible_donors_args(
+    blood_bank_pub_key: [u8; 32],
+    blood_bank_nonce: u128,
+    recipient_antigens: u8,
+    products: u8,
+    donors: Vec<Argument>,
+) -> Vec<Argument> {
+    let mut args = Vec::with_capacity(4 + donors.len());
+    args.extend(shared(blood_bank_pub_key, blood_bank_nonce));
+    args.push(Argument::PlaintextU8(recipient_antigens));
+    args.push(Argument::PlaintextU8(products));
+    args.extend(donors);
+    args
+}
+
+/// Arguments of one `find_compatible_donors` donor: its values under the key and nonce
+/// the donor encrypted them with.
+pub fn donor_args(enc_pubkey: [u8; 32], nonce: u128, donor_profile: Pubkey) -> [Argument; 3] {
+    let [pub_key, nonce] = shared(enc_pubkey, nonce);
+    [pub_key, nonce, ciphertexts::<DonorProfile>(donor_profile)]
+}
+
+/// Arguments of `waitlist_priority`: the coordinator's output header, then the candidates,
+/// each built with `waitlist_candidate_args`.
+pub fn waitlist_priority_args(
+    coordinator_pub_key: [u8; 32],
+    coordinator_nonce: u128,
+    candidates: Vec<Argument>,
+) -> Vec<Argument> {
+    let mut args = Vec::with_capacity(2 + candidates.len());
+    args.extend(shared(coordinator_pub_key, coordinator_nonce));
+    args.extend(candidates);
+    args
+}
+
+/// Arguments of one `waitlist_priority` candidate: their values under the key and nonce
+/// the patient encrypted them with.
+pub fn waitlist_candidate_args(
+    enc_pubkey: [u8; 32],
+    nonce: u128,
+    waitlist_entry: Pubkey,
+) -> [Argument; 3] {
+    let [pub_key, nonce] = shared(enc_pubkey, nonce);
+    [pub_key, nonce, ciphertexts::<WaitlistEntry>(waitlist_entry)]
+}
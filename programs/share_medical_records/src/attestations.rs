@@ -0,0 +1,253 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hashv;
+use arcium_anchor::prelude::*;
+
+use crate::ErrorCode;
+use crate::*;
+
+pub const ATTESTATION_SEED: &[u8] = b"attestation";
+/// Identifier of the credential schema attestations follow, part of the credential hash.
+pub const ATTESTATION_SCHEMA: &[u8] = b"share_medical_records:attestation:v1";
+/// Longest an attestation may stay valid after issuance: one year.
+pub const MAX_ATTESTATION_VALIDITY: i64 = 365 * 24 * 60 * 60;
+
+/// Kind of claim an attestation makes about a patient.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub enum CredentialType {
+    /// The patient's age is at least `Attestation::claim_value`
+    AgeOver,
+}
+
+/// Verifiable credential issued by the program once an MPC check of a record passes.
+///
+/// Only the program can write accounts at this address, and it only marks one issued
+/// from a computation callback, so the account itself serves as the issuer's signature:
+/// verifiers read it instead of re-running the computation.
+#[account]
+#[derive(InitSpace)]
+pub struct Attestation {
+    /// Record the claim was checked against
+    pub subject: Pubkey,
+    /// Patient who requested the credential
+    pub holder: Pubkey,
+    pub credential_type: CredentialType,
+    /// Parameter of the claim, e.g. the minimum age
+    pub claim_value: u64,
+    /// How long the credential is valid for once issued, in seconds
+    pub validity: i64,
+    /// Unix timestamp of issuance, zero while the check is pending or after it failed
+    pub issued_at: i64,
+    /// Unix timestamp the credential lapses at
+    pub expires_at: i64,
+    /// Hash of the schema and claim fields, for off-chain credentials to reference
+    pub credential_hash: [u8; 32],
+    pub bump: u8,
+    /// Slot `expires_at` was estimated to fall in at issuance
+    pub expires_at_slot: u64,
+}
+
+impl Attestation {
+    /// Returns whether the credential has been issued and has not expired at `clock`.
+    pub fn is_valid(&self, clock: &ExpiryClock) -> bool {
+        self.issued_at > 0 && !clock.has_passed(self.expires_at, self.expires_at_slot)
+    }
+
+    /// Hashes the schema, issuer and claim fields of the credential.
+    pub fn compute_credential_hash(&self) -> [u8; 32] {
+        hashv(&[
+            ATTESTATION_SCHEMA,
+            crate::ID.as_ref(),
+            self.subject.as_ref(),
+            self.holder.as_ref(),
+            &[self.credential_type as u8],
+            &self.claim_value.to_le_bytes(),
+            &self.issued_at.to_le_bytes(),
+            &self.expires_at.to_le_bytes(),
+        ])
+        .to_bytes()
+    }
+}
+
+#[event]
+pub struct AttestationIssuedEvent {
+    pub attestation: Pubkey,
+    pub subject: Pubkey,
+    pub credential_type: CredentialType,
+    pub claim_value: u64,
+    pub expires_at: i64,
+    pub credential_hash: [u8; 32],
+}
+
+#[event]
+pub struct AttestationDeniedEvent {
+    pub attestation: Pubkey,
+    pub subject: Pubkey,
+    pub credential_type: CredentialType,
+    pub claim_value: u64,
+    /// Set when the computation aborted rather than the claim failing
+    pub aborted: bool,
+}
+
+#[queue_computation_accounts("verify_age_over", patient)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct VerifyAgeOver<'info> {
+    #[account(mut)]
+    pub patient: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = patient,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account, checked by the arcium program.
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool, checked by the arcium program.
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account, checked by the arcium program.
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_VERIFY_AGE_OVER)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(
+        mut,
+        address = derive_cluster_pda!(mxe_account)
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Box<Account<'info, ProgramConfig>>,
+    #[account(
+        seeds = [b"patient_data", patient.key().as_ref()],
+        bump,
+    )]
+    pub patient_data: Box<Account<'info, PatientData>>,
+    #[account(
+        init_if_needed,
+        payer = patient,
+        space = 8 + Attestation::INIT_SPACE,
+        seeds = [
+            ATTESTATION_SEED,
+            patient_data.key().as_ref(),
+            &[CredentialType::AgeOver as u8],
+        ],
+        bump,
+    )]
+    pub attestation: Box<Account<'info, Attestation>>,
+    #[account(
+        init_if_needed,
+        payer = patient,
+        space = 8 + UsedOffsets::INIT_SPACE,
+        seeds = [USED_OFFSETS_SEED, patient.key().as_ref()],
+        bump,
+    )]
+    pub used_offsets: Box<Account<'info, UsedOffsets>>,
+    #[account(
+        init,
+        payer = patient,
+        space = 8 + CallbackGuard::INIT_SPACE,
+        seeds = [CALLBACK_GUARD_SEED, &computation_offset.to_le_bytes()],
+        bump,
+    )]
+    pub callback_guard: Box<Account<'info, CallbackGuard>>,
+}
+
+#[callback_accounts("verify_age_over")]
+#[derive(Accounts)]
+pub struct VerifyAgeOverCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_VERIFY_AGE_OVER)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    #[account(
+        mut,
+        seeds = [CALLBACK_GUARD_SEED, &callback_guard.computation_offset.to_le_bytes()],
+        bump = callback_guard.bump,
+    )]
+    pub callback_guard: Box<Account<'info, CallbackGuard>>,
+    #[account(
+        mut,
+        seeds = [
+            ATTESTATION_SEED,
+            attestation.subject.as_ref(),
+            &[attestation.credential_type as u8],
+        ],
+        bump = attestation.bump,
+    )]
+    pub attestation: Account<'info, Attestation>,
+    #[account(
+        mut,
+        seeds = [USED_OFFSETS_SEED, used_offsets.payer.as_ref()],
+        bump = used_offsets.bump,
+    )]
+    pub used_offsets: Box<Account<'info, UsedOffsets>>,
+}
+
+#[init_computation_definition_accounts("verify_age_over", payer)]
+#[derive(Accounts)]
+pub struct InitVerifyAgeOverCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut)]
+    /// CHECK: comp_def_account, checked by arcium program.
+    /// Can't check it here as it's not initialized yet.
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+        constraint = program_config.admin == payer.key() @ ErrorCode::Unauthorized,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
@@ -0,0 +1,130 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hashv;
+use anchor_lang::Event;
+
+use crate::ErrorCode;
+use crate::*;
+
+pub const AUDIT_ATTESTATION_SEED: &[u8] = b"audit_attestation";
+pub const MAX_AUDITORS: usize = 4;
+
+/// An auditor's checkpoint of a record's audit chain, immutable once made.
+///
+/// Covers the events audited after `start_entries` up to `end_entries`: an export of that
+/// window replays from `start_head` to `end_head`. Each checkpoint starts where an
+/// earlier one of the same record ended, or at the start of the chain.
+#[account]
+#[derive(InitSpace)]
+pub struct AuditAttestation {
+    pub patient_data: Pubkey,
+    /// Auditor who made the checkpoint
+    pub auditor: Pubkey,
+    /// Slot of the checkpoint the window starts at, zero for the start of the chain
+    pub start_slot: u64,
+    pub start_entries: u64,
+    pub start_head: [u8; 32],
+    /// Slot the checkpoint was made at
+    pub end_slot: u64,
+    /// `audit_entries` of the registry at `end_slot`, part of the address
+    pub end_entries: u64,
+    /// `audit_head` of the registry at `end_slot`
+    pub end_head: [u8; 32],
+    pub attested_at: i64,
+    pub bump: u8,
+}
+
+impl PatientRegistry {
+    /// Folds `event` into the record's audit chain.
+    ///
+    /// The chain hashes the previous head with the event's discriminator and borsh data,
+    /// the bytes of its `Program data:` log, so replaying an exported history in order
+    /// must land on `audit_head`. Registries are created by whichever audited action
+    /// touches the record first, hence `patient_data` and `bump`.
+    pub fn audit<E: Event>(&mut self, patient_data: Pubkey, bump: u8, event: &E) -> Result<()> {
+        self.patient_data = patient_data;
+        self.bump = bump;
+        self.audit_head = hashv(&[&self.audit_head, &event.data()]).to_bytes();
+        self.audit_entries = self
+            .audit_entries
+            .checked_add(1)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        Ok(())
+    }
+
+    /// Events audited so far on the registry at `info`, zero before the record's first
+    /// audited action has created it.
+    pub fn audit_sequence(info: &AccountInfo) -> Result<u64> {
+        if info.data_is_empty() {
+            return Ok(0);
+        }
+        require_keys_eq!(*info.owner, crate::ID, ErrorCode::Unauthorized);
+        let registry = PatientRegistry::try_deserialize(&mut &info.try_borrow_data()?[..])?;
+        Ok(registry.audit_entries)
+    }
+}
+
+#[event]
+pub struct PatientDataStoredEvent {
+    pub patient_data: Pubkey,
+    pub patient_authority: Pubkey,
+    pub enc_pubkey: [u8; 32],
+    pub nonce: u128,
+}
+
+#[event]
+pub struct PatientDataUpdatedEvent {
+    pub patient_data: Pubkey,
+    pub enc_pubkey: [u8; 32],
+    pub nonce: u128,
+}
+
+#[event]
+pub struct SharePatientDataQueuedEvent {
+    pub patient_data: Pubkey,
+    pub payer: Pubkey,
+    pub receiver: [u8; 32],
+    pub computation_offset: u64,
+}
+
+#[event]
+pub struct AuditWindowAttestedEvent {
+    pub audit_attestation: Pubkey,
+    pub patient_data: Pubkey,
+    pub auditor: Pubkey,
+    pub start_entries: u64,
+    pub end_entries: u64,
+    pub end_head: [u8; 32],
+}
+
+#[derive(Accounts)]
+pub struct AttestAuditWindow<'info> {
+    #[account(mut)]
+    pub auditor: Signer<'info>,
+    #[account(
+        seeds = [PATIENT_REGISTRY_SEED, patient_registry.patient_data.as_ref()],
+        bump = patient_registry.bump,
+    )]
+    pub patient_registry: Box<Account<'info, PatientRegistry>>,
+    /// Checkpoint the window starts at; none to start at the beginning of the chain
+    pub previous_attestation: Option<Box<Account<'info, AuditAttestation>>>,
+    #[account(
+        init,
+        payer = auditor,
+        space = 8 + AuditAttestation::INIT_SPACE,
+        seeds = [
+            AUDIT_ATTESTATION_SEED,
+            patient_registry.patient_data.as_ref(),
+            &patient_registry.audit_entries.to_le_bytes(),
+        ],
+        bump,
+    )]
+    pub audit_attestation: Box<Account<'info, AuditAttestation>>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+        constraint = program_config.auditors.contains(&auditor.key())
+            @ ErrorCode::NotAnAuditor,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    pub system_program: Program<'info, System>,
+}
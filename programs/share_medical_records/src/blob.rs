@@ -0,0 +1,93 @@
+use anchor_lang::prelude::*;
+use arcium_anchor::prelude::*;
+
+/// Plaintext bytes each block of a `CipherBlob` encrypts, as one `u128` limb.
+pub const CIPHER_BLOB_LIMB_BYTES: usize = 16;
+
+/// A value too long for one ciphertext, encrypted to the MXE as `N` blocks.
+///
+/// Names, identifiers and keys exceed the 16 bytes one block carries, so they are split
+/// into little-endian `u128` limbs, each encrypted into a 32-byte block, and the circuit
+/// receives them as a `[u128; N]`. The block count is part of the type rather than
+/// stored: a blob serializes as its bare blocks, so the records holding one keep fixed
+/// offsets for the ciphertext regions circuits read. Values that fit one block stay a
+/// plain `[u8; 32]`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct CipherBlob<const N: usize> {
+    pub blocks: [[u8; 32]; N],
+}
+
+impl<const N: usize> CipherBlob<N> {
+    /// Number of ciphertext blocks
+    pub const BLOCKS: usize = N;
+    /// Serialized length, as read by a circuit through `Argument::Account`
+    pub const LEN: u32 = (N * 32) as u32;
+    /// Longest plaintext the blob holds
+    pub const MAX_PLAINTEXT_LEN: usize = N * CIPHER_BLOB_LIMB_BYTES;
+
+    pub fn new(blocks: [[u8; 32]; N]) -> Self {
+        Self { blocks }
+    }
+
+    /// Splits `plaintext` into the limbs to encrypt, zero-padding the last one, or `None`
+    /// when it is longer than the blob holds.
+    pub fn limbs(plaintext: &[u8]) -> Option<[u128; N]> {
+        if plaintext.len() > Self::MAX_PLAINTEXT_LEN {
+            return None;
+        }
+        let mut limbs = [0; N];
+        for (limb, chunk) in limbs
+            .iter_mut()
+            .zip(plaintext.chunks(CIPHER_BLOB_LIMB_BYTES))
+        {
+            let mut bytes = [0; CIPHER_BLOB_LIMB_BYTES];
+            bytes[..chunk.len()].copy_from_slice(chunk);
+            *limb = u128::from_le_bytes(bytes);
+        }
+        Some(limbs)
+    }
+
+    /// Joins decrypted limbs back into the plaintext, dropping the zero padding.
+    pub fn plaintext(limbs: &[u128; N]) -> Vec<u8> {
+        let mut plaintext: Vec<u8> = limbs.iter().flat_map(|limb| limb.to_le_bytes()).collect();
+        let len = plaintext
+            .iter()
+            .rposition(|byte| *byte != 0)
+            .map_or(0, |last| last + 1);
+        plaintext.truncate(len);
+        plaintext
+    }
+
+    /// Circuit arguments passing the blocks by value, one `Enc` limb each.
+    pub fn encrypted_args(&self) -> impl Iterator<Item = Argument> + '_ {
+        self.blocks.iter().copied().map(Argument::EncryptedU128)
+    }
+}
+
+impl<const N: usize> Default for CipherBlob<N> {
+    fn default() -> Self {
+        Self {
+            blocks: [[0; 32]; N],
+        }
+    }
+}
+
+impl<const N: usize> From<[[u8; 32]; N]> for CipherBlob<N> {
+    fn from(blocks: [[u8; 32]; N]) -> Self {
+        Self::new(blocks)
+    }
+}
+
+/// Takes the blob from ciphertexts of exactly `N` blocks, e.g. a slice of a computation
+/// output.
+impl<const N: usize> TryFrom<&[[u8; 32]]> for CipherBlob<N> {
+    type Error = std::array::TryFromSliceError;
+
+    fn try_from(blocks: &[[u8; 32]]) -> std::result::Result<Self, Self::Error> {
+        blocks.try_into().map(Self::new)
+    }
+}
+
+impl<const N: usize> Space for CipherBlob<N> {
+    const INIT_SPACE: usize = N * 32;
+}
@@ -0,0 +1,234 @@
+use anchor_lang::prelude::*;
+use arcium_anchor::prelude::*;
+
+use crate::ErrorCode;
+use crate::*;
+
+/// FHIR resource type of the anchor holding a patient's lab results.
+pub const FHIR_LABS_RESOURCE_TYPE: &str = "DiagnosticReport";
+/// FHIR resource type of the anchor holding a patient's prescriptions.
+pub const FHIR_PRESCRIPTIONS_RESOURCE_TYPE: &str = "MedicationRequest";
+
+/// Key and nonce one record of a bundle was encrypted with.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct BundleSource {
+    pub pub_key: [u8; 32],
+    pub nonce: u128,
+}
+
+/// A record bundle re-encrypted for its receiver: the structured record, then the content
+/// keys of the lab results and prescriptions anchored off-chain.
+#[event]
+pub struct BundleSharedEvent {
+    /// Record that was shared
+    pub patient_record: Pubkey,
+    pub labs_anchor: Pubkey,
+    pub prescriptions_anchor: Pubkey,
+    /// x25519 key the output is encrypted for
+    pub receiver_enc_key: [u8; 32],
+    pub computation_offset: u64,
+    /// Slot the result was delivered at
+    pub slot: u64,
+    pub nonce: [u8; 16],
+    /// Ciphertexts of the record in record order
+    pub record: [[u8; 32]; PATIENT_RECORD_BLOCKS],
+    pub labs: EncryptedContentKey,
+    pub prescriptions: EncryptedContentKey,
+}
+
+#[event]
+pub struct BundleShareQueuedEvent {
+    pub patient_data: Pubkey,
+    pub labs_anchor: Pubkey,
+    pub prescriptions_anchor: Pubkey,
+    pub receiver: [u8; 32],
+    pub computation_offset: u64,
+}
+
+#[event]
+pub struct BundleShareAbortedEvent {
+    pub patient_data: Pubkey,
+    pub computation_offset: u64,
+}
+
+#[queue_computation_accounts("share_bundle", patient)]
+#[derive(Accounts)]
+#[instruction(
+    computation_offset: u64,
+    receiver: [u8; 32],
+    receiver_nonce: u128,
+    labs_anchor_id: u64,
+    prescriptions_anchor_id: u64,
+)]
+pub struct ShareBundle<'info> {
+    #[account(mut)]
+    pub patient: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = patient,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account, checked by the arcium program.
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool, checked by the arcium program.
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account, checked by the arcium program.
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_SHARE_BUNDLE)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(
+        mut,
+        address = derive_cluster_pda!(mxe_account)
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Box<Account<'info, ProgramConfig>>,
+    #[account(
+        seeds = [b"patient_data", patient.key().as_ref()],
+        bump,
+    )]
+    pub patient_data: Box<Account<'info, PatientData>>,
+    #[account(
+        init_if_needed,
+        payer = patient,
+        space = 8 + PatientRegistry::INIT_SPACE,
+        seeds = [PATIENT_REGISTRY_SEED, patient_data.key().as_ref()],
+        bump,
+    )]
+    pub patient_registry: Box<Account<'info, PatientRegistry>>,
+    #[account(
+        seeds = [FHIR_ANCHOR_SEED, patient.key().as_ref(), &labs_anchor_id.to_le_bytes()],
+        bump = labs_anchor.bump,
+        constraint = labs_anchor.resource_type == FHIR_LABS_RESOURCE_TYPE
+            @ ErrorCode::BundleResourceTypeMismatch,
+    )]
+    pub labs_anchor: Box<Account<'info, FhirAnchor>>,
+    #[account(
+        seeds = [
+            FHIR_ANCHOR_SEED,
+            patient.key().as_ref(),
+            &prescriptions_anchor_id.to_le_bytes(),
+        ],
+        bump = prescriptions_anchor.bump,
+        constraint = prescriptions_anchor.resource_type == FHIR_PRESCRIPTIONS_RESOURCE_TYPE
+            @ ErrorCode::BundleResourceTypeMismatch,
+    )]
+    pub prescriptions_anchor: Box<Account<'info, FhirAnchor>>,
+    #[account(
+        init_if_needed,
+        payer = patient,
+        space = 8 + UsedOffsets::INIT_SPACE,
+        seeds = [USED_OFFSETS_SEED, patient.key().as_ref()],
+        bump,
+    )]
+    pub used_offsets: Box<Account<'info, UsedOffsets>>,
+    #[account(
+        init,
+        payer = patient,
+        space = 8 + CallbackGuard::INIT_SPACE,
+        seeds = [CALLBACK_GUARD_SEED, &computation_offset.to_le_bytes()],
+        bump,
+    )]
+    pub callback_guard: Box<Account<'info, CallbackGuard>>,
+}
+
+#[callback_accounts("share_bundle")]
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ShareBundleCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_SHARE_BUNDLE)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    #[account(
+        mut,
+        seeds = [CALLBACK_GUARD_SEED, &callback_guard.computation_offset.to_le_bytes()],
+        bump = callback_guard.bump,
+    )]
+    pub callback_guard: Box<Account<'info, CallbackGuard>>,
+    /// CHECK: patient_data, the shared record, named by the queued callback; only its key
+    /// is used.
+    pub patient_data: UncheckedAccount<'info>,
+    /// CHECK: labs_anchor, named by the queued callback; only its key is used.
+    pub labs_anchor: UncheckedAccount<'info>,
+    /// CHECK: prescriptions_anchor, named by the queued callback; only its key is used.
+    pub prescriptions_anchor: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        seeds = [USED_OFFSETS_SEED, used_offsets.payer.as_ref()],
+        bump = used_offsets.bump,
+    )]
+    pub used_offsets: Box<Account<'info, UsedOffsets>>,
+}
+
+#[init_computation_definition_accounts("share_bundle", payer)]
+#[derive(Accounts)]
+pub struct InitShareBundleCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut)]
+    /// CHECK: comp_def_account, checked by arcium program.
+    /// Can't check it here as it's not initialized yet.
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+        constraint = program_config.admin == payer.key() @ ErrorCode::Unauthorized,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
@@ -0,0 +1,57 @@
+use anchor_lang::prelude::*;
+
+use crate::ErrorCode;
+
+pub const CALLBACK_GUARD_SEED: &[u8] = b"callback_guard";
+
+/// Where a queued computation is in its callback's lifecycle.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub enum CallbackState {
+    /// Queued; its callback has not run yet
+    Expected,
+    /// Called back, or settled without one
+    Consumed,
+}
+
+/// One-shot guard over the callback of a queued computation.
+///
+/// Created as `Expected` by every instruction that queues a computation and passed to
+/// its callback, which moves it to `Consumed` before doing anything else. A replayed or
+/// duplicated callback invocation then fails up front, so it cannot emit the result
+/// again or settle the escrows the first invocation already paid out. The guard stays
+/// open once consumed; computation offsets are never reused, so it is never needed for
+/// another computation.
+#[account]
+#[derive(InitSpace)]
+pub struct CallbackGuard {
+    pub computation_offset: u64,
+    /// Circuit the computation was queued on
+    pub comp_def_offset: u32,
+    pub state: CallbackState,
+    pub bump: u8,
+}
+
+impl CallbackGuard {
+    /// Arms the guard for the computation just queued at `computation_offset`.
+    pub fn expect(&mut self, computation_offset: u64, comp_def_offset: u32, bump: u8) {
+        self.computation_offset = computation_offset;
+        self.comp_def_offset = comp_def_offset;
+        self.state = CallbackState::Expected;
+        self.bump = bump;
+    }
+
+    /// Consumes the guard for a callback of `comp_def_offset`, returning the offset of
+    /// the computation it belongs to.
+    pub fn consume(&mut self, comp_def_offset: u32) -> Result<u64> {
+        require!(
+            self.state == CallbackState::Expected,
+            ErrorCode::CallbackAlreadyConsumed
+        );
+        require!(
+            self.comp_def_offset == comp_def_offset,
+            ErrorCode::CallbackCompDefMismatch
+        );
+        self.state = CallbackState::Consumed;
+        Ok(self.computation_offset)
+    }
+}
@@ -0,0 +1,332 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hashv;
+use arcium_anchor::prelude::*;
+
+use crate::ErrorCode;
+use crate::*;
+
+pub const CAREGIVER_GRANT_SEED: &[u8] = b"caregiver_grant";
+/// Fields a caregiver grant may cover: the record's vitals and allergies, enough to
+/// manage someone's care at home without their identity or demographics.
+pub const CAREGIVER_FIELDS: u16 =
+    PatientField::Weight.bit() | PatientField::Height.bit() | PatientField::Allergies.bit();
+/// Shortest interval between caregiver updates: one hour at the nominal slot rate.
+pub const MIN_CAREGIVER_INTERVAL_SLOTS: u64 = 60 * 60 * 1000 / MS_PER_SLOT;
+
+/// Recurring, read-only access of a family caregiver to a narrow set of a record's
+/// fields.
+///
+/// Anyone may crank `crank_caregiver_update` once per `interval_slots`, and the cluster
+/// re-encrypts `fields` of the record for `caregiver_enc_key` into this account. The
+/// field set and key are fixed by the patient, so the grant cannot be used to trigger any
+/// other share; it is not a `ConsentGrant` and carries no consent scope.
+#[account]
+#[derive(InitSpace)]
+pub struct CaregiverGrant {
+    pub patient_data: Pubkey,
+    /// Wallet that owns the record and granted the access
+    pub patient: Pubkey,
+    /// Caregiver's wallet, part of the grant address
+    pub caregiver: Pubkey,
+    /// x25519 key updates are encrypted for
+    pub caregiver_enc_key: [u8; 32],
+    /// Bitmask of `PatientField` bits, within `CAREGIVER_FIELDS`
+    pub fields: u16,
+    /// Nonce the record was encrypted with, as of `record_version`
+    pub record_nonce: u128,
+    /// `version` of the record `record_nonce` belongs to; an updated record stalls the
+    /// grant until the patient grants it again
+    pub record_version: u32,
+    /// Slots between updates, at least `MIN_CAREGIVER_INTERVAL_SLOTS`
+    pub interval_slots: u64,
+    /// Slot from which the next update may be cranked
+    pub next_due_slot: u64,
+    pub expires_at: i64,
+    /// Slot `expires_at` was estimated to fall in when granted
+    pub expires_at_slot: u64,
+    pub revoked: bool,
+    /// Set while an update is queued and has not called back
+    pub pending: bool,
+    /// Updates queued so far, never reset; each derives a fresh output nonce
+    pub requests: u64,
+    /// x25519 key the latest update is encrypted for
+    pub receiver_enc_key: [u8; 32],
+    pub nonce: [u8; 16],
+    /// Latest update, in record order; fields outside `fields` encrypt zero
+    pub ciphertexts: [[u8; 32]; PATIENT_RECORD_BLOCKS],
+    /// Slot the latest update was delivered at, zero before the first
+    pub updated_slot: u64,
+    pub bump: u8,
+}
+
+impl CaregiverGrant {
+    /// Fails with `CaregiverGrantInactive` once revoked or expired.
+    pub fn require_active(&self, clock: &ExpiryClock) -> Result<()> {
+        require!(
+            !self.revoked && !clock.has_passed(self.expires_at, self.expires_at_slot),
+            ErrorCode::CaregiverGrantInactive
+        );
+        Ok(())
+    }
+}
+
+/// Nonce the `sequence`th update of `caregiver_grant` is encrypted under.
+///
+/// Derived rather than taken from the crank, so no crank can make two updates for one
+/// caregiver key share a nonce.
+pub fn caregiver_update_nonce(caregiver_grant: &Pubkey, sequence: u64) -> u128 {
+    let hash = hashv(&[caregiver_grant.as_ref(), &sequence.to_le_bytes()]).to_bytes();
+    u128::from_le_bytes(hash[..16].try_into().unwrap())
+}
+
+#[event]
+pub struct CaregiverAccessGrantedEvent {
+    pub caregiver_grant: Pubkey,
+    pub patient_data: Pubkey,
+    pub caregiver: Pubkey,
+    pub fields: u16,
+    pub interval_slots: u64,
+    pub expires_at: i64,
+}
+
+#[event]
+pub struct CaregiverAccessRevokedEvent {
+    pub caregiver_grant: Pubkey,
+    pub patient_data: Pubkey,
+    pub caregiver: Pubkey,
+}
+
+/// A caregiver update finished; caregivers subscribe by `caregiver`.
+#[event]
+pub struct CaregiverUpdateDeliveredEvent {
+    pub caregiver_grant: Pubkey,
+    pub caregiver: Pubkey,
+    pub slot: u64,
+    /// Set when the computation aborted; the previous update is left in place and the
+    /// next may be cranked right away
+    pub aborted: bool,
+}
+
+#[derive(Accounts)]
+#[instruction(caregiver: Pubkey)]
+pub struct GrantCaregiverAccess<'info> {
+    #[account(mut)]
+    pub patient: Signer<'info>,
+    #[account(
+        seeds = [b"patient_data", patient.key().as_ref()],
+        bump,
+    )]
+    pub patient_data: Account<'info, PatientData>,
+    #[account(
+        init_if_needed,
+        payer = patient,
+        space = 8 + CaregiverGrant::INIT_SPACE,
+        seeds = [CAREGIVER_GRANT_SEED, patient_data.key().as_ref(), caregiver.as_ref()],
+        bump,
+    )]
+    pub caregiver_grant: Box<Account<'info, CaregiverGrant>>,
+    #[account(
+        init_if_needed,
+        payer = patient,
+        space = 8 + PatientRegistry::INIT_SPACE,
+        seeds = [PATIENT_REGISTRY_SEED, patient_data.key().as_ref()],
+        bump,
+    )]
+    pub patient_registry: Box<Account<'info, PatientRegistry>>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RevokeCaregiverAccess<'info> {
+    #[account(mut)]
+    pub patient: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [
+            CAREGIVER_GRANT_SEED,
+            caregiver_grant.patient_data.as_ref(),
+            caregiver_grant.caregiver.as_ref(),
+        ],
+        bump = caregiver_grant.bump,
+        has_one = patient @ ErrorCode::Unauthorized,
+    )]
+    pub caregiver_grant: Box<Account<'info, CaregiverGrant>>,
+    #[account(
+        init_if_needed,
+        payer = patient,
+        space = 8 + PatientRegistry::INIT_SPACE,
+        seeds = [PATIENT_REGISTRY_SEED, caregiver_grant.patient_data.as_ref()],
+        bump,
+    )]
+    pub patient_registry: Box<Account<'info, PatientRegistry>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[queue_computation_accounts("caregiver_update", crank)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct CrankCaregiverUpdate<'info> {
+    /// Anyone; pays for the computation and gains no access
+    #[account(mut)]
+    pub crank: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = crank,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account, checked by the arcium program.
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool, checked by the arcium program.
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account, checked by the arcium program.
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_CAREGIVER_UPDATE)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(
+        mut,
+        address = derive_cluster_pda!(mxe_account)
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Box<Account<'info, ProgramConfig>>,
+    #[account(
+        seeds = [b"patient_data", caregiver_grant.patient.as_ref()],
+        bump,
+        address = caregiver_grant.patient_data,
+    )]
+    pub patient_data: Box<Account<'info, PatientData>>,
+    #[account(
+        mut,
+        seeds = [
+            CAREGIVER_GRANT_SEED,
+            caregiver_grant.patient_data.as_ref(),
+            caregiver_grant.caregiver.as_ref(),
+        ],
+        bump = caregiver_grant.bump,
+    )]
+    pub caregiver_grant: Box<Account<'info, CaregiverGrant>>,
+    #[account(
+        init_if_needed,
+        payer = crank,
+        space = 8 + UsedOffsets::INIT_SPACE,
+        seeds = [USED_OFFSETS_SEED, crank.key().as_ref()],
+        bump,
+    )]
+    pub used_offsets: Box<Account<'info, UsedOffsets>>,
+    #[account(
+        init,
+        payer = crank,
+        space = 8 + CallbackGuard::INIT_SPACE,
+        seeds = [CALLBACK_GUARD_SEED, &computation_offset.to_le_bytes()],
+        bump,
+    )]
+    pub callback_guard: Box<Account<'info, CallbackGuard>>,
+}
+
+#[callback_accounts("caregiver_update")]
+#[derive(Accounts)]
+pub struct CaregiverUpdateCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_CAREGIVER_UPDATE)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    #[account(
+        mut,
+        seeds = [CALLBACK_GUARD_SEED, &callback_guard.computation_offset.to_le_bytes()],
+        bump = callback_guard.bump,
+    )]
+    pub callback_guard: Box<Account<'info, CallbackGuard>>,
+    #[account(
+        mut,
+        seeds = [
+            CAREGIVER_GRANT_SEED,
+            caregiver_grant.patient_data.as_ref(),
+            caregiver_grant.caregiver.as_ref(),
+        ],
+        bump = caregiver_grant.bump,
+    )]
+    pub caregiver_grant: Box<Account<'info, CaregiverGrant>>,
+    #[account(
+        mut,
+        seeds = [USED_OFFSETS_SEED, used_offsets.payer.as_ref()],
+        bump = used_offsets.bump,
+    )]
+    pub used_offsets: Box<Account<'info, UsedOffsets>>,
+}
+
+#[init_computation_definition_accounts("caregiver_update", payer)]
+#[derive(Accounts)]
+pub struct InitCaregiverUpdateCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut)]
+    /// CHECK: comp_def_account, checked by arcium program.
+    /// Can't check it here as it's not initialized yet.
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+        constraint = program_config.admin == payer.key() @ ErrorCode::Unauthorized,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
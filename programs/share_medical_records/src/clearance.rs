@@ -0,0 +1,271 @@
+use anchor_lang::prelude::*;
+use arcium_anchor::prelude::*;
+
+use crate::ErrorCode;
+use crate::*;
+
+pub const CLEARANCE_TEMPLATE_SEED: &[u8] = b"clearance_template";
+pub const CLEARANCE_SEED: &[u8] = b"clearance";
+/// Allergy slots a record holds, and so the bits a template's allergy mask may set.
+pub const CLEARANCE_ALLERGY_SLOTS: u32 = 5;
+
+/// Health requirements an employer or school checks people against, such as a minimum
+/// age and no allergy to a substance handled on site.
+///
+/// Requirements are public; only whether a record meets them is ever revealed.
+#[account]
+#[derive(InitSpace)]
+pub struct ClearanceTemplate {
+    /// Employer or school that published the template
+    pub employer: Pubkey,
+    pub template_id: u64,
+    /// Age a record must show at least
+    pub min_age: u8,
+    /// Allergies a record must not list, one bit per slot in `PatientData::allergies`
+    pub excluded_allergies: u8,
+    /// Seconds a clearance stays valid once granted
+    pub validity: i64,
+    pub bump: u8,
+}
+
+/// Pass or fail result of checking one record against one clearance template.
+///
+/// Written only by the check's callback, so the employer reads it as the program's
+/// attestation; it holds no field values.
+#[account]
+#[derive(InitSpace)]
+pub struct Clearance {
+    pub template: Pubkey,
+    pub employer: Pubkey,
+    /// Record the template was checked against
+    pub subject: Pubkey,
+    /// Patient who ran the check
+    pub holder: Pubkey,
+    pub passed: bool,
+    /// Seconds a passed clearance stays valid, copied from the template at check time
+    pub validity: i64,
+    /// Unix timestamp of the latest result, zero while the check is pending or after it
+    /// aborted
+    pub checked_at: i64,
+    /// Unix timestamp a passed clearance lapses at
+    pub expires_at: i64,
+    /// Slot `expires_at` was estimated to fall in when the result arrived
+    pub expires_at_slot: u64,
+    pub bump: u8,
+}
+
+impl Clearance {
+    /// Returns whether the record passed the template and the result has not lapsed.
+    pub fn is_valid(&self, clock: &ExpiryClock) -> bool {
+        self.passed
+            && self.checked_at > 0
+            && !clock.has_passed(self.expires_at, self.expires_at_slot)
+    }
+}
+
+#[event]
+pub struct ClearanceTemplateCreatedEvent {
+    pub template: Pubkey,
+    pub employer: Pubkey,
+    pub template_id: u64,
+    pub min_age: u8,
+    pub excluded_allergies: u8,
+}
+
+/// A clearance check finished; employers subscribe by `employer`.
+#[event]
+pub struct ClearanceCheckedEvent {
+    pub clearance: Pubkey,
+    pub template: Pubkey,
+    pub employer: Pubkey,
+    pub subject: Pubkey,
+    pub passed: bool,
+    /// Set when the computation aborted rather than the record failing
+    pub aborted: bool,
+    pub expires_at: i64,
+}
+
+#[derive(Accounts)]
+#[instruction(template_id: u64)]
+pub struct CreateClearanceTemplate<'info> {
+    #[account(mut)]
+    pub employer: Signer<'info>,
+    #[account(
+        init,
+        payer = employer,
+        space = 8 + ClearanceTemplate::INIT_SPACE,
+        seeds = [CLEARANCE_TEMPLATE_SEED, employer.key().as_ref(), &template_id.to_le_bytes()],
+        bump,
+    )]
+    pub clearance_template: Account<'info, ClearanceTemplate>,
+    pub system_program: Program<'info, System>,
+}
+
+#[queue_computation_accounts("clearance_check", patient)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct ClearanceCheck<'info> {
+    #[account(mut)]
+    pub patient: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = patient,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account, checked by the arcium program.
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool, checked by the arcium program.
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account, checked by the arcium program.
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_CLEARANCE_CHECK)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(
+        mut,
+        address = derive_cluster_pda!(mxe_account)
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Box<Account<'info, ProgramConfig>>,
+    #[account(
+        seeds = [b"patient_data", patient.key().as_ref()],
+        bump,
+    )]
+    pub patient_data: Box<Account<'info, PatientData>>,
+    #[account(
+        seeds = [
+            CLEARANCE_TEMPLATE_SEED,
+            clearance_template.employer.as_ref(),
+            &clearance_template.template_id.to_le_bytes(),
+        ],
+        bump = clearance_template.bump,
+    )]
+    pub clearance_template: Box<Account<'info, ClearanceTemplate>>,
+    #[account(
+        init_if_needed,
+        payer = patient,
+        space = 8 + Clearance::INIT_SPACE,
+        seeds = [
+            CLEARANCE_SEED,
+            clearance_template.key().as_ref(),
+            patient_data.key().as_ref(),
+        ],
+        bump,
+    )]
+    pub clearance: Box<Account<'info, Clearance>>,
+    #[account(
+        init_if_needed,
+        payer = patient,
+        space = 8 + UsedOffsets::INIT_SPACE,
+        seeds = [USED_OFFSETS_SEED, patient.key().as_ref()],
+        bump,
+    )]
+    pub used_offsets: Box<Account<'info, UsedOffsets>>,
+    #[account(
+        init,
+        payer = patient,
+        space = 8 + CallbackGuard::INIT_SPACE,
+        seeds = [CALLBACK_GUARD_SEED, &computation_offset.to_le_bytes()],
+        bump,
+    )]
+    pub callback_guard: Box<Account<'info, CallbackGuard>>,
+}
+
+#[callback_accounts("clearance_check")]
+#[derive(Accounts)]
+pub struct ClearanceCheckCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_CLEARANCE_CHECK)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    #[account(
+        mut,
+        seeds = [CALLBACK_GUARD_SEED, &callback_guard.computation_offset.to_le_bytes()],
+        bump = callback_guard.bump,
+    )]
+    pub callback_guard: Box<Account<'info, CallbackGuard>>,
+    #[account(
+        mut,
+        seeds = [CLEARANCE_SEED, clearance.template.as_ref(), clearance.subject.as_ref()],
+        bump = clearance.bump,
+    )]
+    pub clearance: Box<Account<'info, Clearance>>,
+    #[account(
+        mut,
+        seeds = [USED_OFFSETS_SEED, used_offsets.payer.as_ref()],
+        bump = used_offsets.bump,
+    )]
+    pub used_offsets: Box<Account<'info, UsedOffsets>>,
+}
+
+#[init_computation_definition_accounts("clearance_check", payer)]
+#[derive(Accounts)]
+pub struct InitClearanceCheckCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut)]
+    /// CHECK: comp_def_account, checked by arcium program.
+    /// Can't check it here as it's not initialized yet.
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+        constraint = program_config.admin == payer.key() @ ErrorCode::Unauthorized,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
@@ -0,0 +1,109 @@
+use anchor_lang::prelude::*;
+
+use crate::ErrorCode;
+use crate::*;
+
+pub const CODE_SET_SEED: &[u8] = b"code_set";
+/// Also bounds code system identifiers; code set names are used as a PDA seed.
+pub const MAX_CODE_SET_NAME_LEN: usize = 32;
+pub const MAX_CODE_SET_VALUES: usize = 16;
+pub const MAX_CODE_LEN: usize = 16;
+
+/// One permitted value of a coded field: the scalar clients encrypt and the code it
+/// stands for.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub struct CodedValue {
+    pub value: u64,
+    /// Code in the set's code system, e.g. `A+` or a SNOMED CT concept id
+    #[max_len(MAX_CODE_LEN)]
+    pub code: String,
+}
+
+/// Permitted values of a coded field such as blood type, gender or allergen, so clients
+/// built against different locales encrypt the same scalar for the same meaning and MPC
+/// comparisons between their records stay meaningful.
+///
+/// Schema fields name the set their values come from through `FieldDescriptor::code_set`.
+#[account]
+#[derive(InitSpace)]
+pub struct CodeSet {
+    #[max_len(MAX_CODE_SET_NAME_LEN)]
+    pub name: String,
+    /// Identifier of the code system the codes belong to, e.g. `snomed-ct`
+    #[max_len(MAX_CODE_SET_NAME_LEN)]
+    pub system: String,
+    /// Bumped on every change to the values
+    pub version: u16,
+    #[max_len(MAX_CODE_SET_VALUES)]
+    pub values: Vec<CodedValue>,
+    pub bump: u8,
+}
+
+impl CodeSet {
+    /// Checks the system and codes fit and no value or code appears twice.
+    pub fn validate_values(system: &str, values: &[CodedValue]) -> Result<()> {
+        require!(
+            !system.is_empty()
+                && system.len() <= MAX_CODE_SET_NAME_LEN
+                && !values.is_empty()
+                && values.len() <= MAX_CODE_SET_VALUES,
+            ErrorCode::InvalidCodeSet
+        );
+        for (i, coded) in values.iter().enumerate() {
+            require!(
+                !coded.code.is_empty() && coded.code.len() <= MAX_CODE_LEN,
+                ErrorCode::InvalidCodeSet
+            );
+            let distinct = values[..i]
+                .iter()
+                .all(|other| other.value != coded.value && other.code != coded.code);
+            require!(distinct, ErrorCode::InvalidCodeSet);
+        }
+        Ok(())
+    }
+
+    /// Scalar to encrypt for `code`, if the set permits it.
+    pub fn value_of(&self, code: &str) -> Option<u64> {
+        self.values
+            .iter()
+            .find(|coded| coded.code == code)
+            .map(|coded| coded.value)
+    }
+
+    /// Code a decrypted scalar stands for, if the set permits it.
+    pub fn code_of(&self, value: u64) -> Option<&str> {
+        self.values
+            .iter()
+            .find(|coded| coded.value == value)
+            .map(|coded| coded.code.as_str())
+    }
+}
+
+#[event]
+pub struct CodeSetPublishedEvent {
+    pub code_set: Pubkey,
+    pub name: String,
+    pub version: u16,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct PublishCodeSet<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+        has_one = admin @ ErrorCode::Unauthorized,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = 8 + CodeSet::INIT_SPACE,
+        seeds = [CODE_SET_SEED, name.as_bytes()],
+        bump,
+    )]
+    pub code_set: Account<'info, CodeSet>,
+    pub system_program: Program<'info, System>,
+}
@@ -0,0 +1,248 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hashv;
+use arcium_anchor::prelude::*;
+
+use crate::ErrorCode;
+use crate::*;
+
+pub const PATIENT_COMMITMENT_SEED: &[u8] = b"patient_commitment";
+
+/// Rent-minimized form of a patient record, for deployments where `PatientData` and its
+/// nonce and audit accounts cost too much per patient.
+///
+/// Only a SHA-256 commitment to the record's ciphertexts is kept; the ciphertexts are
+/// uploaded to IPFS or Arweave like vault documents, and their CID is emitted in
+/// `PatientCommitmentStoredEvent`. A share passes them back in full, is checked against
+/// the commitment and runs the same re-encryption as `share_patient_data`, delivering
+/// the result through `ReceivedPatientDataEvent`. Because the cluster reads the values
+/// from the instruction rather than the account, a commitment can be replaced while its
+/// shares are in flight.
+#[account]
+#[derive(InitSpace)]
+pub struct PatientCommitment {
+    /// Wallet controlling the record; also the key its address is derived from
+    pub patient_authority: Pubkey,
+    /// `patient_commitment` of the key, nonce and ciphertexts last stored
+    pub commitment: [u8; 32],
+    /// Slot the record was first stored at
+    pub created_slot: u64,
+    pub bump: u8,
+}
+
+/// Commitment to a record: its owner, the key and nonce it is encrypted with, then every
+/// field's ciphertext in `PatientData` order.
+pub fn patient_commitment(
+    patient: &Pubkey,
+    enc_pubkey: &[u8; 32],
+    nonce: u128,
+    fields: &[[u8; 32]; 6],
+    allergies: &[[u8; 32]; 5],
+) -> [u8; 32] {
+    let nonce = nonce.to_le_bytes();
+    let mut values: Vec<&[u8]> = vec![patient.as_ref(), enc_pubkey, &nonce];
+    values.extend(
+        fields
+            .iter()
+            .chain(allergies)
+            .map(|ciphertext| ciphertext.as_slice()),
+    );
+    hashv(&values).to_bytes()
+}
+
+impl PatientCommitment {
+    /// Fails with `CommitmentMismatch` unless the record is the one last stored.
+    pub fn verify(
+        &self,
+        enc_pubkey: &[u8; 32],
+        nonce: u128,
+        fields: &[[u8; 32]; 6],
+        allergies: &[[u8; 32]; 5],
+    ) -> Result<()> {
+        require!(
+            patient_commitment(
+                &self.patient_authority,
+                enc_pubkey,
+                nonce,
+                fields,
+                allergies
+            ) == self.commitment,
+            ErrorCode::CommitmentMismatch
+        );
+        Ok(())
+    }
+}
+
+#[event]
+pub struct PatientCommitmentStoredEvent {
+    pub patient_commitment: Pubkey,
+    pub patient_authority: Pubkey,
+    pub commitment: [u8; 32],
+    /// IPFS CID or Arweave transaction id of the ciphertexts
+    pub cid: String,
+    pub enc_pubkey: [u8; 32],
+    pub nonce: u128,
+}
+
+#[derive(Accounts)]
+pub struct StorePatientCommitment<'info> {
+    #[account(mut)]
+    pub patient: Signer<'info>,
+    #[account(
+        init_if_needed,
+        payer = patient,
+        space = 8 + PatientCommitment::INIT_SPACE,
+        seeds = [PATIENT_COMMITMENT_SEED, patient.key().as_ref()],
+        bump,
+    )]
+    pub patient_commitment: Account<'info, PatientCommitment>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    pub system_program: Program<'info, System>,
+}
+
+#[queue_computation_accounts("share_committed_patient_data", patient)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct ShareCommittedPatientData<'info> {
+    #[account(mut)]
+    pub patient: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = patient,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account, checked by the arcium program.
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool, checked by the arcium program.
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account, checked by the arcium program.
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_SHARE_COMMITTED_PATIENT_DATA)
+    )]
+    pub comp_def_account: Box<Account<'info, ComputationDefinitionAccount>>,
+    #[account(
+        mut,
+        address = derive_cluster_pda!(mxe_account)
+    )]
+    pub cluster_account: Box<Account<'info, Cluster>>,
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Box<Account<'info, FeePool>>,
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Box<Account<'info, ClockAccount>>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Box<Account<'info, ProgramConfig>>,
+    #[account(
+        seeds = [PATIENT_COMMITMENT_SEED, patient.key().as_ref()],
+        bump = patient_commitment.bump,
+    )]
+    pub patient_commitment: Account<'info, PatientCommitment>,
+    #[account(
+        init_if_needed,
+        payer = patient,
+        space = 8 + UsedOffsets::INIT_SPACE,
+        seeds = [USED_OFFSETS_SEED, patient.key().as_ref()],
+        bump,
+    )]
+    pub used_offsets: Box<Account<'info, UsedOffsets>>,
+    #[account(
+        init,
+        payer = patient,
+        space = 8 + CallbackGuard::INIT_SPACE,
+        seeds = [CALLBACK_GUARD_SEED, &computation_offset.to_le_bytes()],
+        bump,
+    )]
+    pub callback_guard: Box<Account<'info, CallbackGuard>>,
+}
+
+#[callback_accounts("share_committed_patient_data")]
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ShareCommittedPatientDataCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_SHARE_COMMITTED_PATIENT_DATA)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    #[account(
+        mut,
+        seeds = [CALLBACK_GUARD_SEED, &callback_guard.computation_offset.to_le_bytes()],
+        bump = callback_guard.bump,
+    )]
+    pub callback_guard: Box<Account<'info, CallbackGuard>>,
+    /// CHECK: patient_commitment, the record that was shared; only its key is read.
+    pub patient_commitment: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        seeds = [USED_OFFSETS_SEED, used_offsets.payer.as_ref()],
+        bump = used_offsets.bump,
+    )]
+    pub used_offsets: Box<Account<'info, UsedOffsets>>,
+}
+
+#[init_computation_definition_accounts("share_committed_patient_data", payer)]
+#[derive(Accounts)]
+pub struct InitShareCommittedPatientDataCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut)]
+    /// CHECK: comp_def_account, checked by arcium program.
+    /// Can't check it here as it's not initialized yet.
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+        constraint = program_config.admin == payer.key() @ ErrorCode::Unauthorized,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
@@ -0,0 +1,332 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hashv;
+use arcium_anchor::prelude::*;
+
+use crate::ErrorCode;
+use crate::*;
+
+pub const COMPRESSED_TREE_SEED: &[u8] = b"compressed_tree";
+/// Depth of every compressed record tree, allowing 2^24 (about 16.7 million) records.
+pub const COMPRESSED_TREE_DEPTH: usize = 24;
+/// Number of recent roots kept, so proofs built shortly before an append stay valid.
+pub const COMPRESSED_ROOT_HISTORY: usize = 32;
+/// Encrypted 128-bit values per compressed record.
+pub const COMPRESSED_RECORD_VALUES: usize = 4;
+
+/// Ciphertexts of one compressed record, encrypted to the MXE.
+pub type CompressedCiphertexts = [[u8; 32]; COMPRESSED_RECORD_VALUES];
+
+/// Append-only Merkle tree of small encrypted records such as vitals or encounters.
+///
+/// Only leaf hashes are folded into the tree; the records themselves are emitted in
+/// `CompressedRecordAppendedEvent` for indexers to keep. Like a concurrent Merkle tree,
+/// a proof is accepted against any of the last `COMPRESSED_ROOT_HISTORY` roots, so
+/// appends landing between building a proof and sharing don't invalidate it.
+#[account]
+#[derive(InitSpace)]
+pub struct CompressedRecordTree {
+    /// Clinic allowed to append records
+    pub authority: Pubkey,
+    pub tree_id: u64,
+    /// Index the next appended leaf gets
+    pub next_index: u64,
+    /// Rightmost left-hand node at every level, enough to append without the leaves
+    pub filled_subtrees: [[u8; 32]; COMPRESSED_TREE_DEPTH],
+    /// Ring buffer of recent roots
+    pub roots: [[u8; 32]; COMPRESSED_ROOT_HISTORY],
+    pub current_root_index: u8,
+    pub bump: u8,
+}
+
+fn hash_pair(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    hashv(&[left, right]).to_bytes()
+}
+
+/// Root of an empty subtree at every level, starting from the empty leaf.
+fn zero_hashes() -> [[u8; 32]; COMPRESSED_TREE_DEPTH + 1] {
+    let mut zeros = [[0u8; 32]; COMPRESSED_TREE_DEPTH + 1];
+    for level in 1..=COMPRESSED_TREE_DEPTH {
+        zeros[level] = hash_pair(&zeros[level - 1], &zeros[level - 1]);
+    }
+    zeros
+}
+
+/// Leaf of a compressed record: the owner, the key and nonce it is encrypted with and
+/// its ciphertexts.
+pub fn compressed_leaf(
+    patient: &Pubkey,
+    enc_pubkey: &[u8; 32],
+    nonce: u128,
+    ciphertexts: &CompressedCiphertexts,
+) -> [u8; 32] {
+    let nonce = nonce.to_le_bytes();
+    let mut fields: Vec<&[u8]> = vec![patient.as_ref(), enc_pubkey, &nonce];
+    fields.extend(ciphertexts.iter().map(|ciphertext| ciphertext.as_slice()));
+    hashv(&fields).to_bytes()
+}
+
+impl CompressedRecordTree {
+    /// Resets the tree to empty, with the empty root as the only known root.
+    pub fn initialize(&mut self) {
+        self.next_index = 0;
+        self.filled_subtrees = [[0; 32]; COMPRESSED_TREE_DEPTH];
+        self.roots = [[0; 32]; COMPRESSED_ROOT_HISTORY];
+        self.roots[0] = zero_hashes()[COMPRESSED_TREE_DEPTH];
+        self.current_root_index = 0;
+    }
+
+    /// Appends `leaf`, returning its index and the new root.
+    pub fn append(&mut self, leaf: [u8; 32]) -> Result<(u64, [u8; 32])> {
+        let leaf_index = self.next_index;
+        require!(
+            leaf_index < 1 << COMPRESSED_TREE_DEPTH,
+            ErrorCode::CompressedTreeFull
+        );
+
+        let zeros = zero_hashes();
+        let mut index = leaf_index;
+        let mut node = leaf;
+        for level in 0..COMPRESSED_TREE_DEPTH {
+            node = if index & 1 == 0 {
+                self.filled_subtrees[level] = node;
+                hash_pair(&node, &zeros[level])
+            } else {
+                hash_pair(&self.filled_subtrees[level], &node)
+            };
+            index >>= 1;
+        }
+
+        self.current_root_index =
+            ((self.current_root_index as usize + 1) % COMPRESSED_ROOT_HISTORY) as u8;
+        self.roots[self.current_root_index as usize] = node;
+        self.next_index = leaf_index + 1;
+        Ok((leaf_index, node))
+    }
+
+    /// Checks that `leaf` sits at `leaf_index` under one of the recent roots.
+    pub fn verify(&self, leaf: [u8; 32], leaf_index: u64, proof: &[[u8; 32]]) -> Result<()> {
+        require!(
+            leaf_index < self.next_index && proof.len() == COMPRESSED_TREE_DEPTH,
+            ErrorCode::InvalidMerkleProof
+        );
+        let mut index = leaf_index;
+        let mut node = leaf;
+        for sibling in proof {
+            node = if index & 1 == 0 {
+                hash_pair(&node, sibling)
+            } else {
+                hash_pair(sibling, &node)
+            };
+            index >>= 1;
+        }
+        require!(self.roots.contains(&node), ErrorCode::InvalidMerkleProof);
+        Ok(())
+    }
+}
+
+#[event]
+pub struct CompressedRecordAppendedEvent {
+    pub tree: Pubkey,
+    pub leaf_index: u64,
+    pub patient: Pubkey,
+    pub enc_pubkey: [u8; 32],
+    pub nonce: u128,
+    pub ciphertexts: CompressedCiphertexts,
+    pub root: [u8; 32],
+}
+
+#[event]
+pub struct CompressedRecordShareQueuedEvent {
+    pub tree: Pubkey,
+    pub leaf_index: u64,
+    /// Receiver's x25519 key the record is re-encrypted for
+    pub receiver: [u8; 32],
+}
+
+#[event]
+pub struct CompressedRecordSharedEvent {
+    pub tree: Pubkey,
+    /// x25519 key the output is encrypted for
+    pub receiver_enc_key: [u8; 32],
+    /// Slot the result was delivered at
+    pub slot: u64,
+    pub nonce: [u8; 16],
+    pub ciphertexts: CompressedCiphertexts,
+}
+
+#[event]
+pub struct CompressedRecordShareAbortedEvent {
+    pub tree: Pubkey,
+}
+
+#[derive(Accounts)]
+#[instruction(tree_id: u64)]
+pub struct InitCompressedTree<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + CompressedRecordTree::INIT_SPACE,
+        seeds = [COMPRESSED_TREE_SEED, authority.key().as_ref(), &tree_id.to_le_bytes()],
+        bump,
+    )]
+    pub tree: Box<Account<'info, CompressedRecordTree>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AppendCompressedRecord<'info> {
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        has_one = authority @ ErrorCode::Unauthorized,
+    )]
+    pub tree: Box<Account<'info, CompressedRecordTree>>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[queue_computation_accounts("share_compressed_record", patient)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct ShareCompressedRecord<'info> {
+    #[account(mut)]
+    pub patient: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = patient,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account, checked by the arcium program.
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool, checked by the arcium program.
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account, checked by the arcium program.
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_SHARE_COMPRESSED_RECORD)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(
+        mut,
+        address = derive_cluster_pda!(mxe_account)
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Box<Account<'info, ProgramConfig>>,
+    pub tree: Box<Account<'info, CompressedRecordTree>>,
+    #[account(
+        init_if_needed,
+        payer = patient,
+        space = 8 + UsedOffsets::INIT_SPACE,
+        seeds = [USED_OFFSETS_SEED, patient.key().as_ref()],
+        bump,
+    )]
+    pub used_offsets: Box<Account<'info, UsedOffsets>>,
+    #[account(
+        init,
+        payer = patient,
+        space = 8 + CallbackGuard::INIT_SPACE,
+        seeds = [CALLBACK_GUARD_SEED, &computation_offset.to_le_bytes()],
+        bump,
+    )]
+    pub callback_guard: Box<Account<'info, CallbackGuard>>,
+}
+
+#[callback_accounts("share_compressed_record")]
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ShareCompressedRecordCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_SHARE_COMPRESSED_RECORD)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    #[account(
+        mut,
+        seeds = [CALLBACK_GUARD_SEED, &callback_guard.computation_offset.to_le_bytes()],
+        bump = callback_guard.bump,
+    )]
+    pub callback_guard: Box<Account<'info, CallbackGuard>>,
+    /// CHECK: tree, the compressed record tree the shared leaf belongs to; only its key is read.
+    pub tree: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        seeds = [USED_OFFSETS_SEED, used_offsets.payer.as_ref()],
+        bump = used_offsets.bump,
+    )]
+    pub used_offsets: Box<Account<'info, UsedOffsets>>,
+}
+
+#[init_computation_definition_accounts("share_compressed_record", payer)]
+#[derive(Accounts)]
+pub struct InitShareCompressedRecordCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut)]
+    /// CHECK: comp_def_account, checked by arcium program.
+    /// Can't check it here as it's not initialized yet.
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+        constraint = program_config.admin == payer.key() @ ErrorCode::Unauthorized,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
@@ -0,0 +1,427 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::sysvar::instructions::{
+    load_current_index_checked, load_instruction_at_checked,
+};
+use arcium_anchor::prelude::*;
+use arcium_client::idl::arcium::types::{CircuitSource, OffChainCircuitSource};
+
+use crate::program::ShareMedicalRecords;
+use crate::ErrorCode;
+use crate::*;
+
+pub const PROGRAM_CONFIG_SEED: &[u8] = b"program_config";
+/// Most circuits the manifest lists, retired ones included. The program initializes 28,
+/// so a few new share circuit versions fit before this has to grow.
+pub const MAX_MANIFEST_CIRCUITS: usize = 32;
+pub const MAX_CIRCUIT_URL_LEN: usize = 128;
+pub const MAX_APPROVED_CLUSTERS: usize = 8;
+/// Seconds after which an uncalled-back computation may be expired, unless configured.
+pub const DEFAULT_COMPUTATION_TIMEOUT: i64 = 60 * 60;
+
+/// Where the compiled circuit for one comp def lives and whether it has been initialized.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
+pub struct CircuitManifestEntry {
+    pub comp_def_offset: u32,
+    #[max_len(MAX_CIRCUIT_URL_LEN)]
+    pub source_url: String,
+    pub source_hash: [u8; 32],
+    pub initialized: bool,
+    /// Computations queued against this comp def whose callback has not run yet
+    pub pending_computations: u32,
+    /// Set once the circuit has been decommissioned; no new computations are accepted
+    pub retired: bool,
+}
+
+/// Program-wide operational settings, initialized once by the upgrade authority.
+#[account]
+#[derive(InitSpace)]
+pub struct ProgramConfig {
+    /// Authority allowed to change the configuration
+    pub admin: Pubkey,
+    /// Proposed next admin, who must accept before the transfer takes effect
+    pub pending_admin: Option<Pubkey>,
+    /// Halts every instruction except configuration and callbacks
+    pub paused: bool,
+    /// Halts storing and modifying records
+    pub paused_store: bool,
+    /// Halts queueing share computations
+    pub paused_share: bool,
+    /// Halts queueing any MPC computation, shares included
+    pub paused_compute: bool,
+    /// Version number of the currently active share circuit
+    pub circuit_version: u32,
+    /// Comp def offset new shares are expected to use
+    pub active_share_comp_def: u32,
+    /// Comp def offset of the previous circuit version, if still in its grace window
+    pub deprecated_share_comp_def: Option<u32>,
+    /// Unix timestamp after which the deprecated comp def is rejected
+    pub deprecation_ends_at: i64,
+    /// Seconds after queueing at which a computation without a callback counts as failed
+    pub computation_timeout: i64,
+    /// Cluster offsets computations may be routed to besides the MXE's own cluster
+    #[max_len(MAX_APPROVED_CLUSTERS)]
+    pub approved_clusters: Vec<u32>,
+    /// Keys allowed to register, revoke and re-role providers
+    #[max_len(MAX_REGISTRARS)]
+    pub registrars: Vec<Pubkey>,
+    /// Admissions feeds allowed to activate emergency consent policies
+    #[max_len(MAX_ADMISSION_ORACLES)]
+    pub admission_oracles: Vec<Pubkey>,
+    /// Keys allowed to request regulatory disclosures
+    #[max_len(MAX_REGULATORS)]
+    pub regulators: Vec<Pubkey>,
+    /// Members whose approvals a regulatory disclosure needs
+    #[max_len(MAX_DISCLOSURE_COMMITTEE)]
+    pub disclosure_committee: Vec<Pubkey>,
+    /// Committee approvals required before a disclosure can be queued
+    pub disclosure_threshold: u8,
+    /// Minimum seconds between two disclosures queued by the same regulator
+    pub disclosure_interval: i64,
+    /// Retention bounds per record type; types without a policy have none
+    #[max_len(MAX_RETENTION_POLICIES)]
+    pub retention_policies: Vec<RetentionPolicy>,
+    /// Emit large callback events through a self-CPI instead of program logs
+    pub event_cpi: bool,
+    /// Address lookup table holding the program's static accounts, if created
+    pub lookup_table: Pubkey,
+    /// Share of paid shares and research payouts routed to the treasury, in basis points
+    pub treasury_bps: u16,
+    /// Mint subscriptions are paid in; the default key disables subscriptions
+    pub subscription_mint: Pubkey,
+    /// Subscription price and duration per tier
+    #[max_len(MAX_SUBSCRIPTION_TIERS)]
+    pub subscription_tiers: Vec<SubscriptionTier>,
+    /// Off-chain sources of every circuit this program uses, in initialization order
+    #[max_len(MAX_MANIFEST_CIRCUITS)]
+    pub circuit_manifest: Vec<CircuitManifestEntry>,
+    /// Seconds the slot estimate of an expiry may run past it before overriding a
+    /// lagging cluster timestamp
+    pub clock_skew_tolerance: i64,
+    /// Computations a single payer may have queued and not yet called back or timed out
+    pub max_in_flight_per_payer: u8,
+    /// `PatientField` bits of the fields this deployment collects; records leave the
+    /// blocks of every other field zeroed
+    pub record_fields: u16,
+    /// Deliver shares into the receiver's `SharedRecord` and emit only a commitment to
+    /// the output, instead of all of its ciphertexts
+    pub compact_events: bool,
+    /// Keys allowed to checkpoint records' audit chains
+    #[max_len(MAX_AUDITORS)]
+    pub auditors: Vec<Pubkey>,
+    /// Members who can declare and end disaster modes
+    #[max_len(MAX_DISASTER_COMMITTEE)]
+    pub disaster_committee: Vec<Pubkey>,
+    /// Members who must co-sign a disaster declaration; zero disables declarations
+    pub disaster_threshold: u8,
+    pub bump: u8,
+}
+
+impl ProgramConfig {
+    /// Returns whether a share queued against `comp_def_offset` is accepted at `now`.
+    ///
+    /// The active offset is always accepted. The deprecated offset is accepted until the
+    /// grace window closes, so transactions built against the old circuit still land.
+    pub fn accepts_share_comp_def(&self, comp_def_offset: u32, now: i64) -> bool {
+        comp_def_offset == self.active_share_comp_def
+            || (self.deprecated_share_comp_def == Some(comp_def_offset)
+                && now <= self.deprecation_ends_at)
+    }
+
+    /// Resolves the share circuit version `comp_def_account` belongs to, failing with
+    /// `CircuitVersionRetired` unless that version is accepted at `now`.
+    pub fn resolve_share_comp_def(&self, comp_def_account: &Pubkey, now: i64) -> Result<u32> {
+        std::iter::once(self.active_share_comp_def)
+            .chain(self.deprecated_share_comp_def)
+            .find(|&offset| {
+                self.accepts_share_comp_def(offset, now)
+                    && derive_comp_def_pda!(offset) == *comp_def_account
+            })
+            .ok_or_else(|| ErrorCode::CircuitVersionRetired.into())
+    }
+
+    pub fn require_store_enabled(&self) -> Result<()> {
+        require!(!self.paused && !self.paused_store, ErrorCode::Paused);
+        Ok(())
+    }
+
+    pub fn require_share_enabled(&self) -> Result<()> {
+        require!(
+            !self.paused && !self.paused_share && !self.paused_compute,
+            ErrorCode::Paused
+        );
+        Ok(())
+    }
+
+    pub fn require_compute_enabled(&self) -> Result<()> {
+        require!(!self.paused && !self.paused_compute, ErrorCode::Paused);
+        Ok(())
+    }
+
+    pub fn is_registrar(&self, key: &Pubkey) -> bool {
+        self.registrars.contains(key)
+    }
+
+    /// Returns the retention policy for `record_type`, or an unbounded one if none is set.
+    pub fn retention_policy(&self, record_type: RecordType) -> RetentionPolicy {
+        self.retention_policies
+            .iter()
+            .find(|policy| policy.record_type == record_type)
+            .copied()
+            .unwrap_or(RetentionPolicy {
+                record_type,
+                min_retention_slots: 0,
+                max_retention_slots: 0,
+            })
+    }
+
+    /// Picks the cluster a computation is queued on.
+    ///
+    /// Without a hint the MXE's own cluster is used. A hint lets the caller route around a
+    /// congested or unavailable cluster, but only to one of the approved offsets.
+    pub fn select_cluster(
+        &self,
+        mxe_cluster: Option<u32>,
+        cluster_hint: Option<u32>,
+    ) -> Result<u32> {
+        let default_cluster = mxe_cluster.ok_or(ErrorCode::ClusterNotSet)?;
+        match cluster_hint {
+            None => Ok(default_cluster),
+            Some(offset) if offset == default_cluster => Ok(offset),
+            Some(offset) => {
+                require!(
+                    self.approved_clusters.contains(&offset),
+                    ErrorCode::ClusterNotApproved
+                );
+                Ok(offset)
+            }
+        }
+    }
+
+    /// Adds or replaces the manifest entry for `comp_def_offset`.
+    ///
+    /// A source can only change until its comp def has been initialized, since the
+    /// Arcium program pins the source at that point.
+    pub fn set_circuit_source(
+        &mut self,
+        comp_def_offset: u32,
+        source_url: String,
+        source_hash: [u8; 32],
+    ) -> Result<()> {
+        require!(
+            source_url.len() <= MAX_CIRCUIT_URL_LEN,
+            ErrorCode::CircuitSourceTooLong
+        );
+        match self
+            .circuit_manifest
+            .iter_mut()
+            .find(|entry| entry.comp_def_offset == comp_def_offset)
+        {
+            Some(entry) => {
+                require!(!entry.initialized, ErrorCode::CircuitAlreadyInitialized);
+                entry.source_url = source_url;
+                entry.source_hash = source_hash;
+            }
+            None => {
+                require!(
+                    self.circuit_manifest.len() < MAX_MANIFEST_CIRCUITS,
+                    ErrorCode::CircuitManifestFull
+                );
+                self.circuit_manifest.push(CircuitManifestEntry {
+                    comp_def_offset,
+                    source_url,
+                    source_hash,
+                    initialized: false,
+                    pending_computations: 0,
+                    retired: false,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Builds the circuit source to hand to `init_comp_def` for `comp_def_offset`.
+    pub fn circuit_source(&self, comp_def_offset: u32) -> Result<CircuitSource> {
+        let entry = self
+            .circuit_manifest
+            .iter()
+            .find(|entry| entry.comp_def_offset == comp_def_offset)
+            .ok_or(ErrorCode::CircuitNotInManifest)?;
+        require!(!entry.initialized, ErrorCode::CircuitAlreadyInitialized);
+        Ok(CircuitSource::OffChain(OffChainCircuitSource {
+            source: entry.source_url.clone(),
+            hash: entry.source_hash,
+        }))
+    }
+
+    /// Hash of the circuit source listed for `comp_def_offset`.
+    pub fn circuit_source_hash(&self, comp_def_offset: u32) -> Result<[u8; 32]> {
+        self.circuit_manifest
+            .iter()
+            .find(|entry| entry.comp_def_offset == comp_def_offset)
+            .map(|entry| entry.source_hash)
+            .ok_or_else(|| ErrorCode::CircuitNotInManifest.into())
+    }
+
+    /// Records that the comp def for `comp_def_offset` has been initialized.
+    pub fn mark_circuit_initialized(&mut self, comp_def_offset: u32) -> Result<()> {
+        self.manifest_entry_mut(comp_def_offset)?.initialized = true;
+        Ok(())
+    }
+
+    /// Counts a computation queued against `comp_def_offset`.
+    pub fn record_queued(&mut self, comp_def_offset: u32) -> Result<()> {
+        let entry = self.manifest_entry_mut(comp_def_offset)?;
+        require!(!entry.retired, ErrorCode::CircuitVersionRetired);
+        entry.pending_computations = entry
+            .pending_computations
+            .checked_add(1)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        Ok(())
+    }
+
+    /// Releases a pending computation once its callback has run, successful or not, or it
+    /// has been expired.
+    pub fn record_completed(&mut self, comp_def_offset: u32) -> Result<()> {
+        let entry = self.manifest_entry_mut(comp_def_offset)?;
+        entry.pending_computations = entry.pending_computations.saturating_sub(1);
+        Ok(())
+    }
+
+    /// Decommissions a circuit that is neither active nor referenced by pending computations.
+    pub fn retire_circuit(&mut self, comp_def_offset: u32) -> Result<()> {
+        require!(
+            comp_def_offset != self.active_share_comp_def,
+            ErrorCode::CircuitInUse
+        );
+        let entry = self.manifest_entry_mut(comp_def_offset)?;
+        require!(entry.pending_computations == 0, ErrorCode::CircuitInUse);
+        entry.retired = true;
+
+        if self.deprecated_share_comp_def == Some(comp_def_offset) {
+            self.deprecated_share_comp_def = None;
+            self.deprecation_ends_at = 0;
+        }
+        Ok(())
+    }
+
+    fn manifest_entry_mut(&mut self, comp_def_offset: u32) -> Result<&mut CircuitManifestEntry> {
+        self.circuit_manifest
+            .iter_mut()
+            .find(|entry| entry.comp_def_offset == comp_def_offset)
+            .ok_or_else(|| ErrorCode::CircuitNotInManifest.into())
+    }
+}
+
+#[derive(Accounts)]
+pub struct InitializeConfig<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + ProgramConfig::INIT_SPACE,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()))]
+    pub program: Program<'info, ShareMedicalRecords>,
+    #[account(
+        constraint = program_data.upgrade_authority_address == Some(payer.key())
+            @ ErrorCode::Unauthorized
+    )]
+    pub program_data: Account<'info, ProgramData>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Address of the Arcium cluster account for `cluster_offset`.
+pub fn cluster_pda(cluster_offset: u32) -> Pubkey {
+    Pubkey::find_program_address(
+        &[CLUSTER_PDA_SEED, &cluster_offset.to_le_bytes()],
+        &ARCIUM_PROG_ID,
+    )
+    .0
+}
+
+/// Callback accounts `#[event_cpi]` appends to a callback context, in order.
+pub fn event_cpi_callback_accounts() -> [CallbackAccount; 2] {
+    let (event_authority, _) = Pubkey::find_program_address(&[b"__event_authority"], &crate::ID);
+    [
+        CallbackAccount {
+            pubkey: event_authority,
+            is_writable: false,
+        },
+        CallbackAccount {
+            pubkey: crate::ID,
+            is_writable: false,
+        },
+    ]
+}
+
+/// Checks that the callback being handled was delivered by Arcium for `comp_def_account`.
+///
+/// The callback arrives by CPI from the top-level Arcium instruction finalizing the
+/// computation, which passes the comp def and that computation's account. A callback
+/// invoked directly, or by Arcium for another computation, is rejected.
+pub fn verify_callback_invocation(
+    instructions_sysvar: &AccountInfo,
+    comp_def_account: &Pubkey,
+    computation_offset: u64,
+) -> Result<()> {
+    let current_index = load_current_index_checked(instructions_sysvar)?;
+    let ix = load_instruction_at_checked(current_index as usize, instructions_sysvar)?;
+    require_keys_eq!(
+        ix.program_id,
+        ARCIUM_PROG_ID,
+        ErrorCode::CallbackNotFromArcium
+    );
+    let passes = |key: &Pubkey| ix.accounts.iter().any(|meta| meta.pubkey == *key);
+    require!(passes(comp_def_account), ErrorCode::CallbackCompDefMismatch);
+    require!(
+        passes(&derive_comp_pda!(computation_offset)),
+        ErrorCode::CallbackComputationMismatch
+    );
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+    pub admin: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+        has_one = admin @ ErrorCode::Unauthorized,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptAdmin<'info> {
+    pub new_admin: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+        constraint = program_config.pending_admin == Some(new_admin.key())
+            @ ErrorCode::Unauthorized,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[derive(Accounts)]
+#[instruction(version: u32, comp_def_offset: u32)]
+pub struct ActivateCircuitVersion<'info> {
+    pub admin: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+        has_one = admin @ ErrorCode::Unauthorized,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    #[account(
+        address = derive_comp_def_pda!(comp_def_offset)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+}
@@ -0,0 +1,213 @@
+use anchor_lang::prelude::*;
+
+use crate::ErrorCode;
+use crate::*;
+
+pub const CONSENT_GRANT_SEED: &[u8] = b"consent_grant";
+
+/// Scope bit covering the structured record in `PatientData`.
+pub const CONSENT_SCOPE_PATIENT_DATA: u16 = 1 << 0;
+/// Scope bit covering documents in the patient's document vault.
+pub const CONSENT_SCOPE_DOCUMENTS: u16 = 1 << 1;
+/// Scope bit covering anchored FHIR resources and HL7 messages.
+pub const CONSENT_SCOPE_CLINICAL_MESSAGES: u16 = 1 << 2;
+/// Scope bit covering DICOM imaging studies.
+pub const CONSENT_SCOPE_IMAGING: u16 = 1 << 3;
+/// Every scope bit defined so far.
+pub const CONSENT_SCOPE_ALL: u16 = CONSENT_SCOPE_PATIENT_DATA
+    | CONSENT_SCOPE_DOCUMENTS
+    | CONSENT_SCOPE_CLINICAL_MESSAGES
+    | CONSENT_SCOPE_IMAGING;
+
+/// A patient's consent for a receiver to access parts of their record until it expires.
+#[account]
+#[derive(InitSpace)]
+pub struct ConsentGrant {
+    pub patient_data: Pubkey,
+    /// Wallet that owns the record and granted the consent
+    pub patient: Pubkey,
+    /// Wallet the consent is granted to
+    pub receiver: Pubkey,
+    /// Bitmask of `CONSENT_SCOPE_*` values the consent covers
+    pub scope: u16,
+    /// Unix timestamp the consent was last granted at
+    pub granted_at: i64,
+    /// Unix timestamp the consent lapses at
+    pub expires_at: i64,
+    pub revoked: bool,
+    pub bump: u8,
+    /// Slot `expires_at` was estimated to fall in when granted
+    pub expires_at_slot: u64,
+}
+
+impl ConsentGrant {
+    /// Returns whether the consent is unrevoked and unexpired at `clock`.
+    pub fn is_active(&self, clock: &ExpiryClock) -> bool {
+        !self.revoked && !clock.has_passed(self.expires_at, self.expires_at_slot)
+    }
+
+    /// Fails with `ConsentRevoked` or `ConsentExpired` unless the consent is active at
+    /// `clock`.
+    pub fn require_active(&self, clock: &ExpiryClock) -> Result<()> {
+        require!(!self.revoked, ErrorCode::ConsentRevoked);
+        require!(
+            !clock.has_passed(self.expires_at, self.expires_at_slot),
+            ErrorCode::ConsentExpired
+        );
+        Ok(())
+    }
+
+    /// Fails unless the consent is active at `clock` and covers every bit of `scope`.
+    pub fn require_covers(&self, scope: u16, clock: &ExpiryClock) -> Result<()> {
+        self.require_active(clock)?;
+        require!(
+            self.scope & scope == scope,
+            ErrorCode::ConsentPurposeNotCovered
+        );
+        Ok(())
+    }
+}
+
+/// Fails unless `scope` is a non-empty set of known scope bits and `expires_at` is after
+/// `now`.
+pub fn require_valid_consent_terms(scope: u16, expires_at: i64, now: i64) -> Result<()> {
+    require!(
+        scope != 0 && scope & !CONSENT_SCOPE_ALL == 0,
+        ErrorCode::InvalidConsentScope
+    );
+    require!(expires_at > now, ErrorCode::InvalidConsentExpiry);
+    Ok(())
+}
+
+/// Validates and records a grant of `scope` on `patient_data` to `receiver`, returning
+/// the event to audit and emit.
+pub fn record_consent_grant(
+    consent_grant: &mut Account<ConsentGrant>,
+    patient_data: &Account<PatientData>,
+    receiver: Pubkey,
+    scope: u16,
+    expires_at: i64,
+    bump: u8,
+) -> Result<ConsentGrantedEvent> {
+    // Slot slack only matters when evaluating the expiry, not when estimating its slot
+    let clock = ExpiryClock::get(0)?;
+    let now = clock.unix_timestamp;
+    require_valid_consent_terms(scope, expires_at, now)?;
+
+    consent_grant.patient_data = patient_data.key();
+    consent_grant.patient = patient_data.patient_authority;
+    consent_grant.receiver = receiver;
+    consent_grant.scope = scope;
+    consent_grant.granted_at = now;
+    consent_grant.expires_at = expires_at;
+    consent_grant.expires_at_slot = clock.slot_at(expires_at);
+    consent_grant.revoked = false;
+    consent_grant.bump = bump;
+
+    Ok(ConsentGrantedEvent {
+        consent_grant: consent_grant.key(),
+        patient_data: consent_grant.patient_data,
+        receiver,
+        scope,
+        expires_at,
+    })
+}
+
+/// Marks `consent_grant` revoked, returning the event to audit and emit.
+pub fn record_consent_revocation(consent_grant: &mut Account<ConsentGrant>) -> ConsentRevokedEvent {
+    consent_grant.revoked = true;
+    ConsentRevokedEvent {
+        consent_grant: consent_grant.key(),
+        patient_data: consent_grant.patient_data,
+        receiver: consent_grant.receiver,
+    }
+}
+
+impl PatientData {
+    /// Fails unless `payer` controls the record or `consent_grant`, the payer's grant on
+    /// it, covers the structured record at `clock`.
+    pub fn require_share_authorized(
+        &self,
+        payer: &Pubkey,
+        consent_grant: Option<&ConsentGrant>,
+        clock: &ExpiryClock,
+    ) -> Result<()> {
+        if *payer == self.patient_authority {
+            return Ok(());
+        }
+        consent_grant
+            .ok_or(ErrorCode::UnauthorizedShare)?
+            .require_covers(CONSENT_SCOPE_PATIENT_DATA, clock)
+    }
+}
+
+#[event]
+pub struct ConsentGrantedEvent {
+    pub consent_grant: Pubkey,
+    pub patient_data: Pubkey,
+    pub receiver: Pubkey,
+    pub scope: u16,
+    pub expires_at: i64,
+}
+
+#[event]
+pub struct ConsentRevokedEvent {
+    pub consent_grant: Pubkey,
+    pub patient_data: Pubkey,
+    pub receiver: Pubkey,
+}
+
+#[derive(Accounts)]
+#[instruction(receiver: Pubkey)]
+pub struct GrantConsent<'info> {
+    #[account(mut)]
+    pub patient: Signer<'info>,
+    #[account(
+        seeds = [b"patient_data", patient.key().as_ref()],
+        bump,
+    )]
+    pub patient_data: Account<'info, PatientData>,
+    #[account(
+        init_if_needed,
+        payer = patient,
+        space = 8 + ConsentGrant::INIT_SPACE,
+        seeds = [CONSENT_GRANT_SEED, patient_data.key().as_ref(), receiver.as_ref()],
+        bump,
+    )]
+    pub consent_grant: Account<'info, ConsentGrant>,
+    #[account(
+        init_if_needed,
+        payer = patient,
+        space = 8 + PatientRegistry::INIT_SPACE,
+        seeds = [PATIENT_REGISTRY_SEED, patient_data.key().as_ref()],
+        bump,
+    )]
+    pub patient_registry: Box<Account<'info, PatientRegistry>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RevokeConsent<'info> {
+    #[account(mut)]
+    pub patient: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [
+            CONSENT_GRANT_SEED,
+            consent_grant.patient_data.as_ref(),
+            consent_grant.receiver.as_ref(),
+        ],
+        bump = consent_grant.bump,
+        has_one = patient @ ErrorCode::Unauthorized,
+    )]
+    pub consent_grant: Account<'info, ConsentGrant>,
+    #[account(
+        init_if_needed,
+        payer = patient,
+        space = 8 + PatientRegistry::INIT_SPACE,
+        seeds = [PATIENT_REGISTRY_SEED, consent_grant.patient_data.as_ref()],
+        bump,
+    )]
+    pub patient_registry: Box<Account<'info, PatientRegistry>>,
+    pub system_program: Program<'info, System>,
+}
@@ -0,0 +1,179 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program::{create_account, CreateAccount};
+
+use crate::ErrorCode;
+use crate::*;
+
+/// Most consents one `process_consents_batch` takes.
+pub const MAX_CONSENT_BATCH_ITEMS: usize = 8;
+/// Remaining accounts passed per batch item: the patient's record, the consent grant to
+/// create or renew and the record's registry.
+pub const CONSENT_BATCH_ITEM_ACCOUNTS: usize = 3;
+/// Result code of a batch item whose consent was granted.
+pub const CONSENT_BATCH_GRANTED: u32 = 0;
+
+/// A consent a patient approved off-chain for the provider submitting the batch.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct ConsentBatchItem {
+    /// Wallet of the patient who owns the record
+    pub patient: Pubkey,
+    pub scope: u16,
+    pub expires_at: i64,
+    /// Unix timestamp after which the signed approval is rejected
+    pub approval_expires_at: i64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct ConsentBatchResult {
+    pub patient_data: Pubkey,
+    /// `CONSENT_BATCH_GRANTED`, or the number of the error the item failed with
+    pub code: u32,
+}
+
+#[event]
+pub struct ConsentBatchProcessedEvent {
+    /// Provider the consents were granted to
+    pub receiver: Pubkey,
+    /// One result per item, in order
+    pub results: Vec<ConsentBatchResult>,
+}
+
+/// Accounts of a batch item that passed every check, ready to be granted.
+pub struct ApprovedConsent<'info> {
+    pub patient_data: Account<'info, PatientData>,
+    pub patient_registry: Account<'info, PatientRegistry>,
+    pub consent_grant: &'info AccountInfo<'info>,
+    pub consent_grant_bump: u8,
+}
+
+impl ConsentBatchItem {
+    /// Checks the item's `accounts` and that signature `index` of `ed25519_data` is the
+    /// patient's approval of the consent to `receiver`, without writing anything.
+    pub fn approve<'info>(
+        &self,
+        receiver: &Pubkey,
+        accounts: &'info [AccountInfo<'info>],
+        ed25519_data: &[u8],
+        index: usize,
+        now: i64,
+    ) -> Result<ApprovedConsent<'info>> {
+        let [patient_data_info, consent_grant_info, registry_info] = accounts else {
+            return err!(ErrorCode::ConsentBatchMismatch);
+        };
+        let (patient_data_address, _) =
+            Pubkey::find_program_address(&[b"patient_data", self.patient.as_ref()], &crate::ID);
+        require_keys_eq!(
+            patient_data_info.key(),
+            patient_data_address,
+            ErrorCode::ConsentBatchMismatch
+        );
+        let patient_data = checked_program_account::<PatientData>(patient_data_info)?;
+        // Registries only ever exist at their record's PDA, so the recorded record is enough
+        let patient_registry = checked_program_account::<PatientRegistry>(registry_info)?;
+        require_keys_eq!(
+            patient_registry.patient_data,
+            patient_data_address,
+            ErrorCode::ConsentBatchMismatch
+        );
+
+        require_valid_consent_terms(self.scope, self.expires_at, now)?;
+        PatientApproval {
+            action: ApprovalAction::GrantConsent,
+            patient: self.patient,
+            payload_hash: grant_consent_payload_hash(
+                receiver,
+                self.scope,
+                self.expires_at,
+                patient_registry.audit_entries,
+            ),
+            expires_at: self.approval_expires_at,
+        }
+        .verify_entry(ed25519_data, index, now)?;
+
+        let (consent_grant_address, consent_grant_bump) = Pubkey::find_program_address(
+            &[
+                CONSENT_GRANT_SEED,
+                patient_data_address.as_ref(),
+                receiver.as_ref(),
+            ],
+            &crate::ID,
+        );
+        require_keys_eq!(
+            consent_grant_info.key(),
+            consent_grant_address,
+            ErrorCode::ConsentBatchMismatch
+        );
+        if !consent_grant_info.data_is_empty() {
+            checked_program_account::<ConsentGrant>(consent_grant_info)?;
+        }
+
+        Ok(ApprovedConsent {
+            patient_data,
+            patient_registry,
+            consent_grant: consent_grant_info,
+            consent_grant_bump,
+        })
+    }
+}
+
+impl<'info> ApprovedConsent<'info> {
+    /// Creates or renews the consent grant to `accounts.provider` and audits it on the
+    /// record, returning the event to emit.
+    pub fn grant(
+        mut self,
+        accounts: &ProcessConsentsBatch<'info>,
+        item: &ConsentBatchItem,
+    ) -> Result<ConsentGrantedEvent> {
+        let patient_data = self.patient_data.key();
+        let receiver = accounts.provider.key();
+        let mut consent_grant = if self.consent_grant.data_is_empty() {
+            let space = 8 + ConsentGrant::INIT_SPACE;
+            create_account(
+                CpiContext::new_with_signer(
+                    accounts.system_program.to_account_info(),
+                    CreateAccount {
+                        from: accounts.provider.to_account_info(),
+                        to: self.consent_grant.clone(),
+                    },
+                    &[&[
+                        CONSENT_GRANT_SEED,
+                        patient_data.as_ref(),
+                        receiver.as_ref(),
+                        &[self.consent_grant_bump],
+                    ]],
+                ),
+                Rent::get()?.minimum_balance(space),
+                space as u64,
+                &crate::ID,
+            )?;
+            Account::<ConsentGrant>::try_from_unchecked(self.consent_grant)?
+        } else {
+            Account::<ConsentGrant>::try_from(self.consent_grant)?
+        };
+
+        let event = record_consent_grant(
+            &mut consent_grant,
+            &self.patient_data,
+            receiver,
+            item.scope,
+            item.expires_at,
+            self.consent_grant_bump,
+        )?;
+        let bump = self.patient_registry.bump;
+        self.patient_registry.audit(patient_data, bump, &event)?;
+        consent_grant.exit(&crate::ID)?;
+        self.patient_registry.exit(&crate::ID)?;
+        Ok(event)
+    }
+}
+
+#[derive(Accounts)]
+pub struct ProcessConsentsBatch<'info> {
+    /// Provider accepting the consents, which are granted to it; pays the rent of new ones
+    #[account(mut)]
+    pub provider: Signer<'info>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
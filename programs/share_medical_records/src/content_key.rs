@@ -0,0 +1,87 @@
+use anchor_lang::prelude::*;
+use arcium_anchor::prelude::*;
+
+use crate::ErrorCode;
+use crate::*;
+
+/// A 256-bit symmetric key encrypted to the MXE, as two 128-bit ciphertext limbs.
+pub type EncryptedContentKey = CipherBlob<2>;
+
+/// Length of an `EncryptedContentKey`, stored first in every account that holds one.
+pub const CONTENT_KEY_CIPHERTEXTS_LEN: u32 = EncryptedContentKey::LEN;
+
+#[event]
+pub struct ContentKeySharedEvent {
+    /// Account the content key belongs to
+    pub anchor: Pubkey,
+    /// x25519 key the output is encrypted for
+    pub receiver_enc_key: [u8; 32],
+    /// Slot the result was delivered at
+    pub slot: u64,
+    pub nonce: [u8; 16],
+    pub content_key: EncryptedContentKey,
+}
+
+#[event]
+pub struct ContentKeyShareAbortedEvent {
+    pub anchor: Pubkey,
+}
+
+#[callback_accounts("share_content_key")]
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ShareContentKeyCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_SHARE_CONTENT_KEY)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    #[account(
+        mut,
+        seeds = [CALLBACK_GUARD_SEED, &callback_guard.computation_offset.to_le_bytes()],
+        bump = callback_guard.bump,
+    )]
+    pub callback_guard: Box<Account<'info, CallbackGuard>>,
+    /// CHECK: anchor, the account whose content key was shared; set by the queueing instruction.
+    pub anchor: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        seeds = [USED_OFFSETS_SEED, used_offsets.payer.as_ref()],
+        bump = used_offsets.bump,
+    )]
+    pub used_offsets: Box<Account<'info, UsedOffsets>>,
+}
+
+#[init_computation_definition_accounts("share_content_key", payer)]
+#[derive(Accounts)]
+pub struct InitShareContentKeyCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut)]
+    /// CHECK: comp_def_account, checked by arcium program.
+    /// Can't check it here as it's not initialized yet.
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+        constraint = program_config.admin == payer.key() @ ErrorCode::Unauthorized,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
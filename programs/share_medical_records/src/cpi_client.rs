@@ -0,0 +1,374 @@
+//! Instruction builders for programs invoking this one through CPI.
+//!
+//! Anchor's generated `cpi` module expects every account of an instruction to be passed
+//! in by the caller. These helpers derive the program-owned and Arcium accounts instead,
+//! so a partner program only supplies what is specific to its call and then invokes the
+//! returned instruction with `invoke` or `invoke_signed`.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::InstructionData;
+use arcium_anchor::prelude::*;
+
+use crate::*;
+
+fn program_address(seeds: &[&[u8]]) -> Pubkey {
+    Pubkey::find_program_address(seeds, &crate::ID).0
+}
+
+/// Builds the accounts of `share_patient_data` for a share without an access fee.
+///
+/// `execute_share` takes the same accounts with `staged_share` set to
+/// [`staged_share_address`].
+///
+/// Set the fee fields on the returned struct when the record charges one, and
+/// `consent_grant` to [`consent_grant_address`] when `payer` shares under the patient's
+/// consent rather than as the record's patient authority. `access_log_page` is the
+/// record's first page; once the record has logged `ACCESS_LOG_PAGE_SIZE` shares, set it
+/// to [`access_log_page_address`] of its registry's `access_log_page()`. While the
+/// program's compact events are on, set `shared_record` to [`shared_record_address`].
+pub fn share_patient_data_accounts(
+    payer: Pubkey,
+    patient_data: Pubkey,
+    receiver: [u8; 32],
+    computation_offset: u64,
+    cluster_offset: u32,
+) -> crate::accounts::SharePatientData {
+    crate::accounts::SharePatientData {
+        payer,
+        sign_pda_account: derive_sign_pda!(),
+        mxe_account: derive_mxe_pda!(),
+        mempool_account: derive_mempool_pda!(),
+        executing_pool: derive_execpool_pda!(),
+        computation_account: derive_comp_pda!(computation_offset),
+        comp_def_account: derive_comp_def_pda!(COMP_DEF_OFFSET_SHARE_PATIENT_DATA),
+        cluster_account: cluster_pda(cluster_offset),
+        pool_account: ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+        clock_account: ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+        system_program: anchor_lang::system_program::ID,
+        arcium_program: ARCIUM_PROG_ID,
+        patient_data,
+        consent_grant: None,
+        program_config: program_address(&[PROGRAM_CONFIG_SEED]),
+        access_fee: program_address(&[ACCESS_FEE_SEED, patient_data.as_ref()]),
+        subscription: program_address(&[SUBSCRIPTION_SEED, payer.as_ref()]),
+        fee_mint: None,
+        payer_fee_account: None,
+        patient_fee_account: None,
+        treasury: None,
+        fee_escrow: None,
+        fee_authority: None,
+        token_program: None,
+        pending_share: pending_share_address(&patient_data, &receiver),
+        refund_balance: program_address(&[REFUND_BALANCE_SEED, payer.as_ref()]),
+        used_offsets: program_address(&[USED_OFFSETS_SEED, payer.as_ref()]),
+        callback_guard: callback_guard_address(computation_offset),
+        used_nonces: program_address(&[USED_NONCES_SEED, patient_data.as_ref()]),
+        instructions_sysvar: anchor_lang::solana_program::sysvar::instructions::ID,
+        patient_registry: program_address(&[PATIENT_REGISTRY_SEED, patient_data.as_ref()]),
+        access_log_page: access_log_page_address(&patient_data, 0),
+        share_receipt: share_receipt_address(&patient_data, computation_offset),
+        shared_record: None,
+        provider_profile: program_address(&[PROVIDER_SEED, payer.as_ref()]),
+        staged_share: None,
+    }
+}
+
+/// Builds the accounts of `preview_share` of the patient's own record.
+pub fn preview_share_accounts(
+    patient: Pubkey,
+    computation_offset: u64,
+    cluster_offset: u32,
+) -> crate::accounts::PreviewShare {
+    crate::accounts::PreviewShare {
+        patient,
+        sign_pda_account: derive_sign_pda!(),
+        mxe_account: derive_mxe_pda!(),
+        mempool_account: derive_mempool_pda!(),
+        executing_pool: derive_execpool_pda!(),
+        computation_account: derive_comp_pda!(computation_offset),
+        comp_def_account: derive_comp_def_pda!(COMP_DEF_OFFSET_PREVIEW_SHARE),
+        cluster_account: cluster_pda(cluster_offset),
+        pool_account: ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+        clock_account: ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+        system_program: anchor_lang::system_program::ID,
+        arcium_program: ARCIUM_PROG_ID,
+        program_config: program_address(&[PROGRAM_CONFIG_SEED]),
+        patient_data: program_address(&[b"patient_data", patient.as_ref()]),
+        used_offsets: program_address(&[USED_OFFSETS_SEED, patient.as_ref()]),
+        callback_guard: callback_guard_address(computation_offset),
+    }
+}
+
+/// Builds the accounts of `share_bundle` of the patient's record and their FHIR anchors
+/// `labs_anchor_id` and `prescriptions_anchor_id`.
+pub fn share_bundle_accounts(
+    patient: Pubkey,
+    labs_anchor_id: u64,
+    prescriptions_anchor_id: u64,
+    computation_offset: u64,
+    cluster_offset: u32,
+) -> crate::accounts::ShareBundle {
+    let patient_data = program_address(&[b"patient_data", patient.as_ref()]);
+    let fhir_anchor = |anchor_id: u64| {
+        program_address(&[FHIR_ANCHOR_SEED, patient.as_ref(), &anchor_id.to_le_bytes()])
+    };
+    crate::accounts::ShareBundle {
+        patient,
+        sign_pda_account: derive_sign_pda!(),
+        mxe_account: derive_mxe_pda!(),
+        mempool_account: derive_mempool_pda!(),
+        executing_pool: derive_execpool_pda!(),
+        computation_account: derive_comp_pda!(computation_offset),
+        comp_def_account: derive_comp_def_pda!(COMP_DEF_OFFSET_SHARE_BUNDLE),
+        cluster_account: cluster_pda(cluster_offset),
+        pool_account: ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+        clock_account: ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+        system_program: anchor_lang::system_program::ID,
+        arcium_program: ARCIUM_PROG_ID,
+        program_config: program_address(&[PROGRAM_CONFIG_SEED]),
+        patient_data,
+        patient_registry: program_address(&[PATIENT_REGISTRY_SEED, patient_data.as_ref()]),
+        labs_anchor: fhir_anchor(labs_anchor_id),
+        prescriptions_anchor: fhir_anchor(prescriptions_anchor_id),
+        used_offsets: program_address(&[USED_OFFSETS_SEED, patient.as_ref()]),
+        callback_guard: callback_guard_address(computation_offset),
+    }
+}
+
+/// Builds the accounts of `redeem_share_code` by `provider` of the code whose key is
+/// `code_key`, for the record `patient_data` the code's account names.
+pub fn redeem_share_code_accounts(
+    provider: Pubkey,
+    code_key: Pubkey,
+    patient_data: Pubkey,
+    computation_offset: u64,
+    cluster_offset: u32,
+) -> crate::accounts::RedeemShareCode {
+    crate::accounts::RedeemShareCode {
+        provider,
+        sign_pda_account: derive_sign_pda!(),
+        mxe_account: derive_mxe_pda!(),
+        mempool_account: derive_mempool_pda!(),
+        executing_pool: derive_execpool_pda!(),
+        computation_account: derive_comp_pda!(computation_offset),
+        comp_def_account: derive_comp_def_pda!(COMP_DEF_OFFSET_REDEEM_SHARE_CODE),
+        cluster_account: cluster_pda(cluster_offset),
+        pool_account: ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+        clock_account: ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+        system_program: anchor_lang::system_program::ID,
+        arcium_program: ARCIUM_PROG_ID,
+        program_config: program_address(&[PROGRAM_CONFIG_SEED]),
+        provider_profile: program_address(&[PROVIDER_SEED, provider.as_ref()]),
+        share_code: program_address(&[SHARE_CODE_SEED, code_key.as_ref()]),
+        patient_data,
+        patient_registry: program_address(&[PATIENT_REGISTRY_SEED, patient_data.as_ref()]),
+        used_offsets: program_address(&[USED_OFFSETS_SEED, provider.as_ref()]),
+        callback_guard: callback_guard_address(computation_offset),
+        instructions_sysvar: anchor_lang::solana_program::sysvar::instructions::ID,
+    }
+}
+
+/// Builds the accounts of `fetch_disaster_packet` by `responder` of the emergency packet
+/// of `patient_data`, encrypted for the EMS region `region`.
+pub fn fetch_disaster_packet_accounts(
+    responder: Pubkey,
+    patient_data: Pubkey,
+    region: u32,
+    computation_offset: u64,
+    cluster_offset: u32,
+) -> crate::accounts::FetchDisasterPacket {
+    crate::accounts::FetchDisasterPacket {
+        responder,
+        sign_pda_account: derive_sign_pda!(),
+        mxe_account: derive_mxe_pda!(),
+        mempool_account: derive_mempool_pda!(),
+        executing_pool: derive_execpool_pda!(),
+        computation_account: derive_comp_pda!(computation_offset),
+        comp_def_account: derive_comp_def_pda!(COMP_DEF_OFFSET_DISASTER_PACKET),
+        cluster_account: cluster_pda(cluster_offset),
+        pool_account: ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+        clock_account: ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+        system_program: anchor_lang::system_program::ID,
+        arcium_program: ARCIUM_PROG_ID,
+        program_config: program_address(&[PROGRAM_CONFIG_SEED]),
+        provider_profile: program_address(&[PROVIDER_SEED, responder.as_ref()]),
+        disaster_mode: program_address(&[DISASTER_MODE_SEED, &region.to_le_bytes()]),
+        emergency_packet: program_address(&[EMERGENCY_PACKET_SEED, patient_data.as_ref()]),
+        patient_data,
+        patient_registry: program_address(&[PATIENT_REGISTRY_SEED, patient_data.as_ref()]),
+        access_log_page: access_log_page_address(&patient_data, 0),
+        inbox: program_address(&[INBOX_SEED, patient_data.as_ref()]),
+        used_offsets: program_address(&[USED_OFFSETS_SEED, responder.as_ref()]),
+        callback_guard: callback_guard_address(computation_offset),
+    }
+}
+
+/// Builds the accounts of `share_fhir_content_key` by `patient` of their anchor
+/// `anchor_id`.
+///
+/// Shares of a genomic anchor must also pass every kin link of the patient's record as
+/// remaining accounts.
+pub fn share_fhir_content_key_accounts(
+    patient: Pubkey,
+    anchor_id: u64,
+    computation_offset: u64,
+    cluster_offset: u32,
+) -> crate::accounts::ShareFhirContentKey {
+    let patient_data = program_address(&[b"patient_data", patient.as_ref()]);
+    crate::accounts::ShareFhirContentKey {
+        patient,
+        sign_pda_account: derive_sign_pda!(),
+        mxe_account: derive_mxe_pda!(),
+        mempool_account: derive_mempool_pda!(),
+        executing_pool: derive_execpool_pda!(),
+        computation_account: derive_comp_pda!(computation_offset),
+        comp_def_account: derive_comp_def_pda!(COMP_DEF_OFFSET_SHARE_CONTENT_KEY),
+        cluster_account: cluster_pda(cluster_offset),
+        pool_account: ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+        clock_account: ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+        system_program: anchor_lang::system_program::ID,
+        arcium_program: ARCIUM_PROG_ID,
+        program_config: program_address(&[PROGRAM_CONFIG_SEED]),
+        fhir_anchor: program_address(&[
+            FHIR_ANCHOR_SEED,
+            patient.as_ref(),
+            &anchor_id.to_le_bytes(),
+        ]),
+        patient_data,
+        kin_roster: program_address(&[KIN_ROSTER_SEED, patient_data.as_ref()]),
+        used_offsets: program_address(&[USED_OFFSETS_SEED, patient.as_ref()]),
+        callback_guard: callback_guard_address(computation_offset),
+    }
+}
+
+/// Builds the accounts of `cohort_query` by `researcher` over `research_escrow`, whose
+/// deposit is in `mint`.
+///
+/// Pass the cohort as remaining accounts: each member's record, research opt-in and
+/// payout token account.
+pub fn cohort_query_accounts(
+    researcher: Pubkey,
+    research_escrow: Pubkey,
+    mint: Pubkey,
+    researcher_token_account: Pubkey,
+    token_program: Pubkey,
+    computation_offset: u64,
+    cluster_offset: u32,
+) -> crate::accounts::CohortQuery {
+    crate::accounts::CohortQuery {
+        researcher,
+        sign_pda_account: derive_sign_pda!(),
+        mxe_account: derive_mxe_pda!(),
+        mempool_account: derive_mempool_pda!(),
+        executing_pool: derive_execpool_pda!(),
+        computation_account: derive_comp_pda!(computation_offset),
+        comp_def_account: derive_comp_def_pda!(COMP_DEF_OFFSET_COHORT_QUERY),
+        cluster_account: cluster_pda(cluster_offset),
+        pool_account: ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+        clock_account: ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+        system_program: anchor_lang::system_program::ID,
+        arcium_program: ARCIUM_PROG_ID,
+        program_config: program_address(&[PROGRAM_CONFIG_SEED]),
+        research_escrow,
+        escrow_vault: program_address(&[RESEARCH_VAULT_SEED, research_escrow.as_ref()]),
+        researcher_token_account,
+        treasury: program_address(&[TREASURY_SEED, mint.as_ref()]),
+        mint,
+        fee_authority: program_address(&[FEE_AUTHORITY_SEED]),
+        token_program,
+        used_offsets: program_address(&[USED_OFFSETS_SEED, researcher.as_ref()]),
+        callback_guard: callback_guard_address(computation_offset),
+        cohort_snapshot: program_address(&[COHORT_SNAPSHOT_SEED, research_escrow.as_ref()]),
+    }
+}
+
+/// Address of page `page` of the access log of `patient_data`.
+pub fn access_log_page_address(patient_data: &Pubkey, page: u32) -> Pubkey {
+    program_address(&[ACCESS_LOG_SEED, patient_data.as_ref(), &page.to_le_bytes()])
+}
+
+/// Address of the receipt of the share of `patient_data` queued at `computation_offset`.
+pub fn share_receipt_address(patient_data: &Pubkey, computation_offset: u64) -> Pubkey {
+    program_address(&[
+        SHARE_RECEIPT_SEED,
+        patient_data.as_ref(),
+        &computation_offset.to_le_bytes(),
+    ])
+}
+
+/// Address of the shared record shares of `patient_data` to `receiver` are delivered to
+/// while compact events are on.
+pub fn shared_record_address(patient_data: &Pubkey, receiver: &[u8; 32]) -> Pubkey {
+    program_address(&[SHARED_RECORD_SEED, patient_data.as_ref(), receiver])
+}
+
+/// Address of the consent `receiver` holds on `patient_data`.
+pub fn consent_grant_address(patient_data: &Pubkey, receiver: &Pubkey) -> Pubkey {
+    program_address(&[CONSENT_GRANT_SEED, patient_data.as_ref(), receiver.as_ref()])
+}
+
+/// Address of the pending share of `patient_data` to `receiver`, which exists while such a
+/// share is in flight.
+pub fn pending_share_address(patient_data: &Pubkey, receiver: &[u8; 32]) -> Pubkey {
+    program_address(&[PENDING_SHARE_SEED, patient_data.as_ref(), receiver])
+}
+
+/// Address of the Arcium computation definition at `comp_def_offset`, e.g. to share
+/// through a newly activated share circuit version.
+pub fn comp_def_address(comp_def_offset: u32) -> Pubkey {
+    derive_comp_def_pda!(comp_def_offset)
+}
+
+/// Address of the guard over the callback of the computation at `computation_offset`.
+pub fn callback_guard_address(computation_offset: u64) -> Pubkey {
+    program_address(&[CALLBACK_GUARD_SEED, &computation_offset.to_le_bytes()])
+}
+
+/// Address of the share `payer` staged with `prepare_share` at `computation_offset`.
+pub fn staged_share_address(payer: &Pubkey, computation_offset: u64) -> Pubkey {
+    program_address(&[
+        STAGED_SHARE_SEED,
+        payer.as_ref(),
+        &computation_offset.to_le_bytes(),
+    ])
+}
+
+/// Builds a `share_patient_data` instruction from its accounts and arguments.
+pub fn share_patient_data_ix(
+    accounts: crate::accounts::SharePatientData,
+    args: crate::instruction::SharePatientData,
+) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: accounts.to_account_metas(None),
+        data: args.data(),
+    }
+}
+
+/// Reads the program config, e.g. to check pause flags before building a share.
+pub fn load_program_config(info: &AccountInfo) -> Result<ProgramConfig> {
+    require_keys_eq!(
+        info.key(),
+        program_address(&[PROGRAM_CONFIG_SEED]),
+        ErrorCode::InvalidProgramConfig
+    );
+    require_keys_eq!(*info.owner, crate::ID, ErrorCode::InvalidProgramConfig);
+    ProgramConfig::try_deserialize(&mut &info.try_borrow_data()?[..])
+}
+
+/// Returns whether `provider` is an active registered provider, given its profile account.
+pub fn is_active_provider(provider: &Pubkey, provider_profile: &AccountInfo) -> Result<bool> {
+    if provider_profile.key() != program_address(&[PROVIDER_SEED, provider.as_ref()])
+        || provider_profile.data_is_empty()
+    {
+        return Ok(false);
+    }
+    require_keys_eq!(
+        *provider_profile.owner,
+        crate::ID,
+        ErrorCode::InvalidProgramConfig
+    );
+    let profile = ProviderProfile::try_deserialize(&mut &provider_profile.try_borrow_data()?[..])?;
+    Ok(profile.active)
+}
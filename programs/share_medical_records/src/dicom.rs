@@ -0,0 +1,250 @@
+use anchor_lang::prelude::*;
+use arcium_anchor::prelude::*;
+
+use crate::ErrorCode;
+use crate::*;
+
+pub const DICOM_REFERENCE_SEED: &[u8] = b"dicom_reference";
+/// Enough for DICOM modality codes (`CT`, `MR`, `US`, ...) and short vendor variants.
+pub const MAX_DICOM_MODALITY_LEN: usize = 16;
+pub const MAX_DICOM_POINTER_LEN: usize = 128;
+/// Length of the ciphertexts at the start of `DicomReference`, the part circuits read.
+pub const DICOM_CIPHERTEXTS_LEN: u32 = EncryptedStudyUid::LEN + 32;
+
+/// A study instance UID, up to 64 characters, encrypted to the MXE as four limbs.
+pub type EncryptedStudyUid = CipherBlob<4>;
+
+/// Reference to a DICOM imaging study held by an imaging center's PACS.
+///
+/// The study instance UID and accession number identify the patient at the imaging
+/// center, so they are stored encrypted to the MXE and only re-encrypted for receivers.
+#[account]
+#[derive(InitSpace)]
+pub struct DicomReference {
+    /// Encrypted study instance UID
+    pub study_uid: EncryptedStudyUid,
+    /// Encrypted accession number, up to 16 characters
+    pub accession_number: [u8; 32],
+    pub patient_data: Pubkey,
+    /// Imaging center that created the reference
+    pub imaging_center: Pubkey,
+    pub reference_id: u64,
+    /// DICOM modality code of the study
+    #[max_len(MAX_DICOM_MODALITY_LEN)]
+    pub modality: String,
+    /// Where the study can be retrieved, e.g. a DICOMweb endpoint
+    #[max_len(MAX_DICOM_POINTER_LEN)]
+    pub pointer: String,
+    /// x25519 key the identifiers were encrypted with
+    pub enc_pubkey: [u8; 32],
+    /// Nonce the identifiers were encrypted with
+    pub nonce: u128,
+    pub bump: u8,
+}
+
+#[event]
+pub struct DicomReferenceCreatedEvent {
+    pub dicom_reference: Pubkey,
+    pub patient_data: Pubkey,
+    pub imaging_center: Pubkey,
+    pub modality: String,
+}
+
+#[event]
+pub struct DicomStudySharedEvent {
+    pub dicom_reference: Pubkey,
+    /// x25519 key the output is encrypted for
+    pub receiver_enc_key: [u8; 32],
+    /// Slot the result was delivered at
+    pub slot: u64,
+    pub modality: String,
+    pub pointer: String,
+    pub nonce: [u8; 16],
+    pub study_uid: EncryptedStudyUid,
+    pub accession_number: [u8; 32],
+}
+
+#[event]
+pub struct DicomStudyShareAbortedEvent {
+    pub dicom_reference: Pubkey,
+}
+
+#[derive(Accounts)]
+#[instruction(reference_id: u64)]
+pub struct CreateDicomReference<'info> {
+    #[account(mut)]
+    pub imaging_center: Signer<'info>,
+    #[account(
+        seeds = [PROVIDER_SEED, imaging_center.key().as_ref()],
+        bump = provider_profile.bump,
+        constraint = provider_profile.active
+            && provider_profile.role == ProviderRole::ImagingCenter
+            @ ErrorCode::Unauthorized,
+    )]
+    pub provider_profile: Account<'info, ProviderProfile>,
+    pub patient_data: Account<'info, PatientData>,
+    #[account(
+        init,
+        payer = imaging_center,
+        space = 8 + DicomReference::INIT_SPACE,
+        seeds = [
+            DICOM_REFERENCE_SEED,
+            patient_data.key().as_ref(),
+            &reference_id.to_le_bytes(),
+        ],
+        bump,
+    )]
+    pub dicom_reference: Account<'info, DicomReference>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    pub system_program: Program<'info, System>,
+}
+
+#[queue_computation_accounts("share_dicom_reference", patient)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct ShareDicomStudy<'info> {
+    #[account(mut)]
+    pub patient: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = patient,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account, checked by the arcium program.
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool, checked by the arcium program.
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account, checked by the arcium program.
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_SHARE_DICOM_REFERENCE)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(
+        mut,
+        address = derive_cluster_pda!(mxe_account)
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Box<Account<'info, ProgramConfig>>,
+    #[account(
+        seeds = [b"patient_data", patient.key().as_ref()],
+        bump,
+    )]
+    pub patient_data: Box<Account<'info, PatientData>>,
+    #[account(has_one = patient_data)]
+    pub dicom_reference: Box<Account<'info, DicomReference>>,
+    #[account(
+        init_if_needed,
+        payer = patient,
+        space = 8 + UsedOffsets::INIT_SPACE,
+        seeds = [USED_OFFSETS_SEED, patient.key().as_ref()],
+        bump,
+    )]
+    pub used_offsets: Box<Account<'info, UsedOffsets>>,
+    #[account(
+        init,
+        payer = patient,
+        space = 8 + CallbackGuard::INIT_SPACE,
+        seeds = [CALLBACK_GUARD_SEED, &computation_offset.to_le_bytes()],
+        bump,
+    )]
+    pub callback_guard: Box<Account<'info, CallbackGuard>>,
+}
+
+#[callback_accounts("share_dicom_reference")]
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ShareDicomReferenceCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_SHARE_DICOM_REFERENCE)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    #[account(
+        mut,
+        seeds = [CALLBACK_GUARD_SEED, &callback_guard.computation_offset.to_le_bytes()],
+        bump = callback_guard.bump,
+    )]
+    pub callback_guard: Box<Account<'info, CallbackGuard>>,
+    pub dicom_reference: Account<'info, DicomReference>,
+    #[account(
+        mut,
+        seeds = [USED_OFFSETS_SEED, used_offsets.payer.as_ref()],
+        bump = used_offsets.bump,
+    )]
+    pub used_offsets: Box<Account<'info, UsedOffsets>>,
+}
+
+#[init_computation_definition_accounts("share_dicom_reference", payer)]
+#[derive(Accounts)]
+pub struct InitShareDicomReferenceCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut)]
+    /// CHECK: comp_def_account, checked by arcium program.
+    /// Can't check it here as it's not initialized yet.
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+        constraint = program_config.admin == payer.key() @ ErrorCode::Unauthorized,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
@@ -0,0 +1,370 @@
+use anchor_lang::prelude::*;
+use arcium_anchor::prelude::*;
+
+use crate::ErrorCode;
+use crate::*;
+
+pub const DISASTER_MODE_SEED: &[u8] = b"disaster_mode";
+pub const MAX_DISASTER_COMMITTEE: usize = 5;
+/// Longest a disaster declaration may last: 14 days. A longer response is declared again.
+pub const MAX_DISASTER_MODE_SECONDS: i64 = 14 * 24 * 60 * 60;
+/// Furthest ahead a declaration may start: one day, so declarations answer a disaster at
+/// hand rather than sit in reserve.
+pub const MAX_DISASTER_MODE_NOTICE_SECONDS: i64 = 24 * 60 * 60;
+
+/// A declared disaster in one region, during which responders may fetch the emergency
+/// packets of patients whose packets are encrypted for that region.
+///
+/// Declared by `disaster_threshold` members of the configured disaster committee
+/// co-signing. While it runs, a fetch needs no approval from the patient, but every fetch
+/// is logged to the record's access log and audit chain, and posted to the patient's
+/// inbox for them to see afterwards. A region is declared again only once its previous
+/// declaration has ended, so neither the co-signers nor the fetch count of a running one
+/// are ever replaced.
+#[account]
+#[derive(InitSpace)]
+pub struct DisasterMode {
+    /// Region tag of the `EmsAuthority` whose packets are opened, part of the address
+    pub region: u32,
+    pub starts_at: i64,
+    pub ends_at: i64,
+    /// Slot `ends_at` was estimated to fall in when declared
+    pub ends_at_slot: u64,
+    /// Committee members who co-signed the declaration
+    #[max_len(MAX_DISASTER_COMMITTEE)]
+    pub declared_by: Vec<Pubkey>,
+    pub declared_at: i64,
+    /// Packets fetched under this declaration
+    pub fetches: u64,
+    pub bump: u8,
+    /// Declarations made for the region so far, this one included
+    pub declarations: u32,
+}
+
+impl DisasterMode {
+    /// Returns whether the declared window has ended; a region never declared has.
+    pub fn has_ended(&self, clock: &ExpiryClock) -> bool {
+        clock.has_passed(self.ends_at, self.ends_at_slot)
+    }
+
+    /// Fails with `DisasterModeInactive` outside the declared window.
+    pub fn require_active(&self, clock: &ExpiryClock) -> Result<()> {
+        require!(
+            clock.unix_timestamp >= self.starts_at
+                && !clock.has_passed(self.ends_at, self.ends_at_slot),
+            ErrorCode::DisasterModeInactive
+        );
+        Ok(())
+    }
+}
+
+#[event]
+pub struct DisasterModeDeclaredEvent {
+    pub disaster_mode: Pubkey,
+    pub region: u32,
+    /// Ordinal of the declaration among the region's, from 1
+    pub declaration: u32,
+    pub starts_at: i64,
+    pub ends_at: i64,
+    pub declared_by: Vec<Pubkey>,
+    /// Packets fetched under the region's previous declaration, which this one replaces
+    pub previous_fetches: u64,
+}
+
+#[event]
+pub struct DisasterModeEndedEvent {
+    pub disaster_mode: Pubkey,
+    pub region: u32,
+    pub ended_by: Pubkey,
+    pub fetches: u64,
+}
+
+/// A responder fetched a packet under a disaster declaration; patients subscribe by
+/// `patient_data`.
+#[event]
+pub struct DisasterPacketFetchedEvent {
+    pub disaster_mode: Pubkey,
+    pub emergency_packet: Pubkey,
+    pub patient_data: Pubkey,
+    pub responder: Pubkey,
+    /// x25519 key the packet is re-encrypted for
+    pub receiver_enc_key: [u8; 32],
+    pub computation_offset: u64,
+}
+
+/// A fetched packet was delivered; responders subscribe by `responder`.
+#[event]
+pub struct DisasterPacketDeliveredEvent {
+    pub emergency_packet: Pubkey,
+    pub responder: Pubkey,
+    /// x25519 key the output is encrypted for
+    pub receiver_enc_key: [u8; 32],
+    pub slot: u64,
+    pub nonce: [u8; 16],
+    /// Age, blood type, then allergies, in record order
+    pub ciphertexts: [[u8; 32]; EMERGENCY_PACKET_BLOCKS],
+}
+
+#[derive(Accounts)]
+#[instruction(region: u32)]
+pub struct DeclareDisasterMode<'info> {
+    /// Committee member paying for the declaration; the other co-signers are passed in
+    /// `remaining_accounts`
+    #[account(mut)]
+    pub declarer: Signer<'info>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+        constraint = program_config.disaster_committee.contains(&declarer.key())
+            @ ErrorCode::Unauthorized,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    #[account(
+        seeds = [EMS_AUTHORITY_SEED, &region.to_le_bytes()],
+        bump = ems_authority.bump,
+    )]
+    pub ems_authority: Account<'info, EmsAuthority>,
+    #[account(
+        init_if_needed,
+        payer = declarer,
+        space = 8 + DisasterMode::INIT_SPACE,
+        seeds = [DISASTER_MODE_SEED, &region.to_le_bytes()],
+        bump,
+    )]
+    pub disaster_mode: Account<'info, DisasterMode>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct EndDisasterMode<'info> {
+    pub member: Signer<'info>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+        constraint = program_config.disaster_committee.contains(&member.key())
+            @ ErrorCode::Unauthorized,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    #[account(
+        mut,
+        seeds = [DISASTER_MODE_SEED, &disaster_mode.region.to_le_bytes()],
+        bump = disaster_mode.bump,
+    )]
+    pub disaster_mode: Account<'info, DisasterMode>,
+}
+
+#[queue_computation_accounts("disaster_packet", responder)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct FetchDisasterPacket<'info> {
+    #[account(mut)]
+    pub responder: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = responder,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account, checked by the arcium program.
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool, checked by the arcium program.
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account, checked by the arcium program.
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_DISASTER_PACKET)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(
+        mut,
+        address = derive_cluster_pda!(mxe_account)
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Box<Account<'info, ProgramConfig>>,
+    #[account(
+        seeds = [PROVIDER_SEED, responder.key().as_ref()],
+        bump = provider_profile.bump,
+        constraint = provider_profile.active @ ErrorCode::ProviderNotRegistered,
+    )]
+    pub provider_profile: Box<Account<'info, ProviderProfile>>,
+    #[account(
+        mut,
+        seeds = [DISASTER_MODE_SEED, &emergency_packet.region.to_le_bytes()],
+        bump = disaster_mode.bump,
+    )]
+    pub disaster_mode: Box<Account<'info, DisasterMode>>,
+    #[account(
+        seeds = [EMERGENCY_PACKET_SEED, emergency_packet.patient_data.as_ref()],
+        bump = emergency_packet.bump,
+    )]
+    pub emergency_packet: Box<Account<'info, EmergencyPacket>>,
+    #[account(address = emergency_packet.patient_data)]
+    pub patient_data: Box<Account<'info, PatientData>>,
+    #[account(
+        init_if_needed,
+        payer = responder,
+        space = 8 + PatientRegistry::INIT_SPACE,
+        seeds = [PATIENT_REGISTRY_SEED, patient_data.key().as_ref()],
+        bump,
+    )]
+    pub patient_registry: Box<Account<'info, PatientRegistry>>,
+    #[account(
+        init_if_needed,
+        payer = responder,
+        space = 8 + AccessLogPage::INIT_SPACE,
+        seeds = [
+            ACCESS_LOG_SEED,
+            patient_data.key().as_ref(),
+            &patient_registry.access_log_page().to_le_bytes(),
+        ],
+        bump,
+    )]
+    pub access_log_page: Box<Account<'info, AccessLogPage>>,
+    #[account(
+        mut,
+        seeds = [INBOX_SEED, patient_data.key().as_ref()],
+        bump,
+    )]
+    /// CHECK: inbox, empty unless the patient opened one; written in the handler.
+    pub inbox: UncheckedAccount<'info>,
+    #[account(
+        init_if_needed,
+        payer = responder,
+        space = 8 + UsedOffsets::INIT_SPACE,
+        seeds = [USED_OFFSETS_SEED, responder.key().as_ref()],
+        bump,
+    )]
+    pub used_offsets: Box<Account<'info, UsedOffsets>>,
+    #[account(
+        init,
+        payer = responder,
+        space = 8 + CallbackGuard::INIT_SPACE,
+        seeds = [CALLBACK_GUARD_SEED, &computation_offset.to_le_bytes()],
+        bump,
+    )]
+    pub callback_guard: Box<Account<'info, CallbackGuard>>,
+}
+
+#[callback_accounts("disaster_packet")]
+#[derive(Accounts)]
+pub struct DisasterPacketCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_DISASTER_PACKET)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    #[account(
+        mut,
+        seeds = [CALLBACK_GUARD_SEED, &callback_guard.computation_offset.to_le_bytes()],
+        bump = callback_guard.bump,
+    )]
+    pub callback_guard: Box<Account<'info, CallbackGuard>>,
+    #[account(
+        seeds = [EMERGENCY_PACKET_SEED, emergency_packet.patient_data.as_ref()],
+        bump = emergency_packet.bump,
+    )]
+    pub emergency_packet: Box<Account<'info, EmergencyPacket>>,
+    #[account(
+        seeds = [PROVIDER_SEED, provider_profile.provider.as_ref()],
+        bump = provider_profile.bump,
+    )]
+    pub provider_profile: Box<Account<'info, ProviderProfile>>,
+    #[account(
+        mut,
+        seeds = [USED_OFFSETS_SEED, used_offsets.payer.as_ref()],
+        bump = used_offsets.bump,
+    )]
+    pub used_offsets: Box<Account<'info, UsedOffsets>>,
+}
+
+#[init_computation_definition_accounts("disaster_packet", payer)]
+#[derive(Accounts)]
+pub struct InitDisasterPacketCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut)]
+    /// CHECK: comp_def_account, checked by arcium program.
+    /// Can't check it here as it's not initialized yet.
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+        constraint = program_config.admin == payer.key() @ ErrorCode::Unauthorized,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+/// Collects the distinct disaster committee members among `declarer` and the signers in
+/// `remaining_accounts`, failing unless they reach the configured threshold.
+pub fn disaster_declarers(
+    config: &ProgramConfig,
+    declarer: Pubkey,
+    remaining_accounts: &[AccountInfo],
+) -> Result<Vec<Pubkey>> {
+    let mut declarers = vec![declarer];
+    for info in remaining_accounts {
+        if info.is_signer
+            && config.disaster_committee.contains(info.key)
+            && !declarers.contains(info.key)
+        {
+            declarers.push(info.key());
+        }
+    }
+    require!(
+        config.disaster_threshold > 0 && declarers.len() >= config.disaster_threshold as usize,
+        ErrorCode::DisasterThresholdNotMet
+    );
+    Ok(declarers)
+}
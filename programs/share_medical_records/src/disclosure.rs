@@ -0,0 +1,296 @@
+use anchor_lang::prelude::*;
+use arcium_anchor::prelude::*;
+
+use crate::ErrorCode;
+use crate::*;
+
+pub const DISCLOSURE_REQUEST_SEED: &[u8] = b"disclosure_request";
+pub const REGULATOR_ACTIVITY_SEED: &[u8] = b"regulator_activity";
+pub const MAX_REGULATORS: usize = 4;
+pub const MAX_DISCLOSURE_COMMITTEE: usize = 5;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub enum DisclosureStatus {
+    /// Collecting committee approvals
+    Requested,
+    /// Re-encryption queued, waiting for the callback
+    Queued,
+    /// Record delivered to the regulator
+    Disclosed,
+    /// Computation failed; a new request is needed
+    Failed,
+}
+
+/// A regulator's request to have one record disclosed under a court order.
+#[account]
+#[derive(InitSpace)]
+pub struct DisclosureRequest {
+    pub regulator: Pubkey,
+    pub patient_data: Pubkey,
+    /// Hash of the court order backing the request
+    pub court_order_hash: [u8; 32],
+    /// Committee members who approved the request
+    #[max_len(MAX_DISCLOSURE_COMMITTEE)]
+    pub approvals: Vec<Pubkey>,
+    pub status: DisclosureStatus,
+    /// Unix timestamp the request was filed at
+    pub requested_at: i64,
+    pub bump: u8,
+}
+
+/// Disclosure history of one regulator, used to rate-limit them.
+#[account]
+#[derive(InitSpace)]
+pub struct RegulatorActivity {
+    pub regulator: Pubkey,
+    /// Unix timestamp of the regulator's latest queued disclosure
+    pub last_disclosure_at: i64,
+    pub disclosures: u64,
+    pub bump: u8,
+}
+
+#[event]
+pub struct DisclosureRequestedEvent {
+    pub disclosure_request: Pubkey,
+    pub regulator: Pubkey,
+    pub patient_data: Pubkey,
+    pub court_order_hash: [u8; 32],
+}
+
+#[event]
+pub struct DisclosureApprovedEvent {
+    pub disclosure_request: Pubkey,
+    pub approver: Pubkey,
+    pub approvals: u8,
+}
+
+#[event]
+pub struct DisclosureQueuedEvent {
+    pub disclosure_request: Pubkey,
+    pub regulator: Pubkey,
+    pub computation_offset: u64,
+}
+
+#[event]
+pub struct RegulatoryDisclosureEvent {
+    pub disclosure_request: Pubkey,
+    pub regulator: Pubkey,
+    /// x25519 key the output is encrypted for
+    pub receiver_enc_key: [u8; 32],
+    /// Slot the result was delivered at
+    pub slot: u64,
+    pub court_order_hash: [u8; 32],
+    pub nonce: [u8; 16],
+    pub patient_id: [u8; 32],
+    pub age: [u8; 32],
+    pub gender: [u8; 32],
+    pub blood_type: [u8; 32],
+    pub weight: [u8; 32],
+    pub height: [u8; 32],
+    pub allergies: [[u8; 32]; 5],
+}
+
+#[event]
+pub struct RegulatoryDisclosureFailedEvent {
+    pub disclosure_request: Pubkey,
+    pub regulator: Pubkey,
+}
+
+#[derive(Accounts)]
+#[instruction(court_order_hash: [u8; 32])]
+pub struct RequestDisclosure<'info> {
+    #[account(mut)]
+    pub regulator: Signer<'info>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+        constraint = program_config.regulators.contains(&regulator.key())
+            @ ErrorCode::Unauthorized,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    pub patient_data: Account<'info, PatientData>,
+    #[account(
+        init,
+        payer = regulator,
+        space = 8 + DisclosureRequest::INIT_SPACE,
+        seeds = [
+            DISCLOSURE_REQUEST_SEED,
+            patient_data.key().as_ref(),
+            court_order_hash.as_ref(),
+        ],
+        bump,
+    )]
+    pub disclosure_request: Account<'info, DisclosureRequest>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ApproveDisclosure<'info> {
+    pub approver: Signer<'info>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+        constraint = program_config.disclosure_committee.contains(&approver.key())
+            @ ErrorCode::Unauthorized,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    #[account(mut)]
+    pub disclosure_request: Account<'info, DisclosureRequest>,
+}
+
+#[queue_computation_accounts("regulatory_disclosure", regulator)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct RegulatoryDisclosure<'info> {
+    #[account(mut)]
+    pub regulator: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = regulator,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account, checked by the arcium program.
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool, checked by the arcium program.
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account, checked by the arcium program.
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_REGULATORY_DISCLOSURE)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(
+        mut,
+        address = derive_cluster_pda!(mxe_account)
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+        constraint = program_config.regulators.contains(&regulator.key())
+            @ ErrorCode::Unauthorized,
+    )]
+    pub program_config: Box<Account<'info, ProgramConfig>>,
+    #[account(
+        mut,
+        has_one = regulator @ ErrorCode::Unauthorized,
+        has_one = patient_data,
+    )]
+    pub disclosure_request: Box<Account<'info, DisclosureRequest>>,
+    pub patient_data: Box<Account<'info, PatientData>>,
+    #[account(
+        init_if_needed,
+        payer = regulator,
+        space = 8 + RegulatorActivity::INIT_SPACE,
+        seeds = [REGULATOR_ACTIVITY_SEED, regulator.key().as_ref()],
+        bump,
+    )]
+    pub regulator_activity: Box<Account<'info, RegulatorActivity>>,
+    #[account(
+        init_if_needed,
+        payer = regulator,
+        space = 8 + UsedOffsets::INIT_SPACE,
+        seeds = [USED_OFFSETS_SEED, regulator.key().as_ref()],
+        bump,
+    )]
+    pub used_offsets: Box<Account<'info, UsedOffsets>>,
+    #[account(
+        init,
+        payer = regulator,
+        space = 8 + CallbackGuard::INIT_SPACE,
+        seeds = [CALLBACK_GUARD_SEED, &computation_offset.to_le_bytes()],
+        bump,
+    )]
+    pub callback_guard: Box<Account<'info, CallbackGuard>>,
+}
+
+#[callback_accounts("regulatory_disclosure")]
+#[event_cpi]
+#[derive(Accounts)]
+pub struct RegulatoryDisclosureCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_REGULATORY_DISCLOSURE)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    #[account(
+        mut,
+        seeds = [CALLBACK_GUARD_SEED, &callback_guard.computation_offset.to_le_bytes()],
+        bump = callback_guard.bump,
+    )]
+    pub callback_guard: Box<Account<'info, CallbackGuard>>,
+    #[account(mut)]
+    pub disclosure_request: Account<'info, DisclosureRequest>,
+    #[account(
+        mut,
+        seeds = [USED_OFFSETS_SEED, used_offsets.payer.as_ref()],
+        bump = used_offsets.bump,
+    )]
+    pub used_offsets: Box<Account<'info, UsedOffsets>>,
+}
+
+#[init_computation_definition_accounts("regulatory_disclosure", payer)]
+#[derive(Accounts)]
+pub struct InitRegulatoryDisclosureCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut)]
+    /// CHECK: comp_def_account, checked by arcium program.
+    /// Can't check it here as it's not initialized yet.
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+        constraint = program_config.admin == payer.key() @ ErrorCode::Unauthorized,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
@@ -0,0 +1,175 @@
+use anchor_lang::prelude::*;
+use arcium_anchor::prelude::*;
+
+use crate::ErrorCode;
+use crate::*;
+
+pub const DOCUMENT_VAULT_SEED: &[u8] = b"document_vault";
+pub const MAX_VAULT_DOCUMENTS: usize = 8;
+/// Long enough for CIDv1 base32 strings and Arweave transaction ids.
+pub const MAX_DOCUMENT_CID_LEN: usize = 64;
+
+/// One encrypted document stored on IPFS or Arweave.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
+pub struct DocumentEntry {
+    /// IPFS CID or Arweave transaction id of the encrypted document
+    #[max_len(MAX_DOCUMENT_CID_LEN)]
+    pub cid: String,
+    /// SHA-256 of the stored ciphertext
+    pub ciphertext_hash: [u8; 32],
+    /// Document content key, encrypted to the MXE with the vault's key
+    pub content_key: EncryptedContentKey,
+    /// Nonce the content key was encrypted with
+    pub key_nonce: u128,
+}
+
+/// A patient's list of off-chain documents, of which only the keys live on-chain.
+#[account]
+#[derive(InitSpace)]
+pub struct DocumentVault {
+    pub owner: Pubkey,
+    /// x25519 public key the content keys are encrypted with
+    pub enc_pubkey: [u8; 32],
+    #[max_len(MAX_VAULT_DOCUMENTS)]
+    pub entries: Vec<DocumentEntry>,
+    pub bump: u8,
+}
+
+#[event]
+pub struct DocumentAddedEvent {
+    pub document_vault: Pubkey,
+    pub cid: String,
+    pub ciphertext_hash: [u8; 32],
+}
+
+#[event]
+pub struct DocumentRemovedEvent {
+    pub document_vault: Pubkey,
+    pub cid: String,
+}
+
+#[event]
+pub struct DocumentShareQueuedEvent {
+    pub document_vault: Pubkey,
+    pub cid: String,
+    pub ciphertext_hash: [u8; 32],
+    /// Receiver's x25519 key the content key is re-encrypted for
+    pub receiver: [u8; 32],
+}
+
+#[derive(Accounts)]
+pub struct InitDocumentVault<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + DocumentVault::INIT_SPACE,
+        seeds = [DOCUMENT_VAULT_SEED, owner.key().as_ref()],
+        bump,
+    )]
+    pub document_vault: Account<'info, DocumentVault>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateDocumentVault<'info> {
+    pub owner: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [DOCUMENT_VAULT_SEED, owner.key().as_ref()],
+        bump = document_vault.bump,
+    )]
+    pub document_vault: Account<'info, DocumentVault>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[queue_computation_accounts("share_content_key", owner)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct ShareDocument<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = owner,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account, checked by the arcium program.
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool, checked by the arcium program.
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account, checked by the arcium program.
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_SHARE_CONTENT_KEY)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(
+        mut,
+        address = derive_cluster_pda!(mxe_account)
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Box<Account<'info, ProgramConfig>>,
+    #[account(
+        seeds = [DOCUMENT_VAULT_SEED, owner.key().as_ref()],
+        bump = document_vault.bump,
+    )]
+    pub document_vault: Box<Account<'info, DocumentVault>>,
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + UsedOffsets::INIT_SPACE,
+        seeds = [USED_OFFSETS_SEED, owner.key().as_ref()],
+        bump,
+    )]
+    pub used_offsets: Box<Account<'info, UsedOffsets>>,
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + CallbackGuard::INIT_SPACE,
+        seeds = [CALLBACK_GUARD_SEED, &computation_offset.to_le_bytes()],
+        bump,
+    )]
+    pub callback_guard: Box<Account<'info, CallbackGuard>>,
+}
@@ -0,0 +1,286 @@
+use anchor_lang::prelude::*;
+use arcium_anchor::prelude::*;
+
+use crate::ErrorCode;
+use crate::*;
+
+pub const DONOR_PROFILE_SEED: &[u8] = b"donor_profile";
+/// Donors one search runs over, fixed by the `find_compatible_donors` circuit.
+pub const DONOR_SEARCH_SIZE: usize = 4;
+/// Red-cell antigen bits of a blood type; O negative carries none.
+pub const BLOOD_ANTIGEN_A: u8 = 1 << 0;
+pub const BLOOD_ANTIGEN_B: u8 = 1 << 1;
+pub const BLOOD_ANTIGEN_RH_D: u8 = 1 << 2;
+pub const BLOOD_ANTIGENS_ALL: u8 = BLOOD_ANTIGEN_A | BLOOD_ANTIGEN_B | BLOOD_ANTIGEN_RH_D;
+/// Products a donor may be eligible to give.
+pub const DONOR_PRODUCT_WHOLE_BLOOD: u8 = 1 << 0;
+pub const DONOR_PRODUCT_PLATELETS: u8 = 1 << 1;
+pub const DONOR_PRODUCT_PLASMA: u8 = 1 << 2;
+pub const DONOR_PRODUCTS_ALL: u8 =
+    DONOR_PRODUCT_WHOLE_BLOOD | DONOR_PRODUCT_PLATELETS | DONOR_PRODUCT_PLASMA;
+/// Encrypted values of a donor: three antigens, one eligibility flag per product and
+/// their token.
+pub const DONOR_VALUES: usize = 3 + 3 + 1;
+/// Length of the ciphertexts at the start of `DonorProfile`, the part circuits read.
+pub const DONOR_CIPHERTEXTS_LEN: u32 = DONOR_VALUES as u32 * 32;
+
+/// A patient's registration as a blood donor.
+///
+/// Their antigens, per-product eligibility and a random token they chose are encrypted
+/// under their own key. Searches only ever learn the token of a compatible donor, which
+/// the donor's app recognizes so they can answer the blood bank through it.
+#[account]
+#[derive(InitSpace)]
+pub struct DonorProfile {
+    /// Encrypted antigens A, B and RhD, eligibility for each `DONOR_PRODUCT_*` and token
+    pub donor: [[u8; 32]; DONOR_VALUES],
+    pub patient_data: Pubkey,
+    /// Wallet that owns the record and registered as a donor
+    pub patient: Pubkey,
+    /// x25519 key the donor values were encrypted with
+    pub enc_pubkey: [u8; 32],
+    /// Nonce the donor values were encrypted with
+    pub nonce: u128,
+    /// Cleared to withdraw from searches without deregistering
+    pub consenting: bool,
+    pub updated_at: i64,
+    pub bump: u8,
+}
+
+#[event]
+pub struct DonorRegisteredEvent {
+    pub donor_profile: Pubkey,
+    pub patient_data: Pubkey,
+}
+
+#[event]
+pub struct DonorConsentSetEvent {
+    pub donor_profile: Pubkey,
+    pub consenting: bool,
+}
+
+#[event]
+pub struct CompatibleDonorsFoundEvent {
+    pub blood_bank: Pubkey,
+    /// x25519 key the output is encrypted for
+    pub receiver_enc_key: [u8; 32],
+    /// Slot the result was delivered at
+    pub slot: u64,
+    pub nonce: [u8; 16],
+    /// Encrypted number of compatible donors
+    pub count: [u8; 32],
+    /// Encrypted tokens of the compatible donors, zero past `count`
+    pub tokens: [[u8; 32]; DONOR_SEARCH_SIZE],
+}
+
+#[event]
+pub struct DonorSearchAbortedEvent {
+    pub blood_bank: Pubkey,
+}
+
+#[derive(Accounts)]
+pub struct RegisterDonor<'info> {
+    #[account(mut)]
+    pub patient: Signer<'info>,
+    #[account(
+        seeds = [b"patient_data", patient.key().as_ref()],
+        bump,
+    )]
+    pub patient_data: Account<'info, PatientData>,
+    #[account(
+        init_if_needed,
+        payer = patient,
+        space = 8 + DonorProfile::INIT_SPACE,
+        seeds = [DONOR_PROFILE_SEED, patient_data.key().as_ref()],
+        bump,
+    )]
+    pub donor_profile: Box<Account<'info, DonorProfile>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetDonorConsent<'info> {
+    pub patient: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [DONOR_PROFILE_SEED, donor_profile.patient_data.as_ref()],
+        bump = donor_profile.bump,
+        has_one = patient @ ErrorCode::Unauthorized,
+    )]
+    pub donor_profile: Box<Account<'info, DonorProfile>>,
+}
+
+#[queue_computation_accounts("find_compatible_donors", blood_bank)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct FindCompatibleDonors<'info> {
+    #[account(mut)]
+    pub blood_bank: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = blood_bank,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account, checked by the arcium program.
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool, checked by the arcium program.
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account, checked by the arcium program.
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_FIND_COMPATIBLE_DONORS)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(
+        mut,
+        address = derive_cluster_pda!(mxe_account)
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Box<Account<'info, ProgramConfig>>,
+    #[account(
+        seeds = [PROVIDER_SEED, blood_bank.key().as_ref()],
+        bump = provider_profile.bump,
+        constraint = provider_profile.active
+            && provider_profile.role == ProviderRole::BloodBank
+            @ ErrorCode::NotABloodBank,
+    )]
+    pub provider_profile: Box<Account<'info, ProviderProfile>>,
+    #[account(
+        init_if_needed,
+        payer = blood_bank,
+        space = 8 + UsedOffsets::INIT_SPACE,
+        seeds = [USED_OFFSETS_SEED, blood_bank.key().as_ref()],
+        bump,
+    )]
+    pub used_offsets: Box<Account<'info, UsedOffsets>>,
+    #[account(
+        init,
+        payer = blood_bank,
+        space = 8 + CallbackGuard::INIT_SPACE,
+        seeds = [CALLBACK_GUARD_SEED, &computation_offset.to_le_bytes()],
+        bump,
+    )]
+    pub callback_guard: Box<Account<'info, CallbackGuard>>,
+}
+
+#[callback_accounts("find_compatible_donors")]
+#[derive(Accounts)]
+pub struct FindCompatibleDonorsCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_FIND_COMPATIBLE_DONORS)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    #[account(
+        mut,
+        seeds = [CALLBACK_GUARD_SEED, &callback_guard.computation_offset.to_le_bytes()],
+        bump = callback_guard.bump,
+    )]
+    pub callback_guard: Box<Account<'info, CallbackGuard>>,
+    #[account(
+        seeds = [PROVIDER_SEED, provider_profile.provider.as_ref()],
+        bump = provider_profile.bump,
+    )]
+    pub provider_profile: Box<Account<'info, ProviderProfile>>,
+    #[account(
+        mut,
+        seeds = [USED_OFFSETS_SEED, used_offsets.payer.as_ref()],
+        bump = used_offsets.bump,
+    )]
+    pub used_offsets: Box<Account<'info, UsedOffsets>>,
+}
+
+#[init_computation_definition_accounts("find_compatible_donors", payer)]
+#[derive(Accounts)]
+pub struct InitFindCompatibleDonorsCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut)]
+    /// CHECK: comp_def_account, checked by arcium program.
+    /// Can't check it here as it's not initialized yet.
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+        constraint = program_config.admin == payer.key() @ ErrorCode::Unauthorized,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+/// Builds the circuit arguments for the donor profiles passed in `remaining_accounts`.
+///
+/// Expects `DONOR_SEARCH_SIZE` distinct profiles, every one still consenting.
+pub fn donor_search_arguments(remaining_accounts: &[AccountInfo]) -> Result<Vec<Argument>> {
+    require!(
+        remaining_accounts.len() == DONOR_SEARCH_SIZE,
+        ErrorCode::InvalidDonorSearch
+    );
+
+    let mut arguments = Vec::with_capacity(DONOR_SEARCH_SIZE * 3);
+    for (i, info) in remaining_accounts.iter().enumerate() {
+        require!(
+            !remaining_accounts[..i]
+                .iter()
+                .any(|other| other.key == info.key),
+            ErrorCode::DuplicateDonor
+        );
+        let profile = checked_program_account::<DonorProfile>(info)?;
+        require!(profile.consenting, ErrorCode::DonorNotConsenting);
+        require_ciphertext_region::<DonorProfile>(info)?;
+        arguments.extend(donor_args(profile.enc_pubkey, profile.nonce, info.key()));
+    }
+    Ok(arguments)
+}
@@ -0,0 +1,167 @@
+use anchor_lang::prelude::*;
+
+use crate::ErrorCode;
+use crate::*;
+
+pub const EMERGENCY_POLICY_SEED: &[u8] = b"emergency_policy";
+pub const MAX_ADMISSION_ORACLES: usize = 4;
+/// Longest emergency consent a policy may grant per admission: 30 days.
+pub const MAX_EMERGENCY_CONSENT_DURATION: i64 = 30 * 24 * 60 * 60;
+
+/// Consent a patient pre-authorizes an organization to receive if they are admitted there.
+///
+/// Inactive until a whitelisted admissions oracle reports the patient admitted by the
+/// organization, at which point it is turned into a `ConsentGrant`.
+#[account]
+#[derive(InitSpace)]
+pub struct EmergencyConsentPolicy {
+    pub patient_data: Pubkey,
+    pub patient: Pubkey,
+    /// Admitting organization's wallet, the receiver of the emergency consent
+    pub organization: Pubkey,
+    /// Bitmask of `CONSENT_SCOPE_*` values granted on admission
+    pub scope: u16,
+    /// Seconds the emergency consent lasts after an admission
+    pub duration: i64,
+    /// Set by the oracle while the patient is admitted at the organization
+    pub admitted: bool,
+    /// Unix timestamp of the latest admission
+    pub admitted_at: i64,
+    pub bump: u8,
+}
+
+#[event]
+pub struct EmergencyConsentPolicySetEvent {
+    pub emergency_policy: Pubkey,
+    pub patient_data: Pubkey,
+    pub organization: Pubkey,
+    pub scope: u16,
+    pub duration: i64,
+}
+
+#[event]
+pub struct PatientAdmittedEvent {
+    pub emergency_policy: Pubkey,
+    pub consent_grant: Pubkey,
+    pub organization: Pubkey,
+    pub oracle: Pubkey,
+    pub expires_at: i64,
+}
+
+#[event]
+pub struct PatientDischargedEvent {
+    pub emergency_policy: Pubkey,
+    pub organization: Pubkey,
+    pub oracle: Pubkey,
+}
+
+#[derive(Accounts)]
+#[instruction(organization: Pubkey)]
+pub struct SetEmergencyConsentPolicy<'info> {
+    #[account(mut)]
+    pub patient: Signer<'info>,
+    #[account(
+        seeds = [b"patient_data", patient.key().as_ref()],
+        bump,
+    )]
+    pub patient_data: Account<'info, PatientData>,
+    #[account(
+        init_if_needed,
+        payer = patient,
+        space = 8 + EmergencyConsentPolicy::INIT_SPACE,
+        seeds = [EMERGENCY_POLICY_SEED, patient_data.key().as_ref(), organization.as_ref()],
+        bump,
+    )]
+    pub emergency_policy: Account<'info, EmergencyConsentPolicy>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ReportAdmission<'info> {
+    #[account(mut)]
+    pub oracle: Signer<'info>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+        constraint = program_config.admission_oracles.contains(&oracle.key())
+            @ ErrorCode::Unauthorized,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    #[account(
+        mut,
+        seeds = [
+            EMERGENCY_POLICY_SEED,
+            emergency_policy.patient_data.as_ref(),
+            emergency_policy.organization.as_ref(),
+        ],
+        bump = emergency_policy.bump,
+    )]
+    pub emergency_policy: Account<'info, EmergencyConsentPolicy>,
+    #[account(
+        init_if_needed,
+        payer = oracle,
+        space = 8 + ConsentGrant::INIT_SPACE,
+        seeds = [
+            CONSENT_GRANT_SEED,
+            emergency_policy.patient_data.as_ref(),
+            emergency_policy.organization.as_ref(),
+        ],
+        bump,
+    )]
+    pub consent_grant: Account<'info, ConsentGrant>,
+    #[account(
+        mut,
+        seeds = [INBOX_SEED, emergency_policy.patient_data.as_ref()],
+        bump,
+    )]
+    /// CHECK: inbox, empty unless the patient opened one; written in the handler.
+    pub inbox: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+impl EmergencyConsentPolicy {
+    /// Activates the policy, widening the organization's consent to cover it.
+    ///
+    /// Scope bits and expiry of a consent the patient already granted are kept if they
+    /// reach further than the emergency ones.
+    pub fn activate(
+        &mut self,
+        consent_grant: &mut ConsentGrant,
+        clock: &ExpiryClock,
+    ) -> Result<()> {
+        let now = clock.unix_timestamp;
+        let expires_at = now
+            .checked_add(self.duration)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        let active = consent_grant.is_active(clock);
+        consent_grant.patient_data = self.patient_data;
+        consent_grant.patient = self.patient;
+        consent_grant.receiver = self.organization;
+        consent_grant.scope = if active {
+            consent_grant.scope | self.scope
+        } else {
+            self.scope
+        };
+        consent_grant.granted_at = now;
+        if !active || expires_at > consent_grant.expires_at {
+            consent_grant.expires_at = expires_at;
+            consent_grant.expires_at_slot = clock.slot_at(expires_at);
+        }
+        consent_grant.revoked = false;
+
+        self.admitted = true;
+        self.admitted_at = now;
+        Ok(())
+    }
+
+    /// Clears the admission, ending the consent if it was last granted by the admission.
+    pub fn deactivate(&mut self, consent_grant: &mut ConsentGrant, clock: &ExpiryClock) {
+        if consent_grant.granted_at == self.admitted_at
+            && consent_grant.expires_at > clock.unix_timestamp
+        {
+            consent_grant.expires_at = clock.unix_timestamp;
+            consent_grant.expires_at_slot = clock.slot;
+        }
+        self.admitted = false;
+    }
+}
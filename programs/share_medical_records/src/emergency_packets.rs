@@ -0,0 +1,265 @@
+use anchor_lang::prelude::*;
+use arcium_anchor::prelude::*;
+
+use crate::ErrorCode;
+use crate::*;
+
+pub const EMS_AUTHORITY_SEED: &[u8] = b"ems_authority";
+pub const EMERGENCY_PACKET_SEED: &[u8] = b"emergency_packet";
+/// Ciphertexts an emergency packet holds: age, blood type and the five allergies, as
+/// output by the `emergency_packet` circuit.
+pub const EMERGENCY_PACKET_BLOCKS: usize = 7;
+
+/// A regional EMS authority responders decrypt emergency packets with, registered by
+/// the admin.
+#[account]
+#[derive(InitSpace)]
+pub struct EmsAuthority {
+    /// Region the authority dispatches for, part of its address
+    pub region: u32,
+    /// Wallet operating the authority
+    pub authority: Pubkey,
+    /// x25519 key packets for the region are encrypted for
+    pub enc_pubkey: [u8; 32],
+    /// Unix timestamp the key was last set at; packets generated before it are stale
+    pub updated_at: i64,
+    pub bump: u8,
+}
+
+/// Fields of a record responders need at the roadside, re-encrypted ahead of time for
+/// the patient's regional EMS authority.
+///
+/// Generated by the patient through MPC whenever they choose, so responders read it from
+/// this one address instead of queueing a computation during an emergency. It is a copy:
+/// compare `record_version` with the record's to tell whether it has been updated since.
+#[account]
+#[derive(InitSpace)]
+pub struct EmergencyPacket {
+    pub patient_data: Pubkey,
+    /// Region of the EMS authority the packet is encrypted for
+    pub region: u32,
+    /// x25519 key the output is encrypted for
+    pub receiver_enc_key: [u8; 32],
+    pub nonce: [u8; 16],
+    /// Age, blood type, then allergies, in record order
+    pub ciphertexts: [[u8; 32]; EMERGENCY_PACKET_BLOCKS],
+    /// `version` of the record the packet was generated from
+    pub record_version: u32,
+    /// Computation that last delivered; zero until the first delivery
+    pub computation_offset: u64,
+    /// Slot the packet was last delivered at
+    pub generated_slot: u64,
+    /// Region and record version of the latest generation queued, moved into `region`
+    /// and `record_version` once it delivers
+    pub pending_region: u32,
+    pub pending_record_version: u32,
+    pub bump: u8,
+}
+
+#[event]
+pub struct EmsAuthoritySetEvent {
+    pub ems_authority: Pubkey,
+    pub region: u32,
+    pub authority: Pubkey,
+    pub enc_pubkey: [u8; 32],
+}
+
+#[event]
+pub struct EmergencyPacketStoredEvent {
+    pub emergency_packet: Pubkey,
+    pub patient_data: Pubkey,
+    pub region: u32,
+    pub record_version: u32,
+    pub slot: u64,
+}
+
+/// Generating a packet aborted; any earlier packet is left in place.
+#[event]
+pub struct EmergencyPacketFailedEvent {
+    pub emergency_packet: Pubkey,
+    pub patient_data: Pubkey,
+    pub computation_offset: u64,
+}
+
+#[derive(Accounts)]
+#[instruction(region: u32)]
+pub struct SetEmsAuthority<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+        has_one = admin @ ErrorCode::Unauthorized,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = 8 + EmsAuthority::INIT_SPACE,
+        seeds = [EMS_AUTHORITY_SEED, &region.to_le_bytes()],
+        bump,
+    )]
+    pub ems_authority: Account<'info, EmsAuthority>,
+    pub system_program: Program<'info, System>,
+}
+
+#[queue_computation_accounts("emergency_packet", patient)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct GenerateEmergencyPacket<'info> {
+    #[account(mut)]
+    pub patient: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = patient,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account, checked by the arcium program.
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool, checked by the arcium program.
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account, checked by the arcium program.
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_EMERGENCY_PACKET)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(
+        mut,
+        address = derive_cluster_pda!(mxe_account)
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Box<Account<'info, ProgramConfig>>,
+    #[account(
+        seeds = [b"patient_data", patient.key().as_ref()],
+        bump,
+    )]
+    pub patient_data: Box<Account<'info, PatientData>>,
+    #[account(
+        seeds = [EMS_AUTHORITY_SEED, &ems_authority.region.to_le_bytes()],
+        bump = ems_authority.bump,
+    )]
+    pub ems_authority: Box<Account<'info, EmsAuthority>>,
+    #[account(
+        init_if_needed,
+        payer = patient,
+        space = 8 + EmergencyPacket::INIT_SPACE,
+        seeds = [EMERGENCY_PACKET_SEED, patient_data.key().as_ref()],
+        bump,
+    )]
+    pub emergency_packet: Box<Account<'info, EmergencyPacket>>,
+    #[account(
+        init_if_needed,
+        payer = patient,
+        space = 8 + UsedOffsets::INIT_SPACE,
+        seeds = [USED_OFFSETS_SEED, patient.key().as_ref()],
+        bump,
+    )]
+    pub used_offsets: Box<Account<'info, UsedOffsets>>,
+    #[account(
+        init,
+        payer = patient,
+        space = 8 + CallbackGuard::INIT_SPACE,
+        seeds = [CALLBACK_GUARD_SEED, &computation_offset.to_le_bytes()],
+        bump,
+    )]
+    pub callback_guard: Box<Account<'info, CallbackGuard>>,
+}
+
+#[callback_accounts("emergency_packet")]
+#[derive(Accounts)]
+pub struct EmergencyPacketCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_EMERGENCY_PACKET)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    #[account(
+        mut,
+        seeds = [CALLBACK_GUARD_SEED, &callback_guard.computation_offset.to_le_bytes()],
+        bump = callback_guard.bump,
+    )]
+    pub callback_guard: Box<Account<'info, CallbackGuard>>,
+    #[account(
+        mut,
+        seeds = [EMERGENCY_PACKET_SEED, emergency_packet.patient_data.as_ref()],
+        bump = emergency_packet.bump,
+    )]
+    pub emergency_packet: Box<Account<'info, EmergencyPacket>>,
+    #[account(
+        mut,
+        seeds = [USED_OFFSETS_SEED, used_offsets.payer.as_ref()],
+        bump = used_offsets.bump,
+    )]
+    pub used_offsets: Box<Account<'info, UsedOffsets>>,
+}
+
+#[init_computation_definition_accounts("emergency_packet", payer)]
+#[derive(Accounts)]
+pub struct InitEmergencyPacketCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut)]
+    /// CHECK: comp_def_account, checked by arcium program.
+    /// Can't check it here as it's not initialized yet.
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+        constraint = program_config.admin == payer.key() @ ErrorCode::Unauthorized,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
@@ -0,0 +1,408 @@
+use anchor_lang::prelude::*;
+
+/// Errors returned by the program.
+///
+/// Codes are assigned in declaration order, so variants are only ever renamed in place
+/// or appended. Failures a client can act on are split by cause: consent that expired,
+/// was revoked or covers another purpose; computations that aborted, timed out or
+/// returned short output; and records that are frozen, tombstoned or described by a
+/// mismatched schema.
+#[error_code]
+pub enum ErrorCode {
+    #[msg("Computation was aborted by the cluster; queue it again with a new computation offset")]
+    ComputationAborted,
+    #[msg("Computation output is shorter than the callback expects; check the comp def's circuit version")]
+    ComputationOutputLength,
+    #[msg("Cluster not set")]
+    ClusterNotSet,
+    #[msg("Signer is not authorized for this action")]
+    Unauthorized,
+    #[msg("Circuit version must increase and point to a new comp def")]
+    InvalidCircuitVersion,
+    #[msg("This circuit version has been retired")]
+    CircuitVersionRetired,
+    #[msg("Circuit source URL is too long")]
+    CircuitSourceTooLong,
+    #[msg("Circuit manifest is full")]
+    CircuitManifestFull,
+    #[msg("Circuit is not registered in the manifest")]
+    CircuitNotInManifest,
+    #[msg("Circuit comp def has already been initialized")]
+    CircuitAlreadyInitialized,
+    #[msg("Circuit is active or has pending computations")]
+    CircuitInUse,
+    #[msg("Arithmetic overflow")]
+    ArithmeticOverflow,
+    #[msg("This operation is currently paused")]
+    Paused,
+    #[msg("Cluster is not approved for this program")]
+    ClusterNotApproved,
+    #[msg("Too many approved clusters")]
+    TooManyClusters,
+    #[msg("This record charges an access fee but fee accounts were not provided")]
+    AccessFeeAccountsMissing,
+    #[msg("Fee account does not match the record's access fee")]
+    InvalidFeeAccount,
+    #[msg("Expected an ed25519 approval signature instruction before this one")]
+    MissingApprovalSignature,
+    #[msg("Approval signature does not match the expected signer or message")]
+    InvalidApprovalSignature,
+    #[msg("Patient approval has expired")]
+    ApprovalExpired,
+    #[msg("Escrow amount must be greater than zero")]
+    InvalidEscrowAmount,
+    #[msg("Escrow is not open")]
+    EscrowNotOpen,
+    #[msg("Escrow has no cohort query in flight")]
+    EscrowNotPending,
+    #[msg("Cohort must be passed as COHORT_SIZE groups of record, opt-in and payout")]
+    InvalidCohort,
+    #[msg("Subscription tier, mint or account is invalid")]
+    InvalidSubscription,
+    #[msg("Timeout must be greater than zero")]
+    InvalidTimeout,
+    #[msg("Computation has not timed out yet")]
+    ComputationNotTimedOut,
+    #[msg("No refund balance to claim")]
+    NothingToRefund,
+    #[msg("Refund vault does not hold enough lamports")]
+    RefundVaultInsufficient,
+    #[msg("Fee split exceeds 10000 basis points")]
+    InvalidFeeSplit,
+    #[msg("Too many registrars")]
+    TooManyRegistrars,
+    #[msg("Provider is already registered")]
+    ProviderAlreadyRegistered,
+    #[msg("Provider is not registered")]
+    ProviderNotRegistered,
+    #[msg("Invalid regulatory disclosure policy")]
+    InvalidDisclosurePolicy,
+    #[msg("Disclosure request is not in the expected state")]
+    InvalidDisclosureStatus,
+    #[msg("Committee member already approved this disclosure")]
+    DisclosureAlreadyApproved,
+    #[msg("Disclosure request lacks committee approvals")]
+    DisclosureNotApproved,
+    #[msg("Regulator disclosure rate limit reached")]
+    DisclosureRateLimited,
+    #[msg("Invalid retention policy")]
+    InvalidRetentionPolicy,
+    #[msg("Record is still within its minimum retention period")]
+    RetentionPeriodActive,
+    #[msg("Record has been tombstoned and holds no data; store a new record instead")]
+    RecordTombstoned,
+    #[msg("Record was closed and its address cannot hold a new record")]
+    RecordClosed,
+    #[msg("Resource type is too long")]
+    ResourceTypeTooLong,
+    #[msg("Anchor version must increase")]
+    StaleAnchorVersion,
+    #[msg("Encrypted pointer is too long")]
+    PointerTooLong,
+    #[msg("Document vault is full")]
+    DocumentVaultFull,
+    #[msg("Document CID is too long")]
+    DocumentCidTooLong,
+    #[msg("No document at this index")]
+    DocumentNotFound,
+    #[msg("Account is not the program config")]
+    InvalidProgramConfig,
+    #[msg("DICOM modality or pointer is too long")]
+    DicomFieldTooLong,
+    #[msg("Compressed record tree is full")]
+    CompressedTreeFull,
+    #[msg("Merkle proof does not match a recent root")]
+    InvalidMerkleProof,
+    #[msg("DID is too long")]
+    DidTooLong,
+    #[msg("Key does not match the DID verification method")]
+    DidVerificationMethodMismatch,
+    #[msg("DID challenge has expired")]
+    DidChallengeExpired,
+    #[msg("Attestation validity must be positive and at most one year")]
+    InvalidAttestationValidity,
+    #[msg("Consent scope is empty or has unknown bits")]
+    InvalidConsentScope,
+    #[msg("Consent expiry must be in the future")]
+    InvalidConsentExpiry,
+    #[msg("Consent has expired; ask the patient to grant it again")]
+    ConsentExpired,
+    #[msg("Invalid Wormhole account")]
+    InvalidWormholeAccount,
+    #[msg("Invalid address lookup table")]
+    InvalidLookupTable,
+    #[msg("Too many admission oracles")]
+    TooManyAdmissionOracles,
+    #[msg("Emergency consent duration must be positive and at most 30 days")]
+    InvalidEmergencyDuration,
+    #[msg("Patient is already admitted")]
+    PatientAlreadyAdmitted,
+    #[msg("Patient is not admitted")]
+    PatientNotAdmitted,
+    #[msg("SNS name is too long")]
+    SnsNameTooLong,
+    #[msg("Account is not the SNS name account of the name")]
+    InvalidSnsNameAccount,
+    #[msg("SNS name is not owned by the provider")]
+    SnsNameNotOwned,
+    #[msg("SNS name binding is still current")]
+    SnsNameStillOwned,
+    #[msg("Schema descriptor is invalid")]
+    InvalidSchemaDescriptor,
+    #[msg("Schema version must increase")]
+    StaleSchemaVersion,
+    #[msg("Computation offset was already used by this payer")]
+    OffsetAlreadyUsed,
+    #[msg("Payer neither controls the record nor holds active consent to it")]
+    UnauthorizedShare,
+    #[msg("Sender public key does not match the key the record was encrypted under")]
+    SenderKeyMismatch,
+    #[msg("Nonce was already used with this record")]
+    NonceReused,
+    #[msg("Encryption key is not a canonical x25519 key outside the small-order subgroup")]
+    InvalidEncryptionKey,
+    #[msg("Passed account is not owned by this program")]
+    RemainingAccountNotOwned,
+    #[msg("Passed account is not of the expected type")]
+    RemainingAccountWrongType,
+    #[msg("Record appears more than once in the cohort")]
+    DuplicateCohortMember,
+    #[msg("Research opt-in does not belong to the cohort record")]
+    CohortOptInMismatch,
+    #[msg("Cohort member has not opted in to research")]
+    CohortMemberNotOptedIn,
+    #[msg("Payout account does not belong to the patient or uses another mint")]
+    CohortPayoutMismatch,
+    #[msg("Callback was not invoked by the Arcium program")]
+    CallbackNotFromArcium,
+    #[msg("Callback was delivered for another computation definition")]
+    CallbackCompDefMismatch,
+    #[msg("Callback was delivered for another computation")]
+    CallbackComputationMismatch,
+    // Consent
+    #[msg("Consent was revoked by the patient; ask them to grant it again")]
+    ConsentRevoked,
+    #[msg("Consent does not cover this purpose; ask the patient to grant the missing scope")]
+    ConsentPurposeNotCovered,
+    // Computation
+    #[msg("Computation timed out; expire it to reclaim its fee and queue it again")]
+    ComputationTimedOut,
+    // Record data
+    #[msg("Record is frozen while computations on it are in flight; retry once they complete")]
+    RecordFrozen,
+    #[msg("Schema layout does not match the record type's on-chain account")]
+    SchemaMismatch,
+    #[msg("Clock skew tolerance must not be negative")]
+    InvalidClockSkewTolerance,
+    #[msg("Account is not of the record type, or too short for the ciphertexts the circuit reads")]
+    CiphertextRegionMismatch,
+    #[msg("Receiver key is not the one attested for the provider; have a registrar attest it")]
+    ReceiverKeyNotAttested,
+    // Computation
+    #[msg("Payer has too many computations in flight; wait for a callback or the timeout")]
+    TooManyInFlightComputations,
+    #[msg("In-flight cap must be between one and the per-payer maximum")]
+    InvalidInFlightCap,
+    // Record data
+    #[msg(
+        "A ciphertext is all zeros; encrypt every collected field, unused allergy slots included"
+    )]
+    ZeroCiphertext,
+    #[msg(
+        "Two allergy ciphertexts are identical; encrypt the record in one pass with a fresh nonce"
+    )]
+    DuplicateAllergyCiphertext,
+    // Computation
+    #[msg("Callback of this computation has already run")]
+    CallbackAlreadyConsumed,
+    #[msg("Staged share belongs to another record or receiver, or was passed to a direct share")]
+    StagedShareMismatch,
+    // Consent
+    #[msg("Record changed since the staged share's approval was verified; sign a new one")]
+    StagedApprovalStale,
+    // Record data
+    #[msg("Ciphertexts do not match the record's commitment")]
+    CommitmentMismatch,
+    // Consent
+    #[msg("Consent batch accounts or approval signatures do not line up with its items")]
+    ConsentBatchMismatch,
+    // Record data
+    #[msg("Field mask must select at least one field, and only bits of `PatientField`")]
+    InvalidPatientFieldMask,
+    #[msg("Field is not collected by this deployment; leave its blocks zeroed and unselected")]
+    FieldNotCollected,
+    // Schema
+    #[msg("Code set is invalid; codes and values must be non-empty, fit and be unique")]
+    InvalidCodeSet,
+    #[msg("Code set version must increase")]
+    StaleCodeSetVersion,
+    // Computation
+    #[msg("Shared record must be passed exactly when compact events are on")]
+    SharedRecordMismatch,
+    // Record data
+    #[msg("FHIR anchor does not hold the resource type its place in the bundle requires")]
+    BundleResourceTypeMismatch,
+    // Prior authorization
+    #[msg("Account is not an active provider registered as an insurer")]
+    NotAnInsurer,
+    #[msg("Prior authorization request has already been validated or is being validated")]
+    PriorAuthNotPending,
+    #[msg("Prior authorization request is not being validated")]
+    PriorAuthNotValidating,
+    // Prescriptions
+    #[msg("Prescription is not active; it is being issued, held by a pharmacy or exhausted")]
+    PrescriptionNotActive,
+    #[msg("Pharmacy holds no verified fill of this prescription to dispense")]
+    DispenseNotAuthorized,
+    #[msg("Prescription has no fill being verified")]
+    PrescriptionNotVerifying,
+    // Clearance
+    #[msg("Clearance template excludes allergies outside the record's allergy slots")]
+    InvalidClearanceTemplate,
+    // Telehealth sessions
+    #[msg("Session length must be positive and at most MAX_SESSION_SLOTS slots")]
+    InvalidSessionLength,
+    #[msg("Provider already holds an active consent; a session cannot replace it")]
+    SessionConsentActive,
+    #[msg("Session is already closed")]
+    SessionClosed,
+    // Adverse events
+    #[msg("Reporter is neither a patient with a record nor an active provider")]
+    ReporterNotEligible,
+    #[msg("Account is not an active provider registered as a pharma sponsor")]
+    NotAPharmaSponsor,
+    #[msg("Surveillance tally is being initialized or is counting another report")]
+    TallyNotReady,
+    #[msg("Adverse event report has already been counted or is being counted")]
+    AdverseEventAlreadyTallied,
+    #[msg("Too few reports have been counted since the tally's last reveal")]
+    RevealTooSoon,
+    #[msg("Reporter has counted the most reports allowed into this tally")]
+    ReporterTallyLimitReached,
+    #[msg("Surveillance tally has no computation in flight")]
+    TallyNotBusy,
+    #[msg("Report or reporter counter is not the one the tally is counting")]
+    InvalidTallyReport,
+    // Public health
+    #[msg("Case has not been counted and sealed, so there is nothing to reveal")]
+    CaseNotCounted,
+    // Caregiver grants
+    #[msg("Caregiver grant must select fields within CAREGIVER_FIELDS")]
+    InvalidCaregiverFields,
+    #[msg("Caregiver update interval is shorter than MIN_CAREGIVER_INTERVAL_SLOTS")]
+    InvalidCaregiverInterval,
+    #[msg("Caregiver grant has been revoked or has expired")]
+    CaregiverGrantInactive,
+    #[msg("Caregiver update is not due yet or is still in flight")]
+    CaregiverUpdateNotDue,
+    #[msg("Record was updated since the caregiver grant; the patient must grant it again")]
+    CaregiverGrantStale,
+    // Ingest authorities
+    #[msg("Ingest quota must allow at least one record per epoch")]
+    InvalidIngestQuota,
+    #[msg("Ingest authority has been revoked")]
+    IngestAuthorityRevoked,
+    #[msg("Ingest authority has used its quota for this epoch")]
+    IngestQuotaExceeded,
+    // Audit attestations
+    #[msg("Too many auditors")]
+    TooManyAuditors,
+    #[msg("Signer is not a configured auditor")]
+    NotAnAuditor,
+    #[msg("Audit window must start at an earlier checkpoint of the same record")]
+    InvalidAuditWindow,
+    // Inbox
+    #[msg("Consent is not active or lapses later than CONSENT_EXPIRY_NOTICE from now")]
+    ConsentNotExpiring,
+    #[msg("Inbox already holds this notification")]
+    AlreadyNotified,
+    // Provider key rotation
+    #[msg("Provider has no attested key to rotate, or the new key is the current one")]
+    InvalidKeyRotation,
+    #[msg("Provider already has an attested key; rotate it instead")]
+    EncKeyAlreadyAttested,
+    #[msg("Payer's key was rotated since the share was approved or staged")]
+    ProviderKeyRotated,
+    // Donor registry
+    #[msg("Donor search needs DONOR_SEARCH_SIZE donors, antigens within BLOOD_ANTIGENS_ALL and products within DONOR_PRODUCTS_ALL")]
+    InvalidDonorSearch,
+    #[msg("Donor profile passed more than once")]
+    DuplicateDonor,
+    #[msg("Donor has withdrawn from searches")]
+    DonorNotConsenting,
+    #[msg("Caller is not an active provider registered as a blood bank")]
+    NotABloodBank,
+    // Transplant waitlist
+    #[msg("Waitlist run needs WAITLIST_BATCH_SIZE entries")]
+    InvalidWaitlistBatch,
+    #[msg("Waitlist entry passed more than once")]
+    DuplicateWaitlistEntry,
+    #[msg("Candidate has left the waitlist")]
+    CandidateNotListed,
+    #[msg("Caller is not an active provider registered as a transplant coordinator")]
+    NotATransplantCoordinator,
+    // Trial escrows
+    #[msg("Trial needs 1 to MAX_TRIAL_MILESTONES milestones, a payout, an enrollment and a future end")]
+    InvalidTrialTerms,
+    #[msg("Trial has ended or been closed")]
+    TrialClosed,
+    #[msg("Trial has no enrollment slots left")]
+    TrialFull,
+    #[msg("Caller is not the trial's site")]
+    NotTrialSite,
+    #[msg("Visit milestones must be confirmed in order, up to the trial's milestones")]
+    MilestoneOutOfOrder,
+    #[msg("No confirmed milestones left to pay")]
+    NothingToClaim,
+    #[msg("Trial has not ended yet")]
+    TrialNotEnded,
+    // Share codes
+    #[msg("Share code must expire in the future and within MAX_SHARE_CODE_SECONDS")]
+    InvalidShareCodeExpiry,
+    #[msg("Share code has expired")]
+    ShareCodeExpired,
+    #[msg("Share code has already been redeemed")]
+    ShareCodeRedeemed,
+    #[msg("Record has been updated since the share code was created")]
+    ShareCodeStale,
+    // Prior auth escalations
+    #[msg("Prior authorization request is not awaiting review")]
+    PriorAuthNotInReview,
+    #[msg("Escalation has already been answered")]
+    EscalationAnswered,
+    #[msg("Escalation is not approved, or its fields are disclosed or being disclosed")]
+    EscalationNotApproved,
+    #[msg("Escalation approval has lapsed or the record has been updated since")]
+    EscalationApprovalLapsed,
+    // Medication administration
+    #[msg("Staff member's authorization to log medication has been revoked")]
+    MedAdministratorRevoked,
+    // Disaster mode
+    #[msg("Disaster committee exceeds MAX_DISASTER_COMMITTEE, repeats a member or threshold is above its size")]
+    InvalidDisasterCommittee,
+    #[msg("Not enough disaster committee members co-signed the declaration")]
+    DisasterThresholdNotMet,
+    #[msg("Disaster window must start within MAX_DISASTER_MODE_NOTICE_SECONDS and end after its start and now, within MAX_DISASTER_MODE_SECONDS")]
+    InvalidDisasterWindow,
+    #[msg("No disaster is declared for the packet's region right now")]
+    DisasterModeInactive,
+    #[msg(
+        "A disaster declaration for the region has not ended yet; end it before declaring again"
+    )]
+    DisasterModeStillDeclared,
+    #[msg("Emergency packet has not been generated yet")]
+    EmergencyPacketMissing,
+    // Kinship
+    #[msg("Kin links join two distinct records, passed with the lower address first")]
+    InvalidKinLink,
+    #[msg("Record is already linked to MAX_KIN_LINKS relatives")]
+    KinLinkLimitReached,
+    #[msg("Every kin link of the record must be passed, once each, to share genomic data")]
+    KinLinksIncomplete,
+    #[msg("Kin link does not include this record")]
+    KinLinkMismatch,
+    #[msg("Both relatives on every kin link must consent before genomic data is shared")]
+    KinConsentMissing,
+    #[msg("The relative's record is still open; both patients must sign to unlink")]
+    KinRecordNotClosed,
+}
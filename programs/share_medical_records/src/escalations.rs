@@ -0,0 +1,338 @@
+use anchor_lang::prelude::*;
+use arcium_anchor::prelude::*;
+
+use crate::ErrorCode;
+use crate::*;
+
+pub const PRIOR_AUTH_ESCALATION_SEED: &[u8] = b"prior_auth_escalation";
+/// How long a patient's approval of an escalation lets the insurer queue the disclosure.
+pub const ESCALATION_APPROVAL_SECONDS: i64 = 24 * 60 * 60;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub enum EscalationStatus {
+    /// Waiting for the patient to approve or decline it
+    Requested,
+    Approved,
+    Declined,
+    /// Disclosure queued, waiting for the callback
+    Disclosing,
+    Disclosed,
+}
+
+/// An insurer's request to see the fields it disputes in a prior-authorization review,
+/// instead of the whole record.
+///
+/// Only a request that validation left in `NeedsReview` can be escalated, once. Nothing is
+/// disclosed until the patient approves this escalation, and the approval only holds for
+/// `ESCALATION_APPROVAL_SECONDS` and the record version it was given for.
+#[account]
+#[derive(InitSpace)]
+pub struct PriorAuthEscalation {
+    pub prior_auth: Pubkey,
+    pub insurer: Pubkey,
+    pub patient_data: Pubkey,
+    /// Bitmask of `PatientField` bits disputed
+    pub fields: u16,
+    /// Insurer's attested x25519 key when it escalated, the one the patient approves
+    pub insurer_enc_key: [u8; 32],
+    pub status: EscalationStatus,
+    pub requested_at: i64,
+    /// Unix timestamp the patient answered at, zero until then
+    pub answered_at: i64,
+    /// Nonce the record was encrypted with, as of `record_version`
+    pub record_nonce: u128,
+    /// `version` of the record the approval was given for
+    pub record_version: u32,
+    /// Offset of the latest disclosure, zero before the first
+    pub computation_offset: u64,
+    pub bump: u8,
+}
+
+/// An insurer escalated a review; patients subscribe by `patient_data`.
+#[event]
+pub struct PriorAuthEscalatedEvent {
+    pub escalation: Pubkey,
+    pub prior_auth: Pubkey,
+    pub patient_data: Pubkey,
+    pub insurer: Pubkey,
+    pub fields: u16,
+}
+
+#[event]
+pub struct PriorAuthEscalationAnsweredEvent {
+    pub escalation: Pubkey,
+    pub patient_data: Pubkey,
+    pub insurer: Pubkey,
+    pub fields: u16,
+    pub approved: bool,
+}
+
+#[event]
+pub struct EscalatedFieldsQueuedEvent {
+    pub escalation: Pubkey,
+    pub patient_data: Pubkey,
+    pub insurer: Pubkey,
+    pub fields: u16,
+    pub computation_offset: u64,
+}
+
+/// The disputed fields were disclosed; insurers subscribe by `insurer`.
+///
+/// Fields outside the escalation's mask come back as encryptions of zero.
+#[event]
+pub struct EscalatedFieldsDisclosedEvent {
+    pub escalation: Pubkey,
+    pub prior_auth: Pubkey,
+    pub insurer: Pubkey,
+    /// x25519 key the output is encrypted for
+    pub receiver_enc_key: [u8; 32],
+    pub slot: u64,
+    pub nonce: [u8; 16],
+    pub patient_id: [u8; 32],
+    pub age: [u8; 32],
+    pub gender: [u8; 32],
+    pub blood_type: [u8; 32],
+    pub weight: [u8; 32],
+    pub height: [u8; 32],
+    pub allergies: [[u8; 32]; 5],
+}
+
+#[derive(Accounts)]
+pub struct EscalatePriorAuth<'info> {
+    #[account(mut)]
+    pub insurer: Signer<'info>,
+    #[account(
+        seeds = [PROVIDER_SEED, insurer.key().as_ref()],
+        bump = insurer_profile.bump,
+        constraint = insurer_profile.active
+            && insurer_profile.role == ProviderRole::Insurer
+            @ ErrorCode::NotAnInsurer,
+    )]
+    pub insurer_profile: Box<Account<'info, ProviderProfile>>,
+    #[account(
+        seeds = [
+            PRIOR_AUTH_SEED,
+            prior_auth.provider.as_ref(),
+            &prior_auth.request_id.to_le_bytes(),
+        ],
+        bump = prior_auth.bump,
+        has_one = insurer @ ErrorCode::Unauthorized,
+    )]
+    pub prior_auth: Box<Account<'info, PriorAuthRequest>>,
+    #[account(
+        init,
+        payer = insurer,
+        space = 8 + PriorAuthEscalation::INIT_SPACE,
+        seeds = [PRIOR_AUTH_ESCALATION_SEED, prior_auth.key().as_ref()],
+        bump,
+    )]
+    pub escalation: Box<Account<'info, PriorAuthEscalation>>,
+    #[account(
+        mut,
+        seeds = [INBOX_SEED, prior_auth.patient_data.as_ref()],
+        bump,
+    )]
+    /// CHECK: inbox, empty unless the patient opened one; written in the handler.
+    pub inbox: UncheckedAccount<'info>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AnswerPriorAuthEscalation<'info> {
+    #[account(mut)]
+    pub patient: Signer<'info>,
+    #[account(
+        seeds = [b"patient_data", patient.key().as_ref()],
+        bump,
+    )]
+    pub patient_data: Account<'info, PatientData>,
+    #[account(
+        mut,
+        seeds = [PRIOR_AUTH_ESCALATION_SEED, escalation.prior_auth.as_ref()],
+        bump = escalation.bump,
+        has_one = patient_data @ ErrorCode::Unauthorized,
+    )]
+    pub escalation: Box<Account<'info, PriorAuthEscalation>>,
+    #[account(
+        init_if_needed,
+        payer = patient,
+        space = 8 + PatientRegistry::INIT_SPACE,
+        seeds = [PATIENT_REGISTRY_SEED, patient_data.key().as_ref()],
+        bump,
+    )]
+    pub patient_registry: Box<Account<'info, PatientRegistry>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[queue_computation_accounts("disclose_escalated_fields", insurer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct DiscloseEscalatedFields<'info> {
+    #[account(mut)]
+    pub insurer: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = insurer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account, checked by the arcium program.
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool, checked by the arcium program.
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account, checked by the arcium program.
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_DISCLOSE_ESCALATED_FIELDS)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(
+        mut,
+        address = derive_cluster_pda!(mxe_account)
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Box<Account<'info, ProgramConfig>>,
+    #[account(
+        seeds = [PROVIDER_SEED, insurer.key().as_ref()],
+        bump = insurer_profile.bump,
+        constraint = insurer_profile.active
+            && insurer_profile.role == ProviderRole::Insurer
+            @ ErrorCode::NotAnInsurer,
+    )]
+    pub insurer_profile: Box<Account<'info, ProviderProfile>>,
+    #[account(
+        mut,
+        seeds = [PRIOR_AUTH_ESCALATION_SEED, escalation.prior_auth.as_ref()],
+        bump = escalation.bump,
+        has_one = insurer @ ErrorCode::Unauthorized,
+    )]
+    pub escalation: Box<Account<'info, PriorAuthEscalation>>,
+    #[account(address = escalation.patient_data)]
+    pub patient_data: Box<Account<'info, PatientData>>,
+    #[account(
+        mut,
+        seeds = [PATIENT_REGISTRY_SEED, escalation.patient_data.as_ref()],
+        bump = patient_registry.bump,
+    )]
+    pub patient_registry: Box<Account<'info, PatientRegistry>>,
+    #[account(
+        init_if_needed,
+        payer = insurer,
+        space = 8 + UsedOffsets::INIT_SPACE,
+        seeds = [USED_OFFSETS_SEED, insurer.key().as_ref()],
+        bump,
+    )]
+    pub used_offsets: Box<Account<'info, UsedOffsets>>,
+    #[account(
+        init,
+        payer = insurer,
+        space = 8 + CallbackGuard::INIT_SPACE,
+        seeds = [CALLBACK_GUARD_SEED, &computation_offset.to_le_bytes()],
+        bump,
+    )]
+    pub callback_guard: Box<Account<'info, CallbackGuard>>,
+}
+
+#[callback_accounts("disclose_escalated_fields")]
+#[derive(Accounts)]
+pub struct DiscloseEscalatedFieldsCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_DISCLOSE_ESCALATED_FIELDS)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    #[account(
+        mut,
+        seeds = [CALLBACK_GUARD_SEED, &callback_guard.computation_offset.to_le_bytes()],
+        bump = callback_guard.bump,
+    )]
+    pub callback_guard: Box<Account<'info, CallbackGuard>>,
+    #[account(
+        mut,
+        seeds = [PRIOR_AUTH_ESCALATION_SEED, escalation.prior_auth.as_ref()],
+        bump = escalation.bump,
+        constraint = escalation.computation_offset == callback_guard.computation_offset
+            @ ErrorCode::CallbackComputationMismatch,
+    )]
+    pub escalation: Box<Account<'info, PriorAuthEscalation>>,
+    #[account(
+        mut,
+        seeds = [USED_OFFSETS_SEED, used_offsets.payer.as_ref()],
+        bump = used_offsets.bump,
+    )]
+    pub used_offsets: Box<Account<'info, UsedOffsets>>,
+}
+
+#[init_computation_definition_accounts("disclose_escalated_fields", payer)]
+#[derive(Accounts)]
+pub struct InitDiscloseEscalatedFieldsCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut)]
+    /// CHECK: comp_def_account, checked by arcium program.
+    /// Can't check it here as it's not initialized yet.
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+        constraint = program_config.admin == payer.key() @ ErrorCode::Unauthorized,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
@@ -0,0 +1,61 @@
+use anchor_lang::prelude::*;
+
+use crate::*;
+
+/// Nominal slot time the slot side of an expiry is estimated with.
+pub const MS_PER_SLOT: u64 = 400;
+/// Seconds the slot estimate may run past an expiry before it overrides the timestamp,
+/// unless configured.
+pub const DEFAULT_CLOCK_SKEW_TOLERANCE: i64 = 2 * 60;
+
+/// The current time on both cluster clocks, for evaluating expiries.
+///
+/// The unix timestamp is a stake-weighted estimate the validators vote on and can lag
+/// wall time, which would keep consents, subscriptions and attestations alive past
+/// their expiry. Each expiry therefore also records the slot it should fall in at the
+/// nominal slot rate, and has passed once either clock reaches it. Slot times vary, so
+/// the slot side gets `tolerance_slots` of slack before it overrides the timestamp.
+#[derive(Clone, Copy)]
+pub struct ExpiryClock {
+    pub unix_timestamp: i64,
+    pub slot: u64,
+    pub tolerance_slots: u64,
+}
+
+impl ExpiryClock {
+    /// Reads the clock sysvar with `tolerance` seconds of slot slack.
+    pub fn get(tolerance: i64) -> Result<Self> {
+        let clock = Clock::get()?;
+        Ok(Self {
+            unix_timestamp: clock.unix_timestamp,
+            slot: clock.slot,
+            tolerance_slots: seconds_to_slots(tolerance),
+        })
+    }
+
+    /// Slot `expires_at` falls in at the nominal slot rate, counted from now.
+    pub fn slot_at(&self, expires_at: i64) -> u64 {
+        self.slot.saturating_add(seconds_to_slots(
+            expires_at.saturating_sub(self.unix_timestamp),
+        ))
+    }
+
+    /// Whether an expiry at `expires_at`, estimated at `expires_at_slot`, has passed.
+    pub fn has_passed(&self, expires_at: i64, expires_at_slot: u64) -> bool {
+        self.unix_timestamp >= expires_at
+            || self.slot >= expires_at_slot.saturating_add(self.tolerance_slots)
+    }
+}
+
+impl ProgramConfig {
+    /// The current time with the configured clock skew tolerance.
+    pub fn expiry_clock(&self) -> Result<ExpiryClock> {
+        ExpiryClock::get(self.clock_skew_tolerance)
+    }
+}
+
+fn seconds_to_slots(seconds: i64) -> u64 {
+    (seconds.max(0) as u64)
+        .saturating_mul(1000)
+        .div_ceil(MS_PER_SLOT)
+}
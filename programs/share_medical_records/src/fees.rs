@@ -0,0 +1,289 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{
+    close_account, transfer_checked, CloseAccount, Mint, TokenAccount, TokenInterface,
+    TransferChecked,
+};
+use arcium_anchor::prelude::*;
+
+use crate::ErrorCode;
+use crate::*;
+
+pub const ACCESS_FEE_SEED: &[u8] = b"access_fee";
+pub const FEE_ESCROW_SEED: &[u8] = b"fee_escrow";
+pub const FEE_AUTHORITY_SEED: &[u8] = b"fee_authority";
+
+/// Number of accounts appended to the share callback to settle an access fee.
+pub const ACCESS_FEE_CALLBACK_ACCOUNTS: usize = 7;
+
+/// Token fee a patient charges for each share of their record.
+#[account]
+#[derive(InitSpace)]
+pub struct AccessFee {
+    /// Wallet whose token account receives the fee
+    pub patient: Pubkey,
+    /// Mint the fee is paid in
+    pub mint: Pubkey,
+    /// Fee per share in base units; zero disables the fee
+    pub amount: u64,
+    pub bump: u8,
+}
+
+impl AccessFee {
+    /// Reads the fee configured for a record, if the patient charges one.
+    pub fn load(info: &AccountInfo) -> Result<Option<AccessFee>> {
+        if info.data_is_empty() {
+            return Ok(None);
+        }
+        require_keys_eq!(*info.owner, crate::ID, ErrorCode::InvalidFeeAccount);
+        let fee = AccessFee::try_deserialize(&mut &info.try_borrow_data()?[..])?;
+        Ok((fee.amount > 0).then_some(fee))
+    }
+}
+
+#[derive(Accounts)]
+pub struct SetAccessFee<'info> {
+    #[account(mut)]
+    pub patient: Signer<'info>,
+    #[account(
+        seeds = [b"patient_data", patient.key().as_ref()],
+        bump,
+    )]
+    pub patient_data: Account<'info, PatientData>,
+    #[account(
+        init_if_needed,
+        payer = patient,
+        space = 8 + AccessFee::INIT_SPACE,
+        seeds = [ACCESS_FEE_SEED, patient_data.key().as_ref()],
+        bump,
+    )]
+    pub access_fee: Account<'info, AccessFee>,
+    pub fee_mint: InterfaceAccount<'info, Mint>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Bump of the PDA that owns every token escrow of the program.
+pub fn fee_authority_bump() -> u8 {
+    Pubkey::find_program_address(&[FEE_AUTHORITY_SEED], &crate::ID).1
+}
+
+#[event]
+pub struct AccessFeeSettledEvent {
+    pub fee_escrow: Pubkey,
+    pub amount: u64,
+    /// Part of `amount` paid to the treasury
+    pub treasury_amount: u64,
+    pub refunded: bool,
+}
+
+/// Moves the record's access fee, if any, from the payer into the computation's escrow.
+///
+/// Payers with an active subscription are not charged per share; the subscription is only
+/// read when the record charges one. Returns the accounts the callback needs to release
+/// the escrow, with the program id standing in for every account when no fee is due, and
+/// the amount escrowed.
+pub fn escrow_access_fee(
+    accounts: &SharePatientData,
+    clock: &ExpiryClock,
+) -> Result<(Vec<CallbackAccount>, u64)> {
+    let mut fee = AccessFee::load(&accounts.access_fee)?;
+    if fee.is_some() && Subscription::is_active(&accounts.subscription, accounts.payer.key, clock)?
+    {
+        fee = None;
+    }
+    let Some(fee) = fee else {
+        let placeholders = (0..ACCESS_FEE_CALLBACK_ACCOUNTS)
+            .map(|_| CallbackAccount {
+                pubkey: crate::ID,
+                is_writable: false,
+            })
+            .collect();
+        return Ok((placeholders, 0));
+    };
+
+    let (
+        Some(fee_mint),
+        Some(payer_fee_account),
+        Some(patient_fee_account),
+        Some(treasury),
+        Some(fee_escrow),
+        Some(fee_authority),
+        Some(token_program),
+    ) = (
+        &accounts.fee_mint,
+        &accounts.payer_fee_account,
+        &accounts.patient_fee_account,
+        &accounts.treasury,
+        &accounts.fee_escrow,
+        &accounts.fee_authority,
+        &accounts.token_program,
+    )
+    else {
+        return err!(ErrorCode::AccessFeeAccountsMissing);
+    };
+    require_keys_eq!(fee_mint.key(), fee.mint, ErrorCode::InvalidFeeAccount);
+    require_keys_eq!(
+        patient_fee_account.owner,
+        fee.patient,
+        ErrorCode::InvalidFeeAccount
+    );
+    require_keys_eq!(
+        patient_fee_account.mint,
+        fee.mint,
+        ErrorCode::InvalidFeeAccount
+    );
+    require_keys_eq!(
+        treasury.key(),
+        treasury_address(&fee.mint),
+        ErrorCode::InvalidFeeAccount
+    );
+
+    transfer_checked(
+        CpiContext::new(
+            token_program.to_account_info(),
+            TransferChecked {
+                from: payer_fee_account.to_account_info(),
+                mint: fee_mint.to_account_info(),
+                to: fee_escrow.to_account_info(),
+                authority: accounts.payer.to_account_info(),
+            },
+        ),
+        fee.amount,
+        fee_mint.decimals,
+    )?;
+
+    let callback_accounts = vec![
+        CallbackAccount {
+            pubkey: fee_escrow.key(),
+            is_writable: true,
+        },
+        CallbackAccount {
+            pubkey: patient_fee_account.key(),
+            is_writable: true,
+        },
+        CallbackAccount {
+            pubkey: treasury.key(),
+            is_writable: true,
+        },
+        CallbackAccount {
+            pubkey: payer_fee_account.key(),
+            is_writable: true,
+        },
+        CallbackAccount {
+            pubkey: fee_mint.key(),
+            is_writable: false,
+        },
+        CallbackAccount {
+            pubkey: fee_authority.key(),
+            is_writable: false,
+        },
+        CallbackAccount {
+            pubkey: token_program.key(),
+            is_writable: false,
+        },
+    ];
+    Ok((callback_accounts, fee.amount))
+}
+
+/// Releases an access fee escrow to the patient, or back to the payer when `refund` is set.
+///
+/// A release to the patient pays the configured treasury cut first. Does nothing when the
+/// share carried no fee.
+pub fn settle_access_fee(accounts: &SharePatientDataCallback, refund: bool) -> Result<()> {
+    let (Some(fee_escrow), Some(fee_mint), Some(fee_authority), Some(token_program)) = (
+        &accounts.fee_escrow,
+        &accounts.fee_mint,
+        &accounts.fee_authority,
+        &accounts.token_program,
+    ) else {
+        return Ok(());
+    };
+    let (destination, treasury) = if refund {
+        (&accounts.payer_fee_account, None)
+    } else {
+        let treasury = accounts
+            .treasury
+            .as_deref()
+            .ok_or(ErrorCode::AccessFeeAccountsMissing)?;
+        (
+            &accounts.patient_fee_account,
+            Some((treasury, accounts.program_config.treasury_bps)),
+        )
+    };
+    let destination = destination
+        .as_ref()
+        .ok_or(ErrorCode::AccessFeeAccountsMissing)?;
+
+    release_fee_escrow(
+        fee_escrow,
+        destination,
+        treasury,
+        fee_mint,
+        fee_authority,
+        &accounts.payer,
+        token_program,
+        refund,
+    )
+}
+
+/// Empties a fee escrow into `destination` and closes it, returning its rent to
+/// `rent_destination`. When `treasury` is given, its basis-points cut is paid to it first.
+#[allow(clippy::too_many_arguments)]
+pub fn release_fee_escrow<'info>(
+    fee_escrow: &InterfaceAccount<'info, TokenAccount>,
+    destination: &InterfaceAccount<'info, TokenAccount>,
+    treasury: Option<(&InterfaceAccount<'info, TokenAccount>, u16)>,
+    fee_mint: &InterfaceAccount<'info, Mint>,
+    fee_authority: &AccountInfo<'info>,
+    rent_destination: &AccountInfo<'info>,
+    token_program: &Interface<'info, TokenInterface>,
+    refunded: bool,
+) -> Result<()> {
+    let signer_seeds: &[&[&[u8]]] = &[&[FEE_AUTHORITY_SEED, &[fee_authority_bump()]]];
+    let transfer = |to: &InterfaceAccount<'info, TokenAccount>, amount: u64| -> Result<()> {
+        if amount == 0 {
+            return Ok(());
+        }
+        transfer_checked(
+            CpiContext::new_with_signer(
+                token_program.to_account_info(),
+                TransferChecked {
+                    from: fee_escrow.to_account_info(),
+                    mint: fee_mint.to_account_info(),
+                    to: to.to_account_info(),
+                    authority: fee_authority.clone(),
+                },
+                signer_seeds,
+            ),
+            amount,
+            fee_mint.decimals,
+        )
+    };
+
+    let amount = fee_escrow.amount;
+    let (destination_amount, treasury_amount) = match treasury {
+        Some((treasury, treasury_bps)) => {
+            let split = split_fee(amount, treasury_bps)?;
+            transfer(treasury, split.1)?;
+            split
+        }
+        None => (amount, 0),
+    };
+    transfer(destination, destination_amount)?;
+    close_account(CpiContext::new_with_signer(
+        token_program.to_account_info(),
+        CloseAccount {
+            account: fee_escrow.to_account_info(),
+            destination: rent_destination.clone(),
+            authority: fee_authority.clone(),
+        },
+        signer_seeds,
+    ))?;
+
+    emit!(AccessFeeSettledEvent {
+        fee_escrow: fee_escrow.key(),
+        amount,
+        treasury_amount,
+        refunded,
+    });
+    Ok(())
+}
@@ -0,0 +1,171 @@
+use anchor_lang::prelude::*;
+use arcium_anchor::prelude::*;
+
+use crate::ErrorCode;
+use crate::*;
+
+pub const FHIR_ANCHOR_SEED: &[u8] = b"fhir_anchor";
+pub const MAX_FHIR_RESOURCE_TYPE_LEN: usize = 64;
+
+/// On-chain anchor for an encrypted FHIR bundle stored off-chain.
+///
+/// The bundle itself is encrypted with a symmetric content key; only that key is kept
+/// here, encrypted to the MXE, so it can be re-encrypted for a receiver by the
+/// `share_content_key` circuit.
+#[account]
+#[derive(InitSpace)]
+pub struct FhirAnchor {
+    /// Content key of the bundle, encrypted to the MXE; must stay the first field
+    pub content_key: EncryptedContentKey,
+    pub patient: Pubkey,
+    pub anchor_id: u64,
+    /// FHIR resource type of the bundle, e.g. `Bundle` or `DiagnosticReport`
+    #[max_len(MAX_FHIR_RESOURCE_TYPE_LEN)]
+    pub resource_type: String,
+    /// Version of the anchored bundle, increasing with every update
+    pub version: u32,
+    /// SHA-256 of the encrypted bundle
+    pub bundle_hash: [u8; 32],
+    pub bump: u8,
+}
+
+#[event]
+pub struct FhirResourceAnchoredEvent {
+    pub fhir_anchor: Pubkey,
+    pub patient: Pubkey,
+    pub resource_type: String,
+    pub version: u32,
+    pub bundle_hash: [u8; 32],
+}
+
+#[derive(Accounts)]
+#[instruction(anchor_id: u64)]
+pub struct AnchorFhirResource<'info> {
+    #[account(mut)]
+    pub patient: Signer<'info>,
+    #[account(
+        init,
+        payer = patient,
+        space = 8 + FhirAnchor::INIT_SPACE,
+        seeds = [FHIR_ANCHOR_SEED, patient.key().as_ref(), &anchor_id.to_le_bytes()],
+        bump,
+    )]
+    pub fhir_anchor: Account<'info, FhirAnchor>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateFhirAnchor<'info> {
+    pub patient: Signer<'info>,
+    #[account(
+        mut,
+        has_one = patient @ ErrorCode::Unauthorized,
+    )]
+    pub fhir_anchor: Account<'info, FhirAnchor>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[queue_computation_accounts("share_content_key", patient)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct ShareFhirContentKey<'info> {
+    #[account(mut)]
+    pub patient: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = patient,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account, checked by the arcium program.
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool, checked by the arcium program.
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account, checked by the arcium program.
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_SHARE_CONTENT_KEY)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(
+        mut,
+        address = derive_cluster_pda!(mxe_account)
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Box<Account<'info, ProgramConfig>>,
+    #[account(has_one = patient @ ErrorCode::Unauthorized)]
+    pub fhir_anchor: Box<Account<'info, FhirAnchor>>,
+    #[account(
+        seeds = [b"patient_data", patient.key().as_ref()],
+        bump,
+    )]
+    /// CHECK: the patient's record address, which their kin links are keyed by.
+    pub patient_data: UncheckedAccount<'info>,
+    #[account(
+        seeds = [KIN_ROSTER_SEED, patient_data.key().as_ref()],
+        bump,
+    )]
+    /// CHECK: kin roster, empty unless the record was ever linked to a relative; read in
+    /// the handler.
+    pub kin_roster: UncheckedAccount<'info>,
+    #[account(
+        init_if_needed,
+        payer = patient,
+        space = 8 + UsedOffsets::INIT_SPACE,
+        seeds = [USED_OFFSETS_SEED, patient.key().as_ref()],
+        bump,
+    )]
+    pub used_offsets: Box<Account<'info, UsedOffsets>>,
+    #[account(
+        init,
+        payer = patient,
+        space = 8 + CallbackGuard::INIT_SPACE,
+        seeds = [CALLBACK_GUARD_SEED, &computation_offset.to_le_bytes()],
+        bump,
+    )]
+    pub callback_guard: Box<Account<'info, CallbackGuard>>,
+}
@@ -0,0 +1,207 @@
+use anchor_lang::prelude::*;
+use std::ops::Range;
+
+use crate::ErrorCode;
+use crate::*;
+
+/// Ciphertext blocks of a patient record: one per scalar field, then one per allergy.
+pub const PATIENT_RECORD_BLOCKS: usize = 11;
+/// Allergy flags a patient record holds.
+pub const PATIENT_RECORD_ALLERGIES: usize = 5;
+/// Selective-share mask covering every field of a record.
+pub const PATIENT_FIELDS_ALL: u16 = (1 << PatientField::ALL.len()) - 1;
+
+/// A field of a patient record.
+///
+/// Records travel as their ciphertext blocks in `PatientData` order, through instruction
+/// arguments, circuit outputs and client encryption alike; each field names its blocks so
+/// none of those have to index them by position.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum PatientField {
+    PatientId,
+    Age,
+    Gender,
+    BloodType,
+    Weight,
+    Height,
+    Allergies,
+}
+
+impl PatientField {
+    /// Fields encrypted to a single block, in record order.
+    pub const SCALARS: [PatientField; 6] = [
+        PatientField::PatientId,
+        PatientField::Age,
+        PatientField::Gender,
+        PatientField::BloodType,
+        PatientField::Weight,
+        PatientField::Height,
+    ];
+    pub const ALL: [PatientField; 7] = [
+        PatientField::PatientId,
+        PatientField::Age,
+        PatientField::Gender,
+        PatientField::BloodType,
+        PatientField::Weight,
+        PatientField::Height,
+        PatientField::Allergies,
+    ];
+
+    /// Bit of the field in a selective-share mask.
+    pub const fn bit(self) -> u16 {
+        1 << self as u16
+    }
+
+    /// Positions of the field's blocks among a record's.
+    pub const fn blocks(self) -> Range<usize> {
+        match self {
+            PatientField::Allergies => {
+                Self::SCALARS.len()..Self::SCALARS.len() + PATIENT_RECORD_ALLERGIES
+            }
+            field => field as usize..field as usize + 1,
+        }
+    }
+
+    /// The field's blocks out of a whole record's.
+    pub fn of<T>(self, record: &[T; PATIENT_RECORD_BLOCKS]) -> &[T] {
+        &record[self.blocks()]
+    }
+}
+
+/// Fails with `InvalidPatientFieldMask` unless `fields` selects at least one field and only
+/// `PatientField` bits.
+pub fn require_field_mask(fields: u16) -> Result<()> {
+    require!(
+        fields != 0 && fields & !PATIENT_FIELDS_ALL == 0,
+        ErrorCode::InvalidPatientFieldMask
+    );
+    Ok(())
+}
+
+impl ProgramConfig {
+    /// Fails with `FieldNotCollected` if `fields` selects a field the deployment does not
+    /// collect.
+    pub fn require_collected(&self, fields: u16) -> Result<()> {
+        require!(
+            fields & !self.record_fields == 0,
+            ErrorCode::FieldNotCollected
+        );
+        Ok(())
+    }
+}
+
+/// Puts a record's scalar fields and allergies, as instructions take them, back into
+/// record order.
+pub fn join_record<T: Copy>(
+    scalars: [T; 6],
+    allergies: [T; PATIENT_RECORD_ALLERGIES],
+) -> [T; PATIENT_RECORD_BLOCKS] {
+    let allergies_start = PatientField::Allergies.blocks().start;
+    std::array::from_fn(|i| {
+        if i < allergies_start {
+            scalars[i]
+        } else {
+            allergies[i - allergies_start]
+        }
+    })
+}
+
+/// Splits a record into its scalar fields, in `PatientField::SCALARS` order, and allergies.
+pub fn split_record<T: Copy>(
+    record: &[T; PATIENT_RECORD_BLOCKS],
+) -> ([T; 6], [T; PATIENT_RECORD_ALLERGIES]) {
+    let allergies_start = PatientField::Allergies.blocks().start;
+    (
+        PatientField::SCALARS.map(|field| record[field.blocks().start]),
+        std::array::from_fn(|i| record[allergies_start + i]),
+    )
+}
+
+/// Splits the ciphertexts of a record output by a circuit, failing with
+/// `ComputationOutputLength` unless they are a whole record.
+pub fn split_record_output(
+    ciphertexts: &[[u8; 32]],
+) -> Result<([[u8; 32]; 6], [[u8; 32]; PATIENT_RECORD_ALLERGIES])> {
+    let record: &[[u8; 32]; PATIENT_RECORD_BLOCKS] = ciphertexts
+        .try_into()
+        .map_err(|_| ErrorCode::ComputationOutputLength)?;
+    Ok(split_record(record))
+}
+
+impl PatientData {
+    pub fn field(&self, field: PatientField) -> &[[u8; 32]] {
+        match field {
+            PatientField::PatientId => std::slice::from_ref(&self.patient_id),
+            PatientField::Age => std::slice::from_ref(&self.age),
+            PatientField::Gender => std::slice::from_ref(&self.gender),
+            PatientField::BloodType => std::slice::from_ref(&self.blood_type),
+            PatientField::Weight => std::slice::from_ref(&self.weight),
+            PatientField::Height => std::slice::from_ref(&self.height),
+            PatientField::Allergies => &self.allergies,
+        }
+    }
+
+    /// The record's ciphertexts in record order.
+    pub fn ciphertexts(&self) -> [[u8; 32]; PATIENT_RECORD_BLOCKS] {
+        join_record(
+            PatientField::SCALARS.map(|field| self.field(field)[0]),
+            self.allergies,
+        )
+    }
+
+    pub fn set_ciphertexts(&mut self, ciphertexts: &[[u8; 32]; PATIENT_RECORD_BLOCKS]) {
+        let ([patient_id, age, gender, blood_type, weight, height], allergies) =
+            split_record(ciphertexts);
+        self.patient_id = patient_id;
+        self.age = age;
+        self.gender = gender;
+        self.blood_type = blood_type;
+        self.weight = weight;
+        self.height = height;
+        self.allergies = allergies;
+    }
+
+    /// Whether the record already holds these ciphertexts, encrypted under `enc_pubkey`.
+    pub fn holds(
+        &self,
+        ciphertexts: &[[u8; 32]; PATIENT_RECORD_BLOCKS],
+        enc_pubkey: &[u8; 32],
+    ) -> bool {
+        self.ciphertexts() == *ciphertexts && self.enc_pubkey == *enc_pubkey
+    }
+}
+
+impl ReceivedPatientDataEvent {
+    /// The shared record's ciphertexts in record order.
+    pub fn ciphertexts(&self) -> [[u8; 32]; PATIENT_RECORD_BLOCKS] {
+        join_record(
+            [
+                self.patient_id,
+                self.age,
+                self.gender,
+                self.blood_type,
+                self.weight,
+                self.height,
+            ],
+            self.allergies,
+        )
+    }
+}
+
+impl SharePreviewEvent {
+    /// The previewed record's ciphertexts in record order.
+    pub fn ciphertexts(&self) -> [[u8; 32]; PATIENT_RECORD_BLOCKS] {
+        join_record(
+            [
+                self.patient_id,
+                self.age,
+                self.gender,
+                self.blood_type,
+                self.weight,
+                self.height,
+            ],
+            self.allergies,
+        )
+    }
+}
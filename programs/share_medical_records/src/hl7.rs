@@ -0,0 +1,113 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hashv;
+
+use crate::ErrorCode;
+use crate::*;
+
+pub const PATIENT_REGISTRY_SEED: &[u8] = b"patient_registry";
+pub const HL7_ANCHOR_SEED: &[u8] = b"hl7_anchor";
+pub const MAX_HL7_POINTER_LEN: usize = 256;
+
+/// Append-only log of the external documents anchored against one patient record, and
+/// of the store, share and consent events emitted for it.
+///
+/// `head` chains every anchored hash and `audit_head` every audited event, so dropping
+/// or reordering an entry off-chain is detectable against the on-chain value.
+#[account]
+#[derive(InitSpace)]
+pub struct PatientRegistry {
+    pub patient_data: Pubkey,
+    /// Number of documents anchored so far
+    pub entries: u64,
+    /// Hash chain over every anchored document hash
+    pub head: [u8; 32],
+    pub bump: u8,
+    /// Number of events audited so far
+    pub audit_entries: u64,
+    /// Hash chain over every audited event
+    pub audit_head: [u8; 32],
+    /// Number of shares written to the access log so far
+    pub access_entries: u64,
+    /// Set once the record was closed; no new record may be stored at its address, so
+    /// consents, grants and links naming it never carry over to a stranger's record
+    pub record_closed: bool,
+}
+
+impl PatientRegistry {
+    /// Appends `document_hash` to the chain and returns its sequence number.
+    pub fn append(&mut self, document_hash: &[u8; 32]) -> Result<u64> {
+        let sequence = self.entries;
+        self.head = hashv(&[&self.head, document_hash]).to_bytes();
+        self.entries = sequence
+            .checked_add(1)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        Ok(sequence)
+    }
+}
+
+/// Anchor of an HL7v2 ORU result message sent by a lab.
+#[account]
+#[derive(InitSpace)]
+pub struct Hl7Anchor {
+    pub patient_data: Pubkey,
+    /// Registered laboratory that anchored the message
+    pub lab: Pubkey,
+    /// Position of the message in the patient's registry
+    pub sequence: u64,
+    /// SHA-256 of the raw HL7 message
+    pub message_hash: [u8; 32],
+    /// Location of the message, encrypted off-chain for the patient
+    #[max_len(MAX_HL7_POINTER_LEN)]
+    pub encrypted_pointer: Vec<u8>,
+    pub anchored_at: i64,
+    pub bump: u8,
+}
+
+#[event]
+pub struct Hl7MessageAnchoredEvent {
+    pub hl7_anchor: Pubkey,
+    pub patient_data: Pubkey,
+    pub lab: Pubkey,
+    pub sequence: u64,
+    pub message_hash: [u8; 32],
+    /// Registry hash chain head after this message
+    pub registry_head: [u8; 32],
+}
+
+#[derive(Accounts)]
+#[instruction(message_hash: [u8; 32])]
+pub struct AnchorHl7Message<'info> {
+    #[account(mut)]
+    pub lab: Signer<'info>,
+    #[account(
+        seeds = [PROVIDER_SEED, lab.key().as_ref()],
+        bump = provider_profile.bump,
+        constraint = provider_profile.active
+            && provider_profile.role == ProviderRole::Laboratory
+            @ ErrorCode::Unauthorized,
+    )]
+    pub provider_profile: Account<'info, ProviderProfile>,
+    pub patient_data: Account<'info, PatientData>,
+    #[account(
+        init_if_needed,
+        payer = lab,
+        space = 8 + PatientRegistry::INIT_SPACE,
+        seeds = [PATIENT_REGISTRY_SEED, patient_data.key().as_ref()],
+        bump,
+    )]
+    pub patient_registry: Account<'info, PatientRegistry>,
+    #[account(
+        init,
+        payer = lab,
+        space = 8 + Hl7Anchor::INIT_SPACE,
+        seeds = [HL7_ANCHOR_SEED, patient_data.key().as_ref(), message_hash.as_ref()],
+        bump,
+    )]
+    pub hl7_anchor: Account<'info, Hl7Anchor>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    pub system_program: Program<'info, System>,
+}
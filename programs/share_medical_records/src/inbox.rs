@@ -0,0 +1,133 @@
+use anchor_lang::prelude::*;
+
+use crate::ErrorCode;
+use crate::*;
+
+pub const INBOX_SEED: &[u8] = b"inbox";
+/// Notifications an inbox holds before the oldest is overwritten.
+pub const INBOX_CAPACITY: usize = 16;
+/// How long before a consent lapses `notify_consent_expiring` may post about it: 3 days.
+pub const CONSENT_EXPIRY_NOTICE: i64 = 3 * 24 * 60 * 60;
+
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NotificationKind {
+    /// A provider filed an `AccessRequest`; the subject is the request
+    AccessRequested,
+    /// A share of the record was delivered; the subject is its `ShareReceipt`
+    ShareCompleted,
+    /// A consent lapses within `CONSENT_EXPIRY_NOTICE`; the subject is the `ConsentGrant`
+    ConsentExpiring,
+    /// An admission activated an emergency consent; the subject is the
+    /// `EmergencyConsentPolicy`
+    EmergencyAccessUsed,
+    /// An insurer asked to see fields disputed in a prior-authorization review; the
+    /// subject is the `PriorAuthEscalation`
+    PriorAuthEscalated,
+    /// A responder fetched the emergency packet under a disaster declaration; the subject
+    /// is the `DisasterMode`
+    DisasterAccessUsed,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Notification {
+    pub kind: NotificationKind,
+    /// Account the notification is about
+    pub subject: Pubkey,
+    /// Unix timestamp the notification was posted at
+    pub posted_at: i64,
+}
+
+/// Ring buffer of a patient's latest notifications, so a mobile app can poll one account
+/// instead of subscribing to every event about the record.
+///
+/// Notification `n` sits at `n % INBOX_CAPACITY`; `posted` tells the app how many it
+/// missed since it last polled. Instructions that notify take the inbox address whether
+/// or not the patient opened one, and post only if they did.
+#[account]
+#[derive(InitSpace)]
+pub struct Inbox {
+    pub patient_data: Pubkey,
+    /// Notifications posted so far, including overwritten ones
+    pub posted: u64,
+    #[max_len(INBOX_CAPACITY)]
+    pub notifications: Vec<Notification>,
+    pub bump: u8,
+}
+
+impl Inbox {
+    /// Appends `notification`, overwriting the oldest once the inbox is full.
+    pub fn post(&mut self, notification: Notification) -> Result<()> {
+        let index = (self.posted % INBOX_CAPACITY as u64) as usize;
+        if index < self.notifications.len() {
+            self.notifications[index] = notification;
+        } else {
+            self.notifications.push(notification);
+        }
+        self.posted = self
+            .posted
+            .checked_add(1)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        Ok(())
+    }
+
+    /// Posts to the inbox at `info`, which must be checked against its seeds, if the
+    /// patient opened it.
+    pub fn notify(info: &AccountInfo, kind: NotificationKind, subject: Pubkey) -> Result<()> {
+        if info.data_is_empty() {
+            return Ok(());
+        }
+        require_keys_eq!(*info.owner, crate::ID, ErrorCode::Unauthorized);
+        let mut data = info.try_borrow_mut_data()?;
+        let mut inbox = Inbox::try_deserialize(&mut &data[..])?;
+        inbox.post(Notification {
+            kind,
+            subject,
+            posted_at: Clock::get()?.unix_timestamp,
+        })?;
+        inbox.try_serialize(&mut &mut data[..])
+    }
+}
+
+#[derive(Accounts)]
+pub struct OpenInbox<'info> {
+    #[account(mut)]
+    pub patient: Signer<'info>,
+    #[account(
+        seeds = [b"patient_data", patient.key().as_ref()],
+        bump,
+    )]
+    pub patient_data: Account<'info, PatientData>,
+    #[account(
+        init,
+        payer = patient,
+        space = 8 + Inbox::INIT_SPACE,
+        seeds = [INBOX_SEED, patient_data.key().as_ref()],
+        bump,
+    )]
+    pub inbox: Account<'info, Inbox>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct NotifyConsentExpiring<'info> {
+    #[account(
+        seeds = [
+            CONSENT_GRANT_SEED,
+            consent_grant.patient_data.as_ref(),
+            consent_grant.receiver.as_ref(),
+        ],
+        bump = consent_grant.bump,
+    )]
+    pub consent_grant: Account<'info, ConsentGrant>,
+    #[account(
+        mut,
+        seeds = [INBOX_SEED, consent_grant.patient_data.as_ref()],
+        bump = inbox.bump,
+    )]
+    pub inbox: Account<'info, Inbox>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
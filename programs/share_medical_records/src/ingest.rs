@@ -0,0 +1,118 @@
+use anchor_lang::prelude::*;
+
+use crate::ErrorCode;
+use crate::*;
+
+pub const INGEST_AUTHORITY_SEED: &[u8] = b"ingest_authority";
+
+/// Append-only access of an app key, such as a wearable's sync service, to a patient's
+/// vitals log: a compressed record tree the patient owns.
+///
+/// The app may only append records attributed to the tree's owner, at most
+/// `quota_per_epoch` per Solana epoch. It holds no consent grant, so it can neither read
+/// nor share anything, including the records it appended.
+#[account]
+#[derive(InitSpace)]
+pub struct IngestAuthority {
+    /// Vitals log the app appends to
+    pub tree: Pubkey,
+    /// Owner of the tree, who granted the access
+    pub patient: Pubkey,
+    /// App key allowed to append, part of the grant address
+    pub app: Pubkey,
+    /// Records the app may append per epoch
+    pub quota_per_epoch: u32,
+    /// Epoch `appended` counts records for
+    pub epoch: u64,
+    /// Records appended in `epoch`
+    pub appended: u32,
+    pub revoked: bool,
+    pub granted_at: i64,
+    pub bump: u8,
+}
+
+impl IngestAuthority {
+    /// Counts one append against `epoch`'s quota, starting a fresh count in a new epoch.
+    pub fn consume_quota(&mut self, epoch: u64) -> Result<()> {
+        require!(!self.revoked, ErrorCode::IngestAuthorityRevoked);
+        if epoch != self.epoch {
+            self.epoch = epoch;
+            self.appended = 0;
+        }
+        require!(
+            self.appended < self.quota_per_epoch,
+            ErrorCode::IngestQuotaExceeded
+        );
+        self.appended += 1;
+        Ok(())
+    }
+}
+
+#[event]
+pub struct IngestAuthorityGrantedEvent {
+    pub ingest_authority: Pubkey,
+    pub tree: Pubkey,
+    pub app: Pubkey,
+    pub quota_per_epoch: u32,
+}
+
+#[event]
+pub struct IngestAuthorityRevokedEvent {
+    pub ingest_authority: Pubkey,
+    pub tree: Pubkey,
+    pub app: Pubkey,
+}
+
+#[derive(Accounts)]
+#[instruction(app: Pubkey)]
+pub struct GrantIngestAuthority<'info> {
+    #[account(mut)]
+    pub patient: Signer<'info>,
+    #[account(
+        constraint = tree.authority == patient.key() @ ErrorCode::Unauthorized,
+    )]
+    pub tree: Box<Account<'info, CompressedRecordTree>>,
+    #[account(
+        init_if_needed,
+        payer = patient,
+        space = 8 + IngestAuthority::INIT_SPACE,
+        seeds = [INGEST_AUTHORITY_SEED, tree.key().as_ref(), app.as_ref()],
+        bump,
+    )]
+    pub ingest_authority: Account<'info, IngestAuthority>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RevokeIngestAuthority<'info> {
+    pub patient: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [
+            INGEST_AUTHORITY_SEED,
+            ingest_authority.tree.as_ref(),
+            ingest_authority.app.as_ref(),
+        ],
+        bump = ingest_authority.bump,
+        has_one = patient @ ErrorCode::Unauthorized,
+    )]
+    pub ingest_authority: Account<'info, IngestAuthority>,
+}
+
+#[derive(Accounts)]
+pub struct IngestCompressedRecord<'info> {
+    pub app: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [INGEST_AUTHORITY_SEED, tree.key().as_ref(), app.key().as_ref()],
+        bump = ingest_authority.bump,
+    )]
+    pub ingest_authority: Account<'info, IngestAuthority>,
+    #[account(mut)]
+    pub tree: Box<Account<'info, CompressedRecordTree>>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
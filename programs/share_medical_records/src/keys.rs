@@ -0,0 +1,55 @@
+use anchor_lang::prelude::*;
+
+use crate::ErrorCode;
+
+/// u-coordinates of the points of small order on Curve25519 with a canonical encoding.
+///
+/// A shared secret with any of them lands in a subgroup of at most eight elements, so
+/// output encrypted for such a key is either guessable or undecryptable.
+const SMALL_ORDER_POINTS: [[u8; 32]; 5] = [
+    // 0, order 4
+    [0; 32],
+    // 1, order 1
+    [
+        0x01, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0,
+    ],
+    // Order 8
+    [
+        0xe0, 0xeb, 0x7a, 0x7c, 0x3b, 0x41, 0xb8, 0xae, 0x16, 0x56, 0xe3, 0xfa, 0xf1, 0x9f, 0xc4,
+        0x6a, 0xda, 0x09, 0x8d, 0xeb, 0x9c, 0x32, 0xb1, 0xfd, 0x86, 0x62, 0x05, 0x16, 0x5f, 0x49,
+        0xb8, 0x00,
+    ],
+    // Order 8
+    [
+        0x5f, 0x9c, 0x95, 0xbc, 0xa3, 0x50, 0x8c, 0x24, 0xb1, 0xd0, 0xb1, 0x55, 0x9c, 0x83, 0xef,
+        0x5b, 0x04, 0x44, 0x5c, 0xc4, 0x58, 0x1c, 0x8e, 0x86, 0xd8, 0x22, 0x4e, 0xdd, 0xd0, 0x9f,
+        0x11, 0x57,
+    ],
+    // p - 1, order 2
+    [
+        0xec, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+        0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+        0xff, 0x7f,
+    ],
+];
+
+/// Whether `key`, a little-endian u-coordinate, is below p = 2^255 - 19 with the top bit
+/// clear, i.e. the only encoding of its point.
+fn is_canonical(key: &[u8; 32]) -> bool {
+    if key[31] != 0x7f {
+        return key[31] < 0x7f;
+    }
+    // 2^255 - 19 is 0x7f, thirty 0xff bytes and 0xed, most significant byte first
+    key[1..31].iter().any(|&byte| byte != 0xff) || key[0] < 0xed
+}
+
+/// Fails with `InvalidEncryptionKey` unless `key` is a canonical x25519 public key outside
+/// the small-order subgroup.
+pub fn require_valid_x25519_key(key: &[u8; 32]) -> Result<()> {
+    require!(
+        is_canonical(key) && !SMALL_ORDER_POINTS.contains(key),
+        ErrorCode::InvalidEncryptionKey
+    );
+    Ok(())
+}
@@ -0,0 +1,281 @@
+use anchor_lang::prelude::*;
+
+use crate::ErrorCode;
+use crate::*;
+
+pub const KIN_LINK_SEED: &[u8] = b"kin_link";
+pub const KIN_ROSTER_SEED: &[u8] = b"kin_roster";
+/// Most relatives one record can be linked to, so a genomic share can pass every link.
+pub const MAX_KIN_LINKS: u16 = 8;
+/// FHIR resource types whose anchors hold genomic data, which is heritable and so also
+/// describes the patient's linked relatives.
+///
+/// The kin gate on sharing keys off the `resource_type` the patient declared when
+/// anchoring; the program never sees the bundle, so it cannot tell a genomic resource
+/// filed under another type. The type is fixed at anchoring, since `update_fhir_anchor`
+/// only replaces the bundle hash and key, so a linked patient cannot relabel an anchor
+/// later to slip past the gate.
+pub const FHIR_GENOMIC_RESOURCE_TYPES: [&str; 2] = ["MolecularSequence", "GenomicStudy"];
+
+/// Returns whether anchors of `resource_type` hold genomic data.
+pub fn is_genomic_resource_type(resource_type: &str) -> bool {
+    FHIR_GENOMIC_RESOURCE_TYPES.contains(&resource_type)
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub enum KinDegree {
+    /// Parent, child or sibling, sharing about half their DNA
+    First,
+    /// Grandparent, grandchild, aunt, uncle, niece, nephew or half-sibling
+    Second,
+    /// First cousin or more distant
+    Third,
+}
+
+/// A link between the records of two biological relatives, signed by both patients.
+///
+/// Either record's genomic data reveals heritable information about the other, so once
+/// linked, neither patient can share a genomic FHIR anchor unless both consents on every
+/// link of their record are given. Each patient may withdraw or restore their own consent
+/// at any time; removing the link takes both signatures again, so neither can lift the
+/// constraint alone, unless the other record has been closed. `record_a` is the lower
+/// address of the pair.
+#[account]
+#[derive(InitSpace)]
+pub struct KinLink {
+    pub record_a: Pubkey,
+    pub record_b: Pubkey,
+    pub degree: KinDegree,
+    /// Whether `record_a`'s patient consents to heritable data being shared
+    pub consent_a: bool,
+    /// Whether `record_b`'s patient consents to heritable data being shared
+    pub consent_b: bool,
+    pub linked_at: i64,
+    pub bump: u8,
+}
+
+impl KinLink {
+    /// Sets the consent of the patient owning `patient_data`, one of the pair.
+    pub fn set_consent(&mut self, patient_data: &Pubkey, consented: bool) -> Result<()> {
+        if *patient_data == self.record_a {
+            self.consent_a = consented;
+        } else if *patient_data == self.record_b {
+            self.consent_b = consented;
+        } else {
+            return err!(ErrorCode::KinLinkMismatch);
+        }
+        Ok(())
+    }
+
+    /// Returns whether this link joins `record` and `relative`, in either order.
+    pub fn joins(&self, record: &Pubkey, relative: &Pubkey) -> bool {
+        (self.record_a == *record && self.record_b == *relative)
+            || (self.record_a == *relative && self.record_b == *record)
+    }
+}
+
+/// Number of kin links of a record, so a genomic share can check it was given all of
+/// them.
+#[account]
+#[derive(InitSpace)]
+pub struct KinRoster {
+    pub patient_data: Pubkey,
+    pub links: u16,
+    pub bump: u8,
+}
+
+/// Fails unless `remaining_accounts` holds every kin link of `patient_data`, once each,
+/// and both relatives consented on all of them.
+///
+/// `kin_roster` must be checked against its seeds; if it was never created the record has
+/// no relatives linked and nothing is required.
+pub fn require_kin_consents<'info>(
+    patient_data: &Pubkey,
+    kin_roster: &AccountInfo,
+    remaining_accounts: &'info [AccountInfo<'info>],
+) -> Result<()> {
+    let links = if kin_roster.data_is_empty() {
+        0
+    } else {
+        require_keys_eq!(*kin_roster.owner, crate::ID, ErrorCode::Unauthorized);
+        KinRoster::try_deserialize(&mut &kin_roster.try_borrow_data()?[..])?.links
+    };
+    require!(
+        remaining_accounts.len() == links as usize,
+        ErrorCode::KinLinksIncomplete
+    );
+
+    let mut seen: Vec<Pubkey> = Vec::with_capacity(remaining_accounts.len());
+    for info in remaining_accounts {
+        require!(!seen.contains(info.key), ErrorCode::KinLinksIncomplete);
+        seen.push(info.key());
+        let link = checked_program_account::<KinLink>(info)?;
+        require!(
+            link.record_a == *patient_data || link.record_b == *patient_data,
+            ErrorCode::KinLinkMismatch
+        );
+        require!(
+            link.consent_a && link.consent_b,
+            ErrorCode::KinConsentMissing
+        );
+    }
+    Ok(())
+}
+
+#[event]
+pub struct KinLinkedEvent {
+    pub kin_link: Pubkey,
+    pub record_a: Pubkey,
+    pub record_b: Pubkey,
+    pub degree: KinDegree,
+}
+
+#[event]
+pub struct KinConsentChangedEvent {
+    pub kin_link: Pubkey,
+    pub patient_data: Pubkey,
+    pub consented: bool,
+}
+
+#[event]
+pub struct KinUnlinkedEvent {
+    pub kin_link: Pubkey,
+    pub record_a: Pubkey,
+    pub record_b: Pubkey,
+}
+
+#[derive(Accounts)]
+pub struct LinkKin<'info> {
+    #[account(mut)]
+    pub patient_a: Signer<'info>,
+    pub patient_b: Signer<'info>,
+    #[account(
+        seeds = [b"patient_data", patient_a.key().as_ref()],
+        bump,
+    )]
+    pub record_a: Box<Account<'info, PatientData>>,
+    #[account(
+        seeds = [b"patient_data", patient_b.key().as_ref()],
+        bump,
+        constraint = record_a.key() < record_b.key() @ ErrorCode::InvalidKinLink,
+    )]
+    pub record_b: Box<Account<'info, PatientData>>,
+    #[account(
+        init,
+        payer = patient_a,
+        space = 8 + KinLink::INIT_SPACE,
+        seeds = [KIN_LINK_SEED, record_a.key().as_ref(), record_b.key().as_ref()],
+        bump,
+    )]
+    pub kin_link: Account<'info, KinLink>,
+    #[account(
+        init_if_needed,
+        payer = patient_a,
+        space = 8 + KinRoster::INIT_SPACE,
+        seeds = [KIN_ROSTER_SEED, record_a.key().as_ref()],
+        bump,
+    )]
+    pub kin_roster_a: Account<'info, KinRoster>,
+    #[account(
+        init_if_needed,
+        payer = patient_a,
+        space = 8 + KinRoster::INIT_SPACE,
+        seeds = [KIN_ROSTER_SEED, record_b.key().as_ref()],
+        bump,
+    )]
+    pub kin_roster_b: Account<'info, KinRoster>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetKinConsent<'info> {
+    pub patient: Signer<'info>,
+    #[account(
+        seeds = [b"patient_data", patient.key().as_ref()],
+        bump,
+    )]
+    pub patient_data: Account<'info, PatientData>,
+    #[account(
+        mut,
+        seeds = [KIN_LINK_SEED, kin_link.record_a.as_ref(), kin_link.record_b.as_ref()],
+        bump = kin_link.bump,
+    )]
+    pub kin_link: Account<'info, KinLink>,
+}
+
+#[derive(Accounts)]
+pub struct UnlinkKin<'info> {
+    #[account(mut)]
+    pub patient_a: Signer<'info>,
+    pub patient_b: Signer<'info>,
+    #[account(
+        seeds = [b"patient_data", patient_a.key().as_ref()],
+        bump,
+    )]
+    pub record_a: Box<Account<'info, PatientData>>,
+    #[account(
+        seeds = [b"patient_data", patient_b.key().as_ref()],
+        bump,
+    )]
+    pub record_b: Box<Account<'info, PatientData>>,
+    #[account(
+        mut,
+        seeds = [KIN_LINK_SEED, record_a.key().as_ref(), record_b.key().as_ref()],
+        bump = kin_link.bump,
+        close = patient_a,
+    )]
+    pub kin_link: Account<'info, KinLink>,
+    #[account(
+        mut,
+        seeds = [KIN_ROSTER_SEED, record_a.key().as_ref()],
+        bump = kin_roster_a.bump,
+    )]
+    pub kin_roster_a: Account<'info, KinRoster>,
+    #[account(
+        mut,
+        seeds = [KIN_ROSTER_SEED, record_b.key().as_ref()],
+        bump = kin_roster_b.bump,
+    )]
+    pub kin_roster_b: Account<'info, KinRoster>,
+}
+
+/// Removes a link whose other record has been closed, signed by the remaining patient
+/// alone. A closed record's address stays dead, so its patient can no longer sign for
+/// `UnlinkKin` and the link would otherwise hold the remaining record's genomic shares
+/// hostage.
+#[derive(Accounts)]
+pub struct UnlinkClosedKin<'info> {
+    #[account(mut)]
+    pub patient: Signer<'info>,
+    #[account(
+        seeds = [b"patient_data", patient.key().as_ref()],
+        bump,
+    )]
+    pub patient_data: Box<Account<'info, PatientData>>,
+    #[account(
+        constraint = relative_data.data_is_empty() @ ErrorCode::KinRecordNotClosed,
+    )]
+    /// CHECK: the relative's record address; only its closure is checked.
+    pub relative_data: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        seeds = [KIN_LINK_SEED, kin_link.record_a.as_ref(), kin_link.record_b.as_ref()],
+        bump = kin_link.bump,
+        constraint = kin_link.joins(&patient_data.key(), &relative_data.key())
+            @ ErrorCode::KinLinkMismatch,
+        close = patient,
+    )]
+    pub kin_link: Account<'info, KinLink>,
+    #[account(
+        mut,
+        seeds = [KIN_ROSTER_SEED, patient_data.key().as_ref()],
+        bump = kin_roster.bump,
+    )]
+    pub kin_roster: Account<'info, KinRoster>,
+    #[account(
+        mut,
+        seeds = [KIN_ROSTER_SEED, relative_data.key().as_ref()],
+        bump = relative_kin_roster.bump,
+    )]
+    pub relative_kin_roster: Account<'info, KinRoster>,
+}
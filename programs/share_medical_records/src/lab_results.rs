@@ -0,0 +1,112 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hashv;
+
+use crate::ErrorCode;
+use crate::*;
+
+pub const LAB_RESULT_SEED: &[u8] = b"lab_result";
+/// Values one lab result holds, such as an analyte's value, unit and reference range.
+pub const LAB_RESULT_VALUES: usize = 4;
+
+/// A lab result's values, each a `u128` encrypted to the MXE with the lab's key.
+pub type EncryptedLabValues = [[u8; 32]; LAB_RESULT_VALUES];
+
+/// A result a registered lab submitted against a patient's record.
+///
+/// Its hash is appended to the patient's registry like an HL7 message, so the order
+/// results arrived in is tamper-evident.
+#[account]
+#[derive(InitSpace)]
+pub struct LabResult {
+    /// Encrypted result values
+    pub values: EncryptedLabValues,
+    pub patient_data: Pubkey,
+    /// Registered laboratory that submitted the result
+    pub lab: Pubkey,
+    /// Lab chosen identifier, part of the result address
+    pub result_id: u64,
+    /// Position of the result in the patient's registry
+    pub sequence: u64,
+    /// x25519 key the values were encrypted with
+    pub enc_pubkey: [u8; 32],
+    /// Nonce the values were encrypted with
+    pub nonce: u128,
+    pub submitted_at: i64,
+    pub bump: u8,
+}
+
+/// Hash of a lab result's key, nonce and values, as appended to the patient's registry.
+pub fn lab_result_hash(
+    enc_pubkey: &[u8; 32],
+    nonce: u128,
+    values: &EncryptedLabValues,
+) -> [u8; 32] {
+    let nonce = nonce.to_le_bytes();
+    let mut parts: Vec<&[u8]> = Vec::with_capacity(2 + values.len());
+    parts.push(enc_pubkey);
+    parts.push(&nonce);
+    parts.extend(values.iter().map(|value| value.as_slice()));
+    hashv(&parts).to_bytes()
+}
+
+/// A new lab result was written for a patient; their app subscribes by `patient`.
+#[event]
+pub struct NewResultAvailableEvent {
+    pub lab_result: Pubkey,
+    pub patient_data: Pubkey,
+    /// Wallet that owns the record
+    pub patient: Pubkey,
+    pub lab: Pubkey,
+    pub sequence: u64,
+    /// Registry hash chain head after this result
+    pub registry_head: [u8; 32],
+}
+
+#[derive(Accounts)]
+#[instruction(result_id: u64)]
+pub struct SubmitLabResult<'info> {
+    #[account(mut)]
+    pub lab: Signer<'info>,
+    #[account(
+        seeds = [PROVIDER_SEED, lab.key().as_ref()],
+        bump = provider_profile.bump,
+        constraint = provider_profile.active
+            && provider_profile.role == ProviderRole::Laboratory
+            @ ErrorCode::Unauthorized,
+    )]
+    pub provider_profile: Account<'info, ProviderProfile>,
+    pub patient_data: Account<'info, PatientData>,
+    /// The patient's care relationship grant to the lab
+    #[account(
+        seeds = [CONSENT_GRANT_SEED, patient_data.key().as_ref(), lab.key().as_ref()],
+        bump = consent_grant.bump,
+    )]
+    pub consent_grant: Account<'info, ConsentGrant>,
+    #[account(
+        init_if_needed,
+        payer = lab,
+        space = 8 + PatientRegistry::INIT_SPACE,
+        seeds = [PATIENT_REGISTRY_SEED, patient_data.key().as_ref()],
+        bump,
+    )]
+    pub patient_registry: Account<'info, PatientRegistry>,
+    #[account(
+        init,
+        payer = lab,
+        space = 8 + LabResult::INIT_SPACE,
+        seeds = [
+            LAB_RESULT_SEED,
+            patient_data.key().as_ref(),
+            lab.key().as_ref(),
+            &result_id.to_le_bytes(),
+        ],
+        bump,
+    )]
+    pub lab_result: Account<'info, LabResult>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    pub system_program: Program<'info, System>,
+}
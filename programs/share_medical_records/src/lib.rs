@@ -1,8 +1,169 @@
 use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{
+    close_account, transfer_checked, CloseAccount, Mint, TokenAccount, TokenInterface,
+    TransferChecked,
+};
 use arcium_anchor::prelude::*;
-use arcium_client::idl::arcium::types::{CircuitSource, OffChainCircuitSource};
+
+pub mod access_log;
+pub mod adverse_events;
+pub mod approvals;
+pub mod args;
+pub mod attestations;
+pub mod audit;
+pub mod blob;
+pub mod bundle;
+pub mod callbacks;
+pub mod caregiver;
+pub mod clearance;
+pub mod coded_values;
+pub mod commitment;
+pub mod compression;
+pub mod config;
+pub mod consent;
+pub mod consent_batch;
+pub mod content_key;
+#[cfg(feature = "cpi")]
+pub mod cpi_client;
+pub mod dicom;
+pub mod disaster;
+pub mod disclosure;
+pub mod documents;
+pub mod donors;
+pub mod emergency;
+pub mod emergency_packets;
+pub mod errors;
+pub mod escalations;
+pub mod expiry;
+pub mod fees;
+pub mod fhir;
+pub mod fields;
+pub mod hl7;
+pub mod inbox;
+pub mod ingest;
+pub mod keys;
+pub mod kinship;
+pub mod lab_results;
+pub mod lookup_table;
+pub mod med_administration;
+pub mod nonces;
+pub mod offsets;
+pub mod pending;
+pub mod prescriptions;
+pub mod preview;
+pub mod prior_auth;
+pub mod providers;
+pub mod public_health;
+pub mod receipts;
+pub mod research;
+pub mod retention;
+pub mod sanity;
+pub mod schema_registry;
+pub mod sessions;
+pub mod share_codes;
+pub mod shared_records;
+pub mod sns;
+pub mod specialties;
+pub mod staging;
+pub mod subscriptions;
+pub mod treasury;
+pub mod trials;
+pub mod waitlist;
+pub mod wormhole;
+
+pub use access_log::*;
+pub use adverse_events::*;
+pub use approvals::*;
+pub use args::*;
+pub use attestations::*;
+pub use audit::*;
+pub use blob::*;
+pub use bundle::*;
+pub use callbacks::*;
+pub use caregiver::*;
+pub use clearance::*;
+pub use coded_values::*;
+pub use commitment::*;
+pub use compression::*;
+pub use config::*;
+pub use consent::*;
+pub use consent_batch::*;
+pub use content_key::*;
+pub use dicom::*;
+pub use disaster::*;
+pub use disclosure::*;
+pub use documents::*;
+pub use donors::*;
+pub use emergency::*;
+pub use emergency_packets::*;
+pub use errors::*;
+pub use escalations::*;
+pub use expiry::*;
+pub use fees::*;
+pub use fhir::*;
+pub use fields::*;
+pub use hl7::*;
+pub use inbox::*;
+pub use ingest::*;
+pub use keys::*;
+pub use kinship::*;
+pub use lab_results::*;
+pub use lookup_table::*;
+pub use med_administration::*;
+pub use nonces::*;
+pub use offsets::*;
+pub use pending::*;
+pub use prescriptions::*;
+pub use preview::*;
+pub use prior_auth::*;
+pub use providers::*;
+pub use public_health::*;
+pub use receipts::*;
+pub use research::*;
+pub use retention::*;
+pub use sanity::*;
+pub use schema_registry::*;
+pub use sessions::*;
+pub use share_codes::*;
+pub use shared_records::*;
+pub use sns::*;
+pub use specialties::*;
+pub use staging::*;
+pub use subscriptions::*;
+pub use treasury::*;
+pub use trials::*;
+pub use waitlist::*;
+pub use wormhole::*;
 
 const COMP_DEF_OFFSET_SHARE_PATIENT_DATA: u32 = comp_def_offset("share_patient_data");
+const COMP_DEF_OFFSET_COHORT_QUERY: u32 = comp_def_offset("cohort_query");
+const COMP_DEF_OFFSET_REGULATORY_DISCLOSURE: u32 = comp_def_offset("regulatory_disclosure");
+const COMP_DEF_OFFSET_SHARE_CONTENT_KEY: u32 = comp_def_offset("share_content_key");
+const COMP_DEF_OFFSET_SHARE_DICOM_REFERENCE: u32 = comp_def_offset("share_dicom_reference");
+const COMP_DEF_OFFSET_SHARE_COMPRESSED_RECORD: u32 = comp_def_offset("share_compressed_record");
+const COMP_DEF_OFFSET_VERIFY_AGE_OVER: u32 = comp_def_offset("verify_age_over");
+const COMP_DEF_OFFSET_SHARE_COMMITTED_PATIENT_DATA: u32 =
+    comp_def_offset("share_committed_patient_data");
+const COMP_DEF_OFFSET_PREVIEW_SHARE: u32 = comp_def_offset("preview_share");
+const COMP_DEF_OFFSET_SHARE_BUNDLE: u32 = comp_def_offset("share_bundle");
+const COMP_DEF_OFFSET_VALIDATE_PRIOR_AUTH: u32 = comp_def_offset("validate_prior_auth");
+const COMP_DEF_OFFSET_ISSUE_PRESCRIPTION: u32 = comp_def_offset("issue_prescription");
+const COMP_DEF_OFFSET_VERIFY_PRESCRIPTION: u32 = comp_def_offset("verify_prescription");
+const COMP_DEF_OFFSET_CLEARANCE_CHECK: u32 = comp_def_offset("clearance_check");
+const COMP_DEF_OFFSET_EMERGENCY_PACKET: u32 = comp_def_offset("emergency_packet");
+const COMP_DEF_OFFSET_INIT_ADVERSE_EVENT_TALLY: u32 = comp_def_offset("init_adverse_event_tally");
+const COMP_DEF_OFFSET_TALLY_ADVERSE_EVENT: u32 = comp_def_offset("tally_adverse_event");
+const COMP_DEF_OFFSET_ADVERSE_EVENT_COUNTS: u32 = comp_def_offset("adverse_event_counts");
+const COMP_DEF_OFFSET_OPEN_CASE_COUNTS: u32 = comp_def_offset("open_case_counts");
+const COMP_DEF_OFFSET_NOTIFY_CASE: u32 = comp_def_offset("notify_case");
+const COMP_DEF_OFFSET_CASE_COUNTS: u32 = comp_def_offset("case_counts");
+const COMP_DEF_OFFSET_REVEAL_CASE: u32 = comp_def_offset("reveal_case");
+const COMP_DEF_OFFSET_CAREGIVER_UPDATE: u32 = comp_def_offset("caregiver_update");
+const COMP_DEF_OFFSET_FIND_COMPATIBLE_DONORS: u32 = comp_def_offset("find_compatible_donors");
+const COMP_DEF_OFFSET_WAITLIST_PRIORITY: u32 = comp_def_offset("waitlist_priority");
+const COMP_DEF_OFFSET_REDEEM_SHARE_CODE: u32 = comp_def_offset("redeem_share_code");
+const COMP_DEF_OFFSET_DISCLOSE_ESCALATED_FIELDS: u32 = comp_def_offset("disclose_escalated_fields");
+const COMP_DEF_OFFSET_DISASTER_PACKET: u32 = comp_def_offset("disaster_packet");
 
 declare_id!("5NqzyBVgHPSb7TMWT37r5vHBqhKE86wbnYYdqsSLRYgt");
 
@@ -10,11 +171,423 @@ declare_id!("5NqzyBVgHPSb7TMWT37r5vHBqhKE86wbnYYdqsSLRYgt");
 pub mod share_medical_records {
     use super::*;
 
+    /// Creates the program configuration account.
+    ///
+    /// Must be signed by the program's upgrade authority, who becomes the config admin.
+    /// The currently compiled share circuit is recorded as version 1.
+    pub fn initialize_config(ctx: Context<InitializeConfig>) -> Result<()> {
+        let config = &mut ctx.accounts.program_config;
+        config.admin = ctx.accounts.payer.key();
+        config.pending_admin = None;
+        config.paused = false;
+        config.paused_store = false;
+        config.paused_share = false;
+        config.paused_compute = false;
+        config.computation_timeout = DEFAULT_COMPUTATION_TIMEOUT;
+        config.approved_clusters = Vec::new();
+        config.registrars = Vec::new();
+        config.admission_oracles = Vec::new();
+        config.regulators = Vec::new();
+        config.disclosure_committee = Vec::new();
+        config.disclosure_threshold = 0;
+        config.disclosure_interval = 0;
+        config.retention_policies = Vec::new();
+        config.event_cpi = false;
+        config.lookup_table = Pubkey::default();
+        config.treasury_bps = 0;
+        config.subscription_mint = Pubkey::default();
+        config.subscription_tiers = Vec::new();
+        config.circuit_version = 1;
+        config.active_share_comp_def = COMP_DEF_OFFSET_SHARE_PATIENT_DATA;
+        config.deprecated_share_comp_def = None;
+        config.deprecation_ends_at = 0;
+        config.circuit_manifest = Vec::new();
+        config.clock_skew_tolerance = DEFAULT_CLOCK_SKEW_TOLERANCE;
+        config.max_in_flight_per_payer = DEFAULT_MAX_IN_FLIGHT_PER_PAYER;
+        config.record_fields = PATIENT_FIELDS_ALL;
+        config.compact_events = false;
+        config.auditors = Vec::new();
+        config.disaster_committee = Vec::new();
+        config.disaster_threshold = 0;
+        config.bump = ctx.bumps.program_config;
+        Ok(())
+    }
+
+    /// Proposes a new config admin.
+    ///
+    /// The transfer only completes once the proposed key calls `accept_admin`, so a typo
+    /// cannot lock the configuration. Proposing again replaces the pending proposal.
+    ///
+    /// # Arguments
+    /// * `new_admin` - Key that will be allowed to accept the admin role
+    pub fn propose_admin(ctx: Context<UpdateConfig>, new_admin: Pubkey) -> Result<()> {
+        ctx.accounts.program_config.pending_admin = Some(new_admin);
+        Ok(())
+    }
+
+    /// Completes an admin transfer started with `propose_admin`.
+    pub fn accept_admin(ctx: Context<AcceptAdmin>) -> Result<()> {
+        let config = &mut ctx.accounts.program_config;
+        config.admin = ctx.accounts.new_admin.key();
+        config.pending_admin = None;
+        Ok(())
+    }
+
+    /// Sets the global and per-feature pause switches.
+    ///
+    /// Lets an operator halt, for example, sharing during an Arcium cluster incident while
+    /// record storage keeps working. Callbacks are never paused so in-flight computations
+    /// can still settle.
+    ///
+    /// # Arguments
+    /// * `paused` - Halts everything except configuration and callbacks
+    /// * `paused_store` - Halts storing records
+    /// * `paused_share` - Halts queueing shares
+    /// * `paused_compute` - Halts queueing any computation
+    pub fn set_pause_flags(
+        ctx: Context<UpdateConfig>,
+        paused: bool,
+        paused_store: bool,
+        paused_share: bool,
+        paused_compute: bool,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.program_config;
+        config.paused = paused;
+        config.paused_store = paused_store;
+        config.paused_share = paused_share;
+        config.paused_compute = paused_compute;
+
+        emit!(PauseFlagsUpdatedEvent {
+            paused,
+            paused_store,
+            paused_share,
+            paused_compute,
+        });
+        Ok(())
+    }
+
+    /// Sets how long a queued share may go without a callback before it can be expired.
+    ///
+    /// # Arguments
+    /// * `computation_timeout` - Timeout in seconds
+    pub fn set_computation_timeout(
+        ctx: Context<UpdateConfig>,
+        computation_timeout: i64,
+    ) -> Result<()> {
+        require!(computation_timeout > 0, ErrorCode::InvalidTimeout);
+        ctx.accounts.program_config.computation_timeout = computation_timeout;
+        Ok(())
+    }
+
+    /// Sets how far the slot estimate of an expiry may run past it before a consent,
+    /// subscription or attestation counts as expired despite a lagging cluster timestamp.
+    ///
+    /// # Arguments
+    /// * `clock_skew_tolerance` - Tolerance in seconds
+    pub fn set_clock_skew_tolerance(
+        ctx: Context<UpdateConfig>,
+        clock_skew_tolerance: i64,
+    ) -> Result<()> {
+        require!(
+            clock_skew_tolerance >= 0,
+            ErrorCode::InvalidClockSkewTolerance
+        );
+        ctx.accounts.program_config.clock_skew_tolerance = clock_skew_tolerance;
+        Ok(())
+    }
+
+    /// Sets how many computations a single payer may have in flight at once.
+    ///
+    /// # Arguments
+    /// * `max_in_flight_per_payer` - Cap, at most `MAX_IN_FLIGHT_PER_PAYER`
+    pub fn set_max_in_flight_per_payer(
+        ctx: Context<UpdateConfig>,
+        max_in_flight_per_payer: u8,
+    ) -> Result<()> {
+        require!(
+            max_in_flight_per_payer > 0
+                && max_in_flight_per_payer as usize <= MAX_IN_FLIGHT_PER_PAYER,
+            ErrorCode::InvalidInFlightCap
+        );
+        ctx.accounts.program_config.max_in_flight_per_payer = max_in_flight_per_payer;
+        Ok(())
+    }
+
+    /// Sets the record fields this deployment collects, e.g. dropping height and weight
+    /// for a dental clinic, without changing the record layout or circuits.
+    ///
+    /// Set it before the first store: records stored earlier keep the fields they were
+    /// stored with until they are next updated.
+    ///
+    /// # Arguments
+    /// * `record_fields` - Bitmask of `PatientField` bits, at least one
+    pub fn set_record_fields(ctx: Context<UpdateConfig>, record_fields: u16) -> Result<()> {
+        require_field_mask(record_fields)?;
+        ctx.accounts.program_config.record_fields = record_fields;
+        Ok(())
+    }
+
+    /// Replaces the list of clusters computations may be routed to via `cluster_hint`.
+    ///
+    /// # Arguments
+    /// * `cluster_offsets` - Offsets of the approved Arcium clusters
+    pub fn set_approved_clusters(
+        ctx: Context<UpdateConfig>,
+        cluster_offsets: Vec<u32>,
+    ) -> Result<()> {
+        require!(
+            cluster_offsets.len() <= MAX_APPROVED_CLUSTERS,
+            ErrorCode::TooManyClusters
+        );
+        ctx.accounts.program_config.approved_clusters = cluster_offsets;
+        Ok(())
+    }
+
+    /// Replaces the set of registrars allowed to manage the provider registry.
+    ///
+    /// # Arguments
+    /// * `registrars` - Registrar keys
+    pub fn set_registrars(ctx: Context<UpdateConfig>, registrars: Vec<Pubkey>) -> Result<()> {
+        require!(
+            registrars.len() <= MAX_REGISTRARS,
+            ErrorCode::TooManyRegistrars
+        );
+        ctx.accounts.program_config.registrars = registrars;
+        Ok(())
+    }
+
+    /// Replaces the set of admissions oracles allowed to activate emergency consent.
+    ///
+    /// # Arguments
+    /// * `oracles` - Oracle keys, e.g. hospital admissions feeds
+    pub fn set_admission_oracles(ctx: Context<UpdateConfig>, oracles: Vec<Pubkey>) -> Result<()> {
+        require!(
+            oracles.len() <= MAX_ADMISSION_ORACLES,
+            ErrorCode::TooManyAdmissionOracles
+        );
+        ctx.accounts.program_config.admission_oracles = oracles;
+        Ok(())
+    }
+
+    /// Sets who may checkpoint records' audit chains with `attest_audit_window`.
+    ///
+    /// # Arguments
+    /// * `auditors` - Auditor keys
+    pub fn set_auditors(ctx: Context<UpdateConfig>, auditors: Vec<Pubkey>) -> Result<()> {
+        require!(auditors.len() <= MAX_AUDITORS, ErrorCode::TooManyAuditors);
+        ctx.accounts.program_config.auditors = auditors;
+        Ok(())
+    }
+
+    /// Sets who may declare disaster modes and how many of them must co-sign.
+    ///
+    /// # Arguments
+    /// * `committee` - Committee member keys
+    /// * `threshold` - Members who must co-sign a declaration; zero disables declarations
+    pub fn set_disaster_committee(
+        ctx: Context<UpdateConfig>,
+        committee: Vec<Pubkey>,
+        threshold: u8,
+    ) -> Result<()> {
+        require!(
+            committee.len() <= MAX_DISASTER_COMMITTEE
+                && threshold as usize <= committee.len()
+                && committee
+                    .iter()
+                    .enumerate()
+                    .all(|(i, member)| !committee[..i].contains(member)),
+            ErrorCode::InvalidDisasterCommittee
+        );
+        let config = &mut ctx.accounts.program_config;
+        config.disaster_committee = committee;
+        config.disaster_threshold = threshold;
+        Ok(())
+    }
+
+    /// Sets who may request regulatory disclosures and who must approve them.
+    ///
+    /// # Arguments
+    /// * `regulators` - Regulator keys allowed to request disclosures
+    /// * `committee` - Committee members whose approvals count toward the threshold
+    /// * `threshold` - Approvals needed before a disclosure can run
+    /// * `interval` - Minimum seconds between disclosures by the same regulator
+    pub fn set_disclosure_policy(
+        ctx: Context<UpdateConfig>,
+        regulators: Vec<Pubkey>,
+        committee: Vec<Pubkey>,
+        threshold: u8,
+        interval: i64,
+    ) -> Result<()> {
+        require!(
+            regulators.len() <= MAX_REGULATORS
+                && committee.len() <= MAX_DISCLOSURE_COMMITTEE
+                && threshold > 0
+                && threshold as usize <= committee.len()
+                && interval >= 0
+                && regulators
+                    .iter()
+                    .enumerate()
+                    .all(|(i, regulator)| !regulators[..i].contains(regulator))
+                && committee
+                    .iter()
+                    .enumerate()
+                    .all(|(i, member)| !committee[..i].contains(member)),
+            ErrorCode::InvalidDisclosurePolicy
+        );
+        let config = &mut ctx.accounts.program_config;
+        config.regulators = regulators;
+        config.disclosure_committee = committee;
+        config.disclosure_threshold = threshold;
+        config.disclosure_interval = interval;
+        Ok(())
+    }
+
+    /// Sets the retention bounds for one record type.
+    ///
+    /// # Arguments
+    /// * `record_type` - Record type the policy applies to
+    /// * `min_retention_slots` - Slots a record must be kept before its owner can remove it
+    /// * `max_retention_slots` - Slots after which the sweeper tombstones it; zero disables
+    pub fn set_retention_policy(
+        ctx: Context<UpdateConfig>,
+        record_type: RecordType,
+        min_retention_slots: u64,
+        max_retention_slots: u64,
+    ) -> Result<()> {
+        require!(
+            max_retention_slots == 0 || max_retention_slots >= min_retention_slots,
+            ErrorCode::InvalidRetentionPolicy
+        );
+        let policy = RetentionPolicy {
+            record_type,
+            min_retention_slots,
+            max_retention_slots,
+        };
+        let policies = &mut ctx.accounts.program_config.retention_policies;
+        match policies
+            .iter_mut()
+            .find(|existing| existing.record_type == record_type)
+        {
+            Some(existing) => *existing = policy,
+            None => {
+                require!(
+                    policies.len() < MAX_RETENTION_POLICIES,
+                    ErrorCode::InvalidRetentionPolicy
+                );
+                policies.push(policy);
+            }
+        }
+        Ok(())
+    }
+
+    /// Switches large callback events between program logs and Anchor's event CPI.
+    ///
+    /// Logs can be truncated for large payloads, while event CPIs are recorded as inner
+    /// instructions that indexers can always read. Off by default for existing indexers.
+    ///
+    /// # Arguments
+    /// * `enabled` - Whether to emit through event CPI
+    pub fn set_event_cpi(ctx: Context<UpdateConfig>, enabled: bool) -> Result<()> {
+        ctx.accounts.program_config.event_cpi = enabled;
+        Ok(())
+    }
+
+    /// Switches share callbacks between emitting the full output and emitting only a
+    /// 32-byte commitment to it, with the ciphertexts written to the receiver's
+    /// `SharedRecord` instead.
+    ///
+    /// Shares queued while it is on must pass the `SharedRecord`, and shares queued while
+    /// it is off must not.
+    ///
+    /// # Arguments
+    /// * `enabled` - Whether to deliver shares through `SharedRecord`
+    pub fn set_compact_events(ctx: Context<UpdateConfig>, enabled: bool) -> Result<()> {
+        ctx.accounts.program_config.compact_events = enabled;
+        Ok(())
+    }
+
+    /// Sets the cut of access fees and research payouts that goes to the treasury.
+    ///
+    /// # Arguments
+    /// * `treasury_bps` - Treasury share in basis points; the rest goes to patients
+    pub fn set_fee_split(ctx: Context<UpdateConfig>, treasury_bps: u16) -> Result<()> {
+        require!(treasury_bps <= MAX_BPS, ErrorCode::InvalidFeeSplit);
+        let config = &mut ctx.accounts.program_config;
+        emit!(FeeSplitUpdatedEvent {
+            previous_treasury_bps: config.treasury_bps,
+            treasury_bps,
+        });
+        config.treasury_bps = treasury_bps;
+        Ok(())
+    }
+
+    /// Creates the treasury token account for a mint.
+    ///
+    /// Patients can only charge access fees in mints that have a treasury, so that the
+    /// configured split can always be paid out.
+    pub fn init_treasury(_ctx: Context<InitTreasury>) -> Result<()> {
+        Ok(())
+    }
+
+    /// Sets the subscription mint and tier pricing, creating the treasury for that mint.
+    ///
+    /// # Arguments
+    /// * `tiers` - Price and duration of each tier, indexed by tier number
+    pub fn configure_subscriptions(
+        ctx: Context<ConfigureSubscriptions>,
+        tiers: Vec<SubscriptionTier>,
+    ) -> Result<()> {
+        require!(
+            tiers.len() <= MAX_SUBSCRIPTION_TIERS && tiers.iter().all(|tier| tier.duration > 0),
+            ErrorCode::InvalidSubscription
+        );
+        let config = &mut ctx.accounts.program_config;
+        config.subscription_mint = ctx.accounts.mint.key();
+        config.subscription_tiers = tiers;
+        Ok(())
+    }
+
+    /// Switches shares over to a new circuit version.
+    ///
+    /// The previously active comp def offset stays accepted for `grace_period` seconds so
+    /// that share transactions already built against it are not rejected mid-upgrade.
+    /// Callbacks are never gated, so computations queued before the switch still complete.
+    ///
+    /// # Arguments
+    /// * `version` - New circuit version, must be greater than the current one
+    /// * `comp_def_offset` - Offset of the already initialized comp def for the new circuit
+    /// * `grace_period` - Seconds during which the old offset remains accepted
+    pub fn activate_circuit_version(
+        ctx: Context<ActivateCircuitVersion>,
+        version: u32,
+        comp_def_offset: u32,
+        grace_period: i64,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.program_config;
+        require!(
+            version > config.circuit_version,
+            ErrorCode::InvalidCircuitVersion
+        );
+        require!(
+            comp_def_offset != config.active_share_comp_def && grace_period >= 0,
+            ErrorCode::InvalidCircuitVersion
+        );
+
+        let now = Clock::get()?.unix_timestamp;
+        config.deprecated_share_comp_def = Some(config.active_share_comp_def);
+        config.deprecation_ends_at = now.saturating_add(grace_period);
+        config.active_share_comp_def = comp_def_offset;
+        config.circuit_version = version;
+        Ok(())
+    }
+
     /// Stores encrypted patient medical data on-chain.
     ///
     /// This function stores patient medical information in encrypted form. All data fields
     /// are provided as encrypted 32-byte arrays that can only be decrypted by authorized parties.
     /// The data remains confidential while being stored on the public Solana blockchain.
+    /// The record belongs to the signing `patient`; a separate `payer`, such as the clinic
+    /// onboarding them, may cover its rent without gaining any control over it.
     ///
     /// # Arguments
     /// * `patient_id` - Encrypted unique identifier for the patient
@@ -24,6 +597,8 @@ pub mod share_medical_records {
     /// * `weight` - Encrypted patient weight
     /// * `height` - Encrypted patient height
     /// * `allergies` - Array of encrypted allergy information (up to 5 entries)
+    /// * `enc_pubkey` - x25519 public key the fields were encrypted under
+    /// * `nonce` - Nonce the fields were encrypted with, rejected if the record used it before
     pub fn store_patient_data(
         ctx: Context<StorePatientData>,
         patient_id: [u8; 32],
@@ -33,108 +608,7894 @@ pub mod share_medical_records {
         weight: [u8; 32],
         height: [u8; 32],
         allergies: [[u8; 32]; 5],
+        enc_pubkey: [u8; 32],
+        nonce: u128,
+    ) -> Result<()> {
+        ctx.accounts.program_config.require_store_enabled()?;
+        require_plausible_ciphertexts(
+            &[patient_id, age, gender, blood_type, weight, height],
+            &allergies,
+            ctx.accounts.program_config.record_fields,
+        )?;
+        ctx.accounts.used_nonces.claim(
+            ctx.accounts.patient_data.key(),
+            ctx.bumps.used_nonces,
+            nonce,
+        )?;
+
+        let patient_data = &mut ctx.accounts.patient_data;
+        patient_data.set_ciphertexts(&join_record(
+            [patient_id, age, gender, blood_type, weight, height],
+            allergies,
+        ));
+        patient_data.created_slot = Clock::get()?.slot;
+        patient_data.tombstoned = false;
+        patient_data.patient_authority = ctx.accounts.patient.key();
+        patient_data.enc_pubkey = enc_pubkey;
+        patient_data.nonce = nonce;
+        patient_data.version = 1;
+
+        let event = PatientDataStoredEvent {
+            patient_data: patient_data.key(),
+            patient_authority: patient_data.patient_authority,
+            enc_pubkey,
+            nonce,
+        };
+        ctx.accounts.patient_registry.audit(
+            event.patient_data,
+            ctx.bumps.patient_registry,
+            &event,
+        )?;
+        emit!(event);
+        Ok(())
+    }
+
+    /// Replaces the ciphertexts of the caller's record, e.g. after a new measurement.
+    ///
+    /// The record keeps its original creation slot, so retention still runs from the
+    /// first store. Later shares must pass the key and nonce of the new encryption, so
+    /// `enc_pubkey` replaces the stored key too. `nonce` must not have been used with the
+    /// record before.
+    pub fn update_patient_data(
+        ctx: Context<UpdatePatientData>,
+        patient_id: [u8; 32],
+        age: [u8; 32],
+        gender: [u8; 32],
+        blood_type: [u8; 32],
+        weight: [u8; 32],
+        height: [u8; 32],
+        allergies: [[u8; 32]; 5],
+        enc_pubkey: [u8; 32],
+        nonce: u128,
+    ) -> Result<()> {
+        ctx.accounts.program_config.require_store_enabled()?;
+        ctx.accounts.patient_data.require_live()?;
+        ctx.accounts.patient_data.require_not_in_flight()?;
+        require_plausible_ciphertexts(
+            &[patient_id, age, gender, blood_type, weight, height],
+            &allergies,
+            ctx.accounts.program_config.record_fields,
+        )?;
+        ctx.accounts.used_nonces.claim(
+            ctx.accounts.patient_data.key(),
+            ctx.accounts.used_nonces.bump,
+            nonce,
+        )?;
+
+        let patient_data = &mut ctx.accounts.patient_data;
+        patient_data.set_ciphertexts(&join_record(
+            [patient_id, age, gender, blood_type, weight, height],
+            allergies,
+        ));
+        patient_data.enc_pubkey = enc_pubkey;
+        patient_data.nonce = nonce;
+        patient_data.version = patient_data
+            .version
+            .checked_add(1)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+
+        let event = PatientDataUpdatedEvent {
+            patient_data: patient_data.key(),
+            enc_pubkey,
+            nonce,
+        };
+        ctx.accounts.patient_registry.audit(
+            event.patient_data,
+            ctx.bumps.patient_registry,
+            &event,
+        )?;
+        emit!(event);
+        Ok(())
+    }
+
+    /// Stores the caller's record, or replaces it when `overwrite` is set, so onboarding
+    /// pipelines can retry without first checking whether the record exists.
+    ///
+    /// An existing record is left as it is, without claiming `nonce`, when `overwrite` is
+    /// unset or it already holds these ciphertexts, e.g. because an earlier attempt
+    /// landed. Otherwise a new record is stored as by `store_patient_data` and an existing
+    /// one replaced as by `update_patient_data`; either way its `version` is bumped.
+    ///
+    /// # Arguments
+    /// * `patient_id` ... `enc_pubkey` - As for `store_patient_data`
+    /// * `nonce` - Nonce the fields were encrypted with, rejected if the record used it before
+    /// * `overwrite` - Whether to replace a record that already exists
+    pub fn upsert_patient_data(
+        ctx: Context<UpsertPatientData>,
+        patient_id: [u8; 32],
+        age: [u8; 32],
+        gender: [u8; 32],
+        blood_type: [u8; 32],
+        weight: [u8; 32],
+        height: [u8; 32],
+        allergies: [[u8; 32]; 5],
+        enc_pubkey: [u8; 32],
+        nonce: u128,
+        overwrite: bool,
+    ) -> Result<()> {
+        ctx.accounts.program_config.require_store_enabled()?;
+        let fields = [patient_id, age, gender, blood_type, weight, height];
+        let ciphertexts = join_record(fields, allergies);
+        let exists = ctx.accounts.patient_data.created_slot != 0;
+        if exists && (!overwrite || ctx.accounts.patient_data.holds(&ciphertexts, &enc_pubkey)) {
+            return Ok(());
+        }
+        if exists {
+            ctx.accounts.patient_data.require_live()?;
+            ctx.accounts.patient_data.require_not_in_flight()?;
+        }
+        require_plausible_ciphertexts(
+            &fields,
+            &allergies,
+            ctx.accounts.program_config.record_fields,
+        )?;
+        ctx.accounts.used_nonces.claim(
+            ctx.accounts.patient_data.key(),
+            ctx.bumps.used_nonces,
+            nonce,
+        )?;
+
+        let patient_data = &mut ctx.accounts.patient_data;
+        patient_data.set_ciphertexts(&ciphertexts);
+        patient_data.enc_pubkey = enc_pubkey;
+        patient_data.nonce = nonce;
+        patient_data.version = patient_data
+            .version
+            .checked_add(1)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+
+        let key = patient_data.key();
+        let registry = &mut ctx.accounts.patient_registry;
+        if exists {
+            let event = PatientDataUpdatedEvent {
+                patient_data: key,
+                enc_pubkey,
+                nonce,
+            };
+            registry.audit(key, ctx.bumps.patient_registry, &event)?;
+            emit!(event);
+        } else {
+            patient_data.created_slot = Clock::get()?.slot;
+            patient_data.tombstoned = false;
+            patient_data.patient_authority = ctx.accounts.patient.key();
+            let event = PatientDataStoredEvent {
+                patient_data: key,
+                patient_authority: patient_data.patient_authority,
+                enc_pubkey,
+                nonce,
+            };
+            registry.audit(key, ctx.bumps.patient_registry, &event)?;
+            emit!(event);
+        }
+        Ok(())
+    }
+
+    /// Returns page `page` of the record's access log, oldest share first, for clients to
+    /// read through simulation instead of fetching and decoding the page account.
+    ///
+    /// # Arguments
+    /// * `page` - Page to read; the record's current one is `PatientRegistry::access_log_page`
+    pub fn read_access_log(ctx: Context<ReadAccessLog>, _page: u32) -> Result<Vec<AccessLogEntry>> {
+        Ok(ctx.accounts.access_log_page.entries.clone())
+    }
+
+    /// Registers the off-chain source of a circuit in the config manifest.
+    ///
+    /// Every `init_*_comp_def` instruction reads its circuit source from this manifest, so
+    /// initializing all circuits is a matter of registering each source and then running
+    /// the initializers in manifest order. Sources can be corrected until initialized.
+    ///
+    /// # Arguments
+    /// * `comp_def_offset` - Offset of the computation definition the source belongs to
+    /// * `source_url` - URL of the uploaded `.arcis` circuit
+    /// * `source_hash` - Hash of the circuit file
+    pub fn set_circuit_source(
+        ctx: Context<UpdateConfig>,
+        comp_def_offset: u32,
+        source_url: String,
+        source_hash: [u8; 32],
+    ) -> Result<()> {
+        ctx.accounts
+            .program_config
+            .set_circuit_source(comp_def_offset, source_url, source_hash)
+    }
+
+    /// Retires the circuit at `comp_def_offset` so no new computations are queued against it.
+    ///
+    /// Refuses while the circuit is the active share circuit or while any computation
+    /// queued against it is still waiting for its callback or expiry. This does not close
+    /// the comp def account: it is owned by the Arcium program, which offers no
+    /// instruction to close a computation definition, so the account keeps its rent.
+    ///
+    /// # Arguments
+    /// * `comp_def_offset` - Offset of the computation definition to retire
+    pub fn retire_comp_def(ctx: Context<UpdateConfig>, comp_def_offset: u32) -> Result<()> {
+        ctx.accounts.program_config.retire_circuit(comp_def_offset)
+    }
+
+    /// Sets the token fee receivers pay for each share of the caller's record.
+    ///
+    /// The fee is escrowed when a share is queued and released to the patient's token
+    /// account once the computation succeeds, or refunded to the payer if it fails.
+    ///
+    /// # Arguments
+    /// * `amount` - Fee per share in base units of `fee_mint`; zero disables the fee
+    pub fn set_access_fee(ctx: Context<SetAccessFee>, amount: u64) -> Result<()> {
+        let access_fee = &mut ctx.accounts.access_fee;
+        access_fee.patient = ctx.accounts.patient.key();
+        access_fee.mint = ctx.accounts.fee_mint.key();
+        access_fee.amount = amount;
+        access_fee.bump = ctx.bumps.access_fee;
+        Ok(())
+    }
+
+    /// Stores encrypted patient data submitted by a relayer on the patient's behalf.
+    ///
+    /// The relayer pays rent and fees while the patient only signs an off-chain approval
+    /// over the ciphertexts. The transaction must carry an ed25519 program instruction
+    /// verifying that approval immediately before this one. The record is created at the
+    /// same address as if the patient had called `store_patient_data` themselves.
+    ///
+    /// # Arguments
+    /// * `patient` - Wallet of the patient who owns the record
+    /// * `expires_at` - Unix timestamp after which the signed approval is rejected
+    /// * remaining arguments as in `store_patient_data`
+    pub fn store_patient_data_sponsored(
+        ctx: Context<StorePatientDataSponsored>,
+        patient: Pubkey,
+        expires_at: i64,
+        patient_id: [u8; 32],
+        age: [u8; 32],
+        gender: [u8; 32],
+        blood_type: [u8; 32],
+        weight: [u8; 32],
+        height: [u8; 32],
+        allergies: [[u8; 32]; 5],
+        enc_pubkey: [u8; 32],
+        nonce: u128,
+    ) -> Result<()> {
+        ctx.accounts.program_config.require_store_enabled()?;
+        require_plausible_ciphertexts(
+            &[patient_id, age, gender, blood_type, weight, height],
+            &allergies,
+            ctx.accounts.program_config.record_fields,
+        )?;
+
+        PatientApproval {
+            action: ApprovalAction::StorePatientData,
+            patient,
+            payload_hash: store_payload_hash(
+                &patient_id,
+                &age,
+                &gender,
+                &blood_type,
+                &weight,
+                &height,
+                &allergies,
+                &enc_pubkey,
+                nonce,
+            ),
+            expires_at,
+        }
+        .verify(&ctx.accounts.instructions_sysvar)?;
+        ctx.accounts.used_nonces.claim(
+            ctx.accounts.patient_data.key(),
+            ctx.bumps.used_nonces,
+            nonce,
+        )?;
+
+        let patient_data = &mut ctx.accounts.patient_data;
+        patient_data.set_ciphertexts(&join_record(
+            [patient_id, age, gender, blood_type, weight, height],
+            allergies,
+        ));
+        patient_data.created_slot = Clock::get()?.slot;
+        patient_data.tombstoned = false;
+        patient_data.patient_authority = patient;
+        patient_data.enc_pubkey = enc_pubkey;
+        patient_data.nonce = nonce;
+        patient_data.version = 1;
+
+        let event = PatientDataStoredEvent {
+            patient_data: patient_data.key(),
+            patient_authority: patient_data.patient_authority,
+            enc_pubkey,
+            nonce,
+        };
+        ctx.accounts.patient_registry.audit(
+            event.patient_data,
+            ctx.bumps.patient_registry,
+            &event,
+        )?;
+        emit!(event);
+        Ok(())
+    }
+
+    /// Buys or extends a provider subscription.
+    ///
+    /// While the subscription is active the provider's shares are not charged per-share
+    /// access fees. Buying again extends from the current expiry, or from now if lapsed.
+    ///
+    /// # Arguments
+    /// * `tier` - Index of the configured tier to buy one period of
+    pub fn purchase_subscription(ctx: Context<PurchaseSubscription>, tier: u8) -> Result<()> {
+        let plan = *ctx
+            .accounts
+            .program_config
+            .subscription_tiers
+            .get(tier as usize)
+            .ok_or(ErrorCode::InvalidSubscription)?;
+
+        transfer_checked(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.provider_token_account.to_account_info(),
+                    mint: ctx.accounts.mint.to_account_info(),
+                    to: ctx.accounts.treasury.to_account_info(),
+                    authority: ctx.accounts.provider.to_account_info(),
+                },
+            ),
+            plan.price,
+            ctx.accounts.mint.decimals,
+        )?;
+
+        let clock = ctx.accounts.program_config.expiry_clock()?;
+        let subscription = &mut ctx.accounts.subscription;
+        // An expiry the slot estimate already passed counts as lapsed, like in shares
+        let current = if clock.has_passed(subscription.expires_at, subscription.expires_at_slot) {
+            clock.unix_timestamp
+        } else {
+            subscription.expires_at
+        };
+        subscription.provider = ctx.accounts.provider.key();
+        subscription.tier = tier;
+        subscription.expires_at = current
+            .checked_add(plan.duration)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        subscription.expires_at_slot = clock.slot_at(subscription.expires_at);
+        subscription.bump = ctx.bumps.subscription;
+        Ok(())
+    }
+
+    /// Treats a share whose callback never arrived as failed.
+    ///
+    /// Callable by anyone once the configured computation timeout has passed. Refunds the
+    /// escrowed access fee, if the share carried one, and credits the Arcium fee to the
+    /// payer's refund balance, paying it out right away as far as the refund vault covers
+    /// it. Releases the pending slot and the payer's in-flight slot. A callback arriving
+    /// afterwards is rejected.
+    pub fn expire_pending_share(ctx: Context<ExpirePendingShare>) -> Result<()> {
+        let pending_share = &ctx.accounts.pending_share;
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            now >= pending_share
+                .queued_at
+                .saturating_add(ctx.accounts.program_config.computation_timeout),
+            ErrorCode::ComputationNotTimedOut
+        );
+        let comp_def_offset = ctx.accounts.callback_guard.comp_def_offset;
+        ctx.accounts.callback_guard.consume(comp_def_offset)?;
+        ctx.accounts
+            .used_offsets
+            .release(pending_share.computation_offset);
+
+        if pending_share.escrowed_fee > 0 {
+            let (
+                Some(fee_escrow),
+                Some(payer_fee_account),
+                Some(fee_mint),
+                Some(fee_authority),
+                Some(token_program),
+            ) = (
+                &ctx.accounts.fee_escrow,
+                &ctx.accounts.payer_fee_account,
+                &ctx.accounts.fee_mint,
+                &ctx.accounts.fee_authority,
+                &ctx.accounts.token_program,
+            )
+            else {
+                return err!(ErrorCode::AccessFeeAccountsMissing);
+            };
+            release_fee_escrow(
+                fee_escrow,
+                payer_fee_account,
+                None,
+                fee_mint,
+                fee_authority,
+                &ctx.accounts.payer,
+                token_program,
+                true,
+            )?;
+        }
+
+        ctx.accounts
+            .program_config
+            .record_completed(comp_def_offset)?;
+        ctx.accounts.patient_data.end_computation();
+        ctx.accounts
+            .refund_balance
+            .credit(pending_share.arcium_fee)?;
+        let paid_out = ctx.accounts.refund_balance.settle_from(
+            &ctx.accounts.refund_vault.to_account_info(),
+            &ctx.accounts.payer.to_account_info(),
+        )?;
+        emit!(ComputationFeeRefundCreditedEvent {
+            payer: pending_share.payer,
+            computation_offset: pending_share.computation_offset,
+            lamports: pending_share.arcium_fee,
+            paid_out,
+            timed_out: true,
+        });
+        Ok(())
+    }
+
+    /// Adds lamports to the pool refund balances are paid out of.
+    ///
+    /// # Arguments
+    /// * `lamports` - Amount to deposit
+    pub fn fund_refund_vault(ctx: Context<FundRefundVault>, lamports: u64) -> Result<()> {
+        ctx.accounts.refund_vault.bump = ctx.bumps.refund_vault;
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.funder.to_account_info(),
+                    to: ctx.accounts.refund_vault.to_account_info(),
+                },
+            ),
+            lamports,
+        )
+    }
+
+    /// Pays out the caller's accumulated computation-fee refunds from the refund vault.
+    pub fn claim_refund(ctx: Context<ClaimRefund>) -> Result<()> {
+        let lamports = ctx.accounts.refund_balance.lamports;
+        require!(lamports > 0, ErrorCode::NothingToRefund);
+
+        let vault = ctx.accounts.refund_vault.to_account_info();
+        let rent_exempt = Rent::get()?.minimum_balance(vault.data_len());
+        require!(
+            vault.lamports().saturating_sub(rent_exempt) >= lamports,
+            ErrorCode::RefundVaultInsufficient
+        );
+
+        **vault.try_borrow_mut_lamports()? -= lamports;
+        **ctx.accounts.payer.try_borrow_mut_lamports()? += lamports;
+        ctx.accounts.refund_balance.lamports = 0;
+        Ok(())
+    }
+
+    pub fn init_share_patient_data_comp_def(
+        ctx: Context<InitSharePatientDataCompDef>,
+    ) -> Result<()> {
+        let source = ctx
+            .accounts
+            .program_config
+            .circuit_source(COMP_DEF_OFFSET_SHARE_PATIENT_DATA)?;
+        init_comp_def(ctx.accounts, true, 0, Some(source), None)?;
+        ctx.accounts
+            .program_config
+            .mark_circuit_initialized(COMP_DEF_OFFSET_SHARE_PATIENT_DATA)
+    }
+
+    /// Initiates confidential sharing of patient data with a specified receiver.
+    ///
+    /// This function triggers an MPC computation that re-encrypts the patient's medical data
+    /// for a specific receiver. The receiver will be able to decrypt the data using their
+    /// private key, while the data remains encrypted for everyone else. The original
+    /// stored data is not modified and remains encrypted for the original owner.
+    /// If the patient has set an access fee, the payer's tokens are escrowed until the
+    /// callback settles the share.
+    /// The payer must be the record's patient authority or pass a `consent_grant` of theirs
+    /// covering the structured record. Alternatively a relayer may pay, passing
+    /// `approval_expires_at` and, immediately before this instruction, an ed25519 program
+    /// instruction verifying the patient's off-chain `PatientApproval` of the share. The
+    /// approval covers the record's current audit sequence, so it is used once.
+    /// When the accounts and approval do not fit one transaction, stage the arguments with
+    /// `prepare_share` and queue them with `execute_share` instead.
+    ///
+    /// # Arguments
+    /// * `receiver` - Public key of the authorized recipient
+    /// * `receiver_nonce` - Cryptographic nonce for the receiver's encryption, rejected if the
+    ///   record used it before
+    /// * `sender_pub_key` - Sender's public key for the operation, the record's `enc_pubkey`
+    /// * `nonce` - Cryptographic nonce for the sender's encryption
+    /// * `cluster_hint` - Approved cluster to route the computation to instead of the MXE default
+    /// * `approval_expires_at` - Expiry of the patient's signed approval, for relayed shares
+    pub fn share_patient_data(
+        ctx: Context<SharePatientData>,
+        computation_offset: u64,
+        receiver: [u8; 32],
+        receiver_nonce: u128,
+        sender_pub_key: [u8; 32],
+        nonce: u128,
+        cluster_hint: Option<u32>,
+        approval_expires_at: Option<i64>,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.staged_share.is_none(),
+            ErrorCode::StagedShareMismatch
+        );
+        queue_share(
+            ctx,
+            computation_offset,
+            ShareParams {
+                receiver,
+                receiver_nonce,
+                sender_pub_key,
+                nonce,
+                cluster_hint,
+            },
+            approval_expires_at.map(|expires_at| ShareApproval::Signed { expires_at }),
+        )
+    }
+
+    /// Stages the arguments of a share for `execute_share`, the first half of a share
+    /// split across two transactions.
+    ///
+    /// A relayed share verifies the patient's signed approval here, so the ed25519
+    /// instruction travels without the share's accounts; it then holds only until the
+    /// next audited action on the record. Authorization by control of or consent to the
+    /// record is checked when the share executes.
+    ///
+    /// # Arguments
+    /// * `computation_offset` - Offset the share will be queued at
+    /// * `receiver` - Public key of the authorized recipient
+    /// * `receiver_nonce` - Cryptographic nonce for the receiver's encryption
+    /// * `sender_pub_key` - Sender's public key for the operation, the record's `enc_pubkey`
+    /// * `nonce` - Cryptographic nonce for the sender's encryption
+    /// * `cluster_hint` - Approved cluster to route the computation to instead of the MXE default
+    /// * `approval_expires_at` - Expiry of the patient's signed approval, for relayed shares
+    #[allow(clippy::too_many_arguments)]
+    pub fn prepare_share(
+        ctx: Context<PrepareShare>,
+        computation_offset: u64,
+        receiver: [u8; 32],
+        receiver_nonce: u128,
+        sender_pub_key: [u8; 32],
+        nonce: u128,
+        cluster_hint: Option<u32>,
+        approval_expires_at: Option<i64>,
+    ) -> Result<()> {
+        let key_epoch =
+            ProviderProfile::key_epoch_of(&ctx.accounts.provider_profile, ctx.accounts.payer.key)?;
+        let approved_at = match approval_expires_at {
+            Some(expires_at) => {
+                let audit_sequence =
+                    PatientRegistry::audit_sequence(&ctx.accounts.patient_registry)?;
+                PatientApproval {
+                    action: ApprovalAction::SharePatientData,
+                    patient: ctx.accounts.patient_data.patient_authority,
+                    payload_hash: share_payload_hash(
+                        &receiver,
+                        receiver_nonce,
+                        &sender_pub_key,
+                        nonce,
+                        audit_sequence,
+                        key_epoch,
+                    ),
+                    expires_at,
+                }
+                .verify(&ctx.accounts.instructions_sysvar)?;
+                Some(audit_sequence)
+            }
+            None => None,
+        };
+
+        let staged_share = &mut ctx.accounts.staged_share;
+        staged_share.payer = ctx.accounts.payer.key();
+        staged_share.patient_data = ctx.accounts.patient_data.key();
+        staged_share.computation_offset = computation_offset;
+        staged_share.params = ShareParams {
+            receiver,
+            receiver_nonce,
+            sender_pub_key,
+            nonce,
+            cluster_hint,
+        };
+        staged_share.approved_at = approved_at;
+        staged_share.bump = ctx.bumps.staged_share;
+        staged_share.key_epoch = key_epoch;
+
+        emit!(ShareStagedEvent {
+            staged_share: staged_share.key(),
+            payer: staged_share.payer,
+            patient_data: staged_share.patient_data,
+            computation_offset,
+        });
+        Ok(())
+    }
+
+    /// Queues a share staged by `prepare_share`, closing the staging account.
+    ///
+    /// Takes the accounts of `share_patient_data` plus the `staged_share`, and checks
+    /// the share the same way.
+    ///
+    /// # Arguments
+    /// * `computation_offset` - Offset the share was staged at
+    /// * `receiver` - Receiver the share was staged for, which seeds the pending share
+    pub fn execute_share(
+        ctx: Context<SharePatientData>,
+        computation_offset: u64,
+        receiver: [u8; 32],
+    ) -> Result<()> {
+        let staged_share = ctx
+            .accounts
+            .staged_share
+            .as_deref()
+            .ok_or(ErrorCode::StagedShareMismatch)?;
+        let params = staged_share.params;
+        let approval = staged_share
+            .approved_at
+            .map(|audit_sequence| ShareApproval::Staged { audit_sequence });
+        require!(params.receiver == receiver, ErrorCode::StagedShareMismatch);
+        require!(
+            ProviderProfile::key_epoch_of(&ctx.accounts.provider_profile, ctx.accounts.payer.key)?
+                == staged_share.key_epoch,
+            ErrorCode::ProviderKeyRotated
+        );
+        queue_share(ctx, computation_offset, params, approval)
+    }
+
+    /// Closes a staged share that will not be executed, returning its rent to the payer.
+    pub fn discard_staged_share(_ctx: Context<DiscardStagedShare>) -> Result<()> {
+        Ok(())
+    }
+
+    /// Handles the result of the patient data sharing MPC computation.
+    ///
+    /// This callback processes the re-encrypted patient data that has been prepared for
+    /// the specified receiver. It emits an event containing all the medical data fields
+    /// encrypted specifically for the receiver's public key, or, with compact events on,
+    /// writes them to the receiver's `SharedRecord` and emits only a commitment to them.
+    /// A failed computation is
+    /// reported through `SharePatientDataAbortedEvent` so its pending slot is still released,
+    /// and its Arcium fee is credited to the payer's refund balance.
+    #[arcium_callback(encrypted_ix = "share_patient_data")]
+    pub fn share_patient_data_callback(
+        ctx: Context<SharePatientDataCallback>,
+        output: ComputationOutputs<SharePatientDataOutput>,
+    ) -> Result<()> {
+        let comp_def_offset = ctx.accounts.callback_guard.comp_def_offset;
+        let computation_offset = ctx.accounts.callback_guard.consume(comp_def_offset)?;
+        verify_callback_invocation(
+            &ctx.accounts.instructions_sysvar,
+            &ctx.accounts.comp_def_account.key(),
+            computation_offset,
+        )?;
+        ctx.accounts
+            .program_config
+            .record_completed(comp_def_offset)?;
+        ctx.accounts.patient_data.end_computation();
+        ctx.accounts
+            .used_offsets
+            .release(ctx.accounts.pending_share.computation_offset);
+
+        let o = match output {
+            ComputationOutputs::Success(SharePatientDataOutput { field_0 }) => field_0,
+            _ => {
+                settle_access_fee(ctx.accounts, true)?;
+
+                let pending_share = &ctx.accounts.pending_share;
+                ctx.accounts
+                    .refund_balance
+                    .credit(pending_share.arcium_fee)?;
+                emit!(ComputationFeeRefundCreditedEvent {
+                    payer: pending_share.payer,
+                    computation_offset: pending_share.computation_offset,
+                    lamports: pending_share.arcium_fee,
+                    paid_out: 0,
+                    timed_out: false,
+                });
+                emit!(SharePatientDataAbortedEvent {
+                    comp_def_offset,
+                    computation_offset: pending_share.computation_offset,
+                });
+                let payer = ctx.accounts.payer.to_account_info();
+                return ctx.accounts.share_receipt.close(payer);
+            }
+        };
+        settle_access_fee(ctx.accounts, false)?;
+
+        let slot = Clock::get()?.slot;
+        let nonce = o.nonce.to_le_bytes();
+        ctx.accounts.share_receipt.seal(
+            slot,
+            share_output_hash(&o.encryption_key, &nonce, &o.ciphertexts),
+        );
+        Inbox::notify(
+            &ctx.accounts.inbox,
+            NotificationKind::ShareCompleted,
+            ctx.accounts.share_receipt.key(),
+        )?;
+        if let Some(shared_record) = ctx.accounts.shared_record.as_deref_mut() {
+            shared_record.deliver(
+                ctx.accounts.pending_share.computation_offset,
+                slot,
+                o.encryption_key,
+                nonce,
+                &o.ciphertexts,
+            )?;
+            let event = ReceivedPatientDataCompactEvent {
+                patient_record: ctx.accounts.pending_share.patient_data,
+                shared_record: shared_record.key(),
+                computation_offset: ctx.accounts.pending_share.computation_offset,
+                slot,
+                output_hash: ctx.accounts.share_receipt.output_hash,
+            };
+            if ctx.accounts.program_config.event_cpi {
+                emit_cpi!(event);
+            } else {
+                emit!(event);
+            }
+            return Ok(());
+        }
+        let ([patient_id, age, gender, blood_type, weight, height], allergies) =
+            split_record_output(&o.ciphertexts)?;
+        let event = ReceivedPatientDataEvent {
+            patient_record: ctx.accounts.pending_share.patient_data,
+            receiver_enc_key: o.encryption_key,
+            computation_offset: ctx.accounts.pending_share.computation_offset,
+            slot,
+            nonce,
+            patient_id,
+            age,
+            gender,
+            blood_type,
+            weight,
+            height,
+            allergies,
+        };
+        if ctx.accounts.program_config.event_cpi {
+            emit_cpi!(event);
+        } else {
+            emit!(event);
+        }
+        Ok(())
+    }
+
+    /// Opts the caller's record in or out of research cohorts.
+    ///
+    /// Opting in publishes the x25519 public key and nonce the record was encrypted with,
+    /// which lets the MPC cluster include it in `cohort_query` computations. Neither value
+    /// allows anyone but the cluster to read the record.
+    ///
+    /// # Arguments
+    /// * `opted_in` - Whether the record may be included in cohorts
+    /// * `enc_pubkey` - Public key the record's ciphertexts were encrypted under
+    /// * `nonce` - Nonce the record's ciphertexts were encrypted with
+    pub fn set_research_opt_in(
+        ctx: Context<SetResearchOptIn>,
+        opted_in: bool,
+        enc_pubkey: [u8; 32],
+        nonce: u128,
+    ) -> Result<()> {
+        let research_opt_in = &mut ctx.accounts.research_opt_in;
+        research_opt_in.patient = ctx.accounts.patient.key();
+        research_opt_in.opted_in = opted_in;
+        research_opt_in.enc_pubkey = enc_pubkey;
+        research_opt_in.nonce = nonce;
+        research_opt_in.bump = ctx.bumps.research_opt_in;
+        Ok(())
+    }
+
+    /// Deposits tokens that will compensate the patients of one cohort query.
+    ///
+    /// # Arguments
+    /// * `query_id` - Researcher-chosen identifier of the query
+    /// * `amount` - Tokens to split across the cohort once the query succeeds
+    pub fn create_research_escrow(
+        ctx: Context<CreateResearchEscrow>,
+        query_id: u64,
+        amount: u64,
+    ) -> Result<()> {
+        require!(amount > 0, ErrorCode::InvalidEscrowAmount);
+
+        transfer_checked(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.researcher_token_account.to_account_info(),
+                    mint: ctx.accounts.mint.to_account_info(),
+                    to: ctx.accounts.escrow_vault.to_account_info(),
+                    authority: ctx.accounts.researcher.to_account_info(),
+                },
+            ),
+            amount,
+            ctx.accounts.mint.decimals,
+        )?;
+
+        let research_escrow = &mut ctx.accounts.research_escrow;
+        research_escrow.researcher = ctx.accounts.researcher.key();
+        research_escrow.mint = ctx.accounts.mint.key();
+        research_escrow.query_id = query_id;
+        research_escrow.amount = amount;
+        research_escrow.status = EscrowStatus::Open;
+        research_escrow.bump = ctx.bumps.research_escrow;
+        Ok(())
+    }
+
+    /// Returns an escrow that has not been used for a query to the researcher.
+    pub fn cancel_research_escrow(ctx: Context<CancelResearchEscrow>) -> Result<()> {
+        require!(
+            ctx.accounts.research_escrow.status == EscrowStatus::Open,
+            ErrorCode::EscrowNotOpen
+        );
+
+        let signer_seeds: &[&[&[u8]]] = &[&[FEE_AUTHORITY_SEED, &[ctx.bumps.fee_authority]]];
+        transfer_checked(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.escrow_vault.to_account_info(),
+                    mint: ctx.accounts.mint.to_account_info(),
+                    to: ctx.accounts.researcher_token_account.to_account_info(),
+                    authority: ctx.accounts.fee_authority.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            ctx.accounts.escrow_vault.amount,
+            ctx.accounts.mint.decimals,
+        )?;
+        close_account(CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            CloseAccount {
+                account: ctx.accounts.escrow_vault.to_account_info(),
+                destination: ctx.accounts.researcher.to_account_info(),
+                authority: ctx.accounts.fee_authority.to_account_info(),
+            },
+            signer_seeds,
+        ))
+    }
+
+    pub fn init_cohort_query_comp_def(ctx: Context<InitCohortQueryCompDef>) -> Result<()> {
+        let source = ctx
+            .accounts
+            .program_config
+            .circuit_source(COMP_DEF_OFFSET_COHORT_QUERY)?;
+        init_comp_def(ctx.accounts, true, 0, Some(source), None)?;
+        ctx.accounts
+            .program_config
+            .mark_circuit_initialized(COMP_DEF_OFFSET_COHORT_QUERY)
+    }
+
+    /// Runs a paid cohort query over opted-in patient records.
+    ///
+    /// The cohort is passed in `remaining_accounts` as `COHORT_SIZE` groups of (patient
+    /// record, research opt-in, payout token account). The MPC cluster counts the records
+    /// matching the criteria and returns the count encrypted for the researcher. On
+    /// success the treasury takes its cut and the rest of the escrow is split evenly
+    /// across the cohort; on failure it is refunded. The cohort, its opt-ins and the
+    /// circuit version are recorded in a `CohortSnapshot` so the result can be audited.
+    ///
+    /// # Arguments
+    /// * `researcher_pub_key` - Researcher's x25519 key the count is encrypted for
+    /// * `researcher_nonce` - Nonce for the result encryption
+    /// * `min_age` / `max_age` - Inclusive age range to match
+    /// * `blood_type` - Blood type to match, or `u8::MAX` for any
+    pub fn cohort_query(
+        ctx: Context<CohortQuery>,
+        computation_offset: u64,
+        researcher_pub_key: [u8; 32],
+        researcher_nonce: u128,
+        min_age: u8,
+        max_age: u8,
+        blood_type: u8,
+    ) -> Result<()> {
+        ctx.accounts.program_config.require_compute_enabled()?;
+        require!(
+            ctx.accounts.research_escrow.status == EscrowStatus::Open,
+            ErrorCode::EscrowNotOpen
+        );
+
+        let (member_args, payout_accounts, members) =
+            cohort_member_arguments(ctx.remaining_accounts, &ctx.accounts.mint.key())?;
+        let args = cohort_query_args(
+            researcher_pub_key,
+            researcher_nonce,
+            min_age,
+            max_age,
+            blood_type,
+            member_args,
+        );
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+        ctx.accounts.research_escrow.status = EscrowStatus::Pending;
+        ctx.accounts
+            .program_config
+            .record_queued(COMP_DEF_OFFSET_COHORT_QUERY)?;
+        ctx.accounts.used_offsets.claim(
+            ctx.accounts.researcher.key(),
+            ctx.bumps.used_offsets,
+            computation_offset,
+            &ctx.accounts.program_config,
+        )?;
+        ctx.accounts.callback_guard.expect(
+            computation_offset,
+            COMP_DEF_OFFSET_COHORT_QUERY,
+            ctx.bumps.callback_guard,
+        );
+
+        let snapshot = &mut ctx.accounts.cohort_snapshot;
+        snapshot.research_escrow = ctx.accounts.research_escrow.key();
+        snapshot.researcher = ctx.accounts.researcher.key();
+        snapshot.query_id = ctx.accounts.research_escrow.query_id;
+        snapshot.computation_offset = computation_offset;
+        snapshot.comp_def_offset = COMP_DEF_OFFSET_COHORT_QUERY;
+        snapshot.circuit_source_hash = ctx
+            .accounts
+            .program_config
+            .circuit_source_hash(COMP_DEF_OFFSET_COHORT_QUERY)?;
+        snapshot.circuit_version = ctx.accounts.program_config.circuit_version;
+        snapshot.min_age = min_age;
+        snapshot.max_age = max_age;
+        snapshot.blood_type = blood_type;
+        snapshot.members = members;
+        let clock = Clock::get()?;
+        snapshot.queued_slot = clock.slot;
+        snapshot.queued_at = clock.unix_timestamp;
+        snapshot.bump = ctx.bumps.cohort_snapshot;
+
+        let mut callback_accounts = vec![
+            CallbackAccount {
+                pubkey: ctx.accounts.program_config.key(),
+                is_writable: true,
+            },
+            CallbackAccount {
+                pubkey: ctx.accounts.callback_guard.key(),
+                is_writable: true,
+            },
+            CallbackAccount {
+                pubkey: ctx.accounts.research_escrow.key(),
+                is_writable: true,
+            },
+            CallbackAccount {
+                pubkey: ctx.accounts.escrow_vault.key(),
+                is_writable: true,
+            },
+            CallbackAccount {
+                pubkey: ctx.accounts.researcher_token_account.key(),
+                is_writable: true,
+            },
+            CallbackAccount {
+                pubkey: ctx.accounts.treasury.key(),
+                is_writable: true,
+            },
+            CallbackAccount {
+                pubkey: ctx.accounts.researcher.key(),
+                is_writable: true,
+            },
+            CallbackAccount {
+                pubkey: ctx.accounts.mint.key(),
+                is_writable: false,
+            },
+            CallbackAccount {
+                pubkey: ctx.accounts.fee_authority.key(),
+                is_writable: false,
+            },
+            CallbackAccount {
+                pubkey: ctx.accounts.token_program.key(),
+                is_writable: false,
+            },
+            CallbackAccount {
+                pubkey: ctx.accounts.cohort_snapshot.key(),
+                is_writable: true,
+            },
+            CallbackAccount {
+                pubkey: ctx.accounts.used_offsets.key(),
+                is_writable: true,
+            },
+        ];
+        callback_accounts.extend(payout_accounts);
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![CohortQueryCallback::callback_ix(&callback_accounts)],
+        )?;
+        Ok(())
+    }
+
+    /// Delivers the encrypted cohort count and settles the research escrow.
+    ///
+    /// The cohort's payout token accounts arrive as `remaining_accounts`, in the order
+    /// the members were passed to `cohort_query`.
+    #[arcium_callback(encrypted_ix = "cohort_query")]
+    pub fn cohort_query_callback<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CohortQueryCallback<'info>>,
+        output: ComputationOutputs<CohortQueryOutput>,
+    ) -> Result<()> {
+        let computation_offset = ctx
+            .accounts
+            .callback_guard
+            .consume(COMP_DEF_OFFSET_COHORT_QUERY)?;
+        verify_callback_invocation(
+            &ctx.accounts.instructions_sysvar,
+            &ctx.accounts.comp_def_account.key(),
+            computation_offset,
+        )?;
+        ctx.accounts.used_offsets.release(computation_offset);
+        ctx.accounts
+            .program_config
+            .record_completed(COMP_DEF_OFFSET_COHORT_QUERY)?;
+
+        let o = match output {
+            ComputationOutputs::Success(CohortQueryOutput { field_0 }) => field_0,
+            _ => return settle_research_escrow(ctx.accounts, ctx.remaining_accounts, true),
+        };
+
+        let slot = Clock::get()?.slot;
+        let snapshot = &mut ctx.accounts.cohort_snapshot;
+        snapshot.completed_slot = slot;
+        snapshot.result_enc_key = o.encryption_key;
+        snapshot.result_nonce = o.nonce.to_le_bytes();
+        snapshot.result = o.ciphertexts[0];
+
+        emit!(CohortQueryResultEvent {
+            research_escrow: ctx.accounts.research_escrow.key(),
+            receiver_enc_key: o.encryption_key,
+            slot,
+            nonce: o.nonce.to_le_bytes(),
+            count: o.ciphertexts[0],
+        });
+        settle_research_escrow(ctx.accounts, ctx.remaining_accounts, false)
+    }
+
+    /// Refunds a research escrow whose cohort query was never called back.
+    ///
+    /// Callable by anyone once the configured computation timeout has passed since the
+    /// query was queued. Consumes its callback guard so a late callback is rejected,
+    /// returns the whole vault to the researcher and closes the cohort snapshot, which
+    /// recorded a query that never ran.
+    pub fn expire_research_escrow(ctx: Context<ExpireResearchEscrow>) -> Result<()> {
+        require!(
+            ctx.accounts.research_escrow.status == EscrowStatus::Pending,
+            ErrorCode::EscrowNotPending
+        );
+        require!(
+            Clock::get()?.unix_timestamp
+                >= ctx
+                    .accounts
+                    .cohort_snapshot
+                    .queued_at
+                    .saturating_add(ctx.accounts.program_config.computation_timeout),
+            ErrorCode::ComputationNotTimedOut
+        );
+        let computation_offset = ctx
+            .accounts
+            .callback_guard
+            .consume(COMP_DEF_OFFSET_COHORT_QUERY)?;
+        ctx.accounts.used_offsets.release(computation_offset);
+        ctx.accounts
+            .program_config
+            .record_completed(COMP_DEF_OFFSET_COHORT_QUERY)?;
+
+        let amount = ctx.accounts.escrow_vault.amount;
+        let signer_seeds: &[&[&[u8]]] = &[&[FEE_AUTHORITY_SEED, &[ctx.bumps.fee_authority]]];
+        if amount > 0 {
+            transfer_checked(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    TransferChecked {
+                        from: ctx.accounts.escrow_vault.to_account_info(),
+                        mint: ctx.accounts.mint.to_account_info(),
+                        to: ctx.accounts.researcher_token_account.to_account_info(),
+                        authority: ctx.accounts.fee_authority.to_account_info(),
+                    },
+                    signer_seeds,
+                ),
+                amount,
+                ctx.accounts.mint.decimals,
+            )?;
+        }
+        close_account(CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            CloseAccount {
+                account: ctx.accounts.escrow_vault.to_account_info(),
+                destination: ctx.accounts.researcher.to_account_info(),
+                authority: ctx.accounts.fee_authority.to_account_info(),
+            },
+            signer_seeds,
+        ))?;
+
+        ctx.accounts.research_escrow.status = EscrowStatus::Refunded;
+        emit!(ResearchEscrowSettledEvent {
+            research_escrow: ctx.accounts.research_escrow.key(),
+            amount,
+            treasury_amount: 0,
+            recipients: 0,
+            refunded: true,
+        });
+        Ok(())
+    }
+
+    /// Registers a provider in the registry, or re-registers a revoked one.
+    ///
+    /// Only registrars listed in the program config may onboard providers.
+    ///
+    /// # Arguments
+    /// * `provider` - Wallet of the provider being registered
+    /// * `role` - Role the provider is vetted for
+    pub fn register_provider(
+        ctx: Context<RegisterProvider>,
+        provider: Pubkey,
+        role: ProviderRole,
+    ) -> Result<()> {
+        let profile = &mut ctx.accounts.provider_profile;
+        require!(!profile.active, ErrorCode::ProviderAlreadyRegistered);
+        profile.provider = provider;
+        profile.role = role;
+        profile.registrar = ctx.accounts.registrar.key();
+        profile.active = true;
+        profile.registered_at = Clock::get()?.unix_timestamp;
+        profile.bump = ctx.bumps.provider_profile;
+
+        emit!(ProviderRegisteredEvent {
+            provider,
+            role,
+            registrar: profile.registrar,
+        });
+        Ok(())
+    }
+
+    /// Revokes a provider's registration, keeping the profile for the audit trail.
+    pub fn revoke_provider(ctx: Context<UpdateProvider>) -> Result<()> {
+        let profile = &mut ctx.accounts.provider_profile;
+        require!(profile.active, ErrorCode::ProviderNotRegistered);
+        profile.active = false;
+        profile.registrar = ctx.accounts.registrar.key();
+
+        emit!(ProviderRevokedEvent {
+            provider: profile.provider,
+            registrar: profile.registrar,
+        });
+        Ok(())
+    }
+
+    /// Changes the role of a registered provider.
+    ///
+    /// # Arguments
+    /// * `role` - New role
+    pub fn update_provider_role(ctx: Context<UpdateProvider>, role: ProviderRole) -> Result<()> {
+        let profile = &mut ctx.accounts.provider_profile;
+        require!(profile.active, ErrorCode::ProviderNotRegistered);
+        let previous_role = profile.role;
+        profile.role = role;
+        profile.registrar = ctx.accounts.registrar.key();
+
+        emit!(ProviderRoleUpdatedEvent {
+            provider: profile.provider,
+            previous_role,
+            role,
+            registrar: profile.registrar,
+        });
+        Ok(())
+    }
+
+    /// Attests the first x25519 key a registered provider receives shares with; later keys
+    /// replace it through `rotate_provider_enc_key`.
+    ///
+    /// Shares a provider makes under a patient's consent must be encrypted for this key.
+    ///
+    /// # Arguments
+    /// * `enc_pubkey` - Provider's x25519 public key, verified out of band by the registrar
+    pub fn attest_provider_enc_key(
+        ctx: Context<UpdateProvider>,
+        enc_pubkey: [u8; 32],
+    ) -> Result<()> {
+        require_valid_x25519_key(&enc_pubkey)?;
+        let profile = &mut ctx.accounts.provider_profile;
+        require!(profile.active, ErrorCode::ProviderNotRegistered);
+        require!(
+            profile.enc_pubkey == [0; 32],
+            ErrorCode::EncKeyAlreadyAttested
+        );
+        profile.enc_pubkey = enc_pubkey;
+        profile.registrar = ctx.accounts.registrar.key();
+
+        emit!(ProviderEncKeyAttestedEvent {
+            provider: profile.provider,
+            enc_pubkey,
+            registrar: profile.registrar,
+        });
+        Ok(())
+    }
+
+    /// Replaces the attested x25519 key of a staff member whose organization rotated it.
+    ///
+    /// Consents are granted to the provider's wallet, not their key, so every consent
+    /// they hold carries over without the patient re-approving it. Consented shares are
+    /// checked against the attested key when queued, so from this instruction on none can
+    /// be encrypted for the previous key. The rotation also starts a new key epoch, which
+    /// invalidates shares the patient approved or that were staged by the provider under
+    /// the previous one. Shares already queued still deliver to the previous key.
+    ///
+    /// # Arguments
+    /// * `enc_pubkey` - Provider's new x25519 public key, verified out of band by the
+    ///   registrar
+    pub fn rotate_provider_enc_key(
+        ctx: Context<UpdateProvider>,
+        enc_pubkey: [u8; 32],
+    ) -> Result<()> {
+        require_valid_x25519_key(&enc_pubkey)?;
+        let profile = &mut ctx.accounts.provider_profile;
+        require!(profile.active, ErrorCode::ProviderNotRegistered);
+        let previous_enc_pubkey = profile.enc_pubkey;
+        require!(
+            previous_enc_pubkey != [0; 32] && previous_enc_pubkey != enc_pubkey,
+            ErrorCode::InvalidKeyRotation
+        );
+        profile.enc_pubkey = enc_pubkey;
+        profile.key_epoch = profile
+            .key_epoch
+            .checked_add(1)
+            .ok_or(ErrorCode::InvalidKeyRotation)?;
+        profile.registrar = ctx.accounts.registrar.key();
+
+        emit!(ProviderEncKeyRotatedEvent {
+            provider: profile.provider,
+            previous_enc_pubkey,
+            enc_pubkey,
+            key_epoch: profile.key_epoch,
+            registrar: profile.registrar,
+        });
+        Ok(())
+    }
+
+    /// Files a court-ordered request to disclose one record to the calling regulator.
+    ///
+    /// # Arguments
+    /// * `court_order_hash` - Hash of the court order backing the request
+    pub fn request_disclosure(
+        ctx: Context<RequestDisclosure>,
+        court_order_hash: [u8; 32],
+    ) -> Result<()> {
+        let request = &mut ctx.accounts.disclosure_request;
+        request.regulator = ctx.accounts.regulator.key();
+        request.patient_data = ctx.accounts.patient_data.key();
+        request.court_order_hash = court_order_hash;
+        request.approvals = Vec::new();
+        request.status = DisclosureStatus::Requested;
+        request.requested_at = Clock::get()?.unix_timestamp;
+        request.bump = ctx.bumps.disclosure_request;
+
+        emit!(DisclosureRequestedEvent {
+            disclosure_request: request.key(),
+            regulator: request.regulator,
+            patient_data: request.patient_data,
+            court_order_hash,
+        });
+        Ok(())
+    }
+
+    /// Records a disclosure committee member's approval of a pending request.
+    pub fn approve_disclosure(ctx: Context<ApproveDisclosure>) -> Result<()> {
+        let approver = ctx.accounts.approver.key();
+        let request = &mut ctx.accounts.disclosure_request;
+        require!(
+            request.status == DisclosureStatus::Requested,
+            ErrorCode::InvalidDisclosureStatus
+        );
+        require!(
+            !request.approvals.contains(&approver),
+            ErrorCode::DisclosureAlreadyApproved
+        );
+        request.approvals.push(approver);
+
+        emit!(DisclosureApprovedEvent {
+            disclosure_request: request.key(),
+            approver,
+            approvals: request.approvals.len() as u8,
+        });
+        Ok(())
+    }
+
+    pub fn init_regulatory_disclosure_comp_def(
+        ctx: Context<InitRegulatoryDisclosureCompDef>,
+    ) -> Result<()> {
+        let source = ctx
+            .accounts
+            .program_config
+            .circuit_source(COMP_DEF_OFFSET_REGULATORY_DISCLOSURE)?;
+        init_comp_def(ctx.accounts, true, 0, Some(source), None)?;
+        ctx.accounts
+            .program_config
+            .mark_circuit_initialized(COMP_DEF_OFFSET_REGULATORY_DISCLOSURE)
+    }
+
+    /// Re-encrypts a record for the regulator once the committee has approved the request.
+    ///
+    /// Only approvals from current committee members count toward the threshold. Each
+    /// regulator may queue at most one disclosure per configured interval. The record is
+    /// read under the key and nonce it was stored with.
+    ///
+    /// # Arguments
+    /// * `regulator_pub_key` - Regulator's x25519 key the record is re-encrypted for
+    /// * `regulator_nonce` - Nonce for the output encryption
+    pub fn regulatory_disclosure(
+        ctx: Context<RegulatoryDisclosure>,
+        computation_offset: u64,
+        regulator_pub_key: [u8; 32],
+        regulator_nonce: u128,
+    ) -> Result<()> {
+        let config = &ctx.accounts.program_config;
+        config.require_compute_enabled()?;
+        ctx.accounts.patient_data.require_live()?;
+
+        let request = &ctx.accounts.disclosure_request;
+        require!(
+            request.status == DisclosureStatus::Requested,
+            ErrorCode::InvalidDisclosureStatus
+        );
+        let approvals = request
+            .approvals
+            .iter()
+            .filter(|approver| config.disclosure_committee.contains(approver))
+            .count();
+        require!(
+            approvals >= config.disclosure_threshold as usize,
+            ErrorCode::DisclosureNotApproved
+        );
+
+        let now = Clock::get()?.unix_timestamp;
+        let activity = &mut ctx.accounts.regulator_activity;
+        require!(
+            activity.disclosures == 0
+                || now
+                    >= activity
+                        .last_disclosure_at
+                        .saturating_add(config.disclosure_interval),
+            ErrorCode::DisclosureRateLimited
+        );
+        activity.regulator = ctx.accounts.regulator.key();
+        activity.last_disclosure_at = now;
+        activity.disclosures = activity
+            .disclosures
+            .checked_add(1)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        activity.bump = ctx.bumps.regulator_activity;
+
+        require_ciphertext_region::<PatientData>(&ctx.accounts.patient_data.to_account_info())?;
+        let patient_data = &ctx.accounts.patient_data;
+        let args = regulatory_disclosure_args(
+            regulator_pub_key,
+            regulator_nonce,
+            patient_data.enc_pubkey,
+            patient_data.nonce,
+            patient_data.key(),
+        );
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+        ctx.accounts.disclosure_request.status = DisclosureStatus::Queued;
+        ctx.accounts
+            .program_config
+            .record_queued(COMP_DEF_OFFSET_REGULATORY_DISCLOSURE)?;
+        ctx.accounts.used_offsets.claim(
+            ctx.accounts.regulator.key(),
+            ctx.bumps.used_offsets,
+            computation_offset,
+            &ctx.accounts.program_config,
+        )?;
+        ctx.accounts.callback_guard.expect(
+            computation_offset,
+            COMP_DEF_OFFSET_REGULATORY_DISCLOSURE,
+            ctx.bumps.callback_guard,
+        );
+
+        emit!(DisclosureQueuedEvent {
+            disclosure_request: ctx.accounts.disclosure_request.key(),
+            regulator: ctx.accounts.regulator.key(),
+            computation_offset,
+        });
+
+        let mut callback_accounts = vec![
+            CallbackAccount {
+                pubkey: ctx.accounts.program_config.key(),
+                is_writable: true,
+            },
+            CallbackAccount {
+                pubkey: ctx.accounts.callback_guard.key(),
+                is_writable: true,
+            },
+            CallbackAccount {
+                pubkey: ctx.accounts.disclosure_request.key(),
+                is_writable: true,
+            },
+            CallbackAccount {
+                pubkey: ctx.accounts.used_offsets.key(),
+                is_writable: true,
+            },
+        ];
+        callback_accounts.extend(event_cpi_callback_accounts());
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![RegulatoryDisclosureCallback::callback_ix(
+                &callback_accounts,
+            )],
+        )?;
+        Ok(())
+    }
+
+    /// Delivers a disclosed record, encrypted for the regulator, through an event.
+    #[arcium_callback(encrypted_ix = "regulatory_disclosure")]
+    pub fn regulatory_disclosure_callback(
+        ctx: Context<RegulatoryDisclosureCallback>,
+        output: ComputationOutputs<RegulatoryDisclosureOutput>,
+    ) -> Result<()> {
+        let computation_offset = ctx
+            .accounts
+            .callback_guard
+            .consume(COMP_DEF_OFFSET_REGULATORY_DISCLOSURE)?;
+        verify_callback_invocation(
+            &ctx.accounts.instructions_sysvar,
+            &ctx.accounts.comp_def_account.key(),
+            computation_offset,
+        )?;
+        ctx.accounts.used_offsets.release(computation_offset);
+        ctx.accounts
+            .program_config
+            .record_completed(COMP_DEF_OFFSET_REGULATORY_DISCLOSURE)?;
+
+        let request = &mut ctx.accounts.disclosure_request;
+        let o = match output {
+            ComputationOutputs::Success(RegulatoryDisclosureOutput { field_0 }) => field_0,
+            _ => {
+                request.status = DisclosureStatus::Failed;
+                emit!(RegulatoryDisclosureFailedEvent {
+                    disclosure_request: request.key(),
+                    regulator: request.regulator,
+                });
+                return Ok(());
+            }
+        };
+        request.status = DisclosureStatus::Disclosed;
+
+        let ([patient_id, age, gender, blood_type, weight, height], allergies) =
+            split_record_output(&o.ciphertexts)?;
+        let event = RegulatoryDisclosureEvent {
+            disclosure_request: request.key(),
+            regulator: request.regulator,
+            receiver_enc_key: o.encryption_key,
+            slot: Clock::get()?.slot,
+            court_order_hash: request.court_order_hash,
+            nonce: o.nonce.to_le_bytes(),
+            patient_id,
+            age,
+            gender,
+            blood_type,
+            weight,
+            height,
+            allergies,
+        };
+        if ctx.accounts.program_config.event_cpi {
+            emit_cpi!(event);
+        } else {
+            emit!(event);
+        }
+        Ok(())
+    }
+
+    /// Closes the caller's record once its minimum retention period has passed.
+    ///
+    /// Closes the record's used nonces with it. The registry stays open as the record's
+    /// audit trail and is marked closed, so no record can be stored at the address again
+    /// and consents or other grants naming it cannot apply to a new one.
+    pub fn close_patient_data(ctx: Context<ClosePatientData>) -> Result<()> {
+        ctx.accounts.patient_data.require_not_in_flight()?;
+        let policy = ctx
+            .accounts
+            .program_config
+            .retention_policy(RecordType::PatientData);
+        require!(
+            policy.may_remove(ctx.accounts.patient_data.created_slot, Clock::get()?.slot),
+            ErrorCode::RetentionPeriodActive
+        );
+        ctx.accounts.patient_registry.record_closed = true;
+
+        emit!(RecordClosedEvent {
+            record: ctx.accounts.patient_data.key(),
+            record_type: RecordType::PatientData,
+        });
+        Ok(())
+    }
+
+    /// Wipes the caller's record once its minimum retention period has passed.
+    ///
+    /// Unlike closing, the account stays behind so the record cannot be silently recreated.
+    /// Like closing, it waits for computations reading the ciphertexts to settle.
+    pub fn tombstone_patient_data(ctx: Context<RemovePatientData>) -> Result<()> {
+        let policy = ctx
+            .accounts
+            .program_config
+            .retention_policy(RecordType::PatientData);
+        let patient_data = &mut ctx.accounts.patient_data;
+        patient_data.require_live()?;
+        patient_data.require_not_in_flight()?;
+        require!(
+            policy.may_remove(patient_data.created_slot, Clock::get()?.slot),
+            ErrorCode::RetentionPeriodActive
+        );
+        patient_data.tombstone();
+
+        emit!(RecordTombstonedEvent {
+            record: patient_data.key(),
+            record_type: RecordType::PatientData,
+            swept: false,
+        });
+        Ok(())
+    }
+
+    /// Tombstones every record in `remaining_accounts` that is past its maximum retention.
+    ///
+    /// Permissionless so that retention is enforced without relying on the owner. Records
+    /// that are not expired, already tombstoned or read by a computation in flight are
+    /// skipped, so one stale entry doesn't fail the whole batch; a skipped record is swept
+    /// by a later batch once its computations settle.
+    pub fn sweep_expired_records<'info>(
+        ctx: Context<'_, '_, 'info, 'info, SweepExpiredRecords<'info>>,
+    ) -> Result<()> {
+        let policy = ctx
+            .accounts
+            .program_config
+            .retention_policy(RecordType::PatientData);
+        let slot = Clock::get()?.slot;
+
+        for info in ctx.remaining_accounts {
+            let mut record = checked_program_account::<PatientData>(info)?;
+            if record.tombstoned
+                || record.in_flight_computations > 0
+                || !policy.is_expired(record.created_slot, slot)
+            {
+                continue;
+            }
+            record.tombstone();
+            record.exit(&crate::ID)?;
+
+            emit!(RecordTombstonedEvent {
+                record: info.key(),
+                record_type: RecordType::PatientData,
+                swept: true,
+            });
+        }
+        Ok(())
+    }
+
+    pub fn init_share_content_key_comp_def(ctx: Context<InitShareContentKeyCompDef>) -> Result<()> {
+        let source = ctx
+            .accounts
+            .program_config
+            .circuit_source(COMP_DEF_OFFSET_SHARE_CONTENT_KEY)?;
+        init_comp_def(ctx.accounts, true, 0, Some(source), None)?;
+        ctx.accounts
+            .program_config
+            .mark_circuit_initialized(COMP_DEF_OFFSET_SHARE_CONTENT_KEY)
+    }
+
+    /// Anchors an encrypted FHIR bundle stored off-chain.
+    ///
+    /// # Arguments
+    /// * `anchor_id` - Patient-chosen identifier, part of the anchor address
+    /// * `resource_type` - FHIR resource type of the bundle
+    /// * `version` - Version of the bundle
+    /// * `bundle_hash` - SHA-256 of the encrypted bundle
+    /// * `content_key` - Bundle content key, encrypted to the MXE
+    pub fn anchor_fhir_resource(
+        ctx: Context<AnchorFhirResource>,
+        anchor_id: u64,
+        resource_type: String,
+        version: u32,
+        bundle_hash: [u8; 32],
+        content_key: EncryptedContentKey,
+    ) -> Result<()> {
+        ctx.accounts.program_config.require_store_enabled()?;
+        require!(
+            resource_type.len() <= MAX_FHIR_RESOURCE_TYPE_LEN,
+            ErrorCode::ResourceTypeTooLong
+        );
+
+        let fhir_anchor = &mut ctx.accounts.fhir_anchor;
+        fhir_anchor.content_key = content_key;
+        fhir_anchor.patient = ctx.accounts.patient.key();
+        fhir_anchor.anchor_id = anchor_id;
+        fhir_anchor.resource_type = resource_type;
+        fhir_anchor.version = version;
+        fhir_anchor.bundle_hash = bundle_hash;
+        fhir_anchor.bump = ctx.bumps.fhir_anchor;
+
+        emit!(FhirResourceAnchoredEvent {
+            fhir_anchor: fhir_anchor.key(),
+            patient: fhir_anchor.patient,
+            resource_type: fhir_anchor.resource_type.clone(),
+            version,
+            bundle_hash,
+        });
+        Ok(())
+    }
+
+    /// Points an existing FHIR anchor at a newer version of its bundle.
+    ///
+    /// # Arguments
+    /// * `version` - New bundle version, must be greater than the anchored one
+    /// * `bundle_hash` - SHA-256 of the new encrypted bundle
+    /// * `content_key` - Content key of the new bundle, encrypted to the MXE
+    pub fn update_fhir_anchor(
+        ctx: Context<UpdateFhirAnchor>,
+        version: u32,
+        bundle_hash: [u8; 32],
+        content_key: EncryptedContentKey,
+    ) -> Result<()> {
+        ctx.accounts.program_config.require_store_enabled()?;
+        let fhir_anchor = &mut ctx.accounts.fhir_anchor;
+        require!(version > fhir_anchor.version, ErrorCode::StaleAnchorVersion);
+        fhir_anchor.content_key = content_key;
+        fhir_anchor.version = version;
+        fhir_anchor.bundle_hash = bundle_hash;
+
+        emit!(FhirResourceAnchoredEvent {
+            fhir_anchor: fhir_anchor.key(),
+            patient: fhir_anchor.patient,
+            resource_type: fhir_anchor.resource_type.clone(),
+            version,
+            bundle_hash,
+        });
+        Ok(())
+    }
+
+    /// Re-encrypts a FHIR bundle's content key for a receiver.
+    ///
+    /// The receiver fetches the bundle off-chain, checks it against the anchored hash and
+    /// decrypts it with the key delivered in `ContentKeySharedEvent`.
+    ///
+    /// A genomic anchor also describes the patient's relatives, so sharing one takes every
+    /// `KinLink` of the record in `remaining_accounts`, each with both relatives' consent.
+    ///
+    /// # Arguments
+    /// * `receiver` - Receiver's x25519 public key
+    /// * `receiver_nonce` - Nonce for the receiver's encryption
+    /// * `sender_pub_key` - Patient key the content key was encrypted with
+    /// * `nonce` - Nonce the content key was encrypted with
+    pub fn share_fhir_content_key<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ShareFhirContentKey<'info>>,
+        computation_offset: u64,
+        receiver: [u8; 32],
+        receiver_nonce: u128,
+        sender_pub_key: [u8; 32],
+        nonce: u128,
+    ) -> Result<()> {
+        ctx.accounts.program_config.require_share_enabled()?;
+
+        require_ciphertext_region::<FhirAnchor>(&ctx.accounts.fhir_anchor.to_account_info())?;
+        if is_genomic_resource_type(&ctx.accounts.fhir_anchor.resource_type) {
+            require_kin_consents(
+                &ctx.accounts.patient_data.key(),
+                &ctx.accounts.kin_roster,
+                ctx.remaining_accounts,
+            )?;
+        }
+        let args = share_content_key_args(
+            receiver,
+            receiver_nonce,
+            sender_pub_key,
+            nonce,
+            ctx.accounts.fhir_anchor.key(),
+        );
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+        ctx.accounts
+            .program_config
+            .record_queued(COMP_DEF_OFFSET_SHARE_CONTENT_KEY)?;
+        ctx.accounts.used_offsets.claim(
+            ctx.accounts.patient.key(),
+            ctx.bumps.used_offsets,
+            computation_offset,
+            &ctx.accounts.program_config,
+        )?;
+        ctx.accounts.callback_guard.expect(
+            computation_offset,
+            COMP_DEF_OFFSET_SHARE_CONTENT_KEY,
+            ctx.bumps.callback_guard,
+        );
+
+        let mut callback_accounts = vec![
+            CallbackAccount {
+                pubkey: ctx.accounts.program_config.key(),
+                is_writable: true,
+            },
+            CallbackAccount {
+                pubkey: ctx.accounts.callback_guard.key(),
+                is_writable: true,
+            },
+            CallbackAccount {
+                pubkey: ctx.accounts.fhir_anchor.key(),
+                is_writable: false,
+            },
+            CallbackAccount {
+                pubkey: ctx.accounts.used_offsets.key(),
+                is_writable: true,
+            },
+        ];
+        callback_accounts.extend(event_cpi_callback_accounts());
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![ShareContentKeyCallback::callback_ix(&callback_accounts)],
+        )?;
+        Ok(())
+    }
+
+    /// Delivers a re-encrypted content key through `ContentKeySharedEvent`.
+    #[arcium_callback(encrypted_ix = "share_content_key")]
+    pub fn share_content_key_callback(
+        ctx: Context<ShareContentKeyCallback>,
+        output: ComputationOutputs<ShareContentKeyOutput>,
+    ) -> Result<()> {
+        let computation_offset = ctx
+            .accounts
+            .callback_guard
+            .consume(COMP_DEF_OFFSET_SHARE_CONTENT_KEY)?;
+        verify_callback_invocation(
+            &ctx.accounts.instructions_sysvar,
+            &ctx.accounts.comp_def_account.key(),
+            computation_offset,
+        )?;
+        ctx.accounts.used_offsets.release(computation_offset);
+        ctx.accounts
+            .program_config
+            .record_completed(COMP_DEF_OFFSET_SHARE_CONTENT_KEY)?;
+
+        let o = match output {
+            ComputationOutputs::Success(ShareContentKeyOutput { field_0 }) => field_0,
+            _ => {
+                emit!(ContentKeyShareAbortedEvent {
+                    anchor: ctx.accounts.anchor.key(),
+                });
+                return Ok(());
+            }
+        };
+
+        let event = ContentKeySharedEvent {
+            anchor: ctx.accounts.anchor.key(),
+            receiver_enc_key: o.encryption_key,
+            slot: Clock::get()?.slot,
+            nonce: o.nonce.to_le_bytes(),
+            content_key: o.ciphertexts[0..2]
+                .try_into()
+                .map_err(|_| ErrorCode::ComputationOutputLength)?,
+        };
+        if ctx.accounts.program_config.event_cpi {
+            emit_cpi!(event);
+        } else {
+            emit!(event);
+        }
+        Ok(())
+    }
+
+    /// Anchors an HL7v2 ORU result message against a patient's registry.
+    ///
+    /// Called by lab information systems registered as `Laboratory` providers. The message
+    /// hash is appended to the registry's hash chain, giving legacy lab pipelines a
+    /// tamper-evident record of what was delivered and in which order.
+    ///
+    /// # Arguments
+    /// * `message_hash` - SHA-256 of the raw HL7 message
+    /// * `encrypted_pointer` - Off-chain location of the message, encrypted for the patient
+    pub fn anchor_hl7_message(
+        ctx: Context<AnchorHl7Message>,
+        message_hash: [u8; 32],
+        encrypted_pointer: Vec<u8>,
+    ) -> Result<()> {
+        ctx.accounts.program_config.require_store_enabled()?;
+        ctx.accounts.patient_data.require_live()?;
+        require!(
+            encrypted_pointer.len() <= MAX_HL7_POINTER_LEN,
+            ErrorCode::PointerTooLong
+        );
+
+        let registry = &mut ctx.accounts.patient_registry;
+        registry.patient_data = ctx.accounts.patient_data.key();
+        registry.bump = ctx.bumps.patient_registry;
+        let sequence = registry.append(&message_hash)?;
+
+        let hl7_anchor = &mut ctx.accounts.hl7_anchor;
+        hl7_anchor.patient_data = registry.patient_data;
+        hl7_anchor.lab = ctx.accounts.lab.key();
+        hl7_anchor.sequence = sequence;
+        hl7_anchor.message_hash = message_hash;
+        hl7_anchor.encrypted_pointer = encrypted_pointer;
+        hl7_anchor.anchored_at = Clock::get()?.unix_timestamp;
+        hl7_anchor.bump = ctx.bumps.hl7_anchor;
+
+        emit!(Hl7MessageAnchoredEvent {
+            hl7_anchor: hl7_anchor.key(),
+            patient_data: hl7_anchor.patient_data,
+            lab: hl7_anchor.lab,
+            sequence,
+            message_hash,
+            registry_head: registry.head,
+        });
+        Ok(())
+    }
+
+    /// Writes an encrypted lab result against a patient's registry and notifies the
+    /// patient through `NewResultAvailableEvent`.
+    ///
+    /// Called by labs registered as `Laboratory` providers that hold an active care
+    /// relationship grant from the patient: a consent covering clinical messages.
+    ///
+    /// # Arguments
+    /// * `result_id` - Lab chosen identifier, part of the result address
+    /// * `values` - Result values, encrypted to the MXE
+    /// * `enc_pubkey` - x25519 key the values were encrypted with
+    /// * `nonce` - Nonce the values were encrypted with
+    pub fn submit_lab_result(
+        ctx: Context<SubmitLabResult>,
+        result_id: u64,
+        values: EncryptedLabValues,
+        enc_pubkey: [u8; 32],
+        nonce: u128,
+    ) -> Result<()> {
+        ctx.accounts.program_config.require_store_enabled()?;
+        ctx.accounts.patient_data.require_live()?;
+        ctx.accounts.consent_grant.require_covers(
+            CONSENT_SCOPE_CLINICAL_MESSAGES,
+            &ctx.accounts.program_config.expiry_clock()?,
+        )?;
+        require_valid_x25519_key(&enc_pubkey)?;
+
+        let registry = &mut ctx.accounts.patient_registry;
+        registry.patient_data = ctx.accounts.patient_data.key();
+        registry.bump = ctx.bumps.patient_registry;
+        let sequence = registry.append(&lab_result_hash(&enc_pubkey, nonce, &values))?;
+
+        let lab_result = &mut ctx.accounts.lab_result;
+        lab_result.values = values;
+        lab_result.patient_data = registry.patient_data;
+        lab_result.lab = ctx.accounts.lab.key();
+        lab_result.result_id = result_id;
+        lab_result.sequence = sequence;
+        lab_result.enc_pubkey = enc_pubkey;
+        lab_result.nonce = nonce;
+        lab_result.submitted_at = Clock::get()?.unix_timestamp;
+        lab_result.bump = ctx.bumps.lab_result;
+
+        emit!(NewResultAvailableEvent {
+            lab_result: lab_result.key(),
+            patient_data: lab_result.patient_data,
+            patient: ctx.accounts.patient_data.patient_authority,
+            lab: lab_result.lab,
+            sequence,
+            registry_head: registry.head,
+        });
+        Ok(())
+    }
+
+    /// Creates the caller's document vault.
+    ///
+    /// # Arguments
+    /// * `enc_pubkey` - x25519 public key the vault's content keys will be encrypted with
+    pub fn init_document_vault(
+        ctx: Context<InitDocumentVault>,
+        enc_pubkey: [u8; 32],
+    ) -> Result<()> {
+        let document_vault = &mut ctx.accounts.document_vault;
+        document_vault.owner = ctx.accounts.owner.key();
+        document_vault.enc_pubkey = enc_pubkey;
+        document_vault.entries = Vec::new();
+        document_vault.bump = ctx.bumps.document_vault;
+        Ok(())
+    }
+
+    /// Adds an encrypted IPFS or Arweave document to the caller's vault.
+    ///
+    /// # Arguments
+    /// * `cid` - IPFS CID or Arweave transaction id of the encrypted document
+    /// * `ciphertext_hash` - SHA-256 of the stored ciphertext
+    /// * `content_key` - Document content key, encrypted to the MXE with the vault's key
+    /// * `key_nonce` - Nonce the content key was encrypted with
+    pub fn add_document(
+        ctx: Context<UpdateDocumentVault>,
+        cid: String,
+        ciphertext_hash: [u8; 32],
+        content_key: EncryptedContentKey,
+        key_nonce: u128,
+    ) -> Result<()> {
+        ctx.accounts.program_config.require_store_enabled()?;
+        require!(
+            cid.len() <= MAX_DOCUMENT_CID_LEN,
+            ErrorCode::DocumentCidTooLong
+        );
+        let document_vault = &mut ctx.accounts.document_vault;
+        require!(
+            document_vault.entries.len() < MAX_VAULT_DOCUMENTS,
+            ErrorCode::DocumentVaultFull
+        );
+
+        emit!(DocumentAddedEvent {
+            document_vault: document_vault.key(),
+            cid: cid.clone(),
+            ciphertext_hash,
+        });
+        document_vault.entries.push(DocumentEntry {
+            cid,
+            ciphertext_hash,
+            content_key,
+            key_nonce,
+        });
+        Ok(())
+    }
+
+    /// Removes a document from the caller's vault.
+    ///
+    /// # Arguments
+    /// * `index` - Position of the document in the vault
+    pub fn remove_document(ctx: Context<UpdateDocumentVault>, index: u8) -> Result<()> {
+        let document_vault = &mut ctx.accounts.document_vault;
+        require!(
+            (index as usize) < document_vault.entries.len(),
+            ErrorCode::DocumentNotFound
+        );
+        let entry = document_vault.entries.remove(index as usize);
+
+        emit!(DocumentRemovedEvent {
+            document_vault: document_vault.key(),
+            cid: entry.cid,
+        });
+        Ok(())
+    }
+
+    /// Re-encrypts a vault document's content key for a receiver.
+    ///
+    /// The receiver fetches the document by CID, checks it against the ciphertext hash and
+    /// decrypts it with the key delivered in `ContentKeySharedEvent`.
+    ///
+    /// # Arguments
+    /// * `index` - Position of the document in the vault
+    /// * `receiver` - Receiver's x25519 public key
+    /// * `receiver_nonce` - Nonce for the receiver's encryption
+    pub fn share_document(
+        ctx: Context<ShareDocument>,
+        computation_offset: u64,
+        index: u8,
+        receiver: [u8; 32],
+        receiver_nonce: u128,
+    ) -> Result<()> {
+        ctx.accounts.program_config.require_share_enabled()?;
+        let document_vault = &ctx.accounts.document_vault;
+        let entry = document_vault
+            .entries
+            .get(index as usize)
+            .ok_or(ErrorCode::DocumentNotFound)?;
+
+        let args = share_content_key_value_args(
+            receiver,
+            receiver_nonce,
+            document_vault.enc_pubkey,
+            entry.key_nonce,
+            &entry.content_key,
+        );
+        emit!(DocumentShareQueuedEvent {
+            document_vault: document_vault.key(),
+            cid: entry.cid.clone(),
+            ciphertext_hash: entry.ciphertext_hash,
+            receiver,
+        });
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+        ctx.accounts
+            .program_config
+            .record_queued(COMP_DEF_OFFSET_SHARE_CONTENT_KEY)?;
+        ctx.accounts.used_offsets.claim(
+            ctx.accounts.owner.key(),
+            ctx.bumps.used_offsets,
+            computation_offset,
+            &ctx.accounts.program_config,
+        )?;
+        ctx.accounts.callback_guard.expect(
+            computation_offset,
+            COMP_DEF_OFFSET_SHARE_CONTENT_KEY,
+            ctx.bumps.callback_guard,
+        );
+
+        let mut callback_accounts = vec![
+            CallbackAccount {
+                pubkey: ctx.accounts.program_config.key(),
+                is_writable: true,
+            },
+            CallbackAccount {
+                pubkey: ctx.accounts.callback_guard.key(),
+                is_writable: true,
+            },
+            CallbackAccount {
+                pubkey: ctx.accounts.document_vault.key(),
+                is_writable: false,
+            },
+            CallbackAccount {
+                pubkey: ctx.accounts.used_offsets.key(),
+                is_writable: true,
+            },
+        ];
+        callback_accounts.extend(event_cpi_callback_accounts());
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![ShareContentKeyCallback::callback_ix(&callback_accounts)],
+        )?;
+        Ok(())
+    }
+
+    pub fn init_share_dicom_reference_comp_def(
+        ctx: Context<InitShareDicomReferenceCompDef>,
+    ) -> Result<()> {
+        let source = ctx
+            .accounts
+            .program_config
+            .circuit_source(COMP_DEF_OFFSET_SHARE_DICOM_REFERENCE)?;
+        init_comp_def(ctx.accounts, true, 0, Some(source), None)?;
+        ctx.accounts
+            .program_config
+            .mark_circuit_initialized(COMP_DEF_OFFSET_SHARE_DICOM_REFERENCE)
+    }
+
+    /// Records a reference to a DICOM study against a patient's record.
+    ///
+    /// Called by imaging centers registered as `ImagingCenter` providers.
+    ///
+    /// # Arguments
+    /// * `reference_id` - Imaging center chosen identifier, part of the reference address
+    /// * `study_uid` - Study instance UID, encrypted to the MXE as four limbs
+    /// * `accession_number` - Accession number, encrypted to the MXE
+    /// * `modality` - DICOM modality code of the study
+    /// * `pointer` - Where the study can be retrieved
+    /// * `enc_pubkey` - x25519 key the identifiers were encrypted with
+    /// * `nonce` - Nonce the identifiers were encrypted with
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_dicom_reference(
+        ctx: Context<CreateDicomReference>,
+        reference_id: u64,
+        study_uid: EncryptedStudyUid,
+        accession_number: [u8; 32],
+        modality: String,
+        pointer: String,
+        enc_pubkey: [u8; 32],
+        nonce: u128,
+    ) -> Result<()> {
+        ctx.accounts.program_config.require_store_enabled()?;
+        ctx.accounts.patient_data.require_live()?;
+        require!(
+            modality.len() <= MAX_DICOM_MODALITY_LEN && pointer.len() <= MAX_DICOM_POINTER_LEN,
+            ErrorCode::DicomFieldTooLong
+        );
+
+        let dicom_reference = &mut ctx.accounts.dicom_reference;
+        dicom_reference.study_uid = study_uid;
+        dicom_reference.accession_number = accession_number;
+        dicom_reference.patient_data = ctx.accounts.patient_data.key();
+        dicom_reference.imaging_center = ctx.accounts.imaging_center.key();
+        dicom_reference.reference_id = reference_id;
+        dicom_reference.modality = modality;
+        dicom_reference.pointer = pointer;
+        dicom_reference.enc_pubkey = enc_pubkey;
+        dicom_reference.nonce = nonce;
+        dicom_reference.bump = ctx.bumps.dicom_reference;
+
+        emit!(DicomReferenceCreatedEvent {
+            dicom_reference: dicom_reference.key(),
+            patient_data: dicom_reference.patient_data,
+            imaging_center: dicom_reference.imaging_center,
+            modality: dicom_reference.modality.clone(),
+        });
+        Ok(())
+    }
+
+    /// Re-encrypts the identifiers of a DICOM study on the caller's record for a receiver.
+    ///
+    /// # Arguments
+    /// * `receiver` - Receiver's x25519 public key
+    /// * `receiver_nonce` - Nonce for the receiver's encryption
+    pub fn share_dicom_study(
+        ctx: Context<ShareDicomStudy>,
+        computation_offset: u64,
+        receiver: [u8; 32],
+        receiver_nonce: u128,
+    ) -> Result<()> {
+        ctx.accounts.program_config.require_share_enabled()?;
+        ctx.accounts.patient_data.require_live()?;
+
+        let dicom_reference = &ctx.accounts.dicom_reference;
+        require_ciphertext_region::<DicomReference>(&dicom_reference.to_account_info())?;
+        let args = share_dicom_reference_args(
+            receiver,
+            receiver_nonce,
+            dicom_reference.enc_pubkey,
+            dicom_reference.nonce,
+            dicom_reference.key(),
+        );
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+        ctx.accounts
+            .program_config
+            .record_queued(COMP_DEF_OFFSET_SHARE_DICOM_REFERENCE)?;
+        ctx.accounts.used_offsets.claim(
+            ctx.accounts.patient.key(),
+            ctx.bumps.used_offsets,
+            computation_offset,
+            &ctx.accounts.program_config,
+        )?;
+        ctx.accounts.callback_guard.expect(
+            computation_offset,
+            COMP_DEF_OFFSET_SHARE_DICOM_REFERENCE,
+            ctx.bumps.callback_guard,
+        );
+
+        let mut callback_accounts = vec![
+            CallbackAccount {
+                pubkey: ctx.accounts.program_config.key(),
+                is_writable: true,
+            },
+            CallbackAccount {
+                pubkey: ctx.accounts.callback_guard.key(),
+                is_writable: true,
+            },
+            CallbackAccount {
+                pubkey: ctx.accounts.dicom_reference.key(),
+                is_writable: false,
+            },
+            CallbackAccount {
+                pubkey: ctx.accounts.used_offsets.key(),
+                is_writable: true,
+            },
+        ];
+        callback_accounts.extend(event_cpi_callback_accounts());
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![ShareDicomReferenceCallback::callback_ix(&callback_accounts)],
+        )?;
+        Ok(())
+    }
+
+    /// Delivers re-encrypted DICOM study identifiers through `DicomStudySharedEvent`.
+    #[arcium_callback(encrypted_ix = "share_dicom_reference")]
+    pub fn share_dicom_reference_callback(
+        ctx: Context<ShareDicomReferenceCallback>,
+        output: ComputationOutputs<ShareDicomReferenceOutput>,
+    ) -> Result<()> {
+        let computation_offset = ctx
+            .accounts
+            .callback_guard
+            .consume(COMP_DEF_OFFSET_SHARE_DICOM_REFERENCE)?;
+        verify_callback_invocation(
+            &ctx.accounts.instructions_sysvar,
+            &ctx.accounts.comp_def_account.key(),
+            computation_offset,
+        )?;
+        ctx.accounts.used_offsets.release(computation_offset);
+        ctx.accounts
+            .program_config
+            .record_completed(COMP_DEF_OFFSET_SHARE_DICOM_REFERENCE)?;
+
+        let dicom_reference = &ctx.accounts.dicom_reference;
+        let o = match output {
+            ComputationOutputs::Success(ShareDicomReferenceOutput { field_0 }) => field_0,
+            _ => {
+                emit!(DicomStudyShareAbortedEvent {
+                    dicom_reference: dicom_reference.key(),
+                });
+                return Ok(());
+            }
+        };
+
+        let event = DicomStudySharedEvent {
+            dicom_reference: dicom_reference.key(),
+            receiver_enc_key: o.encryption_key,
+            slot: Clock::get()?.slot,
+            modality: dicom_reference.modality.clone(),
+            pointer: dicom_reference.pointer.clone(),
+            nonce: o.nonce.to_le_bytes(),
+            study_uid: o.ciphertexts[0..4]
+                .try_into()
+                .map_err(|_| ErrorCode::ComputationOutputLength)?,
+            accession_number: o.ciphertexts[4],
+        };
+        if ctx.accounts.program_config.event_cpi {
+            emit_cpi!(event);
+        } else {
+            emit!(event);
+        }
+        Ok(())
+    }
+
+    pub fn init_share_compressed_record_comp_def(
+        ctx: Context<InitShareCompressedRecordCompDef>,
+    ) -> Result<()> {
+        let source = ctx
+            .accounts
+            .program_config
+            .circuit_source(COMP_DEF_OFFSET_SHARE_COMPRESSED_RECORD)?;
+        init_comp_def(ctx.accounts, true, 0, Some(source), None)?;
+        ctx.accounts
+            .program_config
+            .mark_circuit_initialized(COMP_DEF_OFFSET_SHARE_COMPRESSED_RECORD)
+    }
+
+    /// Creates an empty compressed record tree owned by the calling clinic.
+    ///
+    /// # Arguments
+    /// * `tree_id` - Clinic-chosen identifier, part of the tree address
+    pub fn init_compressed_tree(ctx: Context<InitCompressedTree>, tree_id: u64) -> Result<()> {
+        let tree = &mut ctx.accounts.tree;
+        tree.authority = ctx.accounts.authority.key();
+        tree.tree_id = tree_id;
+        tree.initialize();
+        tree.bump = ctx.bumps.tree;
+        Ok(())
+    }
+
+    /// Appends a small encrypted record to a compressed tree instead of its own account.
+    ///
+    /// Only the leaf hash changes the tree; the record is emitted for indexers to store
+    /// and must be presented with a proof to be shared.
+    ///
+    /// # Arguments
+    /// * `patient` - Wallet the record belongs to
+    /// * `enc_pubkey` - x25519 key the record was encrypted with
+    /// * `nonce` - Nonce the record was encrypted with
+    /// * `ciphertexts` - Record values, encrypted to the MXE
+    pub fn append_compressed_record(
+        ctx: Context<AppendCompressedRecord>,
+        patient: Pubkey,
+        enc_pubkey: [u8; 32],
+        nonce: u128,
+        ciphertexts: CompressedCiphertexts,
+    ) -> Result<()> {
+        ctx.accounts.program_config.require_store_enabled()?;
+        let leaf = compressed_leaf(&patient, &enc_pubkey, nonce, &ciphertexts);
+        let (leaf_index, root) = ctx.accounts.tree.append(leaf)?;
+
+        emit!(CompressedRecordAppendedEvent {
+            tree: ctx.accounts.tree.key(),
+            leaf_index,
+            patient,
+            enc_pubkey,
+            nonce,
+            ciphertexts,
+            root,
+        });
+        Ok(())
+    }
+
+    /// Re-encrypts one of the caller's compressed records for a receiver.
+    ///
+    /// The record is passed in full and checked against the tree with a Merkle proof
+    /// before its ciphertexts are handed to the MPC cluster.
+    ///
+    /// # Arguments
+    /// * `leaf_index` - Index of the record in the tree
+    /// * `enc_pubkey` - x25519 key the record was encrypted with
+    /// * `nonce` - Nonce the record was encrypted with
+    /// * `ciphertexts` - Record values as appended
+    /// * `proof` - Sibling hashes from the leaf up to the root
+    /// * `receiver` - Receiver's x25519 public key
+    /// * `receiver_nonce` - Nonce for the receiver's encryption
+    #[allow(clippy::too_many_arguments)]
+    pub fn share_compressed_record(
+        ctx: Context<ShareCompressedRecord>,
+        computation_offset: u64,
+        leaf_index: u64,
+        enc_pubkey: [u8; 32],
+        nonce: u128,
+        ciphertexts: CompressedCiphertexts,
+        proof: Vec<[u8; 32]>,
+        receiver: [u8; 32],
+        receiver_nonce: u128,
+    ) -> Result<()> {
+        ctx.accounts.program_config.require_share_enabled()?;
+        let leaf = compressed_leaf(
+            &ctx.accounts.patient.key(),
+            &enc_pubkey,
+            nonce,
+            &ciphertexts,
+        );
+        ctx.accounts.tree.verify(leaf, leaf_index, &proof)?;
+
+        let args =
+            share_compressed_record_args(receiver, receiver_nonce, enc_pubkey, nonce, &ciphertexts);
+        emit!(CompressedRecordShareQueuedEvent {
+            tree: ctx.accounts.tree.key(),
+            leaf_index,
+            receiver,
+        });
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+        ctx.accounts
+            .program_config
+            .record_queued(COMP_DEF_OFFSET_SHARE_COMPRESSED_RECORD)?;
+        ctx.accounts.used_offsets.claim(
+            ctx.accounts.patient.key(),
+            ctx.bumps.used_offsets,
+            computation_offset,
+            &ctx.accounts.program_config,
+        )?;
+        ctx.accounts.callback_guard.expect(
+            computation_offset,
+            COMP_DEF_OFFSET_SHARE_COMPRESSED_RECORD,
+            ctx.bumps.callback_guard,
+        );
+
+        let mut callback_accounts = vec![
+            CallbackAccount {
+                pubkey: ctx.accounts.program_config.key(),
+                is_writable: true,
+            },
+            CallbackAccount {
+                pubkey: ctx.accounts.callback_guard.key(),
+                is_writable: true,
+            },
+            CallbackAccount {
+                pubkey: ctx.accounts.tree.key(),
+                is_writable: false,
+            },
+            CallbackAccount {
+                pubkey: ctx.accounts.used_offsets.key(),
+                is_writable: true,
+            },
+        ];
+        callback_accounts.extend(event_cpi_callback_accounts());
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![ShareCompressedRecordCallback::callback_ix(
+                &callback_accounts,
+            )],
+        )?;
+        Ok(())
+    }
+
+    /// Delivers a re-encrypted compressed record through `CompressedRecordSharedEvent`.
+    #[arcium_callback(encrypted_ix = "share_compressed_record")]
+    pub fn share_compressed_record_callback(
+        ctx: Context<ShareCompressedRecordCallback>,
+        output: ComputationOutputs<ShareCompressedRecordOutput>,
+    ) -> Result<()> {
+        let computation_offset = ctx
+            .accounts
+            .callback_guard
+            .consume(COMP_DEF_OFFSET_SHARE_COMPRESSED_RECORD)?;
+        verify_callback_invocation(
+            &ctx.accounts.instructions_sysvar,
+            &ctx.accounts.comp_def_account.key(),
+            computation_offset,
+        )?;
+        ctx.accounts.used_offsets.release(computation_offset);
+        ctx.accounts
+            .program_config
+            .record_completed(COMP_DEF_OFFSET_SHARE_COMPRESSED_RECORD)?;
+
+        let o = match output {
+            ComputationOutputs::Success(ShareCompressedRecordOutput { field_0 }) => field_0,
+            _ => {
+                emit!(CompressedRecordShareAbortedEvent {
+                    tree: ctx.accounts.tree.key(),
+                });
+                return Ok(());
+            }
+        };
+
+        let event = CompressedRecordSharedEvent {
+            tree: ctx.accounts.tree.key(),
+            receiver_enc_key: o.encryption_key,
+            slot: Clock::get()?.slot,
+            nonce: o.nonce.to_le_bytes(),
+            ciphertexts: [
+                o.ciphertexts[0],
+                o.ciphertexts[1],
+                o.ciphertexts[2],
+                o.ciphertexts[3],
+            ],
+        };
+        if ctx.accounts.program_config.event_cpi {
+            emit_cpi!(event);
+        } else {
+            emit!(event);
+        }
+        Ok(())
+    }
+
+    pub fn init_share_committed_patient_data_comp_def(
+        ctx: Context<InitShareCommittedPatientDataCompDef>,
+    ) -> Result<()> {
+        let source = ctx
+            .accounts
+            .program_config
+            .circuit_source(COMP_DEF_OFFSET_SHARE_COMMITTED_PATIENT_DATA)?;
+        init_comp_def(ctx.accounts, true, 0, Some(source), None)?;
+        ctx.accounts
+            .program_config
+            .mark_circuit_initialized(COMP_DEF_OFFSET_SHARE_COMMITTED_PATIENT_DATA)
+    }
+
+    /// Stores, or replaces, the caller's record in commitment mode.
+    ///
+    /// Only the commitment is written; the ciphertexts are checked like a regular store's
+    /// and must be uploaded to `cid` for later shares.
+    ///
+    /// # Arguments
+    /// * `fields` - Encrypted patient id, age, gender, blood type, weight and height
+    /// * `allergies` - Encrypted allergy flags
+    /// * `enc_pubkey` - x25519 key the record was encrypted with
+    /// * `nonce` - Nonce the record was encrypted with
+    /// * `cid` - IPFS CID or Arweave transaction id of the uploaded ciphertexts
+    pub fn store_patient_commitment(
+        ctx: Context<StorePatientCommitment>,
+        fields: [[u8; 32]; 6],
+        allergies: [[u8; 32]; 5],
+        enc_pubkey: [u8; 32],
+        nonce: u128,
+        cid: String,
+    ) -> Result<()> {
+        ctx.accounts.program_config.require_store_enabled()?;
+        require!(
+            cid.len() <= MAX_DOCUMENT_CID_LEN,
+            ErrorCode::DocumentCidTooLong
+        );
+        require_plausible_ciphertexts(
+            &fields,
+            &allergies,
+            ctx.accounts.program_config.record_fields,
+        )?;
+
+        let patient = ctx.accounts.patient.key();
+        let commitment = patient_commitment(&patient, &enc_pubkey, nonce, &fields, &allergies);
+        let record = &mut ctx.accounts.patient_commitment;
+        if record.created_slot == 0 {
+            record.created_slot = Clock::get()?.slot;
+        }
+        record.patient_authority = patient;
+        record.commitment = commitment;
+        record.bump = ctx.bumps.patient_commitment;
+
+        emit!(PatientCommitmentStoredEvent {
+            patient_commitment: record.key(),
+            patient_authority: patient,
+            commitment,
+            cid,
+            enc_pubkey,
+            nonce,
+        });
+        Ok(())
+    }
+
+    /// Re-encrypts the caller's commitment-mode record for a receiver.
+    ///
+    /// The record is passed in full and checked against its commitment before its
+    /// ciphertexts are handed to the MPC cluster; the result is delivered like that of
+    /// `share_patient_data`.
+    ///
+    /// # Arguments
+    /// * `fields` - Record ciphertexts as stored
+    /// * `allergies` - Allergy ciphertexts as stored
+    /// * `sender_pub_key` - x25519 key the record was encrypted with
+    /// * `nonce` - Nonce the record was encrypted with
+    /// * `receiver` - Receiver's x25519 public key
+    /// * `receiver_nonce` - Nonce for the receiver's encryption
+    #[allow(clippy::too_many_arguments)]
+    pub fn share_committed_patient_data(
+        ctx: Context<ShareCommittedPatientData>,
+        computation_offset: u64,
+        fields: [[u8; 32]; 6],
+        allergies: [[u8; 32]; 5],
+        sender_pub_key: [u8; 32],
+        nonce: u128,
+        receiver: [u8; 32],
+        receiver_nonce: u128,
+    ) -> Result<()> {
+        ctx.accounts.program_config.require_share_enabled()?;
+        require_valid_x25519_key(&receiver)?;
+        require_valid_x25519_key(&sender_pub_key)?;
+        ctx.accounts
+            .patient_commitment
+            .verify(&sender_pub_key, nonce, &fields, &allergies)?;
+
+        let args = share_committed_patient_data_args(
+            receiver,
+            receiver_nonce,
+            sender_pub_key,
+            nonce,
+            &fields,
+            &allergies,
+        );
+        emit!(SharePatientDataQueuedEvent {
+            patient_data: ctx.accounts.patient_commitment.key(),
+            payer: ctx.accounts.patient.key(),
+            receiver,
+            computation_offset,
+        });
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+        ctx.accounts
+            .program_config
+            .record_queued(COMP_DEF_OFFSET_SHARE_COMMITTED_PATIENT_DATA)?;
+        ctx.accounts.used_offsets.claim(
+            ctx.accounts.patient.key(),
+            ctx.bumps.used_offsets,
+            computation_offset,
+            &ctx.accounts.program_config,
+        )?;
+        ctx.accounts.callback_guard.expect(
+            computation_offset,
+            COMP_DEF_OFFSET_SHARE_COMMITTED_PATIENT_DATA,
+            ctx.bumps.callback_guard,
+        );
+
+        let mut callback_accounts = vec![
+            CallbackAccount {
+                pubkey: ctx.accounts.program_config.key(),
+                is_writable: true,
+            },
+            CallbackAccount {
+                pubkey: ctx.accounts.callback_guard.key(),
+                is_writable: true,
+            },
+            CallbackAccount {
+                pubkey: ctx.accounts.patient_commitment.key(),
+                is_writable: false,
+            },
+            CallbackAccount {
+                pubkey: ctx.accounts.used_offsets.key(),
+                is_writable: true,
+            },
+        ];
+        callback_accounts.extend(event_cpi_callback_accounts());
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![ShareCommittedPatientDataCallback::callback_ix(
+                &callback_accounts,
+            )],
+        )?;
+        Ok(())
+    }
+
+    /// Delivers a re-encrypted commitment-mode record through `ReceivedPatientDataEvent`.
+    #[arcium_callback(encrypted_ix = "share_committed_patient_data")]
+    pub fn share_committed_patient_data_callback(
+        ctx: Context<ShareCommittedPatientDataCallback>,
+        output: ComputationOutputs<ShareCommittedPatientDataOutput>,
+    ) -> Result<()> {
+        let computation_offset = ctx
+            .accounts
+            .callback_guard
+            .consume(COMP_DEF_OFFSET_SHARE_COMMITTED_PATIENT_DATA)?;
+        verify_callback_invocation(
+            &ctx.accounts.instructions_sysvar,
+            &ctx.accounts.comp_def_account.key(),
+            computation_offset,
+        )?;
+        ctx.accounts
+            .program_config
+            .record_completed(COMP_DEF_OFFSET_SHARE_COMMITTED_PATIENT_DATA)?;
+        ctx.accounts.used_offsets.release(computation_offset);
+
+        let o = match output {
+            ComputationOutputs::Success(ShareCommittedPatientDataOutput { field_0 }) => field_0,
+            _ => {
+                emit!(SharePatientDataAbortedEvent {
+                    comp_def_offset: COMP_DEF_OFFSET_SHARE_COMMITTED_PATIENT_DATA,
+                    computation_offset,
+                });
+                return Ok(());
+            }
+        };
+
+        let ([patient_id, age, gender, blood_type, weight, height], allergies) =
+            split_record_output(&o.ciphertexts)?;
+        let event = ReceivedPatientDataEvent {
+            patient_record: ctx.accounts.patient_commitment.key(),
+            receiver_enc_key: o.encryption_key,
+            computation_offset,
+            slot: Clock::get()?.slot,
+            nonce: o.nonce.to_le_bytes(),
+            patient_id,
+            age,
+            gender,
+            blood_type,
+            weight,
+            height,
+            allergies,
+        };
+        if ctx.accounts.program_config.event_cpi {
+            emit_cpi!(event);
+        } else {
+            emit!(event);
+        }
+        Ok(())
+    }
+
+    /// Binds a did:sol or did:key identifier to the caller's provider profile.
+    ///
+    /// The binding stays unverified until `verify_provider_did` proves control of the
+    /// DID's verification method.
+    ///
+    /// # Arguments
+    /// * `did` - DID of the provider's existing identity system
+    /// * `verification_method_hash` - SHA-256 of the verification method's ed25519 key
+    pub fn bind_provider_did(
+        ctx: Context<UpdateProviderDid>,
+        did: String,
+        verification_method_hash: [u8; 32],
+    ) -> Result<()> {
+        require!(did.len() <= MAX_DID_LEN, ErrorCode::DidTooLong);
+        let profile = &mut ctx.accounts.provider_profile;
+        profile.did = did;
+        profile.did_verification_method_hash = verification_method_hash;
+        profile.did_verified = false;
+
+        emit!(ProviderDidBoundEvent {
+            provider: profile.provider,
+            did: profile.did.clone(),
+            verified: false,
+        });
+        Ok(())
+    }
+
+    /// Marks the caller's DID binding verified using a signed challenge.
+    ///
+    /// Expects the instruction right before this one to be an ed25519 program
+    /// verification of `did_challenge` signed by the DID's verification method key.
+    ///
+    /// # Arguments
+    /// * `verification_key` - ed25519 key of the DID's verification method
+    /// * `expires_at` - Unix timestamp after which the signed challenge is rejected
+    pub fn verify_provider_did(
+        ctx: Context<UpdateProviderDid>,
+        verification_key: [u8; 32],
+        expires_at: i64,
+    ) -> Result<()> {
+        require!(
+            Clock::get()?.unix_timestamp <= expires_at,
+            ErrorCode::DidChallengeExpired
+        );
+        let profile = &mut ctx.accounts.provider_profile;
+        require!(
+            profile.matches_verification_method(&verification_key),
+            ErrorCode::DidVerificationMethodMismatch
+        );
+        verify_ed25519_signature(
+            &ctx.accounts.instructions_sysvar,
+            &verification_key,
+            &did_challenge(&profile.provider, &profile.did, expires_at),
+        )?;
+        profile.did_verified = true;
+
+        emit!(ProviderDidBoundEvent {
+            provider: profile.provider,
+            did: profile.did.clone(),
+            verified: true,
+        });
+        Ok(())
+    }
+
+    pub fn init_verify_age_over_comp_def(ctx: Context<InitVerifyAgeOverCompDef>) -> Result<()> {
+        let source = ctx
+            .accounts
+            .program_config
+            .circuit_source(COMP_DEF_OFFSET_VERIFY_AGE_OVER)?;
+        init_comp_def(ctx.accounts, true, 0, Some(source), None)?;
+        ctx.accounts
+            .program_config
+            .mark_circuit_initialized(COMP_DEF_OFFSET_VERIFY_AGE_OVER)
+    }
+
+    /// Checks in MPC that the caller's record shows an age of at least `min_age`.
+    ///
+    /// On success the callback issues an `AgeOver` attestation valid for `validity`
+    /// seconds, replacing any earlier one; only the pass or fail result is revealed. The
+    /// record is read under the key and nonce it was stored with.
+    ///
+    /// # Arguments
+    /// * `min_age` - Age the patient claims to be at least
+    /// * `validity` - Seconds the attestation stays valid after issuance
+    pub fn verify_age_over(
+        ctx: Context<VerifyAgeOver>,
+        computation_offset: u64,
+        min_age: u8,
+        validity: i64,
+    ) -> Result<()> {
+        ctx.accounts.program_config.require_compute_enabled()?;
+        ctx.accounts.patient_data.require_live()?;
+        require!(
+            validity > 0 && validity <= MAX_ATTESTATION_VALIDITY,
+            ErrorCode::InvalidAttestationValidity
+        );
+
+        let attestation = &mut ctx.accounts.attestation;
+        attestation.subject = ctx.accounts.patient_data.key();
+        attestation.holder = ctx.accounts.patient.key();
+        attestation.credential_type = CredentialType::AgeOver;
+        attestation.claim_value = min_age as u64;
+        attestation.validity = validity;
+        attestation.issued_at = 0;
+        attestation.expires_at = 0;
+        attestation.expires_at_slot = 0;
+        attestation.credential_hash = [0; 32];
+        attestation.bump = ctx.bumps.attestation;
+
+        require_ciphertext_region::<PatientData>(&ctx.accounts.patient_data.to_account_info())?;
+        let patient_data = &ctx.accounts.patient_data;
+        let args = verify_age_over_args(
+            patient_data.enc_pubkey,
+            patient_data.nonce,
+            patient_data.key(),
+            min_age,
+        );
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+        ctx.accounts
+            .program_config
+            .record_queued(COMP_DEF_OFFSET_VERIFY_AGE_OVER)?;
+        ctx.accounts.used_offsets.claim(
+            ctx.accounts.patient.key(),
+            ctx.bumps.used_offsets,
+            computation_offset,
+            &ctx.accounts.program_config,
+        )?;
+        ctx.accounts.callback_guard.expect(
+            computation_offset,
+            COMP_DEF_OFFSET_VERIFY_AGE_OVER,
+            ctx.bumps.callback_guard,
+        );
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![VerifyAgeOverCallback::callback_ix(&[
+                CallbackAccount {
+                    pubkey: ctx.accounts.program_config.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.callback_guard.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.attestation.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.used_offsets.key(),
+                    is_writable: true,
+                },
+            ])],
+        )?;
+        Ok(())
+    }
+
+    /// Issues the attestation when the age check passed, through `AttestationIssuedEvent`.
+    #[arcium_callback(encrypted_ix = "verify_age_over")]
+    pub fn verify_age_over_callback(
+        ctx: Context<VerifyAgeOverCallback>,
+        output: ComputationOutputs<VerifyAgeOverOutput>,
+    ) -> Result<()> {
+        let computation_offset = ctx
+            .accounts
+            .callback_guard
+            .consume(COMP_DEF_OFFSET_VERIFY_AGE_OVER)?;
+        verify_callback_invocation(
+            &ctx.accounts.instructions_sysvar,
+            &ctx.accounts.comp_def_account.key(),
+            computation_offset,
+        )?;
+        ctx.accounts.used_offsets.release(computation_offset);
+        ctx.accounts
+            .program_config
+            .record_completed(COMP_DEF_OFFSET_VERIFY_AGE_OVER)?;
+
+        let attestation = &mut ctx.accounts.attestation;
+        let (passed, aborted) = match output {
+            ComputationOutputs::Success(VerifyAgeOverOutput { field_0 }) => (field_0, false),
+            _ => (false, true),
+        };
+        if !passed {
+            emit!(AttestationDeniedEvent {
+                attestation: attestation.key(),
+                subject: attestation.subject,
+                credential_type: attestation.credential_type,
+                claim_value: attestation.claim_value,
+                aborted,
+            });
+            return Ok(());
+        }
+
+        let clock = ctx.accounts.program_config.expiry_clock()?;
+        attestation.issued_at = clock.unix_timestamp;
+        attestation.expires_at = clock
+            .unix_timestamp
+            .checked_add(attestation.validity)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        attestation.expires_at_slot = clock.slot_at(attestation.expires_at);
+        attestation.credential_hash = attestation.compute_credential_hash();
+
+        emit!(AttestationIssuedEvent {
+            attestation: attestation.key(),
+            subject: attestation.subject,
+            credential_type: attestation.credential_type,
+            claim_value: attestation.claim_value,
+            expires_at: attestation.expires_at,
+            credential_hash: attestation.credential_hash,
+        });
+        Ok(())
+    }
+
+    pub fn init_preview_share_comp_def(ctx: Context<InitPreviewShareCompDef>) -> Result<()> {
+        let source = ctx
+            .accounts
+            .program_config
+            .circuit_source(COMP_DEF_OFFSET_PREVIEW_SHARE)?;
+        init_comp_def(ctx.accounts, true, 0, Some(source), None)?;
+        ctx.accounts
+            .program_config
+            .mark_circuit_initialized(COMP_DEF_OFFSET_PREVIEW_SHARE)
+    }
+
+    /// Re-encrypts the fields of the caller's record a share would disclose back to their
+    /// own key, so their app can show exactly that subset before they approve a grant.
+    ///
+    /// The preview is delivered through `SharePreviewEvent`, with every field outside
+    /// `fields` encrypting zero; nothing is shared and no consent is touched.
+    ///
+    /// # Arguments
+    /// * `nonce` - Nonce the record was encrypted with
+    /// * `preview_nonce` - Nonce the preview is re-encrypted under
+    /// * `fields` - Bitmask of `PatientField` bits to disclose
+    pub fn preview_share(
+        ctx: Context<PreviewShare>,
+        computation_offset: u64,
+        nonce: u128,
+        preview_nonce: u128,
+        fields: u16,
+    ) -> Result<()> {
+        ctx.accounts.program_config.require_compute_enabled()?;
+        ctx.accounts.patient_data.require_live()?;
+        require_field_mask(fields)?;
+        ctx.accounts.program_config.require_collected(fields)?;
+
+        require_ciphertext_region::<PatientData>(&ctx.accounts.patient_data.to_account_info())?;
+        let enc_pubkey = ctx.accounts.patient_data.enc_pubkey;
+        let args = preview_share_args(
+            enc_pubkey,
+            preview_nonce,
+            enc_pubkey,
+            nonce,
+            ctx.accounts.patient_data.key(),
+            fields,
+        );
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+        ctx.accounts
+            .program_config
+            .record_queued(COMP_DEF_OFFSET_PREVIEW_SHARE)?;
+        ctx.accounts.used_offsets.claim(
+            ctx.accounts.patient.key(),
+            ctx.bumps.used_offsets,
+            computation_offset,
+            &ctx.accounts.program_config,
+        )?;
+        ctx.accounts.callback_guard.expect(
+            computation_offset,
+            COMP_DEF_OFFSET_PREVIEW_SHARE,
+            ctx.bumps.callback_guard,
+        );
+
+        let mut callback_accounts = vec![
+            CallbackAccount {
+                pubkey: ctx.accounts.program_config.key(),
+                is_writable: true,
+            },
+            CallbackAccount {
+                pubkey: ctx.accounts.callback_guard.key(),
+                is_writable: true,
+            },
+            CallbackAccount {
+                pubkey: ctx.accounts.patient_data.key(),
+                is_writable: false,
+            },
+            CallbackAccount {
+                pubkey: ctx.accounts.used_offsets.key(),
+                is_writable: true,
+            },
+        ];
+        callback_accounts.extend(event_cpi_callback_accounts());
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![PreviewShareCallback::callback_ix(&callback_accounts)],
+        )?;
+        Ok(())
+    }
+
+    /// Delivers a preview, encrypted for the record's own key, through `SharePreviewEvent`.
+    #[arcium_callback(encrypted_ix = "preview_share")]
+    pub fn preview_share_callback(
+        ctx: Context<PreviewShareCallback>,
+        output: ComputationOutputs<PreviewShareOutput>,
+    ) -> Result<()> {
+        let computation_offset = ctx
+            .accounts
+            .callback_guard
+            .consume(COMP_DEF_OFFSET_PREVIEW_SHARE)?;
+        verify_callback_invocation(
+            &ctx.accounts.instructions_sysvar,
+            &ctx.accounts.comp_def_account.key(),
+            computation_offset,
+        )?;
+        ctx.accounts.used_offsets.release(computation_offset);
+        ctx.accounts
+            .program_config
+            .record_completed(COMP_DEF_OFFSET_PREVIEW_SHARE)?;
+
+        let o = match output {
+            ComputationOutputs::Success(PreviewShareOutput { field_0 }) => field_0,
+            _ => {
+                emit!(SharePatientDataAbortedEvent {
+                    comp_def_offset: COMP_DEF_OFFSET_PREVIEW_SHARE,
+                    computation_offset,
+                });
+                return Ok(());
+            }
+        };
+
+        let ([patient_id, age, gender, blood_type, weight, height], allergies) =
+            split_record_output(&o.ciphertexts)?;
+        let event = SharePreviewEvent {
+            patient_record: ctx.accounts.patient_data.key(),
+            computation_offset,
+            nonce: o.nonce.to_le_bytes(),
+            patient_id,
+            age,
+            gender,
+            blood_type,
+            weight,
+            height,
+            allergies,
+        };
+        if ctx.accounts.program_config.event_cpi {
+            emit_cpi!(event);
+        } else {
+            emit!(event);
+        }
+        Ok(())
+    }
+
+    /// Grants a receiver consent to parts of the caller's record, or renews an earlier grant.
+    ///
+    /// # Arguments
+    /// * `receiver` - Wallet the consent is granted to
+    /// * `scope` - Bitmask of `CONSENT_SCOPE_*` values covered
+    /// * `expires_at` - Unix timestamp the consent lapses at
+    pub fn grant_consent(
+        ctx: Context<GrantConsent>,
+        receiver: Pubkey,
+        scope: u16,
+        expires_at: i64,
+    ) -> Result<()> {
+        let event = record_consent_grant(
+            &mut ctx.accounts.consent_grant,
+            &ctx.accounts.patient_data,
+            receiver,
+            scope,
+            expires_at,
+            ctx.bumps.consent_grant,
+        )?;
+        ctx.accounts.patient_registry.audit(
+            event.patient_data,
+            ctx.bumps.patient_registry,
+            &event,
+        )?;
+        emit!(event);
+        Ok(())
+    }
+
+    /// Grants consent on a patient's behalf, as submitted by a relayer.
+    ///
+    /// The patient signs a `PatientApproval` off-chain, e.g. in a mobile wallet, and the
+    /// relayer includes an ed25519 program instruction verifying it immediately before
+    /// this one. The approval covers the record's current audit sequence, so it is used
+    /// once and lapses if anything else is audited on the record first.
+    ///
+    /// # Arguments
+    /// * `patient` - Wallet of the patient who owns the record
+    /// * `approval_expires_at` - Unix timestamp after which the signed approval is rejected
+    /// * remaining arguments as in `grant_consent`
+    pub fn grant_consent_sponsored(
+        ctx: Context<GrantConsentSponsored>,
+        patient: Pubkey,
+        receiver: Pubkey,
+        scope: u16,
+        expires_at: i64,
+        approval_expires_at: i64,
+    ) -> Result<()> {
+        PatientApproval {
+            action: ApprovalAction::GrantConsent,
+            patient,
+            payload_hash: grant_consent_payload_hash(
+                &receiver,
+                scope,
+                expires_at,
+                ctx.accounts.patient_registry.audit_entries,
+            ),
+            expires_at: approval_expires_at,
+        }
+        .verify(&ctx.accounts.instructions_sysvar)?;
+
+        let event = record_consent_grant(
+            &mut ctx.accounts.consent_grant,
+            &ctx.accounts.patient_data,
+            receiver,
+            scope,
+            expires_at,
+            ctx.bumps.consent_grant,
+        )?;
+        ctx.accounts.patient_registry.audit(
+            event.patient_data,
+            ctx.bumps.patient_registry,
+            &event,
+        )?;
+        emit!(event);
+        Ok(())
+    }
+
+    /// Grants the calling provider consent to several patients' records at once, e.g.
+    /// when onboarding a ward.
+    ///
+    /// Each patient signs a `GrantConsent` approval off-chain as for
+    /// `grant_consent_sponsored`; the signatures go, in item order, into one ed25519
+    /// program instruction immediately before this one. Every item is passed as
+    /// `CONSENT_BATCH_ITEM_ACCOUNTS` remaining accounts: the record, its consent grant
+    /// to the provider and its registry. An item that fails its checks is skipped and
+    /// reported with its error number in `ConsentBatchProcessedEvent`, so one stale
+    /// approval doesn't fail the whole batch.
+    ///
+    /// # Arguments
+    /// * `items` - Patient, scope and expiry of each consent, and of its approval
+    pub fn process_consents_batch<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ProcessConsentsBatch<'info>>,
+        items: Vec<ConsentBatchItem>,
+    ) -> Result<()> {
+        let ed25519_data = preceding_ed25519_instruction(&ctx.accounts.instructions_sysvar)?;
+        require!(
+            !items.is_empty()
+                && items.len() <= MAX_CONSENT_BATCH_ITEMS
+                && ctx.remaining_accounts.len() == items.len() * CONSENT_BATCH_ITEM_ACCOUNTS
+                && ed25519_data.first().copied().map(usize::from) == Some(items.len()),
+            ErrorCode::ConsentBatchMismatch
+        );
+        let receiver = ctx.accounts.provider.key();
+        let now = Clock::get()?.unix_timestamp;
+
+        let mut results = Vec::with_capacity(items.len());
+        for (index, (item, accounts)) in items
+            .iter()
+            .zip(ctx.remaining_accounts.chunks(CONSENT_BATCH_ITEM_ACCOUNTS))
+            .enumerate()
+        {
+            let patient_data = accounts[0].key();
+            let approved = match item.approve(&receiver, accounts, &ed25519_data, index, now) {
+                Ok(approved) => approved,
+                Err(Error::AnchorError(err)) => {
+                    results.push(ConsentBatchResult {
+                        patient_data,
+                        code: err.error_code_number,
+                    });
+                    continue;
+                }
+                Err(err) => return Err(err),
+            };
+            let event = approved.grant(ctx.accounts, item)?;
+            emit!(event);
+            results.push(ConsentBatchResult {
+                patient_data,
+                code: CONSENT_BATCH_GRANTED,
+            });
+        }
+
+        emit!(ConsentBatchProcessedEvent { receiver, results });
+        Ok(())
+    }
+
+    /// Revokes a consent the caller granted.
+    pub fn revoke_consent(ctx: Context<RevokeConsent>) -> Result<()> {
+        let event = record_consent_revocation(&mut ctx.accounts.consent_grant);
+        ctx.accounts.patient_registry.audit(
+            event.patient_data,
+            ctx.bumps.patient_registry,
+            &event,
+        )?;
+        emit!(event);
+        Ok(())
+    }
+
+    /// Revokes a consent on the granting patient's behalf, as submitted by a relayer.
+    ///
+    /// Authorized like `grant_consent_sponsored`, by an off-chain approval over the
+    /// receiver and the record's current audit sequence.
+    ///
+    /// # Arguments
+    /// * `approval_expires_at` - Unix timestamp after which the signed approval is rejected
+    pub fn revoke_consent_sponsored(
+        ctx: Context<RevokeConsentSponsored>,
+        approval_expires_at: i64,
+    ) -> Result<()> {
+        PatientApproval {
+            action: ApprovalAction::RevokeConsent,
+            patient: ctx.accounts.consent_grant.patient,
+            payload_hash: revoke_consent_payload_hash(
+                &ctx.accounts.consent_grant.receiver,
+                ctx.accounts.patient_registry.audit_entries,
+            ),
+            expires_at: approval_expires_at,
+        }
+        .verify(&ctx.accounts.instructions_sysvar)?;
+
+        let event = record_consent_revocation(&mut ctx.accounts.consent_grant);
+        ctx.accounts.patient_registry.audit(
+            event.patient_data,
+            ctx.bumps.patient_registry,
+            &event,
+        )?;
+        emit!(event);
+        Ok(())
+    }
+
+    /// Posts a Wormhole message attesting to an active consent grant.
+    ///
+    /// EVM health dApps verify the resulting VAA against the guardian set and this
+    /// program's emitter instead of trusting an oracle. Anyone may post the attestation;
+    /// the payer covers the core bridge fee.
+    ///
+    /// # Arguments
+    /// * `nonce` - Wormhole batching nonce passed through to the message
+    pub fn attest_consent_wormhole(ctx: Context<AttestConsentWormhole>, nonce: u32) -> Result<()> {
+        let consent_grant = &ctx.accounts.consent_grant;
+        let clock = ctx.accounts.program_config.expiry_clock()?;
+        let now = clock.unix_timestamp;
+        consent_grant.require_active(&clock)?;
+
+        let payload = consent_attestation_payload(consent_grant, now);
+        let sequence =
+            post_wormhole_message(ctx.accounts, ctx.bumps.wormhole_emitter, nonce, payload)?;
+
+        emit!(ConsentAttestedEvent {
+            consent_grant: ctx.accounts.consent_grant.key(),
+            sequence,
+        });
+        Ok(())
+    }
+
+    /// Creates an address lookup table for the program's static accounts.
+    ///
+    /// Share and other computation transactions pass a dozen fixed PDAs; loading them
+    /// from the table in a versioned transaction keeps them within the size limit.
+    ///
+    /// # Arguments
+    /// * `recent_slot` - Recent slot the table address is derived from
+    pub fn create_lookup_table(ctx: Context<ManageLookupTable>, recent_slot: u64) -> Result<()> {
+        create_program_lookup_table(ctx.accounts, ctx.bumps.lookup_table_authority, recent_slot)
+    }
+
+    /// Adds the static accounts missing from the program's lookup table.
+    ///
+    /// Call again while `LookupTableExtendedEvent::remaining` is non-zero, and after
+    /// approving clusters or registering new circuits.
+    pub fn extend_lookup_table(ctx: Context<ManageLookupTable>) -> Result<()> {
+        extend_program_lookup_table(ctx.accounts, ctx.bumps.lookup_table_authority)
+    }
+
+    /// Pre-authorizes emergency consent for an organization, or updates it.
+    ///
+    /// Nothing is shared until an admissions oracle reports the caller admitted there.
+    ///
+    /// # Arguments
+    /// * `organization` - Wallet of the organization that would admit the patient
+    /// * `scope` - Bitmask of `CONSENT_SCOPE_*` values granted on admission
+    /// * `duration` - Seconds the emergency consent lasts after an admission
+    pub fn set_emergency_consent_policy(
+        ctx: Context<SetEmergencyConsentPolicy>,
+        organization: Pubkey,
+        scope: u16,
+        duration: i64,
+    ) -> Result<()> {
+        require!(
+            scope != 0 && scope & !CONSENT_SCOPE_ALL == 0,
+            ErrorCode::InvalidConsentScope
+        );
+        require!(
+            duration > 0 && duration <= MAX_EMERGENCY_CONSENT_DURATION,
+            ErrorCode::InvalidEmergencyDuration
+        );
+
+        let policy = &mut ctx.accounts.emergency_policy;
+        policy.patient_data = ctx.accounts.patient_data.key();
+        policy.patient = ctx.accounts.patient.key();
+        policy.organization = organization;
+        policy.scope = scope;
+        policy.duration = duration;
+        policy.bump = ctx.bumps.emergency_policy;
+
+        emit!(EmergencyConsentPolicySetEvent {
+            emergency_policy: policy.key(),
+            patient_data: policy.patient_data,
+            organization,
+            scope,
+            duration,
+        });
+        Ok(())
+    }
+
+    /// Reports a patient admitted by an organization, activating its emergency consent.
+    ///
+    /// Called by a whitelisted admissions oracle, which pays for the consent grant if
+    /// the patient had none for the organization.
+    pub fn report_admission(ctx: Context<ReportAdmission>) -> Result<()> {
+        let policy = &mut ctx.accounts.emergency_policy;
+        require!(!policy.admitted, ErrorCode::PatientAlreadyAdmitted);
+        let consent_grant = &mut ctx.accounts.consent_grant;
+        policy.activate(consent_grant, &ctx.accounts.program_config.expiry_clock()?)?;
+        consent_grant.bump = ctx.bumps.consent_grant;
+        Inbox::notify(
+            &ctx.accounts.inbox,
+            NotificationKind::EmergencyAccessUsed,
+            policy.key(),
+        )?;
+
+        emit!(PatientAdmittedEvent {
+            emergency_policy: policy.key(),
+            consent_grant: consent_grant.key(),
+            organization: policy.organization,
+            oracle: ctx.accounts.oracle.key(),
+            expires_at: consent_grant.expires_at,
+        });
+        Ok(())
+    }
+
+    /// Reports a patient discharged, ending the emergency consent early.
+    pub fn report_discharge(ctx: Context<ReportAdmission>) -> Result<()> {
+        let policy = &mut ctx.accounts.emergency_policy;
+        require!(policy.admitted, ErrorCode::PatientNotAdmitted);
+        policy.deactivate(
+            &mut ctx.accounts.consent_grant,
+            &ctx.accounts.program_config.expiry_clock()?,
+        );
+
+        emit!(PatientDischargedEvent {
+            emergency_policy: policy.key(),
+            organization: policy.organization,
+            oracle: ctx.accounts.oracle.key(),
+        });
+        Ok(())
+    }
+
+    /// Binds a `.sol` domain the caller owns to their provider profile.
+    ///
+    /// Ownership is read from the SNS name account, so a domain can be rebound by
+    /// whoever owns it now.
+    ///
+    /// # Arguments
+    /// * `name` - Domain without the `.sol` suffix
+    pub fn bind_provider_name(ctx: Context<BindProviderName>, name: String) -> Result<()> {
+        require!(name.len() <= MAX_SNS_NAME_LEN, ErrorCode::SnsNameTooLong);
+        let provider = ctx.accounts.provider.key();
+        require_keys_eq!(
+            sns_name_owner(&ctx.accounts.name_account)?,
+            provider,
+            ErrorCode::SnsNameNotOwned
+        );
+
+        let name_account = ctx.accounts.name_account.key();
+        let binding = &mut ctx.accounts.provider_name;
+        binding.provider = provider;
+        binding.name_account = name_account;
+        binding.name = name;
+        binding.bound_at = Clock::get()?.unix_timestamp;
+        binding.bump = ctx.bumps.provider_name;
+        ctx.accounts.provider_profile.sns_name_account = name_account;
+
+        emit!(ProviderNameBoundEvent {
+            provider,
+            name_account,
+            name: binding.name.clone(),
+        });
+        Ok(())
+    }
+
+    /// Removes a name binding whose domain changed hands or that the provider replaced.
+    ///
+    /// Permissionless, so stale names stop resolving to a provider without their help.
+    pub fn clear_provider_name(ctx: Context<ClearProviderName>) -> Result<()> {
+        let binding = &ctx.accounts.provider_name;
+        let profile = &mut ctx.accounts.provider_profile;
+        let owned =
+            sns_name_owner(&ctx.accounts.name_account).is_ok_and(|owner| owner == binding.provider);
+        let current = owned && profile.sns_name_account == binding.name_account;
+        require!(!current, ErrorCode::SnsNameStillOwned);
+        if profile.sns_name_account == binding.name_account {
+            profile.sns_name_account = Pubkey::default();
+        }
+
+        emit!(ProviderNameClearedEvent {
+            provider: binding.provider,
+            name_account: binding.name_account,
+        });
+        Ok(())
+    }
+
+    /// Publishes or updates the field layout of a record type.
+    ///
+    /// # Arguments
+    /// * `record_type` - Name of the record type, e.g. `PatientData`
+    /// * `version` - Layout version, higher than any previously published
+    /// * `ciphertexts_len` - Length of the ciphertexts at the start of the account
+    /// * `fields` - Field layout, non-overlapping
+    pub fn publish_schema(
+        ctx: Context<PublishSchema>,
+        record_type: String,
+        version: u16,
+        ciphertexts_len: u32,
+        fields: Vec<FieldDescriptor>,
+    ) -> Result<()> {
+        require!(
+            !record_type.is_empty() && record_type.len() <= MAX_SCHEMA_NAME_LEN,
+            ErrorCode::InvalidSchemaDescriptor
+        );
+        SchemaDescriptor::validate_fields(&fields)?;
+        SchemaDescriptor::validate_ciphertexts_len(&record_type, ciphertexts_len)?;
+        let schema_descriptor = &mut ctx.accounts.schema_descriptor;
+        require!(
+            version > schema_descriptor.version,
+            ErrorCode::StaleSchemaVersion
+        );
+
+        schema_descriptor.record_type = record_type;
+        schema_descriptor.version = version;
+        schema_descriptor.ciphertexts_len = ciphertexts_len;
+        schema_descriptor.fields = fields;
+        schema_descriptor.bump = ctx.bumps.schema_descriptor;
+
+        emit!(SchemaPublishedEvent {
+            schema_descriptor: schema_descriptor.key(),
+            record_type: schema_descriptor.record_type.clone(),
+            version,
+        });
+        Ok(())
+    }
+
+    /// Publishes or updates the permitted values of a coded field.
+    ///
+    /// # Arguments
+    /// * `name` - Name of the code set, e.g. `blood_type`
+    /// * `system` - Identifier of the code system its codes belong to
+    /// * `version` - Version of the set, higher than any previously published
+    /// * `values` - Permitted values, each with a distinct scalar and code
+    pub fn publish_code_set(
+        ctx: Context<PublishCodeSet>,
+        name: String,
+        system: String,
+        version: u16,
+        values: Vec<CodedValue>,
+    ) -> Result<()> {
+        require!(
+            !name.is_empty() && name.len() <= MAX_CODE_SET_NAME_LEN,
+            ErrorCode::InvalidCodeSet
+        );
+        CodeSet::validate_values(&system, &values)?;
+        let code_set = &mut ctx.accounts.code_set;
+        require!(version > code_set.version, ErrorCode::StaleCodeSetVersion);
+
+        code_set.name = name;
+        code_set.system = system;
+        code_set.version = version;
+        code_set.values = values;
+        code_set.bump = ctx.bumps.code_set;
+
+        emit!(CodeSetPublishedEvent {
+            code_set: code_set.key(),
+            name: code_set.name.clone(),
+            version,
+        });
+        Ok(())
+    }
+
+    pub fn init_share_bundle_comp_def(ctx: Context<InitShareBundleCompDef>) -> Result<()> {
+        let source = ctx
+            .accounts
+            .program_config
+            .circuit_source(COMP_DEF_OFFSET_SHARE_BUNDLE)?;
+        init_comp_def(ctx.accounts, true, 0, Some(source), None)?;
+        ctx.accounts
+            .program_config
+            .mark_circuit_initialized(COMP_DEF_OFFSET_SHARE_BUNDLE)
+    }
+
+    /// Re-encrypts the caller's record together with the content keys of their lab
+    /// results and prescriptions for a receiver, in one computation.
+    ///
+    /// The labs and prescriptions are the caller's FHIR anchors of `anchor_id`s
+    /// `labs_anchor_id` and `prescriptions_anchor_id`, which must hold `DiagnosticReport`
+    /// and `MedicationRequest` bundles. The result is delivered through
+    /// `BundleSharedEvent`, so a receiver gets the whole bundle from one share instead of
+    /// orchestrating one per record.
+    ///
+    /// # Arguments
+    /// * `receiver` - x25519 key the bundle is re-encrypted for
+    /// * `receiver_nonce` - Nonce the bundle is re-encrypted under
+    /// * `labs_anchor_id` - `anchor_id` of the labs FHIR anchor
+    /// * `prescriptions_anchor_id` - `anchor_id` of the prescriptions FHIR anchor
+    /// * `record` - Key and nonce the record was encrypted with
+    /// * `labs` - Key and nonce the labs content key was encrypted with
+    /// * `prescriptions` - Key and nonce the prescriptions content key was encrypted with
+    #[allow(clippy::too_many_arguments)]
+    pub fn share_bundle(
+        ctx: Context<ShareBundle>,
+        computation_offset: u64,
+        receiver: [u8; 32],
+        receiver_nonce: u128,
+        labs_anchor_id: u64,
+        prescriptions_anchor_id: u64,
+        record: BundleSource,
+        labs: BundleSource,
+        prescriptions: BundleSource,
+    ) -> Result<()> {
+        // Only part of the anchor addresses, checked by their seeds
+        let _ = (labs_anchor_id, prescriptions_anchor_id);
+        ctx.accounts.program_config.require_share_enabled()?;
+        ctx.accounts.patient_data.require_live()?;
+        require!(
+            record.pub_key == ctx.accounts.patient_data.enc_pubkey,
+            ErrorCode::SenderKeyMismatch
+        );
+        require_valid_x25519_key(&receiver)?;
+
+        require_ciphertext_region::<PatientData>(&ctx.accounts.patient_data.to_account_info())?;
+        require_ciphertext_region::<FhirAnchor>(&ctx.accounts.labs_anchor.to_account_info())?;
+        require_ciphertext_region::<FhirAnchor>(
+            &ctx.accounts.prescriptions_anchor.to_account_info(),
+        )?;
+        let args = share_bundle_args(
+            receiver,
+            receiver_nonce,
+            (record, ctx.accounts.patient_data.key()),
+            (labs, ctx.accounts.labs_anchor.key()),
+            (prescriptions, ctx.accounts.prescriptions_anchor.key()),
+        );
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+        ctx.accounts
+            .program_config
+            .record_queued(COMP_DEF_OFFSET_SHARE_BUNDLE)?;
+        ctx.accounts.used_offsets.claim(
+            ctx.accounts.patient.key(),
+            ctx.bumps.used_offsets,
+            computation_offset,
+            &ctx.accounts.program_config,
+        )?;
+        ctx.accounts.callback_guard.expect(
+            computation_offset,
+            COMP_DEF_OFFSET_SHARE_BUNDLE,
+            ctx.bumps.callback_guard,
+        );
+
+        let mut callback_accounts = vec![
+            CallbackAccount {
+                pubkey: ctx.accounts.program_config.key(),
+                is_writable: true,
+            },
+            CallbackAccount {
+                pubkey: ctx.accounts.callback_guard.key(),
+                is_writable: true,
+            },
+            CallbackAccount {
+                pubkey: ctx.accounts.patient_data.key(),
+                is_writable: false,
+            },
+            CallbackAccount {
+                pubkey: ctx.accounts.labs_anchor.key(),
+                is_writable: false,
+            },
+            CallbackAccount {
+                pubkey: ctx.accounts.prescriptions_anchor.key(),
+                is_writable: false,
+            },
+            CallbackAccount {
+                pubkey: ctx.accounts.used_offsets.key(),
+                is_writable: true,
+            },
+        ];
+        callback_accounts.extend(event_cpi_callback_accounts());
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![ShareBundleCallback::callback_ix(&callback_accounts)],
+        )?;
+
+        let event = BundleShareQueuedEvent {
+            patient_data: ctx.accounts.patient_data.key(),
+            labs_anchor: ctx.accounts.labs_anchor.key(),
+            prescriptions_anchor: ctx.accounts.prescriptions_anchor.key(),
+            receiver,
+            computation_offset,
+        };
+        ctx.accounts.patient_registry.audit(
+            event.patient_data,
+            ctx.bumps.patient_registry,
+            &event,
+        )?;
+        emit!(event);
+        Ok(())
+    }
+
+    /// Delivers a re-encrypted record bundle through `BundleSharedEvent`.
+    #[arcium_callback(encrypted_ix = "share_bundle")]
+    pub fn share_bundle_callback(
+        ctx: Context<ShareBundleCallback>,
+        output: ComputationOutputs<ShareBundleOutput>,
+    ) -> Result<()> {
+        let computation_offset = ctx
+            .accounts
+            .callback_guard
+            .consume(COMP_DEF_OFFSET_SHARE_BUNDLE)?;
+        verify_callback_invocation(
+            &ctx.accounts.instructions_sysvar,
+            &ctx.accounts.comp_def_account.key(),
+            computation_offset,
+        )?;
+        ctx.accounts.used_offsets.release(computation_offset);
+        ctx.accounts
+            .program_config
+            .record_completed(COMP_DEF_OFFSET_SHARE_BUNDLE)?;
+
+        let o = match output {
+            ComputationOutputs::Success(ShareBundleOutput { field_0 }) => field_0,
+            _ => {
+                emit!(BundleShareAbortedEvent {
+                    patient_data: ctx.accounts.patient_data.key(),
+                    computation_offset,
+                });
+                return Ok(());
+            }
+        };
+
+        let (record, content_keys) = o.ciphertexts.split_at(PATIENT_RECORD_BLOCKS);
+        let [labs_lo, labs_hi, prescriptions_lo, prescriptions_hi] = *content_keys else {
+            return err!(ErrorCode::ComputationOutputLength);
+        };
+        let event = BundleSharedEvent {
+            patient_record: ctx.accounts.patient_data.key(),
+            labs_anchor: ctx.accounts.labs_anchor.key(),
+            prescriptions_anchor: ctx.accounts.prescriptions_anchor.key(),
+            receiver_enc_key: o.encryption_key,
+            computation_offset,
+            slot: Clock::get()?.slot,
+            nonce: o.nonce.to_le_bytes(),
+            record: record
+                .try_into()
+                .map_err(|_| ErrorCode::ComputationOutputLength)?,
+            labs: EncryptedContentKey::new([labs_lo, labs_hi]),
+            prescriptions: EncryptedContentKey::new([prescriptions_lo, prescriptions_hi]),
+        };
+        if ctx.accounts.program_config.event_cpi {
+            emit_cpi!(event);
+        } else {
+            emit!(event);
+        }
+        Ok(())
+    }
+
+    pub fn init_validate_prior_auth_comp_def(
+        ctx: Context<InitValidatePriorAuthCompDef>,
+    ) -> Result<()> {
+        let source = ctx
+            .accounts
+            .program_config
+            .circuit_source(COMP_DEF_OFFSET_VALIDATE_PRIOR_AUTH)?;
+        init_comp_def(ctx.accounts, true, 0, Some(source), None)?;
+        ctx.accounts
+            .program_config
+            .mark_circuit_initialized(COMP_DEF_OFFSET_VALIDATE_PRIOR_AUTH)
+    }
+
+    /// Files a request that `insurer_profile`'s insurer authorize a procedure for the
+    /// patient of `patient_data`.
+    ///
+    /// Called by active registered providers other than insurers.
+    ///
+    /// # Arguments
+    /// * `request_id` - Provider chosen identifier, part of the request address
+    /// * `diagnosis` - Diagnosis code value, encrypted
+    /// * `procedure` - Procedure code value, encrypted
+    /// * `enc_pubkey` - x25519 key the codes were encrypted with
+    /// * `nonce` - Nonce the codes were encrypted with
+    pub fn request_prior_auth(
+        ctx: Context<RequestPriorAuth>,
+        request_id: u64,
+        diagnosis: [u8; 32],
+        procedure: [u8; 32],
+        enc_pubkey: [u8; 32],
+        nonce: u128,
+    ) -> Result<()> {
+        ctx.accounts.program_config.require_store_enabled()?;
+        ctx.accounts.patient_data.require_live()?;
+        require_valid_x25519_key(&enc_pubkey)?;
+
+        let prior_auth = &mut ctx.accounts.prior_auth;
+        prior_auth.diagnosis = diagnosis;
+        prior_auth.procedure = procedure;
+        prior_auth.provider = ctx.accounts.provider.key();
+        prior_auth.insurer = ctx.accounts.insurer_profile.provider;
+        prior_auth.patient_data = ctx.accounts.patient_data.key();
+        prior_auth.request_id = request_id;
+        prior_auth.enc_pubkey = enc_pubkey;
+        prior_auth.nonce = nonce;
+        prior_auth.status = PriorAuthStatus::Pending;
+        prior_auth.computation_offset = 0;
+        prior_auth.validating_since = 0;
+        prior_auth.decided_at = 0;
+        prior_auth.bump = ctx.bumps.prior_auth;
+
+        emit!(PriorAuthRequestedEvent {
+            prior_auth: prior_auth.key(),
+            provider: prior_auth.provider,
+            insurer: prior_auth.insurer,
+            patient_data: prior_auth.patient_data,
+            request_id,
+        });
+        Ok(())
+    }
+
+    /// Checks in MPC whether a pending request's codes match a pair the insurer's
+    /// coverage policy covers.
+    ///
+    /// The callback writes the approval, denial or need for review to the request; neither
+    /// the codes nor the policy are revealed.
+    ///
+    /// # Arguments
+    /// * `insurer_pub_key` - Insurer's x25519 key the policy was encrypted with
+    /// * `insurer_nonce` - Nonce the policy was encrypted with
+    /// * `policy` - Covered diagnosis codes, then the procedure codes paired with them
+    pub fn validate_prior_auth(
+        ctx: Context<ValidatePriorAuth>,
+        computation_offset: u64,
+        insurer_pub_key: [u8; 32],
+        insurer_nonce: u128,
+        policy: EncryptedCoveragePolicy,
+    ) -> Result<()> {
+        ctx.accounts.program_config.require_compute_enabled()?;
+        require!(
+            ctx.accounts.prior_auth.status == PriorAuthStatus::Pending,
+            ErrorCode::PriorAuthNotPending
+        );
+        require_valid_x25519_key(&insurer_pub_key)?;
+
+        let prior_auth = &mut ctx.accounts.prior_auth;
+        prior_auth.status = PriorAuthStatus::Validating;
+        prior_auth.computation_offset = computation_offset;
+        prior_auth.validating_since = Clock::get()?.unix_timestamp;
+
+        require_ciphertext_region::<PriorAuthRequest>(&prior_auth.to_account_info())?;
+        let args = validate_prior_auth_args(
+            prior_auth.enc_pubkey,
+            prior_auth.nonce,
+            prior_auth.key(),
+            insurer_pub_key,
+            insurer_nonce,
+            &policy,
+        );
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+        ctx.accounts
+            .program_config
+            .record_queued(COMP_DEF_OFFSET_VALIDATE_PRIOR_AUTH)?;
+        ctx.accounts.used_offsets.claim(
+            ctx.accounts.insurer.key(),
+            ctx.bumps.used_offsets,
+            computation_offset,
+            &ctx.accounts.program_config,
+        )?;
+        ctx.accounts.callback_guard.expect(
+            computation_offset,
+            COMP_DEF_OFFSET_VALIDATE_PRIOR_AUTH,
+            ctx.bumps.callback_guard,
+        );
+
+        let mut callback_accounts = vec![
+            CallbackAccount {
+                pubkey: ctx.accounts.program_config.key(),
+                is_writable: true,
+            },
+            CallbackAccount {
+                pubkey: ctx.accounts.callback_guard.key(),
+                is_writable: true,
+            },
+            CallbackAccount {
+                pubkey: ctx.accounts.prior_auth.key(),
+                is_writable: true,
+            },
+            CallbackAccount {
+                pubkey: ctx.accounts.used_offsets.key(),
+                is_writable: true,
+            },
+        ];
+        callback_accounts.extend(event_cpi_callback_accounts());
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![ValidatePriorAuthCallback::callback_ix(&callback_accounts)],
+        )?;
+        Ok(())
+    }
+
+    /// Writes the insurer's decision to the request, through `PriorAuthDecidedEvent`, or
+    /// marks it `NeedsReview` through `PriorAuthNeedsReviewEvent`.
+    ///
+    /// An aborted validation returns the request to `Pending` for the insurer to retry.
+    #[arcium_callback(encrypted_ix = "validate_prior_auth")]
+    pub fn validate_prior_auth_callback(
+        ctx: Context<ValidatePriorAuthCallback>,
+        output: ComputationOutputs<ValidatePriorAuthOutput>,
+    ) -> Result<()> {
+        let computation_offset = ctx
+            .accounts
+            .callback_guard
+            .consume(COMP_DEF_OFFSET_VALIDATE_PRIOR_AUTH)?;
+        verify_callback_invocation(
+            &ctx.accounts.instructions_sysvar,
+            &ctx.accounts.comp_def_account.key(),
+            computation_offset,
+        )?;
+        ctx.accounts.used_offsets.release(computation_offset);
+        ctx.accounts
+            .program_config
+            .record_completed(COMP_DEF_OFFSET_VALIDATE_PRIOR_AUTH)?;
+
+        let prior_auth = &mut ctx.accounts.prior_auth;
+        let outcome = match output {
+            ComputationOutputs::Success(ValidatePriorAuthOutput { field_0 }) => field_0,
+            _ => {
+                prior_auth.status = PriorAuthStatus::Pending;
+                emit!(PriorAuthValidationAbortedEvent {
+                    prior_auth: prior_auth.key(),
+                    computation_offset,
+                });
+                return Ok(());
+            }
+        };
+
+        if outcome == PRIOR_AUTH_OUTCOME_NEEDS_REVIEW {
+            prior_auth.status = PriorAuthStatus::NeedsReview;
+            let event = PriorAuthNeedsReviewEvent {
+                prior_auth: prior_auth.key(),
+                provider: prior_auth.provider,
+                insurer: prior_auth.insurer,
+                patient_data: prior_auth.patient_data,
+            };
+            if ctx.accounts.program_config.event_cpi {
+                emit_cpi!(event);
+            } else {
+                emit!(event);
+            }
+            return Ok(());
+        }
+
+        let approved = outcome == PRIOR_AUTH_OUTCOME_APPROVED;
+        prior_auth.status = if approved {
+            PriorAuthStatus::Approved
+        } else {
+            PriorAuthStatus::Denied
+        };
+        prior_auth.decided_at = Clock::get()?.unix_timestamp;
+
+        let event = PriorAuthDecidedEvent {
+            prior_auth: prior_auth.key(),
+            provider: prior_auth.provider,
+            insurer: prior_auth.insurer,
+            approved,
+            decided_at: prior_auth.decided_at,
+        };
+        if ctx.accounts.program_config.event_cpi {
+            emit_cpi!(event);
+        } else {
+            emit!(event);
+        }
+        Ok(())
+    }
+
+    /// Returns a prior authorization request whose validation was never called back to
+    /// `Pending`.
+    ///
+    /// Callable by anyone once the configured computation timeout has passed since the
+    /// validation was queued. Consumes its callback guard so a late callback is rejected;
+    /// the insurer may then validate the request again.
+    pub fn expire_prior_auth_validation(ctx: Context<ExpirePriorAuthValidation>) -> Result<()> {
+        let prior_auth = &mut ctx.accounts.prior_auth;
+        require!(
+            Clock::get()?.unix_timestamp
+                >= prior_auth
+                    .validating_since
+                    .saturating_add(ctx.accounts.program_config.computation_timeout),
+            ErrorCode::ComputationNotTimedOut
+        );
+        ctx.accounts
+            .callback_guard
+            .consume(COMP_DEF_OFFSET_VALIDATE_PRIOR_AUTH)?;
+        ctx.accounts
+            .used_offsets
+            .release(prior_auth.computation_offset);
+        ctx.accounts
+            .program_config
+            .record_completed(COMP_DEF_OFFSET_VALIDATE_PRIOR_AUTH)?;
+
+        emit!(PriorAuthValidationExpiredEvent {
+            prior_auth: prior_auth.key(),
+            insurer: prior_auth.insurer,
+            computation_offset: prior_auth.computation_offset,
+        });
+        prior_auth.status = PriorAuthStatus::Pending;
+        Ok(())
+    }
+
+    pub fn init_issue_prescription_comp_def(
+        ctx: Context<InitIssuePrescriptionCompDef>,
+    ) -> Result<()> {
+        let source = ctx
+            .accounts
+            .program_config
+            .circuit_source(COMP_DEF_OFFSET_ISSUE_PRESCRIPTION)?;
+        init_comp_def(ctx.accounts, true, 0, Some(source), None)?;
+        ctx.accounts
+            .program_config
+            .mark_circuit_initialized(COMP_DEF_OFFSET_ISSUE_PRESCRIPTION)
+    }
+
+    pub fn init_verify_prescription_comp_def(
+        ctx: Context<InitVerifyPrescriptionCompDef>,
+    ) -> Result<()> {
+        let source = ctx
+            .accounts
+            .program_config
+            .circuit_source(COMP_DEF_OFFSET_VERIFY_PRESCRIPTION)?;
+        init_comp_def(ctx.accounts, true, 0, Some(source), None)?;
+        ctx.accounts
+            .program_config
+            .mark_circuit_initialized(COMP_DEF_OFFSET_VERIFY_PRESCRIPTION)
+    }
+
+    /// Issues a prescription against a patient's record, moving its refill count into
+    /// state encrypted to the cluster.
+    ///
+    /// Called by active providers registered as `Physician`. The prescription becomes
+    /// active once the callback stores the count.
+    ///
+    /// # Arguments
+    /// * `prescription_id` - Prescriber chosen identifier, part of the prescription address
+    /// * `mxe_nonce` - Nonce to encrypt the refill count to the cluster under
+    /// * `prescriber_pub_key` - Prescriber's x25519 key the count was encrypted with
+    /// * `nonce` - Nonce the count was encrypted with
+    /// * `refills` - Number of fills allowed, encrypted as a `u8`
+    #[allow(clippy::too_many_arguments)]
+    pub fn issue_prescription(
+        ctx: Context<IssuePrescription>,
+        computation_offset: u64,
+        prescription_id: u64,
+        mxe_nonce: u128,
+        prescriber_pub_key: [u8; 32],
+        nonce: u128,
+        refills: [u8; 32],
+    ) -> Result<()> {
+        ctx.accounts.program_config.require_compute_enabled()?;
+        ctx.accounts.patient_data.require_live()?;
+        require_valid_x25519_key(&prescriber_pub_key)?;
+
+        let prescription = &mut ctx.accounts.prescription;
+        prescription.refills = [0; 32];
+        prescription.refills_nonce = 0;
+        prescription.patient_data = ctx.accounts.patient_data.key();
+        prescription.prescriber = ctx.accounts.prescriber.key();
+        prescription.prescription_id = prescription_id;
+        prescription.status = PrescriptionStatus::Issuing;
+        prescription.pharmacy = Pubkey::default();
+        prescription.dispensed = 0;
+        prescription.bump = ctx.bumps.prescription;
+
+        let args = issue_prescription_args(mxe_nonce, prescriber_pub_key, nonce, refills);
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+        ctx.accounts
+            .program_config
+            .record_queued(COMP_DEF_OFFSET_ISSUE_PRESCRIPTION)?;
+        ctx.accounts.used_offsets.claim(
+            ctx.accounts.prescriber.key(),
+            ctx.bumps.used_offsets,
+            computation_offset,
+            &ctx.accounts.program_config,
+        )?;
+        ctx.accounts.callback_guard.expect(
+            computation_offset,
+            COMP_DEF_OFFSET_ISSUE_PRESCRIPTION,
+            ctx.bumps.callback_guard,
+        );
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![IssuePrescriptionCallback::callback_ix(&[
+                CallbackAccount {
+                    pubkey: ctx.accounts.program_config.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.callback_guard.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.prescription.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.used_offsets.key(),
+                    is_writable: true,
+                },
+            ])],
+        )?;
+        Ok(())
+    }
+
+    /// Stores the cluster-encrypted refill count and activates the prescription, through
+    /// `PrescriptionIssuedEvent`.
+    #[arcium_callback(encrypted_ix = "issue_prescription")]
+    pub fn issue_prescription_callback(
+        ctx: Context<IssuePrescriptionCallback>,
+        output: ComputationOutputs<IssuePrescriptionOutput>,
+    ) -> Result<()> {
+        let computation_offset = ctx
+            .accounts
+            .callback_guard
+            .consume(COMP_DEF_OFFSET_ISSUE_PRESCRIPTION)?;
+        verify_callback_invocation(
+            &ctx.accounts.instructions_sysvar,
+            &ctx.accounts.comp_def_account.key(),
+            computation_offset,
+        )?;
+        ctx.accounts.used_offsets.release(computation_offset);
+        ctx.accounts
+            .program_config
+            .record_completed(COMP_DEF_OFFSET_ISSUE_PRESCRIPTION)?;
+
+        let prescription = &mut ctx.accounts.prescription;
+        let aborted = match output {
+            ComputationOutputs::Success(IssuePrescriptionOutput { field_0 }) => {
+                prescription.refills = field_0.ciphertexts[0];
+                prescription.refills_nonce = field_0.nonce;
+                prescription.status = PrescriptionStatus::Active;
+                false
+            }
+            _ => true,
+        };
+
+        emit!(PrescriptionIssuedEvent {
+            prescription: prescription.key(),
+            patient_data: prescription.patient_data,
+            prescriber: prescription.prescriber,
+            aborted,
+        });
+        Ok(())
+    }
+
+    /// Counts a pharmacy's fill against an active prescription's refills in MPC.
+    ///
+    /// Called by active providers registered as `Pharmacist`. The prescription is held for
+    /// the calling pharmacy until the callback: when a refill was left it stays authorized
+    /// for that pharmacy alone to record with `dispense_record`, otherwise it is exhausted.
+    pub fn verify_prescription(
+        ctx: Context<VerifyPrescription>,
+        computation_offset: u64,
+    ) -> Result<()> {
+        ctx.accounts.program_config.require_compute_enabled()?;
+        require!(
+            ctx.accounts.prescription.status == PrescriptionStatus::Active,
+            ErrorCode::PrescriptionNotActive
+        );
+
+        let prescription = &mut ctx.accounts.prescription;
+        prescription.status = PrescriptionStatus::Verifying;
+        prescription.pharmacy = ctx.accounts.pharmacy.key();
+        prescription.computation_offset = computation_offset;
+        prescription.verifying_since = Clock::get()?.unix_timestamp;
+
+        require_ciphertext_region::<Prescription>(&prescription.to_account_info())?;
+        let args = verify_prescription_args(prescription.refills_nonce, prescription.key());
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+        ctx.accounts
+            .program_config
+            .record_queued(COMP_DEF_OFFSET_VERIFY_PRESCRIPTION)?;
+        ctx.accounts.used_offsets.claim(
+            ctx.accounts.pharmacy.key(),
+            ctx.bumps.used_offsets,
+            computation_offset,
+            &ctx.accounts.program_config,
+        )?;
+        ctx.accounts.callback_guard.expect(
+            computation_offset,
+            COMP_DEF_OFFSET_VERIFY_PRESCRIPTION,
+            ctx.bumps.callback_guard,
+        );
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![VerifyPrescriptionCallback::callback_ix(&[
+                CallbackAccount {
+                    pubkey: ctx.accounts.program_config.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.callback_guard.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.prescription.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.used_offsets.key(),
+                    is_writable: true,
+                },
+            ])],
+        )?;
+        Ok(())
+    }
+
+    /// Stores the counted-down refills and authorizes or exhausts the prescription,
+    /// through `PrescriptionVerifiedEvent`.
+    ///
+    /// An aborted computation leaves the count untouched and reactivates the prescription.
+    #[arcium_callback(encrypted_ix = "verify_prescription")]
+    pub fn verify_prescription_callback(
+        ctx: Context<VerifyPrescriptionCallback>,
+        output: ComputationOutputs<VerifyPrescriptionOutput>,
+    ) -> Result<()> {
+        let computation_offset = ctx
+            .accounts
+            .callback_guard
+            .consume(COMP_DEF_OFFSET_VERIFY_PRESCRIPTION)?;
+        verify_callback_invocation(
+            &ctx.accounts.instructions_sysvar,
+            &ctx.accounts.comp_def_account.key(),
+            computation_offset,
+        )?;
+        ctx.accounts.used_offsets.release(computation_offset);
+        ctx.accounts
+            .program_config
+            .record_completed(COMP_DEF_OFFSET_VERIFY_PRESCRIPTION)?;
+
+        let prescription = &mut ctx.accounts.prescription;
+        let pharmacy = prescription.pharmacy;
+        let (dispensable, aborted) = match output {
+            ComputationOutputs::Success(VerifyPrescriptionOutput {
+                field_0:
+                    VerifyPrescriptionOutputStruct0 {
+                        field_0: refills,
+                        field_1: dispensable,
+                    },
+            }) => {
+                prescription.refills = refills.ciphertexts[0];
+                prescription.refills_nonce = refills.nonce;
+                (dispensable, false)
+            }
+            _ => (false, true),
+        };
+        prescription.status = match (aborted, dispensable) {
+            (true, _) => PrescriptionStatus::Active,
+            (false, true) => PrescriptionStatus::Authorized,
+            (false, false) => PrescriptionStatus::Exhausted,
+        };
+        if prescription.status != PrescriptionStatus::Authorized {
+            prescription.pharmacy = Pubkey::default();
+        }
+
+        emit!(PrescriptionVerifiedEvent {
+            prescription: prescription.key(),
+            pharmacy,
+            dispensable,
+            aborted,
+        });
+        Ok(())
+    }
+
+    /// Reactivates a prescription whose fill verification was never called back.
+    ///
+    /// Callable by anyone once the configured computation timeout has passed since the
+    /// fill was queued. Consumes its callback guard so a late callback is rejected. The
+    /// refill count is left as it was, as for an aborted verification.
+    pub fn expire_prescription_verification(
+        ctx: Context<ExpirePrescriptionVerification>,
+    ) -> Result<()> {
+        let prescription = &mut ctx.accounts.prescription;
+        require!(
+            Clock::get()?.unix_timestamp
+                >= prescription
+                    .verifying_since
+                    .saturating_add(ctx.accounts.program_config.computation_timeout),
+            ErrorCode::ComputationNotTimedOut
+        );
+        ctx.accounts
+            .callback_guard
+            .consume(COMP_DEF_OFFSET_VERIFY_PRESCRIPTION)?;
+        ctx.accounts
+            .used_offsets
+            .release(prescription.computation_offset);
+        ctx.accounts
+            .program_config
+            .record_completed(COMP_DEF_OFFSET_VERIFY_PRESCRIPTION)?;
+
+        emit!(PrescriptionVerificationExpiredEvent {
+            prescription: prescription.key(),
+            pharmacy: prescription.pharmacy,
+            computation_offset: prescription.computation_offset,
+        });
+        prescription.status = PrescriptionStatus::Active;
+        prescription.pharmacy = Pubkey::default();
+        Ok(())
+    }
+
+    /// Records the fill the calling pharmacy was authorized for by `verify_prescription`
+    /// in the prescription's dispensing ledger, releasing it for the next fill.
+    pub fn dispense_record(ctx: Context<DispenseRecord>) -> Result<()> {
+        let prescription = &mut ctx.accounts.prescription;
+        let fill = prescription.dispensed;
+        prescription.dispensed = fill.checked_add(1).ok_or(ErrorCode::ArithmeticOverflow)?;
+        prescription.status = PrescriptionStatus::Active;
+        prescription.pharmacy = Pubkey::default();
+
+        let dispense_entry = &mut ctx.accounts.dispense_entry;
+        dispense_entry.prescription = prescription.key();
+        dispense_entry.pharmacy = ctx.accounts.pharmacy.key();
+        dispense_entry.fill = fill;
+        dispense_entry.dispensed_at = Clock::get()?.unix_timestamp;
+        dispense_entry.bump = ctx.bumps.dispense_entry;
+
+        emit!(PrescriptionDispensedEvent {
+            prescription: prescription.key(),
+            dispense_entry: dispense_entry.key(),
+            pharmacy: dispense_entry.pharmacy,
+            fill,
+        });
+        Ok(())
+    }
+
+    /// Publishes an employer's clearance requirements for people to check their records
+    /// against.
+    ///
+    /// # Arguments
+    /// * `template_id` - Employer chosen identifier, part of the template address
+    /// * `min_age` - Age a record must show at least
+    /// * `excluded_allergies` - Allergy slots a record must not list, one bit per slot
+    /// * `validity` - Seconds a passed clearance stays valid
+    pub fn create_clearance_template(
+        ctx: Context<CreateClearanceTemplate>,
+        template_id: u64,
+        min_age: u8,
+        excluded_allergies: u8,
+        validity: i64,
+    ) -> Result<()> {
+        require!(
+            u32::from(excluded_allergies) >> CLEARANCE_ALLERGY_SLOTS == 0,
+            ErrorCode::InvalidClearanceTemplate
+        );
+        require!(
+            validity > 0 && validity <= MAX_ATTESTATION_VALIDITY,
+            ErrorCode::InvalidAttestationValidity
+        );
+
+        let template = &mut ctx.accounts.clearance_template;
+        template.employer = ctx.accounts.employer.key();
+        template.template_id = template_id;
+        template.min_age = min_age;
+        template.excluded_allergies = excluded_allergies;
+        template.validity = validity;
+        template.bump = ctx.bumps.clearance_template;
+
+        emit!(ClearanceTemplateCreatedEvent {
+            template: template.key(),
+            employer: template.employer,
+            template_id,
+            min_age,
+            excluded_allergies,
+        });
+        Ok(())
+    }
+
+    pub fn init_clearance_check_comp_def(ctx: Context<InitClearanceCheckCompDef>) -> Result<()> {
+        let source = ctx
+            .accounts
+            .program_config
+            .circuit_source(COMP_DEF_OFFSET_CLEARANCE_CHECK)?;
+        init_comp_def(ctx.accounts, true, 0, Some(source), None)?;
+        ctx.accounts
+            .program_config
+            .mark_circuit_initialized(COMP_DEF_OFFSET_CLEARANCE_CHECK)
+    }
+
+    /// Checks the caller's record against an employer's clearance template in MPC.
+    ///
+    /// The callback writes only pass or fail to the record's `Clearance` for the template,
+    /// replacing any earlier result; the employer never sees a field value. The record is
+    /// read under the key and nonce it was stored with.
+    pub fn clearance_check(ctx: Context<ClearanceCheck>, computation_offset: u64) -> Result<()> {
+        ctx.accounts.program_config.require_compute_enabled()?;
+        ctx.accounts.patient_data.require_live()?;
+
+        let template = &ctx.accounts.clearance_template;
+        let clearance = &mut ctx.accounts.clearance;
+        clearance.template = template.key();
+        clearance.employer = template.employer;
+        clearance.subject = ctx.accounts.patient_data.key();
+        clearance.holder = ctx.accounts.patient.key();
+        clearance.passed = false;
+        clearance.validity = template.validity;
+        clearance.checked_at = 0;
+        clearance.expires_at = 0;
+        clearance.expires_at_slot = 0;
+        clearance.bump = ctx.bumps.clearance;
+
+        require_ciphertext_region::<PatientData>(&ctx.accounts.patient_data.to_account_info())?;
+        let patient_data = &ctx.accounts.patient_data;
+        let args = clearance_check_args(
+            patient_data.enc_pubkey,
+            patient_data.nonce,
+            patient_data.key(),
+            template.min_age,
+            template.excluded_allergies,
+        );
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+        ctx.accounts
+            .program_config
+            .record_queued(COMP_DEF_OFFSET_CLEARANCE_CHECK)?;
+        ctx.accounts.used_offsets.claim(
+            ctx.accounts.patient.key(),
+            ctx.bumps.used_offsets,
+            computation_offset,
+            &ctx.accounts.program_config,
+        )?;
+        ctx.accounts.callback_guard.expect(
+            computation_offset,
+            COMP_DEF_OFFSET_CLEARANCE_CHECK,
+            ctx.bumps.callback_guard,
+        );
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![ClearanceCheckCallback::callback_ix(&[
+                CallbackAccount {
+                    pubkey: ctx.accounts.program_config.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.callback_guard.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.clearance.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.used_offsets.key(),
+                    is_writable: true,
+                },
+            ])],
+        )?;
+        Ok(())
+    }
+
+    /// Writes the pass or fail result to the clearance, through `ClearanceCheckedEvent`.
+    #[arcium_callback(encrypted_ix = "clearance_check")]
+    pub fn clearance_check_callback(
+        ctx: Context<ClearanceCheckCallback>,
+        output: ComputationOutputs<ClearanceCheckOutput>,
+    ) -> Result<()> {
+        let computation_offset = ctx
+            .accounts
+            .callback_guard
+            .consume(COMP_DEF_OFFSET_CLEARANCE_CHECK)?;
+        verify_callback_invocation(
+            &ctx.accounts.instructions_sysvar,
+            &ctx.accounts.comp_def_account.key(),
+            computation_offset,
+        )?;
+        ctx.accounts.used_offsets.release(computation_offset);
+        ctx.accounts
+            .program_config
+            .record_completed(COMP_DEF_OFFSET_CLEARANCE_CHECK)?;
+
+        let clearance = &mut ctx.accounts.clearance;
+        let (passed, aborted) = match output {
+            ComputationOutputs::Success(ClearanceCheckOutput { field_0 }) => (field_0, false),
+            _ => (false, true),
+        };
+        if !aborted {
+            let clock = ctx.accounts.program_config.expiry_clock()?;
+            clearance.passed = passed;
+            clearance.checked_at = clock.unix_timestamp;
+            if passed {
+                clearance.expires_at = clock
+                    .unix_timestamp
+                    .checked_add(clearance.validity)
+                    .ok_or(ErrorCode::ArithmeticOverflow)?;
+                clearance.expires_at_slot = clock.slot_at(clearance.expires_at);
+            }
+        }
+
+        emit!(ClearanceCheckedEvent {
+            clearance: clearance.key(),
+            template: clearance.template,
+            employer: clearance.employer,
+            subject: clearance.subject,
+            passed,
+            aborted,
+            expires_at: clearance.expires_at,
+        });
+        Ok(())
+    }
+
+    /// Opens a telehealth session, granting the provider consent to the caller's record
+    /// that lapses after `max_slots` slots unless the session is closed first.
+    ///
+    /// The provider must not already hold an active consent, so closing the session
+    /// never cuts short a standing one.
+    ///
+    /// # Arguments
+    /// * `session_id` - Identifier the telehealth platform assigned the session
+    /// * `provider` - Wallet the session's consent is granted to
+    /// * `scope` - Bitmask of `CONSENT_SCOPE_*` values covered
+    /// * `max_slots` - Slots the consent lasts at most, up to `MAX_SESSION_SLOTS`
+    pub fn open_session(
+        ctx: Context<OpenSession>,
+        session_id: [u8; 16],
+        provider: Pubkey,
+        scope: u16,
+        max_slots: u64,
+    ) -> Result<()> {
+        require!(
+            max_slots > 0 && max_slots <= MAX_SESSION_SLOTS,
+            ErrorCode::InvalidSessionLength
+        );
+        let clock = ctx.accounts.program_config.expiry_clock()?;
+        require!(
+            !ctx.accounts.consent_grant.is_active(&clock),
+            ErrorCode::SessionConsentActive
+        );
+
+        let duration = (max_slots * MS_PER_SLOT).div_ceil(1000) as i64;
+        let expires_at = clock
+            .unix_timestamp
+            .checked_add(duration)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        let event = record_consent_grant(
+            &mut ctx.accounts.consent_grant,
+            &ctx.accounts.patient_data,
+            provider,
+            scope,
+            expires_at,
+            ctx.bumps.consent_grant,
+        )?;
+        let ends_at_slot = clock.slot.saturating_add(max_slots);
+        ctx.accounts.consent_grant.expires_at_slot = ends_at_slot;
+
+        let session = &mut ctx.accounts.session;
+        session.patient_data = ctx.accounts.patient_data.key();
+        session.patient = ctx.accounts.patient.key();
+        session.provider = provider;
+        session.session_id = session_id;
+        session.scope = scope;
+        session.opened_at = ctx.accounts.consent_grant.granted_at;
+        session.ends_at_slot = ends_at_slot;
+        session.closed_at = 0;
+        session.bump = ctx.bumps.session;
+
+        ctx.accounts.patient_registry.audit(
+            event.patient_data,
+            ctx.bumps.patient_registry,
+            &event,
+        )?;
+        emit!(event);
+        emit!(TelehealthSessionOpenedEvent {
+            session: session.key(),
+            patient_data: session.patient_data,
+            provider,
+            session_id,
+            consent_grant: ctx.accounts.consent_grant.key(),
+            scope,
+            ends_at_slot,
+        });
+        Ok(())
+    }
+
+    /// Closes a telehealth session, revoking its consent if the patient has not renewed
+    /// it since. Either the patient or the provider may close it.
+    pub fn close_session(ctx: Context<CloseSession>) -> Result<()> {
+        let session = &mut ctx.accounts.session;
+        require!(session.closed_at == 0, ErrorCode::SessionClosed);
+        session.closed_at = Clock::get()?.unix_timestamp;
+
+        let consent_grant = &mut ctx.accounts.consent_grant;
+        let consent_revoked =
+            !consent_grant.revoked && consent_grant.granted_at == session.opened_at;
+        if consent_revoked {
+            let event = record_consent_revocation(consent_grant);
+            ctx.accounts.patient_registry.audit(
+                event.patient_data,
+                ctx.bumps.patient_registry,
+                &event,
+            )?;
+            emit!(event);
+        }
+
+        emit!(TelehealthSessionClosedEvent {
+            session: session.key(),
+            patient_data: session.patient_data,
+            provider: session.provider,
+            closed_by: ctx.accounts.closer.key(),
+            consent_revoked,
+        });
+        Ok(())
+    }
+
+    /// Registers the EMS authority of a region, or rotates its key.
+    ///
+    /// # Arguments
+    /// * `region` - Region the authority dispatches for
+    /// * `authority` - Wallet operating the authority
+    /// * `enc_pubkey` - x25519 key emergency packets for the region are encrypted for
+    pub fn set_ems_authority(
+        ctx: Context<SetEmsAuthority>,
+        region: u32,
+        authority: Pubkey,
+        enc_pubkey: [u8; 32],
+    ) -> Result<()> {
+        let ems_authority = &mut ctx.accounts.ems_authority;
+        ems_authority.region = region;
+        ems_authority.authority = authority;
+        ems_authority.enc_pubkey = enc_pubkey;
+        ems_authority.updated_at = Clock::get()?.unix_timestamp;
+        ems_authority.bump = ctx.bumps.ems_authority;
+
+        emit!(EmsAuthoritySetEvent {
+            ems_authority: ems_authority.key(),
+            region,
+            authority,
+            enc_pubkey,
+        });
+        Ok(())
+    }
+
+    pub fn init_emergency_packet_comp_def(ctx: Context<InitEmergencyPacketCompDef>) -> Result<()> {
+        let source = ctx
+            .accounts
+            .program_config
+            .circuit_source(COMP_DEF_OFFSET_EMERGENCY_PACKET)?;
+        init_comp_def(ctx.accounts, true, 0, Some(source), None)?;
+        ctx.accounts
+            .program_config
+            .mark_circuit_initialized(COMP_DEF_OFFSET_EMERGENCY_PACKET)
+    }
+
+    /// Re-encrypts the caller's age, blood type and allergies for their regional EMS
+    /// authority into their `EmergencyPacket`, replacing any earlier packet on delivery.
+    ///
+    /// # Arguments
+    /// * `ems_nonce` - Nonce for the packet's encryption to the authority's key
+    /// * `sender_pub_key` - Patient's x25519 key the record was encrypted with
+    /// * `nonce` - Nonce the record was encrypted with
+    pub fn generate_emergency_packet(
+        ctx: Context<GenerateEmergencyPacket>,
+        computation_offset: u64,
+        ems_nonce: u128,
+        sender_pub_key: [u8; 32],
+        nonce: u128,
+    ) -> Result<()> {
+        ctx.accounts.program_config.require_compute_enabled()?;
+        ctx.accounts.patient_data.require_live()?;
+
+        let packet = &mut ctx.accounts.emergency_packet;
+        packet.patient_data = ctx.accounts.patient_data.key();
+        packet.pending_region = ctx.accounts.ems_authority.region;
+        packet.pending_record_version = ctx.accounts.patient_data.version;
+        packet.bump = ctx.bumps.emergency_packet;
+
+        require_ciphertext_region::<PatientData>(&ctx.accounts.patient_data.to_account_info())?;
+        let args = emergency_packet_args(
+            ctx.accounts.ems_authority.enc_pubkey,
+            ems_nonce,
+            sender_pub_key,
+            nonce,
+            ctx.accounts.patient_data.key(),
+        );
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+        ctx.accounts
+            .program_config
+            .record_queued(COMP_DEF_OFFSET_EMERGENCY_PACKET)?;
+        ctx.accounts.used_offsets.claim(
+            ctx.accounts.patient.key(),
+            ctx.bumps.used_offsets,
+            computation_offset,
+            &ctx.accounts.program_config,
+        )?;
+        ctx.accounts.callback_guard.expect(
+            computation_offset,
+            COMP_DEF_OFFSET_EMERGENCY_PACKET,
+            ctx.bumps.callback_guard,
+        );
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![EmergencyPacketCallback::callback_ix(&[
+                CallbackAccount {
+                    pubkey: ctx.accounts.program_config.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.callback_guard.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.emergency_packet.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.used_offsets.key(),
+                    is_writable: true,
+                },
+            ])],
+        )?;
+        Ok(())
+    }
+
+    /// Writes the re-encrypted fields into the emergency packet.
+    #[arcium_callback(encrypted_ix = "emergency_packet")]
+    pub fn emergency_packet_callback(
+        ctx: Context<EmergencyPacketCallback>,
+        output: ComputationOutputs<EmergencyPacketOutput>,
+    ) -> Result<()> {
+        let computation_offset = ctx
+            .accounts
+            .callback_guard
+            .consume(COMP_DEF_OFFSET_EMERGENCY_PACKET)?;
+        verify_callback_invocation(
+            &ctx.accounts.instructions_sysvar,
+            &ctx.accounts.comp_def_account.key(),
+            computation_offset,
+        )?;
+        ctx.accounts.used_offsets.release(computation_offset);
+        ctx.accounts
+            .program_config
+            .record_completed(COMP_DEF_OFFSET_EMERGENCY_PACKET)?;
+
+        let packet = &mut ctx.accounts.emergency_packet;
+        let o = match output {
+            ComputationOutputs::Success(EmergencyPacketOutput { field_0 }) => field_0,
+            _ => {
+                emit!(EmergencyPacketFailedEvent {
+                    emergency_packet: packet.key(),
+                    patient_data: packet.patient_data,
+                    computation_offset,
+                });
+                return Ok(());
+            }
+        };
+
+        let slot = Clock::get()?.slot;
+        packet.ciphertexts = o
+            .ciphertexts
+            .as_slice()
+            .try_into()
+            .map_err(|_| ErrorCode::ComputationOutputLength)?;
+        packet.receiver_enc_key = o.encryption_key;
+        packet.nonce = o.nonce.to_le_bytes();
+        packet.region = packet.pending_region;
+        packet.record_version = packet.pending_record_version;
+        packet.computation_offset = computation_offset;
+        packet.generated_slot = slot;
+
+        emit!(EmergencyPacketStoredEvent {
+            emergency_packet: packet.key(),
+            patient_data: packet.patient_data,
+            region: packet.region,
+            record_version: packet.record_version,
+            slot,
+        });
+        Ok(())
+    }
+
+    /// Files an encrypted adverse drug event report.
+    ///
+    /// Called by a patient with a record or an active provider; pass `patient_data` or
+    /// `provider_profile` accordingly. The report is counted into a sponsor's tally with
+    /// `tally_adverse_event`.
+    ///
+    /// # Arguments
+    /// * `report_id` - Reporter chosen identifier, part of the report address
+    /// * `enc_pubkey` - Reporter's x25519 key the codes were encrypted with
+    /// * `nonce` - Nonce the codes were encrypted with
+    /// * `drug_code` - Code of the suspected drug, encrypted as a `u64`
+    /// * `reaction` - Code of the observed reaction, encrypted as a `u64`
+    pub fn file_adverse_event(
+        ctx: Context<FileAdverseEvent>,
+        report_id: u64,
+        enc_pubkey: [u8; 32],
+        nonce: u128,
+        drug_code: [u8; 32],
+        reaction: [u8; 32],
+    ) -> Result<()> {
+        let is_patient = match &ctx.accounts.patient_data {
+            Some(patient_data) => {
+                patient_data.require_live()?;
+                true
+            }
+            None => false,
+        };
+        let is_provider = ctx
+            .accounts
+            .provider_profile
+            .as_ref()
+            .is_some_and(|profile| profile.active);
+        require!(is_patient || is_provider, ErrorCode::ReporterNotEligible);
+        require_valid_x25519_key(&enc_pubkey)?;
+
+        let report = &mut ctx.accounts.report;
+        report.drug_code = drug_code;
+        report.reaction = reaction;
+        report.reporter = ctx.accounts.reporter.key();
+        report.report_id = report_id;
+        report.enc_pubkey = enc_pubkey;
+        report.nonce = nonce;
+        report.tally = Pubkey::default();
+        report.filed_at = Clock::get()?.unix_timestamp;
+        report.bump = ctx.bumps.report;
+
+        emit!(AdverseEventFiledEvent {
+            report: report.key(),
+            reporter: report.reporter,
+            report_id,
+        });
+        Ok(())
+    }
+
+    pub fn init_adverse_event_tally_comp_def(
+        ctx: Context<InitAdverseEventTallyCompDef>,
+    ) -> Result<()> {
+        let source = ctx
+            .accounts
+            .program_config
+            .circuit_source(COMP_DEF_OFFSET_INIT_ADVERSE_EVENT_TALLY)?;
+        init_comp_def(ctx.accounts, true, 0, Some(source), None)?;
+        ctx.accounts
+            .program_config
+            .mark_circuit_initialized(COMP_DEF_OFFSET_INIT_ADVERSE_EVENT_TALLY)
+    }
+
+    pub fn init_tally_adverse_event_comp_def(
+        ctx: Context<InitTallyAdverseEventCompDef>,
+    ) -> Result<()> {
+        let source = ctx
+            .accounts
+            .program_config
+            .circuit_source(COMP_DEF_OFFSET_TALLY_ADVERSE_EVENT)?;
+        init_comp_def(ctx.accounts, true, 0, Some(source), None)?;
+        ctx.accounts
+            .program_config
+            .mark_circuit_initialized(COMP_DEF_OFFSET_TALLY_ADVERSE_EVENT)
+    }
+
+    pub fn init_adverse_event_counts_comp_def(
+        ctx: Context<InitAdverseEventCountsCompDef>,
+    ) -> Result<()> {
+        let source = ctx
+            .accounts
+            .program_config
+            .circuit_source(COMP_DEF_OFFSET_ADVERSE_EVENT_COUNTS)?;
+        init_comp_def(ctx.accounts, true, 0, Some(source), None)?;
+        ctx.accounts
+            .program_config
+            .mark_circuit_initialized(COMP_DEF_OFFSET_ADVERSE_EVENT_COUNTS)
+    }
+
+    /// Opens the caller's surveillance tally for up to `SURVEILLANCE_DRUGS` drugs, with
+    /// every count encrypted to the cluster at zero.
+    ///
+    /// Called by active providers registered as `PharmaSponsor`. The tally accepts reports
+    /// once the callback stores the zeroed counts.
+    ///
+    /// # Arguments
+    /// * `drug_codes` - Codes of the sponsor's drugs; zero marks an unused slot
+    /// * `mxe_nonce` - Nonce to encrypt the counts to the cluster under
+    pub fn init_surveillance_tally(
+        ctx: Context<InitSurveillanceTally>,
+        computation_offset: u64,
+        drug_codes: [u64; SURVEILLANCE_DRUGS],
+        mxe_nonce: u128,
+    ) -> Result<()> {
+        ctx.accounts.program_config.require_compute_enabled()?;
+
+        let tally = &mut ctx.accounts.tally;
+        tally.counts = [[0; 32]; SURVEILLANCE_DRUGS];
+        tally.counts_nonce = 0;
+        tally.sponsor = ctx.accounts.sponsor.key();
+        tally.drug_codes = drug_codes;
+        tally.status = TallyStatus::Initializing;
+        tally.reports = 0;
+        tally.bump = ctx.bumps.tally;
+        tally.reports_at_last_reveal = 0;
+        tally.computation_offset = computation_offset;
+        tally.busy_since = Clock::get()?.unix_timestamp;
+        tally.pending_report = Pubkey::default();
+
+        let args = init_adverse_event_tally_args(mxe_nonce);
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+        ctx.accounts
+            .program_config
+            .record_queued(COMP_DEF_OFFSET_INIT_ADVERSE_EVENT_TALLY)?;
+        ctx.accounts.used_offsets.claim(
+            ctx.accounts.sponsor.key(),
+            ctx.bumps.used_offsets,
+            computation_offset,
+            &ctx.accounts.program_config,
+        )?;
+        ctx.accounts.callback_guard.expect(
+            computation_offset,
+            COMP_DEF_OFFSET_INIT_ADVERSE_EVENT_TALLY,
+            ctx.bumps.callback_guard,
+        );
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![InitAdverseEventTallyCallback::callback_ix(&[
+                CallbackAccount {
+                    pubkey: ctx.accounts.program_config.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.callback_guard.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.tally.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.used_offsets.key(),
+                    is_writable: true,
+                },
+            ])],
+        )?;
+        Ok(())
+    }
+
+    /// Stores the zeroed counts and opens the tally for reports, through
+    /// `SurveillanceTallyInitializedEvent`.
+    #[arcium_callback(encrypted_ix = "init_adverse_event_tally")]
+    pub fn init_adverse_event_tally_callback(
+        ctx: Context<InitAdverseEventTallyCallback>,
+        output: ComputationOutputs<InitAdverseEventTallyOutput>,
+    ) -> Result<()> {
+        let computation_offset = ctx
+            .accounts
+            .callback_guard
+            .consume(COMP_DEF_OFFSET_INIT_ADVERSE_EVENT_TALLY)?;
+        verify_callback_invocation(
+            &ctx.accounts.instructions_sysvar,
+            &ctx.accounts.comp_def_account.key(),
+            computation_offset,
+        )?;
+        ctx.accounts.used_offsets.release(computation_offset);
+        ctx.accounts
+            .program_config
+            .record_completed(COMP_DEF_OFFSET_INIT_ADVERSE_EVENT_TALLY)?;
+
+        let tally = &mut ctx.accounts.tally;
+        let aborted = match output {
+            ComputationOutputs::Success(InitAdverseEventTallyOutput { field_0 }) => {
+                tally.counts = field_0
+                    .ciphertexts
+                    .as_slice()
+                    .try_into()
+                    .map_err(|_| ErrorCode::ComputationOutputLength)?;
+                tally.counts_nonce = field_0.nonce;
+                tally.status = TallyStatus::Ready;
+                false
+            }
+            _ => true,
+        };
+
+        emit!(SurveillanceTallyInitializedEvent {
+            tally: tally.key(),
+            sponsor: tally.sponsor,
+            drug_codes: tally.drug_codes,
+            aborted,
+        });
+        Ok(())
+    }
+
+    /// Counts the caller's adverse event report into a sponsor's tally in MPC.
+    ///
+    /// Called by the report's reporter, against the tally listing the reported drug; the
+    /// tally's drug codes are public. A report is counted once: it is bound to the tally
+    /// here and only released again if the computation aborts or expires. Each reporter
+    /// counts at most `MAX_TALLIED_REPORTS_PER_REPORTER` reports into one tally.
+    pub fn tally_adverse_event(
+        ctx: Context<TallyAdverseEvent>,
+        computation_offset: u64,
+    ) -> Result<()> {
+        ctx.accounts.program_config.require_compute_enabled()?;
+        require!(
+            ctx.accounts.tally.status == TallyStatus::Ready,
+            ErrorCode::TallyNotReady
+        );
+        require!(
+            ctx.accounts.report.tally == Pubkey::default(),
+            ErrorCode::AdverseEventAlreadyTallied
+        );
+
+        let reporter_tally = &mut ctx.accounts.reporter_tally;
+        require!(
+            reporter_tally.reports < MAX_TALLIED_REPORTS_PER_REPORTER,
+            ErrorCode::ReporterTallyLimitReached
+        );
+        reporter_tally.tally = ctx.accounts.tally.key();
+        reporter_tally.reporter = ctx.accounts.reporter.key();
+        reporter_tally.reports += 1;
+        reporter_tally.bump = ctx.bumps.reporter_tally;
+
+        ctx.accounts.report.tally = ctx.accounts.tally.key();
+        let report = ctx.accounts.report.key();
+        ctx.accounts.tally.begin(
+            TallyStatus::Counting,
+            computation_offset,
+            report,
+            Clock::get()?.unix_timestamp,
+        );
+
+        require_ciphertext_region::<AdverseEventReport>(&ctx.accounts.report.to_account_info())?;
+        require_ciphertext_region::<SurveillanceTally>(&ctx.accounts.tally.to_account_info())?;
+        let args = tally_adverse_event_args(
+            ctx.accounts.report.enc_pubkey,
+            ctx.accounts.report.nonce,
+            ctx.accounts.report.key(),
+            ctx.accounts.tally.counts_nonce,
+            ctx.accounts.tally.key(),
+            &ctx.accounts.tally.drug_codes,
+        );
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+        ctx.accounts
+            .program_config
+            .record_queued(COMP_DEF_OFFSET_TALLY_ADVERSE_EVENT)?;
+        ctx.accounts.used_offsets.claim(
+            ctx.accounts.reporter.key(),
+            ctx.bumps.used_offsets,
+            computation_offset,
+            &ctx.accounts.program_config,
+        )?;
+        ctx.accounts.callback_guard.expect(
+            computation_offset,
+            COMP_DEF_OFFSET_TALLY_ADVERSE_EVENT,
+            ctx.bumps.callback_guard,
+        );
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![TallyAdverseEventCallback::callback_ix(&[
+                CallbackAccount {
+                    pubkey: ctx.accounts.program_config.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.callback_guard.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.report.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.tally.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.reporter_tally.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.used_offsets.key(),
+                    is_writable: true,
+                },
+            ])],
+        )?;
+        Ok(())
+    }
+
+    /// Stores the updated counts and releases the tally, through
+    /// `AdverseEventTalliedEvent`.
+    ///
+    /// An aborted computation leaves the counts untouched and releases the report to be
+    /// counted again, giving it back to the reporter's cap.
+    #[arcium_callback(encrypted_ix = "tally_adverse_event")]
+    pub fn tally_adverse_event_callback(
+        ctx: Context<TallyAdverseEventCallback>,
+        output: ComputationOutputs<TallyAdverseEventOutput>,
+    ) -> Result<()> {
+        let computation_offset = ctx
+            .accounts
+            .callback_guard
+            .consume(COMP_DEF_OFFSET_TALLY_ADVERSE_EVENT)?;
+        verify_callback_invocation(
+            &ctx.accounts.instructions_sysvar,
+            &ctx.accounts.comp_def_account.key(),
+            computation_offset,
+        )?;
+        ctx.accounts.used_offsets.release(computation_offset);
+        ctx.accounts
+            .program_config
+            .record_completed(COMP_DEF_OFFSET_TALLY_ADVERSE_EVENT)?;
+
+        let tally = &mut ctx.accounts.tally;
+        let report = &mut ctx.accounts.report;
+        let aborted = match output {
+            ComputationOutputs::Success(TallyAdverseEventOutput { field_0 }) => {
+                tally.counts = field_0
+                    .ciphertexts
+                    .as_slice()
+                    .try_into()
+                    .map_err(|_| ErrorCode::ComputationOutputLength)?;
+                tally.counts_nonce = field_0.nonce;
+                tally.reports = tally
+                    .reports
+                    .checked_add(1)
+                    .ok_or(ErrorCode::ArithmeticOverflow)?;
+                false
+            }
+            _ => {
+                report.tally = Pubkey::default();
+                ctx.accounts.reporter_tally.reports =
+                    ctx.accounts.reporter_tally.reports.saturating_sub(1);
+                true
+            }
+        };
+        tally.release();
+
+        emit!(AdverseEventTalliedEvent {
+            report: report.key(),
+            tally: tally.key(),
+            aborted,
+        });
+        Ok(())
+    }
+
+    /// Re-encrypts the caller's tally for them, revealing each drug's count only once it
+    /// reaches `ADVERSE_EVENT_MIN_COUNT`; smaller counts read as zero.
+    ///
+    /// Called by the tally's sponsor, still an active `PharmaSponsor` provider, once the
+    /// tally has counted `ADVERSE_EVENT_REPORTS_PER_REVEAL` reports since its last reveal.
+    /// The counts arrive in `AdverseEventCountsEvent`.
+    ///
+    /// # Arguments
+    /// * `sponsor_pub_key` - Sponsor's x25519 key to encrypt the counts for
+    /// * `sponsor_nonce` - Nonce for the counts' encryption
+    pub fn adverse_event_counts(
+        ctx: Context<AdverseEventCounts>,
+        computation_offset: u64,
+        sponsor_pub_key: [u8; 32],
+        sponsor_nonce: u128,
+    ) -> Result<()> {
+        ctx.accounts.program_config.require_compute_enabled()?;
+        require_valid_x25519_key(&sponsor_pub_key)?;
+        let tally = &mut ctx.accounts.tally;
+        require!(tally.status == TallyStatus::Ready, ErrorCode::TallyNotReady);
+        require!(
+            tally.reports
+                >= tally
+                    .reports_at_last_reveal
+                    .saturating_add(ADVERSE_EVENT_REPORTS_PER_REVEAL),
+            ErrorCode::RevealTooSoon
+        );
+        tally.begin(
+            TallyStatus::Revealing,
+            computation_offset,
+            Pubkey::default(),
+            Clock::get()?.unix_timestamp,
+        );
+
+        require_ciphertext_region::<SurveillanceTally>(&ctx.accounts.tally.to_account_info())?;
+        let args = adverse_event_counts_args(
+            sponsor_pub_key,
+            sponsor_nonce,
+            ctx.accounts.tally.counts_nonce,
+            ctx.accounts.tally.key(),
+            ADVERSE_EVENT_MIN_COUNT,
+        );
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+        ctx.accounts
+            .program_config
+            .record_queued(COMP_DEF_OFFSET_ADVERSE_EVENT_COUNTS)?;
+        ctx.accounts.used_offsets.claim(
+            ctx.accounts.sponsor.key(),
+            ctx.bumps.used_offsets,
+            computation_offset,
+            &ctx.accounts.program_config,
+        )?;
+        ctx.accounts.callback_guard.expect(
+            computation_offset,
+            COMP_DEF_OFFSET_ADVERSE_EVENT_COUNTS,
+            ctx.bumps.callback_guard,
+        );
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![AdverseEventCountsCallback::callback_ix(&[
+                CallbackAccount {
+                    pubkey: ctx.accounts.program_config.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.callback_guard.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.tally.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.used_offsets.key(),
+                    is_writable: true,
+                },
+            ])],
+        )?;
+        Ok(())
+    }
+
+    /// Emits the thresholded counts for the sponsor and releases the tally.
+    #[arcium_callback(encrypted_ix = "adverse_event_counts")]
+    pub fn adverse_event_counts_callback(
+        ctx: Context<AdverseEventCountsCallback>,
+        output: ComputationOutputs<AdverseEventCountsOutput>,
+    ) -> Result<()> {
+        let computation_offset = ctx
+            .accounts
+            .callback_guard
+            .consume(COMP_DEF_OFFSET_ADVERSE_EVENT_COUNTS)?;
+        verify_callback_invocation(
+            &ctx.accounts.instructions_sysvar,
+            &ctx.accounts.comp_def_account.key(),
+            computation_offset,
+        )?;
+        ctx.accounts.used_offsets.release(computation_offset);
+        ctx.accounts
+            .program_config
+            .record_completed(COMP_DEF_OFFSET_ADVERSE_EVENT_COUNTS)?;
+
+        let tally = &mut ctx.accounts.tally;
+        tally.release();
+
+        let event = match output {
+            ComputationOutputs::Success(AdverseEventCountsOutput { field_0 }) => {
+                tally.reports_at_last_reveal = tally.reports;
+                AdverseEventCountsEvent {
+                    tally: tally.key(),
+                    receiver_enc_key: field_0.encryption_key,
+                    nonce: field_0.nonce.to_le_bytes(),
+                    counts: field_0
+                        .ciphertexts
+                        .as_slice()
+                        .try_into()
+                        .map_err(|_| ErrorCode::ComputationOutputLength)?,
+                    aborted: false,
+                }
+            }
+            _ => AdverseEventCountsEvent {
+                tally: tally.key(),
+                receiver_enc_key: [0; 32],
+                nonce: [0; 16],
+                counts: [[0; 32]; SURVEILLANCE_DRUGS],
+                aborted: true,
+            },
+        };
+        emit!(event);
+        Ok(())
+    }
+
+    /// Releases a surveillance tally whose count or reveal was never called back.
+    ///
+    /// Callable by anyone once the configured computation timeout has passed since the
+    /// computation was queued. Consumes its callback guard so a late callback is
+    /// rejected, and releases a report being counted, with its place in the reporter's
+    /// cap, to be counted again. The counts are left as they were.
+    pub fn expire_tally_computation(ctx: Context<ExpireTallyComputation>) -> Result<()> {
+        let tally = &mut ctx.accounts.tally;
+        let comp_def_offset = match tally.status {
+            TallyStatus::Counting => COMP_DEF_OFFSET_TALLY_ADVERSE_EVENT,
+            TallyStatus::Revealing => COMP_DEF_OFFSET_ADVERSE_EVENT_COUNTS,
+            _ => return err!(ErrorCode::TallyNotBusy),
+        };
+        require!(
+            Clock::get()?.unix_timestamp
+                >= tally
+                    .busy_since
+                    .saturating_add(ctx.accounts.program_config.computation_timeout),
+            ErrorCode::ComputationNotTimedOut
+        );
+        ctx.accounts.callback_guard.consume(comp_def_offset)?;
+
+        if tally.status == TallyStatus::Counting {
+            let (Some(report), Some(reporter_tally)) =
+                (&mut ctx.accounts.report, &mut ctx.accounts.reporter_tally)
+            else {
+                return err!(ErrorCode::InvalidTallyReport);
+            };
+            require_keys_eq!(
+                reporter_tally.reporter,
+                report.reporter,
+                ErrorCode::InvalidTallyReport
+            );
+            require_keys_eq!(
+                ctx.accounts.used_offsets.payer,
+                report.reporter,
+                ErrorCode::InvalidTallyReport
+            );
+            report.tally = Pubkey::default();
+            reporter_tally.reports = reporter_tally.reports.saturating_sub(1);
+        } else {
+            require_keys_eq!(
+                ctx.accounts.used_offsets.payer,
+                tally.sponsor,
+                ErrorCode::Unauthorized
+            );
+        }
+        ctx.accounts.used_offsets.release(tally.computation_offset);
+        ctx.accounts
+            .program_config
+            .record_completed(comp_def_offset)?;
+
+        emit!(TallyComputationExpiredEvent {
+            tally: tally.key(),
+            computation_offset: tally.computation_offset,
+            report: tally.pending_report,
+        });
+        tally.release();
+        Ok(())
+    }
+
+    pub fn init_open_case_counts_comp_def(ctx: Context<InitOpenCaseCountsCompDef>) -> Result<()> {
+        let source = ctx
+            .accounts
+            .program_config
+            .circuit_source(COMP_DEF_OFFSET_OPEN_CASE_COUNTS)?;
+        init_comp_def(ctx.accounts, true, 0, Some(source), None)?;
+        ctx.accounts
+            .program_config
+            .mark_circuit_initialized(COMP_DEF_OFFSET_OPEN_CASE_COUNTS)
+    }
+
+    pub fn init_notify_case_comp_def(ctx: Context<InitNotifyCaseCompDef>) -> Result<()> {
+        let source = ctx
+            .accounts
+            .program_config
+            .circuit_source(COMP_DEF_OFFSET_NOTIFY_CASE)?;
+        init_comp_def(ctx.accounts, true, 0, Some(source), None)?;
+        ctx.accounts
+            .program_config
+            .mark_circuit_initialized(COMP_DEF_OFFSET_NOTIFY_CASE)
+    }
+
+    pub fn init_case_counts_comp_def(ctx: Context<InitCaseCountsCompDef>) -> Result<()> {
+        let source = ctx
+            .accounts
+            .program_config
+            .circuit_source(COMP_DEF_OFFSET_CASE_COUNTS)?;
+        init_comp_def(ctx.accounts, true, 0, Some(source), None)?;
+        ctx.accounts
+            .program_config
+            .mark_circuit_initialized(COMP_DEF_OFFSET_CASE_COUNTS)
+    }
+
+    pub fn init_reveal_case_comp_def(ctx: Context<InitRevealCaseCompDef>) -> Result<()> {
+        let source = ctx
+            .accounts
+            .program_config
+            .circuit_source(COMP_DEF_OFFSET_REVEAL_CASE)?;
+        init_comp_def(ctx.accounts, true, 0, Some(source), None)?;
+        ctx.accounts
+            .program_config
+            .mark_circuit_initialized(COMP_DEF_OFFSET_REVEAL_CASE)
+    }
+
+    /// Registers the public-health authority of a region, starting its case counts at
+    /// zero, encrypted to the cluster.
+    ///
+    /// The authority accepts cases once the callback stores the zeroed counts.
+    ///
+    /// # Arguments
+    /// * `region` - Region the authority covers
+    /// * `authority` - Wallet operating the authority
+    /// * `enc_pubkey` - x25519 key counts and revealed cases are encrypted for
+    /// * `legal_committee` - Wallet whose signature reveals a case's identity
+    /// * `disease_codes` - Diagnosis codes of the notifiable diseases; zero marks an unused
+    ///   slot
+    /// * `mxe_nonce` - Nonce to encrypt the counts to the cluster under
+    #[allow(clippy::too_many_arguments)]
+    pub fn register_public_health_authority(
+        ctx: Context<RegisterPublicHealthAuthority>,
+        computation_offset: u64,
+        region: u32,
+        authority: Pubkey,
+        enc_pubkey: [u8; 32],
+        legal_committee: Pubkey,
+        disease_codes: [u64; NOTIFIABLE_DISEASES],
+        mxe_nonce: u128,
+    ) -> Result<()> {
+        ctx.accounts.program_config.require_compute_enabled()?;
+        require_valid_x25519_key(&enc_pubkey)?;
+
+        let public_health_authority = &mut ctx.accounts.public_health_authority;
+        public_health_authority.counts = [[0; 32]; NOTIFIABLE_DISEASES];
+        public_health_authority.counts_nonce = 0;
+        public_health_authority.region = region;
+        public_health_authority.authority = authority;
+        public_health_authority.enc_pubkey = enc_pubkey;
+        public_health_authority.legal_committee = legal_committee;
+        public_health_authority.disease_codes = disease_codes;
+        public_health_authority.status = TallyStatus::Initializing;
+        public_health_authority.cases = 0;
+        public_health_authority.bump = ctx.bumps.public_health_authority;
+
+        let args = open_case_counts_args(mxe_nonce);
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+        ctx.accounts
+            .program_config
+            .record_queued(COMP_DEF_OFFSET_OPEN_CASE_COUNTS)?;
+        ctx.accounts.used_offsets.claim(
+            ctx.accounts.admin.key(),
+            ctx.bumps.used_offsets,
+            computation_offset,
+            &ctx.accounts.program_config,
+        )?;
+        ctx.accounts.callback_guard.expect(
+            computation_offset,
+            COMP_DEF_OFFSET_OPEN_CASE_COUNTS,
+            ctx.bumps.callback_guard,
+        );
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![OpenCaseCountsCallback::callback_ix(&[
+                CallbackAccount {
+                    pubkey: ctx.accounts.program_config.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.callback_guard.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.public_health_authority.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.used_offsets.key(),
+                    is_writable: true,
+                },
+            ])],
+        )?;
+        Ok(())
+    }
+
+    /// Stores the zeroed counts and opens the authority for cases, through
+    /// `PublicHealthAuthorityRegisteredEvent`.
+    #[arcium_callback(encrypted_ix = "open_case_counts")]
+    pub fn open_case_counts_callback(
+        ctx: Context<OpenCaseCountsCallback>,
+        output: ComputationOutputs<OpenCaseCountsOutput>,
+    ) -> Result<()> {
+        let computation_offset = ctx
+            .accounts
+            .callback_guard
+            .consume(COMP_DEF_OFFSET_OPEN_CASE_COUNTS)?;
+        verify_callback_invocation(
+            &ctx.accounts.instructions_sysvar,
+            &ctx.accounts.comp_def_account.key(),
+            computation_offset,
+        )?;
+        ctx.accounts.used_offsets.release(computation_offset);
+        ctx.accounts
+            .program_config
+            .record_completed(COMP_DEF_OFFSET_OPEN_CASE_COUNTS)?;
+
+        let public_health_authority = &mut ctx.accounts.public_health_authority;
+        let aborted = match output {
+            ComputationOutputs::Success(OpenCaseCountsOutput { field_0 }) => {
+                public_health_authority.counts = field_0
+                    .ciphertexts
+                    .as_slice()
+                    .try_into()
+                    .map_err(|_| ErrorCode::ComputationOutputLength)?;
+                public_health_authority.counts_nonce = field_0.nonce;
+                public_health_authority.status = TallyStatus::Ready;
+                false
+            }
+            _ => true,
+        };
+
+        emit!(PublicHealthAuthorityRegisteredEvent {
+            public_health_authority: public_health_authority.key(),
+            region: public_health_authority.region,
+            authority: public_health_authority.authority,
+            legal_committee: public_health_authority.legal_committee,
+            disease_codes: public_health_authority.disease_codes,
+            aborted,
+        });
+        Ok(())
+    }
+
+    /// Reports a notifiable disease case to a region's public-health authority.
+    ///
+    /// Called by active providers. The case is counted against the matching disease and
+    /// sealed to the cluster in MPC; the authority learns only region-level counts.
+    ///
+    /// # Arguments
+    /// * `case_id` - Provider chosen identifier, part of the case address
+    /// * `sealed_nonce` - Nonce to seal the case to the cluster under
+    /// * `provider_pub_key` - Provider's x25519 key the case was encrypted with
+    /// * `nonce` - Nonce the case was encrypted with
+    /// * `diagnosis` - Diagnosis code, encrypted as a `u64`
+    /// * `patient_id` - Patient identifier, encrypted as a `u64`
+    #[allow(clippy::too_many_arguments)]
+    pub fn notify_case(
+        ctx: Context<NotifyCase>,
+        computation_offset: u64,
+        case_id: u64,
+        sealed_nonce: u128,
+        provider_pub_key: [u8; 32],
+        nonce: u128,
+        diagnosis: [u8; 32],
+        patient_id: [u8; 32],
+    ) -> Result<()> {
+        ctx.accounts.program_config.require_compute_enabled()?;
+        require_valid_x25519_key(&provider_pub_key)?;
+        require!(
+            ctx.accounts.public_health_authority.status == TallyStatus::Ready,
+            ErrorCode::TallyNotReady
+        );
+
+        ctx.accounts.public_health_authority.status = TallyStatus::Counting;
+
+        let case_report = &mut ctx.accounts.case_report;
+        case_report.sealed = [[0; 32]; 2];
+        case_report.sealed_nonce = 0;
+        case_report.public_health_authority = ctx.accounts.public_health_authority.key();
+        case_report.provider = ctx.accounts.provider.key();
+        case_report.case_id = case_id;
+        case_report.reported_at = Clock::get()?.unix_timestamp;
+        case_report.counted = false;
+        case_report.revealed_at = 0;
+        case_report.bump = ctx.bumps.case_report;
+
+        let public_health_authority = &ctx.accounts.public_health_authority;
+        require_ciphertext_region::<PublicHealthAuthority>(
+            &public_health_authority.to_account_info(),
+        )?;
+        let args = notify_case_args(
+            sealed_nonce,
+            provider_pub_key,
+            nonce,
+            diagnosis,
+            patient_id,
+            public_health_authority.counts_nonce,
+            public_health_authority.key(),
+            &public_health_authority.disease_codes,
+        );
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+        ctx.accounts
+            .program_config
+            .record_queued(COMP_DEF_OFFSET_NOTIFY_CASE)?;
+        ctx.accounts.used_offsets.claim(
+            ctx.accounts.provider.key(),
+            ctx.bumps.used_offsets,
+            computation_offset,
+            &ctx.accounts.program_config,
+        )?;
+        ctx.accounts.callback_guard.expect(
+            computation_offset,
+            COMP_DEF_OFFSET_NOTIFY_CASE,
+            ctx.bumps.callback_guard,
+        );
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![NotifyCaseCallback::callback_ix(&[
+                CallbackAccount {
+                    pubkey: ctx.accounts.program_config.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.callback_guard.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.public_health_authority.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.case_report.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.used_offsets.key(),
+                    is_writable: true,
+                },
+            ])],
+        )?;
+        Ok(())
+    }
+
+    /// Stores the updated counts and the sealed case, releasing the authority, through
+    /// `CaseNotifiedEvent`.
+    ///
+    /// An aborted computation leaves the counts untouched and the case uncounted; the
+    /// provider reports it again under a new `case_id`.
+    #[arcium_callback(encrypted_ix = "notify_case")]
+    pub fn notify_case_callback(
+        ctx: Context<NotifyCaseCallback>,
+        output: ComputationOutputs<NotifyCaseOutput>,
+    ) -> Result<()> {
+        let computation_offset = ctx
+            .accounts
+            .callback_guard
+            .consume(COMP_DEF_OFFSET_NOTIFY_CASE)?;
+        verify_callback_invocation(
+            &ctx.accounts.instructions_sysvar,
+            &ctx.accounts.comp_def_account.key(),
+            computation_offset,
+        )?;
+        ctx.accounts.used_offsets.release(computation_offset);
+        ctx.accounts
+            .program_config
+            .record_completed(COMP_DEF_OFFSET_NOTIFY_CASE)?;
+
+        let public_health_authority = &mut ctx.accounts.public_health_authority;
+        let case_report = &mut ctx.accounts.case_report;
+        let aborted = match output {
+            ComputationOutputs::Success(NotifyCaseOutput {
+                field_0:
+                    NotifyCaseOutputStruct0 {
+                        field_0: counts,
+                        field_1: sealed,
+                    },
+            }) => {
+                public_health_authority.counts = counts
+                    .ciphertexts
+                    .as_slice()
+                    .try_into()
+                    .map_err(|_| ErrorCode::ComputationOutputLength)?;
+                public_health_authority.counts_nonce = counts.nonce;
+                public_health_authority.cases = public_health_authority
+                    .cases
+                    .checked_add(1)
+                    .ok_or(ErrorCode::ArithmeticOverflow)?;
+                case_report.sealed = sealed
+                    .ciphertexts
+                    .as_slice()
+                    .try_into()
+                    .map_err(|_| ErrorCode::ComputationOutputLength)?;
+                case_report.sealed_nonce = sealed.nonce;
+                case_report.counted = true;
+                false
+            }
+            _ => true,
+        };
+        public_health_authority.status = TallyStatus::Ready;
+
+        emit!(CaseNotifiedEvent {
+            case_report: case_report.key(),
+            public_health_authority: public_health_authority.key(),
+            provider: case_report.provider,
+            aborted,
+        });
+        Ok(())
+    }
+
+    /// Re-encrypts the caller's region-level case counts for them; the counts arrive in
+    /// `CaseCountsEvent`.
+    ///
+    /// Called by the authority's wallet.
+    ///
+    /// # Arguments
+    /// * `authority_nonce` - Nonce for the counts' encryption to the authority's key
+    pub fn case_counts(
+        ctx: Context<CaseCounts>,
+        computation_offset: u64,
+        authority_nonce: u128,
+    ) -> Result<()> {
+        ctx.accounts.program_config.require_compute_enabled()?;
+        require!(
+            ctx.accounts.public_health_authority.status == TallyStatus::Ready,
+            ErrorCode::TallyNotReady
+        );
+
+        let public_health_authority = &mut ctx.accounts.public_health_authority;
+        public_health_authority.status = TallyStatus::Revealing;
+
+        require_ciphertext_region::<PublicHealthAuthority>(
+            &public_health_authority.to_account_info(),
+        )?;
+        let args = case_counts_args(
+            public_health_authority.enc_pubkey,
+            authority_nonce,
+            public_health_authority.counts_nonce,
+            public_health_authority.key(),
+        );
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+        ctx.accounts
+            .program_config
+            .record_queued(COMP_DEF_OFFSET_CASE_COUNTS)?;
+        ctx.accounts.used_offsets.claim(
+            ctx.accounts.authority.key(),
+            ctx.bumps.used_offsets,
+            computation_offset,
+            &ctx.accounts.program_config,
+        )?;
+        ctx.accounts.callback_guard.expect(
+            computation_offset,
+            COMP_DEF_OFFSET_CASE_COUNTS,
+            ctx.bumps.callback_guard,
+        );
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![CaseCountsCallback::callback_ix(&[
+                CallbackAccount {
+                    pubkey: ctx.accounts.program_config.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.callback_guard.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.public_health_authority.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.used_offsets.key(),
+                    is_writable: true,
+                },
+            ])],
+        )?;
+        Ok(())
+    }
+
+    /// Emits the counts for the authority and releases it.
+    #[arcium_callback(encrypted_ix = "case_counts")]
+    pub fn case_counts_callback(
+        ctx: Context<CaseCountsCallback>,
+        output: ComputationOutputs<CaseCountsOutput>,
+    ) -> Result<()> {
+        let computation_offset = ctx
+            .accounts
+            .callback_guard
+            .consume(COMP_DEF_OFFSET_CASE_COUNTS)?;
+        verify_callback_invocation(
+            &ctx.accounts.instructions_sysvar,
+            &ctx.accounts.comp_def_account.key(),
+            computation_offset,
+        )?;
+        ctx.accounts.used_offsets.release(computation_offset);
+        ctx.accounts
+            .program_config
+            .record_completed(COMP_DEF_OFFSET_CASE_COUNTS)?;
+
+        let public_health_authority = &mut ctx.accounts.public_health_authority;
+        public_health_authority.status = TallyStatus::Ready;
+
+        let event = match output {
+            ComputationOutputs::Success(CaseCountsOutput { field_0 }) => CaseCountsEvent {
+                public_health_authority: public_health_authority.key(),
+                receiver_enc_key: field_0.encryption_key,
+                nonce: field_0.nonce.to_le_bytes(),
+                counts: field_0
+                    .ciphertexts
+                    .as_slice()
+                    .try_into()
+                    .map_err(|_| ErrorCode::ComputationOutputLength)?,
+                aborted: false,
+            },
+            _ => CaseCountsEvent {
+                public_health_authority: public_health_authority.key(),
+                receiver_enc_key: [0; 32],
+                nonce: [0; 16],
+                counts: [[0; 32]; NOTIFIABLE_DISEASES],
+                aborted: true,
+            },
+        };
+        emit!(event);
+        Ok(())
+    }
+
+    /// Re-encrypts a counted case's diagnosis and patient identifier for the public-health
+    /// authority, through `CaseRevealedEvent`.
+    ///
+    /// Called by the authority's legal committee only; each reveal is stamped on the case
+    /// as `revealed_at`.
+    ///
+    /// # Arguments
+    /// * `authority_nonce` - Nonce for the case's encryption to the authority's key
+    pub fn reveal_case(
+        ctx: Context<RevealCase>,
+        computation_offset: u64,
+        authority_nonce: u128,
+    ) -> Result<()> {
+        ctx.accounts.program_config.require_compute_enabled()?;
+        require!(ctx.accounts.case_report.counted, ErrorCode::CaseNotCounted);
+
+        let case_report = &ctx.accounts.case_report;
+        require_ciphertext_region::<CaseReport>(&case_report.to_account_info())?;
+        let args = reveal_case_args(
+            ctx.accounts.public_health_authority.enc_pubkey,
+            authority_nonce,
+            case_report.sealed_nonce,
+            case_report.key(),
+        );
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+        ctx.accounts
+            .program_config
+            .record_queued(COMP_DEF_OFFSET_REVEAL_CASE)?;
+        ctx.accounts.used_offsets.claim(
+            ctx.accounts.legal_committee.key(),
+            ctx.bumps.used_offsets,
+            computation_offset,
+            &ctx.accounts.program_config,
+        )?;
+        ctx.accounts.callback_guard.expect(
+            computation_offset,
+            COMP_DEF_OFFSET_REVEAL_CASE,
+            ctx.bumps.callback_guard,
+        );
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![RevealCaseCallback::callback_ix(&[
+                CallbackAccount {
+                    pubkey: ctx.accounts.program_config.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.callback_guard.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.public_health_authority.key(),
+                    is_writable: false,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.case_report.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.used_offsets.key(),
+                    is_writable: true,
+                },
+            ])],
+        )?;
+        Ok(())
+    }
+
+    /// Stamps the reveal on the case and emits it for the authority.
+    #[arcium_callback(encrypted_ix = "reveal_case")]
+    pub fn reveal_case_callback(
+        ctx: Context<RevealCaseCallback>,
+        output: ComputationOutputs<RevealCaseOutput>,
+    ) -> Result<()> {
+        let computation_offset = ctx
+            .accounts
+            .callback_guard
+            .consume(COMP_DEF_OFFSET_REVEAL_CASE)?;
+        verify_callback_invocation(
+            &ctx.accounts.instructions_sysvar,
+            &ctx.accounts.comp_def_account.key(),
+            computation_offset,
+        )?;
+        ctx.accounts.used_offsets.release(computation_offset);
+        ctx.accounts
+            .program_config
+            .record_completed(COMP_DEF_OFFSET_REVEAL_CASE)?;
+
+        let case_report = &mut ctx.accounts.case_report;
+        let legal_committee = ctx.accounts.public_health_authority.legal_committee;
+        let event = match output {
+            ComputationOutputs::Success(RevealCaseOutput { field_0 }) => {
+                case_report.revealed_at = Clock::get()?.unix_timestamp;
+                CaseRevealedEvent {
+                    case_report: case_report.key(),
+                    public_health_authority: case_report.public_health_authority,
+                    legal_committee,
+                    receiver_enc_key: field_0.encryption_key,
+                    nonce: field_0.nonce.to_le_bytes(),
+                    ciphertexts: field_0
+                        .ciphertexts
+                        .as_slice()
+                        .try_into()
+                        .map_err(|_| ErrorCode::ComputationOutputLength)?,
+                    aborted: false,
+                }
+            }
+            _ => CaseRevealedEvent {
+                case_report: case_report.key(),
+                public_health_authority: case_report.public_health_authority,
+                legal_committee,
+                receiver_enc_key: [0; 32],
+                nonce: [0; 16],
+                ciphertexts: [[0; 32]; 2],
+                aborted: true,
+            },
+        };
+        emit!(event);
+        Ok(())
+    }
+
+    /// Grants a family caregiver recurring, read-only access to `fields` of the caller's
+    /// record, or replaces an earlier grant to them.
+    ///
+    /// Updates are cranked by anyone through `crank_caregiver_update`, at most once per
+    /// `interval_slots`; the caregiver cannot trigger any other share. Updating the record
+    /// stalls the grant until it is granted again with the new nonce.
+    ///
+    /// # Arguments
+    /// * `caregiver` - Caregiver's wallet
+    /// * `caregiver_enc_key` - x25519 key updates are encrypted for
+    /// * `fields` - Bitmask of `PatientField` bits within `CAREGIVER_FIELDS`
+    /// * `nonce` - Nonce the record was encrypted with
+    /// * `interval_slots` - Slots between updates, at least `MIN_CAREGIVER_INTERVAL_SLOTS`
+    /// * `expires_at` - Unix timestamp after which no more updates are cranked
+    #[allow(clippy::too_many_arguments)]
+    pub fn grant_caregiver_access(
+        ctx: Context<GrantCaregiverAccess>,
+        caregiver: Pubkey,
+        caregiver_enc_key: [u8; 32],
+        fields: u16,
+        nonce: u128,
+        interval_slots: u64,
+        expires_at: i64,
+    ) -> Result<()> {
+        ctx.accounts.patient_data.require_live()?;
+        require_valid_x25519_key(&caregiver_enc_key)?;
+        require_field_mask(fields)?;
+        require!(
+            fields & !CAREGIVER_FIELDS == 0,
+            ErrorCode::InvalidCaregiverFields
+        );
+        ctx.accounts.program_config.require_collected(fields)?;
+        require!(
+            interval_slots >= MIN_CAREGIVER_INTERVAL_SLOTS,
+            ErrorCode::InvalidCaregiverInterval
+        );
+        let clock = ExpiryClock::get(0)?;
+        require!(
+            expires_at > clock.unix_timestamp,
+            ErrorCode::InvalidConsentExpiry
+        );
+
+        let grant = &mut ctx.accounts.caregiver_grant;
+        grant.patient_data = ctx.accounts.patient_data.key();
+        grant.patient = ctx.accounts.patient.key();
+        grant.caregiver = caregiver;
+        grant.caregiver_enc_key = caregiver_enc_key;
+        grant.fields = fields;
+        grant.record_nonce = nonce;
+        grant.record_version = ctx.accounts.patient_data.version;
+        grant.interval_slots = interval_slots;
+        grant.next_due_slot = clock.slot;
+        grant.expires_at = expires_at;
+        grant.expires_at_slot = clock.slot_at(expires_at);
+        grant.revoked = false;
+        grant.bump = ctx.bumps.caregiver_grant;
+
+        let event = CaregiverAccessGrantedEvent {
+            caregiver_grant: grant.key(),
+            patient_data: grant.patient_data,
+            caregiver,
+            fields,
+            interval_slots,
+            expires_at,
+        };
+        ctx.accounts.patient_registry.audit(
+            event.patient_data,
+            ctx.bumps.patient_registry,
+            &event,
+        )?;
+        emit!(event);
+        Ok(())
+    }
+
+    /// Revokes a caregiver grant; an update already in flight is still delivered.
+    pub fn revoke_caregiver_access(ctx: Context<RevokeCaregiverAccess>) -> Result<()> {
+        let grant = &mut ctx.accounts.caregiver_grant;
+        grant.revoked = true;
+
+        let event = CaregiverAccessRevokedEvent {
+            caregiver_grant: grant.key(),
+            patient_data: grant.patient_data,
+            caregiver: grant.caregiver,
+        };
+        ctx.accounts.patient_registry.audit(
+            event.patient_data,
+            ctx.bumps.patient_registry,
+            &event,
+        )?;
+        emit!(event);
+        Ok(())
+    }
+
+    pub fn init_caregiver_update_comp_def(ctx: Context<InitCaregiverUpdateCompDef>) -> Result<()> {
+        let source = ctx
+            .accounts
+            .program_config
+            .circuit_source(COMP_DEF_OFFSET_CAREGIVER_UPDATE)?;
+        init_comp_def(ctx.accounts, true, 0, Some(source), None)?;
+        ctx.accounts
+            .program_config
+            .mark_circuit_initialized(COMP_DEF_OFFSET_CAREGIVER_UPDATE)
+    }
+
+    /// Re-encrypts a caregiver grant's fields of the record for the caregiver, once the
+    /// grant's interval has elapsed since the last update.
+    ///
+    /// Permissionless, so a crank service keeps grants current; the crank pays for the
+    /// computation but chooses neither the fields, the key nor the output nonce.
+    pub fn crank_caregiver_update(
+        ctx: Context<CrankCaregiverUpdate>,
+        computation_offset: u64,
+    ) -> Result<()> {
+        ctx.accounts.program_config.require_compute_enabled()?;
+        ctx.accounts.patient_data.require_live()?;
+        let clock = ctx.accounts.program_config.expiry_clock()?;
+
+        let grant_key = ctx.accounts.caregiver_grant.key();
+        let grant = &mut ctx.accounts.caregiver_grant;
+        grant.require_active(&clock)?;
+        require!(
+            !grant.pending && clock.slot >= grant.next_due_slot,
+            ErrorCode::CaregiverUpdateNotDue
+        );
+        require!(
+            grant.record_version == ctx.accounts.patient_data.version,
+            ErrorCode::CaregiverGrantStale
+        );
+
+        let caregiver_nonce = caregiver_update_nonce(&grant_key, grant.requests);
+        grant.requests = grant
+            .requests
+            .checked_add(1)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        grant.pending = true;
+        grant.next_due_slot = clock.slot.saturating_add(grant.interval_slots);
+
+        require_ciphertext_region::<PatientData>(&ctx.accounts.patient_data.to_account_info())?;
+        let args = caregiver_update_args(
+            grant.caregiver_enc_key,
+            caregiver_nonce,
+            ctx.accounts.patient_data.enc_pubkey,
+            grant.record_nonce,
+            ctx.accounts.patient_data.key(),
+            grant.fields,
+        );
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+        ctx.accounts
+            .program_config
+            .record_queued(COMP_DEF_OFFSET_CAREGIVER_UPDATE)?;
+        ctx.accounts.used_offsets.claim(
+            ctx.accounts.crank.key(),
+            ctx.bumps.used_offsets,
+            computation_offset,
+            &ctx.accounts.program_config,
+        )?;
+        ctx.accounts.callback_guard.expect(
+            computation_offset,
+            COMP_DEF_OFFSET_CAREGIVER_UPDATE,
+            ctx.bumps.callback_guard,
+        );
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![CaregiverUpdateCallback::callback_ix(&[
+                CallbackAccount {
+                    pubkey: ctx.accounts.program_config.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.callback_guard.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.caregiver_grant.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.used_offsets.key(),
+                    is_writable: true,
+                },
+            ])],
+        )?;
+        Ok(())
+    }
+
+    /// Writes the caregiver's update into their grant.
+    ///
+    /// An aborted computation keeps the previous update and makes the next one due at once.
+    #[arcium_callback(encrypted_ix = "caregiver_update")]
+    pub fn caregiver_update_callback(
+        ctx: Context<CaregiverUpdateCallback>,
+        output: ComputationOutputs<CaregiverUpdateOutput>,
+    ) -> Result<()> {
+        let computation_offset = ctx
+            .accounts
+            .callback_guard
+            .consume(COMP_DEF_OFFSET_CAREGIVER_UPDATE)?;
+        verify_callback_invocation(
+            &ctx.accounts.instructions_sysvar,
+            &ctx.accounts.comp_def_account.key(),
+            computation_offset,
+        )?;
+        ctx.accounts.used_offsets.release(computation_offset);
+        ctx.accounts
+            .program_config
+            .record_completed(COMP_DEF_OFFSET_CAREGIVER_UPDATE)?;
+
+        let slot = Clock::get()?.slot;
+        let grant = &mut ctx.accounts.caregiver_grant;
+        grant.pending = false;
+        let aborted = match output {
+            ComputationOutputs::Success(CaregiverUpdateOutput { field_0 }) => {
+                grant.ciphertexts = field_0
+                    .ciphertexts
+                    .as_slice()
+                    .try_into()
+                    .map_err(|_| ErrorCode::ComputationOutputLength)?;
+                grant.receiver_enc_key = field_0.encryption_key;
+                grant.nonce = field_0.nonce.to_le_bytes();
+                grant.updated_slot = slot;
+                false
+            }
+            _ => {
+                grant.next_due_slot = slot;
+                true
+            }
+        };
+
+        emit!(CaregiverUpdateDeliveredEvent {
+            caregiver_grant: grant.key(),
+            caregiver: grant.caregiver,
+            slot,
+            aborted,
+        });
+        Ok(())
+    }
+
+    /// Sets the fields a specialty requests by default.
+    ///
+    /// # Arguments
+    /// * `specialty` - Specialty the template is for
+    /// * `fields` - Bitmask of `PatientField` bits, at least one
+    pub fn set_specialty_template(
+        ctx: Context<SetSpecialtyTemplate>,
+        specialty: Specialty,
+        fields: u16,
+    ) -> Result<()> {
+        require_field_mask(fields)?;
+
+        let template = &mut ctx.accounts.specialty_template;
+        template.specialty = specialty;
+        template.fields = fields;
+        template.updated_at = Clock::get()?.unix_timestamp;
+        template.bump = ctx.bumps.specialty_template;
+
+        emit!(SpecialtyTemplateSetEvent {
+            specialty_template: template.key(),
+            specialty,
+            fields,
+        });
+        Ok(())
+    }
+
+    /// Requests access to a patient's record for a visit of the template's specialty,
+    /// replacing any earlier request by the caller.
+    ///
+    /// Called by active providers. Without `fields` the request asks for the template's,
+    /// the minimum the specialty needs; asking for more is allowed but flagged as
+    /// `exceeds_template` for the patient to see.
+    ///
+    /// # Arguments
+    /// * `fields` - Bitmask of `PatientField` bits, or none for the template's
+    /// * `purpose` - `CONSENT_SCOPE_*` bits access is requested for
+    pub fn request_access(
+        ctx: Context<RequestAccess>,
+        fields: Option<u16>,
+        purpose: u16,
+    ) -> Result<()> {
+        ctx.accounts.patient_data.require_live()?;
+        require!(
+            purpose != 0 && purpose & !CONSENT_SCOPE_ALL == 0,
+            ErrorCode::InvalidConsentScope
+        );
+        let template = &ctx.accounts.specialty_template;
+        let fields = fields.unwrap_or(template.fields);
+        require_field_mask(fields)?;
+        ctx.accounts.program_config.require_collected(fields)?;
+
+        let request = &mut ctx.accounts.access_request;
+        request.patient_data = ctx.accounts.patient_data.key();
+        request.provider = ctx.accounts.provider.key();
+        request.specialty = template.specialty;
+        request.fields = fields;
+        request.exceeds_template = fields & !template.fields != 0;
+        request.purpose = purpose;
+        request.requested_at = Clock::get()?.unix_timestamp;
+        request.bump = ctx.bumps.access_request;
+        Inbox::notify(
+            &ctx.accounts.inbox,
+            NotificationKind::AccessRequested,
+            request.key(),
+        )?;
+
+        emit!(AccessRequestedEvent {
+            access_request: request.key(),
+            patient_data: request.patient_data,
+            provider: request.provider,
+            specialty: request.specialty,
+            fields,
+            exceeds_template: request.exceeds_template,
+            purpose,
+        });
+        Ok(())
+    }
+
+    /// Lets an app key append records to the caller's vitals log, replacing any earlier
+    /// grant to the same app.
+    ///
+    /// The app gains no read or share rights; see `IngestAuthority`.
+    ///
+    /// # Arguments
+    /// * `app` - Key the app signs appends with
+    /// * `quota_per_epoch` - Records the app may append per epoch, at least one
+    pub fn grant_ingest_authority(
+        ctx: Context<GrantIngestAuthority>,
+        app: Pubkey,
+        quota_per_epoch: u32,
+    ) -> Result<()> {
+        require!(quota_per_epoch > 0, ErrorCode::InvalidIngestQuota);
+
+        let clock = Clock::get()?;
+        let ingest_authority = &mut ctx.accounts.ingest_authority;
+        ingest_authority.tree = ctx.accounts.tree.key();
+        ingest_authority.patient = ctx.accounts.patient.key();
+        ingest_authority.app = app;
+        ingest_authority.quota_per_epoch = quota_per_epoch;
+        ingest_authority.epoch = clock.epoch;
+        ingest_authority.appended = 0;
+        ingest_authority.revoked = false;
+        ingest_authority.granted_at = clock.unix_timestamp;
+        ingest_authority.bump = ctx.bumps.ingest_authority;
+
+        emit!(IngestAuthorityGrantedEvent {
+            ingest_authority: ingest_authority.key(),
+            tree: ingest_authority.tree,
+            app,
+            quota_per_epoch,
+        });
+        Ok(())
+    }
+
+    /// Stops an app from appending to the caller's vitals log. Records already appended
+    /// stay in the tree.
+    pub fn revoke_ingest_authority(ctx: Context<RevokeIngestAuthority>) -> Result<()> {
+        let ingest_authority = &mut ctx.accounts.ingest_authority;
+        ingest_authority.revoked = true;
+
+        emit!(IngestAuthorityRevokedEvent {
+            ingest_authority: ingest_authority.key(),
+            tree: ingest_authority.tree,
+            app: ingest_authority.app,
+        });
+        Ok(())
+    }
+
+    /// Appends a record to a patient's vitals log on behalf of an app holding an
+    /// `IngestAuthority`.
+    ///
+    /// The record is attributed to the tree's owner, never a wallet of the app's choosing,
+    /// and counts against the app's quota for the current epoch.
+    ///
+    /// # Arguments
+    /// * `enc_pubkey` - x25519 key the record was encrypted with
+    /// * `nonce` - Nonce the record was encrypted with
+    /// * `ciphertexts` - Record values, encrypted to the MXE
+    pub fn ingest_compressed_record(
+        ctx: Context<IngestCompressedRecord>,
+        enc_pubkey: [u8; 32],
+        nonce: u128,
+        ciphertexts: CompressedCiphertexts,
+    ) -> Result<()> {
+        ctx.accounts.program_config.require_store_enabled()?;
+        ctx.accounts
+            .ingest_authority
+            .consume_quota(Clock::get()?.epoch)?;
+
+        let patient = ctx.accounts.ingest_authority.patient;
+        let leaf = compressed_leaf(&patient, &enc_pubkey, nonce, &ciphertexts);
+        let (leaf_index, root) = ctx.accounts.tree.append(leaf)?;
+
+        emit!(CompressedRecordAppendedEvent {
+            tree: ctx.accounts.tree.key(),
+            leaf_index,
+            patient,
+            enc_pubkey,
+            nonce,
+            ciphertexts,
+            root,
+        });
+        Ok(())
+    }
+
+    /// Checkpoints a record's audit chain as it stands now into an immutable
+    /// `AuditAttestation`.
+    ///
+    /// Called by configured auditors. The window runs from `previous_attestation`, or from
+    /// the start of the chain without one, so an export of the events in between can be
+    /// checked to replay from one checkpoint's head to the other's.
+    pub fn attest_audit_window(ctx: Context<AttestAuditWindow>) -> Result<()> {
+        let registry = &ctx.accounts.patient_registry;
+        let (start_slot, start_entries, start_head) = match &ctx.accounts.previous_attestation {
+            Some(previous) => {
+                require!(
+                    previous.patient_data == registry.patient_data
+                        && previous.end_entries < registry.audit_entries,
+                    ErrorCode::InvalidAuditWindow
+                );
+                (previous.end_slot, previous.end_entries, previous.end_head)
+            }
+            None => (0, 0, [0; 32]),
+        };
+
+        let clock = Clock::get()?;
+        let attestation = &mut ctx.accounts.audit_attestation;
+        attestation.patient_data = registry.patient_data;
+        attestation.auditor = ctx.accounts.auditor.key();
+        attestation.start_slot = start_slot;
+        attestation.start_entries = start_entries;
+        attestation.start_head = start_head;
+        attestation.end_slot = clock.slot;
+        attestation.end_entries = registry.audit_entries;
+        attestation.end_head = registry.audit_head;
+        attestation.attested_at = clock.unix_timestamp;
+        attestation.bump = ctx.bumps.audit_attestation;
+
+        emit!(AuditWindowAttestedEvent {
+            audit_attestation: attestation.key(),
+            patient_data: attestation.patient_data,
+            auditor: attestation.auditor,
+            start_entries,
+            end_entries: attestation.end_entries,
+            end_head: attestation.end_head,
+        });
+        Ok(())
+    }
+
+    /// Opens the caller's notification inbox. From then on access requests, completed
+    /// shares, emergency admissions and lapsing consents post to it.
+    pub fn open_inbox(ctx: Context<OpenInbox>) -> Result<()> {
+        let inbox = &mut ctx.accounts.inbox;
+        inbox.patient_data = ctx.accounts.patient_data.key();
+        inbox.posted = 0;
+        inbox.notifications = Vec::new();
+        inbox.bump = ctx.bumps.inbox;
+        Ok(())
+    }
+
+    /// Posts a notice to the patient's inbox that a consent lapses within
+    /// `CONSENT_EXPIRY_NOTICE`.
+    ///
+    /// Permissionless, so a keeper can watch consents for patients whose apps are
+    /// offline. Each grant of a consent is posted about at most once.
+    pub fn notify_consent_expiring(ctx: Context<NotifyConsentExpiring>) -> Result<()> {
+        let clock = ctx.accounts.program_config.expiry_clock()?;
+        let grant = &ctx.accounts.consent_grant;
+        require!(
+            grant.is_active(&clock)
+                && grant.expires_at <= clock.unix_timestamp + CONSENT_EXPIRY_NOTICE,
+            ErrorCode::ConsentNotExpiring
+        );
+        let subject = grant.key();
+        let inbox = &mut ctx.accounts.inbox;
+        require!(
+            !inbox.notifications.iter().any(|notification| {
+                notification.kind == NotificationKind::ConsentExpiring
+                    && notification.subject == subject
+                    && notification.posted_at >= grant.granted_at
+            }),
+            ErrorCode::AlreadyNotified
+        );
+        inbox.post(Notification {
+            kind: NotificationKind::ConsentExpiring,
+            subject,
+            posted_at: clock.unix_timestamp,
+        })
+    }
+
+    /// Registers the caller as a blood donor, or replaces their donor values, and opts
+    /// them in to searches.
+    ///
+    /// # Arguments
+    /// * `enc_pubkey` - x25519 key the donor values were encrypted with
+    /// * `nonce` - Nonce the donor values were encrypted with
+    /// * `donor` - Antigens, eligibility per product and token, as in `DonorProfile`
+    pub fn register_donor(
+        ctx: Context<RegisterDonor>,
+        enc_pubkey: [u8; 32],
+        nonce: u128,
+        donor: [[u8; 32]; DONOR_VALUES],
+    ) -> Result<()> {
+        ctx.accounts.patient_data.require_live()?;
+        require_valid_x25519_key(&enc_pubkey)?;
+
+        let profile = &mut ctx.accounts.donor_profile;
+        profile.donor = donor;
+        profile.patient_data = ctx.accounts.patient_data.key();
+        profile.patient = ctx.accounts.patient.key();
+        profile.enc_pubkey = enc_pubkey;
+        profile.nonce = nonce;
+        profile.consenting = true;
+        profile.updated_at = Clock::get()?.unix_timestamp;
+        profile.bump = ctx.bumps.donor_profile;
+
+        emit!(DonorRegisteredEvent {
+            donor_profile: profile.key(),
+            patient_data: profile.patient_data,
+        });
+        Ok(())
+    }
+
+    /// Opts the caller's donor profile in to or out of searches.
+    pub fn set_donor_consent(ctx: Context<SetDonorConsent>, consenting: bool) -> Result<()> {
+        let profile = &mut ctx.accounts.donor_profile;
+        profile.consenting = consenting;
+        profile.updated_at = Clock::get()?.unix_timestamp;
+
+        emit!(DonorConsentSetEvent {
+            donor_profile: profile.key(),
+            consenting,
+        });
+        Ok(())
+    }
+
+    pub fn init_find_compatible_donors_comp_def(
+        ctx: Context<InitFindCompatibleDonorsCompDef>,
+    ) -> Result<()> {
+        let source = ctx
+            .accounts
+            .program_config
+            .circuit_source(COMP_DEF_OFFSET_FIND_COMPATIBLE_DONORS)?;
+        init_comp_def(ctx.accounts, true, 0, Some(source), None)?;
+        ctx.accounts
+            .program_config
+            .mark_circuit_initialized(COMP_DEF_OFFSET_FIND_COMPATIBLE_DONORS)
+    }
+
+    /// Searches a batch of consenting donors for those a recipient can receive red cells
+    /// from and who may give one of `products`.
+    ///
+    /// Called by active providers registered as `BloodBank`. The batch is passed in
+    /// `remaining_accounts` as `DONOR_SEARCH_SIZE` donor profiles. Only the number of
+    /// compatible donors and their tokens are returned, encrypted for the blood bank.
+    ///
+    /// # Arguments
+    /// * `blood_bank_pub_key` - Blood bank's x25519 key the result is encrypted for
+    /// * `blood_bank_nonce` - Nonce for the result encryption
+    /// * `recipient_antigens` - `BLOOD_ANTIGEN_*` bits of the recipient's blood type
+    /// * `products` - `DONOR_PRODUCT_*` bits of the products needed, at least one
+    pub fn find_compatible_donors(
+        ctx: Context<FindCompatibleDonors>,
+        computation_offset: u64,
+        blood_bank_pub_key: [u8; 32],
+        blood_bank_nonce: u128,
+        recipient_antigens: u8,
+        products: u8,
+    ) -> Result<()> {
+        ctx.accounts.program_config.require_compute_enabled()?;
+        require_valid_x25519_key(&blood_bank_pub_key)?;
+        require!(
+            recipient_antigens & !BLOOD_ANTIGENS_ALL == 0
+                && products != 0
+                && products & !DONOR_PRODUCTS_ALL == 0,
+            ErrorCode::InvalidDonorSearch
+        );
+
+        let donors = donor_search_arguments(ctx.remaining_accounts)?;
+        let args = find_compatible_donors_args(
+            blood_bank_pub_key,
+            blood_bank_nonce,
+            recipient_antigens,
+            products,
+            donors,
+        );
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+        ctx.accounts
+            .program_config
+            .record_queued(COMP_DEF_OFFSET_FIND_COMPATIBLE_DONORS)?;
+        ctx.accounts.used_offsets.claim(
+            ctx.accounts.blood_bank.key(),
+            ctx.bumps.used_offsets,
+            computation_offset,
+            &ctx.accounts.program_config,
+        )?;
+        ctx.accounts.callback_guard.expect(
+            computation_offset,
+            COMP_DEF_OFFSET_FIND_COMPATIBLE_DONORS,
+            ctx.bumps.callback_guard,
+        );
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![FindCompatibleDonorsCallback::callback_ix(&[
+                CallbackAccount {
+                    pubkey: ctx.accounts.program_config.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.callback_guard.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.provider_profile.key(),
+                    is_writable: false,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.used_offsets.key(),
+                    is_writable: true,
+                },
+            ])],
+        )?;
+        Ok(())
+    }
+
+    /// Delivers the encrypted count and tokens of compatible donors to the blood bank.
+    #[arcium_callback(encrypted_ix = "find_compatible_donors")]
+    pub fn find_compatible_donors_callback(
+        ctx: Context<FindCompatibleDonorsCallback>,
+        output: ComputationOutputs<FindCompatibleDonorsOutput>,
+    ) -> Result<()> {
+        let computation_offset = ctx
+            .accounts
+            .callback_guard
+            .consume(COMP_DEF_OFFSET_FIND_COMPATIBLE_DONORS)?;
+        verify_callback_invocation(
+            &ctx.accounts.instructions_sysvar,
+            &ctx.accounts.comp_def_account.key(),
+            computation_offset,
+        )?;
+        ctx.accounts.used_offsets.release(computation_offset);
+        ctx.accounts
+            .program_config
+            .record_completed(COMP_DEF_OFFSET_FIND_COMPATIBLE_DONORS)?;
+
+        let blood_bank = ctx.accounts.provider_profile.provider;
+        let o = match output {
+            ComputationOutputs::Success(FindCompatibleDonorsOutput { field_0 }) => field_0,
+            _ => {
+                emit!(DonorSearchAbortedEvent { blood_bank });
+                return Ok(());
+            }
+        };
+        let [count, tokens @ ..]: [[u8; 32]; DONOR_SEARCH_SIZE + 1] = o
+            .ciphertexts
+            .as_slice()
+            .try_into()
+            .map_err(|_| ErrorCode::ComputationOutputLength)?;
+
+        emit!(CompatibleDonorsFoundEvent {
+            blood_bank,
+            receiver_enc_key: o.encryption_key,
+            slot: Clock::get()?.slot,
+            nonce: o.nonce.to_le_bytes(),
+            count,
+            tokens,
+        });
+        Ok(())
+    }
+
+    /// Lists the caller as a transplant candidate, or replaces their candidate values.
+    ///
+    /// # Arguments
+    /// * `enc_pubkey` - x25519 key the candidate values were encrypted with
+    /// * `nonce` - Nonce the candidate values were encrypted with
+    /// * `candidate` - Urgency score, days waiting and token, as in `WaitlistEntry`
+    pub fn list_waitlist_candidate(
+        ctx: Context<ListWaitlistCandidate>,
+        enc_pubkey: [u8; 32],
+        nonce: u128,
+        candidate: [[u8; 32]; WAITLIST_VALUES],
+    ) -> Result<()> {
+        ctx.accounts.patient_data.require_live()?;
+        require_valid_x25519_key(&enc_pubkey)?;
+
+        let entry = &mut ctx.accounts.waitlist_entry;
+        entry.candidate = candidate;
+        entry.patient_data = ctx.accounts.patient_data.key();
+        entry.patient = ctx.accounts.patient.key();
+        entry.enc_pubkey = enc_pubkey;
+        entry.nonce = nonce;
+        entry.listed = true;
+        entry.updated_at = Clock::get()?.unix_timestamp;
+        entry.bump = ctx.bumps.waitlist_entry;
+
+        emit!(WaitlistListingSetEvent {
+            waitlist_entry: entry.key(),
+            patient_data: entry.patient_data,
+            listed: true,
+        });
+        Ok(())
+    }
+
+    /// Returns the caller's entry to the waitlist or takes it off.
+    pub fn set_waitlist_listing(ctx: Context<SetWaitlistListing>, listed: bool) -> Result<()> {
+        let entry = &mut ctx.accounts.waitlist_entry;
+        entry.listed = listed;
+        entry.updated_at = Clock::get()?.unix_timestamp;
+
+        emit!(WaitlistListingSetEvent {
+            waitlist_entry: entry.key(),
+            patient_data: entry.patient_data,
+            listed,
+        });
+        Ok(())
+    }
+
+    pub fn init_waitlist_priority_comp_def(
+        ctx: Context<InitWaitlistPriorityCompDef>,
+    ) -> Result<()> {
+        let source = ctx
+            .accounts
+            .program_config
+            .circuit_source(COMP_DEF_OFFSET_WAITLIST_PRIORITY)?;
+        init_comp_def(ctx.accounts, true, 0, Some(source), None)?;
+        ctx.accounts
+            .program_config
+            .mark_circuit_initialized(COMP_DEF_OFFSET_WAITLIST_PRIORITY)
+    }
+
+    /// Ranks a batch of listed transplant candidates by urgency, then days waiting.
+    ///
+    /// Called by active providers registered as `TransplantCoordinator`. The batch is
+    /// passed in `remaining_accounts` as `WAITLIST_BATCH_SIZE` waitlist entries. Every run
+    /// is logged in a `WaitlistRun` PDA keyed by the computation offset, which receives
+    /// the candidates' tokens from highest to lowest priority, encrypted for the
+    /// coordinator.
+    ///
+    /// # Arguments
+    /// * `coordinator_pub_key` - Coordinator's x25519 key the ranking is encrypted for
+    /// * `coordinator_nonce` - Nonce for the ranking encryption
+    pub fn waitlist_priority(
+        ctx: Context<WaitlistPriority>,
+        computation_offset: u64,
+        coordinator_pub_key: [u8; 32],
+        coordinator_nonce: u128,
+    ) -> Result<()> {
+        ctx.accounts.program_config.require_compute_enabled()?;
+        require_valid_x25519_key(&coordinator_pub_key)?;
+
+        let (candidates, entries) = waitlist_batch_arguments(ctx.remaining_accounts)?;
+        let args = waitlist_priority_args(coordinator_pub_key, coordinator_nonce, candidates);
+
+        let run = &mut ctx.accounts.waitlist_run;
+        run.coordinator = ctx.accounts.coordinator.key();
+        run.entries = entries;
+        run.computation_offset = computation_offset;
+        run.queued_slot = Clock::get()?.slot;
+        run.bump = ctx.bumps.waitlist_run;
+        emit!(WaitlistPriorityQueuedEvent {
+            waitlist_run: run.key(),
+            coordinator: run.coordinator,
+            entries,
+        });
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+        ctx.accounts
+            .program_config
+            .record_queued(COMP_DEF_OFFSET_WAITLIST_PRIORITY)?;
+        ctx.accounts.used_offsets.claim(
+            ctx.accounts.coordinator.key(),
+            ctx.bumps.used_offsets,
+            computation_offset,
+            &ctx.accounts.program_config,
+        )?;
+        ctx.accounts.callback_guard.expect(
+            computation_offset,
+            COMP_DEF_OFFSET_WAITLIST_PRIORITY,
+            ctx.bumps.callback_guard,
+        );
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![WaitlistPriorityCallback::callback_ix(&[
+                CallbackAccount {
+                    pubkey: ctx.accounts.program_config.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.callback_guard.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.waitlist_run.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.used_offsets.key(),
+                    is_writable: true,
+                },
+            ])],
+        )?;
+        Ok(())
+    }
+
+    /// Stores the encrypted ranking in the run's log.
+    #[arcium_callback(encrypted_ix = "waitlist_priority")]
+    pub fn waitlist_priority_callback(
+        ctx: Context<WaitlistPriorityCallback>,
+        output: ComputationOutputs<WaitlistPriorityOutput>,
+    ) -> Result<()> {
+        let computation_offset = ctx
+            .accounts
+            .callback_guard
+            .consume(COMP_DEF_OFFSET_WAITLIST_PRIORITY)?;
+        verify_callback_invocation(
+            &ctx.accounts.instructions_sysvar,
+            &ctx.accounts.comp_def_account.key(),
+            computation_offset,
+        )?;
+        ctx.accounts.used_offsets.release(computation_offset);
+        ctx.accounts
+            .program_config
+            .record_completed(COMP_DEF_OFFSET_WAITLIST_PRIORITY)?;
+
+        let run_key = ctx.accounts.waitlist_run.key();
+        let run = &mut ctx.accounts.waitlist_run;
+        run.completed_slot = Clock::get()?.slot;
+        match output {
+            ComputationOutputs::Success(WaitlistPriorityOutput { field_0 }) => {
+                run.ranking = field_0
+                    .ciphertexts
+                    .as_slice()
+                    .try_into()
+                    .map_err(|_| ErrorCode::ComputationOutputLength)?;
+                run.receiver_enc_key = field_0.encryption_key;
+                run.nonce = field_0.nonce.to_le_bytes();
+            }
+            _ => run.aborted = true,
+        }
+
+        emit!(WaitlistPriorityRankedEvent {
+            waitlist_run: run_key,
+            coordinator: run.coordinator,
+            aborted: run.aborted,
+        });
+        Ok(())
+    }
+
+    /// Funds a trial's milestone payouts for every enrollment slot up front.
+    ///
+    /// Called by active providers registered as `PharmaSponsor`. The deposit is
+    /// `milestones * payout_per_milestone * max_enrollments`.
+    ///
+    /// # Arguments
+    /// * `trial_id` - Sponsor chosen identifier, part of the escrow address
+    /// * `site` - Provider who enrolls patients and confirms their visits
+    /// * `milestones` - Visits each patient is paid for
+    /// * `payout_per_milestone` - Tokens a patient earns per confirmed visit
+    /// * `max_enrollments` - Patients the trial can enroll
+    /// * `ends_at` - Unix timestamp enrollments and confirmations stop at
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_trial_escrow(
+        ctx: Context<CreateTrialEscrow>,
+        trial_id: u64,
+        site: Pubkey,
+        milestones: u8,
+        payout_per_milestone: u64,
+        max_enrollments: u16,
+        ends_at: i64,
+    ) -> Result<()> {
+        require!(
+            (1..=MAX_TRIAL_MILESTONES).contains(&milestones)
+                && payout_per_milestone > 0
+                && max_enrollments > 0
+                && ends_at > Clock::get()?.unix_timestamp,
+            ErrorCode::InvalidTrialTerms
+        );
+        let amount = payout_per_milestone
+            .checked_mul(milestones as u64)
+            .and_then(|amount| amount.checked_mul(max_enrollments as u64))
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+
+        transfer_checked(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.sponsor_token_account.to_account_info(),
+                    mint: ctx.accounts.mint.to_account_info(),
+                    to: ctx.accounts.escrow_vault.to_account_info(),
+                    authority: ctx.accounts.sponsor.to_account_info(),
+                },
+            ),
+            amount,
+            ctx.accounts.mint.decimals,
+        )?;
+
+        let trial = &mut ctx.accounts.trial_escrow;
+        trial.sponsor = ctx.accounts.sponsor.key();
+        trial.site = site;
+        trial.mint = ctx.accounts.mint.key();
+        trial.trial_id = trial_id;
+        trial.milestones = milestones;
+        trial.payout_per_milestone = payout_per_milestone;
+        trial.max_enrollments = max_enrollments;
+        trial.ends_at = ends_at;
+        trial.bump = ctx.bumps.trial_escrow;
+
+        emit!(TrialEscrowCreatedEvent {
+            trial_escrow: trial.key(),
+            sponsor: trial.sponsor,
+            site,
+            milestones,
+            payout_per_milestone,
+            max_enrollments,
+            ends_at,
+        });
+        Ok(())
+    }
+
+    /// Enrolls a patient in a running trial, signed by both the patient and the site.
+    ///
+    /// The patient's signature is their consent to take part, and the site's that they
+    /// were screened as a match.
+    pub fn enroll_trial_patient(ctx: Context<EnrollTrialPatient>) -> Result<()> {
+        ctx.accounts.patient_data.require_live()?;
+        let now = Clock::get()?.unix_timestamp;
+        let trial = &mut ctx.accounts.trial_escrow;
+        trial.require_running(now)?;
+        require!(trial.enrolled < trial.max_enrollments, ErrorCode::TrialFull);
+        trial.enrolled += 1;
+
+        let enrollment = &mut ctx.accounts.trial_enrollment;
+        enrollment.trial_escrow = trial.key();
+        enrollment.patient_data = ctx.accounts.patient_data.key();
+        enrollment.patient = ctx.accounts.patient.key();
+        enrollment.enrolled_at = now;
+        enrollment.bump = ctx.bumps.trial_enrollment;
+
+        emit!(TrialPatientEnrolledEvent {
+            trial_enrollment: enrollment.key(),
+            trial_escrow: enrollment.trial_escrow,
+            patient_data: enrollment.patient_data,
+        });
+        Ok(())
+    }
+
+    /// Records the site's encrypted confirmation of an enrolled patient's next visit,
+    /// unlocking its payout.
+    ///
+    /// # Arguments
+    /// * `milestone` - Index of the visit, the enrollment's next unconfirmed one
+    /// * `enc_pubkey` - x25519 key the confirmation was encrypted with
+    /// * `nonce` - Nonce the confirmation was encrypted with
+    /// * `confirmation` - Encrypted visit details
+    pub fn confirm_trial_visit(
+        ctx: Context<ConfirmTrialVisit>,
+        milestone: u8,
+        enc_pubkey: [u8; 32],
+        nonce: u128,
+        confirmation: [u8; 32],
+    ) -> Result<()> {
+        require_valid_x25519_key(&enc_pubkey)?;
+        let trial = &mut ctx.accounts.trial_escrow;
+        trial.require_running(Clock::get()?.unix_timestamp)?;
+        let enrollment = &mut ctx.accounts.trial_enrollment;
+        require!(
+            milestone == enrollment.milestones_confirmed && milestone < trial.milestones,
+            ErrorCode::MilestoneOutOfOrder
+        );
+
+        enrollment.milestones_confirmed += 1;
+        enrollment.confirmation_head = trial_confirmation_hash(
+            &enrollment.confirmation_head,
+            milestone,
+            &enc_pubkey,
+            nonce,
+            &confirmation,
+        );
+        trial.unlocked = trial
+            .unlocked
+            .checked_add(trial.payout_per_milestone)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+
+        emit!(TrialVisitConfirmedEvent {
+            trial_enrollment: enrollment.key(),
+            trial_escrow: enrollment.trial_escrow,
+            milestone,
+            enc_pubkey,
+            nonce,
+            confirmation,
+            confirmation_head: enrollment.confirmation_head,
+        });
+        Ok(())
+    }
+
+    /// Pays an enrolled patient for every confirmed milestone not yet paid, less the
+    /// treasury's cut. Anyone may call it; the payout only ever goes to the patient.
+    pub fn claim_trial_payout(ctx: Context<ClaimTrialPayout>) -> Result<()> {
+        let trial = &mut ctx.accounts.trial_escrow;
+        let enrollment = &mut ctx.accounts.trial_enrollment;
+        let milestones = enrollment.milestones_confirmed - enrollment.milestones_paid;
+        require!(milestones > 0, ErrorCode::NothingToClaim);
+        let amount = trial.payout_per_milestone * milestones as u64;
+        let (patient_amount, treasury_amount) =
+            split_fee(amount, ctx.accounts.program_config.treasury_bps)?;
+        enrollment.milestones_paid = enrollment.milestones_confirmed;
+        trial.paid += amount;
+
+        let signer_seeds: &[&[&[u8]]] = &[&[FEE_AUTHORITY_SEED, &[ctx.bumps.fee_authority]]];
+        for (to, amount) in [
+            (
+                ctx.accounts.patient_token_account.to_account_info(),
+                patient_amount,
+            ),
+            (ctx.accounts.treasury.to_account_info(), treasury_amount),
+        ] {
+            if amount == 0 {
+                continue;
+            }
+            transfer_checked(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    TransferChecked {
+                        from: ctx.accounts.escrow_vault.to_account_info(),
+                        mint: ctx.accounts.mint.to_account_info(),
+                        to,
+                        authority: ctx.accounts.fee_authority.to_account_info(),
+                    },
+                    signer_seeds,
+                ),
+                amount,
+                ctx.accounts.mint.decimals,
+            )?;
+        }
+
+        emit!(TrialPayoutClaimedEvent {
+            trial_enrollment: enrollment.key(),
+            milestones,
+            amount: patient_amount,
+            treasury_amount,
+        });
+        Ok(())
+    }
+
+    /// Returns the deposit no confirmed milestone earned to the sponsor once the trial
+    /// has ended. Milestones confirmed before the end stay claimable.
+    pub fn close_trial_escrow(ctx: Context<CloseTrialEscrow>) -> Result<()> {
+        let trial = &mut ctx.accounts.trial_escrow;
+        require!(!trial.closed, ErrorCode::TrialClosed);
+        require!(
+            Clock::get()?.unix_timestamp >= trial.ends_at,
+            ErrorCode::TrialNotEnded
+        );
+        let owed = trial.unlocked - trial.paid;
+        let refunded = ctx.accounts.escrow_vault.amount.saturating_sub(owed);
+        trial.closed = true;
+
+        if refunded > 0 {
+            let signer_seeds: &[&[&[u8]]] = &[&[FEE_AUTHORITY_SEED, &[ctx.bumps.fee_authority]]];
+            transfer_checked(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    TransferChecked {
+                        from: ctx.accounts.escrow_vault.to_account_info(),
+                        mint: ctx.accounts.mint.to_account_info(),
+                        to: ctx.accounts.sponsor_token_account.to_account_info(),
+                        authority: ctx.accounts.fee_authority.to_account_info(),
+                    },
+                    signer_seeds,
+                ),
+                refunded,
+                ctx.accounts.mint.decimals,
+            )?;
+        }
+
+        emit!(TrialEscrowClosedEvent {
+            trial_escrow: trial.key(),
+            refunded,
+        });
+        Ok(())
+    }
+
+    /// Creates a one-time share code for the caller's record, to be shown as a QR code.
+    ///
+    /// # Arguments
+    /// * `code_key` - ed25519 public key derived from a random code only the patient's
+    ///   app knows
+    /// * `fields` - Bitmask of `PatientField` bits the redeeming provider receives
+    /// * `nonce` - Nonce the record's current ciphertexts were encrypted with
+    /// * `expires_at` - Unix timestamp the code lapses at, within `MAX_SHARE_CODE_SECONDS`
+    pub fn create_share_code(
+        ctx: Context<CreateShareCode>,
+        code_key: Pubkey,
+        fields: u16,
+        nonce: u128,
+        expires_at: i64,
+    ) -> Result<()> {
+        ctx.accounts.patient_data.require_live()?;
+        require_field_mask(fields)?;
+        ctx.accounts.program_config.require_collected(fields)?;
+        let clock = ExpiryClock::get(0)?;
+        require!(
+            expires_at > clock.unix_timestamp
+                && expires_at - clock.unix_timestamp <= MAX_SHARE_CODE_SECONDS,
+            ErrorCode::InvalidShareCodeExpiry
+        );
+
+        let share_code = &mut ctx.accounts.share_code;
+        share_code.patient_data = ctx.accounts.patient_data.key();
+        share_code.patient = ctx.accounts.patient.key();
+        share_code.code_key = code_key;
+        share_code.fields = fields;
+        share_code.record_nonce = nonce;
+        share_code.record_version = ctx.accounts.patient_data.version;
+        share_code.expires_at = expires_at;
+        share_code.expires_at_slot = clock.slot_at(expires_at);
+        share_code.bump = ctx.bumps.share_code;
+
+        let event = ShareCodeCreatedEvent {
+            share_code: share_code.key(),
+            patient_data: share_code.patient_data,
+            fields,
+            expires_at,
+        };
+        ctx.accounts.patient_registry.audit(
+            event.patient_data,
+            ctx.bumps.patient_registry,
+            &event,
+        )?;
+        emit!(event);
+        Ok(())
+    }
+
+    /// Closes a share code, redeemed or not, returning its rent to the patient.
+    pub fn cancel_share_code(ctx: Context<CancelShareCode>) -> Result<()> {
+        emit!(ShareCodeCancelledEvent {
+            share_code: ctx.accounts.share_code.key(),
+            patient_data: ctx.accounts.share_code.patient_data,
+        });
+        Ok(())
+    }
+
+    pub fn init_redeem_share_code_comp_def(ctx: Context<InitRedeemShareCodeCompDef>) -> Result<()> {
+        let source = ctx
+            .accounts
+            .program_config
+            .circuit_source(COMP_DEF_OFFSET_REDEEM_SHARE_CODE)?;
+        init_comp_def(ctx.accounts, true, 0, Some(source), None)?;
+        ctx.accounts
+            .program_config
+            .mark_circuit_initialized(COMP_DEF_OFFSET_REDEEM_SHARE_CODE)
+    }
+
+    /// Redeems a one-time share code, sharing the fields it covers with the caller.
+    ///
+    /// Called by active providers with an attested x25519 key, which the fields are
+    /// encrypted for, right after an ed25519 program instruction verifying
+    /// `share_code_redemption_message` for the provider and that key, signed with the key
+    /// derived from the scanned code. The code is spent when the share is queued, whether
+    /// or not it completes.
+    ///
+    /// # Arguments
+    /// * `provider_nonce` - Nonce for the provider's encryption
+    pub fn redeem_share_code(
+        ctx: Context<RedeemShareCode>,
+        computation_offset: u64,
+        provider_nonce: u128,
+    ) -> Result<()> {
+        ctx.accounts.program_config.require_compute_enabled()?;
+        ctx.accounts.patient_data.require_live()?;
+        let provider_enc_key = ctx.accounts.provider_profile.enc_pubkey;
+        require!(
+            provider_enc_key != [0; 32],
+            ErrorCode::ReceiverKeyNotAttested
+        );
+        verify_ed25519_signature(
+            &ctx.accounts.instructions_sysvar,
+            ctx.accounts.share_code.code_key.as_ref(),
+            &share_code_redemption_message(ctx.accounts.provider.key, &provider_enc_key),
+        )?;
+        let clock = ctx.accounts.program_config.expiry_clock()?;
+
+        let share_code = &mut ctx.accounts.share_code;
+        require!(
+            share_code.redeemed_by == Pubkey::default(),
+            ErrorCode::ShareCodeRedeemed
+        );
+        require!(
+            !clock.has_passed(share_code.expires_at, share_code.expires_at_slot),
+            ErrorCode::ShareCodeExpired
+        );
+        require!(
+            share_code.record_version == ctx.accounts.patient_data.version,
+            ErrorCode::ShareCodeStale
+        );
+        share_code.redeemed_by = ctx.accounts.provider.key();
+        share_code.redeemed_at = clock.unix_timestamp;
+
+        require_ciphertext_region::<PatientData>(&ctx.accounts.patient_data.to_account_info())?;
+        let args = redeem_share_code_args(
+            provider_enc_key,
+            provider_nonce,
+            ctx.accounts.patient_data.enc_pubkey,
+            share_code.record_nonce,
+            ctx.accounts.patient_data.key(),
+            share_code.fields,
+        );
+
+        let event = ShareCodeRedeemedEvent {
+            share_code: share_code.key(),
+            patient_data: share_code.patient_data,
+            provider: share_code.redeemed_by,
+            fields: share_code.fields,
+            computation_offset,
+        };
+        let bump = ctx.accounts.patient_registry.bump;
+        ctx.accounts
+            .patient_registry
+            .audit(event.patient_data, bump, &event)?;
+        emit!(event);
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+        ctx.accounts
+            .program_config
+            .record_queued(COMP_DEF_OFFSET_REDEEM_SHARE_CODE)?;
+        ctx.accounts.used_offsets.claim(
+            ctx.accounts.provider.key(),
+            ctx.bumps.used_offsets,
+            computation_offset,
+            &ctx.accounts.program_config,
+        )?;
+        ctx.accounts.callback_guard.expect(
+            computation_offset,
+            COMP_DEF_OFFSET_REDEEM_SHARE_CODE,
+            ctx.bumps.callback_guard,
+        );
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![RedeemShareCodeCallback::callback_ix(&[
+                CallbackAccount {
+                    pubkey: ctx.accounts.program_config.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.callback_guard.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.share_code.key(),
+                    is_writable: false,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.used_offsets.key(),
+                    is_writable: true,
+                },
+            ])],
+        )?;
+        Ok(())
+    }
+
+    /// Delivers the fields of a redeemed share code through `ShareCodeDeliveredEvent`.
+    #[arcium_callback(encrypted_ix = "redeem_share_code")]
+    pub fn redeem_share_code_callback(
+        ctx: Context<RedeemShareCodeCallback>,
+        output: ComputationOutputs<RedeemShareCodeOutput>,
+    ) -> Result<()> {
+        let computation_offset = ctx
+            .accounts
+            .callback_guard
+            .consume(COMP_DEF_OFFSET_REDEEM_SHARE_CODE)?;
+        verify_callback_invocation(
+            &ctx.accounts.instructions_sysvar,
+            &ctx.accounts.comp_def_account.key(),
+            computation_offset,
+        )?;
+        ctx.accounts.used_offsets.release(computation_offset);
+        ctx.accounts
+            .program_config
+            .record_completed(COMP_DEF_OFFSET_REDEEM_SHARE_CODE)?;
+
+        let o = match output {
+            ComputationOutputs::Success(RedeemShareCodeOutput { field_0 }) => field_0,
+            _ => {
+                emit!(SharePatientDataAbortedEvent {
+                    comp_def_offset: COMP_DEF_OFFSET_REDEEM_SHARE_CODE,
+                    computation_offset,
+                });
+                return Ok(());
+            }
+        };
+
+        let ([patient_id, age, gender, blood_type, weight, height], allergies) =
+            split_record_output(&o.ciphertexts)?;
+        emit!(ShareCodeDeliveredEvent {
+            share_code: ctx.accounts.share_code.key(),
+            provider: ctx.accounts.share_code.redeemed_by,
+            receiver_enc_key: o.encryption_key,
+            slot: Clock::get()?.slot,
+            nonce: o.nonce.to_le_bytes(),
+            patient_id,
+            age,
+            gender,
+            blood_type,
+            weight,
+            height,
+            allergies,
+        });
+        Ok(())
+    }
+
+    /// Settles a request validation left in `NeedsReview` with the insurer's own decision,
+    /// typically after reviewing the fields disclosed through an escalation.
+    pub fn decide_prior_auth_review(
+        ctx: Context<DecidePriorAuthReview>,
+        approved: bool,
+    ) -> Result<()> {
+        let prior_auth = &mut ctx.accounts.prior_auth;
+        require!(
+            prior_auth.status == PriorAuthStatus::NeedsReview,
+            ErrorCode::PriorAuthNotInReview
+        );
+        prior_auth.status = if approved {
+            PriorAuthStatus::Approved
+        } else {
+            PriorAuthStatus::Denied
+        };
+        prior_auth.decided_at = Clock::get()?.unix_timestamp;
+
+        emit!(PriorAuthDecidedEvent {
+            prior_auth: prior_auth.key(),
+            provider: prior_auth.provider,
+            insurer: prior_auth.insurer,
+            approved,
+            decided_at: prior_auth.decided_at,
+        });
+        Ok(())
+    }
+
+    /// Asks the patient to disclose the fields the insurer disputes in a review, rather
+    /// than the whole record.
+    ///
+    /// Called by the insurer of a request in `NeedsReview`, once per request. The fields
+    /// will be encrypted for the insurer's attested key as of now.
+    ///
+    /// # Arguments
+    /// * `fields` - Bitmask of `PatientField` bits disputed
+    pub fn escalate_prior_auth(ctx: Context<EscalatePriorAuth>, fields: u16) -> Result<()> {
+        require!(
+            ctx.accounts.prior_auth.status == PriorAuthStatus::NeedsReview,
+            ErrorCode::PriorAuthNotInReview
+        );
+        require_field_mask(fields)?;
+        ctx.accounts.program_config.require_collected(fields)?;
+        let insurer_enc_key = ctx.accounts.insurer_profile.enc_pubkey;
+        require!(
+            insurer_enc_key != [0; 32],
+            ErrorCode::ReceiverKeyNotAttested
+        );
+
+        let escalation = &mut ctx.accounts.escalation;
+        escalation.prior_auth = ctx.accounts.prior_auth.key();
+        escalation.insurer = ctx.accounts.insurer.key();
+        escalation.patient_data = ctx.accounts.prior_auth.patient_data;
+        escalation.fields = fields;
+        escalation.insurer_enc_key = insurer_enc_key;
+        escalation.status = EscalationStatus::Requested;
+        escalation.requested_at = Clock::get()?.unix_timestamp;
+        escalation.bump = ctx.bumps.escalation;
+        Inbox::notify(
+            &ctx.accounts.inbox,
+            NotificationKind::PriorAuthEscalated,
+            escalation.key(),
+        )?;
+
+        emit!(PriorAuthEscalatedEvent {
+            escalation: escalation.key(),
+            prior_auth: escalation.prior_auth,
+            patient_data: escalation.patient_data,
+            insurer: escalation.insurer,
+            fields,
+        });
+        Ok(())
+    }
+
+    /// Approves or declines an escalation of the caller's record.
+    ///
+    /// An approval lets the insurer queue the disclosure within
+    /// `ESCALATION_APPROVAL_SECONDS`, and lapses if the record is updated first. Declining
+    /// is final; the insurer decides the review without the fields.
+    ///
+    /// # Arguments
+    /// * `approve` - Whether to disclose the disputed fields
+    /// * `nonce` - Nonce the record's current ciphertexts were encrypted with
+    pub fn answer_prior_auth_escalation(
+        ctx: Context<AnswerPriorAuthEscalation>,
+        approve: bool,
+        nonce: u128,
+    ) -> Result<()> {
+        let escalation = &mut ctx.accounts.escalation;
+        require!(
+            escalation.status == EscalationStatus::Requested,
+            ErrorCode::EscalationAnswered
+        );
+        escalation.status = if approve {
+            EscalationStatus::Approved
+        } else {
+            EscalationStatus::Declined
+        };
+        escalation.answered_at = Clock::get()?.unix_timestamp;
+        escalation.record_nonce = nonce;
+        escalation.record_version = ctx.accounts.patient_data.version;
+
+        let event = PriorAuthEscalationAnsweredEvent {
+            escalation: escalation.key(),
+            patient_data: escalation.patient_data,
+            insurer: escalation.insurer,
+            fields: escalation.fields,
+            approved: approve,
+        };
+        ctx.accounts.patient_registry.audit(
+            event.patient_data,
+            ctx.bumps.patient_registry,
+            &event,
+        )?;
+        emit!(event);
+        Ok(())
+    }
+
+    pub fn init_disclose_escalated_fields_comp_def(
+        ctx: Context<InitDiscloseEscalatedFieldsCompDef>,
+    ) -> Result<()> {
+        let source = ctx
+            .accounts
+            .program_config
+            .circuit_source(COMP_DEF_OFFSET_DISCLOSE_ESCALATED_FIELDS)?;
+        init_comp_def(ctx.accounts, true, 0, Some(source), None)?;
+        ctx.accounts
+            .program_config
+            .mark_circuit_initialized(COMP_DEF_OFFSET_DISCLOSE_ESCALATED_FIELDS)
+    }
+
+    /// Discloses the disputed fields of an approved escalation to the insurer.
+    ///
+    /// Called by the escalation's insurer while the patient's approval is fresh. An
+    /// aborted disclosure can be queued again within the same approval.
+    ///
+    /// # Arguments
+    /// * `insurer_nonce` - Nonce for the insurer's encryption
+    pub fn disclose_escalated_fields(
+        ctx: Context<DiscloseEscalatedFields>,
+        computation_offset: u64,
+        insurer_nonce: u128,
+    ) -> Result<()> {
+        ctx.accounts.program_config.require_compute_enabled()?;
+        ctx.accounts.patient_data.require_live()?;
+        let now = Clock::get()?.unix_timestamp;
+
+        let escalation = &mut ctx.accounts.escalation;
+        require!(
+            escalation.status == EscalationStatus::Approved,
+            ErrorCode::EscalationNotApproved
+        );
+        require!(
+            now - escalation.answered_at <= ESCALATION_APPROVAL_SECONDS,
+            ErrorCode::EscalationApprovalLapsed
+        );
+        require!(
+            escalation.record_version == ctx.accounts.patient_data.version,
+            ErrorCode::EscalationApprovalLapsed
+        );
+        escalation.status = EscalationStatus::Disclosing;
+        escalation.computation_offset = computation_offset;
+
+        require_ciphertext_region::<PatientData>(&ctx.accounts.patient_data.to_account_info())?;
+        let args = disclose_escalated_fields_args(
+            escalation.insurer_enc_key,
+            insurer_nonce,
+            ctx.accounts.patient_data.enc_pubkey,
+            escalation.record_nonce,
+            ctx.accounts.patient_data.key(),
+            escalation.fields,
+        );
+
+        let event = EscalatedFieldsQueuedEvent {
+            escalation: escalation.key(),
+            patient_data: escalation.patient_data,
+            insurer: escalation.insurer,
+            fields: escalation.fields,
+            computation_offset,
+        };
+        let bump = ctx.accounts.patient_registry.bump;
+        ctx.accounts
+            .patient_registry
+            .audit(event.patient_data, bump, &event)?;
+        emit!(event);
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+        ctx.accounts
+            .program_config
+            .record_queued(COMP_DEF_OFFSET_DISCLOSE_ESCALATED_FIELDS)?;
+        ctx.accounts.used_offsets.claim(
+            ctx.accounts.insurer.key(),
+            ctx.bumps.used_offsets,
+            computation_offset,
+            &ctx.accounts.program_config,
+        )?;
+        ctx.accounts.callback_guard.expect(
+            computation_offset,
+            COMP_DEF_OFFSET_DISCLOSE_ESCALATED_FIELDS,
+            ctx.bumps.callback_guard,
+        );
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![DiscloseEscalatedFieldsCallback::callback_ix(&[
+                CallbackAccount {
+                    pubkey: ctx.accounts.program_config.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.callback_guard.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.escalation.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.used_offsets.key(),
+                    is_writable: true,
+                },
+            ])],
+        )?;
+        Ok(())
+    }
+
+    /// Delivers the disputed fields through `EscalatedFieldsDisclosedEvent`.
+    ///
+    /// An aborted disclosure returns the escalation to `Approved` for the insurer to retry.
+    #[arcium_callback(encrypted_ix = "disclose_escalated_fields")]
+    pub fn disclose_escalated_fields_callback(
+        ctx: Context<DiscloseEscalatedFieldsCallback>,
+        output: ComputationOutputs<DiscloseEscalatedFieldsOutput>,
+    ) -> Result<()> {
+        let computation_offset = ctx
+            .accounts
+            .callback_guard
+            .consume(COMP_DEF_OFFSET_DISCLOSE_ESCALATED_FIELDS)?;
+        verify_callback_invocation(
+            &ctx.accounts.instructions_sysvar,
+            &ctx.accounts.comp_def_account.key(),
+            computation_offset,
+        )?;
+        ctx.accounts.used_offsets.release(computation_offset);
+        ctx.accounts
+            .program_config
+            .record_completed(COMP_DEF_OFFSET_DISCLOSE_ESCALATED_FIELDS)?;
+
+        let escalation = &mut ctx.accounts.escalation;
+        let o = match output {
+            ComputationOutputs::Success(DiscloseEscalatedFieldsOutput { field_0 }) => field_0,
+            _ => {
+                escalation.status = EscalationStatus::Approved;
+                emit!(SharePatientDataAbortedEvent {
+                    comp_def_offset: COMP_DEF_OFFSET_DISCLOSE_ESCALATED_FIELDS,
+                    computation_offset,
+                });
+                return Ok(());
+            }
+        };
+        escalation.status = EscalationStatus::Disclosed;
+
+        let ([patient_id, age, gender, blood_type, weight, height], allergies) =
+            split_record_output(&o.ciphertexts)?;
+        emit!(EscalatedFieldsDisclosedEvent {
+            escalation: escalation.key(),
+            prior_auth: escalation.prior_auth,
+            insurer: escalation.insurer,
+            receiver_enc_key: o.encryption_key,
+            slot: Clock::get()?.slot,
+            nonce: o.nonce.to_le_bytes(),
+            patient_id,
+            age,
+            gender,
+            blood_type,
+            weight,
+            height,
+            allergies,
+        });
+        Ok(())
+    }
+
+    /// Lets a staff member, such as a school nurse, log medication given to the caller,
+    /// replacing any earlier authorization of the same staff member.
+    ///
+    /// Entries are encrypted for the caregiver of `caregiver_grant`, which must be one of
+    /// the caller's grants and still active.
+    ///
+    /// # Arguments
+    /// * `staff` - Wallet the staff member signs administrations with
+    pub fn authorize_med_administrator(
+        ctx: Context<AuthorizeMedAdministrator>,
+        staff: Pubkey,
+    ) -> Result<()> {
+        ctx.accounts.patient_data.require_live()?;
+        ctx.accounts
+            .caregiver_grant
+            .require_active(&ExpiryClock::get(0)?)?;
+
+        let log = &mut ctx.accounts.med_administration_log;
+        if log.patient_data == Pubkey::default() {
+            log.patient_data = ctx.accounts.patient_data.key();
+            log.bump = ctx.bumps.med_administration_log;
+        }
+
+        let med_administrator = &mut ctx.accounts.med_administrator;
+        med_administrator.patient_data = ctx.accounts.patient_data.key();
+        med_administrator.patient = ctx.accounts.patient.key();
+        med_administrator.staff = staff;
+        med_administrator.caregiver_grant = ctx.accounts.caregiver_grant.key();
+        med_administrator.revoked = false;
+        med_administrator.authorized_at = Clock::get()?.unix_timestamp;
+        med_administrator.bump = ctx.bumps.med_administrator;
+
+        emit!(MedAdministratorAuthorizedEvent {
+            med_administrator: med_administrator.key(),
+            patient_data: med_administrator.patient_data,
+            staff,
+            caregiver_grant: med_administrator.caregiver_grant,
+        });
+        Ok(())
+    }
+
+    /// Stops a staff member from logging medication. Entries already logged stay in the
+    /// log.
+    pub fn revoke_med_administrator(ctx: Context<RevokeMedAdministrator>) -> Result<()> {
+        let med_administrator = &mut ctx.accounts.med_administrator;
+        med_administrator.revoked = true;
+
+        emit!(MedAdministratorRevokedEvent {
+            med_administrator: med_administrator.key(),
+            patient_data: med_administrator.patient_data,
+            staff: med_administrator.staff,
+        });
+        Ok(())
+    }
+
+    /// Logs a medication given to the patient, through `MedAdministeredEvent`.
+    ///
+    /// Called by an authorized staff member, who encrypts the drug, dose and time given
+    /// for the caregiver key of the grant the patient named. The entry is numbered and
+    /// chained into the record's `MedAdministrationLog`.
+    ///
+    /// # Arguments
+    /// * `values` - Encrypted drug, dose and time given
+    /// * `enc_pubkey` - Staff member's x25519 key the values were encrypted with
+    /// * `nonce` - Nonce the values were encrypted with
+    pub fn med_administration(
+        ctx: Context<MedAdministration>,
+        values: EncryptedMedAdministration,
+        enc_pubkey: [u8; 32],
+        nonce: u128,
+    ) -> Result<()> {
+        ctx.accounts.program_config.require_store_enabled()?;
+        ctx.accounts.patient_data.require_live()?;
+        let grant = &ctx.accounts.caregiver_grant;
+        grant.require_active(&ctx.accounts.program_config.expiry_clock()?)?;
+        require_valid_x25519_key(&enc_pubkey)?;
+
+        let staff = ctx.accounts.staff.key();
+        let logged_at = Clock::get()?.unix_timestamp;
+        let log = &mut ctx.accounts.med_administration_log;
+        let sequence = log.entries;
+        log.head = med_administration_hash(
+            &log.head,
+            sequence,
+            &staff,
+            &enc_pubkey,
+            nonce,
+            &values,
+            logged_at,
+        );
+        log.entries += 1;
+
+        emit!(MedAdministeredEvent {
+            patient_data: log.patient_data,
+            staff,
+            caregiver: grant.caregiver,
+            sequence,
+            enc_pubkey,
+            receiver_enc_key: grant.caregiver_enc_key,
+            nonce,
+            values,
+            logged_at,
+            head: log.head,
+        });
+        Ok(())
+    }
+
+    /// Declares a disaster in a region, letting responders fetch the emergency packets of
+    /// patients whose packets are encrypted for it until `ends_at`.
+    ///
+    /// Needs `disaster_threshold` distinct committee members: the caller, plus co-signers
+    /// passed as signers in `remaining_accounts`. A region is declared again only once its
+    /// previous declaration has lapsed or been ended with `end_disaster_mode`.
+    ///
+    /// # Arguments
+    /// * `region` - Region tag of the EMS authority whose packets are opened
+    /// * `starts_at` - Unix timestamp fetches are allowed from, within
+    ///   `MAX_DISASTER_MODE_NOTICE_SECONDS`
+    /// * `ends_at` - Unix timestamp fetches stop at, within `MAX_DISASTER_MODE_SECONDS`
+    pub fn declare_disaster_mode(
+        ctx: Context<DeclareDisasterMode>,
+        region: u32,
+        starts_at: i64,
+        ends_at: i64,
     ) -> Result<()> {
-        let patient_data = &mut ctx.accounts.patient_data;
-        patient_data.patient_id = patient_id;
-        patient_data.age = age;
-        patient_data.gender = gender;
-        patient_data.blood_type = blood_type;
-        patient_data.weight = weight;
-        patient_data.height = height;
-        patient_data.allergies = allergies;
+        let declared_by = disaster_declarers(
+            &ctx.accounts.program_config,
+            ctx.accounts.declarer.key(),
+            ctx.remaining_accounts,
+        )?;
+        let clock = ctx.accounts.program_config.expiry_clock()?;
+        require!(
+            starts_at
+                <= clock
+                    .unix_timestamp
+                    .saturating_add(MAX_DISASTER_MODE_NOTICE_SECONDS)
+                && ends_at > starts_at
+                && ends_at > clock.unix_timestamp
+                && ends_at - starts_at.max(clock.unix_timestamp) <= MAX_DISASTER_MODE_SECONDS,
+            ErrorCode::InvalidDisasterWindow
+        );
+
+        let disaster_mode = &mut ctx.accounts.disaster_mode;
+        require!(
+            disaster_mode.has_ended(&clock),
+            ErrorCode::DisasterModeStillDeclared
+        );
+        let previous_fetches = disaster_mode.fetches;
+        disaster_mode.declarations = disaster_mode
+            .declarations
+            .checked_add(1)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        disaster_mode.region = region;
+        disaster_mode.starts_at = starts_at;
+        disaster_mode.ends_at = ends_at;
+        disaster_mode.ends_at_slot = clock.slot_at(ends_at);
+        disaster_mode.declared_by = declared_by;
+        disaster_mode.declared_at = clock.unix_timestamp;
+        disaster_mode.fetches = 0;
+        disaster_mode.bump = ctx.bumps.disaster_mode;
 
+        emit!(DisasterModeDeclaredEvent {
+            disaster_mode: disaster_mode.key(),
+            region,
+            declaration: disaster_mode.declarations,
+            starts_at,
+            ends_at,
+            declared_by: disaster_mode.declared_by.clone(),
+            previous_fetches,
+        });
         Ok(())
     }
 
-    pub fn init_share_patient_data_comp_def(
-        ctx: Context<InitSharePatientDataCompDef>,
-    ) -> Result<()> {
-        // TODO: Replace this URL with your actual circuit URL after uploading
-        let circuit_url = "https://your-storage.com/share_patient_data_testnet.arcis";
+    /// Ends a disaster declaration early. Any one committee member may end it.
+    pub fn end_disaster_mode(ctx: Context<EndDisasterMode>) -> Result<()> {
+        let clock = ctx.accounts.program_config.expiry_clock()?;
+        let disaster_mode = &mut ctx.accounts.disaster_mode;
+        if disaster_mode.ends_at > clock.unix_timestamp {
+            disaster_mode.ends_at = clock.unix_timestamp;
+            disaster_mode.ends_at_slot = clock.slot;
+        }
 
-        init_comp_def(
-            ctx.accounts,
-            true,
-            0,
-            Some(CircuitSource::OffChain(OffChainCircuitSource {
-                source: circuit_url.to_string(),
-                hash: [0; 32], // Hash verification not enforced yet
-            })),
-            None,
-        )?;
+        emit!(DisasterModeEndedEvent {
+            disaster_mode: disaster_mode.key(),
+            region: disaster_mode.region,
+            ended_by: ctx.accounts.member.key(),
+            fetches: disaster_mode.fetches,
+        });
         Ok(())
     }
 
-    /// Initiates confidential sharing of patient data with a specified receiver.
+    pub fn init_disaster_packet_comp_def(ctx: Context<InitDisasterPacketCompDef>) -> Result<()> {
+        let source = ctx
+            .accounts
+            .program_config
+            .circuit_source(COMP_DEF_OFFSET_DISASTER_PACKET)?;
+        init_comp_def(ctx.accounts, true, 0, Some(source), None)?;
+        ctx.accounts
+            .program_config
+            .mark_circuit_initialized(COMP_DEF_OFFSET_DISASTER_PACKET)
+    }
+
+    /// Re-encrypts a patient's emergency packet for the calling responder while a
+    /// disaster is declared for the packet's region, without the patient's approval.
     ///
-    /// This function triggers an MPC computation that re-encrypts the patient's medical data
-    /// for a specific receiver. The receiver will be able to decrypt the data using their
-    /// private key, while the data remains encrypted for everyone else. The original
-    /// stored data is not modified and remains encrypted for the original owner.
+    /// Called by active providers with an attested x25519 key, which the packet is
+    /// encrypted for. The fetch is written to the record's access log and audit chain and
+    /// posted to the patient's inbox before it is queued.
     ///
     /// # Arguments
-    /// * `receiver` - Public key of the authorized recipient
-    /// * `receiver_nonce` - Cryptographic nonce for the receiver's encryption
-    /// * `sender_pub_key` - Sender's public key for the operation
-    /// * `nonce` - Cryptographic nonce for the sender's encryption
-    pub fn share_patient_data(
-        ctx: Context<SharePatientData>,
+    /// * `responder_nonce` - Nonce for the responder's encryption
+    pub fn fetch_disaster_packet(
+        ctx: Context<FetchDisasterPacket>,
         computation_offset: u64,
-        receiver: [u8; 32],
-        receiver_nonce: u128,
-        sender_pub_key: [u8; 32],
-        nonce: u128,
+        responder_nonce: u128,
     ) -> Result<()> {
-        let args = vec![
-            Argument::ArcisPubkey(receiver),
-            Argument::PlaintextU128(receiver_nonce),
-            Argument::ArcisPubkey(sender_pub_key),
-            Argument::PlaintextU128(nonce),
-            Argument::Account(
-                ctx.accounts.patient_data.key(),
-                8,
-                PatientData::INIT_SPACE as u32,
-            ),
-        ];
+        ctx.accounts.program_config.require_compute_enabled()?;
+        ctx.accounts.patient_data.require_live()?;
+        let clock = ctx.accounts.program_config.expiry_clock()?;
+        ctx.accounts.disaster_mode.require_active(&clock)?;
+        let responder_enc_key = ctx.accounts.provider_profile.enc_pubkey;
+        require!(
+            responder_enc_key != [0; 32],
+            ErrorCode::ReceiverKeyNotAttested
+        );
+        let packet = &ctx.accounts.emergency_packet;
+        require!(
+            packet.computation_offset != 0,
+            ErrorCode::EmergencyPacketMissing
+        );
+
+        let args = disaster_packet_args(
+            responder_enc_key,
+            responder_nonce,
+            packet.receiver_enc_key,
+            u128::from_le_bytes(packet.nonce),
+            &packet.ciphertexts,
+        );
+
+        let disaster_mode = &mut ctx.accounts.disaster_mode;
+        disaster_mode.fetches = disaster_mode
+            .fetches
+            .checked_add(1)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        let event = DisasterPacketFetchedEvent {
+            disaster_mode: disaster_mode.key(),
+            emergency_packet: packet.key(),
+            patient_data: packet.patient_data,
+            responder: ctx.accounts.responder.key(),
+            receiver_enc_key: responder_enc_key,
+            computation_offset,
+        };
+        ctx.accounts.patient_registry.log_access(
+            event.patient_data,
+            &mut ctx.accounts.access_log_page,
+            ctx.bumps.access_log_page,
+            AccessLogEntry {
+                accessor: event.responder,
+                receiver: responder_enc_key,
+                computation_offset,
+                slot: clock.slot,
+            },
+        )?;
+        ctx.accounts.patient_registry.audit(
+            event.patient_data,
+            ctx.bumps.patient_registry,
+            &event,
+        )?;
+        Inbox::notify(
+            &ctx.accounts.inbox,
+            NotificationKind::DisasterAccessUsed,
+            event.disaster_mode,
+        )?;
+        emit!(event);
 
         ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+        ctx.accounts
+            .program_config
+            .record_queued(COMP_DEF_OFFSET_DISASTER_PACKET)?;
+        ctx.accounts.used_offsets.claim(
+            ctx.accounts.responder.key(),
+            ctx.bumps.used_offsets,
+            computation_offset,
+            &ctx.accounts.program_config,
+        )?;
+        ctx.accounts.callback_guard.expect(
+            computation_offset,
+            COMP_DEF_OFFSET_DISASTER_PACKET,
+            ctx.bumps.callback_guard,
+        );
 
         queue_computation(
             ctx.accounts,
             computation_offset,
             args,
             None,
-            vec![SharePatientDataCallback::callback_ix(&[])],
+            vec![DisasterPacketCallback::callback_ix(&[
+                CallbackAccount {
+                    pubkey: ctx.accounts.program_config.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.callback_guard.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.emergency_packet.key(),
+                    is_writable: false,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.provider_profile.key(),
+                    is_writable: false,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.used_offsets.key(),
+                    is_writable: true,
+                },
+            ])],
         )?;
         Ok(())
     }
 
-    /// Handles the result of the patient data sharing MPC computation.
-    ///
-    /// This callback processes the re-encrypted patient data that has been prepared for
-    /// the specified receiver. It emits an event containing all the medical data fields
-    /// encrypted specifically for the receiver's public key.
-    #[arcium_callback(encrypted_ix = "share_patient_data")]
-    pub fn share_patient_data_callback(
-        ctx: Context<SharePatientDataCallback>,
-        output: ComputationOutputs<SharePatientDataOutput>,
+    /// Delivers a fetched packet through `DisasterPacketDeliveredEvent`.
+    #[arcium_callback(encrypted_ix = "disaster_packet")]
+    pub fn disaster_packet_callback(
+        ctx: Context<DisasterPacketCallback>,
+        output: ComputationOutputs<DisasterPacketOutput>,
     ) -> Result<()> {
+        let computation_offset = ctx
+            .accounts
+            .callback_guard
+            .consume(COMP_DEF_OFFSET_DISASTER_PACKET)?;
+        verify_callback_invocation(
+            &ctx.accounts.instructions_sysvar,
+            &ctx.accounts.comp_def_account.key(),
+            computation_offset,
+        )?;
+        ctx.accounts.used_offsets.release(computation_offset);
+        ctx.accounts
+            .program_config
+            .record_completed(COMP_DEF_OFFSET_DISASTER_PACKET)?;
+
         let o = match output {
-            ComputationOutputs::Success(SharePatientDataOutput { field_0 }) => field_0,
-            _ => return Err(ErrorCode::AbortedComputation.into()),
+            ComputationOutputs::Success(DisasterPacketOutput { field_0 }) => field_0,
+            _ => {
+                emit!(SharePatientDataAbortedEvent {
+                    comp_def_offset: COMP_DEF_OFFSET_DISASTER_PACKET,
+                    computation_offset,
+                });
+                return Ok(());
+            }
         };
 
-        emit!(ReceivedPatientDataEvent {
+        emit!(DisasterPacketDeliveredEvent {
+            emergency_packet: ctx.accounts.emergency_packet.key(),
+            responder: ctx.accounts.provider_profile.provider,
+            receiver_enc_key: o.encryption_key,
+            slot: Clock::get()?.slot,
             nonce: o.nonce.to_le_bytes(),
-            patient_id: o.ciphertexts[0],
-            age: o.ciphertexts[1],
-            gender: o.ciphertexts[2],
-            blood_type: o.ciphertexts[3],
-            weight: o.ciphertexts[4],
-            height: o.ciphertexts[5],
-            allergies: o.ciphertexts[6..11]
+            ciphertexts: o
+                .ciphertexts
+                .as_slice()
                 .try_into()
-                .map_err(|_| ErrorCode::InvalidAllergyData)?,
+                .map_err(|_| ErrorCode::ComputationOutputLength)?,
+        });
+        Ok(())
+    }
+
+    /// Links the records of two biological relatives, signed by both patients, who both
+    /// consent to heritable data being shared until either withdraws.
+    ///
+    /// From then on neither can share a genomic FHIR anchor without passing this link.
+    /// `record_a` must be the lower of the two record addresses.
+    ///
+    /// # Arguments
+    /// * `degree` - How closely the two are related
+    pub fn link_kin(ctx: Context<LinkKin>, degree: KinDegree) -> Result<()> {
+        let record_a = ctx.accounts.record_a.key();
+        let record_b = ctx.accounts.record_b.key();
+        for (roster, patient_data, bump) in [
+            (
+                &mut ctx.accounts.kin_roster_a,
+                record_a,
+                ctx.bumps.kin_roster_a,
+            ),
+            (
+                &mut ctx.accounts.kin_roster_b,
+                record_b,
+                ctx.bumps.kin_roster_b,
+            ),
+        ] {
+            require!(roster.links < MAX_KIN_LINKS, ErrorCode::KinLinkLimitReached);
+            roster.patient_data = patient_data;
+            roster.links += 1;
+            roster.bump = bump;
+        }
+
+        let kin_link = &mut ctx.accounts.kin_link;
+        kin_link.record_a = record_a;
+        kin_link.record_b = record_b;
+        kin_link.degree = degree;
+        kin_link.consent_a = true;
+        kin_link.consent_b = true;
+        kin_link.linked_at = Clock::get()?.unix_timestamp;
+        kin_link.bump = ctx.bumps.kin_link;
+
+        emit!(KinLinkedEvent {
+            kin_link: kin_link.key(),
+            record_a,
+            record_b,
+            degree,
+        });
+        Ok(())
+    }
+
+    /// Withdraws or restores the calling patient's consent on a kin link. While either
+    /// consent is withdrawn, neither relative can share genomic data.
+    ///
+    /// # Arguments
+    /// * `consented` - Whether the patient consents to heritable data being shared
+    pub fn set_kin_consent(ctx: Context<SetKinConsent>, consented: bool) -> Result<()> {
+        let patient_data = ctx.accounts.patient_data.key();
+        let kin_link = &mut ctx.accounts.kin_link;
+        kin_link.set_consent(&patient_data, consented)?;
+
+        emit!(KinConsentChangedEvent {
+            kin_link: kin_link.key(),
+            patient_data,
+            consented,
+        });
+        Ok(())
+    }
+
+    /// Removes a kin link, signed by both patients again, so neither can lift the other's
+    /// protection alone.
+    pub fn unlink_kin(ctx: Context<UnlinkKin>) -> Result<()> {
+        ctx.accounts.kin_roster_a.links -= 1;
+        ctx.accounts.kin_roster_b.links -= 1;
+
+        emit!(KinUnlinkedEvent {
+            kin_link: ctx.accounts.kin_link.key(),
+            record_a: ctx.accounts.record_a.key(),
+            record_b: ctx.accounts.record_b.key(),
+        });
+        Ok(())
+    }
+
+    pub fn unlink_closed_kin(ctx: Context<UnlinkClosedKin>) -> Result<()> {
+        ctx.accounts.kin_roster.links -= 1;
+        ctx.accounts.relative_kin_roster.links -= 1;
+
+        let kin_link = &ctx.accounts.kin_link;
+        emit!(KinUnlinkedEvent {
+            kin_link: kin_link.key(),
+            record_a: kin_link.record_a,
+            record_b: kin_link.record_b,
         });
         Ok(())
     }
@@ -142,22 +8503,119 @@ pub mod share_medical_records {
 
 #[derive(Accounts)]
 pub struct StorePatientData<'info> {
+    /// Pays the record's rent, e.g. a clinic; gains no control over the record
     #[account(mut)]
     pub payer: Signer<'info>,
+    /// Owner of the record
+    pub patient: Signer<'info>,
     pub system_program: Program<'info, System>,
     #[account(
         init,
         payer = payer,
         space = 8 + PatientData::INIT_SPACE,
-        seeds = [b"patient_data", payer.key().as_ref()],
+        seeds = [b"patient_data", patient.key().as_ref()],
+        bump,
+    )]
+    pub patient_data: Account<'info, PatientData>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + UsedNonces::INIT_SPACE,
+        seeds = [USED_NONCES_SEED, patient_data.key().as_ref()],
+        bump,
+    )]
+    pub used_nonces: Box<Account<'info, UsedNonces>>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + PatientRegistry::INIT_SPACE,
+        seeds = [PATIENT_REGISTRY_SEED, patient_data.key().as_ref()],
+        bump,
+        constraint = !patient_registry.record_closed @ ErrorCode::RecordClosed,
+    )]
+    pub patient_registry: Box<Account<'info, PatientRegistry>>,
+}
+
+#[derive(Accounts)]
+pub struct UpdatePatientData<'info> {
+    #[account(mut)]
+    pub patient: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"patient_data", patient.key().as_ref()],
+        bump,
+    )]
+    pub patient_data: Account<'info, PatientData>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    #[account(
+        mut,
+        seeds = [USED_NONCES_SEED, patient_data.key().as_ref()],
+        bump = used_nonces.bump,
+    )]
+    pub used_nonces: Box<Account<'info, UsedNonces>>,
+    #[account(
+        init_if_needed,
+        payer = patient,
+        space = 8 + PatientRegistry::INIT_SPACE,
+        seeds = [PATIENT_REGISTRY_SEED, patient_data.key().as_ref()],
+        bump,
+    )]
+    pub patient_registry: Box<Account<'info, PatientRegistry>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpsertPatientData<'info> {
+    /// Pays the rent of a new record; gains no control over it
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// Owner of the record
+    pub patient: Signer<'info>,
+    pub system_program: Program<'info, System>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + PatientData::INIT_SPACE,
+        seeds = [b"patient_data", patient.key().as_ref()],
         bump,
     )]
     pub patient_data: Account<'info, PatientData>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + UsedNonces::INIT_SPACE,
+        seeds = [USED_NONCES_SEED, patient_data.key().as_ref()],
+        bump,
+    )]
+    pub used_nonces: Box<Account<'info, UsedNonces>>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + PatientRegistry::INIT_SPACE,
+        seeds = [PATIENT_REGISTRY_SEED, patient_data.key().as_ref()],
+        bump,
+        constraint = !patient_registry.record_closed @ ErrorCode::RecordClosed,
+    )]
+    pub patient_registry: Box<Account<'info, PatientRegistry>>,
 }
 
 #[queue_computation_accounts("share_patient_data", payer)]
 #[derive(Accounts)]
-#[instruction(computation_offset: u64)]
+#[instruction(computation_offset: u64, receiver: [u8; 32])]
 pub struct SharePatientData<'info> {
     #[account(mut)]
     pub payer: Signer<'info>,
@@ -167,13 +8625,12 @@ pub struct SharePatientData<'info> {
         payer = payer,
         seeds = [&SIGN_PDA_SEED],
         bump,
-        address = derive_sign_pda!(),
     )]
     pub sign_pda_account: Account<'info, SignerAccount>,
     #[account(
         address = derive_mxe_pda!()
     )]
-    pub mxe_account: Account<'info, MXEAccount>,
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
     #[account(
         mut,
         address = derive_mempool_pda!()
@@ -192,40 +8649,265 @@ pub struct SharePatientData<'info> {
     )]
     /// CHECK: computation_account, checked by the arcium program.
     pub computation_account: UncheckedAccount<'info>,
+    /// Checked against the active share circuit, or the deprecated one during its grace
+    /// window, in the handler.
+    pub comp_def_account: Box<Account<'info, ComputationDefinitionAccount>>,
+    /// Checked against the MXE cluster or the approved `cluster_hint` in the handler.
+    #[account(mut)]
+    pub cluster_account: Box<Account<'info, Cluster>>,
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Box<Account<'info, FeePool>>,
     #[account(
-        address = derive_comp_def_pda!(COMP_DEF_OFFSET_SHARE_PATIENT_DATA)
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
     )]
-    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    pub clock_account: Box<Account<'info, ClockAccount>>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(mut)]
+    pub patient_data: Box<Account<'info, PatientData>>,
+    /// Payer's consent to the record; required unless the payer is its patient authority
+    #[account(
+        seeds = [CONSENT_GRANT_SEED, patient_data.key().as_ref(), payer.key().as_ref()],
+        bump = consent_grant.bump,
+    )]
+    pub consent_grant: Option<Box<Account<'info, ConsentGrant>>>,
     #[account(
         mut,
-        address = derive_cluster_pda!(mxe_account)
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Box<Account<'info, ProgramConfig>>,
+    #[account(
+        seeds = [ACCESS_FEE_SEED, patient_data.key().as_ref()],
+        bump,
     )]
-    pub cluster_account: Account<'info, Cluster>,
+    /// CHECK: access_fee, empty unless the patient charges for shares; read in the handler.
+    pub access_fee: UncheckedAccount<'info>,
+    /// CHECK: subscription, empty unless the payer subscribed; read, and checked against
+    /// its seeds, in the handler only when a fee is due.
+    pub subscription: UncheckedAccount<'info>,
+    pub fee_mint: Option<Box<InterfaceAccount<'info, Mint>>>,
     #[account(
         mut,
-        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+        token::mint = fee_mint,
+        token::authority = payer,
     )]
-    pub pool_account: Account<'info, FeePool>,
+    pub payer_fee_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    pub patient_fee_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    /// Treasury for `fee_mint`; checked in the handler
+    #[account(mut)]
+    pub treasury: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
     #[account(
-        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+        init,
+        payer = payer,
+        token::mint = fee_mint,
+        token::authority = fee_authority,
+        token::token_program = token_program,
+        seeds = [FEE_ESCROW_SEED, &computation_offset.to_le_bytes()],
+        bump,
     )]
-    pub clock_account: Account<'info, ClockAccount>,
-    pub system_program: Program<'info, System>,
-    pub arcium_program: Program<'info, Arcium>,
-    pub patient_data: Account<'info, PatientData>,
+    pub fee_escrow: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    #[account(
+        seeds = [FEE_AUTHORITY_SEED],
+        bump,
+    )]
+    /// CHECK: fee_authority, PDA owning fee escrows, checked by the seeds constraint.
+    pub fee_authority: Option<UncheckedAccount<'info>>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + PendingShare::INIT_SPACE,
+        seeds = [PENDING_SHARE_SEED, patient_data.key().as_ref(), &receiver],
+        bump,
+    )]
+    pub pending_share: Box<Account<'info, PendingShare>>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + RefundBalance::INIT_SPACE,
+        seeds = [REFUND_BALANCE_SEED, payer.key().as_ref()],
+        bump,
+    )]
+    pub refund_balance: Box<Account<'info, RefundBalance>>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + UsedOffsets::INIT_SPACE,
+        seeds = [USED_OFFSETS_SEED, payer.key().as_ref()],
+        bump,
+    )]
+    pub used_offsets: Box<Account<'info, UsedOffsets>>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + CallbackGuard::INIT_SPACE,
+        seeds = [CALLBACK_GUARD_SEED, &computation_offset.to_le_bytes()],
+        bump,
+    )]
+    pub callback_guard: Box<Account<'info, CallbackGuard>>,
+    #[account(
+        mut,
+        seeds = [USED_NONCES_SEED, patient_data.key().as_ref()],
+        bump = used_nonces.bump,
+    )]
+    pub used_nonces: Box<Account<'info, UsedNonces>>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + PatientRegistry::INIT_SPACE,
+        seeds = [PATIENT_REGISTRY_SEED, patient_data.key().as_ref()],
+        bump,
+    )]
+    pub patient_registry: Box<Account<'info, PatientRegistry>>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + AccessLogPage::INIT_SPACE,
+        seeds = [
+            ACCESS_LOG_SEED,
+            patient_data.key().as_ref(),
+            &patient_registry.access_log_page().to_le_bytes(),
+        ],
+        bump,
+    )]
+    pub access_log_page: Box<Account<'info, AccessLogPage>>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + ShareReceipt::INIT_SPACE,
+        seeds = [
+            SHARE_RECEIPT_SEED,
+            patient_data.key().as_ref(),
+            &computation_offset.to_le_bytes(),
+        ],
+        bump,
+    )]
+    pub share_receipt: Box<Account<'info, ShareReceipt>>,
+    /// Receiver's shared record; passed exactly when compact events are on
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + SharedRecord::INIT_SPACE,
+        seeds = [SHARED_RECORD_SEED, patient_data.key().as_ref(), &receiver],
+        bump,
+    )]
+    pub shared_record: Option<Box<Account<'info, SharedRecord>>>,
+    /// CHECK: provider_profile, empty unless the payer is a registered provider; read, and
+    /// checked against its seeds, in the handler for consented, signed and staged shares.
+    pub provider_profile: UncheckedAccount<'info>,
+    /// Arguments staged by `prepare_share`; only passed to `execute_share`
+    #[account(
+        mut,
+        close = payer,
+        seeds = [STAGED_SHARE_SEED, payer.key().as_ref(), &computation_offset.to_le_bytes()],
+        bump = staged_share.bump,
+        constraint = staged_share.patient_data == patient_data.key()
+            && staged_share.params.receiver == receiver
+            @ ErrorCode::StagedShareMismatch,
+    )]
+    pub staged_share: Option<Box<Account<'info, StagedShare>>>,
 }
 
 #[callback_accounts("share_patient_data")]
+#[event_cpi]
 #[derive(Accounts)]
 pub struct SharePatientDataCallback<'info> {
     pub arcium_program: Program<'info, Arcium>,
+    /// Comp def of the share circuit version the computation was queued on, which stays
+    /// valid for its callback after a newer version is activated
     #[account(
-        address = derive_comp_def_pda!(COMP_DEF_OFFSET_SHARE_PATIENT_DATA)
+        address = derive_comp_def_pda!(callback_guard.comp_def_offset)
     )]
     pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
     #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
     /// CHECK: instructions_sysvar, checked by the account constraint
     pub instructions_sysvar: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    #[account(
+        mut,
+        seeds = [CALLBACK_GUARD_SEED, &callback_guard.computation_offset.to_le_bytes()],
+        bump = callback_guard.bump,
+    )]
+    pub callback_guard: Box<Account<'info, CallbackGuard>>,
+    #[account(
+        mut,
+        close = payer,
+        constraint = pending_share.computation_offset == callback_guard.computation_offset
+            @ ErrorCode::CallbackComputationMismatch,
+    )]
+    pub pending_share: Account<'info, PendingShare>,
+    #[account(mut, address = pending_share.payer)]
+    /// CHECK: payer, receives rent and refunds back; checked against the pending share.
+    pub payer: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        seeds = [REFUND_BALANCE_SEED, pending_share.payer.as_ref()],
+        bump = refund_balance.bump,
+    )]
+    pub refund_balance: Account<'info, RefundBalance>,
+    #[account(mut, address = pending_share.patient_data)]
+    pub patient_data: Account<'info, PatientData>,
+    #[account(
+        mut,
+        seeds = [USED_OFFSETS_SEED, pending_share.payer.as_ref()],
+        bump = used_offsets.bump,
+    )]
+    pub used_offsets: Box<Account<'info, UsedOffsets>>,
+    #[account(
+        mut,
+        seeds = [
+            SHARE_RECEIPT_SEED,
+            pending_share.patient_data.as_ref(),
+            &pending_share.computation_offset.to_le_bytes(),
+        ],
+        bump = share_receipt.bump,
+    )]
+    pub share_receipt: Box<Account<'info, ShareReceipt>>,
+    #[account(
+        mut,
+        seeds = [
+            SHARED_RECORD_SEED,
+            pending_share.patient_data.as_ref(),
+            &pending_share.receiver,
+        ],
+        bump = shared_record.bump,
+    )]
+    pub shared_record: Option<Box<Account<'info, SharedRecord>>>,
+    #[account(
+        mut,
+        seeds = [INBOX_SEED, pending_share.patient_data.as_ref()],
+        bump,
+    )]
+    /// CHECK: inbox, empty unless the patient opened one; written in the handler.
+    pub inbox: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub fee_escrow: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    #[account(mut)]
+    pub patient_fee_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    #[account(mut)]
+    pub treasury: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    #[account(mut)]
+    pub payer_fee_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    pub fee_mint: Option<Box<InterfaceAccount<'info, Mint>>>,
+    #[account(
+        seeds = [FEE_AUTHORITY_SEED],
+        bump,
+    )]
+    /// CHECK: fee_authority, PDA owning fee escrows, checked by the seeds constraint.
+    pub fee_authority: Option<UncheckedAccount<'info>>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
 }
 
 #[init_computation_definition_accounts("share_patient_data", payer)]
@@ -244,10 +8926,24 @@ pub struct InitSharePatientDataCompDef<'info> {
     pub comp_def_account: UncheckedAccount<'info>,
     pub arcium_program: Program<'info, Arcium>,
     pub system_program: Program<'info, System>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+        constraint = program_config.admin == payer.key() @ ErrorCode::Unauthorized,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
 }
 
 #[event]
 pub struct ReceivedPatientDataEvent {
+    /// Record that was shared
+    pub patient_record: Pubkey,
+    /// x25519 key the output is encrypted for
+    pub receiver_enc_key: [u8; 32],
+    pub computation_offset: u64,
+    /// Slot the result was delivered at
+    pub slot: u64,
     pub nonce: [u8; 16],
     pub patient_id: [u8; 32],
     pub age: [u8; 32],
@@ -258,6 +8954,20 @@ pub struct ReceivedPatientDataEvent {
     pub allergies: [[u8; 32]; 5],
 }
 
+#[event]
+pub struct PauseFlagsUpdatedEvent {
+    pub paused: bool,
+    pub paused_store: bool,
+    pub paused_share: bool,
+    pub paused_compute: bool,
+}
+
+#[event]
+pub struct SharePatientDataAbortedEvent {
+    pub comp_def_offset: u32,
+    pub computation_offset: u64,
+}
+
 /// Stores encrypted patient medical information.
 #[account]
 #[derive(InitSpace)]
@@ -276,14 +8986,23 @@ pub struct PatientData {
     pub height: [u8; 32],
     /// Array of encrypted allergy information (up to 5 allergies)
     pub allergies: [[u8; 32]; 5],
+    /// Slot the record was stored at, used for retention
+    pub created_slot: u64,
+    /// Set once the ciphertexts have been wiped under the retention policy
+    pub tombstoned: bool,
+    /// Wallet controlling the record, whoever paid its rent; also the key its address
+    /// is derived from
+    pub patient_authority: Pubkey,
+    /// x25519 public key the ciphertexts were encrypted under, which shares must pass
+    pub enc_pubkey: [u8; 32],
+    /// Nonce the ciphertexts were encrypted with
+    pub nonce: u128,
+    /// Share computations queued against the record that have not called back or
+    /// expired; the record cannot be updated or closed while any are in flight
+    pub in_flight_computations: u8,
+    /// Times the ciphertexts have been stored, starting at 1 for the first store
+    pub version: u32,
 }
 
-#[error_code]
-pub enum ErrorCode {
-    #[msg("The computation was aborted")]
-    AbortedComputation,
-    #[msg("Invalid allergy data format")]
-    InvalidAllergyData,
-    #[msg("Cluster not set")]
-    ClusterNotSet,
-}
+/// Length of the ciphertexts at the start of `PatientData`, the part circuits read.
+pub const PATIENT_DATA_CIPHERTEXTS_LEN: u32 = PATIENT_RECORD_BLOCKS as u32 * 32;
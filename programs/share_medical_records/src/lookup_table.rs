@@ -0,0 +1,178 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::address_lookup_table::{
+    self,
+    instruction::{create_lookup_table, extend_lookup_table},
+    state::AddressLookupTable,
+};
+use anchor_lang::solana_program::program::invoke_signed;
+use anchor_lang::solana_program::sysvar::instructions as instructions_sysvar;
+use arcium_anchor::prelude::*;
+
+use crate::ErrorCode;
+use crate::*;
+
+pub const LOOKUP_TABLE_AUTHORITY_SEED: &[u8] = b"lookup_table_authority";
+/// Most addresses added per `extend_program_lookup_table` call, keeping it within one
+/// transaction.
+pub const MAX_LOOKUP_TABLE_EXTEND: usize = 20;
+
+#[event]
+pub struct LookupTableExtendedEvent {
+    pub lookup_table: Pubkey,
+    pub added: u8,
+    /// Addresses still missing from the table after this call
+    pub remaining: u8,
+}
+
+impl ProgramConfig {
+    /// Accounts every computation of the program passes regardless of who calls it.
+    ///
+    /// Covers the program and Arcium PDAs, the clusters computations may be routed to,
+    /// every comp def in the manifest, and the program id that stands in for absent
+    /// optional accounts.
+    pub fn lookup_table_addresses(&self, mxe_cluster: Option<u32>) -> Vec<Pubkey> {
+        let program_address = |seeds: &[&[u8]]| Pubkey::find_program_address(seeds, &crate::ID).0;
+        let mut addresses = vec![
+            crate::ID,
+            program_address(&[PROGRAM_CONFIG_SEED]),
+            program_address(&[FEE_AUTHORITY_SEED]),
+            program_address(&[b"__event_authority"]),
+            derive_sign_pda!(),
+            derive_mxe_pda!(),
+            derive_mempool_pda!(),
+            derive_execpool_pda!(),
+            ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+            ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+            ARCIUM_PROG_ID,
+            anchor_lang::system_program::ID,
+            instructions_sysvar::ID,
+        ];
+        addresses.extend(
+            mxe_cluster
+                .iter()
+                .chain(self.approved_clusters.iter())
+                .map(|&offset| cluster_pda(offset)),
+        );
+        addresses.extend(
+            self.circuit_manifest
+                .iter()
+                .map(|entry| derive_comp_def_pda!(entry.comp_def_offset)),
+        );
+        let mut unique = Vec::with_capacity(addresses.len());
+        for address in addresses {
+            if !unique.contains(&address) {
+                unique.push(address);
+            }
+        }
+        unique
+    }
+}
+
+#[derive(Accounts)]
+pub struct ManageLookupTable<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+        has_one = admin @ ErrorCode::Unauthorized,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    #[account(
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(mut)]
+    /// CHECK: lookup_table, checked against the derived or configured address in the handler.
+    pub lookup_table: UncheckedAccount<'info>,
+    #[account(
+        seeds = [LOOKUP_TABLE_AUTHORITY_SEED],
+        bump,
+    )]
+    /// CHECK: lookup_table_authority, PDA owning the table, checked by the seeds constraint.
+    pub lookup_table_authority: UncheckedAccount<'info>,
+    #[account(address = address_lookup_table::program::ID)]
+    /// CHECK: address_lookup_table_program, checked by the account constraint.
+    pub address_lookup_table_program: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> ManageLookupTable<'info> {
+    fn invoke_as_authority(
+        &self,
+        ix: &anchor_lang::solana_program::instruction::Instruction,
+        bump: u8,
+    ) -> Result<()> {
+        invoke_signed(
+            ix,
+            &[
+                self.lookup_table.to_account_info(),
+                self.lookup_table_authority.to_account_info(),
+                self.admin.to_account_info(),
+                self.system_program.to_account_info(),
+                self.address_lookup_table_program.to_account_info(),
+            ],
+            &[&[LOOKUP_TABLE_AUTHORITY_SEED, &[bump]]],
+        )?;
+        Ok(())
+    }
+}
+
+/// Creates the program's lookup table, owned by the lookup table authority PDA.
+pub fn create_program_lookup_table(
+    accounts: &mut ManageLookupTable,
+    authority_bump: u8,
+    recent_slot: u64,
+) -> Result<()> {
+    let (ix, lookup_table) = create_lookup_table(
+        accounts.lookup_table_authority.key(),
+        accounts.admin.key(),
+        recent_slot,
+    );
+    require_keys_eq!(
+        accounts.lookup_table.key(),
+        lookup_table,
+        ErrorCode::InvalidLookupTable
+    );
+    accounts.invoke_as_authority(&ix, authority_bump)?;
+    accounts.program_config.lookup_table = lookup_table;
+    Ok(())
+}
+
+/// Adds up to `MAX_LOOKUP_TABLE_EXTEND` of the static addresses missing from the table.
+pub fn extend_program_lookup_table(accounts: &ManageLookupTable, authority_bump: u8) -> Result<()> {
+    require_keys_eq!(
+        accounts.lookup_table.key(),
+        accounts.program_config.lookup_table,
+        ErrorCode::InvalidLookupTable
+    );
+    let missing: Vec<Pubkey> = {
+        let data = accounts.lookup_table.try_borrow_data()?;
+        let table =
+            AddressLookupTable::deserialize(&data).map_err(|_| ErrorCode::InvalidLookupTable)?;
+        accounts
+            .program_config
+            .lookup_table_addresses(accounts.mxe_account.cluster)
+            .into_iter()
+            .filter(|address| !table.addresses.contains(address))
+            .collect()
+    };
+    let added = missing.len().min(MAX_LOOKUP_TABLE_EXTEND);
+    if added > 0 {
+        let ix = extend_lookup_table(
+            accounts.lookup_table.key(),
+            accounts.lookup_table_authority.key(),
+            Some(accounts.admin.key()),
+            missing[..added].to_vec(),
+        );
+        accounts.invoke_as_authority(&ix, authority_bump)?;
+    }
+
+    emit!(LookupTableExtendedEvent {
+        lookup_table: accounts.lookup_table.key(),
+        added: added as u8,
+        remaining: (missing.len() - added) as u8,
+    });
+    Ok(())
+}
@@ -0,0 +1,187 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hashv;
+
+use crate::ErrorCode;
+use crate::*;
+
+pub const MED_ADMINISTRATOR_SEED: &[u8] = b"med_administrator";
+pub const MED_ADMINISTRATION_LOG_SEED: &[u8] = b"med_administration_log";
+/// Encrypted values of one administration: drug, dose and time given.
+pub const MED_ADMINISTRATION_VALUES: usize = 3;
+
+/// An administration's values, each encrypted by the staff member for the guardian's
+/// caregiver key.
+pub type EncryptedMedAdministration = [[u8; 32]; MED_ADMINISTRATION_VALUES];
+
+/// Authorization of a staff member, such as a school nurse, to log medication given to
+/// the patient, typically a dependent whose guardian holds a caregiver grant.
+///
+/// Entries are encrypted for the `caregiver_grant` the patient named here, so staff can
+/// write to the guardian but read nothing of the record.
+#[account]
+#[derive(InitSpace)]
+pub struct MedAdministrator {
+    pub patient_data: Pubkey,
+    /// Wallet that owns the record and authorized the staff member
+    pub patient: Pubkey,
+    /// Staff member's wallet, part of the address
+    pub staff: Pubkey,
+    /// Grant whose caregiver key entries are encrypted for
+    pub caregiver_grant: Pubkey,
+    pub revoked: bool,
+    pub authorized_at: i64,
+    pub bump: u8,
+}
+
+/// Chain of every medication administration logged against a record.
+///
+/// Entries are only emitted, in `MedAdministeredEvent`; the log keeps their count and
+/// `hash(prev || entry)` over all of them, so a guardian replaying the events can tell
+/// whether any was dropped, reordered or altered.
+#[account]
+#[derive(InitSpace)]
+pub struct MedAdministrationLog {
+    pub patient_data: Pubkey,
+    /// Entries logged so far; the next entry's sequence
+    pub entries: u64,
+    pub head: [u8; 32],
+    pub bump: u8,
+}
+
+/// Hash folded into a log's `head` for the `sequence`th administration.
+pub fn med_administration_hash(
+    head: &[u8; 32],
+    sequence: u64,
+    staff: &Pubkey,
+    enc_pubkey: &[u8; 32],
+    nonce: u128,
+    values: &EncryptedMedAdministration,
+    logged_at: i64,
+) -> [u8; 32] {
+    let sequence = sequence.to_le_bytes();
+    let nonce = nonce.to_le_bytes();
+    let logged_at = logged_at.to_le_bytes();
+    let mut parts: Vec<&[u8]> = Vec::with_capacity(6 + values.len());
+    parts.push(head);
+    parts.push(&sequence);
+    parts.push(staff.as_ref());
+    parts.push(enc_pubkey);
+    parts.push(&nonce);
+    parts.push(&logged_at);
+    parts.extend(values.iter().map(|value| value.as_slice()));
+    hashv(&parts).to_bytes()
+}
+
+#[event]
+pub struct MedAdministratorAuthorizedEvent {
+    pub med_administrator: Pubkey,
+    pub patient_data: Pubkey,
+    pub staff: Pubkey,
+    pub caregiver_grant: Pubkey,
+}
+
+#[event]
+pub struct MedAdministratorRevokedEvent {
+    pub med_administrator: Pubkey,
+    pub patient_data: Pubkey,
+    pub staff: Pubkey,
+}
+
+/// A medication administration was logged; guardians subscribe by `caregiver`.
+#[event]
+pub struct MedAdministeredEvent {
+    pub patient_data: Pubkey,
+    pub staff: Pubkey,
+    pub caregiver: Pubkey,
+    /// Position of the entry in the record's log
+    pub sequence: u64,
+    /// x25519 key the staff member encrypted the values with
+    pub enc_pubkey: [u8; 32],
+    /// Caregiver key the values are encrypted for
+    pub receiver_enc_key: [u8; 32],
+    pub nonce: u128,
+    /// Encrypted drug, dose and time given
+    pub values: EncryptedMedAdministration,
+    pub logged_at: i64,
+    /// Log head after this entry
+    pub head: [u8; 32],
+}
+
+#[derive(Accounts)]
+#[instruction(staff: Pubkey)]
+pub struct AuthorizeMedAdministrator<'info> {
+    #[account(mut)]
+    pub patient: Signer<'info>,
+    #[account(
+        seeds = [b"patient_data", patient.key().as_ref()],
+        bump,
+    )]
+    pub patient_data: Account<'info, PatientData>,
+    #[account(
+        seeds = [
+            CAREGIVER_GRANT_SEED,
+            patient_data.key().as_ref(),
+            caregiver_grant.caregiver.as_ref(),
+        ],
+        bump = caregiver_grant.bump,
+    )]
+    pub caregiver_grant: Box<Account<'info, CaregiverGrant>>,
+    #[account(
+        init_if_needed,
+        payer = patient,
+        space = 8 + MedAdministrator::INIT_SPACE,
+        seeds = [MED_ADMINISTRATOR_SEED, patient_data.key().as_ref(), staff.as_ref()],
+        bump,
+    )]
+    pub med_administrator: Account<'info, MedAdministrator>,
+    #[account(
+        init_if_needed,
+        payer = patient,
+        space = 8 + MedAdministrationLog::INIT_SPACE,
+        seeds = [MED_ADMINISTRATION_LOG_SEED, patient_data.key().as_ref()],
+        bump,
+    )]
+    pub med_administration_log: Account<'info, MedAdministrationLog>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RevokeMedAdministrator<'info> {
+    pub patient: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [
+            MED_ADMINISTRATOR_SEED,
+            med_administrator.patient_data.as_ref(),
+            med_administrator.staff.as_ref(),
+        ],
+        bump = med_administrator.bump,
+        has_one = patient @ ErrorCode::Unauthorized,
+    )]
+    pub med_administrator: Account<'info, MedAdministrator>,
+}
+
+#[derive(Accounts)]
+pub struct MedAdministration<'info> {
+    pub staff: Signer<'info>,
+    #[account(
+        seeds = [MED_ADMINISTRATOR_SEED, patient_data.key().as_ref(), staff.key().as_ref()],
+        bump = med_administrator.bump,
+        constraint = !med_administrator.revoked @ ErrorCode::MedAdministratorRevoked,
+    )]
+    pub med_administrator: Account<'info, MedAdministrator>,
+    pub patient_data: Account<'info, PatientData>,
+    #[account(address = med_administrator.caregiver_grant)]
+    pub caregiver_grant: Box<Account<'info, CaregiverGrant>>,
+    #[account(
+        mut,
+        seeds = [MED_ADMINISTRATION_LOG_SEED, patient_data.key().as_ref()],
+        bump = med_administration_log.bump,
+    )]
+    pub med_administration_log: Account<'info, MedAdministrationLog>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
@@ -0,0 +1,46 @@
+use anchor_lang::prelude::*;
+
+use crate::ErrorCode;
+
+pub const USED_NONCES_SEED: &[u8] = b"used_nonces";
+/// Nonces each record remembers before the oldest is forgotten.
+pub const USED_NONCES_CAPACITY: usize = 64;
+
+/// Ring buffer of the nonces most recently used with a record.
+///
+/// Rescue is a stream cipher: two encryptions under the same shared secret and nonce
+/// leak the difference of their plaintexts. Stores and updates record the nonce the
+/// fields were encrypted with, and shares the nonce of the receiver's re-encryption, so
+/// a client replaying one fails with `NonceReused` instead of weakening the ciphertexts.
+/// Nonces are drawn at random, so a window of recent ones catches accidental reuse; the
+/// buffer is a fixed size so its rent does not grow with the record's history.
+#[account]
+#[derive(InitSpace)]
+pub struct UsedNonces {
+    pub patient_data: Pubkey,
+    pub nonces: [u128; USED_NONCES_CAPACITY],
+    /// Slot in `nonces` the next nonce is written to
+    pub next: u8,
+    /// Nonces recorded so far, at most `USED_NONCES_CAPACITY` of which are remembered
+    pub count: u64,
+    pub bump: u8,
+}
+
+impl UsedNonces {
+    /// Whether `nonce` is among the remembered nonces.
+    pub fn contains(&self, nonce: u128) -> bool {
+        let remembered = (self.count as usize).min(USED_NONCES_CAPACITY);
+        self.nonces[..remembered].contains(&nonce)
+    }
+
+    /// Records `nonce` as used with `patient_data`, rejecting one still remembered.
+    pub fn claim(&mut self, patient_data: Pubkey, bump: u8, nonce: u128) -> Result<()> {
+        require!(!self.contains(nonce), ErrorCode::NonceReused);
+        self.nonces[self.next as usize] = nonce;
+        self.next = ((self.next as usize + 1) % USED_NONCES_CAPACITY) as u8;
+        self.patient_data = patient_data;
+        self.bump = bump;
+        self.count = self.count.saturating_add(1);
+        Ok(())
+    }
+}
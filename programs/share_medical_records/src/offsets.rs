@@ -0,0 +1,103 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hash;
+
+use crate::ErrorCode;
+use crate::*;
+
+pub const USED_OFFSETS_SEED: &[u8] = b"used_offsets";
+/// Size of each payer's filter of used computation offsets.
+pub const USED_OFFSETS_BITS: usize = 8192;
+/// Bits set per offset in the filter.
+pub const USED_OFFSETS_HASHES: usize = 3;
+/// Computations a payer can have in flight at most, whatever the config allows.
+pub const MAX_IN_FLIGHT_PER_PAYER: usize = 16;
+pub const DEFAULT_MAX_IN_FLIGHT_PER_PAYER: u8 = 8;
+
+/// A computation a payer queued that has not called back yet.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, InitSpace)]
+pub struct InFlightComputation {
+    pub computation_offset: u64,
+    /// Unix timestamp the computation times out at and stops counting against the cap;
+    /// zero for a free slot
+    pub deadline: i64,
+}
+
+/// Bloom filter over every computation offset a payer has queued.
+///
+/// Arcium keeps one computation account per offset, so queueing an offset twice fails
+/// deep inside the Arcium program. Checking the filter first turns that into
+/// `OffsetAlreadyUsed`. A fresh offset may collide with the filter as it fills up; clients
+/// avoid this by reading the filter and drawing another offset (see
+/// [`UsedOffsets::may_contain`]).
+#[account]
+#[derive(InitSpace)]
+pub struct UsedOffsets {
+    pub payer: Pubkey,
+    pub bits: [u8; USED_OFFSETS_BITS / 8],
+    /// Offsets recorded so far, to judge how full the filter is
+    pub count: u64,
+    pub bump: u8,
+    /// Computations queued by the payer, capped by the config's `max_in_flight_per_payer`
+    pub in_flight: [InFlightComputation; MAX_IN_FLIGHT_PER_PAYER],
+}
+
+impl UsedOffsets {
+    /// Filter bits of `offset`, taken from a single SHA-256 of it.
+    pub fn bit_indices(offset: u64) -> [usize; USED_OFFSETS_HASHES] {
+        let digest = hash(&offset.to_le_bytes()).to_bytes();
+        let mut indices = [0; USED_OFFSETS_HASHES];
+        for (i, index) in indices.iter_mut().enumerate() {
+            let word = u32::from_le_bytes(digest[4 * i..4 * i + 4].try_into().unwrap());
+            *index = word as usize % USED_OFFSETS_BITS;
+        }
+        indices
+    }
+
+    /// Whether `offset` may have been used; false means it certainly has not.
+    pub fn may_contain(&self, offset: u64) -> bool {
+        Self::bit_indices(offset)
+            .iter()
+            .all(|&index| self.bits[index / 8] & (1 << (index % 8)) != 0)
+    }
+
+    /// Records `offset` as used by `payer` and in flight, rejecting one the filter
+    /// already holds or a payer at the config's in-flight cap.
+    ///
+    /// Computations without a callback stop counting once the config's computation
+    /// timeout has passed, so a lost callback cannot lock the payer out for good.
+    pub fn claim(
+        &mut self,
+        payer: Pubkey,
+        bump: u8,
+        offset: u64,
+        config: &ProgramConfig,
+    ) -> Result<()> {
+        require!(!self.may_contain(offset), ErrorCode::OffsetAlreadyUsed);
+        let now = Clock::get()?.unix_timestamp;
+        let slot = self.in_flight[..config.max_in_flight_per_payer as usize]
+            .iter_mut()
+            .find(|computation| computation.deadline <= now)
+            .ok_or(ErrorCode::TooManyInFlightComputations)?;
+        *slot = InFlightComputation {
+            computation_offset: offset,
+            deadline: now.saturating_add(config.computation_timeout),
+        };
+        for index in Self::bit_indices(offset) {
+            self.bits[index / 8] |= 1 << (index % 8);
+        }
+        self.payer = payer;
+        self.bump = bump;
+        self.count = self.count.saturating_add(1);
+        Ok(())
+    }
+
+    /// Frees the in-flight slot of `offset` once its computation has called back or
+    /// been expired.
+    pub fn release(&mut self, offset: u64) {
+        if let Some(computation) = self.in_flight.iter_mut().find(|computation| {
+            computation.deadline > 0 && computation.computation_offset == offset
+        }) {
+            *computation = InFlightComputation::default();
+        }
+    }
+}
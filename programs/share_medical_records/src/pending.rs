@@ -0,0 +1,214 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+use crate::ErrorCode;
+use crate::*;
+
+pub const PENDING_SHARE_SEED: &[u8] = b"pending_share";
+pub const REFUND_BALANCE_SEED: &[u8] = b"refund_balance";
+pub const REFUND_VAULT_SEED: &[u8] = b"refund_vault";
+
+/// Bookkeeping for a share computation that has been queued but not yet called back.
+///
+/// Seeded by the record and the receiver's key, so it doubles as a lock: while one share
+/// of a record to a receiver is in flight, queueing another, e.g. a resubmitted
+/// transaction, fails to create the account instead of paying for a second computation.
+/// The callback or `expire_pending_share` closes it.
+#[account]
+#[derive(InitSpace)]
+pub struct PendingShare {
+    pub payer: Pubkey,
+    pub patient_data: Pubkey,
+    /// x25519 key the record is being re-encrypted for
+    pub receiver: [u8; 32],
+    pub computation_offset: u64,
+    /// Lamports the payer spent queueing the computation with Arcium
+    pub arcium_fee: u64,
+    /// Access fee escrowed for the share, in base units of the record's fee mint; zero
+    /// when none was charged
+    pub escrowed_fee: u64,
+    /// Unix timestamp the computation was queued at
+    pub queued_at: i64,
+    pub bump: u8,
+}
+
+impl PatientData {
+    /// Marks a computation reading the record's ciphertexts as queued.
+    pub fn begin_computation(&mut self) -> Result<()> {
+        self.in_flight_computations = self
+            .in_flight_computations
+            .checked_add(1)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        Ok(())
+    }
+
+    /// Marks a queued computation as called back or expired.
+    pub fn end_computation(&mut self) {
+        self.in_flight_computations = self.in_flight_computations.saturating_sub(1);
+    }
+
+    /// Fails with `RecordFrozen` while computations reading the ciphertexts are queued,
+    /// so they are not rewritten or closed under the cluster.
+    pub fn require_not_in_flight(&self) -> Result<()> {
+        require!(self.in_flight_computations == 0, ErrorCode::RecordFrozen);
+        Ok(())
+    }
+}
+
+/// Computation fees owed back to a payer for shares that failed or timed out.
+#[account]
+#[derive(InitSpace)]
+pub struct RefundBalance {
+    pub payer: Pubkey,
+    pub lamports: u64,
+    pub bump: u8,
+}
+
+impl RefundBalance {
+    pub fn credit(&mut self, lamports: u64) -> Result<()> {
+        self.lamports = self
+            .lamports
+            .checked_add(lamports)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        Ok(())
+    }
+
+    /// Pays `payer` as much of the balance as `refund_vault` holds above its rent
+    /// exemption, returning the lamports paid. Whatever the vault cannot cover stays owed.
+    pub fn settle_from(&mut self, refund_vault: &AccountInfo, payer: &AccountInfo) -> Result<u64> {
+        let rent_exempt = Rent::get()?.minimum_balance(refund_vault.data_len());
+        let lamports = self
+            .lamports
+            .min(refund_vault.lamports().saturating_sub(rent_exempt));
+        **refund_vault.try_borrow_mut_lamports()? -= lamports;
+        **payer.try_borrow_mut_lamports()? += lamports;
+        self.lamports -= lamports;
+        Ok(lamports)
+    }
+}
+
+/// Program-owned lamport pool that refund balances are paid out of.
+#[account]
+#[derive(InitSpace)]
+pub struct RefundVault {
+    pub bump: u8,
+}
+
+#[event]
+pub struct ComputationFeeRefundCreditedEvent {
+    pub payer: Pubkey,
+    pub computation_offset: u64,
+    pub lamports: u64,
+    /// Part of the payer's refund balance paid out of the refund vault on the spot
+    pub paid_out: u64,
+    pub timed_out: bool,
+}
+
+#[derive(Accounts)]
+pub struct ExpirePendingShare<'info> {
+    #[account(
+        mut,
+        close = payer,
+    )]
+    pub pending_share: Account<'info, PendingShare>,
+    #[account(mut, address = pending_share.payer)]
+    /// CHECK: payer, receives the pending share rent back; checked against the pending share.
+    pub payer: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        seeds = [REFUND_BALANCE_SEED, pending_share.payer.as_ref()],
+        bump = refund_balance.bump,
+    )]
+    pub refund_balance: Account<'info, RefundBalance>,
+    #[account(
+        mut,
+        seeds = [REFUND_VAULT_SEED],
+        bump = refund_vault.bump,
+    )]
+    pub refund_vault: Account<'info, RefundVault>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    #[account(mut, address = pending_share.patient_data)]
+    pub patient_data: Account<'info, PatientData>,
+    #[account(
+        mut,
+        seeds = [FEE_ESCROW_SEED, &pending_share.computation_offset.to_le_bytes()],
+        bump,
+    )]
+    pub fee_escrow: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    #[account(
+        mut,
+        token::mint = fee_mint,
+        token::authority = payer,
+    )]
+    pub payer_fee_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    pub fee_mint: Option<Box<InterfaceAccount<'info, Mint>>>,
+    #[account(
+        seeds = [FEE_AUTHORITY_SEED],
+        bump,
+    )]
+    /// CHECK: fee_authority, PDA owning fee escrows, checked by the seeds constraint.
+    pub fee_authority: Option<UncheckedAccount<'info>>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    #[account(
+        mut,
+        seeds = [CALLBACK_GUARD_SEED, &pending_share.computation_offset.to_le_bytes()],
+        bump = callback_guard.bump,
+    )]
+    pub callback_guard: Box<Account<'info, CallbackGuard>>,
+    #[account(
+        mut,
+        seeds = [USED_OFFSETS_SEED, pending_share.payer.as_ref()],
+        bump = used_offsets.bump,
+    )]
+    pub used_offsets: Box<Account<'info, UsedOffsets>>,
+    #[account(
+        mut,
+        close = payer,
+        seeds = [
+            SHARE_RECEIPT_SEED,
+            pending_share.patient_data.as_ref(),
+            &pending_share.computation_offset.to_le_bytes(),
+        ],
+        bump = share_receipt.bump,
+    )]
+    pub share_receipt: Box<Account<'info, ShareReceipt>>,
+}
+
+#[derive(Accounts)]
+pub struct FundRefundVault<'info> {
+    #[account(mut)]
+    pub funder: Signer<'info>,
+    #[account(
+        init_if_needed,
+        payer = funder,
+        space = 8 + RefundVault::INIT_SPACE,
+        seeds = [REFUND_VAULT_SEED],
+        bump,
+    )]
+    pub refund_vault: Account<'info, RefundVault>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimRefund<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [REFUND_BALANCE_SEED, payer.key().as_ref()],
+        bump = refund_balance.bump,
+        has_one = payer @ ErrorCode::Unauthorized,
+    )]
+    pub refund_balance: Account<'info, RefundBalance>,
+    #[account(
+        mut,
+        seeds = [REFUND_VAULT_SEED],
+        bump = refund_vault.bump,
+    )]
+    pub refund_vault: Account<'info, RefundVault>,
+}
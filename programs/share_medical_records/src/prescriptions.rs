@@ -0,0 +1,499 @@
+use anchor_lang::prelude::*;
+use arcium_anchor::prelude::*;
+
+use crate::ErrorCode;
+use crate::*;
+
+pub const PRESCRIPTION_SEED: &[u8] = b"prescription";
+pub const DISPENSE_ENTRY_SEED: &[u8] = b"dispense_entry";
+/// Length of the ciphertext at the start of `Prescription`, the part circuits read.
+pub const PRESCRIPTION_CIPHERTEXTS_LEN: u32 = 32;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub enum PrescriptionStatus {
+    /// Waiting for the refill count to be moved into cluster-owned state
+    Issuing,
+    /// Fills may be verified
+    Active,
+    /// A pharmacy's fill is being counted, waiting for the callback
+    Verifying,
+    /// A pharmacy's fill was counted and may be dispensed by it
+    Authorized,
+    /// No refills left
+    Exhausted,
+}
+
+/// A prescription issued by a physician against a patient's record.
+///
+/// The refill count is encrypted to the cluster itself, so no party can read or reset it
+/// and only `verify_prescription` counts it down. Fills are serialized through `status`:
+/// one pharmacy at a time holds the prescription from verification until it records the
+/// dispense, so two pharmacies can never dispense the same refill.
+#[account]
+#[derive(InitSpace)]
+pub struct Prescription {
+    /// Refills left, encrypted to the cluster
+    pub refills: [u8; 32],
+    /// Nonce of `refills`
+    pub refills_nonce: u128,
+    pub patient_data: Pubkey,
+    pub prescriber: Pubkey,
+    pub prescription_id: u64,
+    pub status: PrescriptionStatus,
+    /// Pharmacy verifying or authorized to dispense the current fill
+    pub pharmacy: Pubkey,
+    /// Fills dispensed so far, also the number of the next `DispenseEntry`
+    pub dispensed: u16,
+    /// Offset of the fill being verified, while `Verifying`
+    pub computation_offset: u64,
+    /// Unix timestamp the fill being verified was queued at, which its expiry counts from
+    pub verifying_since: i64,
+    pub bump: u8,
+}
+
+/// One fill of a prescription, recorded by the pharmacy that dispensed it.
+#[account]
+#[derive(InitSpace)]
+pub struct DispenseEntry {
+    pub prescription: Pubkey,
+    pub pharmacy: Pubkey,
+    /// Zero-based number of the fill
+    pub fill: u16,
+    /// Unix timestamp of the dispense
+    pub dispensed_at: i64,
+    pub bump: u8,
+}
+
+#[event]
+pub struct PrescriptionIssuedEvent {
+    pub prescription: Pubkey,
+    pub patient_data: Pubkey,
+    pub prescriber: Pubkey,
+    /// Set when the computation aborted; the prescription stays `Issuing` and is not usable
+    pub aborted: bool,
+}
+
+#[event]
+pub struct PrescriptionVerifiedEvent {
+    pub prescription: Pubkey,
+    pub pharmacy: Pubkey,
+    /// Whether a refill was left and the fill may be dispensed
+    pub dispensable: bool,
+    /// Set when the computation aborted; the prescription is active again
+    pub aborted: bool,
+}
+
+#[event]
+pub struct PrescriptionVerificationExpiredEvent {
+    pub prescription: Pubkey,
+    /// Pharmacy whose fill was being verified; it may verify again
+    pub pharmacy: Pubkey,
+    pub computation_offset: u64,
+}
+
+#[event]
+pub struct PrescriptionDispensedEvent {
+    pub prescription: Pubkey,
+    pub dispense_entry: Pubkey,
+    pub pharmacy: Pubkey,
+    pub fill: u16,
+}
+
+#[queue_computation_accounts("issue_prescription", prescriber)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64, prescription_id: u64)]
+pub struct IssuePrescription<'info> {
+    #[account(mut)]
+    pub prescriber: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = prescriber,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account, checked by the arcium program.
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool, checked by the arcium program.
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account, checked by the arcium program.
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_ISSUE_PRESCRIPTION)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(
+        mut,
+        address = derive_cluster_pda!(mxe_account)
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Box<Account<'info, ProgramConfig>>,
+    #[account(
+        seeds = [PROVIDER_SEED, prescriber.key().as_ref()],
+        bump = provider_profile.bump,
+        constraint = provider_profile.active
+            && provider_profile.role == ProviderRole::Physician
+            @ ErrorCode::Unauthorized,
+    )]
+    pub provider_profile: Box<Account<'info, ProviderProfile>>,
+    pub patient_data: Box<Account<'info, PatientData>>,
+    #[account(
+        init,
+        payer = prescriber,
+        space = 8 + Prescription::INIT_SPACE,
+        seeds = [
+            PRESCRIPTION_SEED,
+            patient_data.key().as_ref(),
+            &prescription_id.to_le_bytes(),
+        ],
+        bump,
+    )]
+    pub prescription: Box<Account<'info, Prescription>>,
+    #[account(
+        init_if_needed,
+        payer = prescriber,
+        space = 8 + UsedOffsets::INIT_SPACE,
+        seeds = [USED_OFFSETS_SEED, prescriber.key().as_ref()],
+        bump,
+    )]
+    pub used_offsets: Box<Account<'info, UsedOffsets>>,
+    #[account(
+        init,
+        payer = prescriber,
+        space = 8 + CallbackGuard::INIT_SPACE,
+        seeds = [CALLBACK_GUARD_SEED, &computation_offset.to_le_bytes()],
+        bump,
+    )]
+    pub callback_guard: Box<Account<'info, CallbackGuard>>,
+}
+
+#[callback_accounts("issue_prescription")]
+#[derive(Accounts)]
+pub struct IssuePrescriptionCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_ISSUE_PRESCRIPTION)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    #[account(
+        mut,
+        seeds = [CALLBACK_GUARD_SEED, &callback_guard.computation_offset.to_le_bytes()],
+        bump = callback_guard.bump,
+    )]
+    pub callback_guard: Box<Account<'info, CallbackGuard>>,
+    #[account(
+        mut,
+        seeds = [
+            PRESCRIPTION_SEED,
+            prescription.patient_data.as_ref(),
+            &prescription.prescription_id.to_le_bytes(),
+        ],
+        bump = prescription.bump,
+    )]
+    pub prescription: Box<Account<'info, Prescription>>,
+    #[account(
+        mut,
+        seeds = [USED_OFFSETS_SEED, used_offsets.payer.as_ref()],
+        bump = used_offsets.bump,
+    )]
+    pub used_offsets: Box<Account<'info, UsedOffsets>>,
+}
+
+#[queue_computation_accounts("verify_prescription", pharmacy)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct VerifyPrescription<'info> {
+    #[account(mut)]
+    pub pharmacy: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = pharmacy,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account, checked by the arcium program.
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool, checked by the arcium program.
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account, checked by the arcium program.
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_VERIFY_PRESCRIPTION)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(
+        mut,
+        address = derive_cluster_pda!(mxe_account)
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Box<Account<'info, ProgramConfig>>,
+    #[account(
+        seeds = [PROVIDER_SEED, pharmacy.key().as_ref()],
+        bump = provider_profile.bump,
+        constraint = provider_profile.active
+            && provider_profile.role == ProviderRole::Pharmacist
+            @ ErrorCode::Unauthorized,
+    )]
+    pub provider_profile: Box<Account<'info, ProviderProfile>>,
+    #[account(
+        mut,
+        seeds = [
+            PRESCRIPTION_SEED,
+            prescription.patient_data.as_ref(),
+            &prescription.prescription_id.to_le_bytes(),
+        ],
+        bump = prescription.bump,
+    )]
+    pub prescription: Box<Account<'info, Prescription>>,
+    #[account(
+        init_if_needed,
+        payer = pharmacy,
+        space = 8 + UsedOffsets::INIT_SPACE,
+        seeds = [USED_OFFSETS_SEED, pharmacy.key().as_ref()],
+        bump,
+    )]
+    pub used_offsets: Box<Account<'info, UsedOffsets>>,
+    #[account(
+        init,
+        payer = pharmacy,
+        space = 8 + CallbackGuard::INIT_SPACE,
+        seeds = [CALLBACK_GUARD_SEED, &computation_offset.to_le_bytes()],
+        bump,
+    )]
+    pub callback_guard: Box<Account<'info, CallbackGuard>>,
+}
+
+#[callback_accounts("verify_prescription")]
+#[derive(Accounts)]
+pub struct VerifyPrescriptionCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_VERIFY_PRESCRIPTION)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    #[account(
+        mut,
+        seeds = [CALLBACK_GUARD_SEED, &callback_guard.computation_offset.to_le_bytes()],
+        bump = callback_guard.bump,
+    )]
+    pub callback_guard: Box<Account<'info, CallbackGuard>>,
+    #[account(
+        mut,
+        seeds = [
+            PRESCRIPTION_SEED,
+            prescription.patient_data.as_ref(),
+            &prescription.prescription_id.to_le_bytes(),
+        ],
+        bump = prescription.bump,
+    )]
+    pub prescription: Box<Account<'info, Prescription>>,
+    #[account(
+        mut,
+        seeds = [USED_OFFSETS_SEED, used_offsets.payer.as_ref()],
+        bump = used_offsets.bump,
+    )]
+    pub used_offsets: Box<Account<'info, UsedOffsets>>,
+}
+
+#[derive(Accounts)]
+pub struct DispenseRecord<'info> {
+    #[account(mut)]
+    pub pharmacy: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [
+            PRESCRIPTION_SEED,
+            prescription.patient_data.as_ref(),
+            &prescription.prescription_id.to_le_bytes(),
+        ],
+        bump = prescription.bump,
+        constraint = prescription.status == PrescriptionStatus::Authorized
+            && prescription.pharmacy == pharmacy.key()
+            @ ErrorCode::DispenseNotAuthorized,
+    )]
+    pub prescription: Account<'info, Prescription>,
+    #[account(
+        init,
+        payer = pharmacy,
+        space = 8 + DispenseEntry::INIT_SPACE,
+        seeds = [
+            DISPENSE_ENTRY_SEED,
+            prescription.key().as_ref(),
+            &prescription.dispensed.to_le_bytes(),
+        ],
+        bump,
+    )]
+    pub dispense_entry: Account<'info, DispenseEntry>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ExpirePrescriptionVerification<'info> {
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    #[account(
+        mut,
+        seeds = [
+            PRESCRIPTION_SEED,
+            prescription.patient_data.as_ref(),
+            &prescription.prescription_id.to_le_bytes(),
+        ],
+        bump = prescription.bump,
+        constraint = prescription.status == PrescriptionStatus::Verifying
+            @ ErrorCode::PrescriptionNotVerifying,
+    )]
+    pub prescription: Account<'info, Prescription>,
+    #[account(
+        mut,
+        seeds = [CALLBACK_GUARD_SEED, &prescription.computation_offset.to_le_bytes()],
+        bump = callback_guard.bump,
+    )]
+    pub callback_guard: Account<'info, CallbackGuard>,
+    #[account(
+        mut,
+        seeds = [USED_OFFSETS_SEED, prescription.pharmacy.as_ref()],
+        bump = used_offsets.bump,
+    )]
+    pub used_offsets: Box<Account<'info, UsedOffsets>>,
+}
+
+#[init_computation_definition_accounts("issue_prescription", payer)]
+#[derive(Accounts)]
+pub struct InitIssuePrescriptionCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut)]
+    /// CHECK: comp_def_account, checked by arcium program.
+    /// Can't check it here as it's not initialized yet.
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+        constraint = program_config.admin == payer.key() @ ErrorCode::Unauthorized,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[init_computation_definition_accounts("verify_prescription", payer)]
+#[derive(Accounts)]
+pub struct InitVerifyPrescriptionCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut)]
+    /// CHECK: comp_def_account, checked by arcium program.
+    /// Can't check it here as it's not initialized yet.
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+        constraint = program_config.admin == payer.key() @ ErrorCode::Unauthorized,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
@@ -0,0 +1,364 @@
+use anchor_lang::prelude::*;
+use arcium_anchor::prelude::*;
+
+use crate::ErrorCode;
+use crate::*;
+
+pub const PRIOR_AUTH_SEED: &[u8] = b"prior_auth";
+/// Diagnosis and procedure pairs a coverage policy holds, fixed by the
+/// `validate_prior_auth` circuit.
+pub const COVERAGE_POLICY_PAIRS: usize = 4;
+/// Length of the ciphertexts at the start of `PriorAuthRequest`, the part circuits read.
+pub const PRIOR_AUTH_CIPHERTEXTS_LEN: u32 = 2 * 32;
+/// Outcomes `validate_prior_auth` reveals.
+pub const PRIOR_AUTH_OUTCOME_DENIED: u8 = 0;
+pub const PRIOR_AUTH_OUTCOME_APPROVED: u8 = 1;
+/// The policy covers the diagnosis but not with the requested procedure.
+pub const PRIOR_AUTH_OUTCOME_NEEDS_REVIEW: u8 = 2;
+
+/// An insurer's covered pairs encrypted as `u64` code values: the diagnoses, then the
+/// procedures they are paired with.
+pub type EncryptedCoveragePolicy = [[u8; 32]; 2 * COVERAGE_POLICY_PAIRS];
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub enum PriorAuthStatus {
+    /// Waiting for the insurer to validate it
+    Pending,
+    /// Validation queued, waiting for the callback
+    Validating,
+    Approved,
+    Denied,
+    /// Validation found the diagnosis covered but not the procedure; the insurer decides
+    /// with `decide_prior_auth_review`, optionally after escalating for disputed fields
+    NeedsReview,
+}
+
+/// A provider's request that an insurer authorize a procedure for a patient in advance.
+///
+/// The diagnosis and procedure codes are encrypted `u64` values of the code sets the
+/// parties agreed on, so only the insurer's MPC check sees them. The decision is written
+/// back to this account, which both the provider and the insurer read.
+#[account]
+#[derive(InitSpace)]
+pub struct PriorAuthRequest {
+    /// Encrypted diagnosis code
+    pub diagnosis: [u8; 32],
+    /// Encrypted procedure code
+    pub procedure: [u8; 32],
+    /// Provider that filed the request
+    pub provider: Pubkey,
+    /// Insurer asked to authorize it
+    pub insurer: Pubkey,
+    /// Record of the patient the procedure is for
+    pub patient_data: Pubkey,
+    pub request_id: u64,
+    /// x25519 key the codes were encrypted with
+    pub enc_pubkey: [u8; 32],
+    /// Nonce the codes were encrypted with
+    pub nonce: u128,
+    pub status: PriorAuthStatus,
+    /// Offset of the latest validation, zero before the first
+    pub computation_offset: u64,
+    /// Unix timestamp the latest validation was queued at, which its expiry counts from
+    pub validating_since: i64,
+    /// Unix timestamp of the decision, zero while undecided
+    pub decided_at: i64,
+    pub bump: u8,
+}
+
+#[event]
+pub struct PriorAuthRequestedEvent {
+    pub prior_auth: Pubkey,
+    pub provider: Pubkey,
+    pub insurer: Pubkey,
+    pub patient_data: Pubkey,
+    pub request_id: u64,
+}
+
+#[event]
+pub struct PriorAuthDecidedEvent {
+    pub prior_auth: Pubkey,
+    pub provider: Pubkey,
+    pub insurer: Pubkey,
+    pub approved: bool,
+    pub decided_at: i64,
+}
+
+/// Validation could not decide the request; insurers subscribe by `insurer`.
+#[event]
+pub struct PriorAuthNeedsReviewEvent {
+    pub prior_auth: Pubkey,
+    pub provider: Pubkey,
+    pub insurer: Pubkey,
+    pub patient_data: Pubkey,
+}
+
+/// The validation aborted; the request is pending again for the insurer to retry.
+#[event]
+pub struct PriorAuthValidationAbortedEvent {
+    pub prior_auth: Pubkey,
+    pub computation_offset: u64,
+}
+
+/// The validation was never called back; the request is pending again for the insurer.
+#[event]
+pub struct PriorAuthValidationExpiredEvent {
+    pub prior_auth: Pubkey,
+    pub insurer: Pubkey,
+    pub computation_offset: u64,
+}
+
+#[derive(Accounts)]
+#[instruction(request_id: u64)]
+pub struct RequestPriorAuth<'info> {
+    #[account(mut)]
+    pub provider: Signer<'info>,
+    #[account(
+        seeds = [PROVIDER_SEED, provider.key().as_ref()],
+        bump = provider_profile.bump,
+        constraint = provider_profile.active
+            && provider_profile.role != ProviderRole::Insurer
+            @ ErrorCode::Unauthorized,
+    )]
+    pub provider_profile: Account<'info, ProviderProfile>,
+    #[account(
+        seeds = [PROVIDER_SEED, insurer_profile.provider.as_ref()],
+        bump = insurer_profile.bump,
+        constraint = insurer_profile.active
+            && insurer_profile.role == ProviderRole::Insurer
+            @ ErrorCode::NotAnInsurer,
+    )]
+    pub insurer_profile: Account<'info, ProviderProfile>,
+    pub patient_data: Account<'info, PatientData>,
+    #[account(
+        init,
+        payer = provider,
+        space = 8 + PriorAuthRequest::INIT_SPACE,
+        seeds = [PRIOR_AUTH_SEED, provider.key().as_ref(), &request_id.to_le_bytes()],
+        bump,
+    )]
+    pub prior_auth: Account<'info, PriorAuthRequest>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DecidePriorAuthReview<'info> {
+    pub insurer: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [
+            PRIOR_AUTH_SEED,
+            prior_auth.provider.as_ref(),
+            &prior_auth.request_id.to_le_bytes(),
+        ],
+        bump = prior_auth.bump,
+        has_one = insurer @ ErrorCode::Unauthorized,
+    )]
+    pub prior_auth: Account<'info, PriorAuthRequest>,
+}
+
+#[queue_computation_accounts("validate_prior_auth", insurer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct ValidatePriorAuth<'info> {
+    #[account(mut)]
+    pub insurer: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = insurer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account, checked by the arcium program.
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool, checked by the arcium program.
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account, checked by the arcium program.
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_VALIDATE_PRIOR_AUTH)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(
+        mut,
+        address = derive_cluster_pda!(mxe_account)
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Box<Account<'info, ProgramConfig>>,
+    #[account(
+        seeds = [PROVIDER_SEED, insurer.key().as_ref()],
+        bump = insurer_profile.bump,
+        constraint = insurer_profile.active
+            && insurer_profile.role == ProviderRole::Insurer
+            @ ErrorCode::NotAnInsurer,
+    )]
+    pub insurer_profile: Box<Account<'info, ProviderProfile>>,
+    #[account(
+        mut,
+        seeds = [
+            PRIOR_AUTH_SEED,
+            prior_auth.provider.as_ref(),
+            &prior_auth.request_id.to_le_bytes(),
+        ],
+        bump = prior_auth.bump,
+        has_one = insurer @ ErrorCode::Unauthorized,
+    )]
+    pub prior_auth: Box<Account<'info, PriorAuthRequest>>,
+    #[account(
+        init_if_needed,
+        payer = insurer,
+        space = 8 + UsedOffsets::INIT_SPACE,
+        seeds = [USED_OFFSETS_SEED, insurer.key().as_ref()],
+        bump,
+    )]
+    pub used_offsets: Box<Account<'info, UsedOffsets>>,
+    #[account(
+        init,
+        payer = insurer,
+        space = 8 + CallbackGuard::INIT_SPACE,
+        seeds = [CALLBACK_GUARD_SEED, &computation_offset.to_le_bytes()],
+        bump,
+    )]
+    pub callback_guard: Box<Account<'info, CallbackGuard>>,
+}
+
+#[derive(Accounts)]
+pub struct ExpirePriorAuthValidation<'info> {
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    #[account(
+        mut,
+        seeds = [
+            PRIOR_AUTH_SEED,
+            prior_auth.provider.as_ref(),
+            &prior_auth.request_id.to_le_bytes(),
+        ],
+        bump = prior_auth.bump,
+        constraint = prior_auth.status == PriorAuthStatus::Validating
+            @ ErrorCode::PriorAuthNotValidating,
+    )]
+    pub prior_auth: Account<'info, PriorAuthRequest>,
+    #[account(
+        mut,
+        seeds = [CALLBACK_GUARD_SEED, &prior_auth.computation_offset.to_le_bytes()],
+        bump = callback_guard.bump,
+    )]
+    pub callback_guard: Account<'info, CallbackGuard>,
+    #[account(
+        mut,
+        seeds = [USED_OFFSETS_SEED, prior_auth.insurer.as_ref()],
+        bump = used_offsets.bump,
+    )]
+    pub used_offsets: Box<Account<'info, UsedOffsets>>,
+}
+
+#[callback_accounts("validate_prior_auth")]
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ValidatePriorAuthCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_VALIDATE_PRIOR_AUTH)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    #[account(
+        mut,
+        seeds = [CALLBACK_GUARD_SEED, &callback_guard.computation_offset.to_le_bytes()],
+        bump = callback_guard.bump,
+    )]
+    pub callback_guard: Box<Account<'info, CallbackGuard>>,
+    #[account(
+        mut,
+        seeds = [
+            PRIOR_AUTH_SEED,
+            prior_auth.provider.as_ref(),
+            &prior_auth.request_id.to_le_bytes(),
+        ],
+        bump = prior_auth.bump,
+    )]
+    pub prior_auth: Box<Account<'info, PriorAuthRequest>>,
+    #[account(
+        mut,
+        seeds = [USED_OFFSETS_SEED, used_offsets.payer.as_ref()],
+        bump = used_offsets.bump,
+    )]
+    pub used_offsets: Box<Account<'info, UsedOffsets>>,
+}
+
+#[init_computation_definition_accounts("validate_prior_auth", payer)]
+#[derive(Accounts)]
+pub struct InitValidatePriorAuthCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut)]
+    /// CHECK: comp_def_account, checked by arcium program.
+    /// Can't check it here as it's not initialized yet.
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+        constraint = program_config.admin == payer.key() @ ErrorCode::Unauthorized,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
@@ -0,0 +1,226 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hash;
+
+use crate::ErrorCode;
+use crate::*;
+
+pub const PROVIDER_SEED: &[u8] = b"provider";
+pub const MAX_REGISTRARS: usize = 4;
+/// Long enough for did:sol and did:key identifiers of ed25519 keys.
+pub const MAX_DID_LEN: usize = 128;
+/// Prefix of every DID challenge, so the signature can't be replayed elsewhere.
+pub const DID_CHALLENGE_DOMAIN: &[u8] = b"share_medical_records:did_challenge:v1";
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub enum ProviderRole {
+    Physician,
+    Nurse,
+    Pharmacist,
+    Laboratory,
+    Researcher,
+    ImagingCenter,
+    /// Payer that decides prior-authorization requests
+    Insurer,
+    /// Drug manufacturer that receives post-market surveillance counts for its drugs and
+    /// funds clinical trial payouts
+    PharmaSponsor,
+    /// Blood bank that searches the donor registry for compatible donors
+    BloodBank,
+    /// Transplant center coordinator who ranks waitlist candidates
+    TransplantCoordinator,
+}
+
+/// A healthcare provider vetted by one of the configured registrars.
+#[account]
+#[derive(InitSpace)]
+pub struct ProviderProfile {
+    /// Wallet the provider signs and receives shares with
+    pub provider: Pubkey,
+    pub role: ProviderRole,
+    /// Registrar that last registered or updated the provider
+    pub registrar: Pubkey,
+    /// Cleared on revocation; the profile is kept so past activity stays attributable
+    pub active: bool,
+    /// Unix timestamp of the most recent registration
+    pub registered_at: i64,
+    /// did:sol or did:key identifier the provider claims, empty if none
+    #[max_len(MAX_DID_LEN)]
+    pub did: String,
+    /// SHA-256 of the ed25519 public key of the DID's verification method
+    pub did_verification_method_hash: [u8; 32],
+    /// Set once the provider has signed a challenge with the verification method's key
+    pub did_verified: bool,
+    /// SNS name account the provider proved ownership of, the default key if none
+    pub sns_name_account: Pubkey,
+    pub bump: u8,
+    /// x25519 key the registrar attested the provider decrypts shares with, all zeros if
+    /// none; consented shares to the provider must be encrypted for it
+    pub enc_pubkey: [u8; 32],
+    /// Rotations of `enc_pubkey` so far; a share the patient approved or staged under an
+    /// earlier epoch can no longer be queued by the provider
+    pub key_epoch: u32,
+}
+
+/// Bytes a DID verification method signs to prove control of a provider profile.
+pub fn did_challenge(provider: &Pubkey, did: &str, expires_at: i64) -> Vec<u8> {
+    let mut message = Vec::with_capacity(DID_CHALLENGE_DOMAIN.len() + 32 + did.len() + 8);
+    message.extend_from_slice(DID_CHALLENGE_DOMAIN);
+    message.extend_from_slice(provider.as_ref());
+    message.extend_from_slice(did.as_bytes());
+    message.extend_from_slice(&expires_at.to_le_bytes());
+    message
+}
+
+impl ProviderProfile {
+    /// Returns whether `verification_key` is the key whose hash the profile records.
+    pub fn matches_verification_method(&self, verification_key: &[u8; 32]) -> bool {
+        hash(verification_key).to_bytes() == self.did_verification_method_hash
+    }
+
+    /// Reads the profile of `provider` stored at `info`, `None` if it was never created.
+    ///
+    /// `info` is checked here rather than by a seeds constraint so shares by the patient
+    /// skip the derivation; a stored profile is checked against the bump it records.
+    fn load(info: &AccountInfo, provider: &Pubkey) -> Result<Option<ProviderProfile>> {
+        if info.data_is_empty() {
+            let (address, _) =
+                Pubkey::find_program_address(&[PROVIDER_SEED, provider.as_ref()], &crate::ID);
+            require_keys_eq!(info.key(), address, ErrorCode::ReceiverKeyNotAttested);
+            return Ok(None);
+        }
+        require_keys_eq!(*info.owner, crate::ID, ErrorCode::ReceiverKeyNotAttested);
+        let profile = ProviderProfile::try_deserialize(&mut &info.try_borrow_data()?[..])?;
+        let address = Pubkey::create_program_address(
+            &[PROVIDER_SEED, provider.as_ref(), &[profile.bump]],
+            &crate::ID,
+        )
+        .map_err(|_| error!(ErrorCode::ReceiverKeyNotAttested))?;
+        require_keys_eq!(info.key(), address, ErrorCode::ReceiverKeyNotAttested);
+        Ok(Some(profile))
+    }
+
+    /// Fails with `ReceiverKeyNotAttested` if the profile of `provider` stored at `info`, if
+    /// any, does not attest `receiver` as the provider's key.
+    ///
+    /// Keeps a consent a patient gave a provider from being used to re-encrypt the record
+    /// for some other key, e.g. by a compromised wallet. Payers without a profile are not
+    /// providers and are held to their consent alone.
+    pub fn require_attested_receiver(
+        info: &AccountInfo,
+        provider: &Pubkey,
+        receiver: &[u8; 32],
+    ) -> Result<()> {
+        if let Some(profile) = Self::load(info, provider)? {
+            require!(
+                profile.enc_pubkey == *receiver,
+                ErrorCode::ReceiverKeyNotAttested
+            );
+        }
+        Ok(())
+    }
+
+    /// Returns the key epoch of the profile of `provider` stored at `info`, 0 for a payer
+    /// without a profile.
+    pub fn key_epoch_of(info: &AccountInfo, provider: &Pubkey) -> Result<u32> {
+        Ok(Self::load(info, provider)?.map_or(0, |profile| profile.key_epoch))
+    }
+}
+
+#[event]
+pub struct ProviderRegisteredEvent {
+    pub provider: Pubkey,
+    pub role: ProviderRole,
+    pub registrar: Pubkey,
+}
+
+#[event]
+pub struct ProviderRevokedEvent {
+    pub provider: Pubkey,
+    pub registrar: Pubkey,
+}
+
+#[event]
+pub struct ProviderRoleUpdatedEvent {
+    pub provider: Pubkey,
+    pub previous_role: ProviderRole,
+    pub role: ProviderRole,
+    pub registrar: Pubkey,
+}
+
+#[event]
+pub struct ProviderEncKeyAttestedEvent {
+    pub provider: Pubkey,
+    pub enc_pubkey: [u8; 32],
+    pub registrar: Pubkey,
+}
+
+#[event]
+pub struct ProviderEncKeyRotatedEvent {
+    pub provider: Pubkey,
+    /// Key shares may no longer be encrypted for
+    pub previous_enc_pubkey: [u8; 32],
+    pub enc_pubkey: [u8; 32],
+    pub key_epoch: u32,
+    pub registrar: Pubkey,
+}
+
+#[event]
+pub struct ProviderDidBoundEvent {
+    pub provider: Pubkey,
+    pub did: String,
+    pub verified: bool,
+}
+
+#[derive(Accounts)]
+#[instruction(provider: Pubkey)]
+pub struct RegisterProvider<'info> {
+    #[account(mut)]
+    pub registrar: Signer<'info>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+        constraint = program_config.is_registrar(&registrar.key()) @ ErrorCode::Unauthorized,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    #[account(
+        init_if_needed,
+        payer = registrar,
+        space = 8 + ProviderProfile::INIT_SPACE,
+        seeds = [PROVIDER_SEED, provider.as_ref()],
+        bump,
+    )]
+    pub provider_profile: Account<'info, ProviderProfile>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateProvider<'info> {
+    pub registrar: Signer<'info>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+        constraint = program_config.is_registrar(&registrar.key()) @ ErrorCode::Unauthorized,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    #[account(
+        mut,
+        seeds = [PROVIDER_SEED, provider_profile.provider.as_ref()],
+        bump = provider_profile.bump,
+    )]
+    pub provider_profile: Account<'info, ProviderProfile>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateProviderDid<'info> {
+    pub provider: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [PROVIDER_SEED, provider.key().as_ref()],
+        bump = provider_profile.bump,
+        has_one = provider @ ErrorCode::Unauthorized,
+    )]
+    pub provider_profile: Account<'info, ProviderProfile>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+}
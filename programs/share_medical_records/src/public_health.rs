@@ -0,0 +1,761 @@
+use anchor_lang::prelude::*;
+use arcium_anchor::prelude::*;
+
+use crate::ErrorCode;
+use crate::*;
+
+pub const PUBLIC_HEALTH_AUTHORITY_SEED: &[u8] = b"public_health_authority";
+pub const CASE_REPORT_SEED: &[u8] = b"case_report";
+/// Diseases one authority counts cases of, fixed by the case reporting circuits.
+pub const NOTIFIABLE_DISEASES: usize = 8;
+/// Length of the ciphertexts at the start of `PublicHealthAuthority`, the part circuits read.
+pub const CASE_COUNTS_CIPHERTEXTS_LEN: u32 = NOTIFIABLE_DISEASES as u32 * 32;
+/// Length of the ciphertexts at the start of `CaseReport`, the part circuits read.
+pub const SEALED_CASE_CIPHERTEXTS_LEN: u32 = 2 * 32;
+
+/// A regional public-health authority notifiable disease cases are reported to,
+/// registered by the admin.
+///
+/// Holds the region's case counts per disease, encrypted to the cluster; the authority
+/// reads them through `case_counts`, never the cases behind them. Cases are counted one
+/// computation at a time, through `status`.
+#[account]
+#[derive(InitSpace)]
+pub struct PublicHealthAuthority {
+    /// Case counts per disease, encrypted to the cluster
+    pub counts: [[u8; 32]; NOTIFIABLE_DISEASES],
+    /// Nonce of `counts`
+    pub counts_nonce: u128,
+    /// Region the authority covers, part of its address
+    pub region: u32,
+    /// Wallet operating the authority
+    pub authority: Pubkey,
+    /// x25519 key counts and revealed cases are encrypted for
+    pub enc_pubkey: [u8; 32],
+    /// Wallet, typically a multisig, whose signature reveals a case's identity
+    pub legal_committee: Pubkey,
+    /// Diagnosis code of each notifiable disease, zero for unused slots
+    pub disease_codes: [u64; NOTIFIABLE_DISEASES],
+    pub status: TallyStatus,
+    /// Cases reported so far, whether or not they named a notifiable disease
+    pub cases: u64,
+    pub bump: u8,
+}
+
+/// A case a provider reported to a public-health authority.
+///
+/// The diagnosis and patient identity are sealed to the cluster once counted, so neither
+/// the authority nor anyone else reads them; only a `reveal_case` signed by the
+/// authority's legal committee re-encrypts them for the authority.
+#[account]
+#[derive(InitSpace)]
+pub struct CaseReport {
+    /// Diagnosis code and patient identifier, encrypted to the cluster
+    pub sealed: [[u8; 32]; 2],
+    /// Nonce of `sealed`
+    pub sealed_nonce: u128,
+    pub public_health_authority: Pubkey,
+    /// Provider that reported the case
+    pub provider: Pubkey,
+    /// Provider chosen identifier, part of the case address
+    pub case_id: u64,
+    pub reported_at: i64,
+    /// Set once the callback counted the case and sealed it
+    pub counted: bool,
+    /// Unix timestamp of the latest reveal, zero if never revealed
+    pub revealed_at: i64,
+    pub bump: u8,
+}
+
+#[event]
+pub struct PublicHealthAuthorityRegisteredEvent {
+    pub public_health_authority: Pubkey,
+    pub region: u32,
+    pub authority: Pubkey,
+    pub legal_committee: Pubkey,
+    pub disease_codes: [u64; NOTIFIABLE_DISEASES],
+    /// Set when the computation aborted; the authority stays `Initializing` and is not
+    /// usable
+    pub aborted: bool,
+}
+
+#[event]
+pub struct CaseNotifiedEvent {
+    pub case_report: Pubkey,
+    pub public_health_authority: Pubkey,
+    pub provider: Pubkey,
+    /// Set when the computation aborted; the case was not counted
+    pub aborted: bool,
+}
+
+/// Region-level case counts, encrypted for the authority.
+#[event]
+pub struct CaseCountsEvent {
+    pub public_health_authority: Pubkey,
+    /// x25519 key the output is encrypted for
+    pub receiver_enc_key: [u8; 32],
+    pub nonce: [u8; 16],
+    /// Counts in `disease_codes` order
+    pub counts: [[u8; 32]; NOTIFIABLE_DISEASES],
+    /// Set when the computation aborted; the other fields are zeros
+    pub aborted: bool,
+}
+
+/// A case's diagnosis and patient identifier, re-encrypted for the authority on the
+/// legal committee's order.
+#[event]
+pub struct CaseRevealedEvent {
+    pub case_report: Pubkey,
+    pub public_health_authority: Pubkey,
+    pub legal_committee: Pubkey,
+    /// x25519 key the output is encrypted for
+    pub receiver_enc_key: [u8; 32],
+    pub nonce: [u8; 16],
+    /// Diagnosis code, then patient identifier
+    pub ciphertexts: [[u8; 32]; 2],
+    /// Set when the computation aborted; the other fields are zeros
+    pub aborted: bool,
+}
+
+#[queue_computation_accounts("open_case_counts", admin)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64, region: u32)]
+pub struct RegisterPublicHealthAuthority<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = admin,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account, checked by the arcium program.
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool, checked by the arcium program.
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account, checked by the arcium program.
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_OPEN_CASE_COUNTS)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(
+        mut,
+        address = derive_cluster_pda!(mxe_account)
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+        has_one = admin @ ErrorCode::Unauthorized,
+    )]
+    pub program_config: Box<Account<'info, ProgramConfig>>,
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + PublicHealthAuthority::INIT_SPACE,
+        seeds = [PUBLIC_HEALTH_AUTHORITY_SEED, &region.to_le_bytes()],
+        bump,
+    )]
+    pub public_health_authority: Box<Account<'info, PublicHealthAuthority>>,
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = 8 + UsedOffsets::INIT_SPACE,
+        seeds = [USED_OFFSETS_SEED, admin.key().as_ref()],
+        bump,
+    )]
+    pub used_offsets: Box<Account<'info, UsedOffsets>>,
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + CallbackGuard::INIT_SPACE,
+        seeds = [CALLBACK_GUARD_SEED, &computation_offset.to_le_bytes()],
+        bump,
+    )]
+    pub callback_guard: Box<Account<'info, CallbackGuard>>,
+}
+
+#[callback_accounts("open_case_counts")]
+#[derive(Accounts)]
+pub struct OpenCaseCountsCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_OPEN_CASE_COUNTS)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    #[account(
+        mut,
+        seeds = [CALLBACK_GUARD_SEED, &callback_guard.computation_offset.to_le_bytes()],
+        bump = callback_guard.bump,
+    )]
+    pub callback_guard: Box<Account<'info, CallbackGuard>>,
+    #[account(
+        mut,
+        seeds = [PUBLIC_HEALTH_AUTHORITY_SEED, &public_health_authority.region.to_le_bytes()],
+        bump = public_health_authority.bump,
+    )]
+    pub public_health_authority: Box<Account<'info, PublicHealthAuthority>>,
+    #[account(
+        mut,
+        seeds = [USED_OFFSETS_SEED, used_offsets.payer.as_ref()],
+        bump = used_offsets.bump,
+    )]
+    pub used_offsets: Box<Account<'info, UsedOffsets>>,
+}
+
+#[queue_computation_accounts("notify_case", provider)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64, case_id: u64)]
+pub struct NotifyCase<'info> {
+    #[account(mut)]
+    pub provider: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = provider,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account, checked by the arcium program.
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool, checked by the arcium program.
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account, checked by the arcium program.
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_NOTIFY_CASE)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(
+        mut,
+        address = derive_cluster_pda!(mxe_account)
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Box<Account<'info, ProgramConfig>>,
+    #[account(
+        seeds = [PROVIDER_SEED, provider.key().as_ref()],
+        bump = provider_profile.bump,
+        constraint = provider_profile.active @ ErrorCode::ProviderNotRegistered,
+    )]
+    pub provider_profile: Box<Account<'info, ProviderProfile>>,
+    #[account(
+        mut,
+        seeds = [PUBLIC_HEALTH_AUTHORITY_SEED, &public_health_authority.region.to_le_bytes()],
+        bump = public_health_authority.bump,
+    )]
+    pub public_health_authority: Box<Account<'info, PublicHealthAuthority>>,
+    #[account(
+        init,
+        payer = provider,
+        space = 8 + CaseReport::INIT_SPACE,
+        seeds = [CASE_REPORT_SEED, provider.key().as_ref(), &case_id.to_le_bytes()],
+        bump,
+    )]
+    pub case_report: Box<Account<'info, CaseReport>>,
+    #[account(
+        init_if_needed,
+        payer = provider,
+        space = 8 + UsedOffsets::INIT_SPACE,
+        seeds = [USED_OFFSETS_SEED, provider.key().as_ref()],
+        bump,
+    )]
+    pub used_offsets: Box<Account<'info, UsedOffsets>>,
+    #[account(
+        init,
+        payer = provider,
+        space = 8 + CallbackGuard::INIT_SPACE,
+        seeds = [CALLBACK_GUARD_SEED, &computation_offset.to_le_bytes()],
+        bump,
+    )]
+    pub callback_guard: Box<Account<'info, CallbackGuard>>,
+}
+
+#[callback_accounts("notify_case")]
+#[derive(Accounts)]
+pub struct NotifyCaseCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_NOTIFY_CASE)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    #[account(
+        mut,
+        seeds = [CALLBACK_GUARD_SEED, &callback_guard.computation_offset.to_le_bytes()],
+        bump = callback_guard.bump,
+    )]
+    pub callback_guard: Box<Account<'info, CallbackGuard>>,
+    #[account(
+        mut,
+        seeds = [PUBLIC_HEALTH_AUTHORITY_SEED, &public_health_authority.region.to_le_bytes()],
+        bump = public_health_authority.bump,
+    )]
+    pub public_health_authority: Box<Account<'info, PublicHealthAuthority>>,
+    #[account(
+        mut,
+        seeds = [
+            CASE_REPORT_SEED,
+            case_report.provider.as_ref(),
+            &case_report.case_id.to_le_bytes(),
+        ],
+        bump = case_report.bump,
+    )]
+    pub case_report: Box<Account<'info, CaseReport>>,
+    #[account(
+        mut,
+        seeds = [USED_OFFSETS_SEED, used_offsets.payer.as_ref()],
+        bump = used_offsets.bump,
+    )]
+    pub used_offsets: Box<Account<'info, UsedOffsets>>,
+}
+
+#[queue_computation_accounts("case_counts", authority)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct CaseCounts<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = authority,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account, checked by the arcium program.
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool, checked by the arcium program.
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account, checked by the arcium program.
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_CASE_COUNTS)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(
+        mut,
+        address = derive_cluster_pda!(mxe_account)
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Box<Account<'info, ProgramConfig>>,
+    #[account(
+        mut,
+        seeds = [PUBLIC_HEALTH_AUTHORITY_SEED, &public_health_authority.region.to_le_bytes()],
+        bump = public_health_authority.bump,
+        has_one = authority @ ErrorCode::Unauthorized,
+    )]
+    pub public_health_authority: Box<Account<'info, PublicHealthAuthority>>,
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + UsedOffsets::INIT_SPACE,
+        seeds = [USED_OFFSETS_SEED, authority.key().as_ref()],
+        bump,
+    )]
+    pub used_offsets: Box<Account<'info, UsedOffsets>>,
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + CallbackGuard::INIT_SPACE,
+        seeds = [CALLBACK_GUARD_SEED, &computation_offset.to_le_bytes()],
+        bump,
+    )]
+    pub callback_guard: Box<Account<'info, CallbackGuard>>,
+}
+
+#[callback_accounts("case_counts")]
+#[derive(Accounts)]
+pub struct CaseCountsCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_CASE_COUNTS)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    #[account(
+        mut,
+        seeds = [CALLBACK_GUARD_SEED, &callback_guard.computation_offset.to_le_bytes()],
+        bump = callback_guard.bump,
+    )]
+    pub callback_guard: Box<Account<'info, CallbackGuard>>,
+    #[account(
+        mut,
+        seeds = [PUBLIC_HEALTH_AUTHORITY_SEED, &public_health_authority.region.to_le_bytes()],
+        bump = public_health_authority.bump,
+    )]
+    pub public_health_authority: Box<Account<'info, PublicHealthAuthority>>,
+    #[account(
+        mut,
+        seeds = [USED_OFFSETS_SEED, used_offsets.payer.as_ref()],
+        bump = used_offsets.bump,
+    )]
+    pub used_offsets: Box<Account<'info, UsedOffsets>>,
+}
+
+#[queue_computation_accounts("reveal_case", legal_committee)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct RevealCase<'info> {
+    #[account(mut)]
+    pub legal_committee: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = legal_committee,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account, checked by the arcium program.
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool, checked by the arcium program.
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account, checked by the arcium program.
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_REVEAL_CASE)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(
+        mut,
+        address = derive_cluster_pda!(mxe_account)
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Box<Account<'info, ProgramConfig>>,
+    #[account(
+        seeds = [PUBLIC_HEALTH_AUTHORITY_SEED, &public_health_authority.region.to_le_bytes()],
+        bump = public_health_authority.bump,
+        has_one = legal_committee @ ErrorCode::Unauthorized,
+    )]
+    pub public_health_authority: Box<Account<'info, PublicHealthAuthority>>,
+    #[account(
+        seeds = [
+            CASE_REPORT_SEED,
+            case_report.provider.as_ref(),
+            &case_report.case_id.to_le_bytes(),
+        ],
+        bump = case_report.bump,
+        has_one = public_health_authority @ ErrorCode::Unauthorized,
+    )]
+    pub case_report: Box<Account<'info, CaseReport>>,
+    #[account(
+        init_if_needed,
+        payer = legal_committee,
+        space = 8 + UsedOffsets::INIT_SPACE,
+        seeds = [USED_OFFSETS_SEED, legal_committee.key().as_ref()],
+        bump,
+    )]
+    pub used_offsets: Box<Account<'info, UsedOffsets>>,
+    #[account(
+        init,
+        payer = legal_committee,
+        space = 8 + CallbackGuard::INIT_SPACE,
+        seeds = [CALLBACK_GUARD_SEED, &computation_offset.to_le_bytes()],
+        bump,
+    )]
+    pub callback_guard: Box<Account<'info, CallbackGuard>>,
+}
+
+#[callback_accounts("reveal_case")]
+#[derive(Accounts)]
+pub struct RevealCaseCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_REVEAL_CASE)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    #[account(
+        mut,
+        seeds = [CALLBACK_GUARD_SEED, &callback_guard.computation_offset.to_le_bytes()],
+        bump = callback_guard.bump,
+    )]
+    pub callback_guard: Box<Account<'info, CallbackGuard>>,
+    #[account(
+        seeds = [PUBLIC_HEALTH_AUTHORITY_SEED, &public_health_authority.region.to_le_bytes()],
+        bump = public_health_authority.bump,
+    )]
+    pub public_health_authority: Box<Account<'info, PublicHealthAuthority>>,
+    #[account(
+        mut,
+        seeds = [
+            CASE_REPORT_SEED,
+            case_report.provider.as_ref(),
+            &case_report.case_id.to_le_bytes(),
+        ],
+        bump = case_report.bump,
+    )]
+    pub case_report: Box<Account<'info, CaseReport>>,
+    #[account(
+        mut,
+        seeds = [USED_OFFSETS_SEED, used_offsets.payer.as_ref()],
+        bump = used_offsets.bump,
+    )]
+    pub used_offsets: Box<Account<'info, UsedOffsets>>,
+}
+
+#[init_computation_definition_accounts("open_case_counts", payer)]
+#[derive(Accounts)]
+pub struct InitOpenCaseCountsCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut)]
+    /// CHECK: comp_def_account, checked by arcium program.
+    /// Can't check it here as it's not initialized yet.
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+        constraint = program_config.admin == payer.key() @ ErrorCode::Unauthorized,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[init_computation_definition_accounts("notify_case", payer)]
+#[derive(Accounts)]
+pub struct InitNotifyCaseCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut)]
+    /// CHECK: comp_def_account, checked by arcium program.
+    /// Can't check it here as it's not initialized yet.
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+        constraint = program_config.admin == payer.key() @ ErrorCode::Unauthorized,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[init_computation_definition_accounts("case_counts", payer)]
+#[derive(Accounts)]
+pub struct InitCaseCountsCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut)]
+    /// CHECK: comp_def_account, checked by arcium program.
+    /// Can't check it here as it's not initialized yet.
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+        constraint = program_config.admin == payer.key() @ ErrorCode::Unauthorized,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[init_computation_definition_accounts("reveal_case", payer)]
+#[derive(Accounts)]
+pub struct InitRevealCaseCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut)]
+    /// CHECK: comp_def_account, checked by arcium program.
+    /// Can't check it here as it's not initialized yet.
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+        constraint = program_config.admin == payer.key() @ ErrorCode::Unauthorized,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
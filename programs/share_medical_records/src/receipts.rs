@@ -0,0 +1,80 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hashv;
+
+use crate::*;
+
+pub const SHARE_RECEIPT_SEED: &[u8] = b"share_receipt";
+
+/// Evidence that a share was delivered: who the record was disclosed to, under which
+/// purpose, which fields and at which slot, with a hash binding the exact output.
+///
+/// Opened with the share and sealed by its callback; receipts of shares that abort or
+/// time out are closed again. Sealed receipts are never closed, so they stay small enough
+/// to keep for as long as the disclosure may need proving.
+#[account]
+#[derive(InitSpace)]
+pub struct ShareReceipt {
+    /// Record that was shared
+    pub patient_data: Pubkey,
+    /// x25519 key the record was re-encrypted for
+    pub receiver: [u8; 32],
+    /// `CONSENT_SCOPE_*` purpose the share was made under
+    pub purpose: u16,
+    /// `PatientField` bits of the fields disclosed
+    pub fields: u16,
+    pub computation_offset: u64,
+    /// Slot the output was delivered at; zero until the callback seals the receipt
+    pub slot: u64,
+    /// `share_output_hash` of the delivered output
+    pub output_hash: [u8; 32],
+    pub bump: u8,
+}
+
+impl ShareReceipt {
+    /// Opens the receipt of a share just queued, to be sealed by its callback.
+    pub fn open(
+        &mut self,
+        patient_data: Pubkey,
+        receiver: [u8; 32],
+        purpose: u16,
+        fields: u16,
+        computation_offset: u64,
+        bump: u8,
+    ) {
+        self.patient_data = patient_data;
+        self.receiver = receiver;
+        self.purpose = purpose;
+        self.fields = fields;
+        self.computation_offset = computation_offset;
+        self.slot = 0;
+        self.output_hash = [0; 32];
+        self.bump = bump;
+    }
+
+    /// Records the output delivered at `slot`.
+    pub fn seal(&mut self, slot: u64, output_hash: [u8; 32]) {
+        self.slot = slot;
+        self.output_hash = output_hash;
+    }
+}
+
+/// Hash of a share's output, its encryption key, nonce and ciphertexts in record order,
+/// as sealed into its `ShareReceipt`.
+pub fn share_output_hash(
+    encryption_key: &[u8; 32],
+    nonce: &[u8; 16],
+    ciphertexts: &[[u8; 32]],
+) -> [u8; 32] {
+    let mut values: Vec<&[u8]> = Vec::with_capacity(2 + ciphertexts.len());
+    values.push(encryption_key);
+    values.push(nonce);
+    values.extend(ciphertexts.iter().map(|ciphertext| ciphertext.as_slice()));
+    hashv(&values).to_bytes()
+}
+
+impl ReceivedPatientDataEvent {
+    /// Hash of the delivered output, to check against the share's `ShareReceipt`.
+    pub fn output_hash(&self) -> [u8; 32] {
+        share_output_hash(&self.receiver_enc_key, &self.nonce, &self.ciphertexts())
+    }
+}
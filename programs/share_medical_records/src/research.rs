@@ -0,0 +1,670 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{
+    close_account, transfer_checked, CloseAccount, Mint, TokenAccount, TokenInterface,
+    TransferChecked,
+};
+use arcium_anchor::prelude::*;
+
+use crate::ErrorCode;
+use crate::*;
+
+pub const RESEARCH_OPT_IN_SEED: &[u8] = b"research_opt_in";
+pub const RESEARCH_ESCROW_SEED: &[u8] = b"research_escrow";
+pub const RESEARCH_VAULT_SEED: &[u8] = b"research_vault";
+pub const COHORT_SNAPSHOT_SEED: &[u8] = b"cohort_snapshot";
+
+/// Number of records a cohort query runs over, fixed by the `cohort_query` circuit.
+pub const COHORT_SIZE: usize = 4;
+/// Accounts passed per cohort member: record, opt-in and payout token account.
+pub const COHORT_MEMBER_ACCOUNTS: usize = 3;
+
+/// A patient's consent to have their record included in research cohorts.
+#[account]
+#[derive(InitSpace)]
+pub struct ResearchOptIn {
+    /// Wallet that owns the record and receives research payouts
+    pub patient: Pubkey,
+    pub opted_in: bool,
+    /// x25519 public key the record's ciphertexts were encrypted under
+    pub enc_pubkey: [u8; 32],
+    /// Nonce the record's ciphertexts were encrypted with
+    pub nonce: u128,
+    pub bump: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub enum EscrowStatus {
+    /// Funded and waiting for the researcher to run the query
+    Open,
+    /// Query queued, waiting for the callback
+    Pending,
+    /// Paid out to the cohort
+    Settled,
+    /// Returned to the researcher
+    Refunded,
+}
+
+/// Tokens a researcher deposits to pay the patients of one cohort query.
+#[account]
+#[derive(InitSpace)]
+pub struct ResearchEscrow {
+    pub researcher: Pubkey,
+    pub mint: Pubkey,
+    pub query_id: u64,
+    pub amount: u64,
+    pub status: EscrowStatus,
+    pub bump: u8,
+}
+
+/// One record of a cohort as it stood when the query was queued.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, InitSpace)]
+pub struct CohortMember {
+    pub patient_data: Pubkey,
+    /// Opt-in the record was admitted through
+    pub research_opt_in: Pubkey,
+    /// `version` of the record, which changes whenever its ciphertexts are replaced
+    pub record_version: u32,
+    /// `patient_commitment` of the record under the opt-in's key and nonce, the exact
+    /// inputs the circuit read
+    pub record_commitment: [u8; 32],
+}
+
+/// Everything a cohort query ran over, kept so a published result can be audited and
+/// re-run later.
+///
+/// Written when the query is queued and completed by its callback. The members, their
+/// opt-ins and the circuit's source hash pin the computation; re-running `cohort_query`
+/// over records with the same commitments must give the same count.
+#[account]
+#[derive(InitSpace)]
+pub struct CohortSnapshot {
+    pub research_escrow: Pubkey,
+    pub researcher: Pubkey,
+    pub query_id: u64,
+    pub computation_offset: u64,
+    /// Comp def the query was queued against
+    pub comp_def_offset: u32,
+    /// Manifest hash of the circuit source the comp def was built from
+    pub circuit_source_hash: [u8; 32],
+    /// `circuit_version` of the program config when queued
+    pub circuit_version: u32,
+    pub min_age: u8,
+    pub max_age: u8,
+    pub blood_type: u8,
+    /// Members in the order they were passed to the circuit
+    pub members: [CohortMember; COHORT_SIZE],
+    /// Slot the cohort was checked and queued at
+    pub queued_slot: u64,
+    /// Unix timestamp the query was queued at, which its expiry counts from
+    pub queued_at: i64,
+    /// Slot the result was delivered at, zero until then or if the query aborted
+    pub completed_slot: u64,
+    /// x25519 key the count is encrypted for
+    pub result_enc_key: [u8; 32],
+    pub result_nonce: [u8; 16],
+    /// Encrypted count
+    pub result: [u8; 32],
+    pub bump: u8,
+}
+
+#[event]
+pub struct CohortQueryResultEvent {
+    pub research_escrow: Pubkey,
+    /// x25519 key the output is encrypted for
+    pub receiver_enc_key: [u8; 32],
+    /// Slot the result was delivered at
+    pub slot: u64,
+    pub nonce: [u8; 16],
+    pub count: [u8; 32],
+}
+
+#[event]
+pub struct ResearchEscrowSettledEvent {
+    pub research_escrow: Pubkey,
+    pub amount: u64,
+    /// Part of `amount` paid to the treasury
+    pub treasury_amount: u64,
+    pub recipients: u8,
+    pub refunded: bool,
+}
+
+#[derive(Accounts)]
+pub struct SetResearchOptIn<'info> {
+    #[account(mut)]
+    pub patient: Signer<'info>,
+    #[account(
+        seeds = [b"patient_data", patient.key().as_ref()],
+        bump,
+    )]
+    pub patient_data: Account<'info, PatientData>,
+    #[account(
+        init_if_needed,
+        payer = patient,
+        space = 8 + ResearchOptIn::INIT_SPACE,
+        seeds = [RESEARCH_OPT_IN_SEED, patient_data.key().as_ref()],
+        bump,
+    )]
+    pub research_opt_in: Account<'info, ResearchOptIn>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(query_id: u64)]
+pub struct CreateResearchEscrow<'info> {
+    #[account(mut)]
+    pub researcher: Signer<'info>,
+    #[account(
+        init,
+        payer = researcher,
+        space = 8 + ResearchEscrow::INIT_SPACE,
+        seeds = [RESEARCH_ESCROW_SEED, researcher.key().as_ref(), &query_id.to_le_bytes()],
+        bump,
+    )]
+    pub research_escrow: Account<'info, ResearchEscrow>,
+    #[account(
+        init,
+        payer = researcher,
+        token::mint = mint,
+        token::authority = fee_authority,
+        token::token_program = token_program,
+        seeds = [RESEARCH_VAULT_SEED, research_escrow.key().as_ref()],
+        bump,
+    )]
+    pub escrow_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        token::mint = mint,
+        token::authority = researcher,
+    )]
+    pub researcher_token_account: InterfaceAccount<'info, TokenAccount>,
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        seeds = [FEE_AUTHORITY_SEED],
+        bump,
+    )]
+    /// CHECK: fee_authority, PDA owning escrow vaults, checked by the seeds constraint.
+    pub fee_authority: UncheckedAccount<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CancelResearchEscrow<'info> {
+    #[account(mut)]
+    pub researcher: Signer<'info>,
+    #[account(
+        mut,
+        has_one = researcher @ ErrorCode::Unauthorized,
+        has_one = mint,
+        close = researcher,
+    )]
+    pub research_escrow: Account<'info, ResearchEscrow>,
+    #[account(
+        mut,
+        seeds = [RESEARCH_VAULT_SEED, research_escrow.key().as_ref()],
+        bump,
+    )]
+    pub escrow_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        token::mint = mint,
+        token::authority = researcher,
+    )]
+    pub researcher_token_account: InterfaceAccount<'info, TokenAccount>,
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        seeds = [FEE_AUTHORITY_SEED],
+        bump,
+    )]
+    /// CHECK: fee_authority, PDA owning escrow vaults, checked by the seeds constraint.
+    pub fee_authority: UncheckedAccount<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[queue_computation_accounts("cohort_query", researcher)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct CohortQuery<'info> {
+    #[account(mut)]
+    pub researcher: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = researcher,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account, checked by the arcium program.
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool, checked by the arcium program.
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account, checked by the arcium program.
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_COHORT_QUERY)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(
+        mut,
+        address = derive_cluster_pda!(mxe_account)
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Box<Account<'info, ProgramConfig>>,
+    #[account(
+        mut,
+        has_one = researcher @ ErrorCode::Unauthorized,
+        has_one = mint,
+    )]
+    pub research_escrow: Box<Account<'info, ResearchEscrow>>,
+    #[account(
+        seeds = [RESEARCH_VAULT_SEED, research_escrow.key().as_ref()],
+        bump,
+    )]
+    pub escrow_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        token::mint = mint,
+        token::authority = researcher,
+    )]
+    pub researcher_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        seeds = [TREASURY_SEED, mint.key().as_ref()],
+        bump,
+    )]
+    pub treasury: Box<InterfaceAccount<'info, TokenAccount>>,
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        seeds = [FEE_AUTHORITY_SEED],
+        bump,
+    )]
+    /// CHECK: fee_authority, PDA owning escrow vaults, checked by the seeds constraint.
+    pub fee_authority: UncheckedAccount<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    #[account(
+        init_if_needed,
+        payer = researcher,
+        space = 8 + UsedOffsets::INIT_SPACE,
+        seeds = [USED_OFFSETS_SEED, researcher.key().as_ref()],
+        bump,
+    )]
+    pub used_offsets: Box<Account<'info, UsedOffsets>>,
+    #[account(
+        init,
+        payer = researcher,
+        space = 8 + CallbackGuard::INIT_SPACE,
+        seeds = [CALLBACK_GUARD_SEED, &computation_offset.to_le_bytes()],
+        bump,
+    )]
+    pub callback_guard: Box<Account<'info, CallbackGuard>>,
+    #[account(
+        init,
+        payer = researcher,
+        space = 8 + CohortSnapshot::INIT_SPACE,
+        seeds = [COHORT_SNAPSHOT_SEED, research_escrow.key().as_ref()],
+        bump,
+    )]
+    pub cohort_snapshot: Box<Account<'info, CohortSnapshot>>,
+}
+
+#[callback_accounts("cohort_query")]
+#[derive(Accounts)]
+pub struct CohortQueryCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_COHORT_QUERY)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Box<Account<'info, ProgramConfig>>,
+    #[account(
+        mut,
+        seeds = [CALLBACK_GUARD_SEED, &callback_guard.computation_offset.to_le_bytes()],
+        bump = callback_guard.bump,
+    )]
+    pub callback_guard: Box<Account<'info, CallbackGuard>>,
+    #[account(mut)]
+    pub research_escrow: Box<Account<'info, ResearchEscrow>>,
+    #[account(
+        mut,
+        seeds = [RESEARCH_VAULT_SEED, research_escrow.key().as_ref()],
+        bump,
+    )]
+    pub escrow_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(mut)]
+    pub researcher_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED, mint.key().as_ref()],
+        bump,
+    )]
+    pub treasury: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(mut, address = research_escrow.researcher)]
+    /// CHECK: researcher, receives the vault rent back; checked against the escrow.
+    pub researcher: UncheckedAccount<'info>,
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        seeds = [FEE_AUTHORITY_SEED],
+        bump,
+    )]
+    /// CHECK: fee_authority, PDA owning escrow vaults, checked by the seeds constraint.
+    pub fee_authority: UncheckedAccount<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    #[account(
+        mut,
+        seeds = [COHORT_SNAPSHOT_SEED, research_escrow.key().as_ref()],
+        bump = cohort_snapshot.bump,
+    )]
+    pub cohort_snapshot: Box<Account<'info, CohortSnapshot>>,
+    #[account(
+        mut,
+        seeds = [USED_OFFSETS_SEED, used_offsets.payer.as_ref()],
+        bump = used_offsets.bump,
+    )]
+    pub used_offsets: Box<Account<'info, UsedOffsets>>,
+}
+
+#[derive(Accounts)]
+pub struct ExpireResearchEscrow<'info> {
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Box<Account<'info, ProgramConfig>>,
+    #[account(mut, has_one = mint)]
+    pub research_escrow: Box<Account<'info, ResearchEscrow>>,
+    #[account(
+        mut,
+        seeds = [RESEARCH_VAULT_SEED, research_escrow.key().as_ref()],
+        bump,
+    )]
+    pub escrow_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        token::mint = mint,
+        token::authority = research_escrow.researcher,
+    )]
+    pub researcher_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(mut, address = research_escrow.researcher)]
+    /// CHECK: researcher, receives the vault and snapshot rent back; checked against the
+    /// escrow.
+    pub researcher: UncheckedAccount<'info>,
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        seeds = [FEE_AUTHORITY_SEED],
+        bump,
+    )]
+    /// CHECK: fee_authority, PDA owning escrow vaults, checked by the seeds constraint.
+    pub fee_authority: UncheckedAccount<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    #[account(
+        mut,
+        seeds = [CALLBACK_GUARD_SEED, &cohort_snapshot.computation_offset.to_le_bytes()],
+        bump = callback_guard.bump,
+    )]
+    pub callback_guard: Box<Account<'info, CallbackGuard>>,
+    #[account(
+        mut,
+        seeds = [USED_OFFSETS_SEED, research_escrow.researcher.as_ref()],
+        bump = used_offsets.bump,
+    )]
+    pub used_offsets: Box<Account<'info, UsedOffsets>>,
+    #[account(
+        mut,
+        seeds = [COHORT_SNAPSHOT_SEED, research_escrow.key().as_ref()],
+        bump = cohort_snapshot.bump,
+        close = researcher,
+    )]
+    pub cohort_snapshot: Box<Account<'info, CohortSnapshot>>,
+}
+
+#[init_computation_definition_accounts("cohort_query", payer)]
+#[derive(Accounts)]
+pub struct InitCohortQueryCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut)]
+    /// CHECK: comp_def_account, checked by arcium program.
+    /// Can't check it here as it's not initialized yet.
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+        constraint = program_config.admin == payer.key() @ ErrorCode::Unauthorized,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+/// Loads a program account of type `T` passed outside the instruction's declared accounts.
+///
+/// Checks ownership and the discriminator explicitly first, so an account from another
+/// program or of another type fails with `RemainingAccountNotOwned` or
+/// `RemainingAccountWrongType` rather than a generic deserialization error.
+pub fn checked_program_account<'a, 'info, T>(info: &'a AccountInfo<'info>) -> Result<Account<'a, T>>
+where
+    T: AccountSerialize + AccountDeserialize + Owner + Clone + Discriminator,
+{
+    require_keys_eq!(*info.owner, crate::ID, ErrorCode::RemainingAccountNotOwned);
+    require!(
+        info.try_borrow_data()?.starts_with(T::DISCRIMINATOR),
+        ErrorCode::RemainingAccountWrongType
+    );
+    Account::try_from(info)
+}
+
+/// Builds the circuit arguments for the cohort members passed in `remaining_accounts`.
+///
+/// Expects `COHORT_SIZE` groups of (patient record, research opt-in, payout token account).
+/// Every member must be a distinct live record whose patient opted in through the opt-in
+/// derived from it, and its payout account must belong to the patient and use the escrow
+/// mint. Returns the record arguments, the payout accounts to hand to the callback and
+/// each member as recorded in the cohort snapshot.
+pub fn cohort_member_arguments(
+    remaining_accounts: &[AccountInfo],
+    mint: &Pubkey,
+) -> Result<(
+    Vec<Argument>,
+    Vec<CallbackAccount>,
+    [CohortMember; COHORT_SIZE],
+)> {
+    require!(
+        remaining_accounts.len() == COHORT_SIZE * COHORT_MEMBER_ACCOUNTS,
+        ErrorCode::InvalidCohort
+    );
+
+    let mut arguments = Vec::with_capacity(COHORT_SIZE * 3);
+    let mut payout_accounts = Vec::with_capacity(COHORT_SIZE);
+    let mut members = Vec::with_capacity(COHORT_SIZE);
+    let mut snapshot = [CohortMember::default(); COHORT_SIZE];
+    for (i, member) in remaining_accounts
+        .chunks(COHORT_MEMBER_ACCOUNTS)
+        .enumerate()
+    {
+        let (record_info, opt_in_info, payout_info) = (&member[0], &member[1], &member[2]);
+
+        require!(
+            !members.contains(record_info.key),
+            ErrorCode::DuplicateCohortMember
+        );
+        members.push(*record_info.key);
+        let record = checked_program_account::<PatientData>(record_info)?;
+        record.require_live()?;
+
+        let (expected_opt_in, _) = Pubkey::find_program_address(
+            &[RESEARCH_OPT_IN_SEED, record_info.key.as_ref()],
+            &crate::ID,
+        );
+        require_keys_eq!(
+            *opt_in_info.key,
+            expected_opt_in,
+            ErrorCode::CohortOptInMismatch
+        );
+        let opt_in = checked_program_account::<ResearchOptIn>(opt_in_info)?;
+        require_keys_eq!(
+            opt_in.patient,
+            record.patient_authority,
+            ErrorCode::CohortOptInMismatch
+        );
+        require!(opt_in.opted_in, ErrorCode::CohortMemberNotOptedIn);
+
+        let payout = InterfaceAccount::<TokenAccount>::try_from(payout_info)?;
+        require_keys_eq!(
+            payout.owner,
+            opt_in.patient,
+            ErrorCode::CohortPayoutMismatch
+        );
+        require_keys_eq!(payout.mint, *mint, ErrorCode::CohortPayoutMismatch);
+
+        require_ciphertext_region::<PatientData>(record_info)?;
+        arguments.extend(cohort_member_args(
+            opt_in.enc_pubkey,
+            opt_in.nonce,
+            record_info.key(),
+        ));
+        payout_accounts.push(CallbackAccount {
+            pubkey: payout_info.key(),
+            is_writable: true,
+        });
+        snapshot[i] = CohortMember {
+            patient_data: record_info.key(),
+            research_opt_in: opt_in_info.key(),
+            record_version: record.version,
+            record_commitment: patient_commitment(
+                &record.patient_authority,
+                &opt_in.enc_pubkey,
+                opt_in.nonce,
+                &[
+                    record.patient_id,
+                    record.age,
+                    record.gender,
+                    record.blood_type,
+                    record.weight,
+                    record.height,
+                ],
+                &record.allergies,
+            ),
+        };
+    }
+    Ok((arguments, payout_accounts, snapshot))
+}
+
+/// Pays the treasury cut and splits the rest of the escrow evenly across the cohort's
+/// payout accounts, or refunds it all to the researcher when `refund` is set. Any
+/// indivisible remainder goes back to the researcher, and the emptied vault is closed.
+pub fn settle_research_escrow<'info>(
+    accounts: &mut CohortQueryCallback<'info>,
+    payout_accounts: &[AccountInfo<'info>],
+    refund: bool,
+) -> Result<()> {
+    let signer_seeds: &[&[&[u8]]] = &[&[FEE_AUTHORITY_SEED, &[fee_authority_bump()]]];
+    let transfer = |to: AccountInfo<'info>, amount: u64| -> Result<()> {
+        if amount == 0 {
+            return Ok(());
+        }
+        transfer_checked(
+            CpiContext::new_with_signer(
+                accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: accounts.escrow_vault.to_account_info(),
+                    mint: accounts.mint.to_account_info(),
+                    to,
+                    authority: accounts.fee_authority.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            amount,
+            accounts.mint.decimals,
+        )
+    };
+
+    let total = accounts.escrow_vault.amount;
+    let (cohort_amount, treasury_amount) = if refund {
+        (total, 0)
+    } else {
+        split_fee(total, accounts.program_config.treasury_bps)?
+    };
+    transfer(accounts.treasury.to_account_info(), treasury_amount)?;
+
+    let recipients = if refund { 0 } else { payout_accounts.len() };
+    let share = cohort_amount.checked_div(recipients as u64).unwrap_or(0);
+    for payout in payout_accounts.iter().take(recipients) {
+        transfer(payout.clone(), share)?;
+    }
+    let remainder = cohort_amount - share * recipients as u64;
+    transfer(
+        accounts.researcher_token_account.to_account_info(),
+        remainder,
+    )?;
+
+    close_account(CpiContext::new_with_signer(
+        accounts.token_program.to_account_info(),
+        CloseAccount {
+            account: accounts.escrow_vault.to_account_info(),
+            destination: accounts.researcher.to_account_info(),
+            authority: accounts.fee_authority.to_account_info(),
+        },
+        signer_seeds,
+    ))?;
+
+    accounts.research_escrow.status = if refund {
+        EscrowStatus::Refunded
+    } else {
+        EscrowStatus::Settled
+    };
+    emit!(ResearchEscrowSettledEvent {
+        research_escrow: accounts.research_escrow.key(),
+        amount: total,
+        treasury_amount,
+        recipients: recipients as u8,
+        refunded: refund,
+    });
+    Ok(())
+}
@@ -0,0 +1,132 @@
+use anchor_lang::prelude::*;
+
+use crate::ErrorCode;
+use crate::*;
+
+pub const MAX_RETENTION_POLICIES: usize = 4;
+
+/// Kind of on-chain record a retention policy applies to.
+///
+/// Only `PatientData` records are covered. Commitment-mode records (`PatientCommitment`)
+/// and the copies derived from a record, namely emergency packets, caregiver grant
+/// ciphertexts and `SharedRecord`s, are not: no instruction closes them, and they are
+/// neither swept nor removed when the record they came from is closed or tombstoned.
+/// Patients who need a record gone must not create those copies of it.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub enum RecordType {
+    PatientData,
+}
+
+/// How long records of one type must and may be kept.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, InitSpace)]
+pub struct RetentionPolicy {
+    pub record_type: RecordType,
+    /// Slots a record must exist before its owner may close or tombstone it
+    pub min_retention_slots: u64,
+    /// Slots after which anyone may tombstone the record; zero keeps records indefinitely
+    pub max_retention_slots: u64,
+}
+
+impl RetentionPolicy {
+    /// Returns whether a record created at `created_slot` may be removed by its owner.
+    pub fn may_remove(&self, created_slot: u64, slot: u64) -> bool {
+        slot >= created_slot.saturating_add(self.min_retention_slots)
+    }
+
+    /// Returns whether a record created at `created_slot` is past its maximum retention.
+    pub fn is_expired(&self, created_slot: u64, slot: u64) -> bool {
+        self.max_retention_slots > 0
+            && slot >= created_slot.saturating_add(self.max_retention_slots)
+    }
+}
+
+impl PatientData {
+    /// Wipes the ciphertexts, leaving the account behind as a marker of the removed record.
+    pub fn tombstone(&mut self) {
+        self.patient_id = [0; 32];
+        self.age = [0; 32];
+        self.gender = [0; 32];
+        self.blood_type = [0; 32];
+        self.weight = [0; 32];
+        self.height = [0; 32];
+        self.allergies = [[0; 32]; 5];
+        self.tombstoned = true;
+    }
+
+    pub fn require_live(&self) -> Result<()> {
+        require!(!self.tombstoned, ErrorCode::RecordTombstoned);
+        Ok(())
+    }
+}
+
+#[event]
+pub struct RecordClosedEvent {
+    pub record: Pubkey,
+    pub record_type: RecordType,
+}
+
+#[event]
+pub struct RecordTombstonedEvent {
+    pub record: Pubkey,
+    pub record_type: RecordType,
+    /// Set when the record was tombstoned by the sweeper rather than its owner
+    pub swept: bool,
+}
+
+#[derive(Accounts)]
+pub struct ClosePatientData<'info> {
+    #[account(mut)]
+    pub patient: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"patient_data", patient.key().as_ref()],
+        bump,
+        close = patient,
+    )]
+    pub patient_data: Account<'info, PatientData>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    #[account(
+        mut,
+        seeds = [USED_NONCES_SEED, patient_data.key().as_ref()],
+        bump = used_nonces.bump,
+        close = patient,
+    )]
+    pub used_nonces: Box<Account<'info, UsedNonces>>,
+    /// Kept open as the record's audit trail, and marked so the address stays dead
+    #[account(
+        mut,
+        seeds = [PATIENT_REGISTRY_SEED, patient_data.key().as_ref()],
+        bump = patient_registry.bump,
+    )]
+    pub patient_registry: Box<Account<'info, PatientRegistry>>,
+}
+
+#[derive(Accounts)]
+pub struct RemovePatientData<'info> {
+    #[account(mut)]
+    pub patient: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"patient_data", patient.key().as_ref()],
+        bump,
+    )]
+    pub patient_data: Account<'info, PatientData>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[derive(Accounts)]
+pub struct SweepExpiredRecords<'info> {
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
@@ -0,0 +1,44 @@
+use anchor_lang::prelude::*;
+
+use crate::ErrorCode;
+use crate::*;
+
+/// Fails when a record's ciphertexts show a client-side encryption bug, or hold a field
+/// outside `record_fields`, the deployment's `PatientField` bits.
+///
+/// Rescue ciphertexts look uniformly random, so an all-zero block of a collected field is
+/// a field that was never encrypted, and two identical allergy blocks are the same
+/// plaintext encrypted under a reused counter or copied slot by slot. Neither would
+/// decrypt to what the patient meant to store, so they are rejected before anything is
+/// written. Fields the deployment does not collect must be left as zero blocks instead.
+pub fn require_plausible_ciphertexts(
+    fields: &[[u8; 32]; 6],
+    allergies: &[[u8; 32]; 5],
+    record_fields: u16,
+) -> Result<()> {
+    let record = join_record(*fields, *allergies);
+    for field in PatientField::ALL {
+        let blocks = field.of(&record);
+        if record_fields & field.bit() == 0 {
+            require!(
+                blocks.iter().all(|block| *block == [0; 32]),
+                ErrorCode::FieldNotCollected
+            );
+        } else {
+            require!(
+                blocks.iter().all(|block| *block != [0; 32]),
+                ErrorCode::ZeroCiphertext
+            );
+        }
+    }
+    if record_fields & PatientField::Allergies.bit() == 0 {
+        return Ok(());
+    }
+    for (i, allergy) in allergies.iter().enumerate() {
+        require!(
+            !allergies[i + 1..].contains(allergy),
+            ErrorCode::DuplicateAllergyCiphertext
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,122 @@
+use anchor_lang::prelude::*;
+
+use crate::ErrorCode;
+use crate::*;
+
+pub const SCHEMA_DESCRIPTOR_SEED: &[u8] = b"schema_descriptor";
+/// Also bounds field names; record type names are used as a PDA seed.
+pub const MAX_SCHEMA_NAME_LEN: usize = 32;
+pub const MAX_SCHEMA_FIELDS: usize = 16;
+
+/// Type a field decrypts or deserializes to.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub enum SchemaValueType {
+    Bool,
+    U8,
+    U16,
+    U32,
+    U64,
+    U128,
+    Pubkey,
+    Bytes,
+}
+
+/// Where one field of a record type lives in its account and how to decode it.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
+pub struct FieldDescriptor {
+    #[max_len(MAX_SCHEMA_NAME_LEN)]
+    pub name: String,
+    /// Byte offset in the account data, after the 8-byte discriminator
+    pub offset: u32,
+    /// Bytes the field takes; a ciphertext is 32 bytes per encrypted scalar
+    pub len: u32,
+    /// Whether the field is Rescue ciphertext rather than plaintext
+    pub encrypted: bool,
+    pub value_type: SchemaValueType,
+    /// Number of values of `value_type` packed in the field, e.g. 5 for an allergy array
+    pub count: u16,
+    /// `CodeSet` the field's values are drawn from, if it is coded
+    pub code_set: Option<Pubkey>,
+}
+
+/// Published field layout of one record type, so generic clients and indexers can
+/// decode and encode it without recompiling against this program.
+#[account]
+#[derive(InitSpace)]
+pub struct SchemaDescriptor {
+    #[max_len(MAX_SCHEMA_NAME_LEN)]
+    pub record_type: String,
+    /// Bumped on every layout change
+    pub version: u16,
+    /// Length of the ciphertexts at the start of the account, the part circuits read
+    pub ciphertexts_len: u32,
+    #[max_len(MAX_SCHEMA_FIELDS)]
+    pub fields: Vec<FieldDescriptor>,
+    pub bump: u8,
+}
+
+impl SchemaDescriptor {
+    /// Checks field names fit and no two fields overlap.
+    pub fn validate_fields(fields: &[FieldDescriptor]) -> Result<()> {
+        require!(
+            fields.len() <= MAX_SCHEMA_FIELDS,
+            ErrorCode::InvalidSchemaDescriptor
+        );
+        for (i, field) in fields.iter().enumerate() {
+            require!(
+                !field.name.is_empty() && field.name.len() <= MAX_SCHEMA_NAME_LEN && field.len > 0,
+                ErrorCode::InvalidSchemaDescriptor
+            );
+            let end = field
+                .offset
+                .checked_add(field.len)
+                .ok_or(ErrorCode::InvalidSchemaDescriptor)?;
+            for other in &fields[..i] {
+                let disjoint = end <= other.offset || other.offset + other.len <= field.offset;
+                require!(disjoint, ErrorCode::InvalidSchemaDescriptor);
+            }
+        }
+        Ok(())
+    }
+
+    /// Fails with `SchemaMismatch` if `record_type` is an account of this program and
+    /// `ciphertexts_len` is not the length its circuits read.
+    pub fn validate_ciphertexts_len(record_type: &str, ciphertexts_len: u32) -> Result<()> {
+        let expected = match record_type {
+            "PatientData" => PATIENT_DATA_CIPHERTEXTS_LEN,
+            "DicomReference" => DICOM_CIPHERTEXTS_LEN,
+            _ => return Ok(()),
+        };
+        require!(ciphertexts_len == expected, ErrorCode::SchemaMismatch);
+        Ok(())
+    }
+}
+
+#[event]
+pub struct SchemaPublishedEvent {
+    pub schema_descriptor: Pubkey,
+    pub record_type: String,
+    pub version: u16,
+}
+
+#[derive(Accounts)]
+#[instruction(record_type: String)]
+pub struct PublishSchema<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+        has_one = admin @ ErrorCode::Unauthorized,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = 8 + SchemaDescriptor::INIT_SPACE,
+        seeds = [SCHEMA_DESCRIPTOR_SEED, record_type.as_bytes()],
+        bump,
+    )]
+    pub schema_descriptor: Account<'info, SchemaDescriptor>,
+    pub system_program: Program<'info, System>,
+}
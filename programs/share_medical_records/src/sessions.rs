@@ -0,0 +1,131 @@
+use anchor_lang::prelude::*;
+
+use crate::ErrorCode;
+use crate::*;
+
+pub const TELEHEALTH_SESSION_SEED: &[u8] = b"telehealth_session";
+/// Longest a session's consent may last: four hours at the nominal slot rate.
+pub const MAX_SESSION_SLOTS: u64 = 4 * 60 * 60 * 1000 / MS_PER_SLOT;
+
+/// A telehealth session a patient opened with a provider, backing the provider's
+/// `ConsentGrant` for its duration.
+///
+/// The grant lapses at `ends_at_slot` on its own and is revoked when either party closes
+/// the session, so shares under it stop with the call. A grant the patient renews while
+/// the session is open is theirs and is left alone on close.
+#[account]
+#[derive(InitSpace)]
+pub struct TelehealthSession {
+    pub patient_data: Pubkey,
+    /// Wallet that owns the record and opened the session
+    pub patient: Pubkey,
+    /// Wallet the session's consent is granted to
+    pub provider: Pubkey,
+    /// Identifier the telehealth platform assigned the session, e.g. a UUID
+    pub session_id: [u8; 16],
+    /// Bitmask of `CONSENT_SCOPE_*` values granted for the session
+    pub scope: u16,
+    /// Unix timestamp the session was opened at, also its consent's `granted_at`
+    pub opened_at: i64,
+    /// Slot the session's consent lapses at if it is not closed first
+    pub ends_at_slot: u64,
+    /// Unix timestamp the session was closed at, zero while open
+    pub closed_at: i64,
+    pub bump: u8,
+}
+
+#[event]
+pub struct TelehealthSessionOpenedEvent {
+    pub session: Pubkey,
+    pub patient_data: Pubkey,
+    pub provider: Pubkey,
+    pub session_id: [u8; 16],
+    pub consent_grant: Pubkey,
+    pub scope: u16,
+    pub ends_at_slot: u64,
+}
+
+#[event]
+pub struct TelehealthSessionClosedEvent {
+    pub session: Pubkey,
+    pub patient_data: Pubkey,
+    pub provider: Pubkey,
+    /// Patient or provider that closed it
+    pub closed_by: Pubkey,
+    /// Set when the session's consent was still in place and has been revoked
+    pub consent_revoked: bool,
+}
+
+#[derive(Accounts)]
+#[instruction(session_id: [u8; 16], provider: Pubkey)]
+pub struct OpenSession<'info> {
+    #[account(mut)]
+    pub patient: Signer<'info>,
+    #[account(
+        seeds = [b"patient_data", patient.key().as_ref()],
+        bump,
+    )]
+    pub patient_data: Account<'info, PatientData>,
+    #[account(
+        init,
+        payer = patient,
+        space = 8 + TelehealthSession::INIT_SPACE,
+        seeds = [TELEHEALTH_SESSION_SEED, patient_data.key().as_ref(), &session_id],
+        bump,
+    )]
+    pub session: Account<'info, TelehealthSession>,
+    #[account(
+        init_if_needed,
+        payer = patient,
+        space = 8 + ConsentGrant::INIT_SPACE,
+        seeds = [CONSENT_GRANT_SEED, patient_data.key().as_ref(), provider.as_ref()],
+        bump,
+    )]
+    pub consent_grant: Account<'info, ConsentGrant>,
+    #[account(
+        init_if_needed,
+        payer = patient,
+        space = 8 + PatientRegistry::INIT_SPACE,
+        seeds = [PATIENT_REGISTRY_SEED, patient_data.key().as_ref()],
+        bump,
+    )]
+    pub patient_registry: Box<Account<'info, PatientRegistry>>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CloseSession<'info> {
+    /// The session's patient or provider
+    #[account(
+        mut,
+        constraint = closer.key() == session.patient || closer.key() == session.provider
+            @ ErrorCode::Unauthorized,
+    )]
+    pub closer: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [TELEHEALTH_SESSION_SEED, session.patient_data.as_ref(), &session.session_id],
+        bump = session.bump,
+    )]
+    pub session: Account<'info, TelehealthSession>,
+    #[account(
+        mut,
+        seeds = [CONSENT_GRANT_SEED, session.patient_data.as_ref(), session.provider.as_ref()],
+        bump = consent_grant.bump,
+    )]
+    pub consent_grant: Account<'info, ConsentGrant>,
+    #[account(
+        init_if_needed,
+        payer = closer,
+        space = 8 + PatientRegistry::INIT_SPACE,
+        seeds = [PATIENT_REGISTRY_SEED, session.patient_data.as_ref()],
+        bump,
+    )]
+    pub patient_registry: Box<Account<'info, PatientRegistry>>,
+    pub system_program: Program<'info, System>,
+}
@@ -0,0 +1,311 @@
+use anchor_lang::prelude::*;
+use arcium_anchor::prelude::*;
+
+use crate::ErrorCode;
+use crate::*;
+
+pub const SHARE_CODE_SEED: &[u8] = b"share_code";
+/// Prefix of every redemption message, so a code's signature can't be replayed elsewhere.
+pub const SHARE_CODE_DOMAIN: &[u8] = b"share_medical_records:share_code:v1";
+/// Longest a share code stays redeemable: long enough to wait at a front desk, short
+/// enough that a photographed QR code is soon useless.
+pub const MAX_SHARE_CODE_SECONDS: i64 = 60 * 60;
+
+/// Bytes the code's key signs to redeem it for `provider` and their attested
+/// `provider_enc_key`.
+pub fn share_code_redemption_message(provider: &Pubkey, provider_enc_key: &[u8; 32]) -> Vec<u8> {
+    let mut message = Vec::with_capacity(SHARE_CODE_DOMAIN.len() + 32 + 32);
+    message.extend_from_slice(SHARE_CODE_DOMAIN);
+    message.extend_from_slice(provider.as_ref());
+    message.extend_from_slice(provider_enc_key);
+    message
+}
+
+/// A share the patient pre-approves for whoever presents a random code, typically shown
+/// as a QR code at a front desk.
+///
+/// The code is the seed of an ed25519 keypair, and only its public key is stored, as
+/// part of the account's address, so a clinic derives the account from the scanned code
+/// without knowing the patient in advance, and the patient need not know the clinic's
+/// key. The first active provider to sign `share_code_redemption_message` for themselves
+/// with the code's key within the window receives `fields` of the record, encrypted for
+/// their attested key. The code itself never goes on-chain, and a redemption seen in
+/// flight names its provider and key, so it can't be replayed for anyone else.
+#[account]
+#[derive(InitSpace)]
+pub struct OneTimeShareCode {
+    pub patient_data: Pubkey,
+    /// Wallet that owns the record and created the code
+    pub patient: Pubkey,
+    /// ed25519 public key derived from the code, part of the address
+    pub code_key: Pubkey,
+    /// Bitmask of `PatientField` bits shared
+    pub fields: u16,
+    /// Nonce the record was encrypted with, as of `record_version`
+    pub record_nonce: u128,
+    /// `version` of the record `record_nonce` belongs to; an updated record voids the code
+    pub record_version: u32,
+    pub expires_at: i64,
+    /// Slot `expires_at` was estimated to fall in when created
+    pub expires_at_slot: u64,
+    /// Provider who redeemed the code, the default key until then
+    pub redeemed_by: Pubkey,
+    pub redeemed_at: i64,
+    pub bump: u8,
+}
+
+#[event]
+pub struct ShareCodeCreatedEvent {
+    pub share_code: Pubkey,
+    pub patient_data: Pubkey,
+    pub fields: u16,
+    pub expires_at: i64,
+}
+
+#[event]
+pub struct ShareCodeCancelledEvent {
+    pub share_code: Pubkey,
+    pub patient_data: Pubkey,
+}
+
+#[event]
+pub struct ShareCodeRedeemedEvent {
+    pub share_code: Pubkey,
+    pub patient_data: Pubkey,
+    pub provider: Pubkey,
+    pub fields: u16,
+    pub computation_offset: u64,
+}
+
+/// A redeemed code's share finished; providers subscribe by `provider`.
+///
+/// Fields outside the code's mask come back as encryptions of zero.
+#[event]
+pub struct ShareCodeDeliveredEvent {
+    pub share_code: Pubkey,
+    pub provider: Pubkey,
+    /// x25519 key the output is encrypted for
+    pub receiver_enc_key: [u8; 32],
+    pub slot: u64,
+    pub nonce: [u8; 16],
+    pub patient_id: [u8; 32],
+    pub age: [u8; 32],
+    pub gender: [u8; 32],
+    pub blood_type: [u8; 32],
+    pub weight: [u8; 32],
+    pub height: [u8; 32],
+    pub allergies: [[u8; 32]; 5],
+}
+
+#[derive(Accounts)]
+#[instruction(code_key: Pubkey)]
+pub struct CreateShareCode<'info> {
+    #[account(mut)]
+    pub patient: Signer<'info>,
+    #[account(
+        seeds = [b"patient_data", patient.key().as_ref()],
+        bump,
+    )]
+    pub patient_data: Account<'info, PatientData>,
+    #[account(
+        init,
+        payer = patient,
+        space = 8 + OneTimeShareCode::INIT_SPACE,
+        seeds = [SHARE_CODE_SEED, code_key.as_ref()],
+        bump,
+    )]
+    pub share_code: Box<Account<'info, OneTimeShareCode>>,
+    #[account(
+        init_if_needed,
+        payer = patient,
+        space = 8 + PatientRegistry::INIT_SPACE,
+        seeds = [PATIENT_REGISTRY_SEED, patient_data.key().as_ref()],
+        bump,
+    )]
+    pub patient_registry: Box<Account<'info, PatientRegistry>>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CancelShareCode<'info> {
+    #[account(mut)]
+    pub patient: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [SHARE_CODE_SEED, share_code.code_key.as_ref()],
+        bump = share_code.bump,
+        has_one = patient @ ErrorCode::Unauthorized,
+        close = patient,
+    )]
+    pub share_code: Box<Account<'info, OneTimeShareCode>>,
+}
+
+#[queue_computation_accounts("redeem_share_code", provider)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct RedeemShareCode<'info> {
+    #[account(mut)]
+    pub provider: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = provider,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account, checked by the arcium program.
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool, checked by the arcium program.
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account, checked by the arcium program.
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_REDEEM_SHARE_CODE)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(
+        mut,
+        address = derive_cluster_pda!(mxe_account)
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Box<Account<'info, ProgramConfig>>,
+    #[account(
+        seeds = [PROVIDER_SEED, provider.key().as_ref()],
+        bump = provider_profile.bump,
+        constraint = provider_profile.active @ ErrorCode::ProviderNotRegistered,
+    )]
+    pub provider_profile: Box<Account<'info, ProviderProfile>>,
+    #[account(
+        mut,
+        seeds = [SHARE_CODE_SEED, share_code.code_key.as_ref()],
+        bump = share_code.bump,
+    )]
+    pub share_code: Box<Account<'info, OneTimeShareCode>>,
+    #[account(address = share_code.patient_data)]
+    pub patient_data: Box<Account<'info, PatientData>>,
+    #[account(
+        mut,
+        seeds = [PATIENT_REGISTRY_SEED, share_code.patient_data.as_ref()],
+        bump = patient_registry.bump,
+    )]
+    pub patient_registry: Box<Account<'info, PatientRegistry>>,
+    #[account(
+        init_if_needed,
+        payer = provider,
+        space = 8 + UsedOffsets::INIT_SPACE,
+        seeds = [USED_OFFSETS_SEED, provider.key().as_ref()],
+        bump,
+    )]
+    pub used_offsets: Box<Account<'info, UsedOffsets>>,
+    #[account(
+        init,
+        payer = provider,
+        space = 8 + CallbackGuard::INIT_SPACE,
+        seeds = [CALLBACK_GUARD_SEED, &computation_offset.to_le_bytes()],
+        bump,
+    )]
+    pub callback_guard: Box<Account<'info, CallbackGuard>>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+}
+
+#[callback_accounts("redeem_share_code")]
+#[derive(Accounts)]
+pub struct RedeemShareCodeCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_REDEEM_SHARE_CODE)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    #[account(
+        mut,
+        seeds = [CALLBACK_GUARD_SEED, &callback_guard.computation_offset.to_le_bytes()],
+        bump = callback_guard.bump,
+    )]
+    pub callback_guard: Box<Account<'info, CallbackGuard>>,
+    #[account(
+        seeds = [SHARE_CODE_SEED, share_code.code_key.as_ref()],
+        bump = share_code.bump,
+    )]
+    pub share_code: Box<Account<'info, OneTimeShareCode>>,
+    #[account(
+        mut,
+        seeds = [USED_OFFSETS_SEED, used_offsets.payer.as_ref()],
+        bump = used_offsets.bump,
+    )]
+    pub used_offsets: Box<Account<'info, UsedOffsets>>,
+}
+
+#[init_computation_definition_accounts("redeem_share_code", payer)]
+#[derive(Accounts)]
+pub struct InitRedeemShareCodeCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut)]
+    /// CHECK: comp_def_account, checked by arcium program.
+    /// Can't check it here as it's not initialized yet.
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+        constraint = program_config.admin == payer.key() @ ErrorCode::Unauthorized,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
@@ -0,0 +1,73 @@
+use anchor_lang::prelude::*;
+
+use crate::ErrorCode;
+use crate::*;
+
+pub const SHARED_RECORD_SEED: &[u8] = b"shared_record";
+
+/// Latest share of a record to a receiver, written by the share callback while
+/// `ProgramConfig::compact_events` is on.
+///
+/// The eleven ciphertexts of a share make its event long enough for RPC log limits to
+/// truncate it; with compact events the callback writes them here instead and emits
+/// only `ReceivedPatientDataCompactEvent`, whose `output_hash` commits to them. Each
+/// share to the same receiver overwrites the previous one, and a share that aborts
+/// leaves it untouched.
+#[account]
+#[derive(InitSpace)]
+pub struct SharedRecord {
+    /// Record that was shared
+    pub patient_data: Pubkey,
+    /// x25519 key the share was requested for
+    pub receiver: [u8; 32],
+    /// x25519 key the output is encrypted for
+    pub receiver_enc_key: [u8; 32],
+    /// Computation that last delivered; zero until the first delivery
+    pub computation_offset: u64,
+    /// Slot the output was delivered at
+    pub slot: u64,
+    pub nonce: [u8; 16],
+    /// Ciphertexts in record order
+    pub ciphertexts: [[u8; 32]; PATIENT_RECORD_BLOCKS],
+    pub bump: u8,
+}
+
+impl SharedRecord {
+    /// Writes the output of the share queued at `computation_offset`, delivered at `slot`.
+    pub fn deliver(
+        &mut self,
+        computation_offset: u64,
+        slot: u64,
+        receiver_enc_key: [u8; 32],
+        nonce: [u8; 16],
+        ciphertexts: &[[u8; 32]],
+    ) -> Result<()> {
+        self.ciphertexts = ciphertexts
+            .try_into()
+            .map_err(|_| ErrorCode::ComputationOutputLength)?;
+        self.computation_offset = computation_offset;
+        self.slot = slot;
+        self.receiver_enc_key = receiver_enc_key;
+        self.nonce = nonce;
+        Ok(())
+    }
+
+    /// Hash of the delivered output, as committed to by its event and `ShareReceipt`.
+    pub fn output_hash(&self) -> [u8; 32] {
+        share_output_hash(&self.receiver_enc_key, &self.nonce, &self.ciphertexts)
+    }
+}
+
+/// Delivery of a share into its `SharedRecord`, emitted instead of
+/// `ReceivedPatientDataEvent` while compact events are on.
+#[event]
+pub struct ReceivedPatientDataCompactEvent {
+    /// Record that was shared
+    pub patient_record: Pubkey,
+    pub shared_record: Pubkey,
+    pub computation_offset: u64,
+    /// Slot the result was delivered at
+    pub slot: u64,
+    /// `share_output_hash` of the output written to `shared_record`
+    pub output_hash: [u8; 32],
+}
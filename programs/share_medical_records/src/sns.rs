@@ -0,0 +1,123 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hashv;
+
+use crate::ErrorCode;
+use crate::*;
+
+/// Solana Name Service program owning `.sol` name accounts.
+pub const SNS_NAME_SERVICE_ID: Pubkey = pubkey!("namesLPneVptA9Z5rqUDD9tMTWEJwofgaYwp8cawRkX");
+/// Name account of the `.sol` top-level domain, parent of every `.sol` name.
+pub const SOL_TLD_AUTHORITY: Pubkey = pubkey!("58PwtjSDuFHuUkYjH9BYnnQKHfwo9reZhC2zMJv9JJX");
+/// Prefix SNS hashes names with before deriving their account.
+const SNS_HASH_PREFIX: &[u8] = b"SPL Name Service";
+/// Length of the parent, owner and class header of a name account.
+const SNS_HEADER_LEN: usize = 96;
+
+pub const PROVIDER_NAME_SEED: &[u8] = b"provider_name";
+pub const MAX_SNS_NAME_LEN: usize = 64;
+
+/// Reverse lookup from a `.sol` name to the provider that proved ownership of it.
+///
+/// Lets a patient resolve "cityhospital.sol" to a registered provider before granting
+/// consent, instead of pasting a raw key.
+#[account]
+#[derive(InitSpace)]
+pub struct ProviderNameBinding {
+    pub provider: Pubkey,
+    /// SNS name account of the domain
+    pub name_account: Pubkey,
+    /// Domain without the `.sol` suffix
+    #[max_len(MAX_SNS_NAME_LEN)]
+    pub name: String,
+    /// Unix timestamp the binding was verified at
+    pub bound_at: i64,
+    pub bump: u8,
+}
+
+/// Address of the SNS name account of `name`.sol.
+pub fn sns_name_account(name: &str) -> Pubkey {
+    let hashed_name = hashv(&[SNS_HASH_PREFIX, name.as_bytes()]).to_bytes();
+    Pubkey::find_program_address(
+        &[&hashed_name, &[0; 32], SOL_TLD_AUTHORITY.as_ref()],
+        &SNS_NAME_SERVICE_ID,
+    )
+    .0
+}
+
+/// Reads the current owner of an SNS name account.
+pub fn sns_name_owner(name_account: &AccountInfo) -> Result<Pubkey> {
+    require_keys_eq!(
+        *name_account.owner,
+        SNS_NAME_SERVICE_ID,
+        ErrorCode::InvalidSnsNameAccount
+    );
+    let data = name_account.try_borrow_data()?;
+    require!(
+        data.len() >= SNS_HEADER_LEN,
+        ErrorCode::InvalidSnsNameAccount
+    );
+    Ok(Pubkey::try_from(&data[32..64]).unwrap())
+}
+
+#[event]
+pub struct ProviderNameBoundEvent {
+    pub provider: Pubkey,
+    pub name_account: Pubkey,
+    pub name: String,
+}
+
+#[event]
+pub struct ProviderNameClearedEvent {
+    pub provider: Pubkey,
+    pub name_account: Pubkey,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct BindProviderName<'info> {
+    #[account(mut)]
+    pub provider: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [PROVIDER_SEED, provider.key().as_ref()],
+        bump = provider_profile.bump,
+        has_one = provider @ ErrorCode::Unauthorized,
+        constraint = provider_profile.active @ ErrorCode::ProviderNotRegistered,
+    )]
+    pub provider_profile: Account<'info, ProviderProfile>,
+    #[account(address = sns_name_account(&name) @ ErrorCode::InvalidSnsNameAccount)]
+    /// CHECK: name_account, checked against the name and parsed in the handler.
+    pub name_account: UncheckedAccount<'info>,
+    #[account(
+        init_if_needed,
+        payer = provider,
+        space = 8 + ProviderNameBinding::INIT_SPACE,
+        seeds = [PROVIDER_NAME_SEED, name_account.key().as_ref()],
+        bump,
+    )]
+    pub provider_name: Account<'info, ProviderNameBinding>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClearProviderName<'info> {
+    #[account(
+        mut,
+        close = provider,
+        seeds = [PROVIDER_NAME_SEED, name_account.key().as_ref()],
+        bump = provider_name.bump,
+    )]
+    pub provider_name: Account<'info, ProviderNameBinding>,
+    #[account(address = provider_name.name_account)]
+    /// CHECK: name_account, checked against the binding and parsed in the handler.
+    pub name_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        seeds = [PROVIDER_SEED, provider_name.provider.as_ref()],
+        bump = provider_profile.bump,
+    )]
+    pub provider_profile: Account<'info, ProviderProfile>,
+    #[account(mut, address = provider_name.provider)]
+    /// CHECK: provider, receives the binding rent back; checked against the binding.
+    pub provider: UncheckedAccount<'info>,
+}
@@ -0,0 +1,135 @@
+use anchor_lang::prelude::*;
+
+use crate::ErrorCode;
+use crate::*;
+
+pub const SPECIALTY_TEMPLATE_SEED: &[u8] = b"specialty_template";
+pub const ACCESS_REQUEST_SEED: &[u8] = b"access_request";
+
+/// Clinical specialty a provider requests record access for.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+#[repr(u8)]
+pub enum Specialty {
+    GeneralPractice,
+    Dentistry,
+    Cardiology,
+    Dermatology,
+    Oncology,
+    Pediatrics,
+}
+
+/// Fields a specialty needs by default, published by the admin.
+///
+/// `request_access` falls back to these when the provider names no fields, so requests
+/// ask for the minimum a visit of that specialty needs unless the provider asks for more.
+#[account]
+#[derive(InitSpace)]
+pub struct SpecialtyTemplate {
+    pub specialty: Specialty,
+    /// Bitmask of `PatientField` bits requested by default
+    pub fields: u16,
+    /// Unix timestamp the template was last set at
+    pub updated_at: i64,
+    pub bump: u8,
+}
+
+/// A provider's request for access to a patient's record, for the patient to answer with
+/// `grant_consent`.
+///
+/// One per provider and record; a new request replaces the previous one.
+#[account]
+#[derive(InitSpace)]
+pub struct AccessRequest {
+    pub patient_data: Pubkey,
+    /// Provider asking for access
+    pub provider: Pubkey,
+    pub specialty: Specialty,
+    /// Bitmask of `PatientField` bits requested
+    pub fields: u16,
+    /// Set when `fields` asks for more than the specialty's template
+    pub exceeds_template: bool,
+    /// `CONSENT_SCOPE_*` bits of the purpose access is requested for
+    pub purpose: u16,
+    pub requested_at: i64,
+    pub bump: u8,
+}
+
+#[event]
+pub struct SpecialtyTemplateSetEvent {
+    pub specialty_template: Pubkey,
+    pub specialty: Specialty,
+    pub fields: u16,
+}
+
+/// A provider requested access; patients subscribe by `patient_data`.
+#[event]
+pub struct AccessRequestedEvent {
+    pub access_request: Pubkey,
+    pub patient_data: Pubkey,
+    pub provider: Pubkey,
+    pub specialty: Specialty,
+    pub fields: u16,
+    pub exceeds_template: bool,
+    pub purpose: u16,
+}
+
+#[derive(Accounts)]
+#[instruction(specialty: Specialty)]
+pub struct SetSpecialtyTemplate<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+        has_one = admin @ ErrorCode::Unauthorized,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = 8 + SpecialtyTemplate::INIT_SPACE,
+        seeds = [SPECIALTY_TEMPLATE_SEED, &[specialty as u8]],
+        bump,
+    )]
+    pub specialty_template: Account<'info, SpecialtyTemplate>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RequestAccess<'info> {
+    #[account(mut)]
+    pub provider: Signer<'info>,
+    #[account(
+        seeds = [PROVIDER_SEED, provider.key().as_ref()],
+        bump = provider_profile.bump,
+        constraint = provider_profile.active @ ErrorCode::ProviderNotRegistered,
+    )]
+    pub provider_profile: Account<'info, ProviderProfile>,
+    pub patient_data: Account<'info, PatientData>,
+    #[account(
+        seeds = [SPECIALTY_TEMPLATE_SEED, &[specialty_template.specialty as u8]],
+        bump = specialty_template.bump,
+    )]
+    pub specialty_template: Account<'info, SpecialtyTemplate>,
+    #[account(
+        init_if_needed,
+        payer = provider,
+        space = 8 + AccessRequest::INIT_SPACE,
+        seeds = [ACCESS_REQUEST_SEED, patient_data.key().as_ref(), provider.key().as_ref()],
+        bump,
+    )]
+    pub access_request: Account<'info, AccessRequest>,
+    #[account(
+        mut,
+        seeds = [INBOX_SEED, patient_data.key().as_ref()],
+        bump,
+    )]
+    /// CHECK: inbox, empty unless the patient opened one; written in the handler.
+    pub inbox: UncheckedAccount<'info>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    pub system_program: Program<'info, System>,
+}
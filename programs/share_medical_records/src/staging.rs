@@ -0,0 +1,347 @@
+use anchor_lang::prelude::*;
+use arcium_anchor::prelude::*;
+
+use crate::ErrorCode;
+use crate::*;
+
+pub const STAGED_SHARE_SEED: &[u8] = b"staged_share";
+/// Compute units to request for a transaction queueing a share.
+///
+/// Queueing one stays well under the 200k an instruction gets by default; requesting this
+/// instead keeps the priority fee down and leaves room for the compute-budget and lookup
+/// table instructions sent alongside.
+pub const SHARE_COMPUTE_UNIT_LIMIT: u32 = 160_000;
+/// Accounts the share callback is invoked with: its own, then the escrow's and those of
+/// `#[event_cpi]`.
+const SHARE_CALLBACK_ACCOUNTS: usize = 10 + ACCESS_FEE_CALLBACK_ACCOUNTS + 2;
+
+/// Arguments of a share other than the computation offset, as passed to
+/// `share_patient_data` or staged by `prepare_share`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, InitSpace)]
+pub struct ShareParams {
+    /// x25519 key the record is re-encrypted for
+    pub receiver: [u8; 32],
+    pub receiver_nonce: u128,
+    /// Key the record was encrypted with, its `enc_pubkey`
+    pub sender_pub_key: [u8; 32],
+    pub nonce: u128,
+    /// Approved cluster to route the computation to instead of the MXE default
+    pub cluster_hint: Option<u32>,
+}
+
+/// Share parameters written ahead of the instruction queueing them.
+///
+/// A share carries the Arcium accounts, fee accounts, consent and often an ed25519
+/// approval instruction, which together can push `share_patient_data` past the packet
+/// limit. `prepare_share` stores the arguments, and verifies a relayed approval, in a
+/// transaction of its own; `execute_share` then queues the computation with only the
+/// offset and receiver as arguments and closes the staging account.
+#[account]
+#[derive(InitSpace)]
+pub struct StagedShare {
+    pub payer: Pubkey,
+    pub patient_data: Pubkey,
+    pub computation_offset: u64,
+    pub params: ShareParams,
+    /// Audit sequence of the record the patient's relayed approval was verified at
+    pub approved_at: Option<u64>,
+    pub bump: u8,
+    /// Key epoch of the payer's provider profile when staged, 0 without one; the share
+    /// only executes within it
+    pub key_epoch: u32,
+}
+
+/// How a share not backed by the payer's control of, or consent to, the record was
+/// approved by the patient.
+#[derive(Clone, Copy)]
+pub enum ShareApproval {
+    /// Signed approval expiring at `expires_at`, verified against the ed25519
+    /// instruction preceding the share; it covers the payer's current key epoch
+    Signed { expires_at: i64 },
+    /// Signed approval already verified by `prepare_share` at `audit_sequence`; it only
+    /// holds while no audited action has happened to the record since
+    Staged { audit_sequence: u64 },
+}
+
+#[event]
+pub struct ShareStagedEvent {
+    pub staged_share: Pubkey,
+    pub payer: Pubkey,
+    pub patient_data: Pubkey,
+    pub computation_offset: u64,
+}
+
+/// Queues the share of `ctx.accounts.patient_data` described by `params`, for
+/// `share_patient_data` and `execute_share`.
+pub fn queue_share(
+    ctx: Context<SharePatientData>,
+    computation_offset: u64,
+    params: ShareParams,
+    approval: Option<ShareApproval>,
+) -> Result<()> {
+    let ShareParams {
+        receiver,
+        receiver_nonce,
+        sender_pub_key,
+        nonce,
+        cluster_hint,
+    } = params;
+    ctx.accounts.program_config.require_share_enabled()?;
+    ctx.accounts.patient_data.require_live()?;
+    require!(
+        sender_pub_key == ctx.accounts.patient_data.enc_pubkey,
+        ErrorCode::SenderKeyMismatch
+    );
+    require_valid_x25519_key(&receiver)?;
+    require_valid_x25519_key(&sender_pub_key)?;
+    require!(
+        ctx.accounts.shared_record.is_some() == ctx.accounts.program_config.compact_events,
+        ErrorCode::SharedRecordMismatch
+    );
+    let clock = ctx.accounts.program_config.expiry_clock()?;
+    let now = clock.unix_timestamp;
+    match approval {
+        Some(ShareApproval::Signed { expires_at }) => PatientApproval {
+            action: ApprovalAction::SharePatientData,
+            patient: ctx.accounts.patient_data.patient_authority,
+            payload_hash: share_payload_hash(
+                &receiver,
+                receiver_nonce,
+                &sender_pub_key,
+                nonce,
+                ctx.accounts.patient_registry.audit_entries,
+                ProviderProfile::key_epoch_of(
+                    &ctx.accounts.provider_profile,
+                    ctx.accounts.payer.key,
+                )?,
+            ),
+            expires_at,
+        }
+        .verify(&ctx.accounts.instructions_sysvar)?,
+        Some(ShareApproval::Staged { audit_sequence }) => require!(
+            audit_sequence == ctx.accounts.patient_registry.audit_entries,
+            ErrorCode::StagedApprovalStale
+        ),
+        None => {
+            let patient_data = &ctx.accounts.patient_data;
+            patient_data.require_share_authorized(
+                ctx.accounts.payer.key,
+                ctx.accounts.consent_grant.as_deref().map(|grant| &**grant),
+                &clock,
+            )?;
+            if ctx.accounts.payer.key() != patient_data.patient_authority {
+                ProviderProfile::require_attested_receiver(
+                    &ctx.accounts.provider_profile,
+                    ctx.accounts.payer.key,
+                    &receiver,
+                )?;
+            }
+        }
+    }
+
+    let cluster_offset = ctx
+        .accounts
+        .program_config
+        .select_cluster(ctx.accounts.mxe_account.cluster, cluster_hint)?;
+    require_keys_eq!(
+        ctx.accounts.cluster_account.key(),
+        cluster_pda(cluster_offset),
+        ErrorCode::ClusterNotApproved
+    );
+
+    let comp_def_offset = ctx
+        .accounts
+        .program_config
+        .resolve_share_comp_def(&ctx.accounts.comp_def_account.key(), now)?;
+
+    require_ciphertext_region::<PatientData>(&ctx.accounts.patient_data.to_account_info())?;
+    let args = share_patient_data_args(
+        receiver,
+        receiver_nonce,
+        sender_pub_key,
+        nonce,
+        ctx.accounts.patient_data.key(),
+    );
+
+    ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+    ctx.accounts.program_config.record_queued(comp_def_offset)?;
+    ctx.accounts.used_offsets.claim(
+        ctx.accounts.payer.key(),
+        ctx.bumps.used_offsets,
+        computation_offset,
+        &ctx.accounts.program_config,
+    )?;
+    ctx.accounts.callback_guard.expect(
+        computation_offset,
+        comp_def_offset,
+        ctx.bumps.callback_guard,
+    );
+    ctx.accounts.used_nonces.claim(
+        ctx.accounts.patient_data.key(),
+        ctx.accounts.used_nonces.bump,
+        receiver_nonce,
+    )?;
+    ctx.accounts.patient_data.begin_computation()?;
+
+    // Sized up front so the escrow and event accounts appended below never reallocate.
+    let mut callback_accounts = Vec::with_capacity(SHARE_CALLBACK_ACCOUNTS);
+    callback_accounts.extend([
+        CallbackAccount {
+            pubkey: ctx.accounts.program_config.key(),
+            is_writable: true,
+        },
+        CallbackAccount {
+            pubkey: ctx.accounts.callback_guard.key(),
+            is_writable: true,
+        },
+        CallbackAccount {
+            pubkey: ctx.accounts.pending_share.key(),
+            is_writable: true,
+        },
+        CallbackAccount {
+            pubkey: ctx.accounts.payer.key(),
+            is_writable: true,
+        },
+        CallbackAccount {
+            pubkey: ctx.accounts.refund_balance.key(),
+            is_writable: true,
+        },
+        CallbackAccount {
+            pubkey: ctx.accounts.patient_data.key(),
+            is_writable: true,
+        },
+        CallbackAccount {
+            pubkey: ctx.accounts.used_offsets.key(),
+            is_writable: true,
+        },
+        CallbackAccount {
+            pubkey: ctx.accounts.share_receipt.key(),
+            is_writable: true,
+        },
+        match &ctx.accounts.shared_record {
+            Some(shared_record) => CallbackAccount {
+                pubkey: shared_record.key(),
+                is_writable: true,
+            },
+            None => CallbackAccount {
+                pubkey: crate::ID,
+                is_writable: false,
+            },
+        },
+        CallbackAccount {
+            pubkey: Pubkey::find_program_address(
+                &[INBOX_SEED, ctx.accounts.patient_data.key().as_ref()],
+                &crate::ID,
+            )
+            .0,
+            is_writable: true,
+        },
+    ]);
+    let (fee_callback_accounts, escrowed_fee) = escrow_access_fee(ctx.accounts, &clock)?;
+    callback_accounts.extend(fee_callback_accounts);
+    callback_accounts.extend(event_cpi_callback_accounts());
+
+    let refund_balance = &mut ctx.accounts.refund_balance;
+    refund_balance.payer = ctx.accounts.payer.key();
+    refund_balance.bump = ctx.bumps.refund_balance;
+
+    let lamports_before = ctx.accounts.payer.lamports();
+    queue_computation(
+        ctx.accounts,
+        computation_offset,
+        args,
+        None,
+        vec![SharePatientDataCallback::callback_ix(&callback_accounts)],
+    )?;
+
+    let pending_share = &mut ctx.accounts.pending_share;
+    pending_share.payer = ctx.accounts.payer.key();
+    pending_share.patient_data = ctx.accounts.patient_data.key();
+    pending_share.receiver = receiver;
+    pending_share.computation_offset = computation_offset;
+    pending_share.arcium_fee = lamports_before.saturating_sub(ctx.accounts.payer.lamports());
+    pending_share.escrowed_fee = escrowed_fee;
+    pending_share.queued_at = now;
+    pending_share.bump = ctx.bumps.pending_share;
+    ctx.accounts.share_receipt.open(
+        pending_share.patient_data,
+        receiver,
+        CONSENT_SCOPE_PATIENT_DATA,
+        ctx.accounts.program_config.record_fields,
+        computation_offset,
+        ctx.bumps.share_receipt,
+    );
+    if let (Some(shared_record), Some(bump)) = (
+        ctx.accounts.shared_record.as_deref_mut(),
+        ctx.bumps.shared_record,
+    ) {
+        shared_record.patient_data = pending_share.patient_data;
+        shared_record.receiver = receiver;
+        shared_record.bump = bump;
+    }
+
+    let event = SharePatientDataQueuedEvent {
+        patient_data: pending_share.patient_data,
+        payer: pending_share.payer,
+        receiver,
+        computation_offset,
+    };
+    ctx.accounts.patient_registry.log_access(
+        event.patient_data,
+        &mut ctx.accounts.access_log_page,
+        ctx.bumps.access_log_page,
+        AccessLogEntry {
+            accessor: event.payer,
+            receiver,
+            computation_offset,
+            slot: clock.slot,
+        },
+    )?;
+    ctx.accounts
+        .patient_registry
+        .audit(event.patient_data, ctx.bumps.patient_registry, &event)?;
+    emit!(event);
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct PrepareShare<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub patient_data: Account<'info, PatientData>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + StagedShare::INIT_SPACE,
+        seeds = [STAGED_SHARE_SEED, payer.key().as_ref(), &computation_offset.to_le_bytes()],
+        bump,
+    )]
+    pub staged_share: Account<'info, StagedShare>,
+    #[account(
+        seeds = [PATIENT_REGISTRY_SEED, patient_data.key().as_ref()],
+        bump,
+    )]
+    /// CHECK: patient_registry, empty until the record's first audited action; read in
+    /// the handler.
+    pub patient_registry: UncheckedAccount<'info>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    /// CHECK: provider_profile, empty unless the payer is a registered provider; read, and
+    /// checked against its seeds, in the handler for its key epoch.
+    pub provider_profile: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DiscardStagedShare<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        close = payer,
+        has_one = payer @ ErrorCode::Unauthorized,
+    )]
+    pub staged_share: Account<'info, StagedShare>,
+}
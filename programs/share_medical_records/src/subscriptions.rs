@@ -0,0 +1,120 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+use crate::ErrorCode;
+use crate::*;
+
+pub const SUBSCRIPTION_SEED: &[u8] = b"subscription";
+pub const MAX_SUBSCRIPTION_TIERS: usize = 4;
+
+/// Price and length of one subscription period.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, InitSpace)]
+pub struct SubscriptionTier {
+    /// Price per period in base units of the subscription mint
+    pub price: u64,
+    /// Period length in seconds
+    pub duration: i64,
+}
+
+/// A provider's prepaid plan covering unlimited shares until it expires.
+#[account]
+#[derive(InitSpace)]
+pub struct Subscription {
+    pub provider: Pubkey,
+    pub tier: u8,
+    /// Unix timestamp the subscription lapses at
+    pub expires_at: i64,
+    pub bump: u8,
+    /// Slot `expires_at` was estimated to fall in when last extended
+    pub expires_at_slot: u64,
+}
+
+impl Subscription {
+    /// Returns whether the subscription of `provider` stored at `info`, if any, is active at
+    /// `clock`.
+    ///
+    /// An empty account is taken as no subscription without deriving its address: passing
+    /// the wrong one only forgoes the payer's own subscription. A stored one is checked
+    /// against the bump it records, which is cheaper than a seeds constraint.
+    pub fn is_active(info: &AccountInfo, provider: &Pubkey, clock: &ExpiryClock) -> Result<bool> {
+        if info.data_is_empty() {
+            return Ok(false);
+        }
+        require_keys_eq!(*info.owner, crate::ID, ErrorCode::InvalidSubscription);
+        let subscription = Subscription::try_deserialize(&mut &info.try_borrow_data()?[..])?;
+        let address = Pubkey::create_program_address(
+            &[SUBSCRIPTION_SEED, provider.as_ref(), &[subscription.bump]],
+            &crate::ID,
+        )
+        .map_err(|_| error!(ErrorCode::InvalidSubscription))?;
+        require_keys_eq!(info.key(), address, ErrorCode::InvalidSubscription);
+        Ok(!clock.has_passed(subscription.expires_at, subscription.expires_at_slot))
+    }
+}
+
+#[derive(Accounts)]
+pub struct ConfigureSubscriptions<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+        has_one = admin @ ErrorCode::Unauthorized,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        init_if_needed,
+        payer = admin,
+        token::mint = mint,
+        token::authority = fee_authority,
+        token::token_program = token_program,
+        seeds = [TREASURY_SEED, mint.key().as_ref()],
+        bump,
+    )]
+    pub treasury: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        seeds = [FEE_AUTHORITY_SEED],
+        bump,
+    )]
+    /// CHECK: fee_authority, PDA owning the treasury, checked by the seeds constraint.
+    pub fee_authority: UncheckedAccount<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct PurchaseSubscription<'info> {
+    #[account(mut)]
+    pub provider: Signer<'info>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    #[account(
+        init_if_needed,
+        payer = provider,
+        space = 8 + Subscription::INIT_SPACE,
+        seeds = [SUBSCRIPTION_SEED, provider.key().as_ref()],
+        bump,
+    )]
+    pub subscription: Account<'info, Subscription>,
+    #[account(
+        mut,
+        token::mint = mint,
+        token::authority = provider,
+    )]
+    pub provider_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED, mint.key().as_ref()],
+        bump,
+    )]
+    pub treasury: InterfaceAccount<'info, TokenAccount>,
+    #[account(address = program_config.subscription_mint @ ErrorCode::InvalidSubscription)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
@@ -0,0 +1,56 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+use crate::ErrorCode;
+use crate::*;
+
+pub const TREASURY_SEED: &[u8] = b"treasury";
+/// Denominator of every basis-points value in the config.
+pub const MAX_BPS: u16 = 10_000;
+
+/// Address of the protocol treasury token account for `mint`.
+pub fn treasury_address(mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[TREASURY_SEED, mint.as_ref()], &crate::ID).0
+}
+
+/// Splits `amount` into the recipient's part and the treasury's `treasury_bps` cut.
+///
+/// The treasury cut is rounded down, so any dust stays with the recipient.
+pub fn split_fee(amount: u64, treasury_bps: u16) -> Result<(u64, u64)> {
+    let treasury_amount = (amount as u128)
+        .checked_mul(treasury_bps as u128)
+        .map(|product| product / MAX_BPS as u128)
+        .ok_or(ErrorCode::ArithmeticOverflow)? as u64;
+    Ok((amount - treasury_amount, treasury_amount))
+}
+
+#[event]
+pub struct FeeSplitUpdatedEvent {
+    pub previous_treasury_bps: u16,
+    pub treasury_bps: u16,
+}
+
+#[derive(Accounts)]
+pub struct InitTreasury<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        init,
+        payer = payer,
+        token::mint = mint,
+        token::authority = fee_authority,
+        token::token_program = token_program,
+        seeds = [TREASURY_SEED, mint.key().as_ref()],
+        bump,
+    )]
+    pub treasury: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        seeds = [FEE_AUTHORITY_SEED],
+        bump,
+    )]
+    /// CHECK: fee_authority, PDA owning the treasury, checked by the seeds constraint.
+    pub fee_authority: UncheckedAccount<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
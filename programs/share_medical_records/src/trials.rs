@@ -0,0 +1,325 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hashv;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+use crate::ErrorCode;
+use crate::*;
+
+pub const TRIAL_ESCROW_SEED: &[u8] = b"trial_escrow";
+pub const TRIAL_VAULT_SEED: &[u8] = b"trial_vault";
+pub const TRIAL_ENROLLMENT_SEED: &[u8] = b"trial_enrollment";
+/// Most visits a trial can pay out for.
+pub const MAX_TRIAL_MILESTONES: u8 = 16;
+
+/// Tokens a sponsor deposits to pay the patients enrolled in one trial, one payout per
+/// visit milestone.
+///
+/// Funded up front for every milestone of every enrollment slot, so a confirmed
+/// milestone can always be paid. Once the trial ends, the sponsor takes back whatever
+/// confirmed milestones have not earned.
+#[account]
+#[derive(InitSpace)]
+pub struct TrialEscrow {
+    pub sponsor: Pubkey,
+    /// Provider running the trial's visits, who enrolls patients and confirms visits
+    pub site: Pubkey,
+    pub mint: Pubkey,
+    /// Sponsor chosen identifier, part of the escrow address
+    pub trial_id: u64,
+    /// Visits each enrolled patient is paid for, at most `MAX_TRIAL_MILESTONES`
+    pub milestones: u8,
+    pub payout_per_milestone: u64,
+    pub max_enrollments: u16,
+    pub enrolled: u16,
+    /// Tokens earned by confirmed milestones so far
+    pub unlocked: u64,
+    /// Tokens paid out of `unlocked`, including the treasury's cut
+    pub paid: u64,
+    /// Unix timestamp after which no patient is enrolled and no visit confirmed
+    pub ends_at: i64,
+    /// Set once the sponsor has taken back the unearned deposit
+    pub closed: bool,
+    pub bump: u8,
+}
+
+impl TrialEscrow {
+    /// Fails with `TrialClosed` once the trial has ended or been closed.
+    pub fn require_running(&self, now: i64) -> Result<()> {
+        require!(!self.closed && now < self.ends_at, ErrorCode::TrialClosed);
+        Ok(())
+    }
+}
+
+/// A patient's enrollment in a trial, and the visits the site has confirmed for it.
+#[account]
+#[derive(InitSpace)]
+pub struct TrialEnrollment {
+    pub trial_escrow: Pubkey,
+    pub patient_data: Pubkey,
+    /// Wallet that owns the record and receives the payouts
+    pub patient: Pubkey,
+    pub enrolled_at: i64,
+    /// Milestones the site has confirmed, in order
+    pub milestones_confirmed: u8,
+    /// Milestones paid out to the patient
+    pub milestones_paid: u8,
+    /// `hash(prev || confirmation)` over every confirmation, so an export of the
+    /// encrypted confirmations can be checked for completeness
+    pub confirmation_head: [u8; 32],
+    pub bump: u8,
+}
+
+/// Hash folded into an enrollment's `confirmation_head` for one visit confirmation.
+pub fn trial_confirmation_hash(
+    head: &[u8; 32],
+    milestone: u8,
+    enc_pubkey: &[u8; 32],
+    nonce: u128,
+    confirmation: &[u8; 32],
+) -> [u8; 32] {
+    hashv(&[
+        head,
+        &[milestone],
+        enc_pubkey,
+        &nonce.to_le_bytes(),
+        confirmation,
+    ])
+    .to_bytes()
+}
+
+#[event]
+pub struct TrialEscrowCreatedEvent {
+    pub trial_escrow: Pubkey,
+    pub sponsor: Pubkey,
+    pub site: Pubkey,
+    pub milestones: u8,
+    pub payout_per_milestone: u64,
+    pub max_enrollments: u16,
+    pub ends_at: i64,
+}
+
+#[event]
+pub struct TrialPatientEnrolledEvent {
+    pub trial_enrollment: Pubkey,
+    pub trial_escrow: Pubkey,
+    pub patient_data: Pubkey,
+}
+
+/// A visit was confirmed; patients and sponsors subscribe by `trial_escrow`.
+#[event]
+pub struct TrialVisitConfirmedEvent {
+    pub trial_enrollment: Pubkey,
+    pub trial_escrow: Pubkey,
+    pub milestone: u8,
+    /// x25519 key the confirmation was encrypted with
+    pub enc_pubkey: [u8; 32],
+    pub nonce: u128,
+    /// Encrypted visit details, readable by whoever the site encrypted them for
+    pub confirmation: [u8; 32],
+    pub confirmation_head: [u8; 32],
+}
+
+#[event]
+pub struct TrialPayoutClaimedEvent {
+    pub trial_enrollment: Pubkey,
+    /// Milestones the payout covers
+    pub milestones: u8,
+    pub amount: u64,
+    pub treasury_amount: u64,
+}
+
+#[event]
+pub struct TrialEscrowClosedEvent {
+    pub trial_escrow: Pubkey,
+    /// Unearned tokens returned to the sponsor
+    pub refunded: u64,
+}
+
+#[derive(Accounts)]
+#[instruction(trial_id: u64)]
+pub struct CreateTrialEscrow<'info> {
+    #[account(mut)]
+    pub sponsor: Signer<'info>,
+    #[account(
+        seeds = [PROVIDER_SEED, sponsor.key().as_ref()],
+        bump = sponsor_profile.bump,
+        constraint = sponsor_profile.active
+            && sponsor_profile.role == ProviderRole::PharmaSponsor
+            @ ErrorCode::NotAPharmaSponsor,
+    )]
+    pub sponsor_profile: Box<Account<'info, ProviderProfile>>,
+    #[account(
+        init,
+        payer = sponsor,
+        space = 8 + TrialEscrow::INIT_SPACE,
+        seeds = [TRIAL_ESCROW_SEED, sponsor.key().as_ref(), &trial_id.to_le_bytes()],
+        bump,
+    )]
+    pub trial_escrow: Box<Account<'info, TrialEscrow>>,
+    #[account(
+        init,
+        payer = sponsor,
+        token::mint = mint,
+        token::authority = fee_authority,
+        token::token_program = token_program,
+        seeds = [TRIAL_VAULT_SEED, trial_escrow.key().as_ref()],
+        bump,
+    )]
+    pub escrow_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        token::mint = mint,
+        token::authority = sponsor,
+    )]
+    pub sponsor_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        seeds = [FEE_AUTHORITY_SEED],
+        bump,
+    )]
+    /// CHECK: fee_authority, PDA owning escrow vaults, checked by the seeds constraint.
+    pub fee_authority: UncheckedAccount<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct EnrollTrialPatient<'info> {
+    #[account(mut)]
+    pub patient: Signer<'info>,
+    pub site: Signer<'info>,
+    #[account(
+        seeds = [PROVIDER_SEED, site.key().as_ref()],
+        bump = site_profile.bump,
+        constraint = site_profile.active @ ErrorCode::ProviderNotRegistered,
+    )]
+    pub site_profile: Box<Account<'info, ProviderProfile>>,
+    #[account(
+        seeds = [b"patient_data", patient.key().as_ref()],
+        bump,
+    )]
+    pub patient_data: Box<Account<'info, PatientData>>,
+    #[account(
+        mut,
+        has_one = site @ ErrorCode::NotTrialSite,
+    )]
+    pub trial_escrow: Box<Account<'info, TrialEscrow>>,
+    #[account(
+        init,
+        payer = patient,
+        space = 8 + TrialEnrollment::INIT_SPACE,
+        seeds = [
+            TRIAL_ENROLLMENT_SEED,
+            trial_escrow.key().as_ref(),
+            patient_data.key().as_ref(),
+        ],
+        bump,
+    )]
+    pub trial_enrollment: Box<Account<'info, TrialEnrollment>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ConfirmTrialVisit<'info> {
+    pub site: Signer<'info>,
+    #[account(
+        seeds = [PROVIDER_SEED, site.key().as_ref()],
+        bump = site_profile.bump,
+        constraint = site_profile.active @ ErrorCode::ProviderNotRegistered,
+    )]
+    pub site_profile: Box<Account<'info, ProviderProfile>>,
+    #[account(
+        mut,
+        has_one = site @ ErrorCode::NotTrialSite,
+    )]
+    pub trial_escrow: Box<Account<'info, TrialEscrow>>,
+    #[account(
+        mut,
+        seeds = [
+            TRIAL_ENROLLMENT_SEED,
+            trial_escrow.key().as_ref(),
+            trial_enrollment.patient_data.as_ref(),
+        ],
+        bump = trial_enrollment.bump,
+    )]
+    pub trial_enrollment: Box<Account<'info, TrialEnrollment>>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimTrialPayout<'info> {
+    #[account(mut, has_one = mint)]
+    pub trial_escrow: Box<Account<'info, TrialEscrow>>,
+    #[account(
+        mut,
+        seeds = [
+            TRIAL_ENROLLMENT_SEED,
+            trial_escrow.key().as_ref(),
+            trial_enrollment.patient_data.as_ref(),
+        ],
+        bump = trial_enrollment.bump,
+    )]
+    pub trial_enrollment: Box<Account<'info, TrialEnrollment>>,
+    #[account(
+        mut,
+        seeds = [TRIAL_VAULT_SEED, trial_escrow.key().as_ref()],
+        bump,
+    )]
+    pub escrow_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        token::mint = mint,
+        token::authority = trial_enrollment.patient,
+    )]
+    pub patient_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED, mint.key().as_ref()],
+        bump,
+    )]
+    pub treasury: Box<InterfaceAccount<'info, TokenAccount>>,
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Box<Account<'info, ProgramConfig>>,
+    #[account(
+        seeds = [FEE_AUTHORITY_SEED],
+        bump,
+    )]
+    /// CHECK: fee_authority, PDA owning escrow vaults, checked by the seeds constraint.
+    pub fee_authority: UncheckedAccount<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct CloseTrialEscrow<'info> {
+    #[account(mut)]
+    pub sponsor: Signer<'info>,
+    #[account(
+        mut,
+        has_one = sponsor @ ErrorCode::Unauthorized,
+        has_one = mint,
+    )]
+    pub trial_escrow: Box<Account<'info, TrialEscrow>>,
+    #[account(
+        mut,
+        seeds = [TRIAL_VAULT_SEED, trial_escrow.key().as_ref()],
+        bump,
+    )]
+    pub escrow_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        token::mint = mint,
+        token::authority = sponsor,
+    )]
+    pub sponsor_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        seeds = [FEE_AUTHORITY_SEED],
+        bump,
+    )]
+    /// CHECK: fee_authority, PDA owning escrow vaults, checked by the seeds constraint.
+    pub fee_authority: UncheckedAccount<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
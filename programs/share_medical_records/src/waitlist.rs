@@ -0,0 +1,312 @@
+use anchor_lang::prelude::*;
+use arcium_anchor::prelude::*;
+
+use crate::ErrorCode;
+use crate::*;
+
+pub const WAITLIST_ENTRY_SEED: &[u8] = b"waitlist_entry";
+pub const WAITLIST_RUN_SEED: &[u8] = b"waitlist_run";
+/// Candidates one run ranks, fixed by the `waitlist_priority` circuit.
+pub const WAITLIST_BATCH_SIZE: usize = 4;
+/// Encrypted values of a candidate: urgency, days waiting and their token.
+pub const WAITLIST_VALUES: usize = 3;
+/// Length of the ciphertexts at the start of `WaitlistEntry`, the part circuits read.
+pub const WAITLIST_CIPHERTEXTS_LEN: u32 = WAITLIST_VALUES as u32 * 32;
+
+/// A patient's listing as a transplant candidate.
+///
+/// The urgency score and days waiting their transplant center reported, and a random
+/// token they chose, are encrypted under the patient's key. Coordinators only ever learn
+/// where a token ranks among the batch it was ranked with.
+#[account]
+#[derive(InitSpace)]
+pub struct WaitlistEntry {
+    /// Encrypted urgency score, days waiting and token
+    pub candidate: [[u8; 32]; WAITLIST_VALUES],
+    pub patient_data: Pubkey,
+    /// Wallet that owns the record and listed it
+    pub patient: Pubkey,
+    /// x25519 key the candidate values were encrypted with
+    pub enc_pubkey: [u8; 32],
+    /// Nonce the candidate values were encrypted with
+    pub nonce: u128,
+    /// Cleared to leave the waitlist without closing the entry
+    pub listed: bool,
+    pub updated_at: i64,
+    pub bump: u8,
+}
+
+/// Log of one `waitlist_priority` run: who ran it, over which entries, and its result.
+///
+/// Never closed, so a patient can find every run their entry was ranked in by filtering
+/// on `entries`.
+#[account]
+#[derive(InitSpace)]
+pub struct WaitlistRun {
+    /// Coordinator who queued the run and whom the ranking is encrypted for
+    pub coordinator: Pubkey,
+    /// Entries in the order they were passed to the circuit
+    pub entries: [Pubkey; WAITLIST_BATCH_SIZE],
+    pub computation_offset: u64,
+    pub queued_slot: u64,
+    /// Slot the ranking was delivered at, zero while pending
+    pub completed_slot: u64,
+    pub aborted: bool,
+    /// x25519 key the ranking is encrypted for
+    pub receiver_enc_key: [u8; 32],
+    pub nonce: [u8; 16],
+    /// Encrypted tokens from highest to lowest priority
+    pub ranking: [[u8; 32]; WAITLIST_BATCH_SIZE],
+    pub bump: u8,
+}
+
+#[event]
+pub struct WaitlistListingSetEvent {
+    pub waitlist_entry: Pubkey,
+    pub patient_data: Pubkey,
+    pub listed: bool,
+}
+
+/// A run was queued; patients subscribe by their entry.
+#[event]
+pub struct WaitlistPriorityQueuedEvent {
+    pub waitlist_run: Pubkey,
+    pub coordinator: Pubkey,
+    pub entries: [Pubkey; WAITLIST_BATCH_SIZE],
+}
+
+/// A run finished; coordinators subscribe by `coordinator` and read the ranking from the
+/// run.
+#[event]
+pub struct WaitlistPriorityRankedEvent {
+    pub waitlist_run: Pubkey,
+    pub coordinator: Pubkey,
+    pub aborted: bool,
+}
+
+#[derive(Accounts)]
+pub struct ListWaitlistCandidate<'info> {
+    #[account(mut)]
+    pub patient: Signer<'info>,
+    #[account(
+        seeds = [b"patient_data", patient.key().as_ref()],
+        bump,
+    )]
+    pub patient_data: Account<'info, PatientData>,
+    #[account(
+        init_if_needed,
+        payer = patient,
+        space = 8 + WaitlistEntry::INIT_SPACE,
+        seeds = [WAITLIST_ENTRY_SEED, patient_data.key().as_ref()],
+        bump,
+    )]
+    pub waitlist_entry: Box<Account<'info, WaitlistEntry>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetWaitlistListing<'info> {
+    pub patient: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [WAITLIST_ENTRY_SEED, waitlist_entry.patient_data.as_ref()],
+        bump = waitlist_entry.bump,
+        has_one = patient @ ErrorCode::Unauthorized,
+    )]
+    pub waitlist_entry: Box<Account<'info, WaitlistEntry>>,
+}
+
+#[queue_computation_accounts("waitlist_priority", coordinator)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct WaitlistPriority<'info> {
+    #[account(mut)]
+    pub coordinator: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = coordinator,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account, checked by the arcium program.
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool, checked by the arcium program.
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account, checked by the arcium program.
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_WAITLIST_PRIORITY)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(
+        mut,
+        address = derive_cluster_pda!(mxe_account)
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Box<Account<'info, ProgramConfig>>,
+    #[account(
+        seeds = [PROVIDER_SEED, coordinator.key().as_ref()],
+        bump = provider_profile.bump,
+        constraint = provider_profile.active
+            && provider_profile.role == ProviderRole::TransplantCoordinator
+            @ ErrorCode::NotATransplantCoordinator,
+    )]
+    pub provider_profile: Box<Account<'info, ProviderProfile>>,
+    #[account(
+        init,
+        payer = coordinator,
+        space = 8 + WaitlistRun::INIT_SPACE,
+        seeds = [WAITLIST_RUN_SEED, &computation_offset.to_le_bytes()],
+        bump,
+    )]
+    pub waitlist_run: Box<Account<'info, WaitlistRun>>,
+    #[account(
+        init_if_needed,
+        payer = coordinator,
+        space = 8 + UsedOffsets::INIT_SPACE,
+        seeds = [USED_OFFSETS_SEED, coordinator.key().as_ref()],
+        bump,
+    )]
+    pub used_offsets: Box<Account<'info, UsedOffsets>>,
+    #[account(
+        init,
+        payer = coordinator,
+        space = 8 + CallbackGuard::INIT_SPACE,
+        seeds = [CALLBACK_GUARD_SEED, &computation_offset.to_le_bytes()],
+        bump,
+    )]
+    pub callback_guard: Box<Account<'info, CallbackGuard>>,
+}
+
+#[callback_accounts("waitlist_priority")]
+#[derive(Accounts)]
+pub struct WaitlistPriorityCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_WAITLIST_PRIORITY)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    #[account(
+        mut,
+        seeds = [CALLBACK_GUARD_SEED, &callback_guard.computation_offset.to_le_bytes()],
+        bump = callback_guard.bump,
+    )]
+    pub callback_guard: Box<Account<'info, CallbackGuard>>,
+    #[account(
+        mut,
+        seeds = [WAITLIST_RUN_SEED, &waitlist_run.computation_offset.to_le_bytes()],
+        bump = waitlist_run.bump,
+        constraint = waitlist_run.computation_offset == callback_guard.computation_offset
+            @ ErrorCode::CallbackComputationMismatch,
+    )]
+    pub waitlist_run: Box<Account<'info, WaitlistRun>>,
+    #[account(
+        mut,
+        seeds = [USED_OFFSETS_SEED, used_offsets.payer.as_ref()],
+        bump = used_offsets.bump,
+    )]
+    pub used_offsets: Box<Account<'info, UsedOffsets>>,
+}
+
+#[init_computation_definition_accounts("waitlist_priority", payer)]
+#[derive(Accounts)]
+pub struct InitWaitlistPriorityCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut)]
+    /// CHECK: comp_def_account, checked by arcium program.
+    /// Can't check it here as it's not initialized yet.
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+        constraint = program_config.admin == payer.key() @ ErrorCode::Unauthorized,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+/// Builds the circuit arguments for the waitlist entries passed in `remaining_accounts`,
+/// and returns the entries' keys in the same order.
+///
+/// Expects `WAITLIST_BATCH_SIZE` distinct entries, every one still listed.
+pub fn waitlist_batch_arguments(
+    remaining_accounts: &[AccountInfo],
+) -> Result<(Vec<Argument>, [Pubkey; WAITLIST_BATCH_SIZE])> {
+    require!(
+        remaining_accounts.len() == WAITLIST_BATCH_SIZE,
+        ErrorCode::InvalidWaitlistBatch
+    );
+
+    let mut arguments = Vec::with_capacity(WAITLIST_BATCH_SIZE * 3);
+    let mut entries = [Pubkey::default(); WAITLIST_BATCH_SIZE];
+    for (i, info) in remaining_accounts.iter().enumerate() {
+        require!(
+            !remaining_accounts[..i]
+                .iter()
+                .any(|other| other.key == info.key),
+            ErrorCode::DuplicateWaitlistEntry
+        );
+        let entry = checked_program_account::<WaitlistEntry>(info)?;
+        require!(entry.listed, ErrorCode::CandidateNotListed);
+        require_ciphertext_region::<WaitlistEntry>(info)?;
+        arguments.extend(waitlist_candidate_args(
+            entry.enc_pubkey,
+            entry.nonce,
+            info.key(),
+        ));
+        entries[i] = info.key();
+    }
+    Ok((arguments, entries))
+}
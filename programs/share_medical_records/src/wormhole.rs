@@ -0,0 +1,207 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::program::invoke_signed;
+use anchor_lang::system_program::{transfer, Transfer};
+
+use crate::ErrorCode;
+use crate::*;
+
+/// Wormhole core bridge the program posts messages through.
+#[cfg(feature = "mainnet")]
+pub const WORMHOLE_PROGRAM_ID: Pubkey = pubkey!("worm2ZoG2kUd4vFXhvjh93UUH596ayRfgQ2MgjNMTth");
+#[cfg(not(feature = "mainnet"))]
+pub const WORMHOLE_PROGRAM_ID: Pubkey = pubkey!("3u8hJUVTA4jH1wYAyUur7FFZVQ8H635K3tSHHF4ssjQ5");
+
+pub const WORMHOLE_EMITTER_SEED: &[u8] = b"emitter";
+pub const WORMHOLE_MESSAGE_SEED: &[u8] = b"wormhole_message";
+/// Leading byte of a consent attestation payload, so receivers can tell payload kinds apart.
+pub const CONSENT_ATTESTATION_PAYLOAD_ID: u8 = 1;
+
+/// Index of the core bridge's `PostMessage` instruction.
+const WORMHOLE_POST_MESSAGE: u8 = 1;
+/// Core bridge consistency level waiting for the posting slot to be finalized.
+const WORMHOLE_FINALIZED: u8 = 1;
+/// Offset of the message fee in the core bridge's config account.
+const WORMHOLE_BRIDGE_FEE_OFFSET: usize = 16;
+
+/// Encodes a consent grant for EVM receivers, big-endian like other Wormhole payloads.
+///
+/// Layout: payload id (1), patient data (32), patient (32), receiver (32), scope (2),
+/// granted at (8), expires at (8), attested at (8).
+pub fn consent_attestation_payload(consent_grant: &ConsentGrant, attested_at: i64) -> Vec<u8> {
+    let mut payload = Vec::with_capacity(1 + 3 * 32 + 2 + 3 * 8);
+    payload.push(CONSENT_ATTESTATION_PAYLOAD_ID);
+    payload.extend_from_slice(consent_grant.patient_data.as_ref());
+    payload.extend_from_slice(consent_grant.patient.as_ref());
+    payload.extend_from_slice(consent_grant.receiver.as_ref());
+    payload.extend_from_slice(&consent_grant.scope.to_be_bytes());
+    payload.extend_from_slice(&consent_grant.granted_at.to_be_bytes());
+    payload.extend_from_slice(&consent_grant.expires_at.to_be_bytes());
+    payload.extend_from_slice(&attested_at.to_be_bytes());
+    payload
+}
+
+#[event]
+pub struct ConsentAttestedEvent {
+    pub consent_grant: Pubkey,
+    /// Wormhole sequence of the posted message, used to fetch its VAA
+    pub sequence: u64,
+}
+
+#[derive(Accounts)]
+pub struct AttestConsentWormhole<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        seeds = [
+            CONSENT_GRANT_SEED,
+            consent_grant.patient_data.as_ref(),
+            consent_grant.receiver.as_ref(),
+        ],
+        bump = consent_grant.bump,
+    )]
+    pub consent_grant: Account<'info, ConsentGrant>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    #[account(
+        mut,
+        seeds = [b"Bridge"],
+        bump,
+        seeds::program = wormhole_program,
+    )]
+    /// CHECK: wormhole_bridge, core bridge config, checked by the seeds constraint.
+    pub wormhole_bridge: UncheckedAccount<'info>,
+    #[account(mut)]
+    /// CHECK: wormhole_message, created by the core bridge; its address is checked against
+    /// the next sequence in the handler.
+    pub wormhole_message: UncheckedAccount<'info>,
+    #[account(
+        seeds = [WORMHOLE_EMITTER_SEED],
+        bump,
+    )]
+    /// CHECK: wormhole_emitter, PDA the program posts messages as, checked by the seeds constraint.
+    pub wormhole_emitter: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        seeds = [b"Sequence", wormhole_emitter.key().as_ref()],
+        bump,
+        seeds::program = wormhole_program,
+    )]
+    /// CHECK: wormhole_sequence, emitter's sequence tracker, checked by the seeds constraint.
+    pub wormhole_sequence: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        seeds = [b"fee_collector"],
+        bump,
+        seeds::program = wormhole_program,
+    )]
+    /// CHECK: wormhole_fee_collector, receives the message fee, checked by the seeds constraint.
+    pub wormhole_fee_collector: UncheckedAccount<'info>,
+    #[account(address = WORMHOLE_PROGRAM_ID)]
+    /// CHECK: wormhole_program, checked by the account constraint.
+    pub wormhole_program: UncheckedAccount<'info>,
+    pub clock: Sysvar<'info, Clock>,
+    pub rent: Sysvar<'info, Rent>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Sequence the core bridge will assign to the emitter's next message.
+pub fn next_wormhole_sequence(sequence: &AccountInfo) -> Result<u64> {
+    if sequence.data_is_empty() {
+        return Ok(0);
+    }
+    let data = sequence.try_borrow_data()?;
+    let bytes: [u8; 8] = data
+        .get(..8)
+        .ok_or(ErrorCode::InvalidWormholeAccount)?
+        .try_into()
+        .unwrap();
+    Ok(u64::from_le_bytes(bytes))
+}
+
+/// Pays the core bridge fee and posts `payload` as the program's emitter.
+///
+/// Returns the sequence the message was posted with.
+pub fn post_wormhole_message(
+    accounts: &AttestConsentWormhole,
+    emitter_bump: u8,
+    nonce: u32,
+    payload: Vec<u8>,
+) -> Result<u64> {
+    let fee = {
+        let data = accounts.wormhole_bridge.try_borrow_data()?;
+        let bytes: [u8; 8] = data
+            .get(WORMHOLE_BRIDGE_FEE_OFFSET..WORMHOLE_BRIDGE_FEE_OFFSET + 8)
+            .ok_or(ErrorCode::InvalidWormholeAccount)?
+            .try_into()
+            .unwrap();
+        u64::from_le_bytes(bytes)
+    };
+    if fee > 0 {
+        transfer(
+            CpiContext::new(
+                accounts.system_program.to_account_info(),
+                Transfer {
+                    from: accounts.payer.to_account_info(),
+                    to: accounts.wormhole_fee_collector.to_account_info(),
+                },
+            ),
+            fee,
+        )?;
+    }
+
+    let sequence = next_wormhole_sequence(&accounts.wormhole_sequence)?;
+    let sequence_bytes = sequence.to_le_bytes();
+    let (message, message_bump) =
+        Pubkey::find_program_address(&[WORMHOLE_MESSAGE_SEED, &sequence_bytes], &crate::ID);
+    require_keys_eq!(
+        accounts.wormhole_message.key(),
+        message,
+        ErrorCode::InvalidWormholeAccount
+    );
+
+    let mut data = Vec::with_capacity(1 + 4 + 4 + payload.len() + 1);
+    data.push(WORMHOLE_POST_MESSAGE);
+    data.extend_from_slice(&nonce.to_le_bytes());
+    data.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    data.extend_from_slice(&payload);
+    data.push(WORMHOLE_FINALIZED);
+
+    let ix = Instruction {
+        program_id: WORMHOLE_PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new(accounts.wormhole_bridge.key(), false),
+            AccountMeta::new(message, true),
+            AccountMeta::new_readonly(accounts.wormhole_emitter.key(), true),
+            AccountMeta::new(accounts.wormhole_sequence.key(), false),
+            AccountMeta::new(accounts.payer.key(), true),
+            AccountMeta::new(accounts.wormhole_fee_collector.key(), false),
+            AccountMeta::new_readonly(accounts.clock.key(), false),
+            AccountMeta::new_readonly(accounts.rent.key(), false),
+            AccountMeta::new_readonly(accounts.system_program.key(), false),
+        ],
+        data,
+    };
+    invoke_signed(
+        &ix,
+        &[
+            accounts.wormhole_bridge.to_account_info(),
+            accounts.wormhole_message.to_account_info(),
+            accounts.wormhole_emitter.to_account_info(),
+            accounts.wormhole_sequence.to_account_info(),
+            accounts.payer.to_account_info(),
+            accounts.wormhole_fee_collector.to_account_info(),
+            accounts.clock.to_account_info(),
+            accounts.rent.to_account_info(),
+            accounts.system_program.to_account_info(),
+        ],
+        &[
+            &[WORMHOLE_EMITTER_SEED, &[emitter_bump]],
+            &[WORMHOLE_MESSAGE_SEED, &sequence_bytes, &[message_bump]],
+        ],
+    )?;
+    Ok(sequence)
+}
@@ -60,6 +60,8 @@ describe("ShareMedicalRecords", () => {
 
     console.log("MXE x25519 pubkey is", mxePublicKey);
 
+    await initProgramConfig(program, owner);
+
     console.log("Initializing share patient data computation definition");
     const initSPDSig = await initSharePatientDataCompDef(
       program,
@@ -119,8 +121,14 @@ describe("ShareMedicalRecords", () => {
           ciphertext[8],
           ciphertext[9],
           ciphertext[10],
-        ]
+        ],
+        Array.from(senderPublicKey),
+        new anchor.BN(deserializeLE(nonce).toString())
       )
+      .accountsPartial({
+        payer: owner.publicKey,
+        patient: owner.publicKey,
+      })
       .rpc({ commitment: "confirmed" });
     console.log("Store sig is ", storeSig);
 
@@ -140,7 +148,9 @@ describe("ShareMedicalRecords", () => {
         Array.from(receiverPubKey),
         new anchor.BN(deserializeLE(receiverNonce).toString()),
         Array.from(senderPublicKey),
-        new anchor.BN(deserializeLE(nonce).toString())
+        new anchor.BN(deserializeLE(nonce).toString()),
+        null,
+        null
       )
       .accountsPartial({
         computationAccount: getComputationAccAddress(
@@ -159,6 +169,8 @@ describe("ShareMedicalRecords", () => {
           [Buffer.from("patient_data"), owner.publicKey.toBuffer()],
           program.programId
         )[0],
+        // The owner shares their own record, so no consent is needed
+        consentGrant: null,
       })
       .rpc({ commitment: "confirmed" });
     console.log("Queue sig is ", queueSig);
@@ -212,6 +224,34 @@ describe("ShareMedicalRecords", () => {
     console.log("All patient data fields successfully decrypted and verified");
   });
 
+  async function initProgramConfig(
+    program: Program<ShareMedicalRecords>,
+    owner: anchor.web3.Keypair
+  ): Promise<void> {
+    const [programConfig] = PublicKey.findProgramAddressSync(
+      [Buffer.from("program_config")],
+      program.programId
+    );
+    if (await provider.connection.getAccountInfo(programConfig)) {
+      console.log("Program config already exists, skipping initialization");
+      return;
+    }
+
+    const [programData] = PublicKey.findProgramAddressSync(
+      [program.programId.toBuffer()],
+      anchor.web3.BPF_LOADER_UPGRADEABLE_PROGRAM_ID
+    );
+    const sig = await program.methods
+      .initializeConfig()
+      .accountsPartial({
+        payer: owner.publicKey,
+        programData,
+      })
+      .signers([owner])
+      .rpc({ commitment: "confirmed" });
+    console.log("Program config initialized with signature", sig);
+  }
+
   async function initSharePatientDataCompDef(
     program: Program<ShareMedicalRecords>,
     owner: anchor.web3.Keypair,
@@ -237,6 +277,17 @@ describe("ShareMedicalRecords", () => {
       if (compDefAccount) {
         console.log("Computation definition already exists, skipping initialization");
       } else {
+        // TODO: Replace this URL with your actual circuit URL after uploading
+        await program.methods
+          .setCircuitSource(
+            Buffer.from(offset).readUInt32LE(),
+            "https://your-storage.com/share_patient_data_testnet.arcis",
+            Array(32).fill(0) // Hash verification not enforced yet
+          )
+          .accounts({ admin: owner.publicKey })
+          .signers([owner])
+          .rpc({ commitment: "confirmed" });
+
         sig = await program.methods
           .initSharePatientDataCompDef()
           .accounts({